@@ -1,22 +1,61 @@
 import type { Plugin } from '@opencode-ai/plugin';
 
 export const WorkmuxStatusPlugin: Plugin = async ({ $ }) => {
+  // Events that update the status; `workmux setup` rewrites this line from
+  // the `status_events` config. Waiting for permission is always reported.
+  const events = new Set(['userPromptSubmitted', 'postToolUse', 'agentStop']);
+
+  // Latest token/cost totals per assistant message; message.updated fires
+  // repeatedly while a message streams, so keep the last value per message.
+  const usage = new Map<string, { tokens: number; cost: number }>();
+
+  const usageArgs = (): string[] => {
+    let tokens = 0;
+    let cost = 0;
+    for (const u of usage.values()) {
+      tokens += u.tokens;
+      cost += u.cost;
+    }
+    const args: string[] = [];
+    if (tokens > 0) args.push('--tokens', String(tokens));
+    if (cost > 0) args.push('--cost', cost.toFixed(4));
+    return args;
+  };
+
   return {
     event: async ({ event }) => {
       switch (event.type) {
+        case 'message.updated': {
+          const info = event.properties.info;
+          if (info.role === 'assistant' && info.tokens) {
+            const t = info.tokens;
+            usage.set(info.id, {
+              tokens: t.input + t.output + t.reasoning,
+              cost: info.cost ?? 0,
+            });
+          }
+          break;
+        }
         case 'session.status':
-          if (event.properties.status.type === 'busy') {
-            await $`workmux set-window-status working`.quiet();
+          if (
+            event.properties.status.type === 'busy' &&
+            events.has('userPromptSubmitted')
+          ) {
+            await $`workmux set-window-status working ${usageArgs()}`.quiet();
           }
           break;
         case 'permission.updated':
-          await $`workmux set-window-status waiting`.quiet();
+          await $`workmux set-window-status waiting ${usageArgs()}`.quiet();
           break;
         case 'permission.replied':
-          await $`workmux set-window-status working`.quiet();
+          if (events.has('postToolUse')) {
+            await $`workmux set-window-status working ${usageArgs()}`.quiet();
+          }
           break;
         case 'session.idle':
-          await $`workmux set-window-status done`.quiet();
+          if (events.has('agentStop')) {
+            await $`workmux set-window-status done ${usageArgs()}`.quiet();
+          }
           break;
       }
     },
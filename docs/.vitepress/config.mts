@@ -127,16 +127,22 @@ export default defineConfig({
           { text: "add", link: "/reference/commands/add" },
           { text: "merge", link: "/reference/commands/merge" },
           { text: "remove", link: "/reference/commands/remove" },
+          { text: "rename", link: "/reference/commands/rename" },
           { text: "list", link: "/reference/commands/list" },
           { text: "open", link: "/reference/commands/open" },
           { text: "close", link: "/reference/commands/close" },
           { text: "path", link: "/reference/commands/path" },
+          { text: "exec", link: "/reference/commands/exec" },
+          { text: "attach", link: "/reference/commands/attach" },
+          { text: "prune", link: "/reference/commands/prune" },
           { text: "dashboard", link: "/reference/commands/dashboard" },
           { text: "init", link: "/reference/commands/init" },
+          { text: "doctor", link: "/reference/commands/doctor" },
           { text: "claude prune", link: "/reference/commands/claude" },
           { text: "sandbox", link: "/reference/commands/sandbox" },
           { text: "completions", link: "/reference/commands/completions" },
           { text: "docs", link: "/reference/commands/docs" },
+          { text: "version", link: "/reference/commands/version" },
         ],
       },
     ],
@@ -3,12 +3,12 @@
 //! Detects Claude Code via the `~/.claude/` directory.
 //! Installs hooks by merging into `~/.claude/settings.json`.
 
-use anyhow::{Context, Result};
-use serde_json::Value;
+use serde_json::{Map, Value};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use super::StatusCheck;
+use super::{SetupError, StatusCheck};
+use crate::config::StatusEvent;
 
 /// Hooks extracted from `.claude-plugin/plugin.json` at compile time.
 const PLUGIN_JSON: &str = include_str!("../../.claude-plugin/plugin.json");
@@ -37,7 +37,7 @@ pub fn detect() -> Option<&'static str> {
 /// 1. Plugin: `enabledPlugins` has a key starting with `workmux-status@`
 ///    (regardless of enabled/disabled -- user knows about it)
 /// 2. Manual hooks: `hooks` object contains a command with `workmux set-window-status`
-pub fn check() -> Result<StatusCheck> {
+pub fn check() -> Result<StatusCheck, SetupError> {
     let Some(path) = settings_path() else {
         return Ok(StatusCheck::NotInstalled);
     };
@@ -46,12 +46,13 @@ pub fn check() -> Result<StatusCheck> {
         return Ok(StatusCheck::NotInstalled);
     }
 
-    let content = fs::read_to_string(&path).context("Failed to read ~/.claude/settings.json")?;
-
-    let settings: Value =
-        serde_json::from_str(&content).context("~/.claude/settings.json is not valid JSON")?;
+    Ok(check_settings(&read_settings(&path)?))
+}
 
-    Ok(check_settings(&settings))
+fn read_settings(path: &Path) -> Result<Value, SetupError> {
+    let content = fs::read_to_string(path).map_err(SetupError::io("read", path))?;
+    serde_json::from_str(&content)
+        .map_err(|e| SetupError::invalid(path, format!("is not valid JSON: {}", e)))
 }
 
 /// Check a parsed settings.json value for workmux status tracking configuration.
@@ -99,42 +100,63 @@ fn has_workmux_hooks(settings: &Value) -> bool {
 }
 
 /// Extract the hooks object from the plugin.json manifest.
-fn load_hooks_from_plugin() -> Result<Value> {
+fn load_hooks_from_plugin() -> Value {
     let plugin: Value =
         serde_json::from_str(PLUGIN_JSON).expect("embedded plugin.json is valid JSON");
     plugin
         .get("hooks")
         .cloned()
-        .ok_or_else(|| anyhow::anyhow!("plugin.json missing hooks key"))
+        .expect("embedded plugin.json has a hooks key")
+}
+
+/// Claude Code's hook event for each configurable status event.
+fn claude_event(event: StatusEvent) -> &'static str {
+    match event {
+        StatusEvent::UserPromptSubmitted => "UserPromptSubmit",
+        StatusEvent::PostToolUse => "PostToolUse",
+        StatusEvent::AgentStop => "Stop",
+    }
+}
+
+/// Split the plugin's hooks into those to install for `events` and those
+/// for the other status events. Hooks with no status event equivalent
+/// (`Notification`, which sets waiting) are always installed.
+fn split_hooks(events: &[StatusEvent]) -> (Map<String, Value>, Map<String, Value>) {
+    let hooks = load_hooks_from_plugin();
+    let hooks = hooks.as_object().expect("plugin hooks is an object");
+    hooks.clone().into_iter().partition(|(name, _)| {
+        StatusEvent::ALL
+            .iter()
+            .filter(|e| !events.contains(e))
+            .all(|e| claude_event(*e) != name)
+    })
 }
 
-/// Install workmux hooks into `~/.claude/settings.json`.
+/// Install workmux hooks for `events` into `~/.claude/settings.json`.
 ///
 /// Merges hook groups into existing hooks without clobbering or creating
-/// duplicates. Returns a description of what was done.
-pub fn install() -> Result<String> {
-    let path =
-        settings_path().ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
+/// duplicates, and removes workmux's own groups for events that are no
+/// longer selected. Returns a description of what was done.
+pub fn install(events: &[StatusEvent]) -> Result<String, SetupError> {
+    let path = settings_path().ok_or(SetupError::NoHomeDir)?;
 
     // Read existing settings or start fresh
     let mut settings: Value = if path.exists() {
-        let content =
-            fs::read_to_string(&path).context("Failed to read ~/.claude/settings.json")?;
-        serde_json::from_str(&content).context("~/.claude/settings.json is not valid JSON")?
+        read_settings(&path)?
     } else {
         // Ensure ~/.claude/ directory exists
         if let Some(parent) = path.parent() {
-            fs::create_dir_all(parent).context("Failed to create ~/.claude/ directory")?;
+            fs::create_dir_all(parent).map_err(SetupError::io("create", parent))?;
         }
         Value::Object(serde_json::Map::new())
     };
 
-    let hooks_to_add = load_hooks_from_plugin()?;
+    let (hooks_map, hooks_to_remove) = split_hooks(events);
 
     // Ensure settings.hooks exists as an object
     let settings_obj = settings
         .as_object_mut()
-        .ok_or_else(|| anyhow::anyhow!("settings.json root is not an object"))?;
+        .ok_or_else(|| SetupError::invalid(&path, "root is not an object"))?;
 
     if !settings_obj.contains_key("hooks") {
         settings_obj.insert("hooks".to_string(), Value::Object(serde_json::Map::new()));
@@ -143,19 +165,18 @@ pub fn install() -> Result<String> {
     let existing_hooks = settings_obj
         .get_mut("hooks")
         .and_then(|v| v.as_object_mut())
-        .ok_or_else(|| anyhow::anyhow!("settings.json hooks is not an object"))?;
+        .ok_or_else(|| SetupError::invalid(&path, "hooks is not an object"))?;
 
     // Merge each hook event, deduplicating by value equality
-    let hooks_map = hooks_to_add.as_object().expect("plugin hooks is an object");
-    for (event, hook_groups) in hooks_map {
+    for (event, hook_groups) in &hooks_map {
         let Some(new_groups) = hook_groups.as_array() else {
             continue;
         };
 
         if let Some(existing_groups) = existing_hooks.get_mut(event) {
-            let arr = existing_groups
-                .as_array_mut()
-                .ok_or_else(|| anyhow::anyhow!("hooks.{event} is not an array"))?;
+            let arr = existing_groups.as_array_mut().ok_or_else(|| {
+                SetupError::invalid(&path, format!("hooks.{event} is not an array"))
+            })?;
             for group in new_groups {
                 if !arr.contains(group) {
                     arr.push(group.clone());
@@ -166,9 +187,23 @@ pub fn install() -> Result<String> {
         }
     }
 
+    for (event, hook_groups) in &hooks_to_remove {
+        let Some(arr) = existing_hooks.get_mut(event).and_then(Value::as_array_mut) else {
+            continue;
+        };
+        let ours = hook_groups
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        arr.retain(|group| !ours.contains(group));
+        if arr.is_empty() {
+            existing_hooks.remove(event);
+        }
+    }
+
     // Write back with pretty formatting
-    let output = serde_json::to_string_pretty(&settings)?;
-    fs::write(&path, output + "\n").context("Failed to write ~/.claude/settings.json")?;
+    let output = serde_json::to_string_pretty(&settings).expect("JSON values always serialize");
+    fs::write(&path, output + "\n").map_err(SetupError::io("write", &path))?;
 
     Ok("Installed hooks to ~/.claude/settings.json".to_string())
 }
@@ -216,7 +251,7 @@ mod tests {
 
     #[test]
     fn test_load_hooks_from_plugin() {
-        let hooks = load_hooks_from_plugin().unwrap();
+        let hooks = load_hooks_from_plugin();
         let obj = hooks.as_object().unwrap();
         assert!(obj.contains_key("UserPromptSubmit"));
         assert!(obj.contains_key("Notification"));
@@ -224,10 +259,24 @@ mod tests {
         assert!(obj.contains_key("Stop"));
     }
 
+    #[test]
+    fn test_split_hooks_by_status_event() {
+        let (install, remove) = split_hooks(&StatusEvent::ALL);
+        assert_eq!(install.len(), 4);
+        assert!(remove.is_empty());
+
+        let (install, remove) =
+            split_hooks(&[StatusEvent::UserPromptSubmitted, StatusEvent::AgentStop]);
+        let mut names: Vec<_> = install.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["Notification", "Stop", "UserPromptSubmit"]);
+        assert_eq!(remove.keys().collect::<Vec<_>>(), ["PostToolUse"]);
+    }
+
     #[test]
     fn test_merge_into_empty_settings() {
         let mut settings = json!({});
-        let hooks_to_add = load_hooks_from_plugin().unwrap();
+        let hooks_to_add = load_hooks_from_plugin();
         let hooks_map = hooks_to_add.as_object().unwrap();
 
         let settings_obj = settings.as_object_mut().unwrap();
@@ -259,7 +308,7 @@ mod tests {
             }
         });
 
-        let hooks_to_add = load_hooks_from_plugin().unwrap();
+        let hooks_to_add = load_hooks_from_plugin();
         let hooks_map = hooks_to_add.as_object().unwrap();
 
         let existing_hooks = settings.get_mut("hooks").unwrap().as_object_mut().unwrap();
@@ -387,7 +436,7 @@ mod tests {
             }
         });
 
-        let hooks_to_add = load_hooks_from_plugin().unwrap();
+        let hooks_to_add = load_hooks_from_plugin();
         let hooks_map = hooks_to_add.as_object().unwrap();
 
         let existing_hooks = settings.get_mut("hooks").unwrap().as_object_mut().unwrap();
@@ -7,15 +7,29 @@
 //! Unlike Claude/OpenCode which install globally, Copilot hooks are per-repo.
 //! See https://github.com/github/copilot-cli/issues/1157
 
-use anyhow::{Context, Result};
+use serde_json::Value;
 use std::fs;
 use std::path::PathBuf;
 
-use super::StatusCheck;
+use super::{SetupError, StatusCheck};
+use crate::config::StatusEvent;
 
 /// Hooks configuration embedded at compile time.
 const HOOKS_JSON: &str = include_str!("../../.github/hooks/workmux-status/hooks.json");
 
+/// The embedded hooks.json, keeping only the hooks for `events`.
+fn hooks_json(events: &[StatusEvent]) -> String {
+    if StatusEvent::ALL.iter().all(|e| events.contains(e)) {
+        return HOOKS_JSON.to_string();
+    }
+    let mut parsed: Value =
+        serde_json::from_str(HOOKS_JSON).expect("embedded hooks.json is valid JSON");
+    if let Some(hooks) = parsed.get_mut("hooks").and_then(Value::as_object_mut) {
+        hooks.retain(|name, _| events.iter().any(|e| e.name() == name));
+    }
+    serde_json::to_string_pretty(&parsed).expect("JSON values always serialize") + "\n"
+}
+
 fn copilot_dir() -> Option<PathBuf> {
     home::home_dir().map(|h| h.join(".copilot"))
 }
@@ -33,10 +47,10 @@ pub fn detect() -> Option<&'static str> {
 }
 
 /// Check if workmux hooks are installed for Copilot in the current repo.
-pub fn check() -> Result<StatusCheck> {
-    let root = match crate::git::get_repo_root() {
-        Ok(r) => r,
-        Err(e) => return Ok(StatusCheck::Error(e.to_string())),
+/// Any workmux hook counts, so a subset of `status_events` is installed too.
+pub fn check() -> Result<StatusCheck, SetupError> {
+    let Ok(root) = crate::git::get_repo_root() else {
+        return Err(SetupError::NotInRepo("check Copilot hooks"));
     };
 
     let hooks_dir = root.join(".github/hooks");
@@ -63,16 +77,16 @@ pub fn check() -> Result<StatusCheck> {
     Ok(StatusCheck::NotInstalled)
 }
 
-/// Install workmux hooks for Copilot CLI in the current repo.
-pub fn install() -> Result<String> {
-    let root = crate::git::get_repo_root()
-        .context("Must be in a git repository to install Copilot hooks")?;
+/// Install workmux hooks for `events` for Copilot CLI in the current repo.
+pub fn install(events: &[StatusEvent]) -> Result<String, SetupError> {
+    let root =
+        crate::git::get_repo_root().map_err(|_| SetupError::NotInRepo("install Copilot hooks"))?;
     let hooks_dir = root.join(".github/hooks/workmux-status");
 
-    fs::create_dir_all(&hooks_dir).context("Failed to create .github/hooks/workmux-status/")?;
+    fs::create_dir_all(&hooks_dir).map_err(SetupError::io("create", &hooks_dir))?;
 
     let hooks_file = hooks_dir.join("hooks.json");
-    fs::write(&hooks_file, HOOKS_JSON).context("Failed to write hooks.json")?;
+    fs::write(&hooks_file, hooks_json(events)).map_err(SetupError::io("write", &hooks_file))?;
 
     Ok(format!(
         "Installed hooks to {}",
@@ -102,4 +116,19 @@ mod tests {
     fn test_hooks_json_contains_workmux_command() {
         assert!(HOOKS_JSON.contains("workmux set-window-status"));
     }
+
+    #[test]
+    fn test_hooks_json_keeps_only_selected_events() {
+        assert_eq!(hooks_json(&StatusEvent::ALL), HOOKS_JSON);
+
+        let content = hooks_json(&[StatusEvent::UserPromptSubmitted, StatusEvent::AgentStop]);
+        let parsed: Value = serde_json::from_str(&content).unwrap();
+        assert_eq!(parsed["version"], 1);
+        let hooks = parsed["hooks"].as_object().unwrap();
+        assert!(hooks.contains_key("userPromptSubmitted"));
+        assert!(!hooks.contains_key("postToolUse"));
+        assert!(hooks.contains_key("agentStop"));
+        // A partial install still counts as installed
+        assert!(content.contains("workmux set-window-status"));
+    }
 }
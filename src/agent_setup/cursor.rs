@@ -0,0 +1,216 @@
+//! Cursor CLI status tracking setup.
+//!
+//! Detects Cursor via the `~/.cursor/` directory.
+//! Installs hooks by merging into `~/.cursor/hooks.json`, which both the
+//! Cursor editor and its CLI agent (`cursor-agent`) read.
+//!
+//! Cursor has no hook for permission prompts, so only working/done states
+//! are tracked (no waiting state).
+
+use serde_json::{Map, Value, json};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{SetupError, StatusCheck};
+use crate::config::StatusEvent;
+
+fn cursor_dir() -> Option<PathBuf> {
+    home::home_dir().map(|h| h.join(".cursor"))
+}
+
+fn hooks_path() -> Option<PathBuf> {
+    cursor_dir().map(|d| d.join("hooks.json"))
+}
+
+/// Cursor's hook events for each configurable status event.
+fn cursor_events(event: StatusEvent) -> &'static [&'static str] {
+    match event {
+        StatusEvent::UserPromptSubmitted => &["beforeSubmitPrompt"],
+        StatusEvent::PostToolUse => &["afterShellExecution", "afterFileEdit"],
+        StatusEvent::AgentStop => &["stop"],
+    }
+}
+
+/// The hook entry workmux installs for `event`.
+fn workmux_hook(event: StatusEvent) -> Value {
+    let status = match event {
+        StatusEvent::UserPromptSubmitted | StatusEvent::PostToolUse => "working",
+        StatusEvent::AgentStop => "done",
+    };
+    json!({ "command": format!("workmux set-window-status {}", status) })
+}
+
+/// Detect if Cursor is present via filesystem.
+/// Returns the reason string if detected, None otherwise.
+pub fn detect() -> Option<&'static str> {
+    if cursor_dir().is_some_and(|d| d.is_dir()) {
+        return Some("found ~/.cursor/");
+    }
+
+    None
+}
+
+/// Check if workmux hooks are installed in `~/.cursor/hooks.json`.
+pub fn check() -> Result<StatusCheck, SetupError> {
+    let Some(path) = hooks_path() else {
+        return Ok(StatusCheck::NotInstalled);
+    };
+
+    if !path.exists() {
+        return Ok(StatusCheck::NotInstalled);
+    }
+
+    if has_workmux_hooks(&read_hooks(&path)?) {
+        Ok(StatusCheck::Installed)
+    } else {
+        Ok(StatusCheck::NotInstalled)
+    }
+}
+
+fn read_hooks(path: &Path) -> Result<Value, SetupError> {
+    let content = fs::read_to_string(path).map_err(SetupError::io("read", path))?;
+    serde_json::from_str(&content)
+        .map_err(|e| SetupError::invalid(path, format!("is not valid JSON: {}", e)))
+}
+
+/// Whether any hook in a parsed hooks.json runs `workmux set-window-status`.
+fn has_workmux_hooks(config: &Value) -> bool {
+    let Some(hooks) = config.get("hooks").and_then(Value::as_object) else {
+        return false;
+    };
+    hooks
+        .values()
+        .filter_map(Value::as_array)
+        .flatten()
+        .filter_map(|hook| hook.get("command").and_then(Value::as_str))
+        .any(|cmd| cmd.contains("workmux set-window-status"))
+}
+
+/// Add workmux's hooks for `events` to a parsed hooks.json, without
+/// duplicating them, and remove its hooks for the other status events.
+fn merge_hooks(config: &mut Value, events: &[StatusEvent], path: &Path) -> Result<(), SetupError> {
+    let config_obj = config
+        .as_object_mut()
+        .ok_or_else(|| SetupError::invalid(path, "root is not an object"))?;
+    config_obj.entry("version").or_insert(json!(1));
+    let hooks = config_obj
+        .entry("hooks")
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| SetupError::invalid(path, "hooks is not an object"))?;
+
+    for event in StatusEvent::ALL {
+        let ours = workmux_hook(event);
+        for name in cursor_events(event) {
+            if events.contains(&event) {
+                let arr = hooks
+                    .entry(*name)
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                    .as_array_mut()
+                    .ok_or_else(|| {
+                        SetupError::invalid(path, format!("hooks.{name} is not an array"))
+                    })?;
+                if !arr.contains(&ours) {
+                    arr.push(ours.clone());
+                }
+            } else if let Some(arr) = hooks.get_mut(*name).and_then(Value::as_array_mut) {
+                arr.retain(|hook| *hook != ours);
+                if arr.is_empty() {
+                    hooks.remove(*name);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Install workmux hooks for `events` into `~/.cursor/hooks.json`.
+///
+/// Merges into existing hooks without clobbering or creating duplicates.
+/// Returns a description of what was done.
+pub fn install(events: &[StatusEvent]) -> Result<String, SetupError> {
+    let path = hooks_path().ok_or(SetupError::NoHomeDir)?;
+
+    let mut config = if path.exists() {
+        read_hooks(&path)?
+    } else {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(SetupError::io("create", parent))?;
+        }
+        Value::Object(Map::new())
+    };
+
+    merge_hooks(&mut config, events, &path)?;
+
+    let output = serde_json::to_string_pretty(&config).expect("JSON values always serialize");
+    fs::write(&path, output + "\n").map_err(SetupError::io("write", &path))?;
+
+    Ok(
+        "Installed hooks to ~/.cursor/hooks.json. Restart Cursor for them to take effect."
+            .to_string(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_into_empty_config_installs_every_event() {
+        let mut config = json!({});
+        merge_hooks(&mut config, &StatusEvent::ALL, Path::new("hooks.json")).unwrap();
+        assert_eq!(
+            config,
+            json!({
+                "version": 1,
+                "hooks": {
+                    "beforeSubmitPrompt": [{"command": "workmux set-window-status working"}],
+                    "afterShellExecution": [{"command": "workmux set-window-status working"}],
+                    "afterFileEdit": [{"command": "workmux set-window-status working"}],
+                    "stop": [{"command": "workmux set-window-status done"}]
+                }
+            })
+        );
+        assert!(has_workmux_hooks(&config));
+    }
+
+    #[test]
+    fn merge_keeps_other_hooks_and_drops_deselected_events() {
+        let mut config = json!({
+            "version": 1,
+            "hooks": {
+                "afterFileEdit": [
+                    {"command": "./hooks/format.sh"},
+                    {"command": "workmux set-window-status working"}
+                ],
+                "afterShellExecution": [{"command": "workmux set-window-status working"}]
+            }
+        });
+        assert!(has_workmux_hooks(&config));
+
+        let events = [StatusEvent::UserPromptSubmitted, StatusEvent::AgentStop];
+        merge_hooks(&mut config, &events, Path::new("hooks.json")).unwrap();
+        merge_hooks(&mut config, &events, Path::new("hooks.json")).unwrap();
+        assert_eq!(
+            config["hooks"],
+            json!({
+                "afterFileEdit": [{"command": "./hooks/format.sh"}],
+                "beforeSubmitPrompt": [{"command": "workmux set-window-status working"}],
+                "stop": [{"command": "workmux set-window-status done"}]
+            })
+        );
+    }
+
+    #[test]
+    fn merge_rejects_malformed_config() {
+        let err = merge_hooks(
+            &mut json!({"hooks": {"stop": {}}}),
+            &StatusEvent::ALL,
+            Path::new("hooks.json"),
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "hooks.json hooks.stop is not an array");
+        assert!(!has_workmux_hooks(&json!({"hooks": {"stop": []}})));
+    }
+}
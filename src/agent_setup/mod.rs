@@ -6,6 +6,7 @@
 
 pub mod claude;
 pub mod copilot;
+pub mod cursor;
 pub mod opencode;
 
 use anyhow::{Context, Result};
@@ -14,7 +15,9 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::fs;
 use std::io::{self, IsTerminal, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, StatusEvent};
 
 /// An agent that supports status tracking.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
@@ -22,6 +25,7 @@ use std::path::PathBuf;
 pub enum Agent {
     Claude,
     Copilot,
+    Cursor,
     OpenCode,
 }
 
@@ -30,11 +34,50 @@ impl Agent {
         match self {
             Agent::Claude => "Claude Code",
             Agent::Copilot => "Copilot CLI",
+            Agent::Cursor => "Cursor CLI",
             Agent::OpenCode => "OpenCode",
         }
     }
 }
 
+/// Why checking or installing an agent's status tracking failed.
+#[derive(Debug, thiserror::Error)]
+pub enum SetupError {
+    /// The agent's hooks are per-repo but we're not inside a git repository.
+    #[error("Must be in a git repository to {0}")]
+    NotInRepo(&'static str),
+    #[error("Could not determine home directory")]
+    NoHomeDir,
+    /// Reading or writing an agent's config failed.
+    #[error("Failed to {action} {}: {error}", path.display())]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        error: io::Error,
+    },
+    /// The agent's existing config can't be merged into.
+    #[error("{} {reason}", path.display())]
+    InvalidExistingConfig { path: PathBuf, reason: String },
+}
+
+impl SetupError {
+    fn io(action: &'static str, path: &Path) -> impl FnOnce(io::Error) -> Self {
+        let path = path.to_path_buf();
+        move |error| SetupError::Io {
+            action,
+            path,
+            error,
+        }
+    }
+
+    fn invalid(path: &Path, reason: impl Into<String>) -> Self {
+        SetupError::InvalidExistingConfig {
+            path: path.to_path_buf(),
+            reason: reason.into(),
+        }
+    }
+}
+
 /// Result of verifying an agent's status tracking.
 #[derive(Debug)]
 pub enum StatusCheck {
@@ -43,7 +86,7 @@ pub enum StatusCheck {
     /// Hooks are not installed.
     NotInstalled,
     /// Could not determine status (e.g., invalid JSON in settings file).
-    Error(String),
+    Error(SetupError),
 }
 
 /// Result of detecting and checking a single agent.
@@ -63,7 +106,7 @@ pub fn check_all() -> Vec<AgentCheck> {
     if let Some(reason) = claude::detect() {
         let status = match claude::check() {
             Ok(s) => s,
-            Err(e) => StatusCheck::Error(e.to_string()),
+            Err(e) => StatusCheck::Error(e),
         };
         results.push(AgentCheck {
             agent: Agent::Claude,
@@ -75,7 +118,7 @@ pub fn check_all() -> Vec<AgentCheck> {
     if let Some(reason) = copilot::detect() {
         let status = match copilot::check() {
             Ok(s) => s,
-            Err(e) => StatusCheck::Error(e.to_string()),
+            Err(e) => StatusCheck::Error(e),
         };
         results.push(AgentCheck {
             agent: Agent::Copilot,
@@ -84,10 +127,22 @@ pub fn check_all() -> Vec<AgentCheck> {
         });
     }
 
+    if let Some(reason) = cursor::detect() {
+        let status = match cursor::check() {
+            Ok(s) => s,
+            Err(e) => StatusCheck::Error(e),
+        };
+        results.push(AgentCheck {
+            agent: Agent::Cursor,
+            reason,
+            status,
+        });
+    }
+
     if let Some(reason) = opencode::detect() {
         let status = match opencode::check() {
             Ok(s) => s,
-            Err(e) => StatusCheck::Error(e.to_string()),
+            Err(e) => StatusCheck::Error(e),
         };
         results.push(AgentCheck {
             agent: Agent::OpenCode,
@@ -100,11 +155,21 @@ pub fn check_all() -> Vec<AgentCheck> {
 }
 
 /// Install status tracking for the given agent.
-pub fn install(agent: Agent) -> Result<String> {
+///
+/// `local` installs into the current repository where the agent supports it
+/// (OpenCode); Copilot is always per-repo, and Claude and Cursor always
+/// global. Only the hook events listed in `status_events` are installed.
+pub fn install(agent: Agent, local: bool) -> Result<String, SetupError> {
+    // A config that fails to load is reported by the command itself; fall
+    // back to every event rather than refusing to install
+    let events = Config::load(None)
+        .map(|config| config.status_events().to_vec())
+        .unwrap_or_else(|_| StatusEvent::ALL.to_vec());
     match agent {
-        Agent::Claude => claude::install(),
-        Agent::Copilot => copilot::install(),
-        Agent::OpenCode => opencode::install(),
+        Agent::Claude => claude::install(&events),
+        Agent::Copilot => copilot::install(&events),
+        Agent::Cursor => cursor::install(&events),
+        Agent::OpenCode => opencode::install(local, &events),
     }
 }
 
@@ -199,7 +264,7 @@ fn confirm_install() -> Result<bool> {
     }
 }
 
-fn print_install_result(agent: Agent, result: &Result<String>) {
+fn print_install_result(agent: Agent, result: &Result<String, SetupError>) {
     match result {
         Ok(msg) => println!("  {} {}", style("✔").green(), msg),
         Err(e) => println!("  {} {}: {}", style("✗").red(), agent.name(), e),
@@ -208,7 +273,7 @@ fn print_install_result(agent: Agent, result: &Result<String>) {
 
 fn install_agents(agents: &[&AgentCheck]) {
     for check in agents {
-        let result = install(check.agent);
+        let result = install(check.agent, false);
         print_install_result(check.agent, &result);
     }
 }
@@ -281,6 +346,7 @@ mod tests {
     fn test_agent_name() {
         assert_eq!(Agent::Claude.name(), "Claude Code");
         assert_eq!(Agent::Copilot.name(), "Copilot CLI");
+        assert_eq!(Agent::Cursor.name(), "Cursor CLI");
         assert_eq!(Agent::OpenCode.name(), "OpenCode");
     }
 
@@ -291,6 +357,7 @@ mod tests {
             serde_json::to_string(&Agent::Copilot).unwrap(),
             "\"copilot\""
         );
+        assert_eq!(serde_json::to_string(&Agent::Cursor).unwrap(), "\"cursor\"");
         assert_eq!(
             serde_json::to_string(&Agent::OpenCode).unwrap(),
             "\"opencode\""
@@ -303,10 +370,34 @@ mod tests {
         assert_eq!(agent, Agent::Claude);
         let agent: Agent = serde_json::from_str("\"copilot\"").unwrap();
         assert_eq!(agent, Agent::Copilot);
+        let agent: Agent = serde_json::from_str("\"cursor\"").unwrap();
+        assert_eq!(agent, Agent::Cursor);
         let agent: Agent = serde_json::from_str("\"opencode\"").unwrap();
         assert_eq!(agent, Agent::OpenCode);
     }
 
+    #[test]
+    fn test_setup_error_display() {
+        let err = SetupError::io("write", Path::new("/repo/hooks.json"))(io::Error::from(
+            io::ErrorKind::PermissionDenied,
+        ));
+        assert!(matches!(err, SetupError::Io { .. }));
+        assert!(
+            err.to_string()
+                .starts_with("Failed to write /repo/hooks.json: "),
+            "{err}"
+        );
+
+        let err = SetupError::NotInRepo("install Copilot hooks");
+        assert_eq!(
+            err.to_string(),
+            "Must be in a git repository to install Copilot hooks"
+        );
+
+        let err = SetupError::invalid(Path::new("settings.json"), "is not valid JSON");
+        assert_eq!(err.to_string(), "settings.json is not valid JSON");
+    }
+
     #[test]
     fn test_setup_state_default_is_empty() {
         let state = SetupState::default();
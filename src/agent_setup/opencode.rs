@@ -5,17 +5,36 @@
 //! 2. `XDG_CONFIG_HOME/opencode`
 //! 3. `~/.config/opencode`
 //!
-//! Installs plugin by writing `workmux-status.ts` to the plugin directory.
+//! Installs plugin by writing `workmux-status.ts` to the plugin directory,
+//! or with `local` to `.opencode/plugin/` in the current git repository
+//! (useful when the global config isn't writable).
 
-use anyhow::{Context, Result};
 use std::fs;
 use std::path::PathBuf;
 
-use super::StatusCheck;
+use super::{SetupError, StatusCheck};
+use crate::config::StatusEvent;
 
 /// The OpenCode plugin source, embedded at compile time.
 const PLUGIN_SOURCE: &str = include_str!("../../.opencode/plugin/workmux-status.ts");
 
+/// Start of the plugin line declaring which events update the status.
+const EVENTS_LINE: &str = "const events = new Set([";
+
+/// The plugin source with its events line listing only `events`.
+fn plugin_source(events: &[StatusEvent]) -> String {
+    let names: Vec<String> = events.iter().map(|e| format!("'{}'", e.name())).collect();
+    PLUGIN_SOURCE
+        .lines()
+        .map(|line| match line.find(EVENTS_LINE) {
+            Some(indent) => format!("{}{}{}]);", &line[..indent], EVENTS_LINE, names.join(", ")),
+            None => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
 fn opencode_config_dir() -> Option<PathBuf> {
     if let Ok(dir) = std::env::var("OPENCODE_CONFIG") {
         return Some(PathBuf::from(dir));
@@ -30,6 +49,28 @@ fn plugin_path() -> Option<PathBuf> {
     opencode_config_dir().map(|d| d.join("plugin/workmux-status.ts"))
 }
 
+/// Repo-local OpenCode directory (`<repo>/.opencode`).
+fn local_config_dir() -> Option<PathBuf> {
+    crate::git::get_repo_root()
+        .ok()
+        .map(|root| root.join(".opencode"))
+}
+
+/// Whether any plugin in the repo-local plugin directory calls workmux.
+fn has_local_plugin() -> bool {
+    let Some(plugin_dir) = local_config_dir().map(|d| d.join("plugin")) else {
+        return false;
+    };
+    let Ok(entries) = fs::read_dir(&plugin_dir) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        entry.path().is_file()
+            && fs::read_to_string(entry.path())
+                .is_ok_and(|content| content.contains("workmux set-window-status"))
+    })
+}
+
 /// Detect if OpenCode is present via filesystem.
 /// Returns the reason string if detected, None otherwise.
 pub fn detect() -> Option<&'static str> {
@@ -39,37 +80,57 @@ pub fn detect() -> Option<&'static str> {
     if opencode_config_dir().is_some_and(|d| d.is_dir()) {
         return Some("found ~/.config/opencode/");
     }
+    if local_config_dir().is_some_and(|d| d.is_dir()) {
+        return Some("found .opencode/");
+    }
 
     None
 }
 
-/// Check if workmux plugin is installed for OpenCode.
-pub fn check() -> Result<StatusCheck> {
-    let Some(path) = plugin_path() else {
-        return Ok(StatusCheck::NotInstalled);
-    };
-
-    if path.exists() {
+/// Check if workmux plugin is installed for OpenCode, globally or in the
+/// current repo.
+pub fn check() -> Result<StatusCheck, SetupError> {
+    if plugin_path().is_some_and(|p| p.exists()) || has_local_plugin() {
         Ok(StatusCheck::Installed)
     } else {
         Ok(StatusCheck::NotInstalled)
     }
 }
 
-/// Install workmux plugin for OpenCode.
-/// Returns a description of what was done.
-pub fn install() -> Result<String> {
-    let path =
-        plugin_path().ok_or_else(|| anyhow::anyhow!("Could not determine home directory"))?;
+/// Install workmux plugin for OpenCode, into the current repo with `local`,
+/// reporting only `events`. Returns a description of what was done.
+pub fn install(local: bool, events: &[StatusEvent]) -> Result<String, SetupError> {
+    let path = if local {
+        local_config_dir()
+            .ok_or(SetupError::NotInRepo("install the OpenCode plugin locally"))?
+            .join("plugin/workmux-status.ts")
+    } else {
+        plugin_path().ok_or(SetupError::NoHomeDir)?
+    };
 
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent).context("Failed to create OpenCode plugin directory")?;
+        fs::create_dir_all(parent).map_err(SetupError::io("create", parent))?;
     }
 
-    fs::write(&path, PLUGIN_SOURCE).context("Failed to write OpenCode plugin")?;
+    fs::write(&path, plugin_source(events)).map_err(SetupError::io("write", &path))?;
 
     Ok(format!(
         "Installed plugin to {}. Restart OpenCode for it to take effect.",
         path.display()
     ))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plugin_source_lists_only_selected_events() {
+        assert_eq!(plugin_source(&StatusEvent::ALL), PLUGIN_SOURCE);
+
+        let source = plugin_source(&[StatusEvent::AgentStop]);
+        assert!(source.contains("  const events = new Set(['agentStop']);\n"));
+        assert!(!source.contains("'postToolUse']"));
+        assert!(source.contains("workmux set-window-status"));
+    }
+}
@@ -3,6 +3,7 @@ use crate::{claude, command, config, git, nerdfont};
 use anyhow::{Context, Result};
 use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{Shell, generate};
+use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
 struct WorktreeBranchParser;
@@ -28,11 +29,10 @@ impl WorktreeBranchParser {
 
         worktrees
             .into_iter()
-            .map(|(_, branch)| branch)
+            // Skip detached HEADs and the bare repo, which have no branch.
+            .filter_map(|wt| wt.branch)
             // Filter out the main branch, as it's not a candidate for merging/removing.
             .filter(|branch| main_branch.as_deref() != Some(branch.as_str()))
-            // Filter out detached HEAD states.
-            .filter(|branch| branch != "(detached)")
             .collect()
     }
 }
@@ -83,7 +83,7 @@ impl WorktreeHandleParser {
 
         let main_worktree_root = git::get_main_worktree_root().ok();
 
-        worktrees
+        git::checkouts(worktrees)
             .into_iter()
             .filter_map(|(path, _)| {
                 // Filter out the main worktree
@@ -169,6 +169,23 @@ impl clap::builder::TypedValueParser for GitBranchParser {
 #[command(about = "An opinionated workflow tool that orchestrates git worktrees and tmux")]
 #[command(after_help = "Run 'workmux docs' for detailed documentation.")]
 struct Cli {
+    /// Run against the git repository at this path instead of the current
+    /// directory (also settable via WORKMUX_REPO_ROOT)
+    #[arg(long, global = true, value_name = "PATH")]
+    repo_root: Option<PathBuf>,
+
+    /// Log file format (also settable via WORKMUX_LOG_FORMAT)
+    #[arg(long, global = true, value_enum, value_name = "FORMAT")]
+    log_format: Option<crate::logger::LogFormat>,
+
+    /// Log at debug level (-vv for trace), overriding WORKMUX_LOG and RUST_LOG
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Log only warnings and errors, overriding WORKMUX_LOG and RUST_LOG
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -176,6 +193,7 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Create a new worktree and tmux window
+    #[command(visible_alias = "new")]
     Add {
         /// Name of the branch (creates if it doesn't exist) or remote ref (e.g., origin/feature).
         /// When used with --pr, this becomes the custom local branch name.
@@ -311,6 +329,16 @@ enum Commands {
         keep_branch: bool,
     },
 
+    /// Rename a worktree's branch, moving its directory and window along
+    Rename {
+        /// Worktree name or branch to rename
+        #[arg(value_parser = WorktreeHandleParser::new())]
+        old: String,
+
+        /// New branch name
+        new: String,
+    },
+
     /// List all worktrees
     #[command(visible_alias = "ls")]
     List {
@@ -318,6 +346,64 @@ enum Commands {
         #[arg(long)]
         pr: bool,
 
+        /// Branch to check merge status against (defaults to base_branch, then main_branch)
+        #[arg(long, value_name = "BRANCH")]
+        base: Option<String>,
+
+        /// Only show worktrees with no commits for at least this long (e.g. 7d, 2w)
+        #[arg(long, value_name = "DURATION", value_parser = crate::util::parse_duration)]
+        stale: Option<std::time::Duration>,
+
+        /// Show how far each branch is ahead/behind its upstream
+        #[arg(long)]
+        upstream: bool,
+
+        /// Show each worktree's disk usage, leaving out files git ignores
+        /// (`--size=all` to count them too)
+        #[arg(
+            long,
+            value_enum,
+            value_name = "MODE",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "unignored"
+        )]
+        size: Option<command::list::SizeMode>,
+
+        /// Print one line per worktree from a template instead of the table,
+        /// e.g. `{branch}\t{path}`. Placeholders: current, branch, path, pr,
+        /// agent, tmux (or mux), unmerged, upstream, age, size
+        #[arg(long, value_name = "TEMPLATE", value_parser = command::list::ListFormat::parse)]
+        format: Option<command::list::ListFormat>,
+
+        /// Print tab-separated `branch`, `path`, `tmux` and `unmerged` fields
+        /// (`1`/`0` for the booleans), one worktree per line, in a layout that
+        /// stays stable across releases
+        #[arg(long, conflicts_with = "format")]
+        porcelain: bool,
+
+        /// Keep the table open and redraw it in place every few seconds
+        /// (`--watch=10s` to change the interval), highlighting rows whose
+        /// status changed. Press `q` to quit
+        #[arg(
+            long,
+            value_name = "INTERVAL",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "2s",
+            value_parser = crate::util::parse_duration,
+            conflicts_with_all = ["format", "porcelain"]
+        )]
+        watch: Option<std::time::Duration>,
+
+        /// Show at most N worktrees, taken in branch order
+        #[arg(long, value_name = "N", conflicts_with = "watch")]
+        limit: Option<usize>,
+
+        /// Skip the first M worktrees in branch order (use with --limit to page)
+        #[arg(long, value_name = "M", default_value_t = 0, conflicts_with = "watch")]
+        offset: usize,
+
         /// Filter by worktree name or branch (supports multiple)
         #[arg(value_parser = WorktreeBranchParser::new())]
         filter: Vec<String>,
@@ -325,14 +411,36 @@ enum Commands {
 
     /// Get the filesystem path of a worktree
     Path {
-        /// Worktree name (directory name)
+        /// Worktree name (defaults to the worktree containing the current directory)
+        #[arg(value_parser = WorktreeHandleParser::new())]
+        name: Option<String>,
+
+        /// Print a quoted `cd` command to `eval` instead of the bare path
+        #[arg(long)]
+        cd: bool,
+    },
+
+    /// Kill sessions without a worktree and report idle worktrees (dry run by default)
+    Prune {
+        /// Also remove worktrees with no session and no unmerged commits
+        #[arg(long)]
+        worktrees: bool,
+
+        /// Actually kill and remove instead of listing what would happen
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Switch to (or attach to) a worktree's agent window
+    Attach {
+        /// Worktree name, branch, or a unique part of either
         #[arg(value_parser = WorktreeHandleParser::new())]
         name: String,
     },
 
     /// Send a prompt or instruction to a running agent
     Send {
-        /// Worktree name
+        /// Worktree name, branch, or a unique part of either
         #[arg(value_parser = WorktreeHandleParser::new())]
         name: String,
 
@@ -343,17 +451,141 @@ enum Commands {
         /// Read prompt from file
         #[arg(short, long, conflicts_with = "text")]
         file: Option<String>,
+
+        /// Type the text without pressing Enter
+        #[arg(long)]
+        no_enter: bool,
+
+        /// If the agent pane isn't registered yet (e.g. just launched), keep
+        /// looking for up to this long (e.g. `10s`)
+        #[arg(long, value_name = "DURATION", value_parser = crate::util::parse_duration)]
+        wait_for_agent: Option<std::time::Duration>,
     },
 
     /// Capture terminal output from a running agent
     Capture {
-        /// Worktree name
+        /// Worktree name, branch, or a unique part of either (defaults to the
+        /// worktree containing the current directory)
         #[arg(value_parser = WorktreeHandleParser::new())]
-        name: String,
+        name: Option<String>,
+
+        /// Number of lines to capture (0, or more than 65535, for the entire scrollback)
+        #[arg(
+            short = 'n',
+            long,
+            default_value = "200",
+            value_parser = command::capture::parse_lines,
+            allow_hyphen_values = true
+        )]
+        lines: crate::multiplexer::CaptureRange,
+
+        /// Walk back through the scrollback a page at a time: 1 is the most
+        /// recent page, 2 the one before it, and so on
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..), conflicts_with = "lines")]
+        page: Option<u32>,
+
+        /// Lines per page with --page (default: 200)
+        #[arg(long, value_name = "LINES", value_parser = clap::value_parser!(u32).range(1..), requires = "page")]
+        page_size: Option<u32>,
+
+        /// Pipe the transcript through `capture.summarizer` and print its output
+        #[arg(long)]
+        summarize: bool,
+
+        /// Keep ANSI escape sequences (colors, styles) in the output
+        #[arg(long)]
+        raw: bool,
+
+        /// Keep colors and styles but drop cursor movement, screen clearing
+        /// and other escape sequences that garble a linear log
+        #[arg(long, conflicts_with = "raw")]
+        preserve_color: bool,
+
+        /// Hard-wrap output lines at this many columns, breaking at word
+        /// boundaries where possible
+        #[arg(long, value_name = "COLS", value_parser = clap::value_parser!(u16).range(1..), conflicts_with_all = ["raw", "preserve_color"])]
+        width: Option<u16>,
+
+        /// Don't wrap lines (the default; overrides an earlier --width)
+        #[arg(long, overrides_with = "width")]
+        no_wrap: bool,
+
+        /// Load the output into a tmux paste buffer (optionally named) instead of printing it
+        #[arg(long, value_name = "NAME", num_args = 0..=1, require_equals = true)]
+        to_buffer: Option<Option<String>>,
+
+        /// Print a JSON object with the worktree, branch, pane id and line
+        /// counts alongside the captured content
+        #[arg(long, conflicts_with = "to_buffer")]
+        json: bool,
+
+        /// Wrap the output in a markdown code fence, longer than any run of
+        /// backticks in the capture
+        #[arg(long, conflicts_with_all = ["json", "raw", "preserve_color"])]
+        markdown: bool,
+
+        /// With --markdown, tag the code block with this language
+        #[arg(long, value_name = "LANG", requires = "markdown", value_parser = command::capture::parse_fence_lang)]
+        lang: Option<String>,
+
+        /// Print only the terminal title the agent last set (OSC 0/2), or the
+        /// pane title the multiplexer recorded, instead of the capture
+        #[arg(long, conflicts_with_all = ["with_title", "json", "summarize", "markdown", "width"])]
+        extract_title: bool,
+
+        /// Print the terminal title, then a blank line, then the capture
+        #[arg(long, conflicts_with = "json")]
+        with_title: bool,
+
+        /// Save the capture (ANSI stripped) to the worktree's state directory
+        /// instead of printing it
+        #[arg(long, conflicts_with_all = ["raw", "preserve_color", "width", "summarize", "to_buffer", "json", "markdown", "extract_title", "with_title"])]
+        snapshot: bool,
+
+        /// Print a unified diff between the latest two `--snapshot`s
+        #[arg(long, conflicts_with_all = ["snapshot", "raw", "preserve_color", "width", "summarize", "to_buffer", "json", "markdown", "extract_title", "with_title", "all_panes", "since", "fail_if_empty", "wait"])]
+        diff: bool,
+
+        /// Capture the pane with this role (`panes[].role` in the layout)
+        /// instead of the agent pane
+        #[arg(long, value_name = "ROLE", default_value = command::capture::AGENT_ROLE, conflicts_with = "all_panes")]
+        pane: String,
+
+        /// Capture every pane in the worktree's window, not just the agent pane
+        #[arg(long)]
+        all_panes: bool,
+
+        /// Which output stream to capture. `stderr` needs the agent to run
+        /// under `workmux sandbox run --buffer-stderr`
+        #[arg(long, value_enum, default_value = "combined")]
+        stream: command::capture::CaptureStream,
 
-        /// Number of lines to capture
-        #[arg(short = 'n', long, default_value = "200")]
-        lines: u16,
+        /// Only output lines after the last line containing this marker
+        #[arg(long, value_name = "MARKER")]
+        since: Option<String>,
+
+        /// Exit with status 1 instead of printing when the capture is blank
+        #[arg(long)]
+        fail_if_empty: bool,
+
+        /// Poll the pane until its output (ANSI stripped) matches this regex,
+        /// then print the capture. `^` and `$` match at line boundaries
+        #[arg(long, value_name = "REGEX", value_parser = command::capture::parse_wait_pattern)]
+        wait: Option<regex::Regex>,
+
+        /// With --wait, give up after this long (e.g. `30s`, `5m`) and exit
+        /// with status 124
+        #[arg(long, value_name = "DURATION", requires = "wait", value_parser = crate::util::parse_duration)]
+        timeout: Option<std::time::Duration>,
+
+        /// If the agent pane isn't registered yet (e.g. just launched), keep
+        /// looking for up to this long (e.g. `10s`)
+        #[arg(long, value_name = "DURATION", value_parser = crate::util::parse_duration, conflicts_with = "diff")]
+        wait_for_agent: Option<std::time::Duration>,
+
+        /// With --wait, how often to capture the pane
+        #[arg(long, value_name = "DURATION", requires = "wait", default_value = "1s", value_parser = crate::util::parse_duration)]
+        interval: std::time::Duration,
     },
 
     /// Query agent status for worktrees
@@ -392,7 +624,7 @@ enum Commands {
 
     /// Run a command in a worktree's window
     Run {
-        /// Worktree name
+        /// Worktree name, branch, or a unique part of either
         #[arg(value_parser = WorktreeHandleParser::new())]
         name: String,
 
@@ -411,13 +643,46 @@ enum Commands {
         /// Maximum wait time in seconds
         #[arg(long)]
         timeout: Option<u64>,
+
+        /// If the agent pane isn't registered yet (e.g. just launched), keep
+        /// looking for up to this long (e.g. `10s`)
+        #[arg(long, value_name = "DURATION", value_parser = crate::util::parse_duration)]
+        wait_for_agent: Option<std::time::Duration>,
+    },
+
+    /// Run a one-off command in a worktree's sandbox VM (Lima backend)
+    #[command(name = "exec")]
+    VmExec {
+        /// Worktree name
+        #[arg(value_parser = WorktreeHandleParser::new())]
+        name: String,
+
+        /// Command to run (everything after --)
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
     },
 
     /// Generate example .workmux.yaml configuration file
     Init,
 
     /// Set up agent status tracking hooks
-    Setup,
+    Setup {
+        /// Install the OpenCode plugin into this repository's .opencode/
+        /// instead of the global OpenCode config
+        #[arg(long)]
+        local: bool,
+    },
+
+    /// Diagnose common setup problems (and fix them with --fix)
+    Doctor {
+        /// Offer to fix problems that can be remediated automatically
+        #[arg(long)]
+        fix: bool,
+
+        /// Apply fixes without prompting (with --fix)
+        #[arg(short, long, requires = "fix")]
+        yes: bool,
+    },
 
     /// Show detailed documentation (renders README.md)
     Docs,
@@ -425,6 +690,14 @@ enum Commands {
     /// Show the changelog (what's new in each version)
     Changelog,
 
+    /// Show the workmux version (with --verbose, also the OS, multiplexer,
+    /// limactl and git versions)
+    Version {
+        /// Output the verbose report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
     /// Show a TUI dashboard of all active workmux agents across all sessions
     Dashboard {
         /// Preview pane size as percentage (10-90). Larger = more preview, less table.
@@ -453,6 +726,23 @@ enum Commands {
     SetWindowStatus {
         #[arg(value_enum)]
         command: command::set_window_status::SetWindowStatusCommand,
+
+        /// Tokens used so far, shown as a compact indicator (e.g. ⚡ 12k)
+        #[arg(long)]
+        tokens: Option<u64>,
+
+        /// Cost so far in USD, shown next to the token count
+        #[arg(long)]
+        cost: Option<f64>,
+
+        /// Fail when not running inside a multiplexer pane instead of doing nothing
+        #[arg(long)]
+        require_mux: bool,
+
+        /// Print the label that would be shown (from `status_template`)
+        /// instead of setting it. Works outside a multiplexer
+        #[arg(long, conflicts_with = "require_mux")]
+        print: bool,
     },
 
     /// Set the base branch for the current worktree (used after rebasing)
@@ -487,6 +777,13 @@ enum Commands {
         args: Vec<String>,
     },
 
+    /// Open a worktree file in the host editor (used inside sandbox guests)
+    #[command(hide = true, name = "open-path")]
+    OpenPath {
+        /// File path, relative to the current directory
+        path: std::path::PathBuf,
+    },
+
     /// Generate shell completions
     Completions {
         /// The shell to generate completions for
@@ -532,10 +829,40 @@ fn should_prompt_status_setup(cmd: &Commands) -> bool {
     )
 }
 
+/// Apply `--repo-root` (or `WORKMUX_REPO_ROOT`) for the whole invocation.
+///
+/// Validates the path is inside a git repository, then switches the process
+/// into it so repo discovery, config loading and every git call resolve
+/// against that repository regardless of where workmux was launched.
+fn apply_repo_root(repo_root: Option<PathBuf>) -> Result<()> {
+    let Some(path) = repo_root.or_else(|| {
+        std::env::var_os("WORKMUX_REPO_ROOT")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    }) else {
+        return Ok(());
+    };
+
+    git::get_repo_root_for(&path)
+        .with_context(|| format!("Invalid --repo-root: {}", path.display()))?;
+    std::env::set_current_dir(&path)
+        .with_context(|| format!("Failed to switch to repo root: {}", path.display()))?;
+    Ok(())
+}
+
 // --- Public Entry Point ---
 pub fn run() -> Result<()> {
     let cli = Cli::parse();
 
+    crate::logger::init(
+        crate::logger::LogFormat::resolve(cli.log_format)?,
+        cli.verbose,
+        cli.quiet,
+    )?;
+    tracing::info!(args = ?std::env::args().collect::<Vec<_>>(), "workmux start");
+
+    apply_repo_root(cli.repo_root.clone())?;
+
     // Always initialize nerdfont setting for prefix consistency across commands.
     // Only prompt interactively for commands that display icons.
     // If config fails to load, skip the nerdfont wizard -- it will be shown on
@@ -545,6 +872,7 @@ pub fn run() -> Result<()> {
         Ok(cfg) => (cfg, true),
         Err(_) => (config::Config::default(), false),
     };
+    crate::multiplexer::init(&cfg)?;
     let has_pua = nerdfont::config_has_pua(&cfg);
     let nerdfont_enabled = if cfg.nerdfont.is_some() || has_pua {
         // Already configured or PUA detected
@@ -628,12 +956,124 @@ pub fn run() -> Result<()> {
             force,
             keep_branch,
         } => command::remove::run(names, gone, all, force, keep_branch),
-        Commands::List { pr, filter } => command::list::run(pr, &filter),
-        Commands::Path { name } => command::path::run(&name),
-        Commands::Send { name, text, file } => {
-            command::send::run(&name, text.as_deref(), file.as_deref())
+        Commands::Rename { old, new } => command::rename::run(&old, &new),
+        Commands::List {
+            pr,
+            base,
+            stale,
+            upstream,
+            size,
+            format,
+            porcelain,
+            watch,
+            limit,
+            offset,
+            filter,
+        } => command::list::run(
+            pr,
+            &filter,
+            base.as_deref(),
+            stale,
+            upstream,
+            size,
+            format.as_ref(),
+            porcelain,
+            watch,
+            command::list::Page { offset, limit },
+        ),
+        Commands::Path { name, cd } => command::path::run(name.as_deref(), cd),
+        Commands::Prune { worktrees, yes } => command::prune::run(worktrees, yes),
+        Commands::Attach { name } => command::attach::run(&name),
+        Commands::Send {
+            name,
+            text,
+            file,
+            no_enter,
+            wait_for_agent,
+        } => command::send::run(
+            &name,
+            text.as_deref(),
+            file.as_deref(),
+            no_enter,
+            wait_for_agent,
+        ),
+        Commands::Capture {
+            name,
+            lines,
+            page,
+            page_size,
+            raw,
+            preserve_color,
+            width,
+            no_wrap,
+            summarize,
+            to_buffer,
+            json,
+            markdown,
+            lang,
+            extract_title,
+            with_title,
+            snapshot,
+            diff,
+            pane,
+            all_panes,
+            stream,
+            since,
+            fail_if_empty,
+            wait,
+            timeout,
+            interval,
+            wait_for_agent,
+        } => {
+            if diff {
+                return command::capture::diff_snapshots(name.as_deref());
+            }
+            let lines = match page {
+                Some(page) => command::capture::page_range(
+                    page,
+                    page_size.unwrap_or(command::capture::DEFAULT_PAGE_SIZE),
+                ),
+                None => lines,
+            };
+            let target = match (to_buffer, json) {
+                (Some(buffer), _) => command::capture::CaptureTarget::Buffer(buffer),
+                (None, true) => command::capture::CaptureTarget::Json,
+                (None, false) => command::capture::CaptureTarget::Stdout,
+            };
+            let ansi = match (raw, preserve_color) {
+                (true, _) => command::capture::AnsiMode::Raw,
+                (false, true) => command::capture::AnsiMode::Color,
+                (false, false) => command::capture::AnsiMode::Strip,
+            };
+            let title_mode = match (extract_title, with_title) {
+                (true, _) => command::capture::TitleMode::Only,
+                (false, true) => command::capture::TitleMode::WithBody,
+                (false, false) => command::capture::TitleMode::Off,
+            };
+            command::capture::run(command::capture::CaptureOptions {
+                name,
+                range: lines,
+                ansi,
+                width: width.filter(|_| !no_wrap),
+                summarize,
+                pane,
+                all_panes,
+                stream,
+                since,
+                target,
+                markdown,
+                lang,
+                title_mode,
+                snapshot,
+                fail_if_empty,
+                wait: wait.map(|pattern| command::capture::WaitFor {
+                    pattern,
+                    timeout,
+                    interval,
+                }),
+                wait_for_agent,
+            })
         }
-        Commands::Capture { name, lines } => command::capture::run(&name, lines),
         Commands::Status {
             worktrees,
             json,
@@ -651,19 +1091,29 @@ pub fn run() -> Result<()> {
             background,
             keep,
             timeout,
-        } => command::run::run(&name, command, background, keep, timeout),
+            wait_for_agent,
+        } => command::run::run(&name, command, background, keep, timeout, wait_for_agent),
         Commands::Exec { run_dir } => command::exec::run(&run_dir),
+        Commands::VmExec { name, command } => command::vm_exec::run(&name, &command),
         Commands::Init => crate::config::Config::init(),
-        Commands::Setup => command::setup::run(),
+        Commands::Setup { local } => command::setup::run(local),
+        Commands::Doctor { fix, yes } => command::doctor::run(fix, yes),
         Commands::Docs => command::docs::run(),
         Commands::Changelog => command::changelog::run(),
+        Commands::Version { json } => command::version::run(cli.verbose > 0, json),
         Commands::Dashboard { preview_size, diff } => command::dashboard::run(preview_size, diff),
         Commands::Config(args) => command::config::run(args),
         Commands::Claude { command } => match command {
             ClaudeCommands::Prune => prune_claude_config(),
         },
         Commands::Sandbox(args) => command::sandbox::run(args),
-        Commands::SetWindowStatus { command } => command::set_window_status::run(command),
+        Commands::SetWindowStatus {
+            command,
+            tokens,
+            cost,
+            require_mux,
+            print,
+        } => command::set_window_status::run(command, tokens, cost, require_mux, print),
         Commands::SetBase { base } => command::set_base::run(&base),
         Commands::LastDone => command::last_done::run(),
         Commands::LastAgent => command::last_agent::run(),
@@ -674,6 +1124,7 @@ pub fn run() -> Result<()> {
             let code = command::host_exec::run(command, cmd_args)?;
             std::process::exit(code);
         }
+        Commands::OpenPath { path } => command::open_path::run(&path),
         Commands::Completions { shell } => {
             generate_completions(shell);
             Ok(())
@@ -1,6 +1,6 @@
 use crate::config::MuxMode;
 use crate::multiplexer::handle::mode_label;
-use crate::multiplexer::{MuxHandle, create_backend, detect_backend, util::prefixed};
+use crate::multiplexer::{self, MuxHandle, util::prefixed};
 use crate::prompt::{Prompt, PromptDocument, foreach_from_frontmatter};
 use crate::spinner;
 use crate::template::{
@@ -74,7 +74,7 @@ fn read_stdin_lines() -> Result<Vec<String>> {
 /// Returns Ok(()) if all preconditions are met, or an error listing all failures.
 fn check_preconditions() -> Result<()> {
     let is_git = git::is_git_repo()?;
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let is_mux_running = mux.is_running()?;
 
     if is_git && is_mux_running {
@@ -262,7 +262,7 @@ pub fn run(
         if sandbox_override {
             rescue_config.sandbox.enabled = Some(true);
         }
-        let mux = create_backend(detect_backend());
+        let mux = multiplexer::current();
         let rescue_context = workflow::WorkflowContext::new(rescue_config, mux, rescue_location)?;
         // Derive handle for rescue flow (uses config for naming strategy/prefix)
         let handle =
@@ -520,7 +520,7 @@ impl<'a> CreationPlan<'a> {
         }
 
         // Create backend once for all specs
-        let mux = create_backend(detect_backend());
+        let mux = multiplexer::current();
 
         // Track targets for --wait (all created windows/sessions)
         let mut created_targets = Vec::new();
@@ -0,0 +1,24 @@
+use anyhow::{Result, anyhow, bail};
+
+use crate::{multiplexer, workflow};
+
+/// Jump to a worktree's agent window: switch the client when already inside
+/// the multiplexer, attach to the session otherwise.
+pub fn run(name: &str) -> Result<()> {
+    let mux = multiplexer::current();
+    if !mux.is_running().unwrap_or(false) {
+        bail!("{} is not running", mux.name());
+    }
+
+    let (_path, agents) = workflow::resolve_worktree_agents(name, mux.as_ref())?;
+    let agent = agents
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow!("Worktree '{}' has no live session", name))?;
+
+    if mux.current_pane_id().is_some() {
+        mux.switch_to_pane(&agent.pane_id, Some(&agent.window_name))
+    } else {
+        mux.attach_session(&agent.pane_id)
+    }
+}
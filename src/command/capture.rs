@@ -1,23 +1,811 @@
-use anyhow::{Result, anyhow};
+use std::borrow::Cow;
+use std::io::Write;
+use std::os::unix::fs::DirBuilderExt;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
 use console::strip_ansi_codes;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
 
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::config::Config;
+use crate::git;
+use crate::multiplexer::{self, CaptureRange, Multiplexer};
+use crate::sandbox::rpc::{self, RpcRequest, RpcResponse};
+use crate::state::StateStore;
 use crate::workflow;
 
-pub fn run(name: &str, lines: u16) -> Result<()> {
-    let mux = create_backend(detect_backend());
-    let (_path, agent) = workflow::resolve_worktree_agent(name, mux.as_ref())?;
+/// `--wait`: poll the pane until its output matches `pattern`.
+pub struct WaitFor {
+    pub pattern: Regex,
+    /// Give up after this long; wait indefinitely with `None`
+    pub timeout: Option<Duration>,
+    /// Time between captures
+    pub interval: Duration,
+}
+
+/// Exit status when `--wait` times out, as with `timeout(1)`.
+const EXIT_WAIT_TIMEOUT: i32 = 124;
+
+/// The pane being waited on closed before its output matched.
+#[derive(Debug, thiserror::Error)]
+#[error("Pane {pane_id} closed before its output matched '{pattern}'")]
+pub struct PaneClosed {
+    pane_id: String,
+    pattern: String,
+    #[source]
+    cause: anyhow::Error,
+}
+
+/// Check a `--lang` value: it goes on the opening fence line, so it can't
+/// contain backticks or whitespace.
+pub fn parse_fence_lang(s: &str) -> Result<String, String> {
+    if s.is_empty() || s.contains(|c: char| c == '`' || c.is_whitespace()) {
+        return Err("must be a single word without backticks".to_string());
+    }
+    Ok(s.to_string())
+}
+
+/// Compile the `--wait` pattern, with `^` and `$` matching at line
+/// boundaries. Used as a clap value parser, so a bad pattern is reported
+/// before anything is captured.
+pub fn parse_wait_pattern(s: &str) -> Result<Regex, String> {
+    RegexBuilder::new(s)
+        .multi_line(true)
+        .build()
+        .map_err(|e| format!("invalid --wait pattern: {}", e))
+}
+
+/// Which of the agent's output streams to capture.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureStream {
+    /// The pane contents, stdout and stderr interleaved
+    Combined,
+    /// Only stderr, buffered by a `sandbox run --buffer-stderr` supervisor
+    Stderr,
+}
+
+/// Which terminal escape sequences survive a capture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiMode {
+    /// Remove them all (the default)
+    Strip,
+    /// Keep SGR colors and styles, drop cursor movement, erasing and the
+    /// rest (`--preserve-color`)
+    Color,
+    /// Keep everything (`--raw`)
+    Raw,
+}
+
+impl AnsiMode {
+    fn apply<'a>(self, output: &'a str) -> Cow<'a, str> {
+        match self {
+            // `strip_ansi_codes` leaves OSC sequences (titles, hyperlinks) in
+            AnsiMode::Strip if output.contains("\x1b]") => {
+                Cow::Owned(strip_ansi_codes(&strip_osc(output)).into_owned())
+            }
+            AnsiMode::Strip => strip_ansi_codes(output),
+            AnsiMode::Color => Cow::Owned(keep_sgr_only(output)),
+            AnsiMode::Raw => Cow::Borrowed(output),
+        }
+    }
+}
+
+/// Whether to report the terminal title the agent last set (OSC 0 or 2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleMode {
+    /// Don't (the default)
+    Off,
+    /// Print only the title (`--extract-title`)
+    Only,
+    /// Print the title, a blank line, then the capture (`--with-title`)
+    WithBody,
+}
+
+/// Where the captured (or summarized) output goes.
+pub enum CaptureTarget {
+    /// Print to stdout
+    Stdout,
+    /// Load into a multiplexer paste buffer (`None` = unnamed buffer)
+    Buffer(Option<String>),
+    /// Print a [`CaptureJson`] object to stdout
+    Json,
+}
+
+/// `capture --json` output: the capture plus where it came from.
+#[derive(Serialize)]
+struct CaptureJson<'a> {
+    worktree: &'a str,
+    branch: Option<String>,
+    pane_id: &'a str,
+    /// `null` when the entire scrollback was requested
+    lines_requested: Option<u16>,
+    /// Lines captured, before any summarizer ran
+    line_count: usize,
+    ansi_stripped: bool,
+    content: &'a str,
+}
+
+/// What `workmux capture` captures and what it does with the result.
+pub struct CaptureOptions {
+    /// Worktree whose agent to capture; the current one when `None`
+    pub name: Option<String>,
+    pub range: CaptureRange,
+    pub ansi: AnsiMode,
+    /// Wrap lines at this many columns (`--width`)
+    pub width: Option<u16>,
+    /// Pipe the capture through `capture.summarizer`
+    pub summarize: bool,
+    /// Role of the pane to capture (`--pane`)
+    pub pane: String,
+    /// Capture every pane in the agent's window
+    pub all_panes: bool,
+    pub stream: CaptureStream,
+    /// Only output after the last line containing this marker
+    pub since: Option<String>,
+    pub target: CaptureTarget,
+    /// Wrap the output in a fenced code block, tagged with `lang`
+    pub markdown: bool,
+    pub lang: Option<String>,
+    pub title_mode: TitleMode,
+    /// Save the capture as a snapshot instead of printing it
+    pub snapshot: bool,
+    /// Exit non-zero when the capture is empty
+    pub fail_if_empty: bool,
+    pub wait: Option<WaitFor>,
+    /// How long to wait for the worktree's agent to appear
+    pub wait_for_agent: Option<Duration>,
+}
+
+/// How [`run`] gets the capture to its target.
+#[derive(Debug, PartialEq, Eq)]
+enum Delivery {
+    /// The pane's bytes, even where they aren't UTF-8 (`--raw`)
+    Raw,
+    /// Printed as it's read, so a huge scrollback is never held at once
+    Streamed,
+    /// Collected into a transcript first, for options that need all of it
+    Transcript,
+}
+
+impl CaptureOptions {
+    fn delivery(&self) -> Delivery {
+        // Nothing but line trimming happens to the capture on its way to stdout
+        let verbatim = self.stream == CaptureStream::Combined
+            && !self.all_panes
+            && self.wait.is_none()
+            && self.since.is_none()
+            && !self.summarize
+            && self.width.is_none()
+            && !self.markdown
+            && !self.fail_if_empty
+            && self.title_mode == TitleMode::Off
+            && !self.snapshot
+            && matches!(self.target, CaptureTarget::Stdout);
+        match (verbatim, self.ansi, self.range) {
+            (false, _, _) => Delivery::Transcript,
+            (true, AnsiMode::Raw, _) => Delivery::Raw,
+            (true, _, CaptureRange::All) => Delivery::Streamed,
+            (true, _, _) => Delivery::Transcript,
+        }
+    }
+}
+
+pub fn run(options: CaptureOptions) -> Result<()> {
+    let delivery = options.delivery();
+    let CaptureOptions {
+        name,
+        range,
+        ansi,
+        width,
+        summarize,
+        pane,
+        all_panes,
+        stream,
+        since,
+        target,
+        markdown,
+        lang,
+        title_mode,
+        snapshot,
+        fail_if_empty,
+        wait,
+        wait_for_agent,
+    } = options;
+    let since = since.as_deref();
+    let lang = lang.as_deref();
+    if stream == CaptureStream::Stderr && (all_panes || pane != AGENT_ROLE) {
+        bail!(
+            "--stream stderr only captures the agent; it can't be combined with --pane or --all-panes"
+        );
+    }
+    if stream == CaptureStream::Stderr && wait.is_some() {
+        bail!("--wait polls the pane; it can't be combined with --stream stderr");
+    }
+    if stream == CaptureStream::Stderr && matches!(range, CaptureRange::Span { .. }) {
+        bail!("--page walks the pane's scrollback; it can't be combined with --stream stderr");
+    }
+    let mux = multiplexer::require_running()?;
+    let name = match name {
+        Some(name) => name,
+        None => workflow::resolve_current_worktree()?,
+    };
+    let (path, agent) = workflow::wait_for_worktree_agent(&name, mux.as_ref(), wait_for_agent)?;
+    let pane_id = match pane.as_str() {
+        AGENT_ROLE => agent.pane_id.clone(),
+        role => pane_for_role(mux.as_ref(), &agent.pane_id, role, &name)?,
+    };
+
+    let range = clamp_page(mux.as_ref(), &pane_id, range)?;
+
+    match delivery {
+        Delivery::Raw => {
+            let output = mux.capture_pane_bytes(&pane_id, range)?;
+            let mut out = std::io::stdout().lock();
+            out.write_all(&raw_capture(&output, range))?;
+            out.flush()?;
+            return Ok(());
+        }
+        Delivery::Streamed => {
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            stream_capture(mux.as_ref(), &pane_id, ansi, &mut out)?;
+            out.flush()?;
+            return Ok(());
+        }
+        Delivery::Transcript => {}
+    }
+
+    let Transcript {
+        lines: transcript,
+        marker_found,
+        has_output,
+        title,
+    } = match (stream, &wait) {
+        (CaptureStream::Combined, Some(wait)) => {
+            let capture =
+                || capture_transcript(mux.as_ref(), &pane_id, range, ansi, all_panes, since);
+            match wait_for_match(capture, wait, &pane_id)? {
+                Some(transcript) => transcript,
+                None => {
+                    eprintln!(
+                        "workmux: timed out waiting for '{}' in '{}'",
+                        wait.pattern, name
+                    );
+                    std::process::exit(EXIT_WAIT_TIMEOUT);
+                }
+            }
+        }
+        (CaptureStream::Combined, None) => {
+            capture_transcript(mux.as_ref(), &pane_id, range, ansi, all_panes, since)?
+        }
+        (CaptureStream::Stderr, _) => {
+            stderr_transcript(&fetch_stderr(&path, &name)?, range, ansi, since)
+        }
+    };
 
-    let output = mux
-        .capture_pane(&agent.pane_id, lines)
-        .ok_or_else(|| anyhow!("Failed to capture pane output"))?;
+    if let Some(marker) = since
+        && !marker_found
+    {
+        let searched = match range {
+            CaptureRange::Lines(n) => format!("the last {} lines", n),
+            CaptureRange::All => "the scrollback".to_string(),
+            CaptureRange::Span { .. } => "the requested page".to_string(),
+        };
+        eprintln!(
+            "workmux: marker '{}' not found in {}; showing the full capture",
+            marker, searched
+        );
+    }
+
+    if fail_if_empty && !has_output {
+        eprintln!("workmux: capture of '{}' is empty", name);
+        std::process::exit(1);
+    }
+
+    if snapshot {
+        let dir = snapshot_dir(&StateStore::new()?, &path)?;
+        let saved = save_snapshot(&dir, &transcript, now_ms())?;
+        let retention = Config::load(None)?.capture.snapshot_retention();
+        prune_snapshots(&dir, retention)?;
+        eprintln!("Saved snapshot {}", saved.display());
+        return Ok(());
+    }
+
+    let content = if summarize {
+        let config = Config::load(None)?;
+        let summarizer = config.capture.summarizer().ok_or_else(|| {
+            anyhow!(
+                "No summarizer configured. Set 'capture.summarizer' in your config, e.g.:\n\n  \
+                 capture:\n    summarizer: \"llm -s 'Summarize what the agent did'\""
+            )
+        })?;
+        run_summarizer(summarizer, &transcript)?
+            .trim_end()
+            .to_string()
+    } else {
+        transcript.join("\n")
+    };
+    let content = match width {
+        Some(width) => wrap_lines(&content, width as usize),
+        None => content,
+    };
+    let content = if markdown {
+        fence_code_block(&content, lang)
+    } else {
+        content
+    };
+    let content = if title_mode == TitleMode::Off {
+        content
+    } else {
+        // Multiplexers like tmux consume title sequences rather than keep
+        // them in the scrollback; they record the title instead
+        let title = title
+            .or_else(|| {
+                mux.get_live_pane_info(&pane_id)
+                    .ok()
+                    .flatten()
+                    .and_then(|info| info.title)
+            })
+            .unwrap_or_default();
+        if title.is_empty() {
+            eprintln!("workmux: '{}' hasn't set a terminal title", name);
+        }
+        if title_mode == TitleMode::WithBody && !content.is_empty() {
+            format!("{title}\n\n{content}")
+        } else {
+            title
+        }
+    };
 
-    // Strip ANSI escape codes
-    let stripped = strip_ansi_codes(&output);
+    // Plain output ends with a newline; the JSON `content` doesn't
+    let text = if content.is_empty() {
+        String::new()
+    } else {
+        format!("{content}\n")
+    };
+
+    match target {
+        CaptureTarget::Stdout => print!("{text}"),
+        CaptureTarget::Buffer(buffer) => {
+            mux.set_buffer(buffer.as_deref(), &text)?;
+            match buffer {
+                Some(buffer) => eprintln!("Loaded capture into buffer '{}'", buffer),
+                None => eprintln!("Loaded capture into paste buffer"),
+            }
+        }
+        CaptureTarget::Json => {
+            let json = CaptureJson {
+                worktree: &name,
+                branch: git::get_current_branch_in(&path).ok(),
+                pane_id: &pane_id,
+                lines_requested: match range {
+                    CaptureRange::Lines(n) => Some(n),
+                    CaptureRange::All => None,
+                    CaptureRange::Span { start, end } => u16::try_from(start - end).ok(),
+                },
+                line_count: transcript.len(),
+                ansi_stripped: ansi == AnsiMode::Strip,
+                content: &content,
+            };
+            println!("{}", serde_json::to_string_pretty(&json)?);
+        }
+    }
 
-    // Trim trailing blank lines and limit to requested line count.
-    // tmux capture-pane may return more lines than requested (it captures
-    // from -N to the bottom of the visible pane area).
+    Ok(())
+}
+
+/// `captures/` in the state directory of the worktree at `path`, where
+/// `--snapshot` saves captures. Not in the worktree's `.workmux/`, which a
+/// sandboxed agent could swap for a symlink to have snapshots written, and
+/// old ones deleted, elsewhere on the host.
+fn snapshot_dir(store: &StateStore, path: &Path) -> Result<PathBuf> {
+    let dir = store.worktree_dir(path)?.join("captures");
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir)
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// Write `lines` to `capture-<ms>.txt` in `dir`. The timestamp is
+/// zero-padded so snapshots sort by name in the order they were taken.
+fn save_snapshot(dir: &Path, lines: &[String], at_ms: u128) -> Result<PathBuf> {
+    let path = dir.join(format!("capture-{:015}.txt", at_ms));
+    let mut content = lines.join("\n");
+    if !content.is_empty() {
+        content.push('\n');
+    }
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write snapshot {}", path.display()))?;
+    Ok(path)
+}
+
+/// Snapshots in `dir`, oldest first.
+fn list_snapshots(dir: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", dir.display())),
+    };
+    let mut snapshots: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("capture-") && n.ends_with(".txt"))
+        })
+        .collect();
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// Delete all but the newest `keep` snapshots in `dir`.
+fn prune_snapshots(dir: &Path, keep: usize) -> Result<()> {
+    let snapshots = list_snapshots(dir)?;
+    let excess = snapshots.len().saturating_sub(keep);
+    for old in &snapshots[..excess] {
+        std::fs::remove_file(old)
+            .with_context(|| format!("Failed to remove snapshot {}", old.display()))?;
+    }
+    Ok(())
+}
+
+/// `capture --diff`: print a unified diff between the latest two snapshots
+/// of the worktree `name`.
+pub fn diff_snapshots(name: Option<&str>) -> Result<()> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => workflow::resolve_current_worktree()?,
+    };
+    let dir = snapshot_dir(&StateStore::new()?, &crate::facade::worktree_path(&name)?)?;
+    match snapshot_diff(&dir)? {
+        Some(diff) => print!("{diff}"),
+        None => eprintln!("No changes between the latest two snapshots of '{}'", name),
+    }
+    Ok(())
+}
+
+/// Unified diff between the latest two snapshots in `dir`, or `None` when
+/// they're identical. Relies on `git diff --no-index`.
+fn snapshot_diff(dir: &Path) -> Result<Option<String>> {
+    let snapshots = list_snapshots(dir)?;
+    let [.., old, new] = snapshots.as_slice() else {
+        bail!(
+            "Need two snapshots to diff, found {}. Take them with `workmux capture --snapshot`",
+            snapshots.len()
+        );
+    };
+    let name = |path: &Path| path.file_name().unwrap_or_default().to_os_string();
+    let output = Command::new("git")
+        .args(["diff", "--no-index", "--no-color", "--no-prefix", "--"])
+        .arg(name(old))
+        .arg(name(new))
+        .current_dir(dir)
+        .output()
+        .context("Failed to run git diff")?;
+    // Exit status 1 means the files differ
+    match output.status.code() {
+        Some(0) => Ok(None),
+        Some(1) => {
+            let diff = String::from_utf8_lossy(&output.stdout);
+            // Drop the `diff --git` and `index` lines; the diff starts at `---`
+            let start = diff.find("\n--- ").map_or(0, |i| i + 1);
+            Ok(Some(diff[start..].to_string()))
+        }
+        _ => bail!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+    }
+}
+
+/// The `--pane` role of the pane running the worktree's agent. It's found
+/// through the agent state, not pane tags, so it works in every layout.
+pub const AGENT_ROLE: &str = "agent";
+
+/// Find the pane tagged `role` in the window of the agent pane.
+fn pane_for_role(
+    mux: &dyn Multiplexer,
+    agent_pane: &str,
+    role: &str,
+    worktree: &str,
+) -> Result<String> {
+    let panes = mux.pane_roles(agent_pane)?;
+    if let Some((pane_id, _)) = panes.iter().find(|(_, r)| r.as_deref() == Some(role)) {
+        return Ok(pane_id.clone());
+    }
+
+    let mut available: Vec<&str> = panes.iter().filter_map(|(_, r)| r.as_deref()).collect();
+    available.push(AGENT_ROLE);
+    available.sort_unstable();
+    available.dedup();
+    Err(anyhow!(
+        "No pane with role '{}' in worktree '{}' (available: {})",
+        role,
+        worktree,
+        available.join(", ")
+    ))
+}
+
+/// Captured output after cleanup and `--since` trimming.
+struct Transcript {
+    lines: Vec<String>,
+    /// Whether the `--since` marker was found in any pane
+    marker_found: bool,
+    /// Whether any kept line has non-whitespace content
+    has_output: bool,
+    /// The last terminal title set in the raw output of the agent pane
+    title: Option<String>,
+}
+
+/// Capture the agent pane (or, with `all_panes`, every pane in its window).
+fn capture_transcript(
+    mux: &dyn Multiplexer,
+    pane_id: &str,
+    range: CaptureRange,
+    ansi: AnsiMode,
+    all_panes: bool,
+    since: Option<&str>,
+) -> Result<Transcript> {
+    let mut marker_found = false;
+    let mut has_output = false;
+    let mut trim = |captured: Vec<String>| {
+        let kept = match since {
+            Some(marker) => match lines_after_marker(&captured, marker) {
+                Some(rest) => {
+                    marker_found = true;
+                    rest
+                }
+                None => captured,
+            },
+            None => captured,
+        };
+        has_output |= kept.iter().any(|l| !l.trim().is_empty());
+        kept
+    };
+
+    let mut title = None;
+    let lines = if all_panes {
+        let mut lines = Vec::new();
+        for id in mux.window_pane_ids(pane_id)? {
+            let output = mux.capture_pane_bytes(&id, range)?;
+            let output = decode_capture(&output, &id);
+            if id == pane_id {
+                title = last_title(&output);
+            }
+            lines.push(pane_header(&id));
+            lines.extend(trim(clean_capture(&output, range, ansi)));
+        }
+        lines
+    } else {
+        let output = mux.capture_pane_bytes(pane_id, range)?;
+        let output = decode_capture(&output, pane_id);
+        title = last_title(&output);
+        trim(clean_capture(&output, range, ansi))
+    };
+
+    Ok(Transcript {
+        lines,
+        marker_found,
+        has_output,
+        title,
+    })
+}
+
+/// Decode a pane capture as UTF-8, replacing any invalid bytes (binary
+/// progress output, text in another encoding) with U+FFFD and saying so on
+/// stderr.
+fn decode_capture<'a>(output: &'a [u8], pane_id: &str) -> Cow<'a, str> {
+    let text = String::from_utf8_lossy(output);
+    if let Cow::Owned(_) = text {
+        eprintln!(
+            "workmux: pane {} output isn't valid UTF-8; replaced the invalid bytes with U+FFFD",
+            pane_id
+        );
+    }
+    text
+}
+
+/// `--raw` output: the capture's bytes as they are, limited to the
+/// requested line count with trailing blank lines dropped, as
+/// [`clean_capture`] does for text.
+fn raw_capture(output: &[u8], range: CaptureRange) -> Vec<u8> {
+    let mut lines: Vec<&[u8]> = output.split(|&b| b == b'\n').collect();
+    while lines.last().is_some_and(|l| l.trim_ascii().is_empty()) {
+        lines.pop();
+    }
+    let start = match range {
+        CaptureRange::Lines(n) => lines.len().saturating_sub(n as usize),
+        // Backends capture exactly the span
+        CaptureRange::All | CaptureRange::Span { .. } => 0,
+    };
+    let mut raw = lines[start..].join(&b'\n');
+    if !raw.is_empty() {
+        raw.push(b'\n');
+    }
+    raw
+}
+
+/// Call `capture` every `wait.interval` until its lines, with ANSI codes
+/// stripped, match `wait.pattern`. `None` once `wait.timeout` has passed.
+///
+/// A failing capture means the pane is gone, reported as [`PaneClosed`].
+fn wait_for_match(
+    capture: impl Fn() -> Result<Transcript>,
+    wait: &WaitFor,
+    pane_id: &str,
+) -> Result<Option<Transcript>> {
+    let start = Instant::now();
+    loop {
+        let transcript = capture().map_err(|cause| PaneClosed {
+            pane_id: pane_id.to_string(),
+            pattern: wait.pattern.to_string(),
+            cause,
+        })?;
+        let text = strip_ansi_codes(&transcript.lines.join("\n")).into_owned();
+        if wait.pattern.is_match(&text) {
+            return Ok(Some(transcript));
+        }
+        if wait
+            .timeout
+            .is_some_and(|timeout| start.elapsed() >= timeout)
+        {
+            return Ok(None);
+        }
+        thread::sleep(wait.interval);
+    }
+}
+
+/// Read the agent's buffered stderr from the sandbox supervisor of the
+/// worktree at `path`, using the RPC credentials it saved for it.
+fn fetch_stderr(path: &Path, name: &str) -> Result<String> {
+    let not_supported = || {
+        anyhow!(
+            "--stream stderr is not supported for '{}': multiplexer panes mix stdout and \
+             stderr, so it needs the agent to run under `workmux sandbox run --buffer-stderr`",
+            name
+        )
+    };
+    let session = rpc::load_session(path).ok_or_else(not_supported)?;
+    let mut client = session.connect().map_err(|_| not_supported())?;
+    match client.call(&RpcRequest::ReadStderr { lines: None })? {
+        RpcResponse::StderrLines { lines } => Ok(lines.join("\n")),
+        RpcResponse::Error { message } => bail!(message),
+        other => bail!("Unexpected RPC response: {:?}", other),
+    }
+}
+
+/// Clean and trim buffered stderr the same way as a pane capture.
+fn stderr_transcript(
+    output: &str,
+    range: CaptureRange,
+    ansi: AnsiMode,
+    since: Option<&str>,
+) -> Transcript {
+    let captured = clean_capture(output, range, ansi);
+    let (lines, marker_found) = match since.and_then(|m| lines_after_marker(&captured, m)) {
+        Some(rest) => (rest, true),
+        None => (captured, false),
+    };
+    Transcript {
+        has_output: lines.iter().any(|l| !l.trim().is_empty()),
+        lines,
+        marker_found,
+        title: last_title(output),
+    }
+}
+
+/// Lines per `--page` unless `--page-size` says otherwise.
+pub const DEFAULT_PAGE_SIZE: u32 = 200;
+
+/// The range `--page <page>` (1 = most recent) covers with `size` lines per
+/// page.
+pub fn page_range(page: u32, size: u32) -> CaptureRange {
+    CaptureRange::Span {
+        start: page.saturating_mul(size),
+        end: (page - 1).saturating_mul(size),
+    }
+}
+
+/// Keep a `--page` within the pane's history. A page reaching past the
+/// oldest line comes back short, and one entirely past it is moved to the
+/// oldest page; either way stderr says the start of history was reached.
+fn clamp_page(mux: &dyn Multiplexer, pane_id: &str, range: CaptureRange) -> Result<CaptureRange> {
+    let CaptureRange::Span { start, end } = range else {
+        return Ok(range);
+    };
+    let size = start - end;
+    let captured = line_count(&mux.capture_pane_bytes(pane_id, range)?);
+    if captured >= size as usize {
+        return Ok(range);
+    }
+    eprintln!("workmux: reached start of history");
+    if captured > 0 {
+        return Ok(range);
+    }
+    let total = line_count(&mux.capture_pane_bytes(pane_id, CaptureRange::All)?);
+    let total = u32::try_from(total).unwrap_or(u32::MAX);
+    Ok(CaptureRange::Span {
+        start: total,
+        end: total.saturating_sub(size),
+    })
+}
+
+/// Lines in a capture, not counting the empty one after a final newline.
+fn line_count(output: &[u8]) -> usize {
+    if output.is_empty() {
+        return 0;
+    }
+    output.split(|&b| b == b'\n').count() - usize::from(output.ends_with(b"\n"))
+}
+
+/// Parse the `--lines` argument. `0` means the entire scrollback, and so
+/// does any count too large to request from a backend: more lines than the
+/// pane has only ever yields the whole history anyway.
+pub fn parse_lines(s: &str) -> Result<CaptureRange, String> {
+    let s = s.trim();
+    if s.starts_with('-') {
+        return Err(format!(
+            "invalid line count '{}': must not be negative (use 0 for the entire scrollback)",
+            s
+        ));
+    }
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(format!(
+            "invalid line count '{}': expected a whole number (use 0 for the entire scrollback)",
+            s
+        ));
+    }
+    Ok(match s.parse::<u16>() {
+        Ok(0) | Err(_) => CaptureRange::All,
+        Ok(n) => CaptureRange::Lines(n),
+    })
+}
+
+/// Write the pane's entire scrollback to `out` a line at a time, cleaned as
+/// [`clean_capture`] would. Only a run of blank lines is held back, since
+/// it's dropped if nothing follows it.
+fn stream_capture(
+    mux: &dyn Multiplexer,
+    pane_id: &str,
+    ansi: AnsiMode,
+    out: &mut dyn Write,
+) -> Result<()> {
+    let mut blank_run: Vec<String> = Vec::new();
+    mux.capture_pane_lines(pane_id, CaptureRange::All, &mut |line| {
+        let line = ansi.apply(line);
+        if line.trim().is_empty() {
+            blank_run.push(line.into_owned());
+            return Ok(());
+        }
+        for blank in blank_run.drain(..) {
+            writeln!(out, "{blank}")?;
+        }
+        writeln!(out, "{line}")?;
+        Ok(())
+    })
+}
+
+/// Strip ANSI escape codes (as `ansi` says), trim trailing blank lines
+/// and limit to the requested line count.
+///
+/// tmux capture-pane may return more lines than requested (it captures
+/// from -N to the bottom of the visible pane area).
+pub(crate) fn clean_capture(output: &str, range: CaptureRange, ansi: AnsiMode) -> Vec<String> {
+    let stripped = ansi.apply(output);
     let trimmed: Vec<&str> = stripped
         .lines()
         .collect::<Vec<_>>()
@@ -28,10 +816,733 @@ pub fn run(name: &str, lines: u16) -> Result<()> {
         .into_iter()
         .rev()
         .collect();
-    let start = trimmed.len().saturating_sub(lines as usize);
-    for line in &trimmed[start..] {
-        println!("{line}");
+    let start = match range {
+        CaptureRange::Lines(lines) => trimmed.len().saturating_sub(lines as usize),
+        // Backends capture exactly the span
+        CaptureRange::All | CaptureRange::Span { .. } => 0,
+    };
+    trimmed[start..].iter().map(|l| l.to_string()).collect()
+}
+
+/// The title set by the last OSC 0 (icon name and title) or OSC 2 (title)
+/// sequence in `output`, ended by BEL or ST (`ESC \\`). An unterminated
+/// sequence at the end of a capture is ignored.
+fn last_title(output: &str) -> Option<String> {
+    let mut title = None;
+    let mut rest = output;
+    while let Some(start) = rest.find("\x1b]") {
+        rest = &rest[start + 2..];
+        let end = rest.find(['\x07', '\x1b']).unwrap_or(rest.len());
+        let terminated = rest[end..].starts_with('\x07') || rest[end..].starts_with("\x1b\\");
+        if terminated && let Some(("0" | "2", text)) = rest[..end].split_once(';') {
+            title = Some(text.to_string());
+        }
+        rest = &rest[end..];
     }
+    title
+}
 
-    Ok(())
+/// Remove OSC sequences, up to BEL or ST, or to the end of an unterminated one.
+fn strip_osc(output: &str) -> String {
+    let mut kept = String::with_capacity(output.len());
+    let mut rest = output;
+    while let Some(start) = rest.find("\x1b]") {
+        kept.push_str(&rest[..start]);
+        rest = &rest[start + 2..];
+        rest = match rest.find(['\x07', '\x1b']) {
+            Some(end) if rest[end..].starts_with("\x1b\\") => &rest[end + 2..],
+            Some(end) if rest[end..].starts_with('\x07') => &rest[end + 1..],
+            // Another escape cut the sequence short
+            Some(end) => &rest[end..],
+            None => "",
+        };
+    }
+    kept.push_str(rest);
+    kept
+}
+
+/// Remove every escape sequence except SGR (`ESC [ ... m`, colors and
+/// styles), so the output can be shown as a colored linear log without
+/// cursor movement or screen clearing scrambling it.
+fn keep_sgr_only(output: &str) -> String {
+    let mut kept = String::with_capacity(output.len());
+    let mut chars = output.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            kept.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: parameter and intermediate bytes, then one final byte
+            Some('[') => {
+                let mut seq = String::from("\x1b[");
+                for c in chars.by_ref() {
+                    seq.push(c);
+                    if ('\x40'..='\x7e').contains(&c) {
+                        break;
+                    }
+                }
+                if seq.ends_with('m') {
+                    kept.push_str(&seq);
+                }
+            }
+            // OSC (titles, hyperlinks): up to BEL or ST (`ESC \`)
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                        break;
+                    }
+                }
+            }
+            // Other escapes (`ESC 7`, `ESC ( B`, ...): intermediates, then a final byte
+            Some(c) if ('\x20'..='\x2f').contains(&c) => {
+                while chars.next_if(|c| ('\x20'..='\x2f').contains(c)).is_some() {}
+                chars.next();
+            }
+            _ => {}
+        }
+    }
+    kept
+}
+
+/// Hard-wrap each line of `content` at `width` columns, breaking at word
+/// boundaries where possible and splitting words longer than a line.
+/// Expects ANSI codes to be stripped already, as they'd count toward the width.
+fn wrap_lines(content: &str, width: usize) -> String {
+    content
+        .lines()
+        .flat_map(|line| textwrap::wrap(line, width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wrap `content` in a fenced markdown code block, tagged with `lang`. The
+/// fence is one backtick longer than the longest run of backticks in the
+/// content (and at least three), so nothing inside can close it.
+fn fence_code_block(content: &str, lang: Option<&str>) -> String {
+    let longest_run = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat((longest_run + 1).max(3));
+    let body = if content.is_empty() {
+        String::new()
+    } else {
+        format!("{content}\n")
+    };
+    format!("{fence}{}\n{body}{fence}", lang.unwrap_or(""))
+}
+
+/// Lines after the last one containing `marker`, or `None` if no line does.
+fn lines_after_marker(lines: &[String], marker: &str) -> Option<Vec<String>> {
+    let pos = lines.iter().rposition(|l| l.contains(marker))?;
+    Some(lines[pos + 1..].to_vec())
+}
+
+/// Separator line printed above each pane's output with `--all-panes`.
+fn pane_header(pane_id: &str) -> String {
+    format!("── pane {} ──", pane_id)
+}
+
+/// Pipe the transcript into the configured summarizer and return its stdout.
+fn run_summarizer(command: &str, transcript: &[String]) -> Result<String> {
+    let mut child = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .with_context(|| format!("Failed to run summarizer: {}", command))?;
+
+    // Feed stdin from its own thread: a summarizer that writes while it
+    // reads would otherwise fill its stdout pipe and block on a large
+    // transcript while we block writing the rest of it.
+    let writer = child.stdin.take().map(|mut stdin| {
+        let mut input = transcript.join("\n");
+        input.push('\n');
+        // The summarizer may exit without reading all input; that's its call.
+        thread::spawn(move || {
+            let _ = stdin.write_all(input.as_bytes());
+        })
+    });
+
+    let output = child
+        .wait_with_output()
+        .context("Failed to wait for summarizer")?;
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "Summarizer exited with code {}: {}",
+            output.status.code().unwrap_or(-1),
+            command
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multiplexer::mock::MockMultiplexer;
+
+    fn plain_options(range: CaptureRange, ansi: AnsiMode) -> CaptureOptions {
+        CaptureOptions {
+            name: None,
+            range,
+            ansi,
+            width: None,
+            summarize: false,
+            pane: AGENT_ROLE.to_string(),
+            all_panes: false,
+            stream: CaptureStream::Combined,
+            since: None,
+            target: CaptureTarget::Stdout,
+            markdown: false,
+            lang: None,
+            title_mode: TitleMode::Off,
+            snapshot: false,
+            fail_if_empty: false,
+            wait: None,
+            wait_for_agent: None,
+        }
+    }
+
+    #[test]
+    fn only_unprocessed_captures_skip_the_transcript() {
+        let all = plain_options(CaptureRange::All, AnsiMode::Strip);
+        assert_eq!(all.delivery(), Delivery::Streamed);
+        let raw = plain_options(CaptureRange::Lines(10), AnsiMode::Raw);
+        assert_eq!(raw.delivery(), Delivery::Raw);
+        let lines = plain_options(CaptureRange::Lines(10), AnsiMode::Color);
+        assert_eq!(lines.delivery(), Delivery::Transcript);
+
+        let processed = [
+            CaptureOptions {
+                width: Some(80),
+                ..plain_options(CaptureRange::All, AnsiMode::Strip)
+            },
+            CaptureOptions {
+                since: Some("marker".to_string()),
+                ..plain_options(CaptureRange::All, AnsiMode::Raw)
+            },
+            CaptureOptions {
+                target: CaptureTarget::Json,
+                ..plain_options(CaptureRange::All, AnsiMode::Strip)
+            },
+            CaptureOptions {
+                stream: CaptureStream::Stderr,
+                ..plain_options(CaptureRange::All, AnsiMode::Raw)
+            },
+            CaptureOptions {
+                title_mode: TitleMode::WithBody,
+                ..plain_options(CaptureRange::All, AnsiMode::Strip)
+            },
+        ];
+        for options in processed {
+            assert_eq!(options.delivery(), Delivery::Transcript);
+        }
+    }
+
+    #[test]
+    fn pages_walk_back_and_clamp_at_the_start_of_history() {
+        let history: Vec<String> = (1..=10).map(|i| format!("l{i}")).collect();
+        let mux = MockMultiplexer::new().with_pane("%1", &history.join("\n"));
+        let page = |n| {
+            let range = clamp_page(&mux, "%1", page_range(n, 4)).unwrap();
+            capture_transcript(&mux, "%1", range, AnsiMode::Strip, false, None)
+                .unwrap()
+                .lines
+        };
+
+        assert_eq!(page(1), ["l7", "l8", "l9", "l10"]);
+        assert_eq!(page(2), ["l3", "l4", "l5", "l6"]);
+        // Reaches the start of history: what's left of it
+        assert_eq!(page(3), ["l1", "l2"]);
+        // Entirely past it: the oldest page
+        assert_eq!(page(9), ["l1", "l2", "l3", "l4"]);
+
+        assert_eq!(
+            page_range(u32::MAX, u32::MAX),
+            CaptureRange::Span {
+                start: u32::MAX,
+                end: u32::MAX
+            }
+        );
+        assert_eq!(line_count(b""), 0);
+        assert_eq!(line_count(b"a\nb\n"), 2);
+        assert_eq!(line_count(b"a\n\nb"), 3);
+    }
+
+    #[test]
+    fn capture_transcript_trims_each_pane_after_marker() {
+        let mux = MockMultiplexer::new()
+            .with_pane("%1", "old\n== go ==\nagent output\n\n")
+            .with_pane("%2", "\x1b[1mshell\x1b[0m\n");
+
+        let single = capture_transcript(
+            &mux,
+            "%1",
+            CaptureRange::All,
+            AnsiMode::Strip,
+            false,
+            Some("== go"),
+        )
+        .unwrap();
+        assert_eq!(single.lines, vec!["agent output"]);
+        assert!(single.marker_found && single.has_output);
+
+        let all = capture_transcript(
+            &mux,
+            "%1",
+            CaptureRange::All,
+            AnsiMode::Strip,
+            true,
+            Some("== go"),
+        )
+        .unwrap();
+        assert_eq!(
+            all.lines,
+            vec!["── pane %1 ──", "agent output", "── pane %2 ──", "shell"]
+        );
+
+        let raw = capture_transcript(
+            &mux,
+            "%2",
+            CaptureRange::Lines(5),
+            AnsiMode::Raw,
+            false,
+            None,
+        )
+        .unwrap();
+        assert_eq!(raw.lines, vec!["\x1b[1mshell\x1b[0m"]);
+        assert!(!raw.marker_found);
+    }
+
+    #[test]
+    fn stderr_transcript_limits_lines_and_trims_after_marker() {
+        let output = "old\n\x1b[31merror: a\x1b[0m\n== go ==\nwarning: b\n";
+
+        let tail = stderr_transcript(output, CaptureRange::Lines(2), AnsiMode::Strip, None);
+        assert_eq!(tail.lines, vec!["== go ==", "warning: b"]);
+        assert!(tail.has_output && !tail.marker_found);
+
+        let since = stderr_transcript(output, CaptureRange::All, AnsiMode::Strip, Some("== go"));
+        assert_eq!(since.lines, vec!["warning: b"]);
+        assert!(since.marker_found);
+
+        let raw = stderr_transcript(output, CaptureRange::Lines(3), AnsiMode::Raw, None);
+        assert_eq!(raw.lines[0], "\x1b[31merror: a\x1b[0m");
+    }
+
+    fn wait_for(pattern: &str, timeout: Option<Duration>) -> WaitFor {
+        WaitFor {
+            pattern: parse_wait_pattern(pattern).unwrap(),
+            timeout,
+            interval: Duration::from_millis(1),
+        }
+    }
+
+    fn transcript(lines: &[&str]) -> Transcript {
+        Transcript {
+            lines: lines.iter().map(|l| l.to_string()).collect(),
+            marker_found: false,
+            has_output: true,
+            title: None,
+        }
+    }
+
+    #[test]
+    fn wait_for_match_polls_until_stripped_output_matches() {
+        let polls = std::cell::Cell::new(0);
+        let capture = || {
+            polls.set(polls.get() + 1);
+            Ok(match polls.get() {
+                1 => transcript(&["thinking..."]),
+                2 => transcript(&["\x1b[32mDone\x1b[0m in 3s"]),
+                _ => transcript(&["step 1", "\x1b[1mDone\x1b[0m"]),
+            })
+        };
+
+        let found = wait_for_match(capture, &wait_for("^Done$", None), "%1").unwrap();
+        assert_eq!(found.unwrap().lines, vec!["step 1", "\x1b[1mDone\x1b[0m"]);
+        assert_eq!(polls.get(), 3);
+    }
+
+    #[test]
+    fn wait_for_match_times_out_or_reports_closed_pane() {
+        let waiting = || Ok(transcript(&["still going"]));
+        let wait = wait_for("Done", Some(Duration::from_millis(5)));
+        assert!(wait_for_match(waiting, &wait, "%1").unwrap().is_none());
+
+        let closed = || Err(anyhow!("pane %4 no longer exists"));
+        let err = wait_for_match(closed, &wait, "%4").err().unwrap();
+        assert!(err.is::<PaneClosed>());
+        assert_eq!(
+            err.to_string(),
+            "Pane %4 closed before its output matched 'Done'"
+        );
+        assert_eq!(err.root_cause().to_string(), "pane %4 no longer exists");
+    }
+
+    #[test]
+    fn parse_wait_pattern_rejects_invalid_regex() {
+        let err = parse_wait_pattern("(unclosed").unwrap_err();
+        assert!(err.starts_with("invalid --wait pattern"));
+    }
+
+    #[test]
+    fn fetch_stderr_without_a_supervisor_is_not_supported() {
+        let tmp = tempfile::tempdir().unwrap();
+        let err = fetch_stderr(tmp.path(), "feat").unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("--stream stderr is not supported for 'feat'")
+        );
+    }
+
+    #[test]
+    fn pane_for_role_finds_tagged_pane_or_lists_roles() {
+        let mux = MockMultiplexer::new()
+            .with_pane("%1", "")
+            .with_pane("%2", "")
+            .with_pane("%3", "")
+            .with_role("%2", "editor")
+            .with_role("%3", "server");
+
+        assert_eq!(pane_for_role(&mux, "%1", "editor", "feat").unwrap(), "%2");
+
+        let err = pane_for_role(&mux, "%1", "logs", "feat").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "No pane with role 'logs' in worktree 'feat' (available: agent, editor, server)"
+        );
+    }
+
+    #[test]
+    fn capture_transcript_reports_missing_pane() {
+        let mux = MockMultiplexer::new();
+        assert!(
+            capture_transcript(&mux, "%9", CaptureRange::All, AnsiMode::Strip, false, None)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_lines_boundaries() {
+        assert_eq!(parse_lines("0"), Ok(CaptureRange::All));
+        assert_eq!(parse_lines("1"), Ok(CaptureRange::Lines(1)));
+        assert_eq!(parse_lines("65535"), Ok(CaptureRange::Lines(u16::MAX)));
+        assert_eq!(parse_lines("65536"), Ok(CaptureRange::All));
+        assert_eq!(
+            parse_lines("99999999999999999999999"),
+            Ok(CaptureRange::All)
+        );
+    }
+
+    #[test]
+    fn parse_lines_rejects_negative_and_garbage() {
+        assert!(parse_lines("-5").unwrap_err().contains("negative"));
+        assert!(parse_lines("ten").unwrap_err().contains("whole number"));
+        assert!(parse_lines("1.5").is_err());
+        assert!(parse_lines("").is_err());
+    }
+
+    #[test]
+    fn clean_capture_strips_ansi_and_trailing_blanks() {
+        let output = "\x1b[32mhello\x1b[0m\nworld\n\n   \n";
+        assert_eq!(
+            clean_capture(output, CaptureRange::Lines(10), AnsiMode::Strip),
+            vec!["hello", "world"]
+        );
+    }
+
+    #[test]
+    fn wrap_lines_breaks_at_word_boundaries() {
+        let line = "The agent refactored the capture module and added tests for wrapping";
+        let wrapped = wrap_lines(&format!("{line}\n\nshort"), 40);
+        assert_eq!(
+            wrapped.lines().collect::<Vec<_>>(),
+            vec![
+                "The agent refactored the capture module",
+                "and added tests for wrapping",
+                "",
+                "short",
+            ]
+        );
+        assert!(wrapped.lines().all(|l| l.chars().count() <= 40));
+
+        let long_word = "x".repeat(50);
+        assert_eq!(
+            wrap_lines(&long_word, 40),
+            format!("{}\n{}", "x".repeat(40), "x".repeat(10))
+        );
+    }
+
+    #[test]
+    fn fence_code_block_outgrows_backticks_in_content() {
+        assert_eq!(
+            fence_code_block("cargo test", Some("console")),
+            "```console\ncargo test\n```"
+        );
+        assert_eq!(fence_code_block("", None), "```\n```");
+        assert_eq!(
+            fence_code_block("```rust\nfn main() {}\n```\nuse `x`", None),
+            "````\n```rust\nfn main() {}\n```\nuse `x`\n````"
+        );
+        assert!(fence_code_block("a ````` b", None).starts_with("``````\n"));
+
+        assert!(parse_fence_lang("rust").is_ok());
+        assert!(parse_fence_lang("two words").is_err());
+        assert!(parse_fence_lang("a`b").is_err());
+    }
+
+    /// Records the largest single write, to check nothing is buffered whole.
+    #[derive(Default)]
+    struct WriteSizes {
+        written: Vec<u8>,
+        largest: usize,
+    }
+
+    impl Write for WriteSizes {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.largest = self.largest.max(buf.len());
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // Cleaning only; the tmux line reader is tested in multiplexer::tmux
+    #[test]
+    fn stream_capture_cleans_like_buffered_capture() {
+        let mut content = String::from("\n  \n");
+        for i in 0..100_000 {
+            content.push_str(&format!("\x1b[32mline {i}\x1b[0m with some padding text\n"));
+            if i % 1000 == 0 {
+                content.push_str("   \n\n");
+            }
+        }
+        content.push_str("\n \n\n");
+        assert!(content.len() > 4_000_000);
+        let mux = MockMultiplexer::new().with_pane("%1", &content);
+
+        let mut out = WriteSizes::default();
+        stream_capture(&mux, "%1", AnsiMode::Strip, &mut out).unwrap();
+
+        let buffered =
+            clean_capture(&content, CaptureRange::All, AnsiMode::Strip).join("\n") + "\n";
+        assert_eq!(String::from_utf8(out.written).unwrap(), buffered);
+        assert!(out.largest < 64, "largest write was {} bytes", out.largest);
+    }
+
+    #[test]
+    fn stream_capture_of_blank_pane_writes_nothing() {
+        let mux = MockMultiplexer::new().with_pane("%1", "\n  \n\n");
+        let mut out = WriteSizes::default();
+        stream_capture(&mux, "%1", AnsiMode::Raw, &mut out).unwrap();
+        assert!(out.written.is_empty());
+    }
+
+    #[test]
+    fn clean_capture_raw_keeps_ansi() {
+        let output = "\x1b[32mhello\x1b[0m\n\n";
+        assert_eq!(
+            clean_capture(output, CaptureRange::All, AnsiMode::Raw),
+            vec!["\x1b[32mhello\x1b[0m"]
+        );
+    }
+
+    #[test]
+    fn invalid_utf8_is_replaced_in_text_and_kept_in_raw_output() {
+        let output: &[u8] = b"old\n\x1b[1mbuild\x1b[0m \xff\xfe 50%\r\ndone\n\n";
+        let mux = MockMultiplexer::new().with_pane_bytes("%1", output);
+
+        let text = capture_transcript(&mux, "%1", CaptureRange::All, AnsiMode::Strip, false, None)
+            .unwrap();
+        assert_eq!(
+            text.lines,
+            vec!["old", "build \u{fffd}\u{fffd} 50%", "done"]
+        );
+
+        assert_eq!(
+            raw_capture(output, CaptureRange::Lines(2)),
+            b"\x1b[1mbuild\x1b[0m \xff\xfe 50%\r\ndone\n"
+        );
+        assert_eq!(raw_capture(b"\n \n", CaptureRange::All), b"");
+    }
+
+    #[test]
+    fn snapshots_prune_to_retention_and_diff_the_latest_two() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let lines = |ls: &[&str]| ls.iter().map(|l| l.to_string()).collect::<Vec<_>>();
+
+        save_snapshot(dir, &lines(&["$ cargo test"]), 1_000).unwrap();
+        let err = snapshot_diff(dir).unwrap_err();
+        assert!(err.to_string().contains("found 1"), "{err:#}");
+
+        save_snapshot(dir, &lines(&["$ cargo test", "running"]), 2_000).unwrap();
+        save_snapshot(dir, &lines(&["$ cargo test", "ok", "done"]), 10_000).unwrap();
+        prune_snapshots(dir, 2).unwrap();
+        let names: Vec<_> = list_snapshots(dir)
+            .unwrap()
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(
+            names,
+            ["capture-000000000002000.txt", "capture-000000000010000.txt"]
+        );
+
+        assert_eq!(
+            snapshot_diff(dir).unwrap().unwrap(),
+            "--- capture-000000000002000.txt\n\
+             +++ capture-000000000010000.txt\n\
+             @@ -1,2 +1,3 @@\n \
+             $ cargo test\n\
+             -running\n\
+             +ok\n\
+             +done\n"
+        );
+
+        save_snapshot(dir, &lines(&["$ cargo test", "ok", "done"]), 11_000).unwrap();
+        assert_eq!(snapshot_diff(dir).unwrap(), None);
+    }
+
+    #[test]
+    fn snapshots_ignore_a_captures_link_planted_in_the_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let worktree = tmp.path().join("wt");
+        let target = tmp.path().join("target");
+        std::fs::create_dir_all(worktree.join(".workmux")).unwrap();
+        std::fs::create_dir(&target).unwrap();
+        std::fs::write(target.join("capture-1.txt"), "keep").unwrap();
+        std::os::unix::fs::symlink(&target, worktree.join(".workmux/captures")).unwrap();
+
+        let store = StateStore::with_path(tmp.path().join("state")).unwrap();
+        let dir = snapshot_dir(&store, &worktree).unwrap();
+        assert!(dir.starts_with(tmp.path().join("state")));
+        save_snapshot(&dir, &["$ ls".to_string()], 1_000).unwrap();
+        prune_snapshots(&dir, 0).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(target.join("capture-1.txt")).unwrap(),
+            "keep"
+        );
+        assert_eq!(std::fs::read_dir(&target).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn last_title_takes_the_final_osc_0_or_2_title() {
+        let output = "\x1b]0;claude: starting\x07\x1b[1;32mok\x1b[0m\n\
+                      \x1b[2K\x1b[1Gbuilding \x1b]8;;https://x.dev\x1b\\link\x1b]8;;\x1b\\\n\
+                      \x1b]2;claude: 3/5 tests\x1b\\\x1b]1;icon only\x07\x1b[Hdone\n\
+                      \x1b]2;unterminated";
+        assert_eq!(last_title(output).as_deref(), Some("claude: 3/5 tests"));
+        assert_eq!(
+            clean_capture(output, CaptureRange::All, AnsiMode::Strip),
+            vec!["ok", "building link", "done"]
+        );
+
+        assert_eq!(last_title("\x1b[31mred\x1b[0m\n"), None);
+        assert_eq!(last_title("\x1b]2;\x07").as_deref(), Some(""));
+    }
+
+    #[test]
+    fn capture_transcript_reads_the_agent_pane_title() {
+        let mux = MockMultiplexer::new()
+            .with_pane("%1", "\x1b]2;agent title\x07work\n")
+            .with_pane("%2", "\x1b]2;shell title\x07$\n");
+        let transcript =
+            capture_transcript(&mux, "%1", CaptureRange::All, AnsiMode::Strip, true, None).unwrap();
+        assert_eq!(transcript.title.as_deref(), Some("agent title"));
+    }
+
+    #[test]
+    fn keep_sgr_only_drops_cursor_and_erase_sequences() {
+        let output =
+            "\x1b[2J\x1b[H\x1b[1;32mok\x1b[0m\x1b[K\x1b[3A\x1b[?25l done\x1b[38;5;208m!\x1b[m";
+        assert_eq!(
+            keep_sgr_only(output),
+            "\x1b[1;32mok\x1b[0m done\x1b[38;5;208m!\x1b[m"
+        );
+    }
+
+    #[test]
+    fn keep_sgr_only_drops_osc_and_short_escapes() {
+        let output =
+            "\x1b]0;title\x07\x1b7\x1b(B\x1b[31mred\x1b8\x1b]8;;http://x\x1b\\link\x1b]8;;\x1b\\";
+        assert_eq!(keep_sgr_only(output), "\x1b[31mredlink");
+    }
+
+    #[test]
+    fn clean_capture_preserve_color_keeps_only_sgr() {
+        let output = "\x1b[32mhello\x1b[0m\x1b[K\n\x1b[2Kworld\n\n";
+        assert_eq!(
+            clean_capture(output, CaptureRange::All, AnsiMode::Color),
+            vec!["\x1b[32mhello\x1b[0m", "world"]
+        );
+    }
+
+    #[test]
+    fn clean_capture_limits_lines() {
+        let output = "a\nb\nc\nd\n";
+        assert_eq!(
+            clean_capture(output, CaptureRange::Lines(2), AnsiMode::Strip),
+            vec!["c", "d"]
+        );
+    }
+
+    #[test]
+    fn clean_capture_all_keeps_every_line() {
+        let output = "a\nb\nc\nd\n\n";
+        assert_eq!(
+            clean_capture(output, CaptureRange::All, AnsiMode::Strip),
+            vec!["a", "b", "c", "d"]
+        );
+    }
+
+    #[test]
+    fn lines_after_marker_uses_last_occurrence() {
+        let lines: Vec<String> = ["old", "== mark 1 ==", "a", "== mark 2 ==", "b", "c"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            lines_after_marker(&lines, "== mark"),
+            Some(vec!["b".to_string(), "c".to_string()])
+        );
+        assert_eq!(lines_after_marker(&lines, "c"), Some(vec![]));
+        assert_eq!(lines_after_marker(&lines, "missing"), None);
+    }
+
+    #[test]
+    fn pane_header_names_pane() {
+        assert_eq!(pane_header("%3"), "── pane %3 ──");
+    }
+
+    #[test]
+    fn run_summarizer_pipes_transcript() {
+        let transcript = vec!["line one".to_string(), "line two".to_string()];
+        let out = run_summarizer("wc -l", &transcript).unwrap();
+        assert_eq!(out.trim(), "2");
+    }
+
+    #[test]
+    fn run_summarizer_streams_large_transcripts() {
+        // Far more than a pipe buffer in each direction
+        let transcript = vec!["x".repeat(1023); 1024];
+        let out = run_summarizer("cat", &transcript).unwrap();
+        assert_eq!(out.len(), 1024 * 1024);
+    }
+
+    #[test]
+    fn run_summarizer_reports_failure() {
+        let err = run_summarizer("exit 3", &[]).unwrap_err();
+        assert!(err.to_string().contains("code 3"));
+    }
 }
@@ -1,11 +1,11 @@
 use crate::multiplexer::handle::mode_label;
-use crate::multiplexer::{MuxHandle, create_backend, detect_backend};
+use crate::multiplexer::{self, MuxHandle};
 use crate::{config, git, sandbox};
 use anyhow::{Context, Result, anyhow};
 
 pub fn run(name: Option<&str>) -> Result<()> {
     let config = config::Config::load(None)?;
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let prefix = config.window_prefix();
 
     // Resolve the handle first. When the user passes a branch name that differs
@@ -3,6 +3,7 @@
 use anyhow::{Context, Result, bail};
 use clap::{Args, Subcommand};
 use std::fs;
+use std::path::PathBuf;
 use std::process::Command;
 
 #[derive(Debug, Args)]
@@ -16,17 +17,34 @@ pub enum ConfigCommand {
     /// Open the global configuration file in your editor ($VISUAL, $EDITOR, or vi)
     Edit,
     /// Print the path to the global configuration file
-    Path,
+    Path {
+        /// List every config file that is loaded, lowest precedence first
+        #[arg(long)]
+        all: bool,
+    },
+    /// Check the global and project configuration for errors
+    Validate,
+    /// Print the effective configuration, after merging global and project
+    Dump {
+        /// List each value with the file it came from, or `default`
+        #[arg(long)]
+        origins: bool,
+    },
 }
 
 pub fn run(args: ConfigArgs) -> Result<()> {
     match args.command {
         ConfigCommand::Edit => run_edit(),
-        ConfigCommand::Path => run_path(),
+        ConfigCommand::Path { all } => run_path(all),
+        ConfigCommand::Validate => run_validate(),
+        ConfigCommand::Dump { origins } => run_dump(origins),
     }
 }
 
-fn run_edit() -> Result<()> {
+/// Create the global config file with commented-out defaults if it doesn't exist.
+///
+/// Returns the config path and whether it was created.
+pub(crate) fn ensure_global_config() -> Result<(PathBuf, bool)> {
     let config_path =
         crate::config::global_config_path().context("Could not determine home directory")?;
 
@@ -36,10 +54,18 @@ fn run_edit() -> Result<()> {
             .with_context(|| format!("Failed to create directory {}", parent.display()))?;
     }
 
-    // Create default config if it doesn't exist
-    if !config_path.exists() {
-        fs::write(&config_path, DEFAULT_GLOBAL_CONFIG)
-            .with_context(|| format!("Failed to create {}", config_path.display()))?;
+    if config_path.exists() {
+        return Ok((config_path, false));
+    }
+
+    fs::write(&config_path, DEFAULT_GLOBAL_CONFIG)
+        .with_context(|| format!("Failed to create {}", config_path.display()))?;
+    Ok((config_path, true))
+}
+
+fn run_edit() -> Result<()> {
+    let (config_path, created) = ensure_global_config()?;
+    if created {
         println!("Created {}", config_path.display());
     }
 
@@ -65,13 +91,75 @@ fn run_edit() -> Result<()> {
     Ok(())
 }
 
-fn run_path() -> Result<()> {
+fn run_path(all: bool) -> Result<()> {
     let config_path =
         crate::config::global_config_path().context("Could not determine home directory")?;
-    println!("{}", config_path.display());
+    if !all {
+        println!("{}", config_path.display());
+        return Ok(());
+    }
+
+    // Same discovery as Config::load: global first, then the nearest
+    // project config, which overrides it key by key.
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let mut layers = Vec::new();
+    if config_path.exists() {
+        layers.push((config_path, "global"));
+    }
+    if let Some(location) = crate::config::find_project_config(&cwd)? {
+        layers.push((location.config_path, "project"));
+    }
+
+    if layers.is_empty() {
+        println!("No config files found; using built-in defaults");
+    }
+    for (path, layer) in layers {
+        println!("{} ({})", path.display(), layer);
+    }
+    Ok(())
+}
+
+fn run_validate() -> Result<()> {
+    crate::config::Config::load(None)?;
+    println!("OK");
     Ok(())
 }
 
+fn run_dump(origins: bool) -> Result<()> {
+    let (config, values) = crate::config::Config::load_with_origins()?;
+    if !origins {
+        print!("{}", serde_yaml::to_string(&config)?);
+        return Ok(());
+    }
+    for line in origin_lines(&values)? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+/// Widest `key = value` the origins are lined up after; longer ones (big
+/// lists, mostly) aren't padded.
+const MAX_ALIGN_WIDTH: usize = 60;
+
+/// `key = value  (origin)` lines, values as single-line JSON.
+fn origin_lines(values: &[crate::config::ConfigValueOrigin]) -> Result<Vec<String>> {
+    let assignments = values
+        .iter()
+        .map(|v| Ok(format!("{} = {}", v.key, serde_json::to_string(&v.value)?)))
+        .collect::<Result<Vec<_>>>()?;
+    let width = assignments
+        .iter()
+        .map(|a| a.chars().count())
+        .filter(|&n| n <= MAX_ALIGN_WIDTH)
+        .max()
+        .unwrap_or(0);
+    Ok(assignments
+        .iter()
+        .zip(values)
+        .map(|(assignment, v)| format!("{assignment:width$}  ({})", v.origin))
+        .collect())
+}
+
 const DEFAULT_GLOBAL_CONFIG: &str = r#"# workmux global configuration
 # Settings here apply to all projects. Project-specific .workmux.yaml overrides these.
 # See: https://workmux.raine.dev/guide/configuration
@@ -12,7 +12,7 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use crate::config::Config;
 use crate::git::{self, GitStatus};
 use crate::github::PrSummary;
-use crate::multiplexer::{AgentPane, AgentStatus, Multiplexer};
+use crate::multiplexer::{AgentPane, AgentStatus, CaptureRange, Multiplexer};
 use crate::state::StateStore;
 
 use super::ui::theme::ThemePalette;
@@ -397,9 +397,11 @@ impl App {
         // Only fetch if selection changed
         if current_pane_id != self.preview_pane_id {
             self.preview_pane_id = current_pane_id.clone();
-            self.preview = current_pane_id
-                .as_ref()
-                .and_then(|pane_id| self.mux.capture_pane(pane_id, PREVIEW_LINES));
+            self.preview = current_pane_id.as_ref().and_then(|pane_id| {
+                self.mux
+                    .capture_pane(pane_id, CaptureRange::Lines(PREVIEW_LINES))
+                    .ok()
+            });
             // Reset scroll position when selection changes
             self.preview_scroll = None;
         }
@@ -410,10 +412,11 @@ impl App {
         if !self.mux.supports_preview() {
             return;
         }
-        self.preview = self
-            .preview_pane_id
-            .as_ref()
-            .and_then(|pane_id| self.mux.capture_pane(pane_id, PREVIEW_LINES));
+        self.preview = self.preview_pane_id.as_ref().and_then(|pane_id| {
+            self.mux
+                .capture_pane(pane_id, CaptureRange::Lines(PREVIEW_LINES))
+                .ok()
+        });
     }
 
     /// Parse pane_id to a number for proper ordering.
@@ -47,7 +47,7 @@ use std::time::Duration;
 
 use crate::git;
 use crate::github;
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 
 use self::actions::apply_action;
 use self::app::{App, ViewMode};
@@ -107,7 +107,7 @@ fn handle_mouse_event(app: &mut App, kind: MouseEventKind) {
 }
 
 pub fn run(cli_preview_size: Option<u8>, open_diff: bool) -> Result<()> {
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
 
     // Check if multiplexer is running
     if !mux.is_running().unwrap_or(false) {
@@ -0,0 +1,606 @@
+//! `workmux doctor`: diagnose common setup problems and optionally fix them.
+
+use anyhow::{Result, bail};
+use console::style;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::process::Command;
+
+use crate::agent_setup::{self, Agent, StatusCheck};
+use crate::config::{self, Config, SandboxBackend, SandboxRuntime};
+use crate::git;
+use crate::multiplexer::{self, BackendType};
+use crate::sandbox::lima::LimaInstance;
+use crate::state::StateStore;
+
+/// Outcome of one check, printed in the order the checks ran.
+enum Finding {
+    Pass(String),
+    /// Worth knowing about, but doesn't fail the run
+    Warn {
+        summary: String,
+        hint: Option<String>,
+    },
+    /// Not a problem, but `--fix` can still act on it
+    Info(Issue),
+    Fail(Issue),
+}
+
+/// A problem found by one of the checks.
+struct Issue {
+    summary: String,
+    /// How to resolve the issue by hand, for issues without an automatic fix
+    hint: Option<String>,
+    fix: Option<Fix>,
+}
+
+/// An automatic remediation for an issue.
+enum Fix {
+    InstallHooks(Agent),
+    PruneWorktrees,
+    RemoveStaleSandboxes(Vec<(String, u32)>),
+    CreateGlobalConfig,
+}
+
+impl Fix {
+    fn apply(&self) -> Result<String> {
+        match self {
+            Fix::InstallHooks(agent) => Ok(agent_setup::install(*agent, false)?),
+            Fix::PruneWorktrees => {
+                let output = Command::new("git").args(["worktree", "prune"]).output()?;
+                if !output.status.success() {
+                    bail!(
+                        "git worktree prune failed: {}",
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+                Ok("Pruned stale worktree metadata".to_string())
+            }
+            Fix::RemoveStaleSandboxes(records) => {
+                let store = StateStore::new()?;
+                for (handle, pid) in records {
+                    store.unregister_sandbox(handle, *pid);
+                }
+                Ok(format!("Removed {} stale sandbox record(s)", records.len()))
+            }
+            Fix::CreateGlobalConfig => {
+                let (path, _) = super::config::ensure_global_config()?;
+                Ok(format!("Created {}", path.display()))
+            }
+        }
+    }
+}
+
+pub fn run(fix: bool, yes: bool) -> Result<()> {
+    if fix && !yes && !io::stdin().is_terminal() {
+        bail!("workmux doctor --fix requires an interactive terminal (or pass --yes)");
+    }
+
+    let mut findings = Vec::new();
+    check_git_repo(&mut findings);
+    check_multiplexer(&mut findings);
+    check_config(&mut findings);
+    check_agent_hooks(&mut findings);
+    check_worktrees(&mut findings);
+    check_sandbox_state(&mut findings);
+
+    let mut stdout = io::stdout();
+    let unresolved = report(&findings, fix, yes, &mut stdout, confirm)?;
+    if unresolved > 0 {
+        bail!("{} problem(s) remaining", unresolved);
+    }
+    Ok(())
+}
+
+/// Print `findings` to `out`, applying fixes when `fix` is set and
+/// `confirm` agrees. Returns how many problems are left unresolved.
+fn report(
+    findings: &[Finding],
+    fix: bool,
+    yes: bool,
+    out: &mut impl Write,
+    mut confirm: impl FnMut(&str) -> Result<bool>,
+) -> Result<usize> {
+    let mut unresolved = 0;
+    for finding in findings {
+        let (issue, is_problem) = match finding {
+            Finding::Pass(msg) => {
+                writeln!(out, "{} {}", style("✓").green(), msg)?;
+                continue;
+            }
+            Finding::Warn { summary, hint } => {
+                writeln!(out, "{} {}", style("!").yellow(), summary)?;
+                if let Some(hint) = hint {
+                    writeln!(out, "  {}", style(hint).dim())?;
+                }
+                continue;
+            }
+            Finding::Info(issue) => {
+                writeln!(out, "{} {}", style("·").cyan(), issue.summary)?;
+                (issue, false)
+            }
+            Finding::Fail(issue) => {
+                writeln!(out, "{} {}", style("✗").red(), issue.summary)?;
+                (issue, true)
+            }
+        };
+
+        let resolved = match (&issue.fix, fix) {
+            (Some(remedy), true) => {
+                if !yes && !confirm("Fix?")? {
+                    false
+                } else {
+                    match remedy.apply() {
+                        Ok(msg) => {
+                            writeln!(out, "  {} {}", style("✓").green(), msg)?;
+                            true
+                        }
+                        Err(e) => {
+                            writeln!(out, "  {} {}", style("✗").red(), e)?;
+                            false
+                        }
+                    }
+                }
+            }
+            (Some(_), false) => {
+                writeln!(
+                    out,
+                    "  {}",
+                    style("fixable with `workmux doctor --fix`").dim()
+                )?;
+                false
+            }
+            (None, _) => {
+                if let Some(hint) = &issue.hint {
+                    writeln!(out, "  {}", style(hint).dim())?;
+                }
+                false
+            }
+        };
+        if is_problem && !resolved {
+            unresolved += 1;
+        }
+    }
+
+    if findings
+        .iter()
+        .all(|f| matches!(f, Finding::Pass(_) | Finding::Info(_)))
+    {
+        writeln!(out, "{}", style("No problems found.").green())?;
+    }
+    Ok(unresolved)
+}
+
+/// Whether we're inside a git repository. Most commands need one, but
+/// doctor can still check global setup from anywhere.
+fn check_git_repo(findings: &mut Vec<Finding>) {
+    findings.push(git_repo_finding(git::get_repo_root().ok().as_deref()));
+}
+
+fn git_repo_finding(root: Option<&Path>) -> Finding {
+    match root {
+        Some(root) => Finding::Pass(format!("Git repository: {}", root.display())),
+        None => Finding::Warn {
+            summary: "Not inside a git repository; repository checks skipped".to_string(),
+            hint: Some("Run `workmux doctor` from a repository to check it too".to_string()),
+        },
+    }
+}
+
+/// The multiplexer workmux would drive, and whether it can be reached.
+fn check_multiplexer(findings: &mut Vec<Finding>) {
+    let mux = multiplexer::current();
+    let name = mux.name();
+    let running = mux.is_running();
+    let version = match running {
+        Ok(true) => name
+            .parse::<BackendType>()
+            .ok()
+            .and_then(|backend| backend.version()),
+        _ => None,
+    };
+    findings.push(multiplexer_finding(name, running, version));
+}
+
+/// Classify the multiplexer `name` by whether it's running (an error means
+/// it couldn't even be run), showing `version` when it's known.
+fn multiplexer_finding(name: &str, running: Result<bool>, version: Option<String>) -> Finding {
+    match running {
+        Ok(true) => Finding::Pass(format!(
+            "Multiplexer: {} (running)",
+            version.as_deref().unwrap_or(name)
+        )),
+        Ok(false) => Finding::Warn {
+            summary: format!("Multiplexer {} is not running", name),
+            hint: Some(format!(
+                "Start {} (or set WORKMUX_BACKEND to the multiplexer you use) before creating worktrees",
+                name
+            )),
+        },
+        Err(e) => Finding::Fail(Issue {
+            summary: format!("Multiplexer {} could not be run: {:#}", name, e),
+            hint: Some(format!(
+                "Install {} or set WORKMUX_BACKEND to the multiplexer you use",
+                name
+            )),
+            fix: None,
+        }),
+    }
+}
+
+/// Global config presence, config validity, and sandbox backend availability.
+fn check_config(findings: &mut Vec<Finding>) {
+    if let Some(finding) = global_config_finding(config::global_config_path().as_deref()) {
+        findings.push(finding);
+    }
+
+    let config = match Config::load(None) {
+        Ok(config) => config,
+        Err(e) => {
+            findings.push(Finding::Fail(Issue {
+                summary: format!("Config failed to load: {:#}", e),
+                hint: Some("Fix the config error, then run `workmux doctor` again".to_string()),
+                fix: None,
+            }));
+            return;
+        }
+    };
+    findings.push(Finding::Pass("Config loaded".to_string()));
+
+    if !config.sandbox.is_enabled() {
+        return;
+    }
+
+    findings.push(match config.sandbox.backend() {
+        SandboxBackend::Lima => lima_finding(LimaInstance::version()),
+        SandboxBackend::Container => {
+            let runtime = match config.sandbox.runtime() {
+                SandboxRuntime::Podman => "podman",
+                SandboxRuntime::Docker => "docker",
+            };
+            container_finding(runtime, which::which(runtime).is_ok())
+        }
+    });
+}
+
+/// The global config file at `path`. It's optional, so a missing one is
+/// only offered as a fix.
+fn global_config_finding(path: Option<&Path>) -> Option<Finding> {
+    let path = path.filter(|path| !path.exists())?;
+    Some(Finding::Info(Issue {
+        summary: format!("No global config at {}; using defaults", path.display()),
+        hint: None,
+        fix: Some(Fix::CreateGlobalConfig),
+    }))
+}
+
+/// The Lima backend, given `limactl --version` (`None` when it's missing).
+fn lima_finding(version: Option<String>) -> Finding {
+    match version {
+        Some(version) => Finding::Pass(format!("Sandbox: {}", version)),
+        None => Finding::Fail(Issue {
+            summary: "Sandbox uses the Lima backend but limactl is not installed".to_string(),
+            hint: Some("Install Lima: https://lima-vm.io/docs/installation/".to_string()),
+            fix: None,
+        }),
+    }
+}
+
+/// The container backend, given whether `runtime` is on `PATH`.
+fn container_finding(runtime: &str, installed: bool) -> Finding {
+    if installed {
+        return Finding::Pass(format!("Sandbox: {} found", runtime));
+    }
+    Finding::Fail(Issue {
+        summary: format!(
+            "Sandbox uses the container backend but {} is not installed",
+            runtime
+        ),
+        hint: Some(format!(
+            "Install {} or set 'sandbox.backend: lima' in your config",
+            runtime
+        )),
+        fix: None,
+    })
+}
+
+/// Status tracking hooks for every detected agent.
+fn check_agent_hooks(findings: &mut Vec<Finding>) {
+    findings.extend(
+        agent_setup::check_all()
+            .into_iter()
+            .map(|check| hook_finding(check.agent, check.status)),
+    );
+}
+
+fn hook_finding(agent: Agent, status: StatusCheck) -> Finding {
+    let name = agent.name();
+    match status {
+        StatusCheck::Installed => {
+            Finding::Pass(format!("{} status tracking hooks installed", name))
+        }
+        StatusCheck::NotInstalled => Finding::Fail(Issue {
+            summary: format!("{} status tracking hooks are not installed", name),
+            hint: None,
+            fix: Some(Fix::InstallHooks(agent)),
+        }),
+        StatusCheck::Error(e) => Finding::Fail(Issue {
+            summary: format!("Could not check {} hooks: {}", name, e),
+            hint: Some("Fix the agent's settings file, then run `workmux setup`".to_string()),
+            fix: None,
+        }),
+    }
+}
+
+/// Worktree metadata pointing at directories that no longer exist.
+fn check_worktrees(findings: &mut Vec<Finding>) {
+    if !git::is_git_repo().unwrap_or(false) {
+        return;
+    }
+
+    let Ok(output) = Command::new("git")
+        .args(["worktree", "prune", "--dry-run", "--verbose"])
+        .output()
+    else {
+        return;
+    };
+
+    // git reports what it would prune on stderr
+    findings.extend(stale_worktree_finding(&String::from_utf8_lossy(
+        &output.stderr,
+    )));
+}
+
+/// A problem for each entry `git worktree prune --dry-run --verbose` would
+/// remove, going by its `report`.
+fn stale_worktree_finding(report: &str) -> Option<Finding> {
+    let stale = report.lines().filter(|l| !l.trim().is_empty()).count();
+    (stale > 0).then(|| {
+        Finding::Fail(Issue {
+            summary: format!("{} stale worktree entr(ies) in git metadata", stale),
+            hint: None,
+            fix: Some(Fix::PruneWorktrees),
+        })
+    })
+}
+
+/// Sandbox supervisor records left behind by supervisors that were killed.
+fn check_sandbox_state(findings: &mut Vec<Finding>) {
+    let Ok(store) = StateStore::new() else {
+        return;
+    };
+
+    let stale = store.stale_sandboxes();
+    if !stale.is_empty() {
+        findings.push(Finding::Fail(Issue {
+            summary: format!(
+                "{} stale sandbox RPC record(s) from exited supervisors",
+                stale.len()
+            ),
+            hint: None,
+            fix: Some(Fix::RemoveStaleSandboxes(stale)),
+        }));
+    }
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    let prompt = format!(
+        "  {} {}{}{} ",
+        question,
+        style("[").bold().cyan(),
+        style("Y/n").bold(),
+        style("]").bold().cyan(),
+    );
+
+    loop {
+        print!("{}", prompt);
+        io::stdout().flush()?;
+
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        let answer = input.trim().to_lowercase();
+
+        match answer.as_str() {
+            "" | "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("    {}", style("Please enter y or n").dim()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(finding: &Finding) -> (&str, &str) {
+        match finding {
+            Finding::Pass(msg) => ("pass", msg),
+            Finding::Warn { summary, .. } => ("warn", summary),
+            Finding::Info(issue) => ("info", &issue.summary),
+            Finding::Fail(issue) => ("fail", &issue.summary),
+        }
+    }
+
+    fn render(findings: &[Finding], fix: bool, answer: bool) -> (String, usize, usize) {
+        let mut out = Vec::new();
+        let mut asked = 0;
+        let unresolved = report(findings, fix, false, &mut out, |_| {
+            asked += 1;
+            Ok(answer)
+        })
+        .unwrap();
+        let text = console::strip_ansi_codes(&String::from_utf8(out).unwrap()).into_owned();
+        (text, unresolved, asked)
+    }
+
+    #[test]
+    fn multiplexer_is_classified_by_whether_it_runs() {
+        let running = multiplexer_finding("tmux", Ok(true), Some("tmux 3.4".to_string()));
+        assert_eq!(
+            summary(&running),
+            ("pass", "Multiplexer: tmux 3.4 (running)")
+        );
+        let unversioned = multiplexer_finding("kitty", Ok(true), None);
+        assert_eq!(
+            summary(&unversioned),
+            ("pass", "Multiplexer: kitty (running)")
+        );
+
+        let stopped = multiplexer_finding("tmux", Ok(false), None);
+        assert_eq!(
+            summary(&stopped),
+            ("warn", "Multiplexer tmux is not running")
+        );
+
+        let missing = multiplexer_finding("tmux", Err(anyhow::anyhow!("not found")), None);
+        assert_eq!(
+            summary(&missing),
+            ("fail", "Multiplexer tmux could not be run: not found")
+        );
+        assert!(matches!(
+            missing,
+            Finding::Fail(Issue {
+                fix: None,
+                hint: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn sandbox_backends_fail_without_their_tools() {
+        assert_eq!(
+            summary(&lima_finding(Some("limactl version 1.0".to_string()))),
+            ("pass", "Sandbox: limactl version 1.0")
+        );
+        assert_eq!(summary(&lima_finding(None)).0, "fail");
+        assert_eq!(
+            summary(&container_finding("podman", true)),
+            ("pass", "Sandbox: podman found")
+        );
+        let missing = container_finding("docker", false);
+        assert_eq!(
+            summary(&missing),
+            (
+                "fail",
+                "Sandbox uses the container backend but docker is not installed"
+            )
+        );
+    }
+
+    #[test]
+    fn missing_hooks_are_fixable_and_unreadable_ones_are_not() {
+        assert_eq!(
+            summary(&hook_finding(Agent::Claude, StatusCheck::Installed)).0,
+            "pass"
+        );
+        let missing = hook_finding(Agent::Claude, StatusCheck::NotInstalled);
+        assert!(matches!(
+            missing,
+            Finding::Fail(Issue {
+                fix: Some(Fix::InstallHooks(Agent::Claude)),
+                ..
+            })
+        ));
+        let broken = hook_finding(
+            Agent::Claude,
+            StatusCheck::Error(agent_setup::SetupError::NoHomeDir),
+        );
+        assert!(matches!(
+            broken,
+            Finding::Fail(Issue {
+                fix: None,
+                hint: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn stale_worktrees_are_counted_from_the_prune_report() {
+        assert!(stale_worktree_finding("").is_none());
+        assert!(stale_worktree_finding("\n  \n").is_none());
+        let report = "Removing worktrees/a: gitdir file points to non-existent location\n\
+                      Removing worktrees/b: gitdir file points to non-existent location\n";
+        let finding = stale_worktree_finding(report).unwrap();
+        assert_eq!(
+            summary(&finding),
+            ("fail", "2 stale worktree entr(ies) in git metadata")
+        );
+        assert!(matches!(
+            finding,
+            Finding::Fail(Issue {
+                fix: Some(Fix::PruneWorktrees),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn report_counts_unresolved_problems_but_not_warnings() {
+        let findings = [
+            git_repo_finding(Some(Path::new("/repo"))),
+            git_repo_finding(None),
+            stale_worktree_finding("Removing worktrees/a: gone\n").unwrap(),
+            lima_finding(None),
+        ];
+        let (text, unresolved, asked) = render(&findings, false, true);
+        assert_eq!(unresolved, 2);
+        assert_eq!(asked, 0);
+        assert_eq!(
+            text,
+            "✓ Git repository: /repo\n\
+             ! Not inside a git repository; repository checks skipped\n  \
+             Run `workmux doctor` from a repository to check it too\n\
+             ✗ 1 stale worktree entr(ies) in git metadata\n  \
+             fixable with `workmux doctor --fix`\n\
+             ✗ Sandbox uses the Lima backend but limactl is not installed\n  \
+             Install Lima: https://lima-vm.io/docs/installation/\n"
+        );
+
+        // Declining a fix leaves the problem, and only fixable ones ask
+        let (_, unresolved, asked) = render(&findings, true, false);
+        assert_eq!((unresolved, asked), (2, 1));
+    }
+
+    #[test]
+    fn missing_global_config_is_offered_but_not_a_problem() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("config.yaml");
+        let finding = global_config_finding(Some(&path)).unwrap();
+        assert_eq!(summary(&finding).0, "info");
+        assert!(matches!(
+            finding,
+            Finding::Info(Issue {
+                fix: Some(Fix::CreateGlobalConfig),
+                ..
+            })
+        ));
+
+        let findings = [finding];
+        let (text, unresolved, _) = render(&findings, false, true);
+        assert_eq!(unresolved, 0);
+        assert!(
+            text.contains("fixable with `workmux doctor --fix`"),
+            "{text}"
+        );
+        assert!(text.ends_with("No problems found.\n"), "{text}");
+        // Declining the fix doesn't fail the run either
+        let (_, unresolved, asked) = render(&findings, true, false);
+        assert_eq!((unresolved, asked), (0, 1));
+
+        std::fs::write(&path, "").unwrap();
+        assert!(global_config_finding(Some(&path)).is_none());
+        assert!(global_config_finding(None).is_none());
+    }
+
+    #[test]
+    fn report_says_so_when_everything_passes() {
+        let (text, unresolved, _) = render(&[git_repo_finding(Some(Path::new("/r")))], false, true);
+        assert_eq!(unresolved, 0);
+        assert!(text.ends_with("No problems found.\n"), "{text}");
+
+        let (text, _, _) = render(&[git_repo_finding(None)], false, true);
+        assert!(!text.contains("No problems found."), "{text}");
+    }
+}
@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 use crate::state::StateStore;
 
 /// Switch to the last visited agent.
@@ -11,7 +11,7 @@ use crate::state::StateStore;
 /// Updates last_pane_id to the current pane after successful switch,
 /// but only if the current pane is also an agent pane.
 pub fn run() -> Result<()> {
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let store = StateStore::new()?;
 
     // Load agents to verify panes are actually agent panes
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use tracing::debug;
 
-use crate::multiplexer::{AgentStatus, create_backend, detect_backend};
+use crate::multiplexer::{self, AgentStatus};
 use crate::state::StateStore;
 
 /// Switch to the agent that most recently completed its task.
@@ -10,7 +10,7 @@ use crate::state::StateStore;
 /// one with the most recent timestamp. Cycles through completed agents on
 /// repeated invocations.
 pub fn run() -> Result<()> {
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let store = StateStore::new()?;
 
     // Read agent state directly from disk without validating against tmux.
@@ -37,7 +37,7 @@ pub fn run() -> Result<()> {
     }
 
     // Sort by timestamp descending (most recent first)
-    done_agents.sort_by(|a, b| b.status_ts.cmp(&a.status_ts));
+    done_agents.sort_by_key(|a| std::cmp::Reverse(a.status_ts));
 
     // Get current pane to determine where we are in the cycle
     // Use active_pane_id() instead of current_pane_id() - env var is stale in run-shell
@@ -1,18 +1,163 @@
-use std::io::IsTerminal;
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::config;
-use crate::multiplexer::{AgentStatus, create_backend, detect_backend};
-use crate::workflow::types::AgentStatusSummary;
-use crate::{nerdfont, workflow};
-use anyhow::Result;
+use crate::multiplexer::AgentStatus;
+use crate::util::format_time_ago;
+use crate::workflow::types::{AgentStatusSummary, WorktreeInfo};
+use crate::{ListOptions, Workmux, git, nerdfont, workflow};
+use anyhow::{Result, bail};
+use clap::ValueEnum;
+use console::style;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType};
+use crossterm::{cursor, execute, queue};
 use pathdiff::diff_paths;
 use tabled::{
     Table, Tabled,
     settings::{Padding, Style, disable::Remove, object::Columns},
 };
 
+/// Which files `list --size` counts.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeMode {
+    /// Tracked files and untracked files git doesn't ignore
+    Unignored,
+    /// Everything in the worktree directory, build artifacts included
+    All,
+}
+
+/// A `list --format` template: literal text with `{placeholder}`s naming
+/// table columns. `{{` and `}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListFormat(Vec<FormatPart>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum FormatPart {
+    Text(String),
+    Field(Field),
+}
+
+/// A column `list --format` can print. Enrichment columns (`pr`, `upstream`,
+/// `age`, `size`) are `-` unless the flag that computes them is given.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Current,
+    Branch,
+    Path,
+    Pr,
+    Agent,
+    Tmux,
+    Unmerged,
+    Upstream,
+    Age,
+    Size,
+}
+
+impl Field {
+    const ALL: [(&'static str, Field); 10] = [
+        ("current", Field::Current),
+        ("branch", Field::Branch),
+        ("path", Field::Path),
+        ("pr", Field::Pr),
+        ("agent", Field::Agent),
+        ("tmux", Field::Tmux),
+        ("unmerged", Field::Unmerged),
+        ("upstream", Field::Upstream),
+        ("age", Field::Age),
+        ("size", Field::Size),
+    ];
+
+    fn from_name(name: &str) -> Option<Self> {
+        // `mux` reads better for non-tmux backends
+        if name == "mux" {
+            return Some(Field::Tmux);
+        }
+        Self::ALL.iter().find(|(n, _)| *n == name).map(|(_, f)| *f)
+    }
+}
+
+impl ListFormat {
+    /// Parse a template, rejecting unknown placeholders and unbalanced braces.
+    /// Used as a clap value parser.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(format!("unclosed placeholder '{{{}'", name)),
+                        }
+                    }
+                    let field = Field::from_name(&name).ok_or_else(|| {
+                        let known: Vec<_> = Field::ALL.iter().map(|(n, _)| *n).collect();
+                        format!(
+                            "unknown placeholder '{{{}}}' (expected one of: {})",
+                            name,
+                            known.join(", ")
+                        )
+                    })?;
+                    if !text.is_empty() {
+                        parts.push(FormatPart::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(FormatPart::Field(field));
+                }
+                '}' => return Err("unmatched '}' (use '}}' for a literal brace)".to_string()),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(FormatPart::Text(text));
+        }
+        Ok(Self(parts))
+    }
+
+    fn render(&self, row: &WorktreeRow) -> String {
+        self.0
+            .iter()
+            .map(|part| match part {
+                FormatPart::Text(text) => text.as_str(),
+                FormatPart::Field(field) => row.field(*field),
+            })
+            .collect()
+    }
+}
+
+/// One `list --porcelain` line: branch, path, tmux and unmerged, tab-separated,
+/// with `1`/`0` for booleans. New fields go at the end so existing parsers
+/// keep working.
+fn porcelain_line(branch: &str, path: &Path, has_mux_window: bool, has_unmerged: bool) -> String {
+    let flag = |b: bool| if b { "1" } else { "0" };
+    [
+        branch,
+        &path.display().to_string(),
+        flag(has_mux_window),
+        flag(has_unmerged),
+    ]
+    .join("\t")
+}
+
 #[derive(Tabled)]
 struct WorktreeRow {
+    #[tabled(rename = "")]
+    current: String,
     #[tabled(rename = "BRANCH")]
     branch: String,
     #[tabled(rename = "PR")]
@@ -23,11 +168,37 @@ struct WorktreeRow {
     mux_status: String,
     #[tabled(rename = "UNMERGED")]
     unmerged_status: String,
+    #[tabled(rename = "UPSTREAM")]
+    upstream: String,
+    #[tabled(rename = "AGE")]
+    age: String,
+    #[tabled(rename = "SIZE")]
+    size: String,
     #[tabled(rename = "PATH")]
     path_str: String,
+    /// Absolute path, for `--format`
+    #[tabled(skip)]
+    path: String,
+}
+
+impl WorktreeRow {
+    fn field(&self, field: Field) -> &str {
+        match field {
+            Field::Current => &self.current,
+            Field::Branch => &self.branch,
+            Field::Path => &self.path,
+            Field::Pr => &self.pr_status,
+            Field::Agent => &self.agent_status,
+            Field::Tmux => &self.mux_status,
+            Field::Unmerged => &self.unmerged_status,
+            Field::Upstream => &self.upstream,
+            Field::Age => &self.age,
+            Field::Size => &self.size,
+        }
+    }
 }
 
-fn format_pr_status(pr_info: Option<crate::github::PrSummary>) -> String {
+fn format_pr_status(pr_info: Option<crate::github::PrSummary>, use_color: bool) -> String {
     pr_info
         .map(|pr| {
             let icons = nerdfont::pr_icons();
@@ -39,7 +210,11 @@ fn format_pr_status(pr_info: Option<crate::github::PrSummary>) -> String {
                 "CLOSED" => (icons.closed, "\x1b[31m"),             // red
                 _ => (icons.open, "\x1b[32m"),
             };
-            format!("#{} {}{}\x1b[0m", pr.number, color, icon)
+            if use_color {
+                format!("#{} {}{}\x1b[0m", pr.number, color, icon)
+            } else {
+                format!("#{} {}", pr.number, icon)
+            }
         })
         .unwrap_or_else(|| "-".to_string())
 }
@@ -47,17 +222,9 @@ fn format_pr_status(pr_info: Option<crate::github::PrSummary>) -> String {
 /// Format a single agent status as either an icon (TTY) or text label (piped).
 fn format_status_label(status: AgentStatus, config: &config::Config, use_icons: bool) -> String {
     if use_icons {
-        match status {
-            AgentStatus::Working => config.status_icons.working().to_string(),
-            AgentStatus::Waiting => config.status_icons.waiting().to_string(),
-            AgentStatus::Done => config.status_icons.done().to_string(),
-        }
+        status.glyph(&config.status_icons).to_string()
     } else {
-        match status {
-            AgentStatus::Working => "working".to_string(),
-            AgentStatus::Waiting => "waiting".to_string(),
-            AgentStatus::Done => "done".to_string(),
-        }
+        status.to_string()
     }
 }
 
@@ -109,23 +276,252 @@ fn format_agent_status(
     }
 }
 
-pub fn run(show_pr: bool, filter: &[String]) -> Result<()> {
-    let config = config::Config::load(None)?;
-    let mux = create_backend(detect_backend());
-    let worktrees = workflow::list(&config, mux.as_ref(), show_pr, filter)?;
+/// Describe a branch's upstream: `ahead N, behind M`, `gone`, `detached`
+/// for a detached HEAD, or `-` without an upstream.
+fn format_upstream(branch: &str, tracking: Option<git::UpstreamTracking>) -> String {
+    if branch == "(detached)" {
+        return "detached".to_string();
+    }
+    match tracking {
+        Some(git::UpstreamTracking::Tracking { ahead, behind }) => {
+            format!("ahead {}, behind {}", ahead, behind)
+        }
+        Some(git::UpstreamTracking::Gone) => "gone".to_string(),
+        None => "-".to_string(),
+    }
+}
 
+/// Human-readable size in binary units, like `du -h`: `512B`, `4.0K`, `37M`.
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["K", "M", "G", "T", "P"];
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if size < 10.0 {
+        format!("{:.1}{}", size, UNITS[unit])
+    } else {
+        format!("{:.0}{}", size, UNITS[unit])
+    }
+}
+
+/// Whether `path` is the worktree `here` (the repo root of the cwd) points at.
+fn is_current(path: &Path, here: Option<&Path>) -> bool {
+    here.is_some_and(|here| {
+        let canonical = |p: &Path| p.canonicalize().unwrap_or_else(|_| p.to_path_buf());
+        canonical(path) == canonical(here)
+    })
+}
+
+/// Whether a worktree last active at `last_activity` has been idle for at
+/// least `stale`. Worktrees with no known activity time are never stale.
+fn is_stale(last_activity: Option<SystemTime>, stale: Duration, now: SystemTime) -> bool {
+    last_activity
+        .and_then(|time| now.duration_since(time).ok())
+        .is_some_and(|idle| idle >= stale)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    show_pr: bool,
+    filter: &[String],
+    base: Option<&str>,
+    stale: Option<Duration>,
+    show_upstream: bool,
+    size: Option<SizeMode>,
+    format: Option<&ListFormat>,
+    porcelain: bool,
+    watch: Option<Duration>,
+    page: Page,
+) -> Result<()> {
+    let workmux = Workmux::new()?;
+    let config = workmux.config();
+    let options = ListOptions {
+        pr: show_pr,
+        filter: filter.to_vec(),
+        base: base.map(str::to_string),
+    };
+    if let Some(interval) = watch {
+        let view = View {
+            show_pr,
+            stale,
+            show_upstream,
+            size,
+        };
+        return watch_list(&workmux, &options, &view, interval);
+    }
+    let mut worktrees = workmux.list_with(&options)?;
+
+    // With --format or --porcelain, output is for scripts: no notices, no styling
+    let for_scripts = format.is_some() || porcelain;
     if worktrees.is_empty() {
-        println!("No worktrees found");
+        if !for_scripts {
+            println!("No worktrees found");
+        }
+        return Ok(());
+    }
+
+    let activity = if let Some(stale) = stale {
+        let activity = workflow::last_activity(&worktrees);
+        let now = SystemTime::now();
+        worktrees.retain(|wt| is_stale(activity.get(&wt.path).copied(), stale, now));
+        if worktrees.is_empty() {
+            if !for_scripts {
+                println!("No stale worktrees found");
+            }
+            return Ok(());
+        }
+        activity
+    } else {
+        Default::default()
+    };
+
+    let total = worktrees.len();
+    if page.is_set() {
+        // Page in branch order so pages don't shift between runs; the sort
+        // is stable, so ties keep git's order
+        worktrees.sort_by(|a, b| a.branch.cmp(&b.branch));
+        page.apply(&mut worktrees);
+        if worktrees.is_empty() {
+            if !for_scripts {
+                println!(
+                    "No worktrees past offset {} ({} in total)",
+                    page.offset, total
+                );
+            }
+            return Ok(());
+        }
+    }
+
+    if porcelain {
+        for wt in &worktrees {
+            println!(
+                "{}",
+                porcelain_line(
+                    &wt.branch,
+                    &wt.path,
+                    wt.has_mux_window,
+                    wt.has_unmerged == Some(true)
+                )
+            );
+        }
         return Ok(());
     }
 
     // Use icons when outputting to a terminal, text labels when piped (for agents)
     let use_icons = std::io::stdout().is_terminal();
+    // Colors follow the same rule, but also respect NO_COLOR (https://no-color.org)
+    let use_color = use_icons && format.is_none() && color_enabled();
+    let display_data = rows(
+        worktrees,
+        config,
+        &activity,
+        show_upstream,
+        size,
+        use_icons,
+        use_color,
+    )?;
+
+    if let Some(format) = format {
+        for row in &display_data {
+            println!("{}", format.render(row));
+        }
+        return Ok(());
+    }
+
+    let view = View {
+        show_pr,
+        stale,
+        show_upstream,
+        size,
+    };
+    let shown = display_data.len();
+    println!("{}", view.table(display_data));
+    if page.is_set() && use_icons {
+        let footer = page.footer(shown, total);
+        println!("{}", style(footer).dim().force_styling(use_color));
+    }
+
+    Ok(())
+}
+
+/// Which slice of the matching worktrees `--offset` and `--limit` select.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Page {
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+impl Page {
+    fn is_set(&self) -> bool {
+        self.offset > 0 || self.limit.is_some()
+    }
+
+    /// Keep only this page of `items`. An offset past the end leaves
+    /// nothing rather than failing.
+    fn apply<T>(&self, items: &mut Vec<T>) {
+        let start = self.offset.min(items.len());
+        let end = self
+            .limit
+            .map_or(items.len(), |limit| start.saturating_add(limit))
+            .min(items.len());
+        items.truncate(end);
+        items.drain(..start);
+    }
+
+    /// `showing 21–40 of 137`, 1-based and inclusive, for `shown` rows.
+    fn footer(&self, shown: usize, total: usize) -> String {
+        format!(
+            "showing {}–{} of {}",
+            self.offset + 1,
+            self.offset + shown,
+            total
+        )
+    }
+}
+
+fn color_enabled() -> bool {
+    std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// Build a table row for each worktree, computing the `--upstream` and
+/// `--size` columns when asked for.
+fn rows(
+    worktrees: Vec<WorktreeInfo>,
+    config: &config::Config,
+    activity: &HashMap<PathBuf, SystemTime>,
+    show_upstream: bool,
+    size: Option<SizeMode>,
+    use_icons: bool,
+    use_color: bool,
+) -> Result<Vec<WorktreeRow>> {
+    let upstreams = if show_upstream {
+        workflow::upstream_status(&worktrees)
+    } else {
+        Default::default()
+    };
+
+    let sizes = match size {
+        Some(mode) => workflow::disk_usage(&worktrees, mode == SizeMode::All),
+        None => Default::default(),
+    };
+
     let current_dir = std::env::current_dir()?;
+    let here = git::get_repo_root().ok();
 
-    let display_data: Vec<WorktreeRow> = worktrees
+    Ok(worktrees
         .into_iter()
         .map(|wt| {
+            let current = if is_current(&wt.path, here.as_deref()) {
+                style("*").bold().force_styling(use_color).to_string()
+            } else {
+                String::new()
+            };
             let path_str = diff_paths(&wt.path, &current_dir)
                 .map(|p| {
                     let s = p.display().to_string();
@@ -137,36 +533,446 @@ pub fn run(show_pr: bool, filter: &[String]) -> Result<()> {
                 })
                 .unwrap_or_else(|| wt.path.display().to_string());
 
+            let upstream = format_upstream(&wt.branch, upstreams.get(&wt.path).copied());
+
+            // Table widths are computed with ANSI codes stripped, so styling
+            // doesn't affect alignment
             WorktreeRow {
+                current,
                 branch: wt.branch,
-                pr_status: format_pr_status(wt.pr_info),
-                agent_status: format_agent_status(wt.agent_status.as_ref(), &config, use_icons),
+                pr_status: format_pr_status(wt.pr_info, use_color),
+                agent_status: format_agent_status(wt.agent_status.as_ref(), config, use_icons),
                 mux_status: if wt.has_mux_window {
-                    "✓".to_string()
+                    style("✓").green().force_styling(use_color).to_string()
                 } else {
                     "-".to_string()
                 },
-                unmerged_status: if wt.has_unmerged {
-                    "●".to_string()
+                unmerged_status: if wt.has_unmerged == Some(true) {
+                    style("●").yellow().force_styling(use_color).to_string()
                 } else {
                     "-".to_string()
                 },
-                path_str,
+                upstream,
+                age: activity
+                    .get(&wt.path)
+                    .map(|time| format_time_ago(*time))
+                    .unwrap_or_else(|| "-".to_string()),
+                size: sizes
+                    .get(&wt.path)
+                    .map(|bytes| format_size(*bytes))
+                    .unwrap_or_else(|| "-".to_string()),
+                path_str: style(path_str).dim().force_styling(use_color).to_string(),
+                path: wt.path.display().to_string(),
             }
         })
-        .collect();
+        .collect())
+}
+
+/// The flags that decide which worktrees and optional columns the table shows.
+struct View {
+    show_pr: bool,
+    stale: Option<Duration>,
+    show_upstream: bool,
+    size: Option<SizeMode>,
+}
+
+impl View {
+    /// Render `rows` as the `list` table: a header line, then one line per row.
+    fn table(&self, rows: Vec<WorktreeRow>) -> String {
+        let any_current = rows.iter().any(|row| !row.current.is_empty());
+        let mut table = Table::new(rows);
+        table
+            .with(Style::blank())
+            .modify(Columns::new(0..10), Padding::new(0, 1, 0, 0));
+
+        // Optional columns are removed right to left so earlier indices don't shift
+        // Hide SIZE column unless --size was used (column 8)
+        if self.size.is_none() {
+            table.with(Remove::column(Columns::new(8..9)));
+        }
 
-    let mut table = Table::new(display_data);
-    table
-        .with(Style::blank())
-        .modify(Columns::new(0..6), Padding::new(0, 1, 0, 0));
+        // Hide AGE column unless --stale was used (column 7)
+        if self.stale.is_none() {
+            table.with(Remove::column(Columns::new(7..8)));
+        }
 
-    // Hide PR column if --pr flag not used (column 1)
-    if !show_pr {
-        table.with(Remove::column(Columns::new(1..2)));
+        // Hide UPSTREAM column unless --upstream was used (column 6)
+        if !self.show_upstream {
+            table.with(Remove::column(Columns::new(6..7)));
+        }
+
+        // Hide PR column if --pr flag not used (column 2)
+        if !self.show_pr {
+            table.with(Remove::column(Columns::new(2..3)));
+        }
+
+        // Hide the current-worktree marker column when the cwd is outside every
+        // listed worktree (column 0)
+        if !any_current {
+            table.with(Remove::column(Columns::new(0..1)));
+        }
+
+        table.to_string()
     }
 
-    println!("{table}");
+    /// The current `--watch` frame.
+    fn frame(&self, workmux: &Workmux, options: &ListOptions, use_color: bool) -> Result<Frame> {
+        let mut worktrees = workmux.list_with(options)?;
+        if worktrees.is_empty() {
+            return Ok(Frame::message("No worktrees found"));
+        }
 
-    Ok(())
+        let activity = if let Some(stale) = self.stale {
+            let activity = workflow::last_activity(&worktrees);
+            let now = SystemTime::now();
+            worktrees.retain(|wt| is_stale(activity.get(&wt.path).copied(), stale, now));
+            if worktrees.is_empty() {
+                return Ok(Frame::message("No stale worktrees found"));
+            }
+            activity
+        } else {
+            Default::default()
+        };
+
+        let rows = rows(
+            worktrees,
+            workmux.config(),
+            &activity,
+            self.show_upstream,
+            self.size,
+            true,
+            use_color,
+        )?;
+        let statuses = rows
+            .iter()
+            .map(|row| (row.path.clone(), row_status(row)))
+            .collect();
+        let lines = self.table(rows).lines().map(str::to_string).collect();
+        Ok(Frame { lines, statuses })
+    }
+}
+
+/// What `--watch` draws on one tick.
+struct Frame {
+    lines: Vec<String>,
+    /// Each row's path and [`row_status`], in table order
+    statuses: Vec<(String, String)>,
+}
+
+impl Frame {
+    fn message(text: &str) -> Self {
+        Self {
+            lines: vec![text.to_string()],
+            statuses: Vec::new(),
+        }
+    }
+}
+
+/// What `--watch` compares between frames: the PR, agent, mux and unmerged
+/// columns. Age and size drift on their own, so they're left out.
+fn row_status(row: &WorktreeRow) -> String {
+    [
+        &row.pr_status,
+        &row.agent_status,
+        &row.mux_status,
+        &row.unmerged_status,
+    ]
+    .map(|s| s.as_str())
+    .join("\t")
+}
+
+/// For each row, whether its status differs from the previous frame,
+/// including worktrees that weren't listed before. Nothing counts as changed
+/// on the first frame.
+fn changed_rows(
+    previous: Option<&HashMap<String, String>>,
+    current: &[(String, String)],
+) -> Vec<bool> {
+    current
+        .iter()
+        .map(|(path, status)| previous.is_some_and(|p| p.get(path) != Some(status)))
+        .collect()
+}
+
+/// Show `line` in reverse video, re-applied after each reset in the cells'
+/// own styling so the whole row stays highlighted.
+fn highlight(line: &str) -> String {
+    const REVERSE: &str = "\x1b[7m";
+    const RESET: &str = "\x1b[0m";
+    format!(
+        "{REVERSE}{}{RESET}",
+        line.replace(RESET, &format!("{RESET}{REVERSE}"))
+    )
+}
+
+/// Whether `key` ends `--watch`: `q`, Esc or Ctrl-C.
+fn is_quit(key: &KeyEvent) -> bool {
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => true,
+        KeyCode::Char('c') => key.modifiers.contains(KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+/// Keeps the terminal in raw mode with the cursor hidden while watching;
+/// restored on drop.
+struct WatchTerminal;
+
+impl WatchTerminal {
+    fn enter() -> Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), cursor::Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for WatchTerminal {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), cursor::Show);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// `list --watch`: redraw the table in place every `interval`, highlighting
+/// rows whose status changed since the previous frame, until `q` or Ctrl-C.
+fn watch_list(
+    workmux: &Workmux,
+    options: &ListOptions,
+    view: &View,
+    interval: Duration,
+) -> Result<()> {
+    if !io::stdout().is_terminal() {
+        bail!("--watch redraws the table in place and needs a terminal; drop it to print once");
+    }
+    if interval.is_zero() {
+        bail!("--watch interval must be greater than zero");
+    }
+
+    let use_color = color_enabled();
+    let heading = style(format!(
+        "Every {}, press q to quit",
+        crate::util::format_elapsed_duration(interval)
+    ))
+    .dim()
+    .force_styling(use_color)
+    .to_string();
+
+    let _terminal = WatchTerminal::enter()?;
+    let mut stdout = io::stdout();
+    let mut previous: Option<HashMap<String, String>> = None;
+    let mut drawn: u16 = 0;
+    loop {
+        let Frame { lines, statuses } = view.frame(workmux, options, use_color)?;
+        let changed = changed_rows(previous.as_ref(), &statuses);
+
+        // Overwrite the previous frame line by line instead of clearing the
+        // screen first, so nothing flickers
+        queue!(stdout, cursor::MoveToColumn(0))?;
+        if drawn > 0 {
+            queue!(stdout, cursor::MoveUp(drawn))?;
+        }
+        let width = terminal::size().map_or(80, |(cols, _)| cols.max(1)) as usize;
+        let mut frame = vec![heading.clone(), String::new()];
+        // The table's first line is its header; rows follow in order
+        frame.extend(lines.into_iter().enumerate().map(|(i, line)| {
+            let is_changed = i > 0 && changed.get(i - 1).copied().unwrap_or(false);
+            if use_color && is_changed {
+                highlight(&line)
+            } else {
+                line
+            }
+        }));
+        drawn = 0;
+        for line in &frame {
+            queue!(
+                stdout,
+                Print(line),
+                terminal::Clear(ClearType::UntilNewLine),
+                Print("\r\n")
+            )?;
+            let cells = console::measure_text_width(line);
+            drawn = drawn.saturating_add(cells.div_ceil(width).max(1) as u16);
+        }
+        queue!(stdout, terminal::Clear(ClearType::FromCursorDown))?;
+        stdout.flush()?;
+        previous = Some(statuses.into_iter().collect());
+
+        let next = Instant::now() + interval;
+        while let Some(left) = next.checked_duration_since(Instant::now()) {
+            if event::poll(left)?
+                && let Event::Key(key) = event::read()?
+                && key.kind == KeyEventKind::Press
+                && is_quit(&key)
+            {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_stale_compares_idle_time_against_threshold() {
+        let now = SystemTime::now();
+        let week = Duration::from_secs(7 * 86400);
+        let days_ago = |d: u64| Some(now - Duration::from_secs(d * 86400));
+
+        assert!(is_stale(days_ago(10), week, now));
+        assert!(is_stale(days_ago(7), week, now));
+        assert!(!is_stale(days_ago(2), week, now));
+        // Unknown or future activity never counts as stale
+        assert!(!is_stale(None, week, now));
+        assert!(!is_stale(Some(now + week), week, now));
+    }
+
+    #[test]
+    fn is_current_matches_the_cwd_worktree_only() {
+        let tmp = tempfile::tempdir().unwrap();
+        let main = tmp.path().join("main");
+        let feature = tmp.path().join("feature");
+        std::fs::create_dir_all(&main).unwrap();
+        std::fs::create_dir_all(&feature).unwrap();
+
+        // Paths are compared after resolving `..` and symlinks
+        let here = feature.join("..").join("feature");
+        assert!(is_current(&feature, Some(&here)));
+        assert!(!is_current(&main, Some(&here)));
+        assert!(!is_current(&main, None));
+    }
+
+    fn row() -> WorktreeRow {
+        WorktreeRow {
+            current: String::new(),
+            branch: "feature".to_string(),
+            pr_status: "-".to_string(),
+            agent_status: "working".to_string(),
+            mux_status: "✓".to_string(),
+            unmerged_status: "●".to_string(),
+            upstream: "-".to_string(),
+            age: "-".to_string(),
+            size: "-".to_string(),
+            path_str: "(here)".to_string(),
+            path: "/src/project__worktrees/feature".to_string(),
+        }
+    }
+
+    #[test]
+    fn list_format_renders_placeholders_and_escaped_braces() {
+        let format =
+            ListFormat::parse("{branch}\t{path} [{mux}{unmerged}] {{agent}}: {agent}").unwrap();
+        assert_eq!(
+            format.render(&row()),
+            "feature\t/src/project__worktrees/feature [✓●] {agent}: working"
+        );
+        assert_eq!(ListFormat::parse("").unwrap().render(&row()), "");
+    }
+
+    #[test]
+    fn porcelain_field_order_is_fixed() {
+        // branch, path, tmux, unmerged: add new fields at the end, never
+        // reorder or remove these
+        assert_eq!(
+            porcelain_line("feature", Path::new("/src/feature"), true, false),
+            "feature\t/src/feature\t1\t0"
+        );
+        assert_eq!(
+            porcelain_line("main", Path::new("/src/main"), false, true),
+            "main\t/src/main\t0\t1"
+        );
+    }
+
+    #[test]
+    fn list_format_rejects_unknown_placeholders_and_stray_braces() {
+        let err = ListFormat::parse("{branch} {owner}").unwrap_err();
+        assert!(err.starts_with("unknown placeholder '{owner}'"), "{err}");
+        assert!(err.contains("branch, path"));
+        assert!(ListFormat::parse("{branch").is_err());
+        assert!(ListFormat::parse("branch}").is_err());
+    }
+
+    #[test]
+    fn changed_rows_compares_status_against_previous_frame() {
+        let frame = |rows: &[(&str, &str)]| -> Vec<(String, String)> {
+            rows.iter()
+                .map(|(path, status)| (path.to_string(), status.to_string()))
+                .collect()
+        };
+        let first = frame(&[("/a", "working"), ("/b", "-")]);
+        assert_eq!(changed_rows(None, &first), vec![false, false]);
+
+        let previous = first.into_iter().collect();
+        let next = frame(&[("/a", "done"), ("/b", "-"), ("/c", "-")]);
+        assert_eq!(
+            changed_rows(Some(&previous), &next),
+            vec![true, false, true]
+        );
+    }
+
+    #[test]
+    fn highlight_survives_cell_resets() {
+        assert_eq!(
+            highlight("a \x1b[32m✓\x1b[0m b"),
+            "\x1b[7ma \x1b[32m✓\x1b[0m\x1b[7m b\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn watch_quits_on_q_esc_and_ctrl_c() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        assert!(is_quit(&key(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert!(is_quit(&key(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(is_quit(&key(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(!is_quit(&key(KeyCode::Char('c'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn page_slices_and_tolerates_offsets_past_the_end() {
+        let page = |offset, limit| {
+            let mut items: Vec<u32> = (1..=5).collect();
+            Page { offset, limit }.apply(&mut items);
+            items
+        };
+        assert_eq!(page(0, Some(2)), [1, 2]);
+        assert_eq!(page(2, Some(2)), [3, 4]);
+        assert_eq!(page(4, Some(2)), [5]);
+        assert_eq!(page(3, None), [4, 5]);
+        assert_eq!(page(5, Some(2)), Vec::<u32>::new());
+        assert_eq!(page(100, Some(usize::MAX)), Vec::<u32>::new());
+        assert_eq!(page(1, Some(usize::MAX)), [2, 3, 4, 5]);
+
+        let footer = Page {
+            offset: 20,
+            limit: Some(20),
+        }
+        .footer(20, 137);
+        assert_eq!(footer, "showing 21–40 of 137");
+    }
+
+    #[test]
+    fn format_size_uses_binary_units() {
+        assert_eq!(format_size(0), "0B");
+        assert_eq!(format_size(1023), "1023B");
+        assert_eq!(format_size(4096), "4.0K");
+        assert_eq!(format_size(1536 * 1024), "1.5M");
+        assert_eq!(format_size(37 * 1024 * 1024), "37M");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024 * 1024), "3.0T");
+    }
+
+    #[test]
+    fn format_upstream_covers_each_state() {
+        let tracking = Some(git::UpstreamTracking::Tracking {
+            ahead: 3,
+            behind: 0,
+        });
+        assert_eq!(format_upstream("feature", tracking), "ahead 3, behind 0");
+        assert_eq!(
+            format_upstream("feature", Some(git::UpstreamTracking::Gone)),
+            "gone"
+        );
+        assert_eq!(format_upstream("feature", None), "-");
+        assert_eq!(format_upstream("(detached)", None), "detached");
+    }
 }
@@ -1,5 +1,5 @@
 use crate::config::MergeStrategy;
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 use crate::workflow::WorkflowContext;
 use crate::{config, workflow};
 use anyhow::{Context, Result};
@@ -50,7 +50,7 @@ pub fn run(
     // Note: Must be done BEFORE creating WorkflowContext (which may change CWD)
     let name_to_merge = super::resolve_name(name)?;
 
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let context = WorkflowContext::new(config, mux, None)?;
 
     let skip_hooks = no_verify || no_hooks;
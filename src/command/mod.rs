@@ -1,11 +1,13 @@
 pub mod add;
 pub mod args;
+pub mod attach;
 pub mod capture;
 pub mod changelog;
 pub mod close;
 pub mod config;
 pub mod dashboard;
 pub mod docs;
+pub mod doctor;
 pub mod exec;
 pub mod host_exec;
 pub mod last_agent;
@@ -13,8 +15,11 @@ pub mod last_done;
 pub mod list;
 pub mod merge;
 pub mod open;
+pub mod open_path;
 pub mod path;
+pub mod prune;
 pub mod remove;
+pub mod rename;
 pub mod run;
 pub mod sandbox;
 pub mod sandbox_run;
@@ -23,6 +28,8 @@ pub mod set_base;
 pub mod set_window_status;
 pub mod setup;
 pub mod status;
+pub mod version;
+pub mod vm_exec;
 pub mod wait;
 
 use anyhow::{Context, Result, anyhow};
@@ -1,6 +1,6 @@
 use crate::command::args::PromptArgs;
 use crate::config::MuxMode;
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 use crate::workflow::prompt_loader::{PromptLoadArgs, load_prompt};
 use crate::workflow::{SetupOptions, WorkflowContext};
 use crate::{config, git, workflow};
@@ -23,7 +23,7 @@ pub fn run(
     };
 
     let (config, config_location) = config::Config::load_with_location(None)?;
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let context = WorkflowContext::new(config, mux, config_location)?;
 
     // Determine the target mode from stored metadata
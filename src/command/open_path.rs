@@ -0,0 +1,75 @@
+//! Ask the host to open a worktree file in its editor via RPC.
+//!
+//! Used inside a sandbox guest, where the host editor isn't reachable.
+
+use anyhow::{Context, Result, anyhow, bail};
+use std::path::{Component, Path, PathBuf};
+
+use crate::git;
+use crate::sandbox::rpc::{RpcClient, RpcRequest, RpcResponse};
+
+pub fn run(path: &Path) -> Result<()> {
+    if !crate::sandbox::guest::is_sandbox_guest() {
+        bail!("open-path only works inside a sandbox guest (WM_SANDBOX_GUEST=1)");
+    }
+
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let root = git::get_repo_root()?;
+    let relative = relative_to_root(&cwd.join(path), &root)
+        .ok_or_else(|| anyhow!("{} is outside the worktree", path.display()))?;
+
+    let mut client = RpcClient::from_env()?;
+    match client.call(&RpcRequest::OpenPath {
+        path: relative.to_string_lossy().into_owned(),
+    })? {
+        RpcResponse::Ok => Ok(()),
+        RpcResponse::Error { message } => bail!("{}", message),
+        other => bail!("Unexpected RPC response: {:?}", other),
+    }
+}
+
+/// Lexically normalize `path` (resolving `.` and `..`) and make it relative
+/// to `root`. Returns None if it falls outside `root`.
+fn relative_to_root(path: &Path, root: &Path) -> Option<PathBuf> {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return None;
+                }
+            }
+            Component::CurDir => {}
+            other => normalized.push(other),
+        }
+    }
+    normalized.strip_prefix(root).ok().map(Path::to_path_buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_to_root_resolves_parent_dirs() {
+        let root = Path::new("/work/repo");
+        assert_eq!(
+            relative_to_root(Path::new("/work/repo/src/../README.md"), root),
+            Some(PathBuf::from("README.md"))
+        );
+        assert_eq!(
+            relative_to_root(Path::new("/work/repo/./src/main.rs"), root),
+            Some(PathBuf::from("src/main.rs"))
+        );
+    }
+
+    #[test]
+    fn relative_to_root_rejects_escapes() {
+        let root = Path::new("/work/repo");
+        assert_eq!(
+            relative_to_root(Path::new("/work/repo/../other"), root),
+            None
+        );
+        assert_eq!(relative_to_root(Path::new("/etc/passwd"), root), None);
+    }
+}
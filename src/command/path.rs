@@ -1,14 +1,42 @@
-use crate::git;
-use anyhow::{Context, Result};
-
-pub fn run(name: &str) -> Result<()> {
-    // Smart resolution: try handle first, then branch name
-    let (path, _branch) = git::find_worktree(name).with_context(|| {
-        format!(
-            "No worktree found with name '{}'. Use 'workmux list' to see available worktrees.",
-            name
-        )
-    })?;
-    println!("{}", path.display());
+use crate::{facade, shell, workflow};
+use anyhow::Result;
+use std::path::Path;
+
+pub fn run(name: Option<&str>, cd: bool) -> Result<()> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => workflow::resolve_current_worktree()?,
+    };
+    // Smart resolution: try handle first, then branch name. Unlike
+    // `Workmux::path`, this needs no config, so a broken one can't break it
+    let path = facade::worktree_path(&name)?;
+    if cd {
+        println!("{}", cd_command(&path));
+    } else {
+        println!("{}", path.display());
+    }
     Ok(())
 }
+
+/// A `cd` command for `eval`, with the path quoted so spaces and quotes in it
+/// survive.
+fn cd_command(path: &Path) -> String {
+    format!("cd -- {}", shell::shell_quote(&path.to_string_lossy()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cd_command_quotes_the_path() {
+        assert_eq!(
+            cd_command(Path::new("/src/project__worktrees/feature")),
+            "cd -- /src/project__worktrees/feature"
+        );
+        assert_eq!(
+            cd_command(Path::new("/my projects/it's here")),
+            "cd -- '/my projects/it'\\''s here'"
+        );
+    }
+}
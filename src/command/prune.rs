@@ -0,0 +1,282 @@
+//! `workmux prune`: clean up multiplexer targets without a worktree and
+//! idle worktrees without a multiplexer target.
+
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use crate::multiplexer::{self, Multiplexer};
+use crate::state::StateStore;
+use crate::workflow::{self, WorkflowContext, WorktreeInfo};
+use crate::{config, git};
+
+pub fn run(worktrees: bool, yes: bool) -> Result<()> {
+    let config = config::Config::load(None)?;
+    let mux = multiplexer::current();
+    let infos = workflow::list(&config, mux.as_ref(), false, &[], None)?;
+    let main_root = git::get_main_worktree_root().ok();
+
+    let handles: HashSet<String> = infos
+        .iter()
+        .filter_map(|wt| wt.path.file_name().and_then(|n| n.to_str()))
+        .map(String::from)
+        .collect();
+
+    let prefix = config.window_prefix();
+    // Targets are listed server-wide, so only those shown to have worked
+    // in this repo's worktree directory count as its orphans
+    let worktrees_dir = main_root
+        .as_deref()
+        .and_then(|root| workflow::worktrees_dir(&config, root).ok());
+    let (orphan_sessions, orphan_windows) = match worktrees_dir {
+        Some(dir) if mux.is_running().unwrap_or(false) => {
+            let (session_cwds, window_cwds) = target_cwds(mux.as_ref())?;
+            let orphans = |live, cwds| {
+                orphaned_targets(prefix, live, &handles, &Ownership { dir: &dir, cwds })
+            };
+            (
+                orphans(&mux.get_all_session_names()?, &session_cwds),
+                orphans(&mux.get_all_window_names()?, &window_cwds),
+            )
+        }
+        _ => (Vec::new(), Vec::new()),
+    };
+
+    // Worktrees with neither a live target nor unmerged work are safe to
+    // drop. One whose merge status is unknown might hold unmerged work.
+    let candidates: Vec<&WorktreeInfo> = infos
+        .iter()
+        .filter(|wt| Some(&wt.path) != main_root.as_ref())
+        .filter(|wt| !wt.has_mux_window && wt.branch != "(detached)")
+        .collect();
+    let idle = handles_where(&candidates, |wt| wt.has_unmerged == Some(false));
+    let unknown = handles_where(&candidates, |wt| wt.has_unmerged.is_none());
+
+    for handle in &unknown {
+        println!(
+            "Skipping worktree '{}': couldn't tell whether it's merged",
+            handle
+        );
+    }
+
+    if orphan_sessions.is_empty() && orphan_windows.is_empty() && idle.is_empty() {
+        println!("Nothing to prune");
+        return Ok(());
+    }
+
+    let verb = if yes { "Killing" } else { "Would kill" };
+    for name in &orphan_sessions {
+        println!("{} session '{}' (no worktree)", verb, name);
+        if yes {
+            mux.kill_session(name)
+                .with_context(|| format!("Failed to kill session '{}'", name))?;
+        }
+    }
+    for name in &orphan_windows {
+        println!("{} window '{}' (no worktree)", verb, name);
+        if yes {
+            mux.kill_window(name)
+                .with_context(|| format!("Failed to kill window '{}'", name))?;
+        }
+    }
+
+    if !idle.is_empty() {
+        let verb = match (worktrees, yes) {
+            (true, true) => "Removing",
+            (true, false) => "Would remove",
+            (false, _) => "Idle",
+        };
+        for handle in &idle {
+            println!("{} worktree '{}' (no session, fully merged)", verb, handle);
+        }
+        if worktrees && yes {
+            remove_idle(&idle)?;
+        } else if !worktrees {
+            println!("Pass --worktrees to remove idle worktrees");
+        }
+    }
+
+    if !yes {
+        println!("Dry run: pass --yes to apply");
+    }
+
+    Ok(())
+}
+
+/// Remove idle worktrees, then drop git metadata for directories that are
+/// already gone. Worktrees with uncommitted changes are skipped, not forced.
+fn remove_idle(handles: &[String]) -> Result<()> {
+    let config = config::Config::load(None)?;
+    let context = WorkflowContext::new(config, multiplexer::current(), None)?;
+
+    for handle in handles {
+        match workflow::remove(handle, false, false, &context) {
+            Ok(result) => println!(
+                "✓ Removed worktree '{}' and branch '{}'",
+                handle, result.branch_removed
+            ),
+            Err(e) => println!("✗ Skipped '{}': {:#}", handle, e),
+        }
+    }
+
+    git::prune_worktrees_in(&context.git_common_dir)?;
+    println!("✓ Pruned stale worktree metadata");
+    Ok(())
+}
+
+/// Handles of the worktrees matching `keep`.
+fn handles_where(worktrees: &[&WorktreeInfo], keep: impl Fn(&WorktreeInfo) -> bool) -> Vec<String> {
+    worktrees
+        .iter()
+        .filter(|wt| keep(wt))
+        .filter_map(|wt| wt.path.file_name().and_then(|n| n.to_str()))
+        .map(String::from)
+        .collect()
+}
+
+/// Working directories seen in each session and each window, from its live
+/// panes and the agents recorded in the state store.
+type TargetCwds = HashMap<String, Vec<PathBuf>>;
+
+fn target_cwds(mux: &dyn Multiplexer) -> Result<(TargetCwds, TargetCwds)> {
+    let mut sessions = TargetCwds::new();
+    let mut windows = TargetCwds::new();
+    for pane in mux.get_all_live_pane_info()?.into_values() {
+        // Linux reports the cwd of a removed directory with this suffix
+        let cwd = pane.working_dir.to_string_lossy();
+        let cwd = PathBuf::from(cwd.strip_suffix(" (deleted)").unwrap_or(&cwd));
+        if let Some(session) = pane.session {
+            sessions.entry(session).or_default().push(cwd.clone());
+        }
+        if let Some(window) = pane.window {
+            windows.entry(window).or_default().push(cwd);
+        }
+    }
+    let agents = StateStore::new()
+        .and_then(|store| store.load_reconciled_agents(mux))
+        .unwrap_or_default();
+    for agent in agents {
+        sessions
+            .entry(agent.session)
+            .or_default()
+            .push(agent.path.clone());
+        windows
+            .entry(agent.window_name)
+            .or_default()
+            .push(agent.path);
+    }
+    Ok((sessions, windows))
+}
+
+/// What ties a target to this repository: having worked in the directory
+/// its worktree would live in, under this repo's worktree directory.
+struct Ownership<'a> {
+    dir: &'a Path,
+    cwds: &'a TargetCwds,
+}
+
+impl Ownership<'_> {
+    fn owns(&self, name: &str, handle: &str) -> bool {
+        let worktree = self.dir.join(handle);
+        // Still a checkout, so not this repo's: its worktrees are all listed
+        if worktree.join(".git").exists() {
+            return false;
+        }
+        self.cwds
+            .get(name)
+            .is_some_and(|cwds| cwds.iter().any(|cwd| cwd.starts_with(&worktree)))
+    }
+}
+
+/// Live targets carrying the workmux prefix whose handle has no worktree,
+/// limited to those `ownership` ties to this repository. Other repos'
+/// targets share the prefix and the multiplexer server.
+fn orphaned_targets(
+    prefix: &str,
+    live: &HashSet<String>,
+    handles: &HashSet<String>,
+    ownership: &Ownership,
+) -> Vec<String> {
+    let mut orphans: Vec<String> = live
+        .iter()
+        .filter(|name| {
+            name.strip_prefix(prefix).is_some_and(|handle| {
+                !handle.is_empty() && !handles.contains(handle) && ownership.owns(name, handle)
+            })
+        })
+        .cloned()
+        .collect();
+    orphans.sort();
+    orphans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(items: &[&str]) -> HashSet<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn orphaned_targets_only_reports_this_repos_targets_without_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("repo__worktrees");
+        // Another checkout living in the same directory
+        std::fs::create_dir_all(dir.join("theirs")).unwrap();
+        std::fs::write(dir.join("theirs/.git"), "gitdir: /elsewhere").unwrap();
+
+        let live = set(&[
+            "wm-alive",
+            "wm-gone",
+            "wm-other",
+            "wm-theirs",
+            "wm-idle",
+            "scratch",
+            "wm-",
+        ]);
+        let handles = set(&["alive"]);
+        let cwds: TargetCwds = [
+            ("wm-alive", dir.join("alive")),
+            ("wm-gone", dir.join("gone/src")),
+            ("wm-other", PathBuf::from("/other__worktrees/other")),
+            ("wm-theirs", dir.join("theirs")),
+            ("scratch", dir.join("scratch")),
+        ]
+        .into_iter()
+        .map(|(name, cwd)| (name.to_string(), vec![cwd]))
+        .collect();
+        let ownership = Ownership {
+            dir: &dir,
+            cwds: &cwds,
+        };
+
+        assert_eq!(
+            orphaned_targets("wm-", &live, &handles, &ownership),
+            vec!["wm-gone"]
+        );
+    }
+
+    #[test]
+    fn handles_where_leaves_unknown_merge_status_out_of_idle() {
+        let wt = |name: &str, has_unmerged| WorktreeInfo {
+            branch: name.to_string(),
+            path: std::path::PathBuf::from("/repo__worktrees").join(name),
+            has_mux_window: false,
+            has_unmerged,
+            pr_info: None,
+            agent_status: None,
+        };
+        let infos = [
+            wt("merged", Some(false)),
+            wt("open", Some(true)),
+            wt("unknown", None),
+        ];
+        let candidates: Vec<&WorktreeInfo> = infos.iter().collect();
+
+        let idle = handles_where(&candidates, |wt| wt.has_unmerged == Some(false));
+        assert_eq!(idle, vec!["merged"]);
+        let unknown = handles_where(&candidates, |wt| wt.has_unmerged.is_none());
+        assert_eq!(unknown, vec!["unknown"]);
+    }
+}
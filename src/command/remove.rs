@@ -1,4 +1,4 @@
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 use crate::workflow::WorkflowContext;
 use crate::{config, git, spinner, workflow};
 use anyhow::{Context, Result, anyhow};
@@ -144,18 +144,20 @@ fn run_specified(names: Vec<String>, force: bool, keep_branch: bool) -> Result<(
 }
 
 /// Check if a branch has unmerged commits. Returns Some(base) if unmerged, None otherwise.
+///
+/// Uses the same batched, cached check as `workmux list`, so both commands
+/// agree on what counts as unmerged.
 fn is_unmerged(branch: &str) -> Result<Option<String>> {
-    let main_branch = git::get_default_branch().unwrap_or_else(|_| "main".to_string());
-
-    let base = git::get_branch_base(branch)
-        .ok()
-        .unwrap_or_else(|| main_branch.clone());
+    let base = match git::get_branch_base(branch) {
+        Ok(base) => base,
+        Err(_) => git::get_default_branch()?,
+    };
 
     let base_commit = match git::get_merge_base(&base) {
         Ok(b) => b,
         Err(_) => {
-            // If we can't determine base, try falling back to main
-            match git::get_merge_base(&main_branch) {
+            // If we can't determine base, try falling back to the default branch
+            match git::get_default_branch().and_then(|main| git::get_merge_base(&main)) {
                 Ok(b) => b,
                 Err(_) => return Ok(None), // Can't determine, assume safe
             }
@@ -180,7 +182,7 @@ fn run_all(force: bool, keep_branch: bool) -> Result<()> {
     let mut skipped_uncommitted: Vec<String> = Vec::new();
     let mut skipped_unmerged: Vec<String> = Vec::new();
 
-    for (path, branch) in worktrees {
+    for (path, branch) in git::checkouts(worktrees) {
         // Skip main branch/worktree and detached HEAD
         if branch == main_branch || branch == "(detached)" {
             continue;
@@ -198,17 +200,9 @@ fn run_all(force: bool, keep_branch: bool) -> Result<()> {
         }
 
         // Check for unmerged commits (only when deleting the branch)
-        if !force && !keep_branch {
-            let base = git::get_branch_base(&branch)
-                .ok()
-                .unwrap_or_else(|| main_branch.clone());
-            if let Ok(merge_base) = git::get_merge_base(&base)
-                && let Ok(unmerged_branches) = git::get_unmerged_branches(&merge_base)
-                && unmerged_branches.contains(&branch)
-            {
-                skipped_unmerged.push(branch);
-                continue;
-            }
+        if !force && !keep_branch && matches!(is_unmerged(&branch), Ok(Some(_))) {
+            skipped_unmerged.push(branch);
+            continue;
         }
 
         let handle = path
@@ -335,7 +329,7 @@ fn run_gone(force: bool, keep_branch: bool) -> Result<()> {
     let mut to_remove: Vec<(PathBuf, String, String)> = Vec::new();
     let mut skipped_uncommitted: Vec<String> = Vec::new();
 
-    for (path, branch) in worktrees {
+    for (path, branch) in git::checkouts(worktrees) {
         // Skip main branch/worktree and detached HEAD
         if branch == main_branch || branch == "(detached)" {
             continue;
@@ -450,7 +444,7 @@ fn run_gone(force: bool, keep_branch: bool) -> Result<()> {
 /// Execute the actual worktree removal
 fn remove_worktree(handle: &str, force: bool, keep_branch: bool) -> Result<()> {
     let config = config::Config::load(None)?;
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
     let context = WorkflowContext::new(config, mux, None)?;
 
     super::announce_hooks(&context.config, None, super::HookPhase::PreRemove);
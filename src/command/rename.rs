@@ -0,0 +1,12 @@
+use crate::multiplexer;
+use crate::workflow::WorkflowContext;
+use crate::{config, workflow};
+use anyhow::{Context, Result};
+
+pub fn run(name: &str, new_branch: &str) -> Result<()> {
+    let config = config::Config::load(None)?;
+    let mux = multiplexer::current();
+    let context = WorkflowContext::new(config, mux, None)?;
+
+    workflow::rename(name, new_branch, &context).context("Failed to rename worktree")
+}
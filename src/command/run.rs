@@ -8,7 +8,7 @@ use std::time::{Duration, Instant};
 use anyhow::{Result, anyhow};
 
 use crate::config::SplitDirection;
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 use crate::state::run::{RunSpec, cleanup_run, create_run, generate_run_id, read_result};
 use crate::workflow;
 
@@ -31,15 +31,17 @@ pub fn run(
     background: bool,
     keep: bool,
     timeout: Option<u64>,
+    wait_for_agent: Option<Duration>,
 ) -> Result<()> {
     if command_parts.is_empty() {
         return Err(anyhow!("No command provided"));
     }
 
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
 
     // Resolve worktree to agent pane (consistent with send/capture)
-    let (worktree_path, agent) = workflow::resolve_worktree_agent(worktree_name, mux.as_ref())?;
+    let (worktree_path, agent) =
+        workflow::wait_for_worktree_agent(worktree_name, mux.as_ref(), wait_for_agent)?;
 
     // Build command string (preserve argument boundaries via shell escaping)
     let command = command_parts
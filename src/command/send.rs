@@ -1,15 +1,22 @@
 use std::io::{IsTerminal, Read};
+use std::time::Duration;
 
 use anyhow::{Result, anyhow};
 
 use crate::config;
-use crate::multiplexer::{create_backend, detect_backend};
+use crate::multiplexer;
 use crate::workflow;
 
-pub fn run(name: &str, text: Option<&str>, file: Option<&str>) -> Result<()> {
+pub fn run(
+    name: &str,
+    text: Option<&str>,
+    file: Option<&str>,
+    no_enter: bool,
+    wait_for_agent: Option<Duration>,
+) -> Result<()> {
     let cfg = config::Config::load(None).unwrap_or_default();
-    let mux = create_backend(detect_backend());
-    let (_path, agent) = workflow::resolve_worktree_agent(name, mux.as_ref())?;
+    let mux = multiplexer::current();
+    let (_path, agent) = workflow::wait_for_worktree_agent(name, mux.as_ref(), wait_for_agent)?;
 
     // Determine content: positional arg > --file > stdin
     let content = if let Some(t) = text {
@@ -35,6 +42,11 @@ pub fn run(name: &str, text: Option<&str>, file: Option<&str>) -> Result<()> {
         return Err(anyhow!("No content to send"));
     }
 
+    if no_enter {
+        mux.send_text(&agent.pane_id, content)?;
+        return Ok(());
+    }
+
     // Single-line: use send_keys_to_agent (handles Claude's ! prefix delay)
     // Multi-line: use paste_multiline (already sends Enter in both backends)
     if content.contains('\n') {
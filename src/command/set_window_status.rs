@@ -1,9 +1,20 @@
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
 use clap::ValueEnum;
-use tracing::warn;
+use nix::fcntl::{Flock, FlockArg};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
 
-use crate::config::Config;
-use crate::multiplexer::{AgentStatus, create_backend, detect_backend};
+use crate::config::{self, Config};
+use crate::git;
+use crate::multiplexer::{self, AgentStatus, Multiplexer};
+use crate::state::feed::{self, FeedEvent};
+
+/// Default for `status_debounce_ms`.
+const DEFAULT_DEBOUNCE_MS: u64 = 200;
 
 #[derive(ValueEnum, Debug, Clone)]
 pub enum SetWindowStatusCommand {
@@ -17,71 +28,362 @@ pub enum SetWindowStatusCommand {
     Clear,
 }
 
-pub fn run(cmd: SetWindowStatusCommand) -> Result<()> {
+impl SetWindowStatusCommand {
+    /// The status this command sets, or `None` for `clear`.
+    pub fn status(&self) -> Option<AgentStatus> {
+        match self {
+            Self::Working => Some(AgentStatus::Working),
+            Self::Waiting => Some(AgentStatus::Waiting),
+            Self::Done => Some(AgentStatus::Done),
+            Self::Clear => None,
+        }
+    }
+
+    /// The name this command is given by, e.g. in status feed events.
+    pub fn name(&self) -> &'static str {
+        self.status().map_or("clear", AgentStatus::as_str)
+    }
+
+    /// Parse a status name, ignoring case, as received over RPC.
+    pub fn parse(s: &str) -> Result<Self> {
+        if s.trim().eq_ignore_ascii_case("clear") {
+            return Ok(Self::Clear);
+        }
+        let status: AgentStatus = s.parse().map_err(|_| {
+            anyhow!(
+                "Unknown status '{}': expected one of {}, clear",
+                s,
+                AgentStatus::names()
+            )
+        })?;
+        Ok(match status {
+            AgentStatus::Working => Self::Working,
+            AgentStatus::Waiting => Self::Waiting,
+            AgentStatus::Done => Self::Done,
+        })
+    }
+}
+
+/// What a status update knows besides the status itself, for the
+/// `status_template` placeholders.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StatusDetails<'a> {
+    /// Worktree the agent runs in, used to resolve `{branch}`
+    pub worktree: Option<&'a Path>,
+    pub tokens: Option<u64>,
+    pub cost: Option<f64>,
+}
+
+/// Compact token/cost indicator, e.g. `⚡ 12k $0.42`.
+///
+/// Missing or zero values are left out; returns `None` when there's nothing to show.
+pub fn usage_indicator(tokens: Option<u64>, cost: Option<f64>) -> Option<String> {
+    let parts: Vec<String> = [
+        format_tokens(tokens).map(|t| format!("⚡ {}", t)),
+        format_cost(cost),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn format_tokens(tokens: Option<u64>) -> Option<String> {
+    tokens.filter(|&t| t > 0).map(compact_count)
+}
+
+fn format_cost(cost: Option<f64>) -> Option<String> {
+    cost.filter(|&c| c > 0.0 && c.is_finite())
+        .map(|c| format!("${:.2}", c))
+}
+
+/// Render the configured `status_template` for a status change.
+fn status_label(
+    config: &Config,
+    status: &str,
+    glyph: &str,
+    details: &StatusDetails,
+) -> Result<String> {
+    config::render_status_template(config.status_template(), |name| {
+        Ok(match name {
+            "glyph" => glyph.to_string(),
+            "status" => status.to_string(),
+            "branch" => details
+                .worktree
+                .and_then(|dir| git::get_current_branch_in(dir).ok())
+                .unwrap_or_default(),
+            "tokens" => format_tokens(details.tokens).unwrap_or_default(),
+            "cost" => format_cost(details.cost).unwrap_or_default(),
+            "usage" => usage_indicator(details.tokens, details.cost).unwrap_or_default(),
+            // Rejected when the config is loaded
+            _ => String::new(),
+        })
+    })
+}
+
+/// The label `cmd` puts in the status bar, or an empty string for `clear`.
+pub fn render(
+    cmd: &SetWindowStatusCommand,
+    details: &StatusDetails,
+    config: &Config,
+) -> Result<String> {
+    let Some(status) = cmd.status() else {
+        return Ok(String::new());
+    };
+    status_label(
+        config,
+        status.as_str(),
+        status.glyph(&config.status_icons),
+        details,
+    )
+}
+
+/// Format a count with a k/M suffix: 950, 1.2k, 12k, 3.4M.
+fn compact_count(n: u64) -> String {
+    match n {
+        0..1_000 => n.to_string(),
+        1_000..10_000 => format!("{:.1}k", n as f64 / 1_000.0),
+        10_000..1_000_000 => format!("{}k", n / 1_000),
+        _ => format!("{:.1}M", n as f64 / 1_000_000.0),
+    }
+}
+
+/// Set the status of the current pane.
+///
+/// Hooks call this unconditionally, so outside a multiplexer (e.g. an agent
+/// run in CI) it does nothing unless `require_mux` is set.
+///
+/// With `print`, the label is printed instead of set, which works anywhere.
+pub fn run(
+    cmd: SetWindowStatusCommand,
+    tokens: Option<u64>,
+    cost: Option<f64>,
+    require_mux: bool,
+    print: bool,
+) -> Result<()> {
+    if print {
+        let config = Config::load(None)?;
+        let cwd = std::env::current_dir().ok();
+        let details = StatusDetails {
+            worktree: cwd.as_deref(),
+            tokens,
+            cost,
+        };
+        println!("{}", render(&cmd, &details, &config)?);
+        return Ok(());
+    }
+
     // Inside a sandbox guest, route through RPC to the host supervisor
     if crate::sandbox::guest::is_sandbox_guest() {
-        return run_via_rpc(cmd);
+        return run_via_rpc(cmd, tokens, cost);
     }
 
     let config = Config::load(None)?;
-    let mux = create_backend(detect_backend());
+    if multiplexer::configured_backend(&config)?.is_none() {
+        if require_mux {
+            bail!("Not running inside a multiplexer; cannot set the window status");
+        }
+        debug!(?cmd, "not in a multiplexer, skipping status update");
+        return Ok(());
+    }
+
+    let mux = multiplexer::current();
 
-    // Fail silently if not in a multiplexer session
     let Some(pane_id) = mux.current_pane_id() else {
+        if require_mux {
+            bail!(
+                "Not running in a {} pane; cannot set the window status",
+                mux.name()
+            );
+        }
+        debug!(?cmd, "no current pane, skipping status update");
         return Ok(());
     };
 
-    match cmd {
-        SetWindowStatusCommand::Clear => {
+    let cwd = std::env::current_dir().ok();
+    let details = StatusDetails {
+        worktree: cwd.as_deref(),
+        tokens,
+        cost,
+    };
+    apply_debounced(mux.as_ref(), &pane_id, &cmd, &details, &config)
+}
+
+/// Per-pane debounce state shared by concurrent hook processes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DebounceRecord {
+    /// Id (nanosecond timestamp) of the most recent update request
+    latest: u128,
+    /// When the multiplexer was last written (ms since epoch)
+    applied_at_ms: u128,
+}
+
+/// Outcome of registering an update with the debounce record.
+#[derive(Debug, PartialEq)]
+enum Debounce {
+    /// Nothing was written recently: apply right away.
+    ApplyNow,
+    /// Wait this long, then apply only if no newer update arrived.
+    Wait(Duration),
+}
+
+impl DebounceRecord {
+    fn register(&mut self, id: u128, now_ms: u128, window: Duration) -> Debounce {
+        self.latest = id;
+        let elapsed = now_ms.saturating_sub(self.applied_at_ms);
+        if elapsed >= window.as_millis() {
+            Debounce::ApplyNow
+        } else {
+            Debounce::Wait(window - Duration::from_millis(elapsed as u64))
+        }
+    }
+}
+
+/// `apply`, but coalescing bursts: updates that arrive within
+/// `status_debounce_ms` of the last write wait out the window, and only the
+/// newest of them touches the multiplexer. The final state of a burst is
+/// always applied.
+///
+/// Each hook is its own process, so coordination goes through a small
+/// per-pane file guarded by `flock`.
+pub fn apply_debounced(
+    mux: &dyn Multiplexer,
+    pane_id: &str,
+    cmd: &SetWindowStatusCommand,
+    details: &StatusDetails,
+    config: &Config,
+) -> Result<()> {
+    let window = Duration::from_millis(config.status_debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS));
+    let path = match debounce_path(mux, pane_id) {
+        Ok(path) if !window.is_zero() => path,
+        _ => return apply(mux, pane_id, cmd, details, config),
+    };
+
+    let id = now().as_nanos();
+    let decision = with_record(&path, |record| {
+        let decision = record.register(id, now().as_millis(), window);
+        if decision == Debounce::ApplyNow {
+            apply(mux, pane_id, cmd, details, config)?;
+            record.applied_at_ms = now().as_millis();
+        }
+        Ok(decision)
+    })?;
+
+    if let Debounce::Wait(delay) = decision {
+        std::thread::sleep(delay);
+        with_record(&path, |record| {
+            if record.latest != id {
+                debug!(pane_id, "status update superseded, skipping");
+                return Ok(());
+            }
+            apply(mux, pane_id, cmd, details, config)?;
+            record.applied_at_ms = now().as_millis();
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
+
+fn now() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// `~/.local/state/workmux/status/<backend>-<instance>-<pane>.json`
+fn debounce_path(mux: &dyn Multiplexer, pane_id: &str) -> Result<PathBuf> {
+    let key: String = format!("{}-{}-{}", mux.name(), mux.instance_id(), pane_id)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let dir = crate::state::store::get_state_dir()?.join("workmux/status");
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    Ok(dir.join(format!("{}.json", key)))
+}
+
+/// Run `f` on the debounce record with the file locked, then save it.
+fn with_record<T>(path: &Path, f: impl FnOnce(&mut DebounceRecord) -> Result<T>) -> Result<T> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut file: Flock<File> = Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("Failed to lock {}: {}", path.display(), e))?;
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut record: DebounceRecord = serde_json::from_str(&content).unwrap_or_default();
+
+    let result = f(&mut record)?;
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(serde_json::to_string(&record)?.as_bytes())?;
+    Ok(result)
+}
+
+/// Apply a status change to a pane: update the backend status label
+/// (rendered from `status_template`), persist the agent state for the
+/// dashboard and append it to the status feed.
+///
+/// Shared by this command and the sandbox RPC `set_status` handler.
+pub fn apply(
+    mux: &dyn Multiplexer,
+    pane_id: &str,
+    cmd: &SetWindowStatusCommand,
+    details: &StatusDetails,
+    config: &Config,
+) -> Result<()> {
+    match cmd.status() {
+        None => {
             // Clear icon only - state file cleanup is handled by reconciliation
-            mux.clear_status(&pane_id)?;
+            mux.clear_status(pane_id)?;
         }
-        SetWindowStatusCommand::Working
-        | SetWindowStatusCommand::Waiting
-        | SetWindowStatusCommand::Done => {
-            let (status, icon, auto_clear) = match cmd {
-                SetWindowStatusCommand::Working => {
-                    (AgentStatus::Working, config.status_icons.working(), false)
-                }
-                SetWindowStatusCommand::Waiting => {
-                    (AgentStatus::Waiting, config.status_icons.waiting(), true)
-                }
-                SetWindowStatusCommand::Done => {
-                    (AgentStatus::Done, config.status_icons.done(), true)
-                }
-                SetWindowStatusCommand::Clear => unreachable!(),
-            };
+        Some(status) => {
+            // Working stays until replaced; the others clear once seen
+            let auto_clear = status != AgentStatus::Working;
 
             // Ensure the status format is applied so the icon actually shows up
             if config.status_format.unwrap_or(true) {
-                let _ = mux.ensure_status_format(&pane_id);
+                let _ = mux.ensure_status_format(pane_id);
             }
 
-            // Update backend UI (status bar icon)
-            mux.set_status(&pane_id, icon, auto_clear)?;
+            // Update backend UI (status bar label)
+            let label = render(cmd, details, config)?;
+            mux.set_status(pane_id, &label, auto_clear)?;
 
             // Persist to state store so the dashboard sees this agent
-            crate::state::persist_agent_update(&*mux, &pane_id, Some(status), None);
+            crate::state::persist_agent_update(mux, pane_id, Some(status), None);
         }
     }
 
+    feed::record(
+        &config.status_feed,
+        &FeedEvent::now(details.worktree, pane_id, cmd.name()),
+    );
+
     Ok(())
 }
 
 /// Send a status update via RPC when running inside a sandbox guest.
-fn run_via_rpc(cmd: SetWindowStatusCommand) -> Result<()> {
+fn run_via_rpc(cmd: SetWindowStatusCommand, tokens: Option<u64>, cost: Option<f64>) -> Result<()> {
     use crate::sandbox::rpc::{RpcClient, RpcRequest, RpcResponse};
 
-    let status = match cmd {
-        SetWindowStatusCommand::Working => "working",
-        SetWindowStatusCommand::Waiting => "waiting",
-        SetWindowStatusCommand::Done => "done",
-        SetWindowStatusCommand::Clear => "clear",
-    };
-
     let mut client = RpcClient::from_env()?;
     let response = client.call(&RpcRequest::SetStatus {
-        status: status.to_string(),
+        status: cmd.name().to_string(),
+        tokens,
+        cost,
     })?;
 
     match response {
@@ -93,3 +395,111 @@ fn run_via_rpc(cmd: SetWindowStatusCommand) -> Result<()> {
         _ => Ok(()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn debounce_applies_immediately_after_quiet_period() {
+        let mut record = DebounceRecord::default();
+        let window = Duration::from_millis(200);
+
+        assert_eq!(record.register(1, 10_000, window), Debounce::ApplyNow);
+        assert_eq!(record.latest, 1);
+
+        record.applied_at_ms = 10_000;
+        assert_eq!(record.register(2, 10_500, window), Debounce::ApplyNow);
+    }
+
+    #[test]
+    fn debounce_waits_out_the_rest_of_the_window() {
+        let mut record = DebounceRecord {
+            latest: 1,
+            applied_at_ms: 10_000,
+        };
+        let window = Duration::from_millis(200);
+
+        assert_eq!(
+            record.register(2, 10_050, window),
+            Debounce::Wait(Duration::from_millis(150))
+        );
+        assert_eq!(
+            record.register(3, 10_120, window),
+            Debounce::Wait(Duration::from_millis(80))
+        );
+        // Only the newest request is still current when the waits finish
+        assert_eq!(record.latest, 3);
+    }
+
+    #[test]
+    fn usage_indicator_formats_tokens_and_cost() {
+        assert_eq!(
+            usage_indicator(Some(12_345), Some(0.4219)).as_deref(),
+            Some("⚡ 12k $0.42")
+        );
+        assert_eq!(usage_indicator(Some(950), None).as_deref(), Some("⚡ 950"));
+        assert_eq!(
+            usage_indicator(Some(1_250), None).as_deref(),
+            Some("⚡ 1.2k")
+        );
+        assert_eq!(
+            usage_indicator(Some(3_400_000), None).as_deref(),
+            Some("⚡ 3.4M")
+        );
+        assert_eq!(usage_indicator(None, Some(1.5)).as_deref(), Some("$1.50"));
+    }
+
+    #[test]
+    fn status_label_renders_configured_template() {
+        let details = StatusDetails {
+            worktree: None,
+            tokens: Some(12_345),
+            cost: Some(0.42),
+        };
+        let label = |template: Option<&str>| {
+            let config = Config {
+                status_template: template.map(String::from),
+                ..Default::default()
+            };
+            status_label(&config, "working", "🤖", &details).unwrap()
+        };
+
+        assert_eq!(label(None), "🤖 ⚡ 12k $0.42");
+        assert_eq!(label(Some("{glyph}")), "🤖");
+        assert_eq!(
+            label(Some("{status} ({tokens}, {cost})")),
+            "working (12k, $0.42)"
+        );
+        // No worktree to resolve the branch from: renders empty
+        assert_eq!(label(Some("[{branch}] {glyph}")), "[] 🤖");
+    }
+
+    #[test]
+    fn render_maps_each_command_to_its_glyph() {
+        let config = Config {
+            status_template: Some("{glyph} {status}".to_string()),
+            ..Default::default()
+        };
+        let render = |cmd| render(&cmd, &StatusDetails::default(), &config).unwrap();
+        assert_eq!(
+            render(SetWindowStatusCommand::Waiting),
+            format!("{} waiting", config.status_icons.waiting())
+        );
+        assert_eq!(
+            render(SetWindowStatusCommand::Done),
+            format!("{} done", config.status_icons.done())
+        );
+        assert_eq!(render(SetWindowStatusCommand::Clear), "");
+    }
+
+    #[test]
+    fn usage_indicator_omits_missing_and_zero_values() {
+        assert_eq!(usage_indicator(None, None), None);
+        assert_eq!(usage_indicator(Some(0), Some(0.0)), None);
+        assert_eq!(
+            usage_indicator(Some(0), Some(0.1)).as_deref(),
+            Some("$0.10")
+        );
+    }
+}
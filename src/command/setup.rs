@@ -4,7 +4,7 @@ use std::io::{self, IsTerminal, Write};
 
 use crate::agent_setup::{self, StatusCheck};
 
-pub fn run() -> Result<()> {
+pub fn run(local: bool) -> Result<()> {
     if !io::stdin().is_terminal() {
         anyhow::bail!("workmux setup requires an interactive terminal");
     }
@@ -13,7 +13,7 @@ pub fn run() -> Result<()> {
 
     if checks.is_empty() {
         println!(
-            "No agents detected. Install an agent CLI (Claude Code, OpenCode) to get started."
+            "No agents detected. Install an agent CLI (Claude Code, Cursor, OpenCode) to get started."
         );
         return Ok(());
     }
@@ -63,7 +63,7 @@ pub fn run() -> Result<()> {
     if confirm_install()? {
         let mut any_failed = false;
         for check in &needs_setup {
-            match agent_setup::install(check.agent) {
+            match agent_setup::install(check.agent, local) {
                 Ok(msg) => println!("  {} {}", style("✓").green(), msg),
                 Err(e) => {
                     println!("  {} {}: {}", style("✗").red(), check.agent.name(), e);
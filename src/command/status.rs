@@ -8,7 +8,7 @@ use tabled::{
 };
 
 use crate::git;
-use crate::multiplexer::{AgentStatus, create_backend, detect_backend};
+use crate::multiplexer::{self, AgentStatus};
 use crate::state::StateStore;
 use crate::util;
 use crate::workflow;
@@ -68,16 +68,11 @@ fn git_label(git: &Option<GitInfo>) -> String {
 }
 
 fn status_label(status: Option<AgentStatus>) -> String {
-    match status {
-        Some(AgentStatus::Working) => "working".to_string(),
-        Some(AgentStatus::Waiting) => "waiting".to_string(),
-        Some(AgentStatus::Done) => "done".to_string(),
-        None => "-".to_string(),
-    }
+    status.map_or_else(|| "-".to_string(), |s| s.to_string())
 }
 
 pub fn run(worktrees: &[String], json: bool, show_git: bool) -> Result<()> {
-    let mux = create_backend(detect_backend());
+    let mux = multiplexer::current();
 
     let agent_panes =
         StateStore::new().and_then(|store| store.load_reconciled_agents(mux.as_ref()))?;
@@ -92,7 +87,7 @@ pub fn run(worktrees: &[String], json: bool, show_git: bool) -> Result<()> {
     }
 
     // Get all worktrees for mapping (propagate errors)
-    let all_worktrees = git::list_worktrees()?;
+    let all_worktrees = git::checkouts(git::list_worktrees()?);
 
     // Get unmerged info if --git flag
     let main_branch = if show_git {
@@ -0,0 +1,118 @@
+//! `workmux version`: the workmux version and, with `--verbose`, the tools it
+//! drives, in a form that can be pasted into a bug report.
+
+use anyhow::Result;
+use serde::Serialize;
+
+use crate::multiplexer;
+use crate::sandbox::lima::LimaInstance;
+use crate::util::command_version;
+
+#[derive(Debug, Serialize)]
+struct Report {
+    version: &'static str,
+    os: &'static str,
+    arch: &'static str,
+    multiplexer: MultiplexerInfo,
+    /// `None` when the tool isn't installed
+    limactl: Option<String>,
+    git: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct MultiplexerInfo {
+    backend: String,
+    /// Whether we're running inside it (or `WORKMUX_BACKEND` chose it),
+    /// rather than falling back to the default
+    detected: bool,
+    version: Option<String>,
+}
+
+impl Report {
+    fn gather() -> Self {
+        let detected = multiplexer::detect_backend();
+        let backend = detected.unwrap_or_default();
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            multiplexer: MultiplexerInfo {
+                backend: backend.to_string(),
+                detected: detected.is_some(),
+                version: backend.version(),
+            },
+            limactl: LimaInstance::version(),
+            git: command_version("git", "--version"),
+        }
+    }
+
+    fn render(&self) -> String {
+        let missing = || "not found".to_string();
+        let mux = &self.multiplexer;
+        let mut mux_line = mux
+            .version
+            .clone()
+            .unwrap_or_else(|| format!("{} (not found)", mux.backend));
+        if !mux.detected {
+            mux_line.push_str(" (default, not detected)");
+        }
+        [
+            format!("workmux {}", self.version),
+            format!("  os:          {} {}", self.os, self.arch),
+            format!("  multiplexer: {}", mux_line),
+            format!(
+                "  limactl:     {}",
+                self.limactl.clone().unwrap_or_else(missing)
+            ),
+            format!(
+                "  git:         {}",
+                self.git.clone().unwrap_or_else(missing)
+            ),
+        ]
+        .join("\n")
+    }
+}
+
+pub fn run(verbose: bool, json: bool) -> Result<()> {
+    if !verbose && !json {
+        println!("workmux {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
+    let report = Report::gather();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{}", report.render());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_marks_missing_tools_and_fallback_backend() {
+        let report = Report {
+            version: "1.2.3",
+            os: "linux",
+            arch: "x86_64",
+            multiplexer: MultiplexerInfo {
+                backend: "tmux".to_string(),
+                detected: false,
+                version: None,
+            },
+            limactl: None,
+            git: Some("git version 2.45.0".to_string()),
+        };
+        assert_eq!(
+            report.render(),
+            "workmux 1.2.3\n  \
+             os:          linux x86_64\n  \
+             multiplexer: tmux (not found) (default, not detected)\n  \
+             limactl:     not found\n  \
+             git:         git version 2.45.0"
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! `workmux exec`: run a one-off command in a worktree's sandbox VM.
+
+use anyhow::{Result, anyhow};
+
+use crate::git;
+
+pub fn run(name: &str, command: &[String]) -> Result<()> {
+    let (path, _branch) = git::find_worktree(name).map_err(|e| {
+        if e.is::<git::WorktreeNotFound>() {
+            anyhow!(
+                "No worktree found with name '{}'. Use 'workmux list' to see available worktrees.",
+                name
+            )
+        } else {
+            e
+        }
+    })?;
+
+    let result = super::sandbox_run::exec(&path, command);
+    super::sandbox::exit_with_agent_status(result, None);
+}
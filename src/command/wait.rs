@@ -2,34 +2,22 @@ use std::collections::HashSet;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use anyhow::{Result, anyhow};
+use anyhow::Result;
 
 use crate::git;
-use crate::multiplexer::{AgentStatus, create_backend, detect_backend};
+use crate::multiplexer::{self, AgentStatus};
 use crate::state::StateStore;
 use crate::util;
 use crate::workflow;
 
-fn parse_status(s: &str) -> Result<AgentStatus> {
-    match s {
-        "working" => Ok(AgentStatus::Working),
-        "waiting" => Ok(AgentStatus::Waiting),
-        "done" => Ok(AgentStatus::Done),
-        _ => Err(anyhow!(
-            "Invalid status '{}'. Must be: working, waiting, done",
-            s
-        )),
-    }
-}
-
 pub fn run(
     worktree_names: &[String],
     target_status: &str,
     timeout_secs: Option<u64>,
     any: bool,
 ) -> Result<()> {
-    let target = parse_status(target_status)?;
-    let mux = create_backend(detect_backend());
+    let target: AgentStatus = target_status.parse()?;
+    let mux = multiplexer::current();
     let start = Instant::now();
 
     // Resolve worktree paths upfront
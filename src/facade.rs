@@ -0,0 +1,129 @@
+//! [`Workmux`]: one entry point for tools embedding workmux, holding the
+//! loaded config and multiplexer that the [`crate::workflow`] functions
+//! otherwise take one call at a time.
+
+use anyhow::{Result, anyhow};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::command::capture::{AnsiMode, clean_capture};
+use crate::config::Config;
+use crate::git;
+use crate::multiplexer::{self, CaptureRange, Multiplexer};
+use crate::workflow::{self, WorktreeInfo};
+
+/// Which worktrees [`Workmux::list_with`] returns and what it computes.
+#[derive(Debug, Clone, Default)]
+pub struct ListOptions {
+    /// Fetch each branch's pull request (requires the `gh` CLI)
+    pub pr: bool,
+    /// Only worktrees whose handle or branch is listed; all when empty
+    pub filter: Vec<String>,
+    /// Branch to check merge status against, instead of the configured one
+    pub base: Option<String>,
+}
+
+/// The config and multiplexer of the repository in the current directory.
+pub struct Workmux {
+    config: Config,
+    mux: Arc<dyn Multiplexer>,
+}
+
+impl Workmux {
+    /// Load the config for the current directory and pick the multiplexer
+    /// backend the same way the CLI does.
+    pub fn new() -> Result<Self> {
+        Ok(Self::with(Config::load(None)?, multiplexer::current()))
+    }
+
+    /// Use an already loaded config and backend.
+    pub fn with(config: Config, mux: Arc<dyn Multiplexer>) -> Self {
+        Self { config, mux }
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn multiplexer(&self) -> &dyn Multiplexer {
+        self.mux.as_ref()
+    }
+
+    /// Every worktree, with merge status but no PR status.
+    pub fn list(&self) -> Result<Vec<WorktreeInfo>> {
+        self.list_with(&ListOptions::default())
+    }
+
+    pub fn list_with(&self, options: &ListOptions) -> Result<Vec<WorktreeInfo>> {
+        workflow::list(
+            &self.config,
+            self.mux.as_ref(),
+            options.pr,
+            &options.filter,
+            options.base.as_deref(),
+        )
+    }
+
+    /// The path and branch of the worktree `name` refers to: its handle,
+    /// its branch, or a unique substring of either.
+    pub fn resolve(&self, name: &str) -> Result<(PathBuf, String)> {
+        workflow::find_worktree_fuzzy(name)
+    }
+
+    /// The path of the worktree with handle or branch `name`, matched exactly.
+    pub fn path(&self, name: &str) -> Result<PathBuf> {
+        worktree_path(name)
+    }
+
+    /// The output of the agent running in worktree `name`, with ANSI codes
+    /// and trailing blank lines removed.
+    pub fn capture(&self, name: &str, range: CaptureRange) -> Result<String> {
+        let (_path, agent) = workflow::resolve_worktree_agent(name, self.mux.as_ref())?;
+        self.capture_pane(&agent.pane_id, range)
+    }
+
+    /// Like [`Workmux::capture`], for any pane.
+    pub fn capture_pane(&self, pane_id: &str, range: CaptureRange) -> Result<String> {
+        let output = self.mux.capture_pane(pane_id, range)?;
+        Ok(clean_capture(&output, range, AnsiMode::Strip).join("\n"))
+    }
+}
+
+/// [`Workmux::path`], for callers that don't need a config.
+pub(crate) fn worktree_path(name: &str) -> Result<PathBuf> {
+    let (path, _branch) = git::find_worktree(name).map_err(|e| {
+        if e.is::<git::WorktreeNotFound>() {
+            anyhow!(
+                "No worktree found with name '{}'. Use 'workmux list' to see available worktrees.",
+                name
+            )
+        } else {
+            e
+        }
+    })?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multiplexer::mock::MockMultiplexer;
+
+    #[test]
+    fn capture_pane_cleans_the_output() {
+        let mux = MockMultiplexer::new()
+            .with_pane("%1", "\x1b[32mone\x1b[0m\ntwo\n\n")
+            .with_pane("%2", "a\nb\nc");
+        let workmux = Workmux::with(Config::default(), Arc::new(mux));
+
+        assert_eq!(
+            workmux.capture_pane("%1", CaptureRange::All).unwrap(),
+            "one\ntwo"
+        );
+        assert_eq!(
+            workmux.capture_pane("%2", CaptureRange::Lines(2)).unwrap(),
+            "b\nc"
+        );
+        assert!(workmux.capture_pane("%9", CaptureRange::All).is_err());
+    }
+}
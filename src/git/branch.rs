@@ -1,16 +1,27 @@
 use anyhow::{Context, Result, anyhow};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::OnceLock;
 use tracing::debug;
 
 use crate::cmd::Cmd;
 
 use super::repo::has_commits;
-use super::{ForkBranchSpec, RemoteBranchSpec};
+use super::{ForkBranchSpec, RemoteBranchSpec, UpstreamTracking};
 
-/// Get the default branch (main or master)
+/// Default branch of the current repository, resolved once per invocation.
+static DEFAULT_BRANCH: OnceLock<String> = OnceLock::new();
+
+/// Get the default branch of the current repository
+///
+/// The result is cached for the rest of the process, since several commands
+/// need it more than once and it never changes mid-invocation.
 pub fn get_default_branch() -> Result<String> {
-    get_default_branch_in(None)
+    if let Some(branch) = DEFAULT_BRANCH.get() {
+        return Ok(branch.clone());
+    }
+    let branch = get_default_branch_in(None)?;
+    Ok(DEFAULT_BRANCH.get_or_init(|| branch).clone())
 }
 
 /// Get the default branch for a repository at a specific path
@@ -39,6 +50,13 @@ pub fn get_default_branch_in(workdir: Option<&Path>) -> Result<String> {
         return Ok("master".to_string());
     }
 
+    // No remote HEAD and no main/master: use whatever the current checkout
+    // tracks, which covers repos without a remote that track a local branch
+    if let Some(branch) = upstream_branch_in(workdir) {
+        debug!(branch = branch, "git:default branch from upstream");
+        return Ok(branch);
+    }
+
     // Check if repo has any commits at all
     if !has_commits()? {
         return Err(anyhow!(
@@ -54,6 +72,26 @@ pub fn get_default_branch_in(workdir: Option<&Path>) -> Result<String> {
     ))
 }
 
+/// Branch tracked by the current checkout, without its remote prefix
+fn upstream_branch_in(workdir: Option<&Path>) -> Option<String> {
+    let cmd = Cmd::new("git").args(&["rev-parse", "--symbolic-full-name", "@{upstream}"]);
+    let cmd = match workdir {
+        Some(path) => cmd.workdir(path),
+        None => cmd,
+    };
+    let full_ref = cmd.run_and_capture_stdout().ok()?;
+    branch_from_upstream_ref(full_ref.trim())
+}
+
+/// `refs/heads/<branch>` or `refs/remotes/<remote>/<branch>` to `<branch>`.
+fn branch_from_upstream_ref(full_ref: &str) -> Option<String> {
+    let branch = match full_ref.strip_prefix("refs/heads/") {
+        Some(branch) => branch,
+        None => full_ref.strip_prefix("refs/remotes/")?.split_once('/')?.1,
+    };
+    (!branch.is_empty()).then(|| branch.to_string())
+}
+
 /// Check if a branch exists (can be local or remote tracking branch)
 pub fn branch_exists(branch_name: &str) -> Result<bool> {
     branch_exists_in(branch_name, None)
@@ -117,6 +155,14 @@ pub fn get_current_branch() -> Result<String> {
         .run_and_capture_stdout()
 }
 
+/// Get the branch checked out in `workdir` (empty on a detached HEAD)
+pub fn get_current_branch_in(workdir: &Path) -> Result<String> {
+    Cmd::new("git")
+        .workdir(workdir)
+        .args(&["branch", "--show-current"])
+        .run_and_capture_stdout()
+}
+
 /// List all checkout-able branches (local and remote) for shell completion.
 /// Excludes branches that are already checked out in existing worktrees.
 pub fn list_checkout_branches() -> Result<Vec<String>> {
@@ -134,7 +180,7 @@ pub fn list_checkout_branches() -> Result<Vec<String>> {
     let worktree_branches: HashSet<String> = super::list_worktrees()
         .unwrap_or_default()
         .into_iter()
-        .map(|(_, branch)| branch)
+        .filter_map(|wt| wt.branch)
         .collect();
 
     Ok(output
@@ -162,6 +208,17 @@ pub fn delete_branch_in(branch_name: &str, force: bool, git_common_dir: &Path) -
     Ok(())
 }
 
+/// Rename a local branch (`git branch -m`). Git moves its config section,
+/// including the stored base branch, along with it.
+pub fn rename_branch_in(old_name: &str, new_name: &str, git_common_dir: &Path) -> Result<()> {
+    Cmd::new("git")
+        .workdir(git_common_dir)
+        .args(&["branch", "-m", old_name, new_name])
+        .run()
+        .with_context(|| format!("Failed to rename branch '{}' to '{}'", old_name, new_name))?;
+    Ok(())
+}
+
 /// Get the base branch for merge checks, preferring local branch over remote
 pub fn get_merge_base(main_branch: &str) -> Result<String> {
     // Check if the local branch exists first.
@@ -182,16 +239,28 @@ pub fn get_merge_base(main_branch: &str) -> Result<String> {
 
 /// Get a set of all branches not merged into the base branch
 pub fn get_unmerged_branches(base_branch: &str) -> Result<HashSet<String>> {
+    get_unmerged_branches_in(base_branch, None)
+}
+
+/// Get a set of all branches not merged into the base branch, for a
+/// repository at a specific path
+pub fn get_unmerged_branches_in(
+    base_branch: &str,
+    workdir: Option<&Path>,
+) -> Result<HashSet<String>> {
     // Special handling for potential errors since base branch might not exist
     let no_merged_arg = format!("--no-merged={}", base_branch);
-    let result = Cmd::new("git")
-        .args(&[
-            "for-each-ref",
-            "--format=%(refname:short)",
-            &no_merged_arg,
-            "refs/heads/",
-        ])
-        .run_and_capture_stdout();
+    let cmd = Cmd::new("git").args(&[
+        "for-each-ref",
+        "--format=%(refname:short)",
+        &no_merged_arg,
+        "refs/heads/",
+    ]);
+    let cmd = match workdir {
+        Some(path) => cmd.workdir(path),
+        None => cmd,
+    };
+    let result = cmd.run_and_capture_stdout();
 
     match result {
         Ok(stdout) => {
@@ -231,6 +300,74 @@ pub fn get_gone_branches() -> Result<HashSet<String>> {
     Ok(gone)
 }
 
+/// Get the committer time (Unix seconds) of every local branch's tip.
+///
+/// Branches without commits have no ref yet, so they are simply absent.
+pub fn get_branch_commit_times() -> Result<HashMap<String, u64>> {
+    let output = Cmd::new("git")
+        .args(&[
+            "for-each-ref",
+            "--format=%(refname:short)|%(committerdate:unix)",
+            "refs/heads",
+        ])
+        .run_and_capture_stdout()?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let (branch, time) = line.rsplit_once('|')?;
+            Some((branch.to_string(), time.trim().parse().ok()?))
+        })
+        .collect())
+}
+
+/// Get the upstream tracking state of every local branch that has one.
+///
+/// Branches without an upstream are absent from the map.
+pub fn get_branch_upstreams() -> Result<HashMap<String, UpstreamTracking>> {
+    let output = Cmd::new("git")
+        .args(&[
+            "for-each-ref",
+            // Tab can't appear in ref names
+            "--format=%(refname:short)%09%(upstream:short)%09%(upstream:track,nobracket)",
+            "refs/heads",
+        ])
+        .run_and_capture_stdout()?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let branch = fields.next()?;
+            let upstream = fields.next()?;
+            let track = fields.next()?;
+            if upstream.is_empty() {
+                return None;
+            }
+            Some((branch.to_string(), parse_upstream_track(track)?))
+        })
+        .collect())
+}
+
+/// Parse `%(upstream:track,nobracket)`: empty when in sync, `gone`, or a
+/// comma-separated `ahead N` / `behind N` pair.
+fn parse_upstream_track(track: &str) -> Option<UpstreamTracking> {
+    let track = track.trim();
+    if track == "gone" {
+        return Some(UpstreamTracking::Gone);
+    }
+
+    let (mut ahead, mut behind) = (0, 0);
+    for part in track.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once(' ')? {
+            ("ahead", n) => ahead = n.parse().ok()?,
+            ("behind", n) => behind = n.parse().ok()?,
+            _ => return None,
+        }
+    }
+    Some(UpstreamTracking::Tracking { ahead, behind })
+}
+
 /// Unset the upstream tracking for a branch
 pub fn unset_branch_upstream(branch_name: &str) -> Result<()> {
     if !branch_has_upstream(branch_name)? {
@@ -304,6 +441,17 @@ pub fn get_branch_base_in(branch: &str, workdir: Option<&Path>) -> Result<String
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_upstream_track() {
+        let tracking = |ahead, behind| Some(UpstreamTracking::Tracking { ahead, behind });
+        assert_eq!(parse_upstream_track(""), tracking(0, 0));
+        assert_eq!(parse_upstream_track("ahead 3"), tracking(3, 0));
+        assert_eq!(parse_upstream_track("behind 2"), tracking(0, 2));
+        assert_eq!(parse_upstream_track("ahead 3, behind 2"), tracking(3, 2));
+        assert_eq!(parse_upstream_track("gone"), Some(UpstreamTracking::Gone));
+        assert_eq!(parse_upstream_track("sideways 1"), None);
+    }
+
     #[test]
     fn test_parse_fork_branch_spec_valid() {
         let spec = parse_fork_branch_spec("someuser:feature-branch").unwrap();
@@ -347,4 +495,67 @@ mod tests {
     fn test_parse_fork_branch_spec_remote_branch_format() {
         assert!(parse_fork_branch_spec("origin/feature").is_none());
     }
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn test_branch_from_upstream_ref() {
+        assert_eq!(
+            branch_from_upstream_ref("refs/remotes/origin/develop").as_deref(),
+            Some("develop")
+        );
+        assert_eq!(
+            branch_from_upstream_ref("refs/remotes/upstream/release/2.0").as_deref(),
+            Some("release/2.0")
+        );
+        assert_eq!(
+            branch_from_upstream_ref("refs/heads/trunk").as_deref(),
+            Some("trunk")
+        );
+        assert_eq!(branch_from_upstream_ref("refs/remotes/origin"), None);
+        assert_eq!(branch_from_upstream_ref("HEAD"), None);
+    }
+
+    #[test]
+    fn test_default_branch_falls_back_to_upstream_without_remote() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let repo = temp.path();
+        git(repo, &["init", "-b", "trunk"]);
+        git(repo, &["commit", "--allow-empty", "-m", "init"]);
+        git(repo, &["checkout", "-b", "feature", "--track", "trunk"]);
+
+        assert_eq!(get_default_branch_in(Some(repo)).unwrap(), "trunk");
+    }
+
+    #[test]
+    fn test_unmerged_branches_against_non_default_base() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let repo = temp.path();
+        git(repo, &["init", "-b", "main"]);
+        git(repo, &["commit", "--allow-empty", "-m", "init"]);
+        git(repo, &["checkout", "-b", "develop"]);
+        git(repo, &["checkout", "-b", "feature"]);
+        git(repo, &["commit", "--allow-empty", "-m", "feature work"]);
+        git(repo, &["checkout", "develop"]);
+        git(
+            repo,
+            &["merge", "--no-ff", "-m", "merge feature", "feature"],
+        );
+
+        let against_develop = get_unmerged_branches_in("develop", Some(repo)).unwrap();
+        assert!(!against_develop.contains("feature"));
+
+        let against_main = get_unmerged_branches_in("main", Some(repo)).unwrap();
+        assert!(against_main.contains("feature"));
+        assert!(against_main.contains("develop"));
+    }
 }
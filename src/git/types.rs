@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub struct RemoteBranchSpec {
@@ -12,11 +13,55 @@ pub struct ForkBranchSpec {
     pub branch: String,
 }
 
+/// One worktree as reported by `git worktree list --porcelain`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WorktreeEntry {
+    pub path: PathBuf,
+    /// Checked-out branch without `refs/heads/`; `None` for a detached HEAD
+    /// or a bare repository
+    pub branch: Option<String>,
+    /// Commit checked out; `None` for a bare repository
+    pub head: Option<String>,
+    /// The bare repository itself, listed first in a bare-repo setup
+    pub is_bare: bool,
+    pub is_detached: bool,
+    /// Locked with `git worktree lock`, so prune and remove skip it
+    pub is_locked: bool,
+}
+
+impl WorktreeEntry {
+    /// The branch as workmux shows it, `(detached)` for a detached HEAD.
+    /// `None` when nothing is checked out (a bare repository).
+    pub fn branch_label(&self) -> Option<&str> {
+        match &self.branch {
+            Some(branch) => Some(branch),
+            None if self.is_detached => Some("(detached)"),
+            None => None,
+        }
+    }
+}
+
 /// Custom error type for worktree not found
 #[derive(Debug, thiserror::Error)]
 #[error("Worktree not found: {0}")]
 pub struct WorktreeNotFound(pub String);
 
+/// Custom error type for worktree lookups run outside any git repository
+#[derive(Debug, thiserror::Error)]
+#[error("Not inside a git repository")]
+pub struct NotInRepository;
+
+/// How a local branch relates to its upstream tracking branch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamTracking {
+    /// An upstream is configured but its ref no longer exists
+    Gone,
+    Tracking {
+        ahead: usize,
+        behind: usize,
+    },
+}
+
 /// Git status information for a worktree
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GitStatus {
@@ -4,12 +4,28 @@ use std::path::{Path, PathBuf};
 use crate::cmd::Cmd;
 use crate::config::MuxMode;
 
-use super::WorktreeNotFound;
 use super::branch::unset_branch_upstream;
+use super::{NotInRepository, WorktreeEntry, WorktreeNotFound};
+
+/// The directory name a worktree of `branch` gets (before any
+/// `worktree_naming` strategy or `worktree_prefix` is applied).
+///
+/// - ASCII letters are lowercased and digits kept.
+/// - Other letters are transliterated to ASCII (`é` becomes `e`, `ß`
+///   becomes `ss`); characters with no transliteration are dropped.
+/// - Each run of anything else, including `/`, spaces, dots and
+///   underscores, becomes a single `-`.
+/// - Leading and trailing `-` are trimmed, so `.hidden` becomes `hidden`.
+///
+/// Distinct branches can therefore share a directory name (`feature/foo`
+/// and `Feature_Foo` are both `feature-foo`).
+pub fn sanitize_branch_to_dir(branch: &str) -> String {
+    slug::slugify(branch)
+}
 
 /// Check if a worktree already exists for a branch
 pub fn worktree_exists(branch_name: &str) -> Result<bool> {
-    match get_worktree_path(branch_name) {
+    match resolve_branch_worktree(&list_worktrees()?, branch_name, crate::util::canon_or_self) {
         Ok(_) => Ok(true),
         Err(e) => {
             // Check if this is a WorktreeNotFound error
@@ -58,6 +74,22 @@ pub fn create_worktree(
     Ok(())
 }
 
+/// Move a worktree to a new directory (`git worktree move`).
+pub fn move_worktree_in(from: &Path, to: &Path, git_common_dir: &Path) -> Result<()> {
+    let from_str = from
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid worktree path"))?;
+    let to_str = to
+        .to_str()
+        .ok_or_else(|| anyhow!("Invalid worktree path"))?;
+    Cmd::new("git")
+        .workdir(git_common_dir)
+        .args(&["worktree", "move", from_str, to_str])
+        .run()
+        .context("Failed to move worktree")?;
+    Ok(())
+}
+
 /// Prune stale worktree metadata.
 pub fn prune_worktrees_in(git_common_dir: &Path) -> Result<()> {
     Cmd::new("git")
@@ -68,58 +100,132 @@ pub fn prune_worktrees_in(git_common_dir: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Parse the output of `git worktree list --porcelain`
-pub(super) fn parse_worktree_list_porcelain(output: &str) -> Result<Vec<(PathBuf, String)>> {
-    let mut worktrees = Vec::new();
-    for block in output.trim().split("\n\n") {
-        let mut path: Option<PathBuf> = None;
-        let mut branch: Option<String> = None;
-
-        for line in block.lines() {
-            if let Some(p) = line.strip_prefix("worktree ") {
-                path = Some(PathBuf::from(p));
-            } else if let Some(b) = line.strip_prefix("branch refs/heads/") {
-                branch = Some(b.to_string());
-            } else if line.trim() == "detached" {
-                branch = Some("(detached)".to_string());
+/// Parse the output of `git worktree list --porcelain`: one block per
+/// worktree, separated by blank lines.
+pub(super) fn parse_worktree_list_porcelain(output: &str) -> Vec<WorktreeEntry> {
+    output
+        .trim()
+        .split("\n\n")
+        .filter_map(|block| {
+            let mut entry = WorktreeEntry::default();
+            let mut path = None;
+            for line in block.lines() {
+                let (key, value) = line.split_once(' ').unwrap_or((line.trim(), ""));
+                match key {
+                    "worktree" => path = Some(PathBuf::from(value)),
+                    "HEAD" => entry.head = Some(value.to_string()),
+                    "branch" => entry.branch = value.strip_prefix("refs/heads/").map(String::from),
+                    "bare" => entry.is_bare = true,
+                    "detached" => entry.is_detached = true,
+                    // Followed by the reason, when one was given
+                    "locked" => entry.is_locked = true,
+                    _ => {}
+                }
             }
-        }
+            entry.path = path?;
+            Some(entry)
+        })
+        .collect()
+}
 
-        if let (Some(p), Some(b)) = (path, branch) {
-            worktrees.push((p, b));
-        }
+/// List worktrees of the repository containing `workdir` (or the current
+/// directory).
+///
+/// Fails with [`NotInRepository`] outside a repository, so callers can tell
+/// that apart from a missing worktree. Works from any worktree as well as
+/// from a bare-repo management directory, since `git worktree list` reports
+/// every worktree sharing the same common dir.
+fn list_worktrees_in(workdir: Option<&Path>) -> Result<Vec<WorktreeEntry>> {
+    let check = Cmd::new("git").args(&["rev-parse", "--git-dir"]);
+    let list = Cmd::new("git").args(&["worktree", "list", "--porcelain"]);
+    let (check, list) = match workdir {
+        Some(path) => (check.workdir(path), list.workdir(path)),
+        None => (check, list),
+    };
+
+    if !check.run_as_check()? {
+        return Err(NotInRepository.into());
     }
-    Ok(worktrees)
+
+    let list = list
+        .run_and_capture_stdout()
+        .context("Failed to list worktrees")?;
+    Ok(parse_worktree_list_porcelain(&list))
 }
 
 /// Get the path to a worktree for a given branch
+///
+/// Matches the branch exactly; the sanitized-directory fallback is only
+/// for names a user typed (see [`find_worktree`]).
 pub fn get_worktree_path(branch_name: &str) -> Result<PathBuf> {
-    let list_str = Cmd::new("git")
-        .args(&["worktree", "list", "--porcelain"])
-        .run_and_capture_stdout()
-        .context("Failed to list worktrees while locating worktree path")?;
+    get_worktree_path_in(branch_name, None)
+}
 
-    let worktrees = parse_worktree_list_porcelain(&list_str)?;
+/// Get the path to a worktree for a given branch, in the repository
+/// containing `workdir`
+pub fn get_worktree_path_in(branch_name: &str, workdir: Option<&Path>) -> Result<PathBuf> {
+    resolve_branch_worktree(
+        &list_worktrees_in(workdir)?,
+        branch_name,
+        crate::util::canon_or_self,
+    )
+}
 
-    for (path, branch) in worktrees {
-        if branch == branch_name {
-            return Ok(path);
-        }
+/// Pick the worktree of `branch_name` (matched case-sensitively, as git
+/// does) and resolve its directory on disk with `canonicalize`.
+///
+/// On a case-insensitive filesystem (the macOS default) git can record a
+/// worktree under a different case than its directory has, and worktrees
+/// of branches differing only by case can be the same directory. The
+/// first is fixed up to the real spelling; the second is an error rather
+/// than silently handing out the other branch's worktree.
+fn resolve_branch_worktree(
+    worktrees: &[WorktreeEntry],
+    branch_name: &str,
+    canonicalize: impl Fn(&Path) -> PathBuf,
+) -> Result<PathBuf> {
+    let checkouts = || {
+        worktrees
+            .iter()
+            .filter_map(|wt| Some((&wt.path, wt.branch_label()?)))
+    };
+    let Some((path, _)) = checkouts().find(|(_, branch)| *branch == branch_name) else {
+        return Err(WorktreeNotFound(branch_name.to_string()).into());
+    };
+    let real = canonicalize(path);
+
+    if let Some((_, other)) =
+        checkouts().find(|(p, branch)| *branch != branch_name && canonicalize(p) == real)
+    {
+        return Err(anyhow!(
+            "Branches '{}' and '{}' share the worktree directory {}: the filesystem is \
+             case-insensitive, so their worktrees collide. Rename one of the branches.",
+            branch_name,
+            other,
+            real.display()
+        ));
     }
 
-    Err(WorktreeNotFound(branch_name.to_string()).into())
+    // Only take the canonical path when it fixes the case; symlinked
+    // locations stay as git recorded them
+    if real.as_os_str().eq_ignore_ascii_case(path.as_os_str()) {
+        Ok(real)
+    } else {
+        Ok(path.clone())
+    }
 }
 
 /// Find a worktree by handle (directory name) or branch name.
 /// Tries handle first, then falls back to branch lookup.
 /// Returns both the path and the branch name checked out in that worktree.
 pub fn find_worktree(name: &str) -> Result<(PathBuf, String)> {
-    let list_str = Cmd::new("git")
-        .args(&["worktree", "list", "--porcelain"])
-        .run_and_capture_stdout()
-        .context("Failed to list worktrees")?;
+    find_worktree_in(name, None)
+}
 
-    let worktrees = parse_worktree_list_porcelain(&list_str)?;
+/// Find a worktree by handle or branch name, in the repository containing
+/// `workdir`
+pub fn find_worktree_in(name: &str, workdir: Option<&Path>) -> Result<(PathBuf, String)> {
+    let worktrees = checkouts(list_worktrees_in(workdir)?);
 
     // First: try to match by handle (directory name)
     for (path, branch) in &worktrees {
@@ -131,22 +237,63 @@ pub fn find_worktree(name: &str) -> Result<(PathBuf, String)> {
     }
 
     // Fallback: try to match by branch name
-    for (path, branch) in worktrees {
-        if branch == name {
-            return Ok((path, branch));
-        }
+    if let Some(found) = worktrees.iter().find(|(_, branch)| branch == name) {
+        return Ok(found.clone());
     }
 
-    Err(WorktreeNotFound(name.to_string()).into())
+    // Last resort: the directory the name would have been sanitized to
+    find_by_sanitized_dir(&worktrees, name)?
+        .ok_or_else(|| WorktreeNotFound(name.to_string()).into())
 }
 
-/// List all worktrees with their branches
-pub fn list_worktrees() -> Result<Vec<(PathBuf, String)>> {
-    let list = Cmd::new("git")
-        .args(&["worktree", "list", "--porcelain"])
-        .run_and_capture_stdout()
-        .context("Failed to list worktrees")?;
-    parse_worktree_list_porcelain(&list)
+/// The checkout whose directory is named `sanitize_branch_to_dir(name)`.
+///
+/// Worktrees of different branches can end up with the same directory
+/// name under different parents (e.g. after `worktree_dir` changed); that
+/// is an error rather than a guess.
+fn find_by_sanitized_dir(
+    worktrees: &[(PathBuf, String)],
+    name: &str,
+) -> Result<Option<(PathBuf, String)>> {
+    let dir = sanitize_branch_to_dir(name);
+    if dir.is_empty() {
+        return Ok(None);
+    }
+    let mut matches = worktrees
+        .iter()
+        .filter(|(path, _)| path.file_name().is_some_and(|n| n.to_string_lossy() == dir));
+    let Some(found) = matches.next() else {
+        return Ok(None);
+    };
+    if let Some((_, other)) = matches.next() {
+        return Err(anyhow!(
+            "'{}' is ambiguous: the worktrees of branches '{}' and '{}' are both in a \
+             directory named '{}'. Use the full branch name.",
+            name,
+            found.1,
+            other,
+            dir
+        ));
+    }
+    Ok(Some(found.clone()))
+}
+
+/// List all worktrees of the current repository, including the bare
+/// repository itself in a bare-repo setup
+pub fn list_worktrees() -> Result<Vec<WorktreeEntry>> {
+    list_worktrees_in(None)
+}
+
+/// The worktrees that have something checked out, as `(path, branch)` with
+/// `(detached)` for a detached HEAD. Leaves out a bare repository.
+pub fn checkouts(worktrees: Vec<WorktreeEntry>) -> Vec<(PathBuf, String)> {
+    worktrees
+        .into_iter()
+        .filter_map(|wt| {
+            let branch = wt.branch_label()?.to_string();
+            Some((wt.path, branch))
+        })
+        .collect()
 }
 
 /// Store per-worktree metadata in git config.
@@ -223,6 +370,33 @@ pub fn get_all_worktree_modes() -> std::collections::HashMap<String, MuxMode> {
     modes
 }
 
+/// Move all metadata of a worktree handle to a new handle. A handle without
+/// metadata is left alone.
+pub fn rename_worktree_meta(handle: &str, new_handle: &str) -> Result<()> {
+    let has_meta = Cmd::new("git")
+        .args(&[
+            "config",
+            "--local",
+            "--get-regexp",
+            &format!(r"^workmux\.worktree\.{}\.", regex::escape(handle)),
+        ])
+        .run_as_check()?;
+    if !has_meta {
+        return Ok(());
+    }
+    Cmd::new("git")
+        .args(&[
+            "config",
+            "--local",
+            "--rename-section",
+            &format!("workmux.worktree.{}", handle),
+            &format!("workmux.worktree.{}", new_handle),
+        ])
+        .run()
+        .with_context(|| format!("Failed to move worktree metadata of '{}'", handle))?;
+    Ok(())
+}
+
 /// Remove all metadata for a worktree handle.
 pub fn remove_worktree_meta(handle: &str) -> Result<()> {
     // Use --remove-section to remove all keys under the handle's section
@@ -246,34 +420,20 @@ pub fn get_main_worktree_root() -> Result<PathBuf> {
         .args(&["worktree", "list", "--porcelain"])
         .run_and_capture_stdout()
         .context("Failed to list worktrees while locating main worktree")?;
-
-    // Check if this is a bare repo setup.
-    // The first entry in `git worktree list` is always the main worktree or bare repo.
-    // For bare repos, it looks like:
-    //   worktree /path/to/.bare
-    //   bare
-    if let Some(first_block) = list_str.trim().split("\n\n").next() {
-        let mut path: Option<PathBuf> = None;
-        let mut is_bare = false;
-
-        for line in first_block.lines() {
-            if let Some(p) = line.strip_prefix("worktree ") {
-                path = Some(PathBuf::from(p));
-            } else if line.trim() == "bare" {
-                is_bare = true;
-            }
-        }
-
-        // If this is a bare repo, return its path immediately.
-        // Git commands like `git worktree prune` work correctly from bare repo directories.
-        if is_bare && let Some(p) = path {
-            return Ok(p);
-        }
+    let worktrees = parse_worktree_list_porcelain(&list_str);
+
+    // The first entry in `git worktree list` is always the main worktree or
+    // bare repo. If this is a bare repo, return its path immediately.
+    // Git commands like `git worktree prune` work correctly from bare repo directories.
+    if let Some(first) = worktrees.first()
+        && first.is_bare
+    {
+        return Ok(first.path.clone());
     }
 
     // Not a bare repo - find the first worktree that exists on disk.
     // This handles edge cases where a worktree was deleted but not yet pruned.
-    let worktrees = parse_worktree_list_porcelain(&list_str)?;
+    let worktrees = checkouts(worktrees);
 
     for (path, _) in &worktrees {
         if path.exists() {
@@ -288,3 +448,255 @@ pub fn get_main_worktree_root() -> Result<PathBuf> {
         Err(anyhow!("No main worktree found"))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+    use tempfile::TempDir;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    /// A repo at `<tmp>/repo` with worktrees `<tmp>/wt-a` and `<tmp>/wt-b`.
+    fn fixture() -> (TempDir, PathBuf) {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let repo = root.join("repo");
+        std::fs::create_dir(&repo).unwrap();
+        git(&repo, &["init", "-b", "main"]);
+        git(&repo, &["commit", "--allow-empty", "-m", "init"]);
+        git(&repo, &["worktree", "add", "-b", "feature-a", "../wt-a"]);
+        git(&repo, &["worktree", "add", "-b", "feature-b", "../wt-b"]);
+        (temp, root)
+    }
+
+    #[test]
+    fn resolves_worktrees_from_any_worktree() {
+        let (_temp, root) = fixture();
+
+        for from in [root.join("repo"), root.join("wt-a")] {
+            assert_eq!(
+                get_worktree_path_in("feature-b", Some(&from)).unwrap(),
+                root.join("wt-b")
+            );
+            assert_eq!(
+                find_worktree_in("wt-a", Some(&from)).unwrap(),
+                (root.join("wt-a"), "feature-a".to_string())
+            );
+        }
+    }
+
+    fn worktrees(entries: &[(&str, &str)]) -> Vec<WorktreeEntry> {
+        entries
+            .iter()
+            .map(|(path, branch)| WorktreeEntry {
+                path: PathBuf::from(path),
+                branch: Some(branch.to_string()),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_porcelain_handles_bare_detached_and_locked_worktrees() {
+        let output = "\
+worktree /repo/.bare
+bare
+
+worktree /repo/main
+HEAD 1111111111111111111111111111111111111111
+branch refs/heads/main
+
+worktree /repo/review
+HEAD 2222222222222222222222222222222222222222
+detached
+
+worktree /repo/feature with spaces
+HEAD 3333333333333333333333333333333333333333
+branch refs/heads/feature/x
+locked moved to a USB drive
+prunable gitdir file points to non-existent location
+";
+        let entries = parse_worktree_list_porcelain(output);
+        assert_eq!(
+            entries,
+            vec![
+                WorktreeEntry {
+                    path: PathBuf::from("/repo/.bare"),
+                    is_bare: true,
+                    ..Default::default()
+                },
+                WorktreeEntry {
+                    path: PathBuf::from("/repo/main"),
+                    branch: Some("main".to_string()),
+                    head: Some("1".repeat(40)),
+                    ..Default::default()
+                },
+                WorktreeEntry {
+                    path: PathBuf::from("/repo/review"),
+                    head: Some("2".repeat(40)),
+                    is_detached: true,
+                    ..Default::default()
+                },
+                WorktreeEntry {
+                    path: PathBuf::from("/repo/feature with spaces"),
+                    branch: Some("feature/x".to_string()),
+                    head: Some("3".repeat(40)),
+                    is_locked: true,
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            checkouts(entries),
+            vec![
+                (PathBuf::from("/repo/main"), "main".to_string()),
+                (PathBuf::from("/repo/review"), "(detached)".to_string()),
+                (
+                    PathBuf::from("/repo/feature with spaces"),
+                    "feature/x".to_string()
+                ),
+            ]
+        );
+        assert!(parse_worktree_list_porcelain("").is_empty());
+    }
+
+    #[test]
+    fn branches_differing_by_case_resolve_distinctly_on_case_sensitive_fs() {
+        let list = worktrees(&[("/wt/Feature", "Feature"), ("/wt/feature", "feature")]);
+        let identity = |p: &Path| p.to_path_buf();
+
+        assert_eq!(
+            resolve_branch_worktree(&list, "Feature", identity).unwrap(),
+            PathBuf::from("/wt/Feature")
+        );
+        assert_eq!(
+            resolve_branch_worktree(&list, "feature", identity).unwrap(),
+            PathBuf::from("/wt/feature")
+        );
+        let err = resolve_branch_worktree(&list, "FEATURE", identity).unwrap_err();
+        assert!(err.is::<WorktreeNotFound>());
+    }
+
+    #[test]
+    fn branches_differing_by_case_collide_on_case_insensitive_fs() {
+        // Both recorded paths are the one directory spelled `Feature`
+        let on_disk = |p: &Path| p.with_file_name("Feature");
+        let list = worktrees(&[("/wt/Feature", "Feature"), ("/wt/feature", "feature")]);
+
+        for branch in ["Feature", "feature"] {
+            let err = resolve_branch_worktree(&list, branch, on_disk).unwrap_err();
+            assert!(
+                err.to_string().contains("case-insensitive"),
+                "{branch}: {err:#}"
+            );
+        }
+
+        // A lone worktree recorded in the wrong case gets its real spelling
+        let list = worktrees(&[("/wt/feature", "feature")]);
+        assert_eq!(
+            resolve_branch_worktree(&list, "feature", on_disk).unwrap(),
+            PathBuf::from("/wt/Feature")
+        );
+    }
+
+    #[test]
+    fn sanitize_branch_to_dir_rules() {
+        for (branch, dir) in [
+            ("feature/foo", "feature-foo"),
+            ("Feature/Foo Bar", "feature-foo-bar"),
+            (".hidden", "hidden"),
+            ("fix/.dotfile", "fix-dotfile"),
+            ("user//double__sep..x", "user-double-sep-x"),
+            ("café/straße", "cafe-strasse"),
+            ("ticket-42", "ticket-42"),
+        ] {
+            assert_eq!(sanitize_branch_to_dir(branch), dir, "{branch}");
+            // Sanitizing a directory name leaves it alone
+            assert_eq!(sanitize_branch_to_dir(dir), dir);
+        }
+    }
+
+    #[test]
+    fn branch_with_slash_is_found_by_sanitized_dir_only_in_find_worktree() {
+        let (_temp, root) = fixture();
+        let repo = root.join("repo");
+        let dir = sanitize_branch_to_dir("feature/foo");
+        git(
+            &repo,
+            &["worktree", "add", "-b", "feature/foo", &format!("../{dir}")],
+        );
+        let expected = (root.join("feature-foo"), "feature/foo".to_string());
+
+        for name in ["feature/foo", "feature-foo", "Feature/Foo"] {
+            assert_eq!(find_worktree_in(name, Some(&repo)).unwrap(), expected);
+        }
+        assert_eq!(
+            get_worktree_path_in("feature/foo", Some(&repo)).unwrap(),
+            expected.0
+        );
+        // A branch lookup never lands on another branch's worktree
+        for name in ["feature-foo", "Feature/Foo", "feature/bar"] {
+            let err = get_worktree_path_in(name, Some(&repo)).unwrap_err();
+            assert!(err.is::<WorktreeNotFound>(), "{name}: {err:#}");
+        }
+    }
+
+    #[test]
+    fn sanitized_dir_shared_by_two_branches_is_ambiguous() {
+        let list = checkouts(worktrees(&[
+            ("/old/feature-foo", "feature/foo"),
+            ("/new/feature-foo", "Feature_Foo"),
+        ]));
+
+        let err = find_by_sanitized_dir(&list, "feature-foo").unwrap_err();
+        assert!(err.to_string().contains("ambiguous"), "{err:#}");
+        assert!(err.to_string().contains("'Feature_Foo'"), "{err:#}");
+        assert_eq!(find_by_sanitized_dir(&list, "feature-bar").unwrap(), None);
+        assert_eq!(find_by_sanitized_dir(&list, "...").unwrap(), None);
+    }
+
+    #[test]
+    fn distinguishes_missing_worktree_from_missing_repo() {
+        let (_temp, root) = fixture();
+
+        let err = get_worktree_path_in("nope", Some(&root.join("repo"))).unwrap_err();
+        assert!(err.is::<WorktreeNotFound>(), "{err:#}");
+
+        let outside = TempDir::new().unwrap();
+        let err = get_worktree_path_in("feature-a", Some(outside.path())).unwrap_err();
+        assert!(err.is::<NotInRepository>(), "{err:#}");
+    }
+
+    #[test]
+    fn resolves_worktrees_from_bare_repo_management_dir() {
+        let (_temp, root) = fixture();
+        let project = root.join("project");
+        std::fs::create_dir(&project).unwrap();
+        git(
+            &root,
+            &[
+                "clone",
+                "--bare",
+                "repo",
+                &project.join(".bare").to_string_lossy(),
+            ],
+        );
+        std::fs::write(project.join(".git"), "gitdir: ./.bare\n").unwrap();
+        git(&project, &["worktree", "add", "feature-a", "feature-a"]);
+
+        assert_eq!(
+            get_worktree_path_in("feature-a", Some(&project)).unwrap(),
+            project.join("feature-a")
+        );
+    }
+}
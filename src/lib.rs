@@ -0,0 +1,30 @@
+//! workmux as a library: the CLI lives in [`cli`], and [`workflow`] exposes
+//! the operations behind it (e.g. [`workflow::list`]) for other tools.
+//! [`Workmux`] bundles the common ones behind a loaded config and
+//! multiplexer.
+
+mod agent_setup;
+mod claude;
+pub mod cli;
+mod cmd;
+mod command;
+pub mod config;
+mod facade;
+mod git;
+mod github;
+mod llm;
+mod logger;
+mod markdown;
+pub mod multiplexer;
+mod naming;
+mod nerdfont;
+mod prompt;
+mod sandbox;
+mod shell;
+mod spinner;
+mod state;
+mod template;
+mod util;
+pub mod workflow;
+
+pub use facade::{ListOptions, Workmux};
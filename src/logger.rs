@@ -3,6 +3,7 @@ use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_appender::rolling;
 use tracing_subscriber::prelude::*;
@@ -12,11 +13,47 @@ use crate::sandbox::guest::is_sandbox_guest;
 
 static INIT: OnceLock<()> = OnceLock::new();
 static GUARD: OnceLock<WorkerGuard> = OnceLock::new();
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Log line format for the log file.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq)]
+pub enum LogFormat {
+    /// Human-readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, with event fields as top-level keys
+    Json,
+}
+
+impl LogFormat {
+    /// Resolve the format from the `--log-format` flag, falling back to
+    /// `WORKMUX_LOG_FORMAT`.
+    pub fn resolve(flag: Option<LogFormat>) -> Result<Self> {
+        if let Some(format) = flag {
+            return Ok(format);
+        }
+        match std::env::var("WORKMUX_LOG_FORMAT") {
+            Ok(value) if !value.is_empty() => LogFormat::from_str(&value, true).map_err(|_| {
+                anyhow!(
+                    "Invalid WORKMUX_LOG_FORMAT '{}' (expected text or json)",
+                    value
+                )
+            }),
+            _ => Ok(Self::default()),
+        }
+    }
+}
 
-pub fn init() -> Result<()> {
+/// Initialize file logging.
+///
+/// `verbosity` is the `-v` count: 1 logs at debug, 2+ at trace, overriding
+/// `WORKMUX_LOG` and `RUST_LOG`. `quiet` (`-q`) logs only warnings and errors
+/// and overrides the environment.
+pub fn init(format: LogFormat, verbosity: u8, quiet: bool) -> Result<()> {
     if INIT.get().is_some() {
         return Ok(());
     }
+    let _ = FORMAT.set(format);
 
     // Skip file logging in sandbox guests - they're thin RPC clients and the
     // host supervisor handles all real logging. Also avoids needing to create
@@ -26,12 +63,12 @@ pub fn init() -> Result<()> {
         return Ok(());
     }
 
-    init_inner()?;
+    init_inner(format, verbosity, quiet)?;
     let _ = INIT.set(());
     Ok(())
 }
 
-fn init_inner() -> Result<()> {
+fn init_inner(format: LogFormat, verbosity: u8, quiet: bool) -> Result<()> {
     let log_path = determine_log_path()?;
     if let Some(parent) = log_path.parent() {
         fs::create_dir_all(parent)
@@ -43,22 +80,59 @@ fn init_inner() -> Result<()> {
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
     let _ = GUARD.set(guard);
 
-    let env_filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let directive = filter_directive(
+        quiet,
+        verbosity,
+        std::env::var("WORKMUX_LOG").ok(),
+        std::env::var("RUST_LOG").ok(),
+    );
+    let env_filter = EnvFilter::try_new(&directive).unwrap_or_else(|_| EnvFilter::new("info"));
+
+    let layer = fmt::layer()
+        .with_writer(non_blocking)
+        .with_ansi(false)
+        .with_target(false);
+    let layer = match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().flatten_event(true).boxed(),
+    };
 
     tracing_subscriber::registry()
         .with(env_filter)
-        .with(
-            fmt::layer()
-                .with_writer(non_blocking)
-                .with_ansi(false)
-                .with_target(false),
-        )
+        .with(layer)
         .try_init()
         .context("Failed to initialize tracing subscriber")?;
 
     Ok(())
 }
 
+/// The log format chosen at startup (text before logging is initialized).
+pub fn format() -> LogFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// Pick the filter directive: `-q` first, then `-v` flags, then
+/// `WORKMUX_LOG`, then `RUST_LOG`, then `info`.
+fn filter_directive(
+    quiet: bool,
+    verbosity: u8,
+    workmux_log: Option<String>,
+    rust_log: Option<String>,
+) -> String {
+    if quiet {
+        return "warn".to_string();
+    }
+    match verbosity {
+        0 => workmux_log
+            .into_iter()
+            .chain(rust_log)
+            .find(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| "info".to_string()),
+        1 => "debug".to_string(),
+        _ => "trace".to_string(),
+    }
+}
+
 fn determine_log_path() -> Result<PathBuf> {
     // Check XDG_STATE_HOME environment variable first
     if let Ok(state_home) = std::env::var("XDG_STATE_HOME")
@@ -95,3 +169,27 @@ fn split_path(path: &Path) -> Result<(PathBuf, &str)> {
 
     Ok((dir, file_name))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_directive_precedence() {
+        let some = |s: &str| Some(s.to_string());
+        assert_eq!(filter_directive(false, 0, None, None), "info");
+        assert_eq!(filter_directive(false, 0, None, some("warn")), "warn");
+        assert_eq!(
+            filter_directive(false, 0, some("debug"), some("warn")),
+            "debug"
+        );
+        assert_eq!(filter_directive(false, 0, some(""), some("warn")), "warn");
+        assert_eq!(filter_directive(false, 1, some("warn"), None), "debug");
+        assert_eq!(filter_directive(false, 3, None, None), "trace");
+        assert_eq!(
+            filter_directive(true, 0, some("debug"), some("trace")),
+            "warn"
+        );
+        assert_eq!(filter_directive(true, 2, None, None), "warn");
+    }
+}
@@ -1,34 +1,9 @@
-mod agent_setup;
-mod claude;
-mod cli;
-mod cmd;
-mod command;
-mod config;
-mod git;
-mod github;
-mod llm;
-mod logger;
-mod markdown;
-mod multiplexer;
-mod naming;
-mod nerdfont;
-mod prompt;
-mod sandbox;
-mod shell;
-mod spinner;
-mod state;
-mod template;
-mod util;
-mod workflow;
-
 use anyhow::Result;
 use tracing::{error, info};
 
 fn main() -> Result<()> {
-    logger::init()?;
-    info!(args = ?std::env::args().collect::<Vec<_>>(), "workmux start");
-
-    match cli::run() {
+    // Logging is initialized by cli::run once --log-format is parsed
+    match workmux::cli::run() {
         Ok(result) => {
             info!("workmux finished successfully");
             Ok(result)
@@ -87,7 +87,10 @@ struct FlatPane {
 /// Relies on inherited KITTY_WINDOW_ID and KITTY_LISTEN_ON environment variables.
 /// Requires kitty configuration with `allow_remote_control yes` and `listen_on`.
 #[derive(Debug)]
-pub struct KittyBackend;
+pub struct KittyBackend {
+    /// Program (and leading arguments) that `@` commands are passed to
+    kitten: Vec<String>,
+}
 
 impl Default for KittyBackend {
     fn default() -> Self {
@@ -98,12 +101,27 @@ impl Default for KittyBackend {
 impl KittyBackend {
     /// Create a new KittyBackend instance.
     pub fn new() -> Self {
-        Self
+        Self {
+            kitten: vec!["kitten".to_string()],
+        }
+    }
+
+    /// A backend that sends remote control commands to `program args... @`
+    /// instead of `kitten @`, so tests can stand in a script for kitty.
+    #[cfg(test)]
+    fn with_kitten(program: &str, args: &[&str]) -> Self {
+        Self {
+            kitten: std::iter::once(program)
+                .chain(args.iter().copied())
+                .map(str::to_string)
+                .collect(),
+        }
     }
 
     /// Create a kitten @ command.
-    fn kitten_cmd(&self) -> Cmd<'static> {
-        Cmd::new("kitten").arg("@")
+    fn kitten_cmd(&self) -> Cmd<'_> {
+        let leading: Vec<&str> = self.kitten[1..].iter().map(String::as_str).collect();
+        Cmd::new(&self.kitten[0]).args(&leading).arg("@")
     }
 
     /// Query all windows/tabs/panes as flat list.
@@ -184,7 +202,6 @@ impl KittyBackend {
     }
 
     /// Set the tab title for a window.
-    #[allow(dead_code)]
     fn set_tab_title(&self, window_id: &str, title: &str) -> Result<()> {
         self.kitten_cmd()
             .args(&[
@@ -369,6 +386,16 @@ impl Multiplexer for KittyBackend {
         Ok(window_id)
     }
 
+    fn rename_window(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        let panes = self.list_panes()?;
+        let pane = self
+            .panes_in_current_scope(&panes)
+            .into_iter()
+            .find(|p| p.tab_title == full_name)
+            .ok_or_else(|| anyhow!("No tab named '{}'", full_name))?;
+        self.set_tab_title(&pane.window_id.to_string(), new_full_name)
+    }
+
     fn kill_window(&self, full_name: &str) -> Result<()> {
         let panes = self.list_panes()?;
         let scoped_panes = self.panes_in_current_scope(&panes);
@@ -618,17 +645,20 @@ impl Multiplexer for KittyBackend {
         Ok(new_pane_id)
     }
 
-    fn capture_pane(&self, pane_id: &str, lines: u16) -> Option<String> {
+    fn capture_pane(&self, pane_id: &str, range: CaptureRange) -> Result<String> {
+        let target = format!("id:{}", pane_id);
+        let mut args = vec!["get-text", "--match", &target, "--ansi"];
+        if !matches!(range, CaptureRange::Lines(_)) {
+            args.extend(["--extent", "all"]);
+        }
         let output = self
             .kitten_cmd()
-            .args(&["get-text", "--match", &format!("id:{}", pane_id), "--ansi"])
+            .args(&args)
             .run_and_capture_stdout()
-            .ok()?;
+            .with_context(|| format!("Failed to capture kitty window {}", pane_id))?;
 
         // get-text returns all visible content; take last N lines
-        let all_lines: Vec<&str> = output.lines().collect();
-        let start = all_lines.len().saturating_sub(lines as usize);
-        Some(all_lines[start..].join("\n"))
+        Ok(range.tail(&output))
     }
 
     // === Text I/O ===
@@ -649,6 +679,20 @@ impl Multiplexer for KittyBackend {
         Ok(())
     }
 
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        let target = format!("id:{}", pane_id);
+        let mut args = vec!["send-text", "--match", &target];
+        if text.contains('\n') {
+            args.push("--bracketed-paste");
+        }
+        args.extend(["--", text]);
+        self.kitten_cmd()
+            .args(&args)
+            .run()
+            .context("Failed to send text to pane")?;
+        Ok(())
+    }
+
     fn send_keys_to_agent(&self, pane_id: &str, command: &str, agent: Option<&str>) -> Result<()> {
         if agent::resolve_profile(agent).needs_bang_delay() && command.starts_with('!') {
             // Send ! first
@@ -894,9 +938,120 @@ impl Multiplexer for KittyBackend {
 mod tests {
     use super::*;
 
+    /// Stands in for `kitten`: logs each `@` command to `calls.log` and
+    /// answers `ls`, `get-text` and `launch` with canned output.
+    const FAKE_KITTEN: &str = r#"
+dir=$(dirname "$0")
+echo "$*" >> "$dir/calls.log"
+case "$2" in
+  ls) cat "$dir/ls.json" ;;
+  get-text) printf 'one\ntwo\nthree\n' ;;
+  launch) echo 42 ;;
+  focus-window) exit 1 ;;
+esac
+"#;
+
+    const LS: &str = r#"[{"id": 1, "is_focused": true, "tabs": [
+        {"id": 10, "title": "wm-feature", "is_active": true, "is_focused": true, "windows": [
+            {"id": 7, "title": "claude", "cwd": "/src/feature", "pid": 100,
+             "is_focused": false, "is_active": false,
+             "foreground_processes": [{"pid": 120, "cwd": "/src/feature", "cmdline": ["/usr/bin/claude"]}]},
+            {"id": 8, "title": "zsh", "cwd": "/src/feature", "pid": 200,
+             "is_focused": true, "is_active": true}
+        ]}
+    ]}]"#;
+
+    fn fake_kitty() -> (tempfile::TempDir, KittyBackend) {
+        let dir = tempfile::tempdir().unwrap();
+        let script = dir.path().join("kitten");
+        std::fs::write(&script, FAKE_KITTEN).unwrap();
+        std::fs::write(dir.path().join("ls.json"), LS).unwrap();
+        // Run through `sh` so the script needn't be executable
+        let backend = KittyBackend::with_kitten("sh", &[script.to_str().unwrap()]);
+        (dir, backend)
+    }
+
+    fn calls(dir: &tempfile::TempDir) -> Vec<String> {
+        std::fs::read_to_string(dir.path().join("calls.log"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
     #[test]
     fn test_kitty_backend_name() {
         let backend = KittyBackend::new();
         assert_eq!(backend.name(), "kitty");
     }
+
+    #[test]
+    fn panes_come_from_kitten_ls() {
+        let (_dir, kitty) = fake_kitty();
+        assert!(kitty.is_running().unwrap());
+        assert_eq!(kitty.active_pane_id().as_deref(), Some("8"));
+        assert!(kitty.window_exists_by_full_name("wm-feature").unwrap());
+
+        let info = kitty.get_live_pane_info("7").unwrap().unwrap();
+        assert_eq!(info.pid, Some(120));
+        assert_eq!(info.current_command.as_deref(), Some("claude"));
+        assert_eq!(info.window.as_deref(), Some("wm-feature"));
+        assert!(kitty.get_live_pane_info("99").unwrap().is_none());
+        assert!(kitty.get_live_pane_info("%1").unwrap().is_none());
+    }
+
+    #[test]
+    fn capture_uses_get_text() {
+        let (dir, kitty) = fake_kitty();
+        assert_eq!(
+            kitty.capture_pane("7", CaptureRange::Lines(2)).unwrap(),
+            "two\nthree"
+        );
+        kitty.capture_pane("7", CaptureRange::All).unwrap();
+        assert_eq!(
+            calls(&dir),
+            [
+                "@ get-text --match id:7 --ansi",
+                "@ get-text --match id:7 --ansi --extent all",
+            ]
+        );
+    }
+
+    #[test]
+    fn window_and_status_operations_use_remote_control() {
+        let (dir, kitty) = fake_kitty();
+        let pane = kitty
+            .split_pane(
+                "7",
+                &SplitDirection::Horizontal,
+                Path::new("/src/feature"),
+                None,
+                None,
+                Some("make test"),
+            )
+            .unwrap();
+        assert_eq!(pane, "42");
+        kitty.set_status("7", "🤖", true).unwrap();
+        kitty.clear_status("7").unwrap();
+
+        assert_eq!(
+            calls(&dir),
+            [
+                "@ launch --location vsplit --match id:7 --cwd /src/feature sh -c make test",
+                "@ set-user-vars --match id:7 workmux_status=🤖",
+                "@ set-user-vars --match id:7 workmux_auto_clear=1",
+                "@ set-user-vars --match id:7 workmux_status=",
+            ]
+        );
+    }
+
+    #[test]
+    fn unsupported_and_failing_operations_are_errors() {
+        let (_dir, kitty) = fake_kitty();
+        let err = kitty.switch_to_session("wm-", "feature").unwrap_err();
+        assert!(err.to_string().contains("not supported in Kitty"));
+
+        let err = kitty.select_pane("7").unwrap_err();
+        assert!(format!("{err:#}").contains("Failed to focus window"));
+    }
 }
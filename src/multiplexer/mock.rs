@@ -0,0 +1,329 @@
+//! In-memory [`Multiplexer`] for unit tests of command modules.
+//!
+//! Panes are byte strings returned by `capture_pane`; everything else is
+//! recorded in [`MockMultiplexer::calls`] and succeeds without doing anything.
+
+use anyhow::{Result, bail};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use super::{
+    CaptureRange, CreateSessionParams, CreateWindowParams, LivePaneInfo, Multiplexer, PaneHandshake,
+};
+use crate::config::SplitDirection;
+
+#[derive(Default)]
+pub struct MockMultiplexer {
+    /// Returned by `current_pane_id` and `active_pane_id`
+    current_pane: Option<String>,
+    /// Pane ids and their contents, in layout order
+    panes: Vec<(String, Vec<u8>)>,
+    roles: Mutex<HashMap<String, String>>,
+    calls: Mutex<Vec<String>>,
+}
+
+impl MockMultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `pane_id` the pane this process runs in.
+    pub fn with_current_pane(mut self, pane_id: &str) -> Self {
+        self.current_pane = Some(pane_id.to_string());
+        self
+    }
+
+    /// Add a pane to the (single) window, showing `content`.
+    pub fn with_pane(self, pane_id: &str, content: &str) -> Self {
+        self.with_pane_bytes(pane_id, content.as_bytes())
+    }
+
+    /// Like [`MockMultiplexer::with_pane`], for content that isn't UTF-8.
+    pub fn with_pane_bytes(mut self, pane_id: &str, content: &[u8]) -> Self {
+        self.panes.push((pane_id.to_string(), content.to_vec()));
+        self
+    }
+
+    /// Tag `pane_id` with `role`, as a layout with `panes[].role` would.
+    pub fn with_role(self, pane_id: &str, role: &str) -> Self {
+        self.roles
+            .lock()
+            .unwrap()
+            .insert(pane_id.to_string(), role.to_string());
+        self
+    }
+
+    /// Every call that changed something, e.g. `set_status %1 🤖`.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+}
+
+impl Multiplexer for MockMultiplexer {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn is_running(&self) -> Result<bool> {
+        Ok(true)
+    }
+
+    fn current_pane_id(&self) -> Option<String> {
+        self.current_pane.clone()
+    }
+
+    fn active_pane_id(&self) -> Option<String> {
+        self.current_pane.clone()
+    }
+
+    fn get_client_active_pane_path(&self) -> Result<PathBuf> {
+        bail!("MockMultiplexer has no client")
+    }
+
+    fn create_window(&self, params: CreateWindowParams) -> Result<String> {
+        self.record(format!("create_window {}{}", params.prefix, params.name));
+        Ok(format!("%{}", self.panes.len() + 1))
+    }
+
+    fn create_session(&self, params: CreateSessionParams) -> Result<String> {
+        self.record(format!("create_session {}{}", params.prefix, params.name));
+        Ok(format!("%{}", self.panes.len() + 1))
+    }
+
+    fn switch_to_session(&self, prefix: &str, name: &str) -> Result<()> {
+        self.record(format!("switch_to_session {prefix}{name}"));
+        Ok(())
+    }
+
+    fn session_exists(&self, _full_name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn kill_session(&self, full_name: &str) -> Result<()> {
+        self.record(format!("kill_session {full_name}"));
+        Ok(())
+    }
+
+    fn kill_window(&self, full_name: &str) -> Result<()> {
+        self.record(format!("kill_window {full_name}"));
+        Ok(())
+    }
+
+    fn rename_window(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        self.record(format!("rename_window {full_name} {new_full_name}"));
+        Ok(())
+    }
+
+    fn rename_session(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        self.record(format!("rename_session {full_name} {new_full_name}"));
+        Ok(())
+    }
+
+    fn schedule_window_close(&self, full_name: &str, _delay: Duration) -> Result<()> {
+        self.record(format!("schedule_window_close {full_name}"));
+        Ok(())
+    }
+
+    fn schedule_session_close(&self, full_name: &str, _delay: Duration) -> Result<()> {
+        self.record(format!("schedule_session_close {full_name}"));
+        Ok(())
+    }
+
+    fn run_deferred_script(&self, script: &str) -> Result<()> {
+        self.record(format!("run_deferred_script {script}"));
+        Ok(())
+    }
+
+    fn shell_select_window_cmd(&self, full_name: &str) -> Result<String> {
+        Ok(format!("select {full_name}"))
+    }
+
+    fn shell_kill_window_cmd(&self, full_name: &str) -> Result<String> {
+        Ok(format!("kill {full_name}"))
+    }
+
+    fn shell_switch_session_cmd(&self, full_name: &str) -> Result<String> {
+        Ok(format!("switch {full_name}"))
+    }
+
+    fn shell_kill_session_cmd(&self, full_name: &str) -> Result<String> {
+        Ok(format!("kill-session {full_name}"))
+    }
+
+    fn select_window(&self, prefix: &str, name: &str) -> Result<()> {
+        self.record(format!("select_window {prefix}{name}"));
+        Ok(())
+    }
+
+    fn window_exists(&self, _prefix: &str, _name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn window_exists_by_full_name(&self, _full_name: &str) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn current_window_name(&self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn get_all_window_names(&self) -> Result<HashSet<String>> {
+        Ok(HashSet::new())
+    }
+
+    fn get_all_session_names(&self) -> Result<HashSet<String>> {
+        Ok(HashSet::new())
+    }
+
+    fn filter_active_windows(&self, _windows: &[String]) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
+    fn find_last_window_with_prefix(&self, _prefix: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn find_last_window_with_base_handle(
+        &self,
+        _prefix: &str,
+        _base_handle: &str,
+    ) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn wait_until_windows_closed(&self, _full_window_names: &[String]) -> Result<()> {
+        Ok(())
+    }
+
+    fn wait_until_session_closed(&self, _full_session_name: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn select_pane(&self, pane_id: &str) -> Result<()> {
+        self.record(format!("select_pane {pane_id}"));
+        Ok(())
+    }
+
+    fn switch_to_pane(&self, pane_id: &str, _window_hint: Option<&str>) -> Result<()> {
+        self.record(format!("switch_to_pane {pane_id}"));
+        Ok(())
+    }
+
+    fn respawn_pane(&self, pane_id: &str, _cwd: &Path, cmd: Option<&str>) -> Result<String> {
+        self.record(format!("respawn_pane {pane_id} {}", cmd.unwrap_or("")));
+        Ok(pane_id.to_string())
+    }
+
+    fn capture_pane(&self, pane_id: &str, range: CaptureRange) -> Result<String> {
+        let content = self.capture_pane_bytes(pane_id, CaptureRange::All)?;
+        Ok(range.tail(&String::from_utf8_lossy(&content)))
+    }
+
+    fn capture_pane_bytes(&self, pane_id: &str, range: CaptureRange) -> Result<Vec<u8>> {
+        let Some((_, content)) = self.panes.iter().find(|(id, _)| id == pane_id) else {
+            bail!("pane {} no longer exists", pane_id);
+        };
+        let lines: Vec<&[u8]> = content.split(|&b| b == b'\n').collect();
+        let (start, end) = range.bounds(lines.len());
+        Ok(lines[start..end].join(&b'\n'))
+    }
+
+    fn set_pane_role(&self, pane_id: &str, role: &str) -> Result<()> {
+        self.record(format!("set_pane_role {pane_id} {role}"));
+        self.roles
+            .lock()
+            .unwrap()
+            .insert(pane_id.to_string(), role.to_string());
+        Ok(())
+    }
+
+    fn pane_role(&self, pane_id: &str) -> Option<String> {
+        self.roles.lock().unwrap().get(pane_id).cloned()
+    }
+
+    fn window_pane_ids(&self, _pane_id: &str) -> Result<Vec<String>> {
+        Ok(self.panes.iter().map(|(id, _)| id.clone()).collect())
+    }
+
+    fn send_keys(&self, pane_id: &str, command: &str) -> Result<()> {
+        self.record(format!("send_keys {pane_id} {command}"));
+        Ok(())
+    }
+
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        self.record(format!("send_text {pane_id} {text}"));
+        Ok(())
+    }
+
+    fn send_keys_to_agent(&self, pane_id: &str, command: &str, _agent: Option<&str>) -> Result<()> {
+        self.send_keys(pane_id, command)
+    }
+
+    fn send_key(&self, pane_id: &str, key: &str) -> Result<()> {
+        self.record(format!("send_key {pane_id} {key}"));
+        Ok(())
+    }
+
+    fn paste_multiline(&self, pane_id: &str, content: &str) -> Result<()> {
+        self.record(format!("paste_multiline {pane_id} {content}"));
+        Ok(())
+    }
+
+    fn set_buffer(&self, name: Option<&str>, content: &str) -> Result<()> {
+        self.record(format!("set_buffer {} {content}", name.unwrap_or("")));
+        Ok(())
+    }
+
+    fn get_default_shell(&self) -> Result<String> {
+        Ok("/bin/sh".to_string())
+    }
+
+    fn create_handshake(&self) -> Result<Box<dyn PaneHandshake>> {
+        bail!("MockMultiplexer can't spawn shells")
+    }
+
+    fn set_status(&self, pane_id: &str, icon: &str, _auto_clear_on_focus: bool) -> Result<()> {
+        self.record(format!("set_status {pane_id} {icon}"));
+        Ok(())
+    }
+
+    fn clear_status(&self, pane_id: &str) -> Result<()> {
+        self.record(format!("clear_status {pane_id}"));
+        Ok(())
+    }
+
+    fn ensure_status_format(&self, _pane_id: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn split_pane(
+        &self,
+        target_pane_id: &str,
+        _direction: &SplitDirection,
+        _cwd: &Path,
+        _size: Option<u16>,
+        _percentage: Option<u8>,
+        _command: Option<&str>,
+    ) -> Result<String> {
+        self.record(format!("split_pane {target_pane_id}"));
+        Ok(format!("%{}", self.panes.len() + 1))
+    }
+
+    fn instance_id(&self) -> String {
+        "mock".to_string()
+    }
+
+    fn get_live_pane_info(&self, _pane_id: &str) -> Result<Option<LivePaneInfo>> {
+        Ok(None)
+    }
+
+    fn get_all_live_pane_info(&self) -> Result<HashMap<String, LivePaneInfo>> {
+        Ok(HashMap::new())
+    }
+}
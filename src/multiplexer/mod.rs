@@ -7,16 +7,18 @@ pub mod agent;
 pub mod handle;
 pub mod handshake;
 pub mod kitty;
+#[cfg(test)]
+pub mod mock;
 pub mod tmux;
 pub mod types;
 pub mod util;
 pub mod wezterm;
 pub mod zellij;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
 pub use handle::MuxHandle;
@@ -55,6 +57,18 @@ pub trait Multiplexer: Send + Sync {
     /// Returns: Window identifier (pane ID for tmux/WezTerm, tab name for Zellij)
     fn create_window(&self, params: CreateWindowParams) -> Result<String>;
 
+    /// Open a background window named `name` (no prefix) that runs `command`
+    /// in `cwd`. Returns the ID of the pane running it.
+    fn new_window(&self, name: &str, cwd: &Path, command: &str) -> Result<String> {
+        let pane_id = self.create_window(CreateWindowParams {
+            prefix: "",
+            name,
+            cwd,
+            after_window: None,
+        })?;
+        self.respawn_pane(&pane_id, cwd, Some(command))
+    }
+
     /// Create a new session with the given parameters.
     /// Returns the initial pane ID of the new session.
     /// For backends that don't support sessions (e.g., WezTerm), this may create a workspace.
@@ -85,6 +99,24 @@ pub trait Multiplexer: Send + Sync {
     /// Kill a window by its full name (including prefix)
     fn kill_window(&self, full_name: &str) -> Result<()>;
 
+    /// Rename a window (tab) from one full name to another.
+    fn rename_window(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        let _ = (full_name, new_full_name);
+        Err(anyhow!(
+            "Renaming windows is not supported by the {} backend",
+            self.name()
+        ))
+    }
+
+    /// Rename a session from one full name to another.
+    fn rename_session(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        let _ = (full_name, new_full_name);
+        Err(anyhow!(
+            "Renaming sessions is not supported by the {} backend",
+            self.name()
+        ))
+    }
+
     /// Schedule a window to close after a delay
     fn schedule_window_close(&self, full_name: &str, delay: Duration) -> Result<()>;
 
@@ -176,11 +208,90 @@ pub trait Multiplexer: Send + Sync {
         true
     }
 
+    /// Attach the current terminal to the session containing `pane_id`,
+    /// focusing that pane. Used when running outside the multiplexer; blocks
+    /// until the client detaches.
+    fn attach_session(&self, pane_id: &str) -> Result<()> {
+        let _ = pane_id;
+        Err(anyhow!(
+            "Attaching from outside is not supported by the {} backend",
+            self.name()
+        ))
+    }
+
     /// Respawn a pane with optional command. Returns the (possibly new) pane ID.
     fn respawn_pane(&self, pane_id: &str, cwd: &Path, cmd: Option<&str>) -> Result<String>;
 
-    /// Capture the content of a pane
-    fn capture_pane(&self, pane_id: &str, lines: u16) -> Option<String>;
+    /// Capture the content of a pane. Errors say why it couldn't be read,
+    /// e.g. that the pane no longer exists.
+    fn capture_pane(&self, pane_id: &str, range: CaptureRange) -> Result<String>;
+
+    /// Like [`Multiplexer::capture_pane`], but the bytes exactly as the
+    /// backend returned them, which needn't be valid UTF-8. Backends that
+    /// only hand out text return that.
+    fn capture_pane_bytes(&self, pane_id: &str, range: CaptureRange) -> Result<Vec<u8>> {
+        self.capture_pane(pane_id, range).map(String::into_bytes)
+    }
+
+    /// Like [`Multiplexer::capture_pane`], but hands each line (without its
+    /// newline) to `line` as it's read, so a huge scrollback never has to
+    /// be held at once. Backends that can't stream capture everything first.
+    fn capture_pane_lines(
+        &self,
+        pane_id: &str,
+        range: CaptureRange,
+        line: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<()> {
+        self.capture_pane(pane_id, range)?
+            .lines()
+            .try_for_each(line)
+    }
+
+    /// List the IDs of every pane in the window (tab) containing `pane_id`,
+    /// in layout order.
+    fn window_pane_ids(&self, pane_id: &str) -> Result<Vec<String>> {
+        let _ = pane_id;
+        Err(anyhow!(
+            "Listing window panes is not supported by the {} backend",
+            self.name()
+        ))
+    }
+
+    /// Tag a pane with a layout role (`panes[].role`), for lookups with
+    /// [`pane_roles`](Self::pane_roles).
+    fn set_pane_role(&self, pane_id: &str, role: &str) -> Result<()> {
+        let _ = (pane_id, role);
+        Err(anyhow!(
+            "Pane roles are not supported by the {} backend",
+            self.name()
+        ))
+    }
+
+    /// The role a pane was tagged with, if any.
+    fn pane_role(&self, pane_id: &str) -> Option<String> {
+        let _ = pane_id;
+        None
+    }
+
+    /// List every pane in the window containing `pane_id` with its role, in
+    /// layout order.
+    fn pane_roles(&self, pane_id: &str) -> Result<Vec<(String, Option<String>)>> {
+        Ok(self
+            .window_pane_ids(pane_id)?
+            .into_iter()
+            .map(|id| {
+                let role = self.pane_role(&id);
+                (id, role)
+            })
+            .collect())
+    }
+
+    /// Get the size of a pane as (columns, rows).
+    ///
+    /// Returns None if the backend can't report pane dimensions.
+    fn pane_size(&self, _pane_id: &str) -> Option<(u16, u16)> {
+        None
+    }
 
     /// Whether this backend supports preview capture efficiently.
     /// Defaults to true. Override to return false for backends where preview capture
@@ -194,6 +305,13 @@ pub trait Multiplexer: Send + Sync {
     /// Send keys (command + Enter) to a pane
     fn send_keys(&self, pane_id: &str, command: &str) -> Result<()>;
 
+    /// Type text into a pane literally, without pressing Enter. Multi-line
+    /// text is pasted (bracketed paste where the backend supports it).
+    ///
+    /// Backends that pass `text` as a command-line argument put `--` before
+    /// it, so text starting with `-` isn't parsed as a flag.
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()>;
+
     /// Whether this backend requires focusing a pane before sending input to it.
     /// Defaults to false. Backends like Zellij that can't target unfocused panes
     /// override this to return true.
@@ -210,6 +328,16 @@ pub trait Multiplexer: Send + Sync {
     /// Paste multiline content to a pane (using bracketed paste)
     fn paste_multiline(&self, pane_id: &str, content: &str) -> Result<()>;
 
+    /// Load content into a paste buffer (`None` = the backend's unnamed buffer).
+    /// Only supported by backends with paste buffers (tmux).
+    fn set_buffer(&self, name: Option<&str>, content: &str) -> Result<()> {
+        let _ = (name, content);
+        Err(anyhow!(
+            "Paste buffers are not supported by the {} backend",
+            self.name()
+        ))
+    }
+
     /// Clear the pane screen. Default is no-op; backends override if needed.
     fn clear_pane(&self, _pane_id: &str) -> Result<()> {
         Ok(())
@@ -433,6 +561,12 @@ pub trait Multiplexer: Send + Sync {
                 )?
             };
 
+            if let Some(role) = &pane_config.role
+                && let Err(e) = self.set_pane_role(&pane_id, role)
+            {
+                tracing::warn!(pane_id = %pane_id, role = %role, error = %e, "failed to tag pane role");
+            }
+
             if is_first {
                 pane_ids[0] = pane_id.clone();
             } else {
@@ -516,61 +650,220 @@ pub trait Multiplexer: Send + Sync {
     }
 }
 
+/// Resolve an explicit backend override, if any.
+///
+/// `$WORKMUX_BACKEND` (or its alias `$WORKMUX_MUX`) wins over the
+/// `multiplexer.backend` config key. Returns `None` when nothing is set and
+/// an error listing the supported backends when the value is invalid.
+pub fn backend_override(config_backend: Option<&str>) -> Result<Option<BackendType>> {
+    let env = ["WORKMUX_BACKEND", "WORKMUX_MUX"]
+        .into_iter()
+        .find_map(|var| Some((var, std::env::var(var).ok().filter(|v| !v.is_empty())?)));
+
+    let (source, value) = match (env, config_backend) {
+        (Some((var, value)), _) => (format!("${}", var), value),
+        (None, Some(value)) => ("multiplexer.backend".to_string(), value.to_string()),
+        (None, None) => return Ok(None),
+    };
+
+    value
+        .parse()
+        .map(Some)
+        .map_err(|e: String| anyhow!("Invalid {}: {}", source, e))
+}
+
+/// Apply the configured backend override for this process.
+///
+/// Must run before the first call to [`current`]; without an override,
+/// [`current`] falls back to auto-detection.
+pub fn init(config: &Config) -> Result<()> {
+    if let Some(backend) = backend_override(config.multiplexer.backend.as_deref())? {
+        let _ = CURRENT.set(create_backend(backend));
+    }
+    Ok(())
+}
+
+/// The backend to use under `config`: its override if any, otherwise the
+/// detected one. `None` means a plain shell with no override.
+pub fn configured_backend(config: &Config) -> Result<Option<BackendType>> {
+    Ok(backend_override(config.multiplexer.backend.as_deref())?.or_else(detect_backend))
+}
+
 /// Detect which backend to use based on environment.
 ///
-/// Checks `$WORKMUX_BACKEND` first for an explicit override, then auto-detects
-/// from multiplexer environment variables. Session-specific variables (set only
-/// when inside the multiplexer) are checked before ambient variables (inherited
-/// from the parent terminal):
+/// Checks `$WORKMUX_BACKEND` / `$WORKMUX_MUX` first for an explicit override,
+/// then auto-detects from multiplexer environment variables. Session-specific
+/// variables (set only when inside the multiplexer) are checked before
+/// ambient variables (inherited from the parent terminal):
 ///
-/// 1. `$WORKMUX_BACKEND` set → use that backend
-/// 2. `$TMUX` set → tmux
+/// 1. `$WORKMUX_BACKEND` or `$WORKMUX_MUX` set → use that backend
+/// 2. `$TMUX` set and its server reachable → tmux
 /// 3. `$WEZTERM_PANE` set → WezTerm
 /// 4. `$ZELLIJ` set → Zellij
 /// 5. `$KITTY_WINDOW_ID` set → Kitty
-/// 6. None → defaults to tmux (for backward compatibility)
+/// 6. None → `None`: not inside any multiplexer (a plain shell)
 ///
 /// This ordering ensures that running tmux inside kitty (or wezterm) correctly
-/// selects the innermost multiplexer.
-pub fn detect_backend() -> BackendType {
-    if let Ok(val) = std::env::var("WORKMUX_BACKEND") {
-        match val.parse() {
-            Ok(bt) => return bt,
-            Err(_) => {
-                eprintln!(
-                    "workmux: invalid WORKMUX_BACKEND={val:?}, expected tmux|wezterm|kitty|zellij"
-                );
-            }
-        }
+/// selects the innermost multiplexer. Environment variables alone can't tell
+/// which of tmux and Zellij is nested in the other, so when both are set a
+/// warning (once per process) names the one chosen and how to override it.
+///
+/// `$TMUX` outlives a crashed server, so it only counts when its socket
+/// still accepts connections; otherwise detection carries on as if it were
+/// unset, with a warning.
+pub fn detect_backend() -> Option<BackendType> {
+    match backend_override(None) {
+        Ok(Some(backend)) => return Some(backend),
+        Ok(None) => {}
+        Err(e) => eprintln!("workmux: {e}"),
     }
 
-    resolve_backend(
-        std::env::var("TMUX").is_ok(),
+    let detected = detected_backends(
+        std::env::var("TMUX").is_ok_and(|tmux| {
+            let reachable = tmux_server_reachable(&tmux);
+            if !reachable {
+                static WARNED: std::sync::Once = std::sync::Once::new();
+                WARNED.call_once(|| {
+                    let warning = format!(
+                        "$TMUX is set but no tmux server is listening on {}; ignoring it",
+                        tmux_socket(&tmux)
+                    );
+                    tracing::warn!(tmux, "{}", warning);
+                    eprintln!("workmux: {warning}");
+                });
+            }
+            reachable
+        }),
         std::env::var("WEZTERM_PANE").is_ok(),
         std::env::var("ZELLIJ").is_ok(),
         std::env::var("KITTY_WINDOW_ID").is_ok(),
-    )
+    );
+    if let Some(warning) = nesting_warning(&detected) {
+        static WARNED: std::sync::Once = std::sync::Once::new();
+        WARNED.call_once(|| {
+            tracing::warn!(detected = ?detected, "{}", warning);
+            eprintln!("workmux: {warning}");
+        });
+    } else if detected.len() > 1 {
+        tracing::debug!(detected = ?detected, "multiplexer running inside a terminal backend");
+    }
+    detected.first().copied()
 }
 
-/// Pure auto-detection logic, separated for testability.
-fn resolve_backend(tmux: bool, wezterm: bool, zellij: bool, kitty: bool) -> BackendType {
-    if tmux {
-        return BackendType::Tmux;
-    }
+/// The server socket in a `$TMUX` value (`<socket>,<server pid>,<session>`).
+fn tmux_socket(tmux: &str) -> &str {
+    tmux.split(',').next().unwrap_or_default()
+}
+
+/// Whether the tmux server named by a `$TMUX` value is still running,
+/// judged by connecting to its socket. A value without a socket path can't
+/// be checked and is trusted.
+fn tmux_server_reachable(tmux: &str) -> bool {
+    let socket = tmux_socket(tmux);
+    socket.is_empty() || std::os::unix::net::UnixStream::connect(socket).is_ok()
+}
 
-    if wezterm {
-        return BackendType::WezTerm;
+/// Backends whose environment variables are set, in detection priority
+/// order. Pure auto-detection logic, separated for testability.
+fn detected_backends(tmux: bool, wezterm: bool, zellij: bool, kitty: bool) -> Vec<BackendType> {
+    [
+        (tmux, BackendType::Tmux),
+        (wezterm, BackendType::WezTerm),
+        (zellij, BackendType::Zellij),
+        (kitty, BackendType::Kitty),
+    ]
+    .into_iter()
+    .filter_map(|(set, backend)| set.then_some(backend))
+    .collect()
+}
+
+/// Warning for when more than one multiplexer claims the current shell, or
+/// `None` when the choice is unambiguous.
+///
+/// WezTerm and Kitty are also terminals, so finding them alongside tmux or
+/// Zellij just means they host it, which is the common case. Two of the same
+/// kind (tmux and Zellij, or WezTerm and Kitty) means one is nested in the
+/// other and the priority order may pick the wrong layer.
+fn nesting_warning(detected: &[BackendType]) -> Option<String> {
+    let is_terminal = |b: &BackendType| matches!(b, BackendType::WezTerm | BackendType::Kitty);
+    let layers: Vec<_> = if detected.iter().all(is_terminal) {
+        detected.to_vec()
+    } else {
+        detected
+            .iter()
+            .filter(|b| !is_terminal(b))
+            .copied()
+            .collect()
+    };
+    let [chosen, others @ ..] = layers.as_slice() else {
+        return None;
+    };
+    if others.is_empty() {
+        return None;
     }
+    let others: Vec<String> = others.iter().map(ToString::to_string).collect();
+    Some(format!(
+        "running inside both {} and {}; using {}. Set WORKMUX_BACKEND (or multiplexer.backend in config) to choose",
+        chosen,
+        others.join(" and "),
+        chosen
+    ))
+}
+
+/// Backend for this process, created on first use by [`current`].
+static CURRENT: OnceLock<Arc<dyn Multiplexer>> = OnceLock::new();
+
+#[cfg(test)]
+thread_local! {
+    static TEST_OVERRIDE: std::cell::RefCell<Option<Arc<dyn Multiplexer>>> =
+        const { std::cell::RefCell::new(None) };
+}
 
-    if zellij {
-        return BackendType::Zellij;
+/// Get the multiplexer backend for the current process.
+///
+/// Detection runs once and the created backend is reused for the rest of the
+/// process, so the environment it inspects is assumed not to change during a
+/// single run. Outside any multiplexer this is tmux, for backward
+/// compatibility; commands that can't work without one should use
+/// [`require_running`].
+pub fn current() -> Arc<dyn Multiplexer> {
+    #[cfg(test)]
+    if let Some(mux) = TEST_OVERRIDE.with(|o| o.borrow().clone()) {
+        return mux;
     }
 
-    if kitty {
-        return BackendType::Kitty;
+    Arc::clone(CURRENT.get_or_init(|| create_backend(detect_backend().unwrap_or_default())))
+}
+
+/// The current backend, for commands that can't do anything without a live
+/// multiplexer.
+///
+/// Running from a plain shell is fine as long as the backend's server is up
+/// (e.g. capturing a tmux pane from another terminal); otherwise this fails
+/// instead of letting pane lookups come back empty.
+pub fn require_running() -> Result<Arc<dyn Multiplexer>> {
+    let mux = current();
+    if mux.is_running().unwrap_or(false) {
+        return Ok(mux);
     }
+    match detect_backend() {
+        None => bail!(
+            "Not running inside a supported multiplexer (tmux, wezterm, kitty or zellij), and no {} server is running",
+            mux.name()
+        ),
+        Some(BackendType::Kitty) => bail!(
+            "Can't reach kitty over remote control (`kitten @ ls` failed). Set \
+             `allow_remote_control yes` and `listen_on unix:/tmp/kitty-{{kitty_pid}}` in \
+             kitty.conf, then restart kitty"
+        ),
+        Some(_) => bail!("{} is not running", mux.name()),
+    }
+}
 
-    BackendType::Tmux
+/// Make [`current`] return `mux` on this test thread (`None` restores detection).
+#[cfg(test)]
+pub fn set_current_for_test(mux: Option<Arc<dyn Multiplexer>>) {
+    TEST_OVERRIDE.with(|o| *o.borrow_mut() = mux);
 }
 
 /// Create a backend instance based on the backend type.
@@ -588,18 +881,121 @@ mod tests {
     use super::*;
 
     #[test]
-    fn no_env_defaults_to_tmux() {
+    fn current_is_cached_and_overridable() {
+        let first = current();
+        assert!(Arc::ptr_eq(&first, &current()));
+
+        let mock = create_backend(BackendType::Zellij);
+        set_current_for_test(Some(Arc::clone(&mock)));
+        assert!(Arc::ptr_eq(&mock, &current()));
+
+        set_current_for_test(None);
+        assert!(Arc::ptr_eq(&first, &current()));
+    }
+
+    #[test]
+    fn backend_override_from_config() {
+        // Env overrides take precedence; nothing to check if the runner sets them
+        if std::env::var_os("WORKMUX_BACKEND").is_some()
+            || std::env::var_os("WORKMUX_MUX").is_some()
+        {
+            return;
+        }
+
+        assert_eq!(backend_override(None).unwrap(), None);
         assert_eq!(
-            resolve_backend(false, false, false, false),
-            BackendType::Tmux
+            backend_override(Some("Zellij")).unwrap(),
+            Some(BackendType::Zellij)
         );
+
+        let err = backend_override(Some("screen")).unwrap_err().to_string();
+        assert!(err.contains("multiplexer.backend"), "{}", err);
+        assert!(err.contains("tmux, wezterm, kitty, zellij"), "{}", err);
+
+        let mut config = Config::default();
+        config.multiplexer.backend = Some("wezterm".to_string());
+        assert_eq!(
+            configured_backend(&config).unwrap(),
+            Some(BackendType::WezTerm)
+        );
+    }
+
+    #[test]
+    fn new_window_creates_then_runs_command() {
+        let mux = mock::MockMultiplexer::new();
+        let pane = mux
+            .new_window("sandbox-tree", Path::new("/tmp"), "workmux sandbox run .")
+            .unwrap();
+
+        assert_eq!(pane, "%1");
+        assert_eq!(
+            mux.calls(),
+            vec![
+                "create_window sandbox-tree",
+                "respawn_pane %1 workmux sandbox run .",
+            ]
+        );
+    }
+
+    fn resolve_backend(
+        tmux: bool,
+        wezterm: bool,
+        zellij: bool,
+        kitty: bool,
+    ) -> Option<BackendType> {
+        detected_backends(tmux, wezterm, zellij, kitty)
+            .first()
+            .copied()
+    }
+
+    #[test]
+    fn stale_tmux_env_is_not_a_live_server() {
+        let tmp = tempfile::tempdir().unwrap();
+        let socket = tmp.path().join("default");
+        let tmux = format!("{},4242,0", socket.display());
+        assert_eq!(tmux_socket(&tmux), socket.to_str().unwrap());
+        assert!(!tmux_server_reachable(&tmux));
+
+        // A socket file left behind by a crashed server refuses connections
+        drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+        assert!(socket.exists());
+        assert!(!tmux_server_reachable(&tmux));
+
+        let _listener = std::os::unix::net::UnixListener::bind(tmp.path().join("live")).unwrap();
+        let live = format!("{},4242,0", tmp.path().join("live").display());
+        assert!(tmux_server_reachable(&live));
+    }
+
+    #[test]
+    fn nesting_warning_only_for_competing_layers() {
+        use BackendType::*;
+        assert_eq!(nesting_warning(&[]), None);
+        assert_eq!(nesting_warning(&[Tmux]), None);
+        // A multiplexer inside a terminal backend is the expected setup
+        assert_eq!(nesting_warning(&[Tmux, WezTerm, Kitty]), None);
+        assert_eq!(nesting_warning(&[Zellij, Kitty]), None);
+
+        let warning = nesting_warning(&[Tmux, Zellij, Kitty]).unwrap();
+        assert!(
+            warning.starts_with("running inside both tmux and zellij; using tmux."),
+            "{warning}"
+        );
+        assert!(warning.contains("WORKMUX_BACKEND"), "{warning}");
+
+        let warning = nesting_warning(&[WezTerm, Kitty]).unwrap();
+        assert!(warning.contains("using wezterm"), "{warning}");
+    }
+
+    #[test]
+    fn no_env_is_headless() {
+        assert_eq!(resolve_backend(false, false, false, false), None);
     }
 
     #[test]
     fn tmux_only() {
         assert_eq!(
             resolve_backend(true, false, false, false),
-            BackendType::Tmux
+            Some(BackendType::Tmux)
         );
     }
 
@@ -607,7 +1003,7 @@ mod tests {
     fn wezterm_only() {
         assert_eq!(
             resolve_backend(false, true, false, false),
-            BackendType::WezTerm
+            Some(BackendType::WezTerm)
         );
     }
 
@@ -615,7 +1011,7 @@ mod tests {
     fn zellij_only() {
         assert_eq!(
             resolve_backend(false, false, true, false),
-            BackendType::Zellij
+            Some(BackendType::Zellij)
         );
     }
 
@@ -623,30 +1019,39 @@ mod tests {
     fn kitty_only() {
         assert_eq!(
             resolve_backend(false, false, false, true),
-            BackendType::Kitty
+            Some(BackendType::Kitty)
         );
     }
 
     #[test]
     fn tmux_inside_kitty() {
-        assert_eq!(resolve_backend(true, false, false, true), BackendType::Tmux);
+        assert_eq!(
+            resolve_backend(true, false, false, true),
+            Some(BackendType::Tmux)
+        );
     }
 
     #[test]
     fn tmux_inside_wezterm() {
-        assert_eq!(resolve_backend(true, true, false, false), BackendType::Tmux);
+        assert_eq!(
+            resolve_backend(true, true, false, false),
+            Some(BackendType::Tmux)
+        );
     }
 
     #[test]
     fn tmux_inside_zellij() {
-        assert_eq!(resolve_backend(true, false, true, false), BackendType::Tmux);
+        assert_eq!(
+            resolve_backend(true, false, true, false),
+            Some(BackendType::Tmux)
+        );
     }
 
     #[test]
     fn wezterm_inside_kitty() {
         assert_eq!(
             resolve_backend(false, true, false, true),
-            BackendType::WezTerm
+            Some(BackendType::WezTerm)
         );
     }
 
@@ -654,12 +1059,15 @@ mod tests {
     fn zellij_inside_kitty() {
         assert_eq!(
             resolve_backend(false, false, true, true),
-            BackendType::Zellij
+            Some(BackendType::Zellij)
         );
     }
 
     #[test]
     fn all_env_vars_set() {
-        assert_eq!(resolve_backend(true, true, true, true), BackendType::Tmux);
+        assert_eq!(
+            resolve_backend(true, true, true, true),
+            Some(BackendType::Tmux)
+        );
     }
 }
@@ -46,6 +46,38 @@ impl TmuxBackend {
             .with_context(|| format!("tmux query failed: {:?}", args))
     }
 
+    /// The `-S`/`-E` arguments of `capture-pane` for `range`, or `None` for
+    /// a span entirely above the oldest line of history.
+    fn capture_bounds(&self, pane_id: &str, range: CaptureRange) -> Result<Option<Vec<String>>> {
+        Ok(match range {
+            // "-S -" starts at the beginning of the history
+            CaptureRange::All => Some(vec!["-S".into(), "-".into()]),
+            CaptureRange::Lines(lines) => Some(vec!["-S".into(), format!("-{}", lines)]),
+            CaptureRange::Span { start, end } => {
+                let output = self.tmux_query(&[
+                    "display-message",
+                    "-p",
+                    "-t",
+                    pane_id,
+                    "#{history_size} #{pane_height}",
+                ])?;
+                let (history, height) = output
+                    .trim()
+                    .split_once(' ')
+                    .and_then(|(h, r)| Some((h.parse().ok()?, r.parse().ok()?)))
+                    .ok_or_else(|| anyhow!("Unexpected pane size from tmux: {}", output.trim()))?;
+                span_bounds(start, end, history, height).map(|(first, last)| {
+                    vec![
+                        "-S".into(),
+                        first.to_string(),
+                        "-E".into(),
+                        last.to_string(),
+                    ]
+                })
+            }
+        })
+    }
+
     /// Get the default shell configured in tmux.
     fn get_default_shell_internal(&self) -> Result<String> {
         let output = self.tmux_query(&["show-option", "-gqv", "default-shell"])?;
@@ -338,6 +370,16 @@ impl Multiplexer for TmuxBackend {
         self.tmux_cmd(&["kill-window", "-t", &target])
     }
 
+    fn rename_window(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        let target = format!("={}", full_name);
+        self.tmux_cmd(&["rename-window", "-t", &target, new_full_name])
+    }
+
+    fn rename_session(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        let target = format!("={}", full_name);
+        self.tmux_cmd(&["rename-session", "-t", &target, new_full_name])
+    }
+
     fn schedule_window_close(&self, full_name: &str, delay: Duration) -> Result<()> {
         let delay_secs = format!("{:.3}", delay.as_secs_f64());
         let target = format!("={}", full_name);
@@ -566,6 +608,21 @@ impl Multiplexer for TmuxBackend {
         self.tmux_cmd(&["switch-client", "-t", pane_id])
     }
 
+    fn attach_session(&self, pane_id: &str) -> Result<()> {
+        self.tmux_cmd(&["select-window", "-t", pane_id])?;
+        self.tmux_cmd(&["select-pane", "-t", pane_id])?;
+
+        // Needs the real terminal, so bypass Cmd's captured output
+        let status = std::process::Command::new("tmux")
+            .args(["attach-session", "-t", pane_id])
+            .status()
+            .context("Failed to run tmux attach-session")?;
+        if !status.success() {
+            return Err(anyhow!("tmux attach-session exited with {}", status));
+        }
+        Ok(())
+    }
+
     fn respawn_pane(&self, pane_id: &str, cwd: &Path, cmd: Option<&str>) -> Result<String> {
         let working_dir_str = cwd
             .to_str()
@@ -588,10 +645,114 @@ impl Multiplexer for TmuxBackend {
         Ok(pane_id.to_string())
     }
 
-    fn capture_pane(&self, pane_id: &str, lines: u16) -> Option<String> {
-        let start_line = format!("-{}", lines);
-        self.tmux_query(&["capture-pane", "-p", "-e", "-S", &start_line, "-t", pane_id])
+    fn capture_pane(&self, pane_id: &str, range: CaptureRange) -> Result<String> {
+        let output = self.capture_pane_bytes(pane_id, range)?;
+        Ok(String::from_utf8_lossy(&output).trim().to_string())
+    }
+
+    fn capture_pane_bytes(&self, pane_id: &str, range: CaptureRange) -> Result<Vec<u8>> {
+        let Some(bounds) = self.capture_bounds(pane_id, range)? else {
+            return Ok(Vec::new());
+        };
+        Cmd::new("tmux")
+            .args(&["capture-pane", "-p", "-e", "-t", pane_id])
+            .args(&bounds.iter().map(String::as_str).collect::<Vec<_>>())
+            .run()
+            .map(|output| output.stdout)
+            .map_err(|e| match capture_failure(pane_id, &format!("{:#}", e)) {
+                Some(reason) => anyhow!(reason),
+                None => e.context(format!("Failed to capture pane {}", pane_id)),
+            })
+    }
+
+    fn capture_pane_lines(
+        &self,
+        pane_id: &str,
+        range: CaptureRange,
+        line: &mut dyn FnMut(&str) -> Result<()>,
+    ) -> Result<()> {
+        use std::io::{BufReader, Read};
+        use std::process::{Command, Stdio};
+
+        let Some(bounds) = self.capture_bounds(pane_id, range)? else {
+            return Ok(());
+        };
+        let mut child = Command::new("tmux")
+            .args(["capture-pane", "-p", "-e", "-t", pane_id])
+            .args(&bounds)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|_| anyhow!("tmux is not installed or not on PATH"))?;
+
+        let mut stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        let result = for_each_line(&mut stdout, line);
+        // Stop tmux if we gave up early, rather than leave it blocked on a full pipe
+        if result.is_err() {
+            let _ = child.kill();
+        }
+        drop(stdout);
+
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr);
+        }
+        let status = child.wait()?;
+        result?;
+        if !status.success() {
+            let message = stderr.trim();
+            return Err(match capture_failure(pane_id, message) {
+                Some(reason) => anyhow!(reason),
+                None => anyhow!("Failed to capture pane {}: {}", pane_id, message),
+            });
+        }
+        Ok(())
+    }
+
+    fn set_pane_role(&self, pane_id: &str, role: &str) -> Result<()> {
+        self.tmux_cmd(&["set-option", "-p", "-t", pane_id, "@workmux_role", role])
+    }
+
+    fn pane_role(&self, pane_id: &str) -> Option<String> {
+        self.tmux_query(&["display-message", "-p", "-t", pane_id, "#{@workmux_role}"])
             .ok()
+            .map(|role| role.trim().to_string())
+            .filter(|role| !role.is_empty())
+    }
+
+    fn pane_roles(&self, pane_id: &str) -> Result<Vec<(String, Option<String>)>> {
+        let output = self.tmux_query(&[
+            "list-panes",
+            "-t",
+            pane_id,
+            "-F",
+            "#{pane_id}\t#{@workmux_role}",
+        ])?;
+        Ok(parse_pane_roles(&output))
+    }
+
+    fn window_pane_ids(&self, pane_id: &str) -> Result<Vec<String>> {
+        // list-panes with a pane target lists every pane in that pane's window
+        let output = self.tmux_query(&["list-panes", "-t", pane_id, "-F", "#{pane_id}"])?;
+        Ok(output
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+
+    fn pane_size(&self, pane_id: &str) -> Option<(u16, u16)> {
+        let output = self
+            .tmux_query(&[
+                "display-message",
+                "-p",
+                "-t",
+                pane_id,
+                "#{pane_width} #{pane_height}",
+            ])
+            .ok()?;
+        parse_pane_size(&output)
     }
 
     // === Text I/O ===
@@ -601,6 +762,15 @@ impl Multiplexer for TmuxBackend {
         self.tmux_cmd(&["send-keys", "-t", pane_id, "Enter"])
     }
 
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        if text.contains('\n') {
+            self.set_buffer(None, text)?;
+            self.tmux_cmd(&["paste-buffer", "-t", pane_id, "-p", "-d"])
+        } else {
+            self.tmux_cmd(&send_literal_args(pane_id, text))
+        }
+    }
+
     fn send_keys_to_agent(&self, pane_id: &str, command: &str, agent: Option<&str>) -> Result<()> {
         if agent::resolve_profile(agent).needs_bang_delay() && command.starts_with('!') {
             // Send ! first
@@ -624,10 +794,22 @@ impl Multiplexer for TmuxBackend {
     }
 
     fn paste_multiline(&self, pane_id: &str, content: &str) -> Result<()> {
+        self.set_buffer(None, content)?;
+        self.tmux_cmd(&["paste-buffer", "-t", pane_id, "-p", "-d"])?;
+        self.tmux_cmd(&["send-keys", "-t", pane_id, "Enter"])
+    }
+
+    fn set_buffer(&self, name: Option<&str>, content: &str) -> Result<()> {
         use std::io::Write;
 
+        let mut args = vec!["load-buffer"];
+        if let Some(name) = name {
+            args.extend(["-b", name]);
+        }
+        args.push("-");
+
         let mut child = std::process::Command::new("tmux")
-            .args(["load-buffer", "-"])
+            .args(&args)
             .stdin(std::process::Stdio::piped())
             .spawn()
             .context("Failed to spawn tmux load-buffer")?;
@@ -644,9 +826,7 @@ impl Multiplexer for TmuxBackend {
         if !status.success() {
             return Err(anyhow::anyhow!("tmux load-buffer failed"));
         }
-
-        self.tmux_cmd(&["paste-buffer", "-t", pane_id, "-p", "-d"])?;
-        self.tmux_cmd(&["send-keys", "-t", pane_id, "Enter"])
+        Ok(())
     }
 
     // === Shell ===
@@ -673,9 +853,10 @@ impl Multiplexer for TmuxBackend {
         // Set up hook to auto-clear status when window receives focus.
         // Used for "waiting" and "done" statuses so they clear once the user sees them.
         if auto_clear_on_focus {
-            // Only clear if status still matches this icon (avoids clearing a newer status)
+            // Only clear if status still matches this icon (avoids clearing a newer status).
+            // Single quotes keep tmux from expanding `$` in usage labels like `$0.42`.
             let hook_cmd = format!(
-                "if-shell -F \"#{{==:#{{@workmux_status}},{}}}\" \"set-option -uw @workmux_status\"",
+                "if-shell -F '#{{==:#{{@workmux_status}},{}}}' \"set-option -uw @workmux_status\"",
                 icon
             );
             let _ = self.tmux_cmd(&["set-hook", "-w", "-t", pane_id, "pane-focus-in", &hook_cmd]);
@@ -792,6 +973,83 @@ impl Multiplexer for TmuxBackend {
         Ok(panes)
     }
 }
+
+/// `send-keys` arguments typing `text` into `pane_id` as-is.
+fn send_literal_args<'a>(pane_id: &'a str, text: &'a str) -> [&'a str; 6] {
+    ["send-keys", "-t", pane_id, "-l", "--", text]
+}
+
+/// Hand each line of `reader` (without its newline, invalid UTF-8
+/// replaced) to `line`, one at a time, stopping at the first error.
+fn for_each_line(
+    mut reader: impl std::io::BufRead,
+    line: &mut dyn FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        if reader.read_until(b'\n', &mut buf)? == 0 {
+            return Ok(());
+        }
+        if buf.last() == Some(&b'\n') {
+            buf.pop();
+        }
+        line(&String::from_utf8_lossy(&buf))?;
+    }
+}
+
+/// Parse `"<cols> <rows>"` output from `display-message`.
+fn parse_pane_size(output: &str) -> Option<(u16, u16)> {
+    let (cols, rows) = output.trim().split_once(' ')?;
+    let cols: u16 = cols.parse().ok()?;
+    let rows: u16 = rows.parse().ok()?;
+    if cols == 0 || rows == 0 {
+        return None;
+    }
+    Some((cols, rows))
+}
+
+/// Parse `"<pane_id>\t<role>"` lines from `list-panes`; an empty role
+/// means the pane has none.
+fn parse_pane_roles(output: &str) -> Vec<(String, Option<String>)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (id, role) = line.split_once('\t').unwrap_or((line, ""));
+            let id = id.trim();
+            let role = role.trim();
+            (!id.is_empty()).then(|| (id.to_string(), (!role.is_empty()).then(|| role.to_string())))
+        })
+        .collect()
+}
+
+/// tmux line numbers for the first and last rows of a
+/// [`CaptureRange::Span`], in a pane with `history` lines of scrollback
+/// above `height` visible rows. Row 0 is the top of the visible screen and
+/// history rows are negative. Clamped to the oldest line; `None` when the
+/// whole span is above it.
+fn span_bounds(start: u32, end: u32, history: i64, height: i64) -> Option<(i64, i64)> {
+    let last = height - i64::from(end) - 1;
+    if last < -history || end >= start {
+        return None;
+    }
+    Some(((height - i64::from(start)).max(-history), last))
+}
+
+/// Explain a failed `capture-pane` from tmux's error output, or `None` when
+/// it isn't one of the failures we recognize.
+fn capture_failure(pane_id: &str, message: &str) -> Option<String> {
+    if message.contains("can't find pane") || message.contains("can't find window") {
+        Some(format!("pane {} no longer exists", pane_id))
+    } else if message.contains("no server running") || message.contains("error connecting to") {
+        Some("tmux server not running".to_string())
+    } else if message.contains("Failed to execute command") {
+        Some("tmux is not installed or not on PATH".to_string())
+    } else {
+        None
+    }
+}
+
 /// Format string to inject into tmux window-status-format.
 const WORKMUX_STATUS_FORMAT: &str = "#{?@workmux_status, #{@workmux_status},}";
 
@@ -812,6 +1070,111 @@ fn inject_status_format(format: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn for_each_line_streams_a_large_capture() {
+        let mut content = Vec::new();
+        for i in 0..100_000 {
+            content.extend_from_slice(format!("\x1b[32mline {i}\x1b[0m padding\n").as_bytes());
+        }
+        content.extend_from_slice(b"bad \xff byte\r\nno newline");
+        assert!(content.len() > 2_000_000);
+
+        // A small buffer, so lines straddle refills as they do on a pipe
+        let mut reader = std::io::BufReader::with_capacity(4096, content.as_slice());
+        let mut lines = Vec::new();
+        for_each_line(&mut reader, &mut |l| {
+            lines.push(l.to_string());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(lines.len(), 100_002);
+        assert_eq!(lines[0], "\x1b[32mline 0\x1b[0m padding");
+        assert_eq!(lines[99_999], "\x1b[32mline 99999\x1b[0m padding");
+        assert_eq!(lines[100_000], "bad \u{fffd} byte\r");
+        assert_eq!(lines[100_001], "no newline");
+    }
+
+    #[test]
+    fn for_each_line_stops_reading_at_the_first_error() {
+        let content = "a\n".repeat(1_000_000);
+        let mut reader = std::io::BufReader::with_capacity(4096, content.as_bytes());
+        let mut seen = 0;
+        let err = for_each_line(&mut reader, &mut |_| {
+            seen += 1;
+            if seen == 3 {
+                anyhow::bail!("reader gone");
+            }
+            Ok(())
+        })
+        .unwrap_err();
+
+        assert_eq!(err.to_string(), "reader gone");
+        assert_eq!(seen, 3);
+        // The rest was left unread for tmux to be killed over
+        assert!(!std::io::BufRead::fill_buf(&mut reader).unwrap().is_empty());
+    }
+
+    #[test]
+    fn send_literal_args_end_options_before_the_text() {
+        assert_eq!(
+            send_literal_args("%1", "-rf"),
+            ["send-keys", "-t", "%1", "-l", "--", "-rf"]
+        );
+    }
+
+    #[test]
+    fn parse_pane_roles_handles_untagged_panes() {
+        assert_eq!(
+            parse_pane_roles("%1\tagent\n%2\t\n%3\teditor\n"),
+            vec![
+                ("%1".to_string(), Some("agent".to_string())),
+                ("%2".to_string(), None),
+                ("%3".to_string(), Some("editor".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn span_bounds_count_back_from_the_bottom_row() {
+        // 100 lines of history above a 40-row screen: rows -100..=39
+        assert_eq!(span_bounds(50, 0, 100, 40), Some((-10, 39)));
+        assert_eq!(span_bounds(100, 50, 100, 40), Some((-60, -11)));
+        // Clamped at the oldest line, then nothing left
+        assert_eq!(span_bounds(150, 100, 100, 40), Some((-100, -61)));
+        assert_eq!(span_bounds(200, 150, 100, 40), None);
+        assert_eq!(span_bounds(140, 140, 100, 40), None);
+    }
+
+    #[test]
+    fn capture_failure_names_the_cause() {
+        assert_eq!(
+            capture_failure(
+                "%5",
+                "Command failed: tmux capture-pane\ncan't find pane: %5"
+            ),
+            Some("pane %5 no longer exists".to_string())
+        );
+        assert_eq!(
+            capture_failure(
+                "%5",
+                "Command failed: tmux capture-pane\nerror connecting to /tmp/tmux-0/default (No such file or directory)"
+            ),
+            Some("tmux server not running".to_string())
+        );
+        assert_eq!(
+            capture_failure(
+                "%5",
+                "Command failed: tmux capture-pane\nno server running on /tmp/tmux-0/default"
+            ),
+            Some("tmux server not running".to_string())
+        );
+        assert_eq!(
+            capture_failure("%5", "Command failed: tmux capture-pane\nsomething else"),
+            None
+        );
+    }
+
     #[test]
     fn test_inject_status_format_standard() {
         let input = "#I:#W#{?window_flags,#{window_flags}, }";
@@ -857,6 +1220,14 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_pane_size() {
+        assert_eq!(parse_pane_size("120 40\n"), Some((120, 40)));
+        assert_eq!(parse_pane_size("0 40"), None);
+        assert_eq!(parse_pane_size("garbage"), None);
+        assert_eq!(parse_pane_size(""), None);
+    }
+
     #[test]
     fn test_trim_end_newlines_preserves_spaces() {
         // Simulates processing tmux show-option output: trailing newlines are
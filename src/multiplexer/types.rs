@@ -3,7 +3,11 @@
 //! These types are used by both the tmux and WezTerm backends.
 
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::config::StatusIcons;
 
 /// Agent status representing the current state of an agent.
 ///
@@ -20,6 +24,54 @@ pub enum AgentStatus {
     Done,
 }
 
+impl AgentStatus {
+    /// Every status, in the order they're listed to users.
+    pub const ALL: [AgentStatus; 3] = [Self::Working, Self::Waiting, Self::Done];
+
+    /// The lowercase name used in JSON, on the command line and over RPC.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Working => "working",
+            Self::Waiting => "waiting",
+            Self::Done => "done",
+        }
+    }
+
+    /// The configured icon for this status.
+    pub fn glyph(self, icons: &StatusIcons) -> &str {
+        match self {
+            Self::Working => icons.working(),
+            Self::Waiting => icons.waiting(),
+            Self::Done => icons.done(),
+        }
+    }
+
+    /// Valid names, comma separated, for error messages.
+    pub fn names() -> String {
+        Self::ALL.map(Self::as_str).join(", ")
+    }
+}
+
+impl fmt::Display for AgentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for AgentStatus {
+    type Err = anyhow::Error;
+
+    /// Parse a status name, ignoring case.
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|status| status.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                anyhow::anyhow!("Unknown status '{}': expected one of {}", s, Self::names())
+            })
+    }
+}
+
 /// Information about a specific pane running a workmux agent
 #[derive(Debug, Clone)]
 pub struct AgentPane {
@@ -96,6 +148,45 @@ pub struct PaneSetupOptions<'a> {
     pub lima_vm_name: Option<&'a str>,
 }
 
+/// How much of a pane's history [`capture_pane`](super::Multiplexer::capture_pane)
+/// returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaptureRange {
+    /// The last N lines
+    Lines(u16),
+    /// The entire scrollback
+    All,
+    /// A window of history, as offsets counted back from the bottom row of
+    /// the pane: the last `start` rows minus the last `end` rows, so
+    /// `Span { start: n, end: 0 }` is the same as `Lines(n)`. Rows above the
+    /// oldest line of history are left out, so a span reaching past it
+    /// comes back short, or empty.
+    Span { start: u32, end: u32 },
+}
+
+impl CaptureRange {
+    /// Trim `output` to the range, for backends that can only capture
+    /// everything and cut client-side.
+    pub fn tail(self, output: &str) -> String {
+        let all_lines: Vec<&str> = output.lines().collect();
+        let (start, end) = self.bounds(all_lines.len());
+        all_lines[start..end].join("\n")
+    }
+
+    /// The indices of the range's first and past-the-end lines among
+    /// `len` lines of output.
+    pub fn bounds(self, len: usize) -> (usize, usize) {
+        match self {
+            CaptureRange::All => (0, len),
+            CaptureRange::Lines(lines) => (len.saturating_sub(lines as usize), len),
+            CaptureRange::Span { start, end } => (
+                len.saturating_sub(start as usize),
+                len.saturating_sub(end as usize),
+            ),
+        }
+    }
+}
+
 /// Backend type for multiplexer selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum BackendType {
@@ -121,6 +212,20 @@ impl std::fmt::Display for BackendType {
     }
 }
 
+impl BackendType {
+    /// The installed version of this backend's CLI (e.g. `tmux 3.4`), or
+    /// `None` if it isn't installed.
+    pub fn version(&self) -> Option<String> {
+        let (program, arg) = match self {
+            BackendType::Tmux => ("tmux", "-V"),
+            BackendType::WezTerm => ("wezterm", "--version"),
+            BackendType::Kitty => ("kitty", "--version"),
+            BackendType::Zellij => ("zellij", "--version"),
+        };
+        crate::util::command_version(program, arg)
+    }
+}
+
 impl std::str::FromStr for BackendType {
     type Err = String;
 
@@ -130,7 +235,10 @@ impl std::str::FromStr for BackendType {
             "wezterm" => Ok(BackendType::WezTerm),
             "kitty" => Ok(BackendType::Kitty),
             "zellij" => Ok(BackendType::Zellij),
-            other => Err(format!("unknown backend: {}", other)),
+            other => Err(format!(
+                "unknown backend '{}' (supported: tmux, wezterm, kitty, zellij)",
+                other
+            )),
         }
     }
 }
@@ -159,3 +267,27 @@ pub struct LivePaneInfo {
     /// Window name
     pub window: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agent_status_round_trips_through_its_name() {
+        for status in AgentStatus::ALL {
+            assert_eq!(status.to_string().parse::<AgentStatus>().unwrap(), status);
+            assert_eq!(
+                serde_json::to_string(&status).unwrap(),
+                format!("\"{}\"", status)
+            );
+        }
+        assert_eq!(
+            " Waiting".parse::<AgentStatus>().unwrap(),
+            AgentStatus::Waiting
+        );
+        assert_eq!(
+            "idle".parse::<AgentStatus>().unwrap_err().to_string(),
+            "Unknown status 'idle': expected one of working, waiting, done"
+        );
+    }
+}
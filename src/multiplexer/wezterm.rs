@@ -269,6 +269,18 @@ impl Multiplexer for WezTermBackend {
         Ok(())
     }
 
+    fn rename_window(&self, full_name: &str, new_full_name: &str) -> Result<()> {
+        let current_ws = self.current_workspace();
+        let pane = self
+            .list_panes()?
+            .into_iter()
+            .find(|p| {
+                p.tab_title == full_name && current_ws.as_ref().is_none_or(|ws| &p.workspace == ws)
+            })
+            .ok_or_else(|| anyhow!("No tab named '{}'", full_name))?;
+        self.set_tab_title(&pane.pane_id.to_string(), new_full_name)
+    }
+
     fn kill_window(&self, full_name: &str) -> Result<()> {
         let panes = self.list_panes()?;
         let current_ws = self.current_workspace();
@@ -624,19 +636,37 @@ impl Multiplexer for WezTermBackend {
         }
     }
 
-    fn capture_pane(&self, pane_id: &str, lines: u16) -> Option<String> {
+    fn window_pane_ids(&self, pane_id: &str) -> Result<Vec<String>> {
+        let panes = self.list_panes()?;
+        let tab_id = panes
+            .iter()
+            .find(|p| p.pane_id.to_string() == pane_id)
+            .map(|p| p.tab_id)
+            .ok_or_else(|| anyhow!("Pane {} not found", pane_id))?;
+
+        Ok(panes
+            .iter()
+            .filter(|p| p.tab_id == tab_id)
+            .map(|p| p.pane_id.to_string())
+            .collect())
+    }
+
+    fn capture_pane(&self, pane_id: &str, range: CaptureRange) -> Result<String> {
         // Note: We don't use --escapes to avoid partial escape sequences like (B
         // appearing in the preview. Plain text is cleaner for dashboard display.
+        let mut args = vec!["cli", "get-text", "--pane-id", pane_id];
+        if !matches!(range, CaptureRange::Lines(_)) {
+            // Negative start lines reach back into the scrollback
+            args.push("--start-line=-2147483648");
+        }
         let output = self
             .wezterm_cmd()
-            .args(&["cli", "get-text", "--pane-id", pane_id])
+            .args(&args)
             .run_and_capture_stdout()
-            .ok()?;
+            .with_context(|| format!("Failed to capture pane {}", pane_id))?;
 
-        // get-text returns all scrollback; take last N lines
-        let all_lines: Vec<&str> = output.lines().collect();
-        let start = all_lines.len().saturating_sub(lines as usize);
-        Some(all_lines[start..].join("\n"))
+        // take last N lines
+        Ok(range.tail(&output))
     }
 
     // === Text I/O ===
@@ -662,6 +692,20 @@ impl Multiplexer for WezTermBackend {
         Ok(())
     }
 
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        let mut args = vec!["cli", "send-text", "--pane-id", pane_id];
+        // Without --no-paste, WezTerm uses bracketed paste
+        if !text.contains('\n') {
+            args.push("--no-paste");
+        }
+        args.extend(["--", text]);
+        self.wezterm_cmd()
+            .args(&args)
+            .run()
+            .context("Failed to send text to pane")?;
+        Ok(())
+    }
+
     fn send_keys_to_agent(&self, pane_id: &str, command: &str, agent: Option<&str>) -> Result<()> {
         if agent::resolve_profile(agent).needs_bang_delay() && command.starts_with('!') {
             // Send ! first
@@ -15,7 +15,7 @@ use crate::cmd::Cmd;
 use crate::config::SplitDirection;
 
 use super::handshake::UnixPipeHandshake;
-use super::types::{CreateWindowParams, LivePaneInfo};
+use super::types::{CaptureRange, CreateWindowParams, LivePaneInfo};
 use super::{Multiplexer, PaneHandshake};
 
 /// Zellij multiplexer backend.
@@ -650,11 +650,11 @@ impl Multiplexer for ZellijBackend {
         Ok(pane_id.to_string())
     }
 
-    fn capture_pane(&self, _pane_id: &str, _lines: u16) -> Option<String> {
+    fn capture_pane(&self, _pane_id: &str, range: CaptureRange) -> Result<String> {
         // Zellij limitation: dump-screen always captures the focused pane,
         // not the pane specified by pane_id. When the dashboard is focused,
         // it captures itself, creating a recursive loop. We detect this and
-        // return None to prevent the recursion.
+        // return an error to prevent the recursion.
 
         // Use PID + thread ID + timestamp for thread-safe temp file naming
         let timestamp = std::time::SystemTime::now()
@@ -670,19 +670,21 @@ impl Multiplexer for ZellijBackend {
         ));
         let temp_str = temp_path.to_string_lossy();
 
-        if Cmd::new("zellij")
-            .args(&["action", "dump-screen", &temp_str])
+        let mut args = vec!["action", "dump-screen", &temp_str];
+        if !matches!(range, CaptureRange::Lines(_)) {
+            args.push("--full");
+        }
+        Cmd::new("zellij")
+            .args(&args)
             .run()
-            .is_ok()
-        {
-            if let Ok(content) = std::fs::read_to_string(&temp_path) {
-                let _ = std::fs::remove_file(&temp_path);
-                return Some(content);
-            }
-            let _ = std::fs::remove_file(&temp_path);
+            .context("Failed to dump the zellij screen")?;
+        let content = std::fs::read_to_string(&temp_path)
+            .with_context(|| format!("Failed to read zellij screen dump {}", temp_str));
+        let _ = std::fs::remove_file(&temp_path);
+        match range {
+            CaptureRange::Span { .. } => content.map(|c| range.tail(&c)),
+            _ => content,
         }
-
-        None
     }
 
     // === Text I/O ===
@@ -702,6 +704,20 @@ impl Multiplexer for ZellijBackend {
         Ok(())
     }
 
+    fn send_text(&self, pane_id: &str, text: &str) -> Result<()> {
+        // write-chars has no paste mode, so newlines would submit each line
+        if text.contains('\n') {
+            return Err(anyhow!(
+                "Sending multi-line text without Enter is not supported by the zellij backend"
+            ));
+        }
+        Cmd::new("zellij")
+            .args(&["action", "write-chars", "--pane-id", pane_id, "--", text])
+            .run()
+            .context("Failed to send text")?;
+        Ok(())
+    }
+
     fn send_keys_to_agent(&self, pane_id: &str, command: &str, agent: Option<&str>) -> Result<()> {
         use super::agent;
 
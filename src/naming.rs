@@ -1,12 +1,12 @@
-use anyhow::{Result, bail};
-use slug::slugify;
-
 use crate::config::Config;
+use crate::git::sanitize_branch_to_dir;
+use anyhow::{Result, bail};
 
 /// Derives the "handle" (worktree dir name + tmux window base name)
 /// from the branch name, optional explicit override, and config.
 ///
-/// The handle is always slugified to ensure filesystem/tmux compatibility.
+/// The handle is always passed through [`sanitize_branch_to_dir`] to ensure
+/// filesystem/tmux compatibility, so lookups can map a branch to it.
 ///
 /// Priority:
 /// 1. Explicit name (--name flag) - bypasses all config (including prefix)
@@ -19,7 +19,7 @@ pub fn derive_handle(
 ) -> Result<String> {
     let handle = if let Some(name) = explicit_name {
         // Explicit --name takes priority and bypasses prefix
-        slugify(name)
+        sanitize_branch_to_dir(name)
     } else {
         // Apply naming strategy
         let derived = config.worktree_naming.derive_name(branch_name);
@@ -31,7 +31,7 @@ pub fn derive_handle(
             derived
         };
 
-        slugify(&with_prefix)
+        sanitize_branch_to_dir(&with_prefix)
     };
 
     validate_handle(&handle)?;
@@ -44,7 +44,7 @@ fn validate_handle(handle: &str) -> Result<()> {
         bail!("Handle cannot be empty");
     }
 
-    // Slugify should have removed these, but double check for safety
+    // Sanitizing should have removed these, but double check for safety
     if handle.contains("..") || handle.starts_with('/') {
         bail!("Handle cannot contain path traversal");
     }
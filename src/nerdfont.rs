@@ -267,30 +267,53 @@ fn save_nerdfont_preference(enabled: bool) -> Result<()> {
     }
 
     // Read existing config or create empty
-    let mut config_content = if config_path.exists() {
+    let config_content = if config_path.exists() {
         fs::read_to_string(&config_path)?
     } else {
         String::new()
     };
+    let format = crate::config::ConfigFormat::of(&config_path);
 
-    // Check if nerdfont key already exists
-    if config_content.contains("nerdfont:") {
+    fs::write(
+        &config_path,
+        with_nerdfont_setting(config_content, format, enabled),
+    )?;
+
+    Ok(())
+}
+
+/// Set the top-level `nerdfont` key in config file contents, keeping the rest.
+fn with_nerdfont_setting(
+    mut content: String,
+    format: crate::config::ConfigFormat,
+    enabled: bool,
+) -> String {
+    let (pattern, line) = match format {
+        crate::config::ConfigFormat::Yaml => {
+            (r"(?m)^nerdfont:.*$", format!("nerdfont: {}", enabled))
+        }
+        crate::config::ConfigFormat::Toml => {
+            (r"(?m)^nerdfont\s*=.*$", format!("nerdfont = {}", enabled))
+        }
+    };
+    let re = regex::Regex::new(pattern).expect("valid regex");
+    if re.is_match(&content) {
         // Update existing value
-        let re = regex::Regex::new(r"(?m)^nerdfont:.*$")?;
-        config_content = re
-            .replace(&config_content, format!("nerdfont: {}", enabled))
-            .to_string();
-    } else {
-        // Add nerdfont key
-        if !config_content.is_empty() && !config_content.ends_with('\n') {
-            config_content.push('\n');
+        return re.replace(&content, line.as_str()).into_owned();
+    }
+    match format {
+        crate::config::ConfigFormat::Yaml => {
+            // Add nerdfont key
+            if !content.is_empty() && !content.ends_with('\n') {
+                content.push('\n');
+            }
+            content.push_str(&line);
+            content.push('\n');
+            content
         }
-        config_content.push_str(&format!("nerdfont: {}\n", enabled));
+        // Top-level TOML keys must come before the first [table]
+        crate::config::ConfigFormat::Toml => format!("{}\n{}", line, content),
     }
-
-    fs::write(&config_path, config_content)?;
-
-    Ok(())
 }
 
 /// Run the nerdfont setup check.
@@ -339,4 +362,23 @@ mod tests {
     fn contains_pua_handles_empty_string() {
         assert!(!contains_pua(""));
     }
+
+    #[test]
+    fn nerdfont_setting_is_written_in_the_file_format() {
+        use crate::config::ConfigFormat;
+
+        let yaml = with_nerdfont_setting("agent: claude".to_string(), ConfigFormat::Yaml, true);
+        assert_eq!(yaml, "agent: claude\nnerdfont: true\n");
+        let yaml = with_nerdfont_setting(yaml, ConfigFormat::Yaml, false);
+        assert_eq!(yaml, "agent: claude\nnerdfont: false\n");
+
+        let toml = with_nerdfont_setting(
+            "[sandbox]\nenabled = true\n".to_string(),
+            ConfigFormat::Toml,
+            true,
+        );
+        assert_eq!(toml, "nerdfont = true\n[sandbox]\nenabled = true\n");
+        let toml = with_nerdfont_setting(toml, ConfigFormat::Toml, false);
+        assert!(toml.starts_with("nerdfont = false\n"));
+    }
 }
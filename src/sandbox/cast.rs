@@ -0,0 +1,153 @@
+//! asciinema v2 recordings (`.cast` files) of sandboxed agent runs.
+//!
+//! The format is a JSON header line followed by one `[time, "o", data]`
+//! array per chunk of output, with `time` in seconds since the recording
+//! started. See <https://docs.asciinema.org/manual/asciicast/v2/>.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Serialize)]
+struct Header<'a> {
+    version: u8,
+    width: u16,
+    height: u16,
+    timestamp: u64,
+    env: BTreeMap<&'a str, String>,
+}
+
+/// Writes output events to a `.cast` file as they happen.
+pub struct CastWriter {
+    out: BufWriter<File>,
+    /// Monotonic reference for event times, so clock changes can't reorder them
+    start: Instant,
+    /// Trailing bytes of an incomplete UTF-8 sequence, held for the next chunk
+    pending: Vec<u8>,
+}
+
+impl CastWriter {
+    /// Create (or truncate) `path` and write the header for a terminal of
+    /// `width` x `height` cells.
+    pub fn create(path: &Path, width: u16, height: u16) -> Result<Self> {
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create recording {}", path.display()))?;
+        let mut writer = Self {
+            out: BufWriter::new(file),
+            start: Instant::now(),
+            pending: Vec::new(),
+        };
+        writer
+            .write_header(width, height)
+            .with_context(|| format!("Failed to write recording {}", path.display()))?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self, width: u16, height: u16) -> io::Result<()> {
+        let env = ["TERM", "SHELL"]
+            .into_iter()
+            .filter_map(|key| std::env::var(key).ok().map(|value| (key, value)))
+            .collect();
+        let header = Header {
+            version: 2,
+            width,
+            height,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+            env,
+        };
+        serde_json::to_writer(&mut self.out, &header)?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+
+    /// Record a chunk of terminal output.
+    ///
+    /// Event data must be valid UTF-8, so a multi-byte character split across
+    /// reads is held back until the rest of it arrives.
+    pub fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(bytes);
+        let complete = complete_utf8_len(&self.pending);
+        if complete == 0 {
+            return Ok(());
+        }
+        let data = String::from_utf8_lossy(&self.pending[..complete]).into_owned();
+        self.pending.drain(..complete);
+
+        let time = self.start.elapsed().as_secs_f64();
+        serde_json::to_writer(&mut self.out, &(round_micros(time), "o", data))?;
+        self.out.write_all(b"\n")?;
+        self.out.flush()
+    }
+}
+
+/// Length of the longest prefix of `bytes` that doesn't end partway through
+/// a UTF-8 sequence. Invalid bytes count as complete (they're replaced).
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    match std::str::from_utf8(bytes) {
+        Ok(_) => bytes.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        Err(_) => bytes.len(),
+    }
+}
+
+fn round_micros(secs: f64) -> f64 {
+    (secs * 1_000_000.0).round() / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_lines(path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn writes_header_and_output_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.cast");
+
+        let mut cast = CastWriter::create(&path, 120, 40).unwrap();
+        cast.output(b"hello\r\n").unwrap();
+        cast.output(b"world").unwrap();
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 120);
+        assert_eq!(lines[0]["height"], 40);
+        assert!(lines[0]["timestamp"].as_u64().unwrap() > 0);
+
+        assert_eq!(lines[1][1], "o");
+        assert_eq!(lines[1][2], "hello\r\n");
+        assert_eq!(lines[2][2], "world");
+        assert!(lines[2][0].as_f64().unwrap() >= lines[1][0].as_f64().unwrap());
+    }
+
+    #[test]
+    fn split_utf8_sequences_are_joined() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.cast");
+        let snowman = "☃".as_bytes();
+
+        let mut cast = CastWriter::create(&path, 80, 24).unwrap();
+        cast.output(&[b'a', snowman[0]]).unwrap();
+        cast.output(&snowman[1..2]).unwrap();
+        cast.output(&[snowman[2], b'b', 0xff]).unwrap();
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1][2], "a");
+        assert_eq!(lines[2][2], "☃b\u{fffd}");
+    }
+}
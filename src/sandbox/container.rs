@@ -410,7 +410,9 @@ pub fn wrap_for_container(
         ));
     }
 
-    parts.push_str(&format!(" -- '{}'", shell_escape(command)));
+    // Panes created by workmux (`open -n`, sandboxed shell panes)
+    // intentionally run several supervisors in one worktree.
+    parts.push_str(&format!(" --force -- '{}'", shell_escape(command)));
 
     // Prefix with space to prevent shell history entry (same as rewrite_agent_command)
     Ok(format!(" {}", parts))
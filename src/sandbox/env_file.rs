@@ -0,0 +1,171 @@
+//! Per-worktree dotenv file (`sandbox.env_file`, default `.workmux.env`)
+//! whose variables are set inside the sandbox.
+
+use std::path::Path;
+use tracing::{debug, warn};
+
+use crate::config::SandboxConfig;
+
+/// Load the env file for the worktree at `worktree_root`.
+///
+/// A missing file yields nothing. Variables also listed in
+/// `env_passthrough` and set on the host are dropped, so the host value wins.
+///
+/// A relative `env_file` must resolve to a file inside the worktree. The
+/// agent can write there, so it could otherwise link the file to host
+/// secrets (e.g. `~/.aws/credentials`) and have them injected into the
+/// next sandbox.
+pub fn load(config: &SandboxConfig, worktree_root: &Path) -> Vec<(String, String)> {
+    let configured = crate::config::expand_tilde(config.env_file());
+    let path = worktree_root.join(&configured);
+    if configured.is_relative() && !stays_inside(&path, worktree_root) {
+        warn!(
+            path = %path.display(),
+            "sandbox env file resolves outside the worktree; ignoring it"
+        );
+        return Vec::new();
+    }
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "failed to read sandbox env file");
+            return Vec::new();
+        }
+    };
+
+    let passthrough = config.env_passthrough();
+    let entries: Vec<(String, String)> = parse(&content, &path)
+        .into_iter()
+        .filter(|(key, _)| {
+            !(passthrough.contains(&key.as_str()) && std::env::var_os(key).is_some())
+        })
+        .collect();
+    debug!(path = %path.display(), count = entries.len(), "loaded sandbox env file");
+    entries
+}
+
+/// Whether `path`, with symlinks resolved, is inside `root`. A missing path
+/// counts as inside, since there's nothing to read.
+fn stays_inside(path: &Path, root: &Path) -> bool {
+    match (path.canonicalize(), root.canonicalize()) {
+        (Ok(path), Ok(root)) => path.starts_with(root),
+        (Err(e), _) => e.kind() == std::io::ErrorKind::NotFound,
+        (Ok(_), Err(_)) => false,
+    }
+}
+
+/// Parse dotenv lines: `KEY=VALUE`, optionally prefixed with `export`.
+/// Blank lines and `#` comments are skipped, and one pair of matching quotes
+/// around the value is removed. Malformed lines are skipped with a warning.
+fn parse(content: &str, path: &Path) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    for (n, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            warn!(path = %path.display(), line = n + 1, "ignoring env file line without '='");
+            continue;
+        };
+        let key = key.trim();
+        if !is_env_name(key) {
+            warn!(path = %path.display(), line = n + 1, key, "ignoring invalid variable name in env file");
+            continue;
+        }
+        entries.push((key.to_string(), unquote(value.trim()).to_string()));
+    }
+    entries
+}
+
+pub fn is_env_name(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_handles_comments_quotes_and_equals() {
+        let content = "\
+# secrets
+API_KEY=abc=def
+export GREETING=\"hello world\"
+SINGLE='a b'
+
+not a var
+1BAD=x
+EMPTY=
+";
+        assert_eq!(
+            parse(content, Path::new(".workmux.env")),
+            vec![
+                ("API_KEY".to_string(), "abc=def".to_string()),
+                ("GREETING".to_string(), "hello world".to_string()),
+                ("SINGLE".to_string(), "a b".to_string()),
+                ("EMPTY".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn load_skips_missing_file_and_prefers_passthrough() {
+        let tmp = tempfile::tempdir().unwrap();
+        let mut config = SandboxConfig {
+            env_passthrough: Some(vec!["HOME".to_string()]),
+            ..Default::default()
+        };
+        assert!(load(&config, tmp.path()).is_empty());
+
+        std::fs::write(tmp.path().join(".workmux.env"), "HOME=/guest\nTOKEN=t\n").unwrap();
+        assert_eq!(
+            load(&config, tmp.path()),
+            vec![("TOKEN".to_string(), "t".to_string())]
+        );
+
+        config.env_file = Some("secrets/dev.env".to_string());
+        assert!(load(&config, tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn load_ignores_links_out_of_the_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let worktree = tmp.path().join("worktree");
+        std::fs::create_dir_all(worktree.join("config")).unwrap();
+        let credentials = tmp.path().join("credentials");
+        std::fs::write(&credentials, "aws_access_key_id = AKIA\n").unwrap();
+        let config = SandboxConfig::default();
+
+        // What a sandboxed agent could plant for the next run
+        std::os::unix::fs::symlink(&credentials, worktree.join(".workmux.env")).unwrap();
+        assert!(load(&config, &worktree).is_empty());
+
+        // Links that stay inside the worktree still work
+        std::fs::remove_file(worktree.join(".workmux.env")).unwrap();
+        std::fs::write(worktree.join("config/dev.env"), "TOKEN=t\n").unwrap();
+        std::os::unix::fs::symlink("config/dev.env", worktree.join(".workmux.env")).unwrap();
+        assert_eq!(
+            load(&config, &worktree),
+            vec![("TOKEN".to_string(), "t".to_string())]
+        );
+    }
+}
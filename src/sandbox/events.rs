@@ -0,0 +1,144 @@
+//! Lifecycle events from the sandbox supervisor, written as newline-delimited
+//! JSON to the file or FIFO given with `workmux sandbox run --events`.
+//!
+//! Writing is best effort: a reader going away must never take the agent
+//! down with it, so failures are logged and further events are dropped.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+/// One supervisor lifecycle event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum Event {
+    /// The Lima VM is running (Lima backend only)
+    VmReady,
+    /// The RPC server is accepting connections
+    RpcListening { port: u16 },
+    /// The agent command is being launched
+    AgentStarted { command: String },
+    /// The agent reported a new status over RPC
+    StatusChanged { status: String },
+    /// The agent command exited (124 when terminated by `--timeout`)
+    AgentExited { exit_code: i32 },
+}
+
+/// A JSON line: the event plus the supervisor context it happened in.
+#[derive(Serialize)]
+struct Record<'a> {
+    /// Unix time in milliseconds
+    timestamp: u64,
+    worktree: &'a Path,
+    vm_name: Option<&'a str>,
+    pane_id: &'a str,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Shared handle to the events file. Cloning is cheap.
+#[derive(Clone)]
+pub struct EventLog(Arc<Inner>);
+
+struct Inner {
+    /// `None` once a write has failed
+    file: Mutex<Option<File>>,
+    worktree: PathBuf,
+    pane_id: String,
+    vm_name: OnceLock<String>,
+}
+
+impl EventLog {
+    /// Open `path` for appending, creating it if needed. Opening a FIFO
+    /// blocks until a reader opens the other end.
+    pub fn open(path: &Path, worktree: &Path, pane_id: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open events file {}", path.display()))?;
+        Ok(Self(Arc::new(Inner {
+            file: Mutex::new(Some(file)),
+            worktree: worktree.to_path_buf(),
+            pane_id: pane_id.to_string(),
+            vm_name: OnceLock::new(),
+        })))
+    }
+
+    /// Record the VM name included in every later event.
+    pub fn set_vm_name(&self, vm_name: &str) {
+        let _ = self.0.vm_name.set(vm_name.to_string());
+    }
+
+    pub fn emit(&self, event: Event) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let record = Record {
+            timestamp,
+            worktree: &self.0.worktree,
+            vm_name: self.0.vm_name.get().map(String::as_str),
+            pane_id: &self.0.pane_id,
+            event: &event,
+        };
+        let Ok(mut line) = serde_json::to_string(&record) else {
+            return;
+        };
+        line.push('\n');
+
+        let mut file = self.0.file.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(f) = file.as_mut()
+            && let Err(e) = f.write_all(line.as_bytes()).and_then(|()| f.flush())
+        {
+            warn!(error = %e, "failed to write sandbox event; dropping further events");
+            *file = None;
+        }
+    }
+}
+
+/// Emit `event` if an events file was requested.
+pub fn emit(events: Option<&EventLog>, event: Event) {
+    if let Some(events) = events {
+        events.emit(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_written_as_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.jsonl");
+        let log = EventLog::open(&path, Path::new("/work/tree"), "%3").unwrap();
+
+        log.emit(Event::RpcListening { port: 4242 });
+        log.set_vm_name("wm-tree");
+        log.emit(Event::AgentExited { exit_code: 0 });
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+
+        assert_eq!(lines[0]["event"], "rpc-listening");
+        assert_eq!(lines[0]["port"], 4242);
+        assert_eq!(lines[0]["worktree"], "/work/tree");
+        assert_eq!(lines[0]["pane_id"], "%3");
+        assert!(lines[0]["vm_name"].is_null());
+        assert!(lines[0]["timestamp"].as_u64().unwrap() > 0);
+
+        assert_eq!(lines[1]["event"], "agent-exited");
+        assert_eq!(lines[1]["exit_code"], 0);
+        assert_eq!(lines[1]["vm_name"], "wm-tree");
+    }
+}
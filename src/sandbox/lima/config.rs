@@ -1,11 +1,137 @@
 //! Lima configuration YAML generation.
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde_yaml::Value;
+use std::path::Path;
 
 use super::mounts::Mount;
 use crate::config::SandboxConfig;
 
+/// Render mounts as the `mounts` list of a Lima config.
+pub(super) fn mounts_value(mounts: &[Mount]) -> Value {
+    let mount_list: Vec<Value> = mounts
+        .iter()
+        .map(|m| {
+            let mut mount_config = serde_yaml::Mapping::new();
+            mount_config.insert(
+                "location".into(),
+                m.host_path.to_string_lossy().to_string().into(),
+            );
+            mount_config.insert("writable".into(), (!m.read_only).into());
+
+            if m.host_path != m.guest_path {
+                mount_config.insert(
+                    "mountPoint".into(),
+                    m.guest_path.to_string_lossy().to_string().into(),
+                );
+            }
+
+            Value::Mapping(mount_config)
+        })
+        .collect();
+    mount_list.into()
+}
+
+/// Whether the `mounts` of an instance's `lima.yaml` differ from `mounts`.
+///
+/// Entries are compared as (location, mount point, writable) regardless of
+/// order, using Lima's defaults for omitted fields. An unparseable config
+/// counts as unchanged so a reconcile is never attempted on a guess.
+pub(super) fn mounts_differ(lima_yaml: &str, mounts: &[Mount]) -> bool {
+    fn normalize(list: &Value) -> Option<Vec<(String, String, bool)>> {
+        let mut entries = list
+            .as_sequence()?
+            .iter()
+            .map(|m| {
+                let location = m.get("location")?.as_str()?.to_string();
+                let mount_point = m
+                    .get("mountPoint")
+                    .and_then(Value::as_str)
+                    .map_or_else(|| location.clone(), String::from);
+                let writable = m.get("writable").and_then(Value::as_bool).unwrap_or(false);
+                Some((location, mount_point, writable))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        entries.sort();
+        Some(entries)
+    }
+
+    let Ok(current) = serde_yaml::from_str::<Value>(lima_yaml) else {
+        return false;
+    };
+    let current = current.get("mounts").map_or(Some(Vec::new()), normalize);
+    match (current, normalize(&mounts_value(mounts))) {
+        (Some(current), Some(wanted)) => current != wanted,
+        _ => false,
+    }
+}
+
+/// The `cpus` and `memory` an instance's `lima.yaml` records, or `None`
+/// if either is missing or the config can't be parsed.
+pub(super) fn resources_in(lima_yaml: &str) -> Option<(u32, String)> {
+    let config = serde_yaml::from_str::<Value>(lima_yaml).ok()?;
+    let cpus = u32::try_from(config.get("cpus")?.as_u64()?).ok()?;
+    let memory = config.get("memory")?.as_str()?.to_string();
+    Some((cpus, memory))
+}
+
+/// Turn `sandbox.lima.template` into a locator `limactl` understands.
+///
+/// Bare names become `template://<name>`; values that look like paths are
+/// tilde-expanded and resolved against the worktree.
+pub(super) fn template_locator(template: &str, worktree: &Path) -> String {
+    if template.contains("://") {
+        return template.to_string();
+    }
+    let is_path = template.contains('/')
+        || template.starts_with('~')
+        || template.ends_with(".yaml")
+        || template.ends_with(".yml");
+    if !is_path {
+        return format!("template://{}", template);
+    }
+    let path = crate::config::expand_tilde(template);
+    worktree.join(path).to_string_lossy().into_owned()
+}
+
+/// Template keys carried over into the generated config. Everything else
+/// (mounts, port forwards, SSH agent forwarding, ...) stays under workmux's
+/// control so a template can't widen what the VM sees of the host.
+const TEMPLATE_KEYS: &[&str] = &["images", "arch", "os", "probes"];
+
+/// Layer a Lima template's base image and provisioning under `lima_config`.
+///
+/// The template's provision steps run before workmux's own. Its images are
+/// skipped when `keep_images` is set, so a configured `sandbox.image` wins.
+pub(super) fn apply_template(
+    lima_config: &str,
+    template_yaml: &str,
+    keep_images: bool,
+) -> Result<String> {
+    let mut config: serde_yaml::Mapping = serde_yaml::from_str(lima_config)?;
+    let template: serde_yaml::Mapping =
+        serde_yaml::from_str(template_yaml).context("Failed to parse Lima template")?;
+
+    for key in TEMPLATE_KEYS {
+        if *key == "images" && keep_images {
+            continue;
+        }
+        if let Some(value) = template.get(*key) {
+            config.insert((*key).into(), value.clone());
+        }
+    }
+
+    if let Some(Value::Sequence(steps)) = template.get("provision") {
+        let mut provision = steps.clone();
+        if let Some(Value::Sequence(ours)) = config.get("provision") {
+            provision.extend(ours.iter().cloned());
+        }
+        config.insert("provision".into(), provision.into());
+    }
+
+    Ok(serde_yaml::to_string(&config)?)
+}
+
 /// Generate the shell commands to install a specific agent in a Lima VM.
 ///
 /// Each agent has different install requirements mirroring the container
@@ -129,28 +255,7 @@ pub fn generate_lima_config(
     containerd.insert("user".into(), false.into());
     config.insert("containerd".into(), containerd.into());
 
-    // Generate mounts
-    let mount_list: Vec<Value> = mounts
-        .iter()
-        .map(|m| {
-            let mut mount_config = serde_yaml::Mapping::new();
-            mount_config.insert(
-                "location".into(),
-                m.host_path.to_string_lossy().to_string().into(),
-            );
-            mount_config.insert("writable".into(), (!m.read_only).into());
-
-            if m.host_path != m.guest_path {
-                mount_config.insert(
-                    "mountPoint".into(),
-                    m.guest_path.to_string_lossy().to_string().into(),
-                );
-            }
-
-            Value::Mapping(mount_config)
-        })
-        .collect();
-    config.insert("mounts".into(), mount_list.into());
+    config.insert("mounts".into(), mounts_value(mounts));
 
     // Provision scripts (run on first VM creation only)
     let mut provisions = Vec::new();
@@ -446,13 +551,13 @@ mod tests {
         // First mount: read-write, same host/guest
         let m0 = &mount_list[0];
         assert_eq!(m0["location"].as_str().unwrap(), "/tmp/project");
-        assert_eq!(m0["writable"].as_bool().unwrap(), true);
+        assert!(m0["writable"].as_bool().unwrap());
         assert!(m0["mountPoint"].is_null());
 
         // Second mount: read-only, different guest path
         let m1 = &mount_list[1];
         assert_eq!(m1["location"].as_str().unwrap(), "/tmp/notes");
-        assert_eq!(m1["writable"].as_bool().unwrap(), false);
+        assert!(!m1["writable"].as_bool().unwrap());
         assert_eq!(m1["mountPoint"].as_str().unwrap(), "/mnt/notes");
     }
 
@@ -578,4 +683,122 @@ mod tests {
         assert!(script.contains("No built-in install script"));
         assert!(script.contains("my-custom-agent"));
     }
+
+    #[test]
+    fn test_mounts_differ_ignores_order_and_defaults() {
+        let mounts = vec![
+            Mount::rw(PathBuf::from("/work")),
+            Mount {
+                host_path: PathBuf::from("/home/me/.cache/models"),
+                guest_path: PathBuf::from("/models"),
+                read_only: true,
+            },
+        ];
+        let yaml = r#"
+mounts:
+  - location: /home/me/.cache/models
+    mountPoint: /models
+  - location: /work
+    writable: true
+"#;
+        assert!(!mounts_differ(yaml, &mounts));
+    }
+
+    #[test]
+    fn test_mounts_differ_detects_changes() {
+        let mounts = vec![Mount::rw(PathBuf::from("/work"))];
+        let readonly = "mounts:\n  - location: /work\n";
+        assert!(mounts_differ(readonly, &mounts));
+
+        let extra = "mounts:\n  - location: /work\n    writable: true\n  - location: /old\n";
+        assert!(mounts_differ(extra, &mounts));
+
+        assert!(mounts_differ("cpus: 4\n", &mounts));
+        assert!(!mounts_differ("not: [valid", &mounts));
+    }
+
+    #[test]
+    fn test_resources_in_reads_cpus_and_memory() {
+        assert_eq!(
+            resources_in("cpus: 8\nmemory: 8GiB\nmounts: []\n"),
+            Some((8, "8GiB".to_string()))
+        );
+        assert_eq!(resources_in("memory: 8GiB\n"), None);
+        assert_eq!(resources_in("not: [valid"), None);
+    }
+
+    #[test]
+    fn test_template_locator() {
+        let wt = Path::new("/work/proj");
+        assert_eq!(template_locator("docker", wt), "template://docker");
+        assert_eq!(
+            template_locator("template://ubuntu-lts", wt),
+            "template://ubuntu-lts"
+        );
+        assert_eq!(
+            template_locator("lima/node.yaml", wt),
+            "/work/proj/lima/node.yaml"
+        );
+        assert_eq!(template_locator("node.yml", wt), "/work/proj/node.yml");
+        assert_eq!(template_locator("/etc/vm.yaml", wt), "/etc/vm.yaml");
+    }
+
+    #[test]
+    fn test_apply_template_keeps_only_image_and_provisioning() {
+        let mounts = vec![Mount::rw(PathBuf::from("/Users/test/code"))];
+        let yaml = generate_lima_config(
+            "test-vm",
+            &mounts,
+            &SandboxConfig::default(),
+            "claude",
+            false,
+        )
+        .unwrap();
+        let template = r#"
+images:
+  - location: https://example.com/node20.qcow2
+    arch: x86_64
+provision:
+  - mode: system
+    script: install-node-20
+mounts:
+  - location: "~"
+    writable: true
+ssh:
+  forwardAgent: true
+"#;
+
+        let merged = apply_template(&yaml, template, false).unwrap();
+        let parsed: Value = serde_yaml::from_str(&merged).unwrap();
+
+        assert_eq!(
+            parsed["images"][0]["location"].as_str(),
+            Some("https://example.com/node20.qcow2")
+        );
+        let provision = parsed["provision"].as_sequence().unwrap();
+        assert_eq!(provision[0]["script"].as_str(), Some("install-node-20"));
+        assert!(provision.len() > 1, "workmux provisioning should follow");
+        let mounts = parsed["mounts"].as_sequence().unwrap();
+        assert_eq!(mounts.len(), 1);
+        assert_eq!(mounts[0]["location"].as_str(), Some("/Users/test/code"));
+        assert!(parsed.get("ssh").is_none());
+    }
+
+    #[test]
+    fn test_apply_template_custom_image_wins() {
+        let sandbox_config = SandboxConfig {
+            image: Some("file:///golden.qcow2".to_string()),
+            ..Default::default()
+        };
+        let yaml = generate_lima_config("test-vm", &[], &sandbox_config, "claude", false).unwrap();
+        let template = "images:\n  - location: https://example.com/other.qcow2\n";
+
+        let merged = apply_template(&yaml, template, true).unwrap();
+        let parsed: Value = serde_yaml::from_str(&merged).unwrap();
+
+        assert_eq!(
+            parsed["images"][0]["location"].as_str(),
+            Some("file:///golden.qcow2")
+        );
+    }
 }
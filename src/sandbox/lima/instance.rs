@@ -1,11 +1,14 @@
 //! Lima VM instance management.
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
-use tracing::{debug, info};
+use std::sync::{Arc, Mutex, OnceLock, mpsc};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
+use super::mounts::Mount;
 use crate::config::Config;
 
 /// Lima instance information from `limactl list --json`.
@@ -57,26 +60,91 @@ pub(crate) fn check_vm_state(vm_name: &str) -> Result<VmState> {
     }
 }
 
+/// How to install Lima, shown whenever `limactl` can't be found.
+const LIMA_INSTALL_HINT: &str = "Install Lima with `brew install lima` (macOS) or see \
+                                 https://lima-vm.io/docs/installation/";
+
+/// Build the error for a failed `limactl` spawn.
+///
+/// A missing binary gets install instructions instead of a bare
+/// "No such file or directory"; other errors keep `context` as-is.
+pub fn limactl_spawn_error(err: std::io::Error, context: &str) -> anyhow::Error {
+    if err.kind() == std::io::ErrorKind::NotFound {
+        anyhow!(
+            "limactl not found in PATH. The Lima sandbox backend requires it.\n{}",
+            LIMA_INSTALL_HINT
+        )
+    } else {
+        anyhow::Error::new(err).context(context.to_string())
+    }
+}
+
+/// `sandbox.lima_home`, recorded when the config is loaded so that every
+/// `limactl` call, including the one that starts the agent, sees the same
+/// instances.
+static LIMA_HOME: OnceLock<PathBuf> = OnceLock::new();
+
+/// Record `sandbox.lima_home`. The first configured value wins.
+pub fn set_lima_home(home: Option<PathBuf>) {
+    if let Some(home) = home {
+        let _ = LIMA_HOME.set(home);
+    }
+}
+
+/// The `limactl` binary: `WORKMUX_LIMACTL` if set, so tests (and wrappers)
+/// can stand in for it, else `limactl` from `PATH`.
+pub fn limactl_program() -> String {
+    std::env::var("WORKMUX_LIMACTL")
+        .ok()
+        .filter(|program| !program.is_empty())
+        .unwrap_or_else(|| "limactl".to_string())
+}
+
+/// A `limactl` command, with `LIMA_HOME` set if `sandbox.lima_home` is.
+pub fn limactl() -> Command {
+    let mut cmd = Command::new(limactl_program());
+    apply_lima_home(&mut cmd);
+    cmd
+}
+
+/// Set `LIMA_HOME` on `cmd` if `sandbox.lima_home` is configured, for
+/// commands that reach the VM without going through [`limactl`].
+pub fn apply_lima_home(cmd: &mut Command) {
+    set_lima_home_env(cmd, LIMA_HOME.get().map(PathBuf::as_path));
+}
+
+fn set_lima_home_env(cmd: &mut Command, home: Option<&Path>) {
+    if let Some(home) = home {
+        cmd.env("LIMA_HOME", home);
+    }
+}
+
 /// Lima VM operations.
 pub struct LimaInstance;
 
 impl LimaInstance {
     /// Check if limactl is available on the system.
     pub fn is_lima_available() -> bool {
-        Command::new("limactl")
+        limactl()
             .arg("--version")
             .output()
             .map(|o| o.status.success())
             .unwrap_or(false)
     }
 
+    /// The installed limactl version line (e.g. `limactl version 1.0.3`),
+    /// or `None` if limactl isn't available.
+    pub fn version() -> Option<String> {
+        crate::util::command_version(&limactl_program(), "--version")
+    }
+
     /// List all Lima instances.
     pub fn list() -> Result<Vec<LimaInstanceInfo>> {
-        let output = Command::new("limactl")
+        let output = limactl()
             .arg("list")
             .arg("--json")
             .output()
-            .context("Failed to list Lima instances")?;
+            .map_err(|e| limactl_spawn_error(e, "Failed to list Lima instances"))?;
 
         if !output.status.success() {
             bail!("Failed to list Lima instances");
@@ -85,13 +153,24 @@ impl LimaInstance {
         parse_lima_instances(&output.stdout)
     }
 
+    /// Path of the SSH config Lima keeps for `name`, usable with
+    /// `ssh -F <path> lima-<name>`.
+    pub fn ssh_config(name: &str) -> Result<PathBuf> {
+        let instance = Self::list()?
+            .into_iter()
+            .find(|i| i.name == name)
+            .ok_or_else(|| anyhow!("Lima VM '{}' not found", name))?;
+        let dir = instance
+            .dir
+            .ok_or_else(|| anyhow!("limactl didn't report a directory for VM '{}'", name))?;
+        Ok(Path::new(&dir).join("ssh.config"))
+    }
+
     /// Stop a Lima VM by name. This is idempotent -- succeeds if the VM is already stopped.
     pub fn stop_by_name(name: &str) -> Result<()> {
-        let output = Command::new("limactl")
-            .arg("stop")
-            .arg(name)
-            .output()
-            .with_context(|| format!("Failed to execute limactl stop for '{}'", name))?;
+        let output = limactl().arg("stop").arg(name).output().map_err(|e| {
+            limactl_spawn_error(e, &format!("Failed to execute limactl stop for '{}'", name))
+        })?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -104,6 +183,475 @@ impl LimaInstance {
 
         Ok(())
     }
+
+    /// Delete a Lima VM by name (stopping it if needed) and its per-VM state directory.
+    pub fn delete_by_name(name: &str) -> Result<()> {
+        let output = limactl()
+            .args(["delete", "--force", name])
+            .output()
+            .map_err(|e| {
+                limactl_spawn_error(
+                    e,
+                    &format!("Failed to execute limactl delete for '{}'", name),
+                )
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Failed to delete Lima VM '{}': {}", name, stderr.trim());
+        }
+
+        if let Ok(state_dir) = super::mounts::lima_state_dir_path(name)
+            && state_dir.exists()
+            && let Err(e) = std::fs::remove_dir_all(&state_dir)
+        {
+            tracing::warn!(vm = %name, error = %e, "failed to clean up state dir");
+        }
+
+        Ok(())
+    }
+
+    /// Take a snapshot of a VM's current state, stored under `tag`.
+    pub fn create_snapshot(name: &str, tag: &str) -> Result<()> {
+        Self::snapshot_cmd("create", name, tag)
+    }
+
+    /// Restore a VM to a snapshot previously taken with `create_snapshot`.
+    pub fn apply_snapshot(name: &str, tag: &str) -> Result<()> {
+        Self::snapshot_cmd("apply", name, tag)
+    }
+
+    fn snapshot_cmd(action: &str, name: &str, tag: &str) -> Result<()> {
+        debug!(vm_name = %name, tag, action, "running limactl snapshot");
+        let output = limactl()
+            .args(["snapshot", action, name, "--tag", tag])
+            .output()
+            .map_err(|e| {
+                limactl_spawn_error(e, &format!("Failed to execute limactl snapshot {}", action))
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!(
+                "Failed to {} snapshot '{}' of Lima VM '{}': {}",
+                action,
+                tag,
+                name,
+                stderr.trim()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether the mounts recorded in the VM's `lima.yaml` differ from `mounts`.
+///
+/// Returns false when the instance config can't be read, leaving the VM as is.
+fn mounts_changed(vm_name: &str, mounts: &[Mount]) -> bool {
+    let Some(dir) = LimaInstance::list()
+        .ok()
+        .and_then(|list| list.into_iter().find(|i| i.name == vm_name))
+        .and_then(|info| info.dir)
+    else {
+        return false;
+    };
+    std::fs::read_to_string(Path::new(&dir).join("lima.yaml"))
+        .is_ok_and(|yaml| super::config::mounts_differ(&yaml, mounts))
+}
+
+/// CPU count and memory of a VM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VmResources {
+    pub cpus: u32,
+    pub memory: String,
+}
+
+impl VmResources {
+    /// What `sandbox.lima` asks for.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            cpus: config.sandbox.lima.cpus(),
+            memory: config.sandbox.lima.memory().to_string(),
+        }
+    }
+
+    /// What the VM's `lima.yaml` records, or `None` when it can't be read.
+    pub fn of_vm(vm_name: &str) -> Option<Self> {
+        let dir = LimaInstance::list()
+            .ok()?
+            .into_iter()
+            .find(|i| i.name == vm_name)?
+            .dir?;
+        let yaml = std::fs::read_to_string(Path::new(&dir).join("lima.yaml")).ok()?;
+        let (cpus, memory) = super::config::resources_in(&yaml)?;
+        Some(Self { cpus, memory })
+    }
+
+    /// Same CPU count and amount of memory, however the size is spelled.
+    pub fn matches(&self, other: &Self) -> bool {
+        self.cpus == other.cpus
+            && crate::config::parse_size(&self.memory) == crate::config::parse_size(&other.memory)
+    }
+}
+
+impl std::fmt::Display for VmResources {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} CPUs, {} memory", self.cpus, self.memory)
+    }
+}
+
+/// The VM's resources when they differ from `wanted`. Unreadable configs
+/// count as matching, so a reconfigure is never attempted on a guess.
+fn resources_changed(vm_name: &str, wanted: &VmResources) -> Option<VmResources> {
+    VmResources::of_vm(vm_name).filter(|current| !current.matches(wanted))
+}
+
+/// Set the CPU count and memory of a stopped VM for its next start.
+fn update_resources(vm_name: &str, resources: &VmResources) -> Result<()> {
+    let expr = format!(
+        ".cpus = {} | .memory = {}",
+        resources.cpus,
+        serde_json::to_string(&resources.memory)?
+    );
+    eprintln!("  Updating VM {} to {}", vm_name, resources);
+    let output = limactl()
+        .args(["edit", "--tty=false", "--set", &expr, vm_name])
+        .output()
+        .map_err(|e| limactl_spawn_error(e, "Failed to run limactl edit"))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to update resources for Lima VM '{}': {}",
+            vm_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    info!(vm_name = %vm_name, cpus = resources.cpus, memory = %resources.memory, "updated VM resources");
+    Ok(())
+}
+
+/// Rewrite the mounts of a stopped VM so the next start picks them up.
+fn update_mounts(vm_name: &str, mounts: &[Mount]) -> Result<()> {
+    let list = serde_json::to_string(&super::config::mounts_value(mounts))?;
+    let expr = format!(".mounts = {}", list);
+    eprintln!("  Updating mounts for VM {}", vm_name);
+    let output = limactl()
+        .args(["edit", "--tty=false", "--set", &expr, vm_name])
+        .output()
+        .map_err(|e| limactl_spawn_error(e, "Failed to run limactl edit"))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to update mounts for Lima VM '{}': {}",
+            vm_name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    info!(vm_name = %vm_name, "updated VM mounts");
+    Ok(())
+}
+
+/// The template locator new VMs for `worktree_path` are created from, or
+/// `None` for the built-in Debian config.
+pub fn resolved_template(config: &Config, worktree_path: &Path) -> Option<String> {
+    config
+        .sandbox
+        .lima
+        .template()
+        .map(|t| super::config::template_locator(t, worktree_path))
+}
+
+/// Render a template with its `base` templates inlined.
+fn load_template(locator: &str) -> Result<String> {
+    let output = limactl()
+        .args(["template", "copy", "--embed", locator, "-"])
+        .output()
+        .map_err(|e| limactl_spawn_error(e, "Failed to run limactl template copy"))?;
+    if !output.status.success() {
+        bail!(
+            "Failed to load Lima template '{}': {}",
+            locator,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// File in the VM's state dir recording which template it was created from.
+const TEMPLATE_RECORD: &str = "template";
+
+/// The template a VM was created from. VMs without a record were created
+/// from the built-in config.
+fn recorded_template(vm_name: &str) -> Option<String> {
+    let dir = super::mounts::lima_state_dir_path(vm_name).ok()?;
+    std::fs::read_to_string(dir.join(TEMPLATE_RECORD))
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn record_template(vm_name: &str, template: Option<&str>) -> Result<()> {
+    let path = super::mounts::lima_state_dir(vm_name)?.join(TEMPLATE_RECORD);
+    match template {
+        Some(template) => std::fs::write(&path, template)?,
+        None if path.exists() => std::fs::remove_file(&path)?,
+        None => {}
+    }
+    Ok(())
+}
+
+/// Stage of a VM boot, inferred from `limactl start` output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BootPhase {
+    Creating,
+    Starting,
+    WaitingForSsh,
+}
+
+impl BootPhase {
+    fn as_str(self) -> &'static str {
+        match self {
+            BootPhase::Creating => "creating",
+            BootPhase::Starting => "starting",
+            BootPhase::WaitingForSsh => "waiting-for-ssh",
+        }
+    }
+
+    /// The phase a limactl log line moves the boot into, if any.
+    fn from_log_line(line: &str) -> Option<Self> {
+        if line.contains("Waiting for the essential requirement")
+            || line.contains("Waiting for ssh")
+        {
+            Some(BootPhase::WaitingForSsh)
+        } else if line.contains("Starting the instance") {
+            Some(BootPhase::Starting)
+        } else {
+            None
+        }
+    }
+}
+
+/// Logs an `info!` heartbeat with the elapsed time and current phase while
+/// a VM boots, so a slow first boot doesn't look hung in the log. Stops
+/// when dropped.
+struct BootHeartbeat {
+    phase: Arc<Mutex<BootPhase>>,
+    _stop: mpsc::Sender<()>,
+}
+
+impl BootHeartbeat {
+    const INTERVAL: Duration = Duration::from_secs(15);
+
+    fn start(vm_name: &str, phase: BootPhase, started: Instant) -> Self {
+        let phase = Arc::new(Mutex::new(phase));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let vm_name = vm_name.to_string();
+        let current = phase.clone();
+
+        std::thread::spawn(move || {
+            while stopped.recv_timeout(Self::INTERVAL) == Err(mpsc::RecvTimeoutError::Timeout) {
+                let phase = *current.lock().unwrap_or_else(|e| e.into_inner());
+                info!(
+                    vm_name = %vm_name,
+                    phase = phase.as_str(),
+                    elapsed_secs = started.elapsed().as_secs(),
+                    "Lima VM still booting"
+                );
+            }
+        });
+
+        Self { phase, _stop: stop }
+    }
+}
+
+/// Run `limactl start`, streaming its output under a spinner and logging
+/// heartbeats until it finishes. Killed after `timeout`, with a
+/// [`crate::spinner::CommandTimedOut`] error.
+fn run_limactl_start(
+    vm_name: &str,
+    msg: &str,
+    cmd: Command,
+    phase: BootPhase,
+    timeout: Duration,
+) -> Result<()> {
+    let start = Instant::now();
+    let heartbeat = BootHeartbeat::start(vm_name, phase, start);
+    let current = heartbeat.phase.clone();
+
+    crate::spinner::with_streaming_command_formatted(msg, cmd, Some(timeout), move |line| {
+        if let Some(next) = BootPhase::from_log_line(line) {
+            let mut phase = current.lock().unwrap_or_else(|e| e.into_inner());
+            if *phase != next {
+                debug!(
+                    from = phase.as_str(),
+                    to = next.as_str(),
+                    "Lima VM boot phase"
+                );
+                *phase = next;
+            }
+        }
+        super::log_format::format_lima_log_line(line, &start)
+    })
+}
+
+/// Boot an existing, stopped VM with streaming progress output.
+fn start_stopped_vm(vm_name: &str, timeout: Duration) -> Result<()> {
+    let msg = format!("Starting Lima VM {}", vm_name);
+    let mut cmd = limactl();
+    cmd.args(["start", "--tty=false", "--progress", vm_name]);
+
+    if let Err(e) = run_limactl_start(vm_name, &msg, cmd, BootPhase::Starting, timeout) {
+        if e.is::<crate::spinner::CommandTimedOut>() {
+            return Err(abandon_boot(vm_name, timeout, false));
+        }
+        // Race condition: another process may have started the VM.
+        // Re-check state before failing.
+        if !matches!(check_vm_state(vm_name)?, VmState::Running) {
+            bail!("Failed to start Lima VM '{}'", vm_name);
+        }
+    }
+    Ok(())
+}
+
+/// Clean up after `limactl start` was killed for exceeding `timeout`, and
+/// describe what happened. The VM is deleted if this boot was `creating`
+/// it, since a half-created VM can't be started, and force-stopped
+/// otherwise, so the next run boots it from scratch either way.
+fn abandon_boot(vm_name: &str, timeout: Duration, creating: bool) -> anyhow::Error {
+    warn!(vm_name, ?timeout, creating, "Lima VM boot timed out");
+    let cleanup = if creating {
+        LimaInstance::delete_by_name(vm_name)
+    } else {
+        force_stop(vm_name)
+    };
+    let cleanup = match cleanup {
+        Ok(()) if creating => {
+            "The half-created VM was deleted; the next run creates it again.".to_string()
+        }
+        Ok(()) => "The VM was stopped; the next run starts it again.".to_string(),
+        Err(e) => {
+            warn!(vm_name, error = %e, "failed to clean up after boot timeout");
+            format!(
+                "Cleaning it up failed ({:#}); run `limactl delete --force {}` before retrying.",
+                e, vm_name
+            )
+        }
+    };
+    anyhow!(
+        "Lima VM '{}' didn't finish booting within {} (sandbox.lima.boot_timeout).\n\
+         {}\n\
+         Check its state with `limactl list`, or run `limactl start --debug {}` to see where the boot hangs.",
+        vm_name,
+        crate::util::format_elapsed_duration(timeout),
+        cleanup,
+        vm_name
+    )
+}
+
+/// Stop a VM without waiting for the guest to shut down, for one that
+/// never finished booting.
+fn force_stop(vm_name: &str) -> Result<()> {
+    let output = limactl()
+        .args(["stop", "--force", vm_name])
+        .output()
+        .map_err(|e| limactl_spawn_error(e, "Failed to execute limactl stop"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        bail!("Failed to stop Lima VM '{}': {}", vm_name, stderr.trim());
+    }
+    Ok(())
+}
+
+/// How many times [`wait_for_ssh`] tries to reach the guest.
+const SSH_PROBE_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry; later retries back off linearly.
+const SSH_PROBE_DELAY: Duration = Duration::from_secs(1);
+
+/// Wait until `limactl shell` can reach the guest over SSH.
+///
+/// Right after boot, a VM that limactl reports as running can still refuse
+/// SSH for a moment. Probing with `true` first keeps that startup race apart
+/// from the agent's own exit status, so the agent itself is never retried.
+/// Failures that don't look like transport errors are left for the agent run
+/// to report.
+pub fn wait_for_ssh(vm_name: &str) -> Result<()> {
+    for attempt in 1..=SSH_PROBE_ATTEMPTS {
+        let output = limactl()
+            .args(["shell", "--workdir", "/", vm_name, "true"])
+            .output()
+            .map_err(|e| limactl_spawn_error(e, "Failed to run limactl shell"))?;
+        if output.status.success() {
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !is_ssh_transport_failure(output.status.code(), &stderr) {
+            debug!(vm_name, code = ?output.status.code(), stderr = %stderr.trim(), "SSH probe failed, but not in transport; continuing");
+            return Ok(());
+        }
+        if attempt == SSH_PROBE_ATTEMPTS {
+            bail!(
+                "Lima VM '{}' is not reachable over SSH after {} attempts: {}",
+                vm_name,
+                attempt,
+                stderr.trim()
+            );
+        }
+        warn!(vm_name, attempt, stderr = %stderr.trim(), "Lima VM not reachable over SSH yet; retrying");
+        std::thread::sleep(SSH_PROBE_DELAY * attempt);
+    }
+    Ok(())
+}
+
+/// Whether a failed `limactl shell` never reached the guest. ssh exits with
+/// 255 on its own errors, which `true` can't produce.
+fn is_ssh_transport_failure(code: Option<i32>, stderr: &str) -> bool {
+    const TRANSPORT_ERRORS: &[&str] = &[
+        "Connection refused",
+        "Connection reset",
+        "Connection timed out",
+        "kex_exchange_identification",
+        "ssh: connect to host",
+    ];
+    code == Some(255) || TRANSPORT_ERRORS.iter().any(|e| stderr.contains(e))
+}
+
+/// Check a user-supplied VM name against Lima's instance name rules:
+/// alphanumeric runs separated by single `.`, `_` or `-`.
+pub fn validate_instance_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name
+            .split(['.', '_', '-'])
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()));
+    if !valid {
+        bail!(
+            "Invalid Lima VM name '{}': use letters and digits, separated by single '.', '_' or '-'",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Make sure an existing VM picked by name is running, starting it if it's
+/// stopped. Unlike [`ensure_vm_running`], never creates the VM or touches its
+/// configuration: it belongs to the user.
+pub fn ensure_named_vm_running(config: &Config, vm_name: &str) -> Result<()> {
+    if !LimaInstance::is_lima_available() {
+        bail!(
+            "Lima backend is enabled but limactl is not installed.\n{}",
+            LIMA_INSTALL_HINT
+        );
+    }
+    validate_instance_name(vm_name)?;
+
+    match check_vm_state(vm_name)? {
+        VmState::Running => Ok(()),
+        VmState::Stopped => start_stopped_vm(vm_name, config.sandbox.lima.boot_timeout()?),
+        VmState::NotFound => bail!(
+            "Lima VM '{}' does not exist; --vm-name only uses existing VMs (see `limactl list`)",
+            vm_name
+        ),
+    }
 }
 
 /// Ensure a Lima VM is running for the given worktree.
@@ -114,19 +662,81 @@ impl LimaInstance {
 ///
 /// Returns the VM name for use by `wrap_for_lima()`.
 pub fn ensure_vm_running(config: &Config, worktree_path: &Path) -> Result<String> {
+    ensure_vm_running_with(config, worktree_path, false)
+}
+
+/// Like [`ensure_vm_running`], but with `recreate` an existing VM created
+/// from a different template is deleted and rebuilt, and a running VM whose
+/// `cpus` or `memory` differ from the config is restarted with the new
+/// values, instead of only triggering a warning.
+pub fn ensure_vm_running_with(
+    config: &Config,
+    worktree_path: &Path,
+    recreate: bool,
+) -> Result<String> {
     if !LimaInstance::is_lima_available() {
         bail!(
             "Lima backend is enabled but limactl is not installed.\n\
-             Install Lima: https://lima-vm.io/docs/installation/\n\
-             Or disable sandbox: set 'sandbox.enabled: false' in config."
+             {}\n\
+             Or disable sandbox: set 'sandbox.enabled: false' in config.",
+            LIMA_INSTALL_HINT
         );
     }
 
+    config.sandbox.validate_extra_mounts()?;
+    config.sandbox.lima.validate_resources()?;
+    let boot_timeout = config.sandbox.lima.boot_timeout()?;
+
     let isolation = config.sandbox.lima.isolation();
     let vm_name = super::instance_name(worktree_path, isolation.clone(), config)?;
+    let agent = crate::multiplexer::agent::resolve_profile(config.agent.as_deref()).name();
+    let mounts = super::generate_mounts(worktree_path, isolation, config, &vm_name, agent)?;
+
+    let template = resolved_template(config, worktree_path);
+    let resources = VmResources::from_config(config);
 
     debug!(vm_name = %vm_name, "checking Lima VM state");
-    let vm_state = check_vm_state(&vm_name)?;
+    let mut vm_state = check_vm_state(&vm_name)?;
+
+    if !matches!(vm_state, VmState::NotFound) {
+        let created_from = recorded_template(&vm_name);
+        if created_from != template {
+            let describe = |t: &Option<String>| t.clone().unwrap_or_else(|| "default".into());
+            if recreate {
+                eprintln!(
+                    "  Recreating VM {} from template {}",
+                    vm_name,
+                    describe(&template)
+                );
+                LimaInstance::delete_by_name(&vm_name)?;
+                vm_state = VmState::NotFound;
+            } else {
+                warn!(vm_name = %vm_name, "VM template is out of date");
+                eprintln!(
+                    "workmux: VM {} was created from template {} but the config asks for {}; run `workmux sandbox shell --recreate` to rebuild it",
+                    vm_name,
+                    describe(&created_from),
+                    describe(&template)
+                );
+            }
+        }
+    }
+
+    if let VmState::Running = vm_state
+        && let Some(current) = resources_changed(&vm_name, &resources)
+    {
+        if recreate {
+            eprintln!("  Restarting VM {} to apply {}", vm_name, resources);
+            LimaInstance::stop_by_name(&vm_name)?;
+            vm_state = VmState::Stopped;
+        } else {
+            warn!(vm_name = %vm_name, "VM resources are out of date");
+            eprintln!(
+                "workmux: VM {} has {} but the config asks for {}; run `workmux sandbox stop {}` to apply them on the next start, or `workmux sandbox shell --recreate` to restart it now",
+                vm_name, current, resources, vm_name
+            );
+        }
+    }
 
     match vm_state {
         VmState::Running => {
@@ -134,39 +744,31 @@ pub fn ensure_vm_running(config: &Config, worktree_path: &Path) -> Result<String
             if config.sandbox.lima.provision_script().is_some() {
                 info!(vm_name = %vm_name, "custom provision script only runs on first VM creation; recreate VM to apply changes");
             }
+            // Other agents may be using the VM, so don't restart it here
+            if mounts_changed(&vm_name, &mounts) {
+                warn!(vm_name = %vm_name, "VM mounts are out of date");
+                eprintln!(
+                    "workmux: mounts for VM {} changed since it started; run `workmux sandbox stop {}` to apply them on the next start",
+                    vm_name, vm_name
+                );
+            }
         }
         VmState::Stopped => {
             info!(vm_name = %vm_name, "starting stopped Lima VM");
             if config.sandbox.lima.provision_script().is_some() {
                 info!(vm_name = %vm_name, "custom provision script only runs on first VM creation; recreate VM to apply changes");
             }
-            let msg = format!("Starting Lima VM {}", vm_name);
-            let mut cmd = Command::new("limactl");
-            cmd.args(["start", "--tty=false", "--progress", &vm_name]);
-
-            let start = std::time::Instant::now();
-            match crate::spinner::with_streaming_command_formatted(&msg, cmd, move |line| {
-                super::log_format::format_lima_log_line(line, &start)
-            }) {
-                Ok(()) => {}
-                Err(_) => {
-                    // Race condition: another process may have started the VM.
-                    // Re-check state before failing.
-                    if matches!(check_vm_state(&vm_name)?, VmState::Running) {
-                        return Ok(vm_name);
-                    }
-                    bail!("Failed to start Lima VM '{}'", vm_name);
-                }
+            if mounts_changed(&vm_name, &mounts) {
+                update_mounts(&vm_name, &mounts)?;
             }
+            if resources_changed(&vm_name, &resources).is_some() {
+                update_resources(&vm_name, &resources)?;
+            }
+            start_stopped_vm(&vm_name, boot_timeout)?;
         }
         VmState::NotFound => {
             info!(vm_name = %vm_name, "creating new Lima VM");
 
-            let agent = crate::multiplexer::agent::resolve_profile(config.agent.as_deref()).name();
-
-            // Only generate config and mounts when we need to create a new VM
-            let mounts = super::generate_mounts(worktree_path, isolation, config, &vm_name, agent)?;
-
             eprintln!("  Mounts:");
             for m in &mounts {
                 if m.host_path == m.guest_path {
@@ -189,8 +791,16 @@ pub fn ensure_vm_running(config: &Config, worktree_path: &Path) -> Result<String
                     != DetectedToolchain::None
             };
 
-            let lima_config =
+            let mut lima_config =
                 super::generate_lima_config(&vm_name, &mounts, &config.sandbox, agent, needs_nix)?;
+            if let Some(locator) = &template {
+                eprintln!("  Template: {}", locator);
+                lima_config = super::config::apply_template(
+                    &lima_config,
+                    &load_template(locator)?,
+                    config.sandbox.image.is_some(),
+                )?;
+            }
 
             let config_path = std::env::temp_dir().join(format!("workmux-lima-{}.yaml", vm_name));
             std::fs::write(&config_path, &lima_config).with_context(|| {
@@ -198,7 +808,7 @@ pub fn ensure_vm_running(config: &Config, worktree_path: &Path) -> Result<String
             })?;
 
             let msg = format!("Creating Lima VM {}", vm_name);
-            let mut cmd = Command::new("limactl");
+            let mut cmd = limactl();
             cmd.args([
                 "start",
                 "--name",
@@ -208,11 +818,15 @@ pub fn ensure_vm_running(config: &Config, worktree_path: &Path) -> Result<String
                 &config_path.to_string_lossy(),
             ]);
 
-            let start = std::time::Instant::now();
-            match crate::spinner::with_streaming_command_formatted(&msg, cmd, move |line| {
-                super::log_format::format_lima_log_line(line, &start)
-            }) {
-                Ok(()) => {}
+            match run_limactl_start(&vm_name, &msg, cmd, BootPhase::Creating, boot_timeout) {
+                Ok(()) => {
+                    if let Err(e) = record_template(&vm_name, template.as_deref()) {
+                        warn!(vm_name = %vm_name, error = %e, "failed to record VM template");
+                    }
+                }
+                Err(e) if e.is::<crate::spinner::CommandTimedOut>() => {
+                    return Err(abandon_boot(&vm_name, boot_timeout, true));
+                }
                 Err(_) => {
                     // Race condition: another process may have created the VM.
                     // Re-check state before failing.
@@ -228,3 +842,79 @@ pub fn ensure_vm_running(config: &Config, worktree_path: &Path) -> Result<String
     info!(vm_name = %vm_name, "Lima VM ready");
     Ok(vm_name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lima_home_is_exported_to_limactl() {
+        let lima_home = |cmd: &Command| {
+            cmd.get_envs()
+                .find(|(key, _)| *key == "LIMA_HOME")
+                .and_then(|(_, value)| value.map(PathBuf::from))
+        };
+
+        let mut cmd = Command::new("limactl");
+        set_lima_home_env(&mut cmd, None);
+        assert_eq!(lima_home(&cmd), None);
+
+        set_lima_home_env(&mut cmd, Some(Path::new("/srv/lima")));
+        assert_eq!(lima_home(&cmd), Some(PathBuf::from("/srv/lima")));
+    }
+
+    #[test]
+    fn vm_resources_compare_memory_by_size() {
+        let wanted = VmResources {
+            cpus: 4,
+            memory: "4GiB".to_string(),
+        };
+        let same = VmResources {
+            cpus: 4,
+            memory: "4096MiB".to_string(),
+        };
+        assert!(wanted.matches(&same));
+        assert!(!wanted.matches(&VmResources { cpus: 8, ..same }));
+        assert!(!wanted.matches(&VmResources {
+            cpus: 4,
+            memory: "8GiB".to_string(),
+        }));
+        assert_eq!(wanted.to_string(), "4 CPUs, 4GiB memory");
+    }
+
+    #[test]
+    fn ssh_transport_failures_are_told_apart() {
+        assert!(is_ssh_transport_failure(Some(255), ""));
+        assert!(is_ssh_transport_failure(
+            Some(1),
+            "ssh: connect to host 127.0.0.1 port 60022: Connection refused"
+        ));
+        assert!(!is_ssh_transport_failure(Some(1), "bash: true: not found"));
+        assert!(!is_ssh_transport_failure(None, ""));
+    }
+
+    #[test]
+    fn boot_phase_follows_limactl_output() {
+        let line = r#"time="2026-02-06T07:30:37+02:00" level=info msg="Starting the instance \"wm-1\" with internal VM driver \"vz\"""#;
+        assert_eq!(BootPhase::from_log_line(line), Some(BootPhase::Starting));
+        let line = r#"time="2026-02-06T07:30:40+02:00" level=info msg="[hostagent] Waiting for the essential requirement 1 of 2: \"ssh\"""#;
+        assert_eq!(
+            BootPhase::from_log_line(line),
+            Some(BootPhase::WaitingForSsh)
+        );
+        assert_eq!(BootPhase::from_log_line("Downloading image"), None);
+    }
+
+    #[test]
+    fn validate_instance_name_follows_lima_rules() {
+        for name in ["default", "wm-abc123", "my.vm_2", "A1"] {
+            assert!(validate_instance_name(name).is_ok(), "{name:?} should pass");
+        }
+        for name in ["", "-vm", "vm-", "a--b", "my vm", "vm/../x", "ümlaut"] {
+            assert!(
+                validate_instance_name(name).is_err(),
+                "{name:?} should fail"
+            );
+        }
+    }
+}
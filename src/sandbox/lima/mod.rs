@@ -9,7 +9,11 @@ pub(crate) mod mounts;
 mod wrap;
 
 pub use config::generate_lima_config;
-pub use instance::{LimaInstance, LimaInstanceInfo, ensure_vm_running, parse_lima_instances};
+pub use instance::{
+    LimaInstance, LimaInstanceInfo, VmResources, apply_lima_home, ensure_named_vm_running,
+    ensure_vm_running, ensure_vm_running_with, limactl, limactl_program, limactl_spawn_error,
+    parse_lima_instances, resolved_template, set_lima_home, validate_instance_name, wait_for_ssh,
+};
 pub use mounts::{determine_project_root, generate_mounts};
 pub use wrap::wrap_for_lima;
 
@@ -48,7 +52,7 @@ fn sanitize_name(name: &str, max_len: usize) -> String {
 }
 
 /// Hash a key and return the first `len` hex characters (zero-padded).
-fn hash_key(key: &str, len: usize) -> String {
+pub(crate) fn hash_key(key: &str, len: usize) -> String {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
 
@@ -62,7 +66,7 @@ fn hash_key(key: &str, len: usize) -> String {
 /// Generate a unique instance name for a worktree based on isolation level.
 ///
 /// For project isolation, the name includes the project directory name for
-/// human readability: `wm-<project>-<hash8>`.
+/// human readability (see [`vm_name_for`]).
 /// For shared isolation, the name is a hash of "global": `wm-<hash8>`.
 pub fn instance_name(
     worktree: &Path,
@@ -80,23 +84,7 @@ pub fn instance_name(
             let canonical = project_root
                 .canonicalize()
                 .unwrap_or_else(|_| project_root.clone());
-            let key = canonical.to_string_lossy();
-
-            let hash = hash_key(&key, 8);
-
-            // Extract project directory name for human-readable prefix
-            // Budget: "wm-" (3) + project (up to 18) + "-" (1) + hash (8) = 30 max
-            let project_dir_name = canonical
-                .file_name()
-                .map(|n| n.to_string_lossy().to_string())
-                .unwrap_or_default();
-            let sanitized = sanitize_name(&project_dir_name, 18);
-
-            if sanitized.is_empty() {
-                format!("{}{}", VM_PREFIX, hash)
-            } else {
-                format!("{}{}-{}", VM_PREFIX, sanitized, hash)
-            }
+            vm_name_for(&canonical)
         }
     };
 
@@ -104,10 +92,71 @@ pub fn instance_name(
     Ok(name)
 }
 
+/// Derive the project-isolation VM name for a canonical project root.
+///
+/// The hash covers the full path, so two projects with the same directory
+/// name still get distinct VMs; the directory name is only there to make
+/// `limactl list` readable: `wm-<project>-<hash8>`.
+pub fn vm_name_for(project_root: &Path) -> String {
+    let hash = hash_key(&project_root.to_string_lossy(), 8);
+
+    // Budget: "wm-" (3) + project (up to 18) + "-" (1) + hash (8) = 30 max
+    let project_dir_name = project_root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let sanitized = sanitize_name(&project_dir_name, 18);
+
+    if sanitized.is_empty() {
+        format!("{}{}", VM_PREFIX, hash)
+    } else {
+        format!("{}{}-{}", VM_PREFIX, sanitized, hash)
+    }
+}
+
+/// Generate a snapshot tag for a worktree from its handle and a unix timestamp.
+pub fn snapshot_tag(handle: &str, timestamp: u64) -> String {
+    let sanitized = sanitize_name(handle, 40);
+    if sanitized.is_empty() {
+        format!("snapshot-{}", timestamp)
+    } else {
+        format!("{}-{}", sanitized, timestamp)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_limactl_spawn_error_not_found() {
+        let err = limactl_spawn_error(
+            std::io::Error::from(std::io::ErrorKind::NotFound),
+            "Failed to execute limactl shell",
+        );
+        let msg = format!("{:#}", err);
+        assert!(msg.contains("limactl not found in PATH"), "{}", msg);
+        assert!(msg.contains("brew install lima"), "{}", msg);
+    }
+
+    #[test]
+    fn test_limactl_spawn_error_other() {
+        let err = limactl_spawn_error(
+            std::io::Error::from(std::io::ErrorKind::PermissionDenied),
+            "Failed to execute limactl shell",
+        );
+        assert_eq!(err.to_string(), "Failed to execute limactl shell");
+    }
+
+    #[test]
+    fn test_snapshot_tag() {
+        assert_eq!(
+            snapshot_tag("Fix/Login_Bug", 1700000000),
+            "fix-login-bug-1700000000"
+        );
+        assert_eq!(snapshot_tag("---", 42), "snapshot-42");
+    }
+
     #[test]
     fn test_sanitize_name_basic() {
         assert_eq!(sanitize_name("workmux", 20), "workmux");
@@ -157,6 +206,41 @@ mod tests {
         assert_eq!(sanitize_name("___", 20), "");
     }
 
+    #[test]
+    fn test_vm_name_for_is_stable() {
+        let path = Path::new("/home/user/code/my-app");
+        assert_eq!(vm_name_for(path), vm_name_for(path));
+        assert!(vm_name_for(path).starts_with("wm-my-app-"));
+    }
+
+    #[test]
+    fn test_vm_name_for_same_dirname_different_paths() {
+        let a = vm_name_for(Path::new("/home/user/work/api"));
+        let b = vm_name_for(Path::new("/home/user/personal/api"));
+        assert_ne!(a, b);
+        assert!(a.starts_with("wm-api-") && b.starts_with("wm-api-"));
+    }
+
+    #[test]
+    fn test_vm_name_for_no_collisions_across_many_paths() {
+        let names: std::collections::HashSet<_> = (0..2000)
+            .map(|i| {
+                vm_name_for(
+                    &Path::new("/projects")
+                        .join(format!("p{}", i % 7))
+                        .join(i.to_string()),
+                )
+            })
+            .collect();
+        assert_eq!(names.len(), 2000);
+    }
+
+    #[test]
+    fn test_vm_name_for_unreadable_dirname() {
+        let name = vm_name_for(Path::new("/srv/___"));
+        assert_eq!(name.len(), VM_PREFIX.len() + 8);
+    }
+
     #[test]
     fn test_hash_key_deterministic() {
         let a = hash_key("test", 8);
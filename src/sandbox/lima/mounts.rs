@@ -142,7 +142,7 @@ fn get_state_dir() -> Result<PathBuf> {
 
 /// Get the host-side state directory for a Lima VM.
 /// Uses XDG state dir: $XDG_STATE_HOME/workmux/lima/<vm_name>/
-fn lima_state_dir(vm_name: &str) -> Result<PathBuf> {
+pub(super) fn lima_state_dir(vm_name: &str) -> Result<PathBuf> {
     let state_dir = get_state_dir()?.join("workmux/lima").join(vm_name);
     std::fs::create_dir_all(&state_dir)?;
     Ok(state_dir)
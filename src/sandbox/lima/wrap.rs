@@ -35,11 +35,13 @@ pub fn wrap_for_lima(
     // supervisor is not an interactive shell.
     let command = command.strip_prefix(' ').unwrap_or(command);
     // Pass the command as a single quoted argument. The sandbox supervisor
-    // (sandbox_run.rs) handles wrapping it in `sh -lc '...'` for limactl,
+    // (sandbox_run.rs) handles quoting it again for `limactl shell` (or `ssh`),
     // which is necessary because limactl/SSH flattens separate args.
+    // --force: panes created by workmux (`open -n`, sandboxed shell panes)
+    // intentionally run several supervisors in one worktree.
     // Prefix with space to prevent shell history entry (same as rewrite_agent_command)
     Ok(format!(
-        " workmux sandbox run '{}' -- '{}'",
+        " workmux sandbox run '{}' --force -- '{}'",
         shell_escape(&working_dir.to_string_lossy()),
         shell_escape(command)
     ))
@@ -104,6 +106,22 @@ mod tests {
         assert!(result.contains("-- 'claude -- \"$(cat PROMPT.md)\"'"));
     }
 
+    #[test]
+    fn test_wrap_preserves_newlines_and_substitutions() {
+        let config = Config::default();
+        let command = "claude --append-system-prompt 'one\ntwo\tthree' -- \"$(cat PROMPT.md)\"";
+        let result = wrap_for_lima(command, &config, "wm-abc12345", Path::new("/tmp/wt")).unwrap();
+
+        // Let a real shell parse the pane command and print its last argument
+        let args = result.replacen(" workmux sandbox run", "", 1);
+        let script = format!("last() {{ for a; do :; done; printf %s \"$a\"; }}; last {args}");
+        let output = std::process::Command::new("sh")
+            .args(["-c", &script])
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), command);
+    }
+
     #[test]
     fn test_wrap_with_spaces_in_path() {
         let config = Config::default();
@@ -157,7 +175,7 @@ mod tests {
         // Verify env var values with special characters are properly escaped
         let env_var = "MY_VAR";
         let val = "hello'world";
-        let flag = format!(" --setenv {}='{}'", env_var, shell_escape(&val));
+        let flag = format!(" --setenv {}='{}'", env_var, shell_escape(val));
         assert_eq!(flag, " --setenv MY_VAR='hello'\\''world'");
     }
 }
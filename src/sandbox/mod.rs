@@ -1,13 +1,19 @@
 //! Sandbox backends for running agents in isolated environments.
 
+pub(crate) mod cast;
 mod container;
+pub mod env_file;
+pub mod events;
 pub mod freshness;
 pub mod guest;
 pub(crate) mod host_exec_sandbox;
 pub mod lima;
 pub mod network_proxy;
+pub(crate) mod output;
 pub mod rpc;
 pub(crate) mod shims;
+pub(crate) mod stderr;
+pub(crate) mod tee;
 pub(crate) mod toolchain;
 
 pub use container::DEFAULT_IMAGE_REGISTRY;
@@ -0,0 +1,172 @@
+//! The agent's recent terminal output, kept by the supervisor with
+//! `sandbox run --buffer-output` and served over RPC by the `tail` method.
+//!
+//! Output is kept as raw bytes, escape sequences and all, exactly as the
+//! PTY relay copies it to the pane. Every byte ever written has an offset,
+//! so a caller polling with the `offset` of its last answer only gets what
+//! arrived since.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Bytes kept before the oldest are dropped.
+pub const CAPACITY: usize = 1024 * 1024;
+
+/// Ring buffer of the most recent output bytes. Cloning is cheap.
+#[derive(Clone, Default)]
+pub struct OutputBuffer(Arc<Mutex<Ring>>);
+
+#[derive(Default)]
+struct Ring {
+    bytes: VecDeque<u8>,
+    /// Offset of the byte after the last one written
+    end: u64,
+}
+
+/// Output read from an [`OutputBuffer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTail {
+    /// The output, decoded as UTF-8 with invalid bytes replaced
+    pub data: String,
+    /// Offset just past `data`; pass it as `since` to read on from here
+    pub offset: u64,
+    /// Whether output after `since` was skipped, because the ring dropped
+    /// it or `max_bytes` left it out
+    pub truncated: bool,
+}
+
+impl OutputBuffer {
+    pub fn push(&self, chunk: &[u8]) {
+        let mut ring = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        ring.bytes.extend(chunk);
+        let excess = ring.bytes.len().saturating_sub(CAPACITY);
+        ring.bytes.drain(..excess);
+        ring.end += chunk.len() as u64;
+    }
+
+    /// Output written after offset `since` (everything kept with `None`),
+    /// limited to its last `max_bytes`.
+    ///
+    /// A character split by the ring or the limit is left out at the start;
+    /// one still being written is left out at the end, and `offset` stops
+    /// before it so the next read picks it up whole.
+    pub fn tail(&self, since: Option<u64>, max_bytes: Option<u32>) -> OutputTail {
+        let ring = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let first = ring.end - ring.bytes.len() as u64;
+        let since = since.unwrap_or(first).min(ring.end);
+        let mut start = since.max(first);
+        if let Some(max) = max_bytes {
+            start = start.max(ring.end.saturating_sub(max as u64));
+        }
+
+        let bytes: Vec<u8> = ring
+            .bytes
+            .range((start - first) as usize..)
+            .copied()
+            .collect();
+        let head = bytes
+            .iter()
+            .take(3)
+            .take_while(|&&b| is_continuation(b))
+            .count();
+        let end = complete_len(&bytes).max(head);
+        OutputTail {
+            data: String::from_utf8_lossy(&bytes[head..end]).into_owned(),
+            offset: start + end as u64,
+            truncated: since < start,
+        }
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Length of `bytes` without a trailing UTF-8 sequence that's still missing
+/// bytes.
+fn complete_len(bytes: &[u8]) -> usize {
+    for back in 1..=bytes.len().min(4) {
+        let byte = bytes[bytes.len() - back];
+        if is_continuation(byte) {
+            continue;
+        }
+        let needed = match byte {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        return if needed > back {
+            bytes.len() - back
+        } else {
+            bytes.len()
+        };
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tail_reads_on_from_the_last_offset() {
+        let buffer = OutputBuffer::default();
+        buffer.push(b"\x1b[1mbuilding\x1b[0m\r\n");
+        let first = buffer.tail(None, None);
+        assert_eq!(first.data, "\x1b[1mbuilding\x1b[0m\r\n");
+        assert!(!first.truncated);
+
+        buffer.push(b"done\n");
+        let next = buffer.tail(Some(first.offset), None);
+        assert_eq!(next.data, "done\n");
+        assert_eq!(next.offset, first.offset + 5);
+
+        let idle = buffer.tail(Some(next.offset), None);
+        assert_eq!((idle.data.as_str(), idle.offset), ("", next.offset));
+        assert_eq!(buffer.tail(None, Some(3)).data, "ne\n");
+    }
+
+    #[test]
+    fn tail_reports_output_dropped_from_the_ring() {
+        let buffer = OutputBuffer::default();
+        buffer.push(&vec![b'a'; CAPACITY]);
+        buffer.push(b"bc");
+        let tail = buffer.tail(Some(0), None);
+        assert!(tail.truncated);
+        assert_eq!(tail.data.len(), CAPACITY);
+        assert!(tail.data.ends_with("abc"));
+        assert_eq!(tail.offset, CAPACITY as u64 + 2);
+    }
+
+    #[test]
+    fn tail_reports_output_left_out_by_the_limit() {
+        let buffer = OutputBuffer::default();
+        buffer.push(b"start ");
+        let since = buffer.tail(None, None).offset;
+        buffer.push(b"0123456789");
+
+        let tail = buffer.tail(Some(since), Some(4));
+        assert_eq!(tail.data, "6789");
+        assert!(tail.truncated);
+
+        let whole = buffer.tail(Some(since), Some(10));
+        assert_eq!(whole.data, "0123456789");
+        assert!(!whole.truncated);
+    }
+
+    #[test]
+    fn tail_keeps_characters_whole() {
+        let buffer = OutputBuffer::default();
+        let check = "\u{2713}".as_bytes();
+        buffer.push(b"ok ");
+        buffer.push(&check[..2]);
+        let partial = buffer.tail(None, None);
+        assert_eq!((partial.data.as_str(), partial.offset), ("ok ", 3));
+
+        buffer.push(&check[2..]);
+        assert_eq!(buffer.tail(Some(partial.offset), None).data, "\u{2713}");
+        // The limit cuts into the check mark, which is skipped
+        assert_eq!(buffer.tail(None, Some(2)).data, "");
+    }
+}
@@ -0,0 +1,338 @@
+//! HTTP/1.1 access to the RPC server, for local tools such as a browser
+//! dashboard (`sandbox.rpc_http`).
+//!
+//! Shares the listener with the native protocol: a connection whose first
+//! line is an HTTP request line is served here instead. Each connection
+//! carries one request:
+//!
+//! - `POST /rpc/<method>` with the method's params as a JSON body (empty for
+//!   methods without params) and `Authorization: Bearer <token>`.
+//! - The response body is the same newline-delimited response frames the
+//!   native protocol sends, streamed until the connection closes.
+//! - `OPTIONS` answers CORS preflights so pages on other origins can call
+//!   it; the token is still required for every `POST`.
+
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+use std::net::TcpStream;
+
+use super::{
+    MAX_REQUEST_LINE, ResponseWriter, RpcContext, RpcRequest, constant_time_eq, read_bounded_line,
+    serve_logged,
+};
+
+/// Most header lines accepted in one request.
+const MAX_HEADERS: usize = 64;
+
+/// Whether `line` looks like an HTTP request line, e.g.
+/// `POST /rpc/heartbeat HTTP/1.1`.
+pub(super) fn is_request_line(line: &str) -> bool {
+    let mut parts = line.trim_end().split(' ');
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    !method.is_empty()
+        && method.bytes().all(|b| b.is_ascii_uppercase())
+        && target.starts_with('/')
+        && version.starts_with("HTTP/1.")
+}
+
+/// A request the server refused, as an HTTP status and message.
+#[derive(Debug, PartialEq)]
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+/// A parsed request: its method, path, headers and body.
+#[derive(Debug)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    /// The first header named `name`, compared case-insensitively.
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Serve the HTTP request whose request line was `first_line`, reading the
+/// rest from `reader` and answering on `stream`.
+pub(super) fn serve(
+    first_line: &str,
+    reader: &mut impl BufRead,
+    stream: &TcpStream,
+    ctx: &RpcContext,
+) -> Result<()> {
+    let request = match read_request(first_line, reader) {
+        Ok(request) => request,
+        Err(e) => return send_error(stream, &e),
+    };
+
+    if request.method == "OPTIONS" {
+        return send_head(
+            stream,
+            204,
+            &[
+                ("Access-Control-Allow-Methods", "POST"),
+                (
+                    "Access-Control-Allow-Headers",
+                    "Authorization, Content-Type",
+                ),
+                ("Access-Control-Max-Age", "600"),
+                ("Content-Length", "0"),
+            ],
+        );
+    }
+
+    let rpc = match authorize(&request, &ctx.token).and_then(|()| parse_rpc(&request)) {
+        Ok(rpc) => rpc,
+        Err(e) => return send_error(stream, &e),
+    };
+
+    // Long-running requests (exec, merge) stream for as long as they take
+    stream.set_read_timeout(None)?;
+    tracing::info!(?rpc, "RPC request received over HTTP");
+    send_head(stream, 200, &[("Content-Type", "application/x-ndjson")])?;
+    let mut writer = ResponseWriter::new(stream);
+    serve_logged(&rpc, ctx, &mut writer)?;
+    let _ = stream.shutdown(std::net::Shutdown::Write);
+    Ok(())
+}
+
+/// Read the headers and body following `first_line`.
+fn read_request(first_line: &str, reader: &mut impl BufRead) -> Result<HttpRequest, HttpError> {
+    let mut parts = first_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    let mut line = String::new();
+    loop {
+        match read_bounded_line(reader, &mut line) {
+            Ok(Some(())) => {}
+            Ok(None) => return Err(HttpError::new(400, "Request ended before its headers")),
+            Err(e) => return Err(HttpError::new(431, format!("{:#}", e))),
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(HttpError::new(431, "Too many request headers"));
+        }
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(HttpError::new(400, format!("Malformed header: {}", line)));
+        };
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut request = HttpRequest {
+        method,
+        path,
+        headers,
+        body: Vec::new(),
+    };
+    if request.header("Transfer-Encoding").is_some() {
+        return Err(HttpError::new(
+            411,
+            "Chunked bodies aren't supported; send a Content-Length",
+        ));
+    }
+    let length = match request.header("Content-Length") {
+        None => 0,
+        Some(value) => value
+            .parse::<usize>()
+            .map_err(|_| HttpError::new(400, format!("Invalid Content-Length: {}", value)))?,
+    };
+    if length > MAX_REQUEST_LINE {
+        return Err(HttpError::new(
+            413,
+            format!("Request body exceeds {} byte limit", MAX_REQUEST_LINE),
+        ));
+    }
+    request.body = vec![0; length];
+    reader
+        .read_exact(&mut request.body)
+        .map_err(|e| HttpError::new(400, format!("Failed to read request body: {}", e)))?;
+    Ok(request)
+}
+
+/// Check the `Authorization: Bearer` header against the session token.
+fn authorize(request: &HttpRequest, token: &str) -> Result<(), HttpError> {
+    let supplied = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    match supplied {
+        Some(supplied) if constant_time_eq(supplied.as_bytes(), token.as_bytes()) => Ok(()),
+        _ => Err(HttpError::new(401, "Invalid token")),
+    }
+}
+
+/// The RPC request a `POST /rpc/<method>` stands for.
+fn parse_rpc(request: &HttpRequest) -> Result<RpcRequest, HttpError> {
+    let Some(method) = request.path.strip_prefix("/rpc/") else {
+        return Err(HttpError::new(
+            404,
+            format!("No route for {}", request.path),
+        ));
+    };
+    if request.method != "POST" {
+        return Err(HttpError::new(405, "RPC methods are called with POST"));
+    }
+    if method == "handshake" {
+        return Err(HttpError::new(
+            400,
+            "HTTP requests don't need a handshake; call ping to check the protocol version",
+        ));
+    }
+
+    let mut frame = json!({ "method": method });
+    if !request.body.iter().all(u8::is_ascii_whitespace) {
+        let params: Value = serde_json::from_slice(&request.body)
+            .map_err(|e| HttpError::new(400, format!("Invalid JSON body: {}", e)))?;
+        frame["params"] = params;
+    }
+    serde_json::from_value(frame)
+        .map_err(|e| HttpError::new(400, format!("Invalid request for {}: {}", method, e)))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Error",
+    }
+}
+
+/// Write the status line and headers. Every response closes the connection
+/// and allows any origin, since the token is what grants access.
+fn send_head(mut stream: &TcpStream, status: u16, headers: &[(&str, &str)]) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nConnection: close\r\nAccess-Control-Allow-Origin: *\r\n",
+        status,
+        reason(status)
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn send_error(mut stream: &TcpStream, error: &HttpError) -> Result<()> {
+    tracing::debug!(status = error.status, message = %error.message, "HTTP RPC request refused");
+    let body = json!({ "error": { "message": error.message } }).to_string() + "\n";
+    send_head(
+        stream,
+        error.status,
+        &[
+            ("Content-Type", "application/json"),
+            ("Content-Length", &body.len().to_string()),
+        ],
+    )?;
+    stream.write_all(body.as_bytes())?;
+    let _ = stream.shutdown(std::net::Shutdown::Write);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::rpc::RpcRequest;
+
+    fn parse(raw: &str) -> Result<HttpRequest, HttpError> {
+        let (first, rest) = raw.split_once('\n').unwrap();
+        read_request(first, &mut rest.as_bytes())
+    }
+
+    #[test]
+    fn detects_request_lines() {
+        assert!(is_request_line("POST /rpc/heartbeat HTTP/1.1\r\n"));
+        assert!(is_request_line("GET / HTTP/1.0\n"));
+        assert!(!is_request_line(
+            r#"{"id":1,"token":"t","method":"heartbeat"}"#
+        ));
+        assert!(!is_request_line("post /rpc/heartbeat HTTP/1.1"));
+        assert!(!is_request_line("POST rpc HTTP/1.1"));
+    }
+
+    #[test]
+    fn parses_post_into_rpc_request() {
+        let request = parse(
+            "POST /rpc/set_title HTTP/1.1\r\n\
+             authorization: Bearer s3cret\r\n\
+             Content-Length: 17\r\n\
+             \r\n\
+             {\"title\":\"docs\"}\n",
+        )
+        .unwrap();
+        assert_eq!(authorize(&request, "s3cret"), Ok(()));
+        assert_eq!(authorize(&request, "other").unwrap_err().status, 401);
+        assert!(matches!(
+            parse_rpc(&request).unwrap(),
+            RpcRequest::SetTitle { title } if title == "docs"
+        ));
+
+        let heartbeat = parse("POST /rpc/heartbeat HTTP/1.1\r\n\r\n").unwrap();
+        assert!(matches!(
+            parse_rpc(&heartbeat).unwrap(),
+            RpcRequest::Heartbeat
+        ));
+        assert_eq!(authorize(&heartbeat, "s3cret").unwrap_err().status, 401);
+    }
+
+    #[test]
+    fn rejects_bad_routes_and_bodies() {
+        let status = |raw: &str| match parse(raw) {
+            Ok(request) => parse_rpc(&request).unwrap_err().status,
+            Err(e) => e.status,
+        };
+        assert_eq!(status("GET /rpc/heartbeat HTTP/1.1\r\n\r\n"), 405);
+        assert_eq!(status("POST /status HTTP/1.1\r\n\r\n"), 404);
+        assert_eq!(status("POST /rpc/nope HTTP/1.1\r\n\r\n"), 400);
+        assert_eq!(status("POST /rpc/handshake HTTP/1.1\r\n\r\n"), 400);
+        assert_eq!(
+            status("POST /rpc/set_title HTTP/1.1\r\nContent-Length: 3\r\n\r\n{x}"),
+            400
+        );
+        assert_eq!(
+            status("POST /rpc/heartbeat HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            411
+        );
+        let huge = format!(
+            "POST /rpc/heartbeat HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_REQUEST_LINE + 1
+        );
+        assert_eq!(status(&huge), 413);
+    }
+}
@@ -0,0 +1,3157 @@
+//! TCP RPC protocol for guest-host communication in sandboxed environments.
+//!
+//! The host-side supervisor runs an RPC server on a random port. The guest
+//! workmux binary connects via a host-internal address and speaks
+//! newline-delimited JSON frames:
+//!
+//! - requests: `{"id", "token", "method", "params"}`
+//! - responses: `{"id", "result"}` or `{"id", "error": {"message"}}`
+//!
+//! The first request on every connection must be `handshake`, carrying the
+//! guest's [`PROTOCOL_VERSION`]. Mismatched versions are rejected up front so
+//! an outdated guest or host binary fails with a clear error.
+//!
+//! The message types live in [`protocol`], which third-party guests can
+//! target through `workmux sandbox rpc-schema`. With `sandbox.rpc_http`, the
+//! same listener also serves the methods over HTTP (see [`http`]).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions, Permissions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::ops::RangeInclusive;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::multiplexer::Multiplexer;
+use crate::state::StateStore;
+
+mod http;
+pub mod protocol;
+pub mod request_log;
+
+pub use protocol::{PROTOCOL_VERSION, RpcRequest, RpcResponse, StatusSnapshot};
+use protocol::{RequestFrame, ResponseFrame};
+
+fn version_mismatch(guest: u32) -> String {
+    format!(
+        "RPC protocol mismatch: guest speaks v{}, host speaks v{}. \
+         Make sure the guest and host run the same workmux version.",
+        guest, PROTOCOL_VERSION
+    )
+}
+
+// ── Server ──────────────────────────────────────────────────────────────
+
+/// Context available to RPC request handlers.
+pub struct RpcContext {
+    /// The tmux/wezterm pane ID of the supervisor pane.
+    pub pane_id: String,
+    /// Path to the worktree being supervised.
+    pub worktree_path: PathBuf,
+    /// Multiplexer backend (resolved once at startup).
+    pub mux: Arc<dyn Multiplexer>,
+    /// Shared secret for authenticating RPC requests.
+    pub token: String,
+    /// Commands allowed for host-exec.
+    pub allowed_commands: std::collections::HashSet<String>,
+    /// Resolved toolchain for host-exec command wrapping.
+    pub detected_toolchain: crate::sandbox::toolchain::DetectedToolchain,
+    /// Whether to allow host-exec without bwrap on Linux.
+    pub allow_unsandboxed_host_exec: bool,
+    /// Where to report status changes (`sandbox run --events`).
+    pub events: Option<crate::sandbox::events::EventLog>,
+    /// The agent's stderr, when kept apart (`sandbox run --buffer-stderr`).
+    pub stderr: Option<crate::sandbox::stderr::StderrBuffer>,
+    /// The agent's recent output (`sandbox run --buffer-output`).
+    pub output: Option<crate::sandbox::output::OutputBuffer>,
+    /// The status the agent last reported, also written to the supervisor's
+    /// state record for `workmux sandbox info`.
+    pub status: Mutex<Option<StatusSnapshot>>,
+    /// Recent requests, for `workmux sandbox rpc-log`.
+    pub requests: request_log::RequestLog,
+}
+
+/// Where the RPC server listens unless `sandbox.rpc_bind` says otherwise.
+///
+/// Lima's user-mode network forwards `host.lima.internal` to the host's
+/// loopback, as Docker Desktop does for `host.docker.internal`, so guests
+/// can reach a loopback-only listener while the rest of the network can't.
+/// The per-session token still guards every request; the narrow bind keeps
+/// it from being the only thing between the LAN and host-exec.
+pub const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+/// Where the RPC server listens for container sandboxes on Linux unless
+/// `sandbox.rpc_bind` says otherwise.
+///
+/// Docker Engine and rootful Podman on Linux reach the host over a bridge
+/// (`host-gateway`, usually `docker0`) rather than its loopback, so a
+/// loopback-only listener is unreachable from the container there.
+pub const LINUX_CONTAINER_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+
+/// How many times `RpcServer::bind` retries after `AddrInUse`.
+const BIND_ATTEMPTS: u32 = 5;
+const BIND_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// Default cap on concurrent RPC connections. One sandbox session typically
+/// uses a single connection, so this is generous while still preventing
+/// thread exhaustion from connection floods.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 16;
+
+/// TCP RPC server that accepts guest connections.
+pub struct RpcServer {
+    listener: TcpListener,
+    port: u16,
+    max_connections: usize,
+    /// Also accept HTTP requests (`sandbox.rpc_http`)
+    http: bool,
+}
+
+impl RpcServer {
+    /// Bind to a random port on `addr`, normally [`DEFAULT_BIND_ADDR`].
+    pub fn bind(addr: IpAddr) -> Result<Self> {
+        for attempt in 1..=BIND_ATTEMPTS {
+            match TcpListener::bind((addr, 0)) {
+                Ok(listener) => return Self::from_listener(listener),
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                    debug!(attempt, "RPC bind collided, retrying");
+                    thread::sleep(BIND_RETRY_DELAY);
+                }
+                Err(e) => return Err(e).context("Failed to bind RPC listener"),
+            }
+        }
+        anyhow::bail!(
+            "Failed to bind RPC listener: no free ephemeral port after {} attempts",
+            BIND_ATTEMPTS
+        )
+    }
+
+    /// Bind to a free port within `range` (inclusive) on `addr`.
+    ///
+    /// Ports are tried starting from a random offset so that supervisors
+    /// launched at the same time don't race for the same port.
+    pub fn bind_on(addr: IpAddr, range: RangeInclusive<u16>) -> Result<Self> {
+        let (start, end) = (*range.start(), *range.end());
+        if start == 0 || start > end {
+            anyhow::bail!("Invalid RPC port range {}-{}", start, end);
+        }
+
+        let len = u32::from(end - start) + 1;
+        let mut seed = [0u8; 4];
+        getrandom::fill(&mut seed).expect("failed to get random bytes");
+        let offset = u32::from_le_bytes(seed) % len;
+
+        for i in 0..len {
+            let port = start + ((offset + i) % len) as u16;
+            match TcpListener::bind((addr, port)) {
+                Ok(listener) => return Self::from_listener(listener),
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse => continue,
+                Err(e) => {
+                    return Err(e)
+                        .with_context(|| format!("Failed to bind RPC listener on port {}", port));
+                }
+            }
+        }
+        anyhow::bail!(
+            "Failed to bind RPC listener: all ports in {}-{} are in use",
+            start,
+            end
+        )
+    }
+
+    fn from_listener(listener: TcpListener) -> Result<Self> {
+        let port = listener.local_addr()?.port();
+        info!(port, "RPC server bound");
+        Ok(Self {
+            listener,
+            port,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            http: false,
+        })
+    }
+
+    /// Limit how many connections are handled at once. Connections beyond
+    /// the limit get an over-capacity error and are closed.
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = max.max(1);
+        self
+    }
+
+    /// Also serve HTTP requests on the same port, authenticated with the
+    /// same token. The native protocol keeps working either way.
+    pub fn with_http(mut self, enabled: bool) -> Self {
+        self.http = enabled;
+        self
+    }
+
+    /// Get the port the server is listening on.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The address the server is listening on.
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Spawn a background thread that accepts connections and dispatches handlers.
+    ///
+    /// The returned [`RpcHandle`] stops the server when shut down or dropped,
+    /// releasing the listening port.
+    pub fn spawn(self, ctx: Arc<RpcContext>) -> RpcHandle {
+        let port = self.port;
+        let wake_addr = self
+            .listener
+            .local_addr()
+            .map(reachable_addr)
+            .unwrap_or_else(|_| SocketAddr::from((Ipv4Addr::LOCALHOST, port)));
+        let max_connections = self.max_connections;
+        let http = self.http;
+        let stopping = Arc::new(AtomicBool::new(false));
+        let connections: Arc<Mutex<HashMap<u64, TcpStream>>> = Arc::default();
+
+        let accept = {
+            let stopping = Arc::clone(&stopping);
+            let connections = Arc::clone(&connections);
+            thread::spawn(move || {
+                let mut next_id = 0u64;
+                accept_loop(self.listener.incoming(), &stopping, |stream| {
+                    let mut active = connections.lock().unwrap();
+                    if active.len() >= max_connections {
+                        drop(active);
+                        warn!(max_connections, "RPC connection limit reached, rejecting");
+                        reject_over_capacity(stream, max_connections);
+                        return;
+                    }
+                    let id = next_id;
+                    next_id += 1;
+                    // Keep a clone so shutdown can abort the connection
+                    if let Ok(clone) = stream.try_clone() {
+                        active.insert(id, clone);
+                    }
+                    drop(active);
+
+                    let ctx = Arc::clone(&ctx);
+                    let connections = Arc::clone(&connections);
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream, &ctx, http) {
+                            debug!(error = %e, "RPC connection ended");
+                        }
+                        connections.lock().unwrap().remove(&id);
+                    });
+                });
+            })
+        };
+
+        RpcHandle {
+            port,
+            wake_addr,
+            stopping,
+            connections,
+            accept: Some(accept),
+        }
+    }
+}
+
+/// Accept connections from `incoming` until the server is stopping or the
+/// listener fails for good, passing each one to `on_stream`.
+///
+/// Transient failures (out of file descriptors, a client giving up mid
+/// handshake) are retried after [`AcceptBackoff`], so the loop neither spins
+/// nor gives up while the condition lasts.
+fn accept_loop<S>(
+    incoming: impl Iterator<Item = io::Result<S>>,
+    stopping: &AtomicBool,
+    mut on_stream: impl FnMut(S),
+) {
+    let mut backoff = AcceptBackoff::default();
+    for stream in incoming {
+        if stopping.load(Ordering::SeqCst) {
+            debug!("RPC server stopping, no longer accepting connections");
+            break;
+        }
+        match stream {
+            Ok(stream) => {
+                if backoff.failures >= AcceptBackoff::WARN_AFTER {
+                    info!(failures = backoff.failures, "RPC accept recovered");
+                }
+                backoff.failures = 0;
+                on_stream(stream);
+            }
+            Err(e) if is_transient_accept_error(&e) => {
+                let delay = backoff.next_delay();
+                if backoff.failures == AcceptBackoff::WARN_AFTER {
+                    warn!(error = %e, failures = backoff.failures, "RPC accept keeps failing, backing off");
+                } else {
+                    debug!(error = %e, ?delay, "RPC accept failed, retrying");
+                }
+                thread::sleep(delay);
+            }
+            Err(e) => {
+                debug!(error = %e, "RPC accept error, shutting down");
+                break;
+            }
+        }
+    }
+}
+
+/// Whether a failed `accept()` may succeed if retried: resource exhaustion
+/// or a connection that died before it was accepted. Anything else (e.g. the
+/// listener being closed) ends the accept loop.
+fn is_transient_accept_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::OutOfMemory
+    ) || matches!(
+        e.raw_os_error(),
+        Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM | libc::EPROTO)
+    )
+}
+
+/// Exponential backoff with jitter between retries of a failing `accept()`.
+#[derive(Default)]
+struct AcceptBackoff {
+    /// Consecutive failures so far
+    failures: u32,
+}
+
+impl AcceptBackoff {
+    const BASE: Duration = Duration::from_millis(10);
+    const MAX: Duration = Duration::from_secs(1);
+    /// Failures in a row before it's logged as a warning
+    const WARN_AFTER: u32 = 5;
+
+    /// Record a failure and return how long to wait before the next attempt:
+    /// `BASE` doubled per consecutive failure up to `MAX`, of which a random
+    /// half is kept so concurrent servers don't retry in lockstep.
+    fn next_delay(&mut self) -> Duration {
+        self.failures += 1;
+        let exp = Self::BASE
+            .saturating_mul(1 << (self.failures - 1).min(16))
+            .min(Self::MAX);
+        let mut seed = [0u8; 4];
+        let _ = getrandom::fill(&mut seed);
+        let jitter = f64::from(u32::from_le_bytes(seed)) / f64::from(u32::MAX);
+        exp.mul_f64(0.5 + jitter / 2.0)
+    }
+}
+
+/// Handle to a running RPC server.
+///
+/// Call [`RpcHandle::shutdown`] to stop the server gracefully. Dropping the
+/// handle also stops it, but aborts in-flight requests immediately.
+pub struct RpcHandle {
+    port: u16,
+    /// Where to connect to wake the accept loop, from [`reachable_addr`]
+    wake_addr: SocketAddr,
+    stopping: Arc<AtomicBool>,
+    connections: Arc<Mutex<HashMap<u64, TcpStream>>>,
+    accept: Option<thread::JoinHandle<()>>,
+}
+
+impl RpcHandle {
+    /// How long in-flight requests may keep running after shutdown starts.
+    const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+
+    /// Stop accepting connections and release the listening port.
+    ///
+    /// In-flight requests get a short grace period to finish; connections
+    /// still open after that are closed.
+    pub fn shutdown(mut self) {
+        self.stop(Self::SHUTDOWN_GRACE);
+    }
+
+    fn stop(&mut self, grace: Duration) {
+        let Some(accept) = self.accept.take() else {
+            return;
+        };
+
+        // The accept loop only observes the flag after `accept()` returns,
+        // so wake it with a throwaway connection. If that can't get through,
+        // joining would block forever; leave the thread to exit with us.
+        self.stopping.store(true, Ordering::SeqCst);
+        match TcpStream::connect_timeout(&self.wake_addr, Duration::from_secs(1)) {
+            Ok(_) => {
+                if accept.join().is_err() {
+                    warn!("RPC accept thread panicked");
+                }
+                debug!(port = self.port, "RPC listener closed");
+            }
+            Err(e) => {
+                warn!(addr = %self.wake_addr, error = %e, "couldn't wake the RPC accept loop; leaving it running");
+            }
+        }
+
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline && !self.connections.lock().unwrap().is_empty() {
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let remaining: Vec<TcpStream> = self
+            .connections
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(_, s)| s)
+            .collect();
+        if !remaining.is_empty() {
+            debug!(
+                count = remaining.len(),
+                "aborting in-flight RPC connections"
+            );
+        }
+        for stream in remaining {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+    }
+}
+
+impl Drop for RpcHandle {
+    fn drop(&mut self) {
+        self.stop(Duration::ZERO);
+    }
+}
+
+/// The address a client on this host connects to for a listener bound to
+/// `addr`: `addr` itself, or the loopback address of the same family when
+/// it's a wildcard (`0.0.0.0`, `::`).
+fn reachable_addr(addr: SocketAddr) -> SocketAddr {
+    let ip = match addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(std::net::Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+    SocketAddr::new(ip, addr.port())
+}
+
+/// Turn away a connection that arrived while the server is full.
+///
+/// Runs on the accept thread, so every step is bounded by a short timeout.
+/// The guest's handshake is drained first: closing with unread input would
+/// reset the connection and could discard the error before it's read.
+fn reject_over_capacity(mut stream: TcpStream, max_connections: usize) {
+    const TIMEOUT: Duration = Duration::from_millis(50);
+
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let _ = stream.set_write_timeout(Some(TIMEOUT));
+    let mut discard = [0u8; 4096];
+    let _ = io::Read::read(&mut stream, &mut discard);
+
+    let mut writer = ResponseWriter::new(&stream);
+    let _ = writer.send(&RpcResponse::Error {
+        message: format!(
+            "RPC server is at capacity ({} connections); try again later",
+            max_connections
+        ),
+    });
+    let _ = stream.shutdown(Shutdown::Write);
+}
+
+/// Random bytes in a token unless `sandbox.token_bytes` says otherwise.
+pub const DEFAULT_TOKEN_BYTES: usize = 32;
+/// Fewest random bytes accepted for a token: 128 bits of entropy.
+pub const MIN_TOKEN_BYTES: usize = 16;
+/// Most random bytes accepted for a token, to keep it a sane env var.
+pub const MAX_TOKEN_BYTES: usize = 256;
+
+/// Generate a random token for RPC authentication with
+/// [`DEFAULT_TOKEN_BYTES`] of entropy.
+pub fn generate_token() -> String {
+    generate_token_with_bytes(DEFAULT_TOKEN_BYTES)
+}
+
+/// Generate a token from `bytes` random bytes (`8 * bytes` bits of entropy).
+///
+/// The token is lowercase hex, so it needs no quoting in `--setenv
+/// WM_RPC_TOKEN=...`, shell strings or JSON.
+pub fn generate_token_with_bytes(bytes: usize) -> String {
+    let mut buf = vec![0u8; bytes];
+    getrandom::fill(&mut buf).expect("failed to get random bytes");
+    buf.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Whether `token` could have come from [`generate_token_with_bytes`].
+fn is_valid_token(token: &str) -> bool {
+    token.len().is_multiple_of(2)
+        && (MIN_TOKEN_BYTES * 2..=MAX_TOKEN_BYTES * 2).contains(&token.len())
+        && token
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Read a pre-provisioned token for `sandbox run --token-file`, ignoring
+/// surrounding whitespace.
+///
+/// Like generated tokens it must need no quoting, so only letters, digits,
+/// `-`, `_` and `.` are allowed, and it must be at least as long as a
+/// [`MIN_TOKEN_BYTES`] token.
+pub fn read_token_file(path: &Path) -> Result<String> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read RPC token file {}", path.display()))?;
+    let token = content.trim();
+    if let Some(c) = token
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+    {
+        anyhow::bail!(
+            "RPC token in {} contains {:?}; only letters, digits, '-', '_' and '.' are allowed",
+            path.display(),
+            c
+        );
+    }
+    let (min, max) = (MIN_TOKEN_BYTES * 2, MAX_TOKEN_BYTES * 2);
+    if !(min..=max).contains(&token.len()) {
+        anyhow::bail!(
+            "RPC token in {} is {} characters long; it must be {} to {}",
+            path.display(),
+            token.len(),
+            min,
+            max
+        );
+    }
+    Ok(token.to_string())
+}
+
+/// Where a supervisor saves its RPC credentials, in the worktree's state
+/// directory ([`StateStore::worktree_dir`]).
+///
+/// Not in the worktree: the guest can write there, so it could swap the
+/// file for a symlink to a host file, or rewrite the token and port that
+/// host-side commands trust.
+const SESSION_FILE: &str = "sandbox.json";
+
+/// RPC credentials saved for a worktree so a restarted supervisor keeps
+/// serving guests that still hold them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedSession {
+    pub token: String,
+    pub port: u16,
+    /// When the token was generated (Unix seconds); it rotates once it's
+    /// older than `sandbox.rpc_token_ttl`
+    pub created_at: u64,
+    /// Address the server is bound to; loopback for sessions saved before
+    /// it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bind: Option<IpAddr>,
+}
+
+impl SavedSession {
+    /// Connect to the supervisor that saved this session, from the host.
+    pub fn connect(&self) -> Result<RpcClient> {
+        let bound = SocketAddr::new(self.bind.unwrap_or(DEFAULT_BIND_ADDR), self.port);
+        let addr = reachable_addr(bound);
+        RpcClient::connect(&addr.ip().to_string(), addr.port(), &self.token)
+    }
+}
+
+/// The state directory sessions for `worktree_root` are saved in.
+fn session_dir(worktree_root: &Path) -> Result<PathBuf> {
+    StateStore::new()?.worktree_dir(worktree_root)
+}
+
+/// Reuse the token saved for `worktree_root` while it's younger than `ttl`
+/// and `token_bytes` long, otherwise generate a fresh one.
+///
+/// Returns a session to pass to [`save_session`] once the server is bound.
+/// Its `port` is the previously used one (0 for a new token), which the
+/// caller should try to bind again.
+pub fn load_or_generate_token(
+    worktree_root: &Path,
+    ttl: Duration,
+    token_bytes: usize,
+) -> SavedSession {
+    let saved = session_dir(worktree_root)
+        .ok()
+        .and_then(|dir| load_session_in(&dir));
+    reuse_or_generate(saved, ttl, token_bytes)
+}
+
+fn reuse_or_generate(
+    saved: Option<SavedSession>,
+    ttl: Duration,
+    token_bytes: usize,
+) -> SavedSession {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    match saved {
+        Some(session)
+            if session.created_at <= now
+                && now - session.created_at < ttl.as_secs()
+                && session.token.len() == token_bytes * 2 =>
+        {
+            debug!(port = session.port, "reusing saved RPC token");
+            session
+        }
+        expired => {
+            if expired.is_some() {
+                info!("saved RPC token expired or resized, rotating");
+            }
+            SavedSession {
+                token: generate_token_with_bytes(token_bytes),
+                port: 0,
+                created_at: now,
+                bind: None,
+            }
+        }
+    }
+}
+
+/// The session saved for `worktree_root`, if there is a well-formed one.
+pub fn load_session(worktree_root: &Path) -> Option<SavedSession> {
+    load_session_in(&session_dir(worktree_root).ok()?)
+}
+
+fn load_session_in(dir: &Path) -> Option<SavedSession> {
+    fs::read_to_string(dir.join(SESSION_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str::<SavedSession>(&content).ok())
+        .filter(|s| is_valid_token(&s.token))
+}
+
+/// Save the session for [`load_or_generate_token`]. The file holds a secret,
+/// so it's readable by the owner only.
+pub fn save_session(worktree_root: &Path, session: &SavedSession) -> Result<()> {
+    save_session_in(&session_dir(worktree_root)?, session)
+}
+
+fn save_session_in(dir: &Path, session: &SavedSession) -> Result<()> {
+    let path = dir.join(SESSION_FILE);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    // mode() only applies when the file is created
+    file.set_permissions(Permissions::from_mode(0o600))?;
+    file.write_all(serde_json::to_string(session)?.as_bytes())?;
+    Ok(())
+}
+
+/// Constant-time byte comparison to prevent timing side-channel attacks.
+/// Always compares every byte regardless of where the first difference is.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff = 0u8;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+// ── Connection handler ──────────────────────────────────────────────────
+
+/// Maximum size of a single RPC request line (1 MB).
+/// Prevents memory exhaustion from a malicious guest sending unbounded data.
+const MAX_REQUEST_LINE: usize = 1024 * 1024;
+
+/// Writes response frames tagged with the id of the request being served.
+struct ResponseWriter<W: Write> {
+    inner: W,
+    id: u64,
+    /// How the request being served ended, for the request log
+    outcome: Option<String>,
+}
+
+impl<W: Write> ResponseWriter<W> {
+    fn new(inner: W) -> Self {
+        Self {
+            inner,
+            id: 0,
+            outcome: None,
+        }
+    }
+
+    fn send(&mut self, response: &RpcResponse) -> Result<()> {
+        if let Some(outcome) = request_log::outcome(response) {
+            self.outcome = Some(outcome);
+        }
+        let frame = ResponseFrame::new(self.id, response.clone());
+        let mut json = serde_json::to_string(&frame)?;
+        json.push('\n');
+        self.inner.write_all(json.as_bytes())?;
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+/// Read a single line from a buffered reader, enforcing a size limit.
+/// Returns `Ok(None)` on EOF, `Err` if the line exceeds the limit.
+///
+/// Accumulates raw bytes first, then validates UTF-8 once the line is
+/// complete. This avoids false rejections when multi-byte UTF-8 characters
+/// are split across buffer boundaries.
+fn read_bounded_line(reader: &mut impl BufRead, buf: &mut String) -> Result<Option<()>> {
+    buf.clear();
+    let mut bytes = Vec::new();
+    let mut total = 0usize;
+
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            if total == 0 {
+                return Ok(None);
+            }
+            break;
+        }
+
+        let (take, done) = match available.iter().position(|&b| b == b'\n') {
+            Some(pos) => (pos + 1, true),
+            None => (available.len(), false),
+        };
+
+        total += take;
+        if total > MAX_REQUEST_LINE {
+            anyhow::bail!("RPC request line exceeds {} byte limit", MAX_REQUEST_LINE);
+        }
+
+        bytes.extend_from_slice(&available[..take]);
+        reader.consume(take);
+
+        if done {
+            break;
+        }
+    }
+
+    let s = std::str::from_utf8(&bytes).context("Invalid UTF-8 in RPC request")?;
+    buf.push_str(s);
+    Ok(Some(()))
+}
+
+fn handle_connection(stream: TcpStream, ctx: &RpcContext, http: bool) -> Result<()> {
+    let peer = stream.peer_addr().ok();
+    debug!(?peer, "RPC connection accepted");
+
+    // Require the handshake within 5 seconds to prevent slowloris-style DoS.
+    stream.set_read_timeout(Some(std::time::Duration::from_secs(5)))?;
+
+    let mut reader = BufReader::new(&stream);
+    let mut writer = ResponseWriter::new(stream.try_clone().context("Failed to clone TCP stream")?);
+    let token_ok = |token: &str| constant_time_eq(token.as_bytes(), ctx.token.as_bytes());
+
+    // First frame must be the handshake (bounded read)
+    let mut line = String::new();
+    match read_bounded_line(&mut reader, &mut line)? {
+        Some(()) => {}
+        None => return Ok(()),
+    }
+    if http && http::is_request_line(&line) {
+        return http::serve(&line, &mut reader, &stream, ctx);
+    }
+    let Ok(handshake) = serde_json::from_str::<RequestFrame<RpcRequest>>(line.trim()) else {
+        // Most likely a guest binary from before the framed protocol
+        writer.send(&RpcResponse::Error {
+            message: version_mismatch(0),
+        })?;
+        return Ok(());
+    };
+    writer.id = handshake.id;
+
+    if !token_ok(&handshake.token) {
+        writer.send(&RpcResponse::Error {
+            message: "Invalid token".to_string(),
+        })?;
+        return Ok(());
+    }
+    match handshake.request {
+        RpcRequest::Handshake { protocol_version } if protocol_version == PROTOCOL_VERSION => {
+            writer.send(&RpcResponse::Handshake {
+                protocol_version: PROTOCOL_VERSION,
+            })?;
+        }
+        RpcRequest::Handshake { protocol_version } => {
+            writer.send(&RpcResponse::Error {
+                message: version_mismatch(protocol_version),
+            })?;
+            return Ok(());
+        }
+        _ => {
+            writer.send(&RpcResponse::Error {
+                message: "Expected handshake as the first RPC request".to_string(),
+            })?;
+            return Ok(());
+        }
+    }
+
+    // Clear timeout for authenticated connections so long-running requests
+    // (e.g., Exec streaming) are not interrupted.
+    stream.set_read_timeout(None)?;
+
+    // Process request frames (bounded reads)
+    loop {
+        match read_bounded_line(&mut reader, &mut line)? {
+            Some(()) => {}
+            None => break,
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let frame: RequestFrame<RpcRequest> = serde_json::from_str(line.trim())
+            .with_context(|| format!("Failed to parse RPC request: {}", line.trim()))?;
+        writer.id = frame.id;
+
+        if !token_ok(&frame.token) {
+            writer.send(&RpcResponse::Error {
+                message: "Invalid token".to_string(),
+            })?;
+            return Ok(());
+        }
+
+        let request = frame.request;
+        info!(id = frame.id, ?request, "RPC request received");
+        serve_logged(&request, ctx, &mut writer)?;
+    }
+
+    Ok(())
+}
+
+/// Serve one authenticated request and record it in the request log.
+fn serve_logged(
+    request: &RpcRequest,
+    ctx: &RpcContext,
+    writer: &mut ResponseWriter<impl Write>,
+) -> Result<()> {
+    writer.outcome = None;
+    let handled = handle_request(request, ctx, writer);
+
+    // Reading the log shouldn't fill it, and polling `tail` would push
+    // everything else out of it
+    if !matches!(
+        request,
+        RpcRequest::ReadRequestLog { .. } | RpcRequest::Tail { .. }
+    ) {
+        let outcome = match &handled {
+            Err(e) => format!("error: {:#}", e),
+            Ok(()) => writer.outcome.take().unwrap_or_default(),
+        };
+        ctx.requests.record(request, &outcome);
+    }
+    handled
+}
+
+/// Serve one request after the handshake, sending its response frames.
+fn handle_request(
+    request: &RpcRequest,
+    ctx: &RpcContext,
+    writer: &mut ResponseWriter<impl Write>,
+) -> Result<()> {
+    // Exec and Merge require streaming multiple responses, handle separately
+    if let RpcRequest::Exec {
+        ref command,
+        ref args,
+    } = *request
+    {
+        return handle_exec(command, args, ctx, writer);
+    }
+
+    if let RpcRequest::Merge {
+        ref name,
+        ref into,
+        rebase,
+        squash,
+        ignore_uncommitted,
+        keep,
+        no_verify: _,
+        no_hooks: _,
+        notification,
+    } = *request
+    {
+        // SECURITY: Force --no-verify --no-hooks regardless of guest request.
+        // Workmux hooks are user-configured shell commands that run unsandboxed
+        // on the host. Git native hooks are also disabled in handle_merge via
+        // core.hooksPath=/dev/null to prevent a compromised guest from planting
+        // hooks in the bind-mounted .git/hooks/ directory.
+        return handle_merge(
+            name,
+            into.as_deref(),
+            rebase,
+            squash,
+            ignore_uncommitted,
+            keep,
+            notification,
+            &ctx.worktree_path,
+            writer,
+        );
+    }
+
+    let response = dispatch_request(request, ctx);
+    debug!(?response, "RPC response");
+    writer.send(&response)
+}
+
+// ── Request dispatch ────────────────────────────────────────────────────
+
+fn dispatch_request(request: &RpcRequest, ctx: &RpcContext) -> RpcResponse {
+    match request {
+        RpcRequest::Handshake { .. } => RpcResponse::Error {
+            message: "Handshake already completed".to_string(),
+        },
+        RpcRequest::Heartbeat => RpcResponse::Ok,
+        RpcRequest::Ping { nonce } => RpcResponse::Pong {
+            protocol_version: PROTOCOL_VERSION,
+            nonce: nonce.clone(),
+        },
+        RpcRequest::SetStatus {
+            status,
+            tokens,
+            cost,
+        } => handle_set_status(status, *tokens, *cost, ctx),
+        RpcRequest::SetTitle { title } => handle_set_title(title, ctx),
+        RpcRequest::OpenPath { path } => handle_open_path(path, ctx),
+        RpcRequest::SpawnAgent {
+            prompt,
+            branch_name,
+            background,
+        } => handle_spawn_agent(
+            prompt,
+            branch_name.as_deref(),
+            *background,
+            &ctx.worktree_path,
+        ),
+        RpcRequest::Exec { .. } => {
+            // Handled in handle_connection before dispatch
+            unreachable!("Exec is handled directly in handle_connection")
+        }
+        RpcRequest::Merge { .. } => {
+            // Handled in handle_connection before dispatch (needs streaming)
+            unreachable!("Merge is handled directly in handle_connection")
+        }
+        RpcRequest::ReadRequestLog { entries } => RpcResponse::RequestLog {
+            entries: ctx.requests.tail(*entries),
+        },
+        RpcRequest::ReadStderr { lines } => match &ctx.stderr {
+            Some(buffer) => RpcResponse::StderrLines {
+                lines: buffer.tail(*lines),
+            },
+            None => RpcResponse::Error {
+                message: "The supervisor isn't buffering stderr; start it with \
+                          `workmux sandbox run --buffer-stderr`"
+                    .to_string(),
+            },
+        },
+        RpcRequest::Tail { since, bytes } => match &ctx.output {
+            Some(buffer) => {
+                let tail = buffer.tail(*since, *bytes);
+                RpcResponse::OutputTail {
+                    data: tail.data,
+                    offset: tail.offset,
+                    truncated: tail.truncated,
+                }
+            }
+            None => RpcResponse::Error {
+                message: "The supervisor isn't buffering output; start it with \
+                          `workmux sandbox run --buffer-output`"
+                    .to_string(),
+            },
+        },
+    }
+}
+
+// ── Handlers ────────────────────────────────────────────────────────────
+
+fn handle_set_status(
+    status: &str,
+    tokens: Option<u64>,
+    cost: Option<f64>,
+    ctx: &RpcContext,
+) -> RpcResponse {
+    use crate::command::set_window_status::{self, SetWindowStatusCommand};
+
+    let cmd = match SetWindowStatusCommand::parse(status) {
+        Ok(cmd) => cmd,
+        Err(e) => {
+            return RpcResponse::Error {
+                message: e.to_string(),
+            };
+        }
+    };
+
+    // Same code path as the set-window-status command
+    let result = Config::load(None)
+        .context("Failed to load config")
+        .and_then(|config| {
+            let details = set_window_status::StatusDetails {
+                worktree: Some(&ctx.worktree_path),
+                tokens,
+                cost,
+            };
+            set_window_status::apply_debounced(&*ctx.mux, &ctx.pane_id, &cmd, &details, &config)
+        });
+    match result {
+        Ok(()) => {
+            crate::sandbox::events::emit(
+                ctx.events.as_ref(),
+                crate::sandbox::events::Event::StatusChanged {
+                    status: status.to_lowercase(),
+                },
+            );
+            record_status(ctx, status, tokens, cost);
+            RpcResponse::Ok
+        }
+        Err(e) => RpcResponse::Error {
+            message: format!("Failed to set status: {:#}", e),
+        },
+    }
+}
+
+/// Keep the reported status in `ctx` and in this supervisor's state record.
+fn record_status(ctx: &RpcContext, status: &str, tokens: Option<u64>, cost: Option<f64>) {
+    let snapshot = StatusSnapshot {
+        status: status.to_lowercase(),
+        tokens,
+        cost,
+        updated_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+    };
+    if let Ok(store) = crate::state::StateStore::new()
+        && let Err(e) = store.update_sandbox_status(std::process::id(), &snapshot)
+    {
+        warn!(error = %e, "failed to record agent status in sandbox state");
+    }
+    *ctx.status.lock().unwrap_or_else(|e| e.into_inner()) = Some(snapshot);
+}
+
+fn handle_set_title(title: &str, ctx: &RpcContext) -> RpcResponse {
+    // Use tmux rename-window via the Cmd helper (consistent with codebase patterns)
+    use crate::cmd::Cmd;
+
+    match Cmd::new("tmux")
+        .args(&["rename-window", "-t", &ctx.pane_id, title])
+        .run()
+    {
+        Ok(_) => {
+            // Persist title to StateStore so the dashboard sees it
+            crate::state::persist_agent_update(
+                &*ctx.mux,
+                &ctx.pane_id,
+                None,
+                Some(title.to_string()),
+            );
+            RpcResponse::Ok
+        }
+        Err(e) => RpcResponse::Error {
+            message: format!("Failed to set title: {}", e),
+        },
+    }
+}
+
+fn handle_open_path(path: &str, ctx: &RpcContext) -> RpcResponse {
+    let target = match resolve_in_worktree(&ctx.worktree_path, path) {
+        Ok(target) => target,
+        Err(e) => {
+            return RpcResponse::Error {
+                message: e.to_string(),
+            };
+        }
+    };
+
+    let config = match Config::load(None) {
+        Ok(c) => c,
+        Err(e) => {
+            return RpcResponse::Error {
+                message: format!("Failed to load config: {}", e),
+            };
+        }
+    };
+    let opener = config
+        .sandbox
+        .open_command
+        .or_else(|| {
+            std::env::var("EDITOR")
+                .ok()
+                .filter(|e| !e.trim().is_empty())
+        })
+        .unwrap_or_else(|| "vi".to_string());
+    if let Some(allowed) = &config.sandbox.rpc_allowed_openers
+        && !opener_allowed(&opener, allowed)
+    {
+        warn!(
+            opener,
+            path, "refused open_path: opener not in rpc_allowed_openers"
+        );
+        return RpcResponse::Error {
+            message: format!(
+                "Opener '{}' is not listed in sandbox.rpc_allowed_openers",
+                opener
+            ),
+        };
+    }
+    let command = format!(
+        "{} {}",
+        opener,
+        crate::shell::shell_quote(&target.to_string_lossy())
+    );
+    info!(command, "opening path for guest");
+
+    match ctx.mux.split_pane(
+        &ctx.pane_id,
+        &crate::config::SplitDirection::Horizontal,
+        &ctx.worktree_path,
+        None,
+        None,
+        Some(&command),
+    ) {
+        Ok(_) => RpcResponse::Ok,
+        Err(e) => RpcResponse::Error {
+            message: format!("Failed to open pane: {}", e),
+        },
+    }
+}
+
+/// Whether the program `opener` runs is in `allowed`. Entries without a
+/// `/` also match a program given by path, e.g. `code` allows
+/// `/usr/local/bin/code -r`.
+fn opener_allowed(opener: &str, allowed: &[String]) -> bool {
+    let Some((program, _)) = crate::config::split_first_token(opener) else {
+        return false;
+    };
+    let name = Path::new(program).file_name().and_then(|n| n.to_str());
+    allowed
+        .iter()
+        .any(|entry| entry == program || (!entry.contains('/') && Some(entry.as_str()) == name))
+}
+
+/// Resolve a guest-supplied path against the worktree root, refusing
+/// anything that would land outside it (absolute paths, `..`, or symlinks
+/// pointing elsewhere).
+fn resolve_in_worktree(worktree: &Path, path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        anyhow::bail!("Path must be relative to the worktree: {}", path);
+    }
+
+    let root = worktree
+        .canonicalize()
+        .with_context(|| format!("Worktree not found: {}", worktree.display()))?;
+    let target = root
+        .join(relative)
+        .canonicalize()
+        .with_context(|| format!("Path not found in worktree: {}", path))?;
+    if !target.starts_with(&root) {
+        anyhow::bail!("Path escapes the worktree: {}", path);
+    }
+    Ok(target)
+}
+
+/// Disable git native hooks for a spawned command and all its children.
+///
+/// Sets `core.hooksPath=/dev/null` via git's `GIT_CONFIG_*` environment
+/// variables. This prevents git from executing hooks (post-merge, post-rewrite,
+/// post-checkout, etc.) that a sandboxed guest could have planted in the
+/// bind-mounted `.git/hooks/` directory.
+///
+/// Intentionally hard-sets `GIT_CONFIG_COUNT=1` rather than appending to any
+/// existing value. RPC-spawned commands should run in a minimal, known-safe
+/// configuration -- inheriting arbitrary env-injected git config from the host
+/// is not needed and could weaken the security boundary.
+fn disable_git_hooks(cmd: &mut std::process::Command) {
+    cmd.env_remove("GIT_CONFIG_PARAMETERS")
+        .env("GIT_CONFIG_COUNT", "1")
+        .env("GIT_CONFIG_KEY_0", "core.hooksPath")
+        .env("GIT_CONFIG_VALUE_0", "/dev/null");
+}
+
+fn handle_spawn_agent(
+    prompt: &str,
+    branch_name: Option<&str>,
+    background: Option<bool>,
+    worktree_path: &PathBuf,
+) -> RpcResponse {
+    use std::process::Command;
+
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("workmux"));
+    let mut cmd = Command::new(exe);
+    cmd.arg("add");
+
+    if let Some(name) = branch_name {
+        cmd.arg(name);
+    } else {
+        cmd.arg("--auto-name");
+    }
+
+    if !prompt.is_empty() {
+        cmd.args(["--prompt", prompt]);
+    }
+
+    if background.unwrap_or(false) {
+        cmd.arg("--background");
+    }
+
+    // SECURITY: Skip workmux hooks AND git native hooks when triggered via RPC.
+    // Workmux hooks are arbitrary shell commands from config that run unsandboxed
+    // on the host. Git native hooks (e.g. post-checkout from `git worktree add`)
+    // could be planted by a compromised guest in the bind-mounted .git/hooks/.
+    cmd.arg("--no-hooks");
+    disable_git_hooks(&mut cmd);
+
+    // Run from the worktree directory so config is found
+    cmd.current_dir(worktree_path);
+
+    match cmd.output() {
+        Ok(output) if output.status.success() => RpcResponse::Ok,
+        Ok(output) => {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            RpcResponse::Error {
+                message: format!("workmux add failed: {}", stderr.trim()),
+            }
+        }
+        Err(e) => RpcResponse::Error {
+            message: format!("Failed to run workmux add: {}", e),
+        },
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_merge(
+    name: &str,
+    into: Option<&str>,
+    rebase: bool,
+    squash: bool,
+    ignore_uncommitted: bool,
+    keep: bool,
+    notification: bool,
+    worktree_path: &PathBuf,
+    writer: &mut ResponseWriter<impl Write>,
+) -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("workmux"));
+    let mut cmd = Command::new(exe);
+    cmd.arg("merge");
+    cmd.arg(name);
+
+    if let Some(target) = into {
+        cmd.args(["--into", target]);
+    }
+    if rebase {
+        cmd.arg("--rebase");
+    }
+    if squash {
+        cmd.arg("--squash");
+    }
+    if ignore_uncommitted {
+        cmd.arg("--ignore-uncommitted");
+    }
+    if keep {
+        cmd.arg("--keep");
+    }
+    if notification {
+        cmd.arg("--notification");
+    }
+
+    // SECURITY: Skip workmux hooks AND git native hooks when triggered via RPC.
+    // --no-verify/--no-hooks skip workmux's own pre_merge hooks (arbitrary shell
+    // commands from config). disable_git_hooks() sets core.hooksPath=/dev/null to
+    // prevent git native hooks (post-merge, post-rewrite, post-checkout) that a
+    // compromised guest could plant in the bind-mounted .git/hooks/ directory.
+    cmd.args(["--no-verify", "--no-hooks"]);
+    disable_git_hooks(&mut cmd);
+
+    // Run from the worktree directory so config is found
+    cmd.current_dir(worktree_path);
+    cmd.stdout(Stdio::piped());
+    cmd.stderr(Stdio::piped());
+
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => {
+            writer.send(&RpcResponse::Error {
+                message: format!("Failed to run workmux merge: {}", e),
+            })?;
+            return Ok(());
+        }
+    };
+
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    // Stream stdout and stderr as Output responses
+    let (tx, rx) = std::sync::mpsc::channel::<String>();
+
+    let tx_out = tx.clone();
+    let stdout_thread = thread::spawn(move || {
+        use std::io::Read;
+        let mut buf = [0u8; 8192];
+        let mut reader = std::io::BufReader::new(stdout);
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let data = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let _ = tx_out.send(data);
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    let tx_err = tx.clone();
+    let stderr_thread = thread::spawn(move || {
+        use std::io::Read;
+        let mut buf = [0u8; 8192];
+        let mut reader = std::io::BufReader::new(stderr);
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let data = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let _ = tx_err.send(data);
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    drop(tx);
+
+    // Stream responses; kill child on write failure (mirrors handle_exec pattern)
+    let stream_result = (|| -> Result<()> {
+        for chunk in rx {
+            writer.send(&RpcResponse::Output { message: chunk })?;
+        }
+        Ok(())
+    })();
+
+    if stream_result.is_err() {
+        let _ = child.kill();
+        let _ = child.wait();
+        return stream_result;
+    }
+
+    stdout_thread.join().ok();
+    stderr_thread.join().ok();
+
+    let status = child.wait()?;
+    if status.success() {
+        writer.send(&RpcResponse::Ok)?;
+    } else {
+        writer.send(&RpcResponse::Error {
+            message: format!(
+                "workmux merge exited with code {}",
+                status.code().unwrap_or(1)
+            ),
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Environment variables allowed to pass through to host-exec child processes.
+/// Everything else is cleared to prevent leaking host secrets.
+const EXEC_ENV_ALLOWLIST: &[&str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "TMPDIR",
+    "TERM",
+    "COLORTERM",
+    "LANG",
+    "LC_ALL",
+];
+
+/// Build a sanitized environment map from the current process environment.
+/// Only variables in the allowlist are included. PATH is normalized to
+/// contain only absolute entries (prevents relative-path hijacking from
+/// the worktree's current directory).
+fn sanitized_env() -> std::collections::HashMap<String, String> {
+    let mut envs = std::collections::HashMap::new();
+    for key in EXEC_ENV_ALLOWLIST {
+        if let Ok(val) = std::env::var(key) {
+            if *key == "PATH" {
+                // Strip relative/empty PATH entries to prevent hijacking
+                let normalized: String = val
+                    .split(':')
+                    .filter(|p| p.starts_with('/'))
+                    .collect::<Vec<_>>()
+                    .join(":");
+                envs.insert(key.to_string(), normalized);
+            } else {
+                envs.insert(key.to_string(), val);
+            }
+        }
+    }
+    envs
+}
+
+fn handle_exec(
+    command: &str,
+    args: &[String],
+    ctx: &RpcContext,
+    writer: &mut ResponseWriter<impl Write>,
+) -> Result<()> {
+    info!(command, ?args, "host-exec request");
+
+    // Validate command name format (strict alphanumeric + dash/underscore/dot)
+    if !crate::sandbox::shims::validate_command_name(command) {
+        let resp = RpcResponse::ExecExit { code: 127 };
+        writer.send(&resp)?;
+        return Ok(());
+    }
+
+    // Validate command is in allowlist
+    if !ctx.allowed_commands.contains(command) {
+        let resp = RpcResponse::ExecExit { code: 127 };
+        writer.send(&resp)?;
+        return Ok(());
+    }
+
+    // Skip toolchain wrapping for built-in host commands (e.g., afplay) since they
+    // exist outside the project's devbox/nix environment
+    let is_builtin = crate::sandbox::shims::BUILTIN_HOST_COMMANDS.contains(&command);
+    let wrapper_script = if !is_builtin {
+        crate::sandbox::toolchain::toolchain_wrapper_script(&ctx.detected_toolchain)
+    } else {
+        None
+    };
+
+    // Build the logical command (program + args), then delegate to sandbox
+    let (program, final_args) = if let Some(script) = wrapper_script {
+        // Safe toolchain wrapping: command and args are passed as positional
+        // parameters to bash, never interpolated into the shell string.
+        // bash -c '<script>' -- <command> <arg1> <arg2> ...
+        let mut script_args = vec![
+            "-c".to_string(),
+            script,
+            "--".to_string(),
+            command.to_string(),
+        ];
+        script_args.extend_from_slice(args);
+        ("bash".to_string(), script_args)
+    } else {
+        // Direct execution: no shell involved, args passed as argv
+        (command.to_string(), args.to_vec())
+    };
+
+    let envs = sanitized_env();
+    let spawn_result = crate::sandbox::host_exec_sandbox::spawn_sandboxed(
+        &program,
+        &final_args,
+        &ctx.worktree_path,
+        &envs,
+        ctx.allow_unsandboxed_host_exec,
+    );
+
+    let mut child = match spawn_result {
+        Ok(child) => child,
+        Err(e) => {
+            warn!(command, error = %e, "failed to spawn command");
+            writer.send(&RpcResponse::ExecError {
+                data: format!("host-exec spawn failed: {e}\n"),
+            })?;
+            writer.send(&RpcResponse::ExecExit { code: 126 })?;
+            return Ok(());
+        }
+    };
+
+    let mut stdout = child.stdout.take().unwrap();
+    let mut stderr = child.stderr.take().unwrap();
+
+    // Read stdout and stderr in threads, collect chunks
+    let (tx, rx) = std::sync::mpsc::channel::<RpcResponse>();
+
+    let tx_out = tx.clone();
+    let stdout_thread = thread::spawn(move || {
+        use std::io::Read;
+        let mut buf = [0u8; 8192];
+        loop {
+            match stdout.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let data = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let _ = tx_out.send(RpcResponse::ExecOutput { data });
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    let tx_err = tx.clone();
+    let stderr_thread = thread::spawn(move || {
+        use std::io::Read;
+        let mut buf = [0u8; 8192];
+        loop {
+            match stderr.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => {
+                    let data = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let _ = tx_err.send(RpcResponse::ExecError { data });
+                }
+                Err(_) => break,
+            }
+        }
+    });
+
+    // Drop our sender so rx closes when threads finish
+    drop(tx);
+
+    // Stream responses as they arrive; kill child on write failure
+    let stream_result = (|| -> Result<()> {
+        for response in rx {
+            writer.send(&response)?;
+        }
+        Ok(())
+    })();
+
+    if stream_result.is_err() {
+        let _ = child.kill();
+        let _ = child.wait();
+        return stream_result;
+    }
+
+    stdout_thread.join().ok();
+    stderr_thread.join().ok();
+
+    let status = child.wait()?;
+    let code = status.code().unwrap_or(1);
+    info!(command, code, "host-exec finished");
+
+    writer.send(&RpcResponse::ExecExit { code })?;
+    Ok(())
+}
+
+// ── Client ──────────────────────────────────────────────────────────────
+
+/// RPC client for guest-side use. Connects to the host supervisor.
+///
+/// Used by the guest workmux binary to send requests to the host supervisor
+/// when `WM_SANDBOX_GUEST=1` is set. Commands like `set-window-status` route
+/// through RPC instead of calling tmux directly.
+pub struct RpcClient {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    token: String,
+    next_id: u64,
+}
+
+impl RpcClient {
+    /// How long to wait for the host to answer the handshake. A host too old
+    /// to know the handshake never answers, so don't wait forever.
+    const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Connect using WM_RPC_HOST, WM_RPC_PORT, and WM_RPC_TOKEN env vars.
+    pub fn from_env() -> Result<Self> {
+        let host = std::env::var("WM_RPC_HOST").context("WM_RPC_HOST not set")?;
+        let port: u16 = std::env::var("WM_RPC_PORT")
+            .context("WM_RPC_PORT not set")?
+            .parse()
+            .context("WM_RPC_PORT is not a valid port")?;
+        let token = std::env::var("WM_RPC_TOKEN").context("WM_RPC_TOKEN not set")?;
+
+        Self::connect(&host, port, &token)
+    }
+
+    /// Connect to a specific host, port, and authenticate with token.
+    ///
+    /// Performs the protocol handshake, failing if the host rejects the token
+    /// or speaks a different protocol version.
+    pub fn connect(host: &str, port: u16, token: &str) -> Result<Self> {
+        let stream = TcpStream::connect((host, port))
+            .with_context(|| format!("Failed to connect to RPC server at {}:{}", host, port))?;
+
+        let writer = stream.try_clone().context("Failed to clone TCP stream")?;
+        let reader = BufReader::new(stream);
+        let mut client = Self {
+            reader,
+            writer,
+            token: token.to_string(),
+            next_id: 0,
+        };
+
+        client
+            .writer
+            .set_read_timeout(Some(Self::HANDSHAKE_TIMEOUT))?;
+        client.send(&RpcRequest::Handshake {
+            protocol_version: PROTOCOL_VERSION,
+        })?;
+        let response = client.recv().map_err(|e| {
+            let timed_out = e.downcast_ref::<io::Error>().is_some_and(|e| {
+                matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                )
+            });
+            if timed_out {
+                anyhow::anyhow!(
+                    "RPC host did not answer the handshake; it is likely running an older \
+                     workmux than the guest"
+                )
+            } else {
+                e.context("RPC handshake failed")
+            }
+        })?;
+        client.writer.set_read_timeout(None)?;
+
+        match response {
+            RpcResponse::Handshake { protocol_version } if protocol_version == PROTOCOL_VERSION => {
+                Ok(client)
+            }
+            RpcResponse::Handshake { protocol_version } => anyhow::bail!(
+                "RPC protocol mismatch: guest speaks v{}, host speaks v{}. \
+                 Make sure the guest and host run the same workmux version.",
+                PROTOCOL_VERSION,
+                protocol_version
+            ),
+            RpcResponse::Error { message } => anyhow::bail!("RPC handshake rejected: {}", message),
+            other => anyhow::bail!("Unexpected RPC handshake response: {:?}", other),
+        }
+    }
+
+    /// Send a request and receive a response.
+    pub fn call(&mut self, request: &RpcRequest) -> Result<RpcResponse> {
+        self.send(request)?;
+        self.recv()
+    }
+
+    /// Send a request without waiting for a response.
+    pub fn send(&mut self, request: &RpcRequest) -> Result<()> {
+        let frame = RequestFrame {
+            id: self.next_id,
+            token: self.token.clone(),
+            request,
+        };
+        self.next_id += 1;
+
+        let mut req_json = serde_json::to_string(&frame)?;
+        req_json.push('\n');
+        (&self.writer).write_all(req_json.as_bytes())?;
+        (&self.writer).flush()?;
+        Ok(())
+    }
+
+    /// Receive a single response frame.
+    pub fn recv(&mut self) -> Result<RpcResponse> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            anyhow::bail!("RPC connection closed by host");
+        }
+        let frame: ResponseFrame = serde_json::from_str(&line)
+            .with_context(|| format!("Failed to parse RPC response: {}", line))?;
+        frame.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::multiplexer;
+
+    #[test]
+    fn test_request_serialization_heartbeat() {
+        let req = RpcRequest::Heartbeat;
+        let json = serde_json::to_string(&req).unwrap();
+        assert_eq!(json, r#"{"method":"heartbeat"}"#);
+    }
+
+    #[test]
+    fn test_request_serialization_set_status() {
+        let req = RpcRequest::SetStatus {
+            status: "working".to_string(),
+            tokens: None,
+            cost: None,
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        assert_eq!(
+            json,
+            r#"{"method":"set_status","params":{"status":"working"}}"#
+        );
+    }
+
+    #[test]
+    fn test_request_set_status_with_usage_roundtrip() {
+        let json =
+            r#"{"method":"set_status","params":{"status":"done","tokens":12000,"cost":0.5}}"#;
+        match serde_json::from_str::<RpcRequest>(json).unwrap() {
+            RpcRequest::SetStatus {
+                status,
+                tokens,
+                cost,
+            } => {
+                assert_eq!(status, "done");
+                assert_eq!(tokens, Some(12000));
+                assert_eq!(cost, Some(0.5));
+            }
+            other => panic!("Expected SetStatus, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_request_serialization_spawn_agent() {
+        let req = RpcRequest::SpawnAgent {
+            prompt: "fix the bug".to_string(),
+            branch_name: Some("fix-bug".to_string()),
+            background: Some(true),
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        let parsed: RpcRequest = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcRequest::SpawnAgent {
+                prompt,
+                branch_name,
+                background,
+            } => {
+                assert_eq!(prompt, "fix the bug");
+                assert_eq!(branch_name.as_deref(), Some("fix-bug"));
+                assert_eq!(background, Some(true));
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_response_serialization() {
+        let frame = ResponseFrame::new(3, RpcResponse::Ok);
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(json, r#"{"id":3,"result":{"type":"ok"}}"#);
+
+        let frame = ResponseFrame::new(
+            4,
+            RpcResponse::Error {
+                message: "oops".to_string(),
+            },
+        );
+        let json = serde_json::to_string(&frame).unwrap();
+        assert_eq!(json, r#"{"id":4,"error":{"message":"oops"}}"#);
+
+        let parsed: ResponseFrame = serde_json::from_str(&json).unwrap();
+        match parsed.into_response().unwrap() {
+            RpcResponse::Error { message } => assert_eq!(message, "oops"),
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resolve_in_worktree_accepts_files_inside() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir(tmp.path().join("src")).unwrap();
+        std::fs::write(tmp.path().join("src/lib.rs"), "").unwrap();
+
+        let resolved = resolve_in_worktree(tmp.path(), "src/lib.rs").unwrap();
+        assert_eq!(
+            resolved,
+            tmp.path().canonicalize().unwrap().join("src/lib.rs")
+        );
+    }
+
+    #[test]
+    fn test_resolve_in_worktree_rejects_escapes() {
+        let tmp = tempfile::tempdir().unwrap();
+        let worktree = tmp.path().join("wt");
+        std::fs::create_dir(&worktree).unwrap();
+        std::fs::write(tmp.path().join("secret"), "").unwrap();
+
+        assert!(resolve_in_worktree(&worktree, "../secret").is_err());
+        assert!(resolve_in_worktree(&worktree, "/etc/passwd").is_err());
+        assert!(resolve_in_worktree(&worktree, "").is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(tmp.path().join("secret"), worktree.join("link")).unwrap();
+            let err = resolve_in_worktree(&worktree, "link").unwrap_err();
+            assert!(err.to_string().contains("escapes the worktree"));
+        }
+    }
+
+    #[test]
+    fn test_opener_allowed_matches_program_name_or_path() {
+        let allowed = vec!["code".to_string(), "/opt/bin/nvim".to_string()];
+        assert!(opener_allowed("code -r", &allowed));
+        assert!(opener_allowed("/usr/local/bin/code -r", &allowed));
+        assert!(opener_allowed("/opt/bin/nvim", &allowed));
+        assert!(!opener_allowed("nvim", &allowed));
+        assert!(!opener_allowed("/tmp/evil/nvim", &allowed));
+        assert!(!opener_allowed("sh -c code", &allowed));
+        assert!(!opener_allowed("  ", &allowed));
+        assert!(!opener_allowed("code", &[]));
+    }
+
+    #[test]
+    fn test_request_roundtrip_deserialization() {
+        let cases = vec![
+            r#"{"id":0,"token":"t","method":"handshake","params":{"protocol_version":1}}"#,
+            r#"{"id":1,"token":"t","method":"heartbeat"}"#,
+            r#"{"id":2,"token":"t","method":"set_status","params":{"status":"working"}}"#,
+            r#"{"id":3,"token":"t","method":"set_title","params":{"title":"my agent"}}"#,
+            r#"{"id":4,"token":"t","method":"spawn_agent","params":{"prompt":"do stuff","branch_name":null,"background":null}}"#,
+            r#"{"id":7,"token":"t","method":"open_path","params":{"path":"src/main.rs"}}"#,
+            r#"{"id":5,"token":"t","method":"exec","params":{"command":"cargo","args":["build","--release"]}}"#,
+            r#"{"id":6,"token":"t","method":"merge","params":{"name":"feat","into":null,"rebase":true,"squash":false,"ignore_uncommitted":false,"keep":false,"no_verify":false,"no_hooks":false,"notification":false}}"#,
+        ];
+        for json in cases {
+            let frame: RequestFrame<RpcRequest> = serde_json::from_str(json).unwrap();
+            assert_eq!(frame.token, "t");
+            // Verify it round-trips
+            let re_json = serde_json::to_string(&frame).unwrap();
+            let _: RequestFrame<RpcRequest> = serde_json::from_str(&re_json).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_generate_token_is_nonempty() {
+        let token = generate_token();
+        assert!(!token.is_empty());
+        assert_eq!(token.len(), 64, "token should be 64 hex chars (32 bytes)");
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+
+    #[test]
+    fn read_token_file_trims_and_rejects_weak_tokens() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("token");
+        let token = "a".repeat(MIN_TOKEN_BYTES * 2 - 4) + "-_.9";
+
+        std::fs::write(&path, format!("  {token}\n")).unwrap();
+        assert_eq!(read_token_file(&path).unwrap(), token);
+
+        std::fs::write(&path, "short").unwrap();
+        let err = read_token_file(&path).unwrap_err().to_string();
+        assert!(err.contains("is 5 characters long"), "{err}");
+
+        std::fs::write(&path, format!("{token} x")).unwrap();
+        let err = read_token_file(&path).unwrap_err().to_string();
+        assert!(err.contains("contains ' '"), "{err}");
+
+        let err = read_token_file(&tmp.path().join("missing")).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to read RPC token file"));
+    }
+
+    #[test]
+    fn test_generated_tokens_are_shell_safe_and_long_enough() {
+        for bytes in [MIN_TOKEN_BYTES, DEFAULT_TOKEN_BYTES, MAX_TOKEN_BYTES] {
+            let token = generate_token_with_bytes(bytes);
+            assert_eq!(token.len(), bytes * 2);
+            assert!(token.len() >= MIN_TOKEN_BYTES * 2);
+            assert!(token.bytes().all(|b| b.is_ascii_alphanumeric()));
+            assert!(is_valid_token(&token));
+        }
+        assert_ne!(generate_token(), generate_token());
+        assert!(!is_valid_token(&"ab".repeat(MIN_TOKEN_BYTES - 1)));
+        assert!(!is_valid_token(&"AB".repeat(MIN_TOKEN_BYTES)));
+    }
+
+    #[test]
+    fn test_accept_backoff_grows_and_is_capped() {
+        let mut backoff = AcceptBackoff::default();
+        let delays: Vec<Duration> = (0..12).map(|_| backoff.next_delay()).collect();
+        assert!(delays[0] >= AcceptBackoff::BASE / 2 && delays[0] <= AcceptBackoff::BASE);
+        assert!(delays[4] >= AcceptBackoff::BASE * 8);
+        assert!(delays.iter().all(|d| *d <= AcceptBackoff::MAX));
+    }
+
+    #[test]
+    fn test_accept_loop_backs_off_on_transient_errors() {
+        let stopping = AtomicBool::new(false);
+        let emfile = || Err(io::Error::from_raw_os_error(libc::EMFILE));
+        let incoming = vec![emfile(), emfile(), emfile(), emfile(), Ok(1), Ok(2)];
+
+        let started = Instant::now();
+        let mut accepted = Vec::new();
+        accept_loop(incoming.into_iter(), &stopping, |n| accepted.push(n));
+
+        // Four failures wait at least half of 10 + 20 + 40 + 80 ms
+        assert!(started.elapsed() >= Duration::from_millis(75));
+        assert_eq!(accepted, [1, 2]);
+    }
+
+    #[test]
+    fn test_accept_loop_stops_on_fatal_error_or_shutdown() {
+        let stopping = AtomicBool::new(false);
+        let incoming = vec![Err(io::Error::from_raw_os_error(libc::EBADF)), Ok(1)];
+        let mut accepted = Vec::new();
+        accept_loop(incoming.into_iter(), &stopping, |n| accepted.push(n));
+        assert!(accepted.is_empty());
+
+        stopping.store(true, Ordering::SeqCst);
+        accept_loop(vec![Ok(1)].into_iter(), &stopping, |n| accepted.push(n));
+        assert!(accepted.is_empty());
+    }
+
+    #[test]
+    fn test_server_bind_assigns_port() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        assert!(server.port() > 0);
+    }
+
+    #[test]
+    fn test_server_binds_loopback_by_default() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        assert!(server.local_addr().unwrap().ip().is_loopback());
+
+        let port = server.port();
+        drop(server);
+        let server = RpcServer::bind_on(DEFAULT_BIND_ADDR, port..=port).unwrap();
+        assert!(server.local_addr().unwrap().ip().is_loopback());
+    }
+
+    #[test]
+    fn test_sequential_binds_get_distinct_ports() {
+        let a = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let b = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        assert_ne!(a.port(), b.port());
+    }
+
+    #[test]
+    fn test_bind_on_stays_in_range() {
+        let taken = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = taken.port();
+
+        let err = RpcServer::bind_on(DEFAULT_BIND_ADDR, port..=port)
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains(&format!("{}-{}", port, port)));
+
+        drop(taken);
+        let server = RpcServer::bind_on(DEFAULT_BIND_ADDR, port..=port).unwrap();
+        assert_eq!(server.port(), port);
+    }
+
+    #[test]
+    fn test_bind_on_rejects_invalid_range() {
+        assert!(RpcServer::bind_on(DEFAULT_BIND_ADDR, 0..=10).is_err());
+        assert!(RpcServer::bind_on(DEFAULT_BIND_ADDR, RangeInclusive::new(5000, 4000)).is_err());
+    }
+
+    #[test]
+    fn test_client_server_heartbeat_roundtrip() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux,
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+
+        let _handle = server.spawn(ctx);
+
+        // Give server thread a moment to start
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let mut client = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        let resp = client.call(&RpcRequest::Heartbeat).unwrap();
+        match resp {
+            RpcResponse::Ok => {}
+            other => panic!("Expected Ok, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_http_requests_share_the_listener_and_token() {
+        let token = generate_token();
+        let serve = |http: bool| {
+            let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap().with_http(http);
+            let port = server.port();
+            let ctx = Arc::new(RpcContext {
+                pane_id: "%0".to_string(),
+                worktree_path: PathBuf::from("/tmp/test"),
+                mux: multiplexer::create_backend(multiplexer::BackendType::Tmux),
+                token: token.clone(),
+                allowed_commands: std::collections::HashSet::new(),
+                detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+                allow_unsandboxed_host_exec: false,
+                events: None,
+                stderr: None,
+                output: None,
+                status: Mutex::default(),
+                requests: Default::default(),
+            });
+            (port, server.spawn(Arc::clone(&ctx)), ctx)
+        };
+        let post = |port: u16, auth: &str, path: &str, body: &str| {
+            let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+            write!(
+                stream,
+                "POST {path} HTTP/1.1\r\nHost: localhost\r\nAuthorization: {auth}\r\n\
+                 Content-Length: {}\r\n\r\n{body}",
+                body.len()
+            )
+            .unwrap();
+            let mut response = String::new();
+            io::Read::read_to_string(&mut stream, &mut response).unwrap();
+            response
+        };
+
+        let (port, _handle, ctx) = serve(true);
+        let bearer = format!("Bearer {token}");
+        let ok = post(port, &bearer, "/rpc/ping", r#"{"nonce":"n1"}"#);
+        assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"), "{ok}");
+        assert!(ok.contains("Access-Control-Allow-Origin: *"), "{ok}");
+        let frame = ok.split("\r\n\r\n").nth(1).unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(frame).unwrap()["result"]["nonce"],
+            "n1"
+        );
+        assert_eq!(ctx.requests.tail(None)[0].method, "ping");
+
+        let denied = post(port, "Bearer wrong", "/rpc/heartbeat", "");
+        assert!(denied.starts_with("HTTP/1.1 401 "), "{denied}");
+        assert!(denied.contains(r#"{"error":{"message":"Invalid token"}}"#));
+
+        // The native protocol still works on the same port
+        let mut client = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        assert!(matches!(
+            client.call(&RpcRequest::Heartbeat).unwrap(),
+            RpcResponse::Ok
+        ));
+
+        // Without rpc_http an HTTP request is just a malformed frame
+        let (port, _handle, _) = serve(false);
+        let refused = post(port, &bearer, "/rpc/heartbeat", "");
+        assert!(refused.contains("RPC protocol mismatch"), "{refused}");
+    }
+
+    #[test]
+    fn test_saved_session_is_reused_until_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let ttl = Duration::from_secs(3600);
+        let load = |ttl, bytes| reuse_or_generate(load_session_in(dir.path()), ttl, bytes);
+
+        let first = load(ttl, DEFAULT_TOKEN_BYTES);
+        assert_eq!(first.port, 0);
+        save_session_in(
+            dir.path(),
+            &SavedSession {
+                port: 4242,
+                ..first.clone()
+            },
+        )
+        .unwrap();
+
+        let mode = fs::metadata(dir.path().join(SESSION_FILE))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let reused = load(ttl, DEFAULT_TOKEN_BYTES);
+        assert_eq!(reused.token, first.token);
+        assert_eq!(reused.port, 4242);
+
+        // Expired: a new token, and the old port is not offered
+        let rotated = load(Duration::ZERO, DEFAULT_TOKEN_BYTES);
+        assert_ne!(rotated.token, first.token);
+        assert_eq!(rotated.port, 0);
+
+        // A different token_bytes also rotates
+        let resized = load(ttl, 48);
+        assert_ne!(resized.token, first.token);
+        assert_eq!(resized.token.len(), 96);
+    }
+
+    #[test]
+    fn test_saved_session_rejects_malformed_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let session = SavedSession {
+            token: "guessable".to_string(),
+            port: 4242,
+            created_at: u64::MAX,
+            bind: None,
+        };
+        save_session_in(dir.path(), &session).unwrap();
+
+        let loaded = reuse_or_generate(load_session_in(dir.path()), Duration::from_secs(3600), 16);
+        assert_ne!(loaded.token, "guessable");
+        assert_eq!(loaded.token.len(), 32);
+    }
+
+    #[test]
+    fn test_session_is_kept_out_of_the_worktree() {
+        let tmp = tempfile::tempdir().unwrap();
+        let store = StateStore::with_path(tmp.path().join("state")).unwrap();
+        let worktree = tmp.path().join("worktree");
+        let victim = tmp.path().join("victim");
+        fs::write(&victim, "host file").unwrap();
+        fs::set_permissions(&victim, Permissions::from_mode(0o644)).unwrap();
+        // A link the guest could plant where the session used to be saved
+        fs::create_dir_all(worktree.join(".workmux")).unwrap();
+        std::os::unix::fs::symlink(&victim, worktree.join(".workmux/sandbox.json")).unwrap();
+
+        let dir = store.worktree_dir(&worktree).unwrap();
+        assert!(!dir.starts_with(&worktree));
+        let session = reuse_or_generate(None, Duration::from_secs(60), DEFAULT_TOKEN_BYTES);
+        save_session_in(&dir, &session).unwrap();
+
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "host file");
+        let mode = fs::metadata(&victim).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o644);
+        assert_eq!(load_session_in(&dir), Some(session));
+    }
+
+    #[test]
+    fn test_connection_limit_rejects_excess() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR)
+            .unwrap()
+            .with_max_connections(2);
+        let port = server.port();
+        let token = generate_token();
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux: multiplexer::create_backend(multiplexer::BackendType::Tmux),
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+        let _handle = server.spawn(ctx);
+
+        let first = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        let mut second = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+
+        let err = RpcClient::connect("127.0.0.1", port, &token)
+            .err()
+            .expect("third connection should be rejected");
+        assert!(
+            format!("{:#}", err).contains("at capacity (2 connections)"),
+            "{err:#}"
+        );
+
+        // Existing connections keep working, and a freed slot is reusable
+        assert!(matches!(
+            second.call(&RpcRequest::Heartbeat).unwrap(),
+            RpcResponse::Ok
+        ));
+        drop(first);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut third = loop {
+            match RpcClient::connect("127.0.0.1", port, &token) {
+                Ok(client) => break client,
+                Err(_) if Instant::now() < deadline => thread::sleep(Duration::from_millis(20)),
+                Err(e) => panic!("slot was not released: {e:#}"),
+            }
+        };
+        assert!(matches!(
+            third.call(&RpcRequest::Heartbeat).unwrap(),
+            RpcResponse::Ok
+        ));
+    }
+
+    #[test]
+    fn test_ping_echoes_nonce() {
+        let (mut client, _tmp, _handle) = start_exec_server(&[], false);
+        let resp = client
+            .call(&RpcRequest::Ping {
+                nonce: "abc123".to_string(),
+            })
+            .unwrap();
+        match resp {
+            RpcResponse::Pong {
+                protocol_version,
+                nonce,
+            } => {
+                assert_eq!(protocol_version, PROTOCOL_VERSION);
+                assert_eq!(nonce, "abc123");
+            }
+            other => panic!("Expected Pong, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_set_status_rejects_unknown_status() {
+        let (mut client, _tmp, _handle) = start_exec_server(&[], true);
+        let resp = client
+            .call(&RpcRequest::SetStatus {
+                status: "busy".to_string(),
+                tokens: None,
+                cost: None,
+            })
+            .unwrap();
+        match resp {
+            RpcResponse::Error { message } => assert_eq!(
+                message,
+                "Unknown status 'busy': expected one of working, waiting, done, clear"
+            ),
+            other => panic!("Expected Error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_record_status_keeps_latest_snapshot() {
+        let ctx = RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux: multiplexer::create_backend(multiplexer::BackendType::Tmux),
+            token: generate_token(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        };
+
+        record_status(&ctx, "Working", None, None);
+        record_status(&ctx, "waiting", Some(1500), Some(0.25));
+
+        let snapshot = ctx.status.lock().unwrap().clone().unwrap();
+        assert_eq!(snapshot.status, "waiting");
+        assert_eq!(snapshot.tokens, Some(1500));
+        assert_eq!(snapshot.cost, Some(0.25));
+        assert!(snapshot.updated_at > 0);
+    }
+
+    #[test]
+    fn test_shutdown_releases_port_and_closes_connections() {
+        let (mut client, _tmp, handle) = start_exec_server(&[], true);
+        let port = handle.port;
+        client.call(&RpcRequest::Heartbeat).unwrap();
+
+        handle.shutdown();
+
+        // Idle connection is aborted after the grace period
+        assert!(client.recv().is_err());
+        // Listener is gone, so new connections are refused
+        assert!(TcpStream::connect(("127.0.0.1", port)).is_err());
+    }
+
+    #[test]
+    fn test_shutdown_wakes_listeners_off_ipv4_loopback() {
+        let v6 = IpAddr::V6(std::net::Ipv6Addr::LOCALHOST);
+        for addr in [v6, IpAddr::V4(Ipv4Addr::UNSPECIFIED)] {
+            let server = RpcServer::bind(addr).unwrap();
+            let port = server.port();
+            let handle = server.spawn(Arc::new(RpcContext {
+                pane_id: "%0".to_string(),
+                worktree_path: PathBuf::from("/tmp/test"),
+                mux: multiplexer::create_backend(multiplexer::BackendType::Tmux),
+                token: generate_token(),
+                allowed_commands: std::collections::HashSet::new(),
+                detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+                allow_unsandboxed_host_exec: false,
+                events: None,
+                stderr: None,
+                output: None,
+                status: Mutex::default(),
+                requests: Default::default(),
+            }));
+
+            let (done, finished) = std::sync::mpsc::channel();
+            thread::spawn(move || {
+                handle.shutdown();
+                let _ = done.send(());
+            });
+            assert!(
+                finished.recv_timeout(Duration::from_secs(10)).is_ok(),
+                "shutdown hung for a listener on {addr}"
+            );
+            assert!(TcpStream::connect(reachable_addr(SocketAddr::new(addr, port))).is_err());
+        }
+    }
+
+    #[test]
+    fn test_saved_session_connects_to_its_bind_address() {
+        let bind = IpAddr::V6(std::net::Ipv6Addr::LOCALHOST);
+        let server = RpcServer::bind(bind).unwrap();
+        let token = generate_token();
+        let session = SavedSession {
+            token: token.clone(),
+            port: server.port(),
+            created_at: 0,
+            bind: Some(bind),
+        };
+        let _handle = server.spawn(Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux: multiplexer::create_backend(multiplexer::BackendType::Tmux),
+            token,
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        }));
+
+        let mut client = session.connect().unwrap();
+        assert!(matches!(
+            client.call(&RpcRequest::Heartbeat).unwrap(),
+            RpcResponse::Ok
+        ));
+        // Not listening on IPv4 loopback, where older sessions point
+        let legacy = SavedSession {
+            bind: None,
+            ..session
+        };
+        assert!(legacy.connect().is_err());
+    }
+
+    #[test]
+    fn test_reachable_addr_maps_wildcards_to_loopback() {
+        let reach = |addr: &str| reachable_addr(addr.parse().unwrap()).to_string();
+        assert_eq!(reach("0.0.0.0:80"), "127.0.0.1:80");
+        assert_eq!(reach("[::]:80"), "[::1]:80");
+        assert_eq!(reach("192.168.5.2:80"), "192.168.5.2:80");
+    }
+
+    #[test]
+    fn test_request_serialization_exec() {
+        let req = RpcRequest::Exec {
+            command: "just".to_string(),
+            args: vec!["check".to_string()],
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        assert!(json.contains("\"method\":\"exec\""));
+        assert!(json.contains("\"command\":\"just\""));
+
+        let parsed: RpcRequest = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcRequest::Exec { command, args } => {
+                assert_eq!(command, "just");
+                assert_eq!(args, vec!["check"]);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_response_serialization_exec_output() {
+        let resp = RpcResponse::ExecOutput {
+            data: "hello\n".to_string(),
+        };
+        let json = serde_json::to_string(&resp).unwrap();
+        assert!(json.contains("\"type\":\"exec_output\""));
+        assert!(json.contains("\"data\":\"hello\\n\""));
+    }
+
+    #[test]
+    fn test_response_serialization_exec_exit() {
+        let resp = RpcResponse::ExecExit { code: 42 };
+        let json = serde_json::to_string(&resp).unwrap();
+        assert!(json.contains("\"type\":\"exec_exit\""));
+        assert!(json.contains("\"code\":42"));
+
+        let parsed: RpcResponse = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcResponse::ExecExit { code } => assert_eq!(code, 42),
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_read_bounded_line_normal() {
+        let data = b"hello world\nsecond line\n";
+        let mut reader = std::io::BufReader::new(&data[..]);
+        let mut buf = String::new();
+
+        let result = read_bounded_line(&mut reader, &mut buf).unwrap();
+        assert!(result.is_some());
+        assert_eq!(buf, "hello world\n");
+
+        let result = read_bounded_line(&mut reader, &mut buf).unwrap();
+        assert!(result.is_some());
+        assert_eq!(buf, "second line\n");
+
+        let result = read_bounded_line(&mut reader, &mut buf).unwrap();
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_sanitized_env_normalizes_path() {
+        // Test the normalization logic directly without modifying env
+        // (set_var is unsafe in Rust 2024 edition due to thread safety)
+        let envs = sanitized_env();
+        if let Some(path) = envs.get("PATH") {
+            for entry in path.split(':') {
+                assert!(
+                    entry.starts_with('/'),
+                    "PATH should only have absolute entries, found: '{}'",
+                    entry
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sanitized_env_excludes_secrets() {
+        let envs = sanitized_env();
+        // Common secret env vars should never appear
+        assert!(!envs.contains_key("AWS_SECRET_ACCESS_KEY"));
+        assert!(!envs.contains_key("GITHUB_TOKEN"));
+        assert!(!envs.contains_key("WM_RPC_TOKEN"));
+        // Only allowlisted keys should be present
+        for key in envs.keys() {
+            assert!(
+                EXEC_ENV_ALLOWLIST.contains(&key.as_str()),
+                "unexpected env key in sanitized env: {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_read_bounded_line_rejects_oversized() {
+        // Create a line that exceeds MAX_REQUEST_LINE
+        let huge = "x".repeat(MAX_REQUEST_LINE + 1);
+        let data = format!("{}\n", huge);
+        let mut reader = std::io::BufReader::new(data.as_bytes());
+        let mut buf = String::new();
+
+        let result = read_bounded_line(&mut reader, &mut buf);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().to_string().contains("exceeds"));
+    }
+
+    #[test]
+    fn test_client_server_invalid_token() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux,
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+
+        let _handle = server.spawn(ctx);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let err = RpcClient::connect("127.0.0.1", port, "wrong-token")
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("Invalid token"));
+    }
+
+    #[test]
+    fn test_read_stderr_returns_buffered_tail() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+        let buffer = crate::sandbox::stderr::StderrBuffer::default();
+        buffer.push("warning: one".to_string());
+        buffer.push("error: two".to_string());
+
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux,
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: Some(buffer),
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+        let _handle = server.spawn(ctx);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let mut client = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        match client
+            .call(&RpcRequest::ReadStderr { lines: Some(1) })
+            .unwrap()
+        {
+            RpcResponse::StderrLines { lines } => assert_eq!(lines, ["error: two"]),
+            other => panic!("unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_tail_returns_output_since_offset() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+        let buffer = crate::sandbox::output::OutputBuffer::default();
+        buffer.push(b"Compiling workmux\r\n");
+
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux,
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: Some(buffer.clone()),
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+        let _handle = server.spawn(Arc::clone(&ctx));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let mut client = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        let offset = match client
+            .call(&RpcRequest::Tail {
+                since: None,
+                bytes: None,
+            })
+            .unwrap()
+        {
+            RpcResponse::OutputTail { data, offset, .. } => {
+                assert_eq!(data, "Compiling workmux\r\n");
+                offset
+            }
+            other => panic!("unexpected response: {:?}", other),
+        };
+
+        buffer.push(b"Finished\r\n");
+        match client
+            .call(&RpcRequest::Tail {
+                since: Some(offset),
+                bytes: None,
+            })
+            .unwrap()
+        {
+            RpcResponse::OutputTail {
+                data, truncated, ..
+            } => assert_eq!((data.as_str(), truncated), ("Finished\r\n", false)),
+            other => panic!("unexpected response: {:?}", other),
+        }
+        // Polling doesn't crowd out the request log
+        assert!(ctx.requests.tail(None).is_empty());
+    }
+
+    #[test]
+    fn test_request_log_records_outcomes_but_not_its_own_reads() {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux,
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+        let _handle = server.spawn(ctx);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let mut client = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        client.call(&RpcRequest::Heartbeat).unwrap();
+        client
+            .call(&RpcRequest::ReadStderr { lines: None })
+            .unwrap();
+        client
+            .call(&RpcRequest::ReadRequestLog { entries: None })
+            .unwrap();
+
+        let entries = match client
+            .call(&RpcRequest::ReadRequestLog { entries: None })
+            .unwrap()
+        {
+            RpcResponse::RequestLog { entries } => entries,
+            other => panic!("unexpected response: {:?}", other),
+        };
+        let summary: Vec<_> = entries
+            .iter()
+            .map(|e| (e.method.as_str(), e.outcome.as_str()))
+            .collect();
+        assert_eq!(summary[0], ("heartbeat", "ok"));
+        assert_eq!(summary[1].0, "read_stderr");
+        assert!(
+            summary[1]
+                .1
+                .starts_with("error: The supervisor isn't buffering stderr")
+        );
+        assert_eq!(summary.len(), 2);
+        assert_eq!(entries[1].params, r#"{"lines":null}"#);
+    }
+
+    /// Start a server and send `first_line` as the raw handshake, returning
+    /// the server's first response frame.
+    fn raw_handshake(first_line: impl FnOnce(&str) -> String) -> ResponseFrame {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: PathBuf::from("/tmp/test"),
+            mux,
+            token: token.clone(),
+            allowed_commands: std::collections::HashSet::new(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: false,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+        let _handle = server.spawn(ctx);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let mut line = first_line(&token);
+        line.push('\n');
+        (&stream).write_all(line.as_bytes()).unwrap();
+
+        let mut reader = BufReader::new(stream);
+        let mut response = String::new();
+        reader.read_line(&mut response).unwrap();
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_handshake_rejects_protocol_mismatch() {
+        let resp = raw_handshake(|token| {
+            serde_json::to_string(&RequestFrame {
+                id: 0,
+                token: token.to_string(),
+                request: RpcRequest::Handshake {
+                    protocol_version: PROTOCOL_VERSION + 1,
+                },
+            })
+            .unwrap()
+        });
+        let message = resp.error.unwrap().message;
+        assert!(message.contains("protocol mismatch"), "{message}");
+    }
+
+    #[test]
+    fn test_handshake_rejects_legacy_auth_header() {
+        let resp = raw_handshake(|token| format!(r#"{{"token":"{}"}}"#, token));
+        assert!(resp.error.unwrap().message.contains("protocol mismatch"));
+    }
+
+    #[test]
+    fn test_handshake_required_first() {
+        let resp = raw_handshake(|token| {
+            serde_json::to_string(&RequestFrame {
+                id: 0,
+                token: token.to_string(),
+                request: RpcRequest::Heartbeat,
+            })
+            .unwrap()
+        });
+        assert!(resp.error.unwrap().message.contains("Expected handshake"));
+    }
+
+    // ── Host-exec integration tests ─────────────────────────────────────
+
+    /// Start an RPC server with the given allowed commands and return a
+    /// connected client. Uses a temp dir as the worktree path.
+    ///
+    /// When `allow_unsandboxed` is true, commands run without OS sandbox
+    /// (no bwrap/sandbox-exec). Use this for tests that verify RPC pipeline
+    /// behavior (streaming, exit codes, env sanitization) rather than
+    /// sandbox enforcement.
+    fn start_exec_server(
+        allowed: &[&str],
+        allow_unsandboxed: bool,
+    ) -> (RpcClient, tempfile::TempDir, RpcHandle) {
+        let server = RpcServer::bind(DEFAULT_BIND_ADDR).unwrap();
+        let port = server.port();
+        let token = generate_token();
+        let tmp = tempfile::tempdir().unwrap();
+
+        let mux = multiplexer::create_backend(multiplexer::BackendType::Tmux);
+        let ctx = Arc::new(RpcContext {
+            pane_id: "%0".to_string(),
+            worktree_path: tmp.path().to_path_buf(),
+            mux,
+            token: token.clone(),
+            allowed_commands: allowed.iter().map(|s| s.to_string()).collect(),
+            detected_toolchain: crate::sandbox::toolchain::DetectedToolchain::None,
+            allow_unsandboxed_host_exec: allow_unsandboxed,
+            events: None,
+            stderr: None,
+            output: None,
+            status: Mutex::default(),
+            requests: Default::default(),
+        });
+
+        let handle = server.spawn(ctx);
+        std::thread::sleep(std::time::Duration::from_millis(50));
+
+        let client = RpcClient::connect("127.0.0.1", port, &token).unwrap();
+        (client, tmp, handle)
+    }
+
+    /// Send an exec request and collect all streaming responses into
+    /// (stdout, stderr, exit_code).
+    fn exec_collect(client: &mut RpcClient, command: &str, args: &[&str]) -> (String, String, i32) {
+        client
+            .send(&RpcRequest::Exec {
+                command: command.to_string(),
+                args: args.iter().map(|s| s.to_string()).collect(),
+            })
+            .unwrap();
+
+        let mut stdout = String::new();
+        let mut stderr = String::new();
+        loop {
+            match client.recv().unwrap() {
+                RpcResponse::ExecOutput { data } => stdout.push_str(&data),
+                RpcResponse::ExecError { data } => stderr.push_str(&data),
+                RpcResponse::ExecExit { code } => return (stdout, stderr, code),
+                other => panic!("Unexpected response: {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_exec_allowed_command() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["echo"], true);
+        let (stdout, _stderr, code) = exec_collect(&mut client, "echo", &["hello", "world"]);
+        assert_eq!(code, 0);
+        assert_eq!(stdout.trim(), "hello world");
+    }
+
+    #[test]
+    fn test_exec_disallowed_command() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["echo"], true);
+        let (_stdout, _stderr, code) = exec_collect(&mut client, "ls", &[]);
+        assert_eq!(code, 127, "disallowed command should return 127");
+    }
+
+    #[test]
+    fn test_exec_invalid_command_name() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["echo"], true);
+
+        // Shell metacharacters in command name
+        let (_stdout, _stderr, code) = exec_collect(&mut client, "echo;whoami", &[]);
+        assert_eq!(code, 127);
+
+        // Path traversal
+        let (_stdout, _stderr, code) = exec_collect(&mut client, "/bin/echo", &[]);
+        assert_eq!(code, 127);
+    }
+
+    #[test]
+    fn test_exec_shell_metacharacters_in_args_not_interpreted() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["echo"], true);
+
+        // $(whoami) should be printed literally, not expanded
+        let (stdout, _stderr, code) = exec_collect(&mut client, "echo", &["$(whoami)"]);
+        assert_eq!(code, 0);
+        assert_eq!(stdout.trim(), "$(whoami)");
+
+        // Backtick substitution should be literal
+        let (stdout, _stderr, code) = exec_collect(&mut client, "echo", &["`whoami`"]);
+        assert_eq!(code, 0);
+        assert_eq!(stdout.trim(), "`whoami`");
+
+        // Semicolons should be literal
+        let (stdout, _stderr, code) = exec_collect(&mut client, "echo", &["hello; whoami"]);
+        assert_eq!(code, 0);
+        assert_eq!(stdout.trim(), "hello; whoami");
+    }
+
+    #[test]
+    fn test_exec_env_sanitized() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["env"], true);
+        let (stdout, _stderr, code) = exec_collect(&mut client, "env", &[]);
+        assert_eq!(code, 0);
+
+        // The RPC token is in our process env but should NOT leak to child
+        let env_lines: Vec<&str> = stdout.lines().collect();
+        assert!(
+            !env_lines.iter().any(|l| l.starts_with("WM_RPC_TOKEN=")),
+            "WM_RPC_TOKEN should not be in child environment"
+        );
+
+        // PATH should still be present (it's in the allowlist)
+        assert!(
+            env_lines.iter().any(|l| l.starts_with("PATH=")),
+            "PATH should be in child environment"
+        );
+
+        // PATH should contain only absolute entries (normalized)
+        let path_line = env_lines.iter().find(|l| l.starts_with("PATH=")).unwrap();
+        let path_val = &path_line["PATH=".len()..];
+        for entry in path_val.split(':') {
+            assert!(
+                entry.starts_with('/'),
+                "PATH entry should be absolute: {}",
+                entry
+            );
+        }
+    }
+
+    #[test]
+    fn test_exec_sandbox_blocks_ssh_read() {
+        #[cfg(target_os = "linux")]
+        {
+            // Probe bwrap usability, not just existence. bwrap requires user
+            // namespaces which are unavailable inside nested sandboxes (Nix
+            // build sandbox, Docker without --privileged, etc.).
+            let probe = std::process::Command::new("bwrap")
+                .args(["--ro-bind", "/", "/", "--", "true"])
+                .status();
+            match probe {
+                Ok(s) if s.success() => {}
+                _ => return, // bwrap not usable in this environment
+            }
+        }
+
+        let (mut client, _tmp, _handle) = start_exec_server(&["ls"], false);
+        let home = std::env::var("HOME").unwrap();
+        let ssh_dir = format!("{}/.ssh", home);
+
+        // If ~/.ssh doesn't exist, skip (CI environments)
+        if !std::path::Path::new(&ssh_dir).exists() {
+            return;
+        }
+
+        let (stdout, stderr, code) = exec_collect(&mut client, "ls", &[&ssh_dir]);
+        let _ = (&stdout, &stderr); // used conditionally per platform
+
+        #[cfg(target_os = "macos")]
+        {
+            // macOS sandbox-exec denies the read entirely
+            assert_ne!(
+                code,
+                0,
+                "ls ~/.ssh should fail under sandbox-exec (stderr: {})",
+                stderr.trim()
+            );
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            // bwrap masks ~/.ssh with tmpfs so ls succeeds but sees nothing
+            assert_eq!(code, 0);
+            assert!(
+                stdout.trim().is_empty(),
+                "~/.ssh should appear empty under bwrap, got: {}",
+                stdout.trim()
+            );
+        }
+    }
+
+    #[test]
+    fn test_exec_nonexistent_command() {
+        let (mut client, _tmp, _handle) =
+            start_exec_server(&["this-command-definitely-does-not-exist-xyz"], true);
+        let (_stdout, _stderr, code) = exec_collect(
+            &mut client,
+            "this-command-definitely-does-not-exist-xyz",
+            &[],
+        );
+        // Should fail to spawn, not hang
+        assert_ne!(code, 0);
+    }
+
+    #[test]
+    fn test_exec_exit_code_propagated() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["sh"], true);
+        let (_stdout, _stderr, code) = exec_collect(&mut client, "sh", &["-c", "exit 42"]);
+        assert_eq!(code, 42, "exit code should be propagated from child");
+    }
+
+    #[test]
+    fn test_exec_stderr_captured() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["sh"], true);
+        let (_stdout, stderr, code) = exec_collect(&mut client, "sh", &["-c", "echo oops >&2"]);
+        assert_eq!(code, 0);
+        assert_eq!(stderr.trim(), "oops");
+    }
+
+    #[test]
+    fn test_exec_multiple_commands_on_same_connection() {
+        let (mut client, _tmp, _handle) = start_exec_server(&["echo"], true);
+
+        let (stdout1, _, code1) = exec_collect(&mut client, "echo", &["first"]);
+        assert_eq!(code1, 0);
+        assert_eq!(stdout1.trim(), "first");
+
+        let (stdout2, _, code2) = exec_collect(&mut client, "echo", &["second"]);
+        assert_eq!(code2, 0);
+        assert_eq!(stdout2.trim(), "second");
+    }
+
+    #[test]
+    fn test_request_serialization_merge() {
+        let req = RpcRequest::Merge {
+            name: "feature-x".to_string(),
+            into: Some("main".to_string()),
+            rebase: true,
+            squash: false,
+            ignore_uncommitted: false,
+            keep: true,
+            no_verify: false,
+            no_hooks: true,
+            notification: true,
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        assert!(json.contains("\"method\":\"merge\""));
+        assert!(json.contains("\"name\":\"feature-x\""));
+        assert!(json.contains("\"rebase\":true"));
+        assert!(json.contains("\"keep\":true"));
+        assert!(json.contains("\"no_hooks\":true"));
+        assert!(json.contains("\"notification\":true"));
+
+        // Roundtrip
+        let parsed: RpcRequest = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcRequest::Merge {
+                name,
+                into,
+                rebase,
+                squash,
+                ignore_uncommitted,
+                keep,
+                no_verify,
+                no_hooks,
+                notification,
+            } => {
+                assert_eq!(name, "feature-x");
+                assert_eq!(into.as_deref(), Some("main"));
+                assert!(rebase);
+                assert!(!squash);
+                assert!(!ignore_uncommitted);
+                assert!(keep);
+                assert!(!no_verify);
+                assert!(no_hooks);
+                assert!(notification);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_response_serialization_output() {
+        let resp = RpcResponse::Output {
+            message: "Merged 'feature' into 'main'".to_string(),
+        };
+        let json = serde_json::to_string(&resp).unwrap();
+        assert!(json.contains("\"type\":\"output\""));
+        let parsed: RpcResponse = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcResponse::Output { message } => {
+                assert_eq!(message, "Merged 'feature' into 'main'");
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    #[test]
+    fn test_spawn_agent_with_empty_prompt_omits_prompt_flag() {
+        // When prompt is empty, handle_spawn_agent should not pass --prompt
+        // This prevents creating blank prompt files on the host
+        let tmp = tempfile::tempdir().unwrap();
+        let resp = handle_spawn_agent("", Some("test-branch"), None, &tmp.path().to_path_buf());
+        // The handler will try to run workmux add, which will fail because
+        // we're not in a real environment, but the key assertion is that it
+        // doesn't hang or crash with empty prompt
+        match resp {
+            RpcResponse::Error { .. } => {} // Expected - no real workmux binary
+            RpcResponse::Ok => {}           // Would happen if workmux existed
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spawn_agent_with_background_flag() {
+        // Test that background flag is wired through (not ignored as _background)
+        let tmp = tempfile::tempdir().unwrap();
+        let resp = handle_spawn_agent(
+            "do stuff",
+            Some("bg-branch"),
+            Some(true),
+            &tmp.path().to_path_buf(),
+        );
+        // The handler will fail to run workmux add, but we're testing that
+        // it doesn't crash when background is Some(true)
+        match resp {
+            RpcResponse::Error { .. } => {} // Expected - no real workmux binary
+            RpcResponse::Ok => {}
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spawn_agent_auto_name_when_branch_is_none() {
+        // When branch_name is None, handler should pass --auto-name
+        let tmp = tempfile::tempdir().unwrap();
+        let resp = handle_spawn_agent("fix bug", None, None, &tmp.path().to_path_buf());
+        match resp {
+            RpcResponse::Error { .. } => {} // Expected
+            RpcResponse::Ok => {}
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_spawn_agent_request_serialization_with_background() {
+        // Verify the SpawnAgent request serializes background correctly
+        let req = RpcRequest::SpawnAgent {
+            prompt: "test".to_string(),
+            branch_name: None,
+            background: Some(true),
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        let parsed: RpcRequest = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcRequest::SpawnAgent { background, .. } => {
+                assert_eq!(background, Some(true));
+            }
+            _ => panic!("Wrong variant"),
+        }
+
+        // Test with None background
+        let req = RpcRequest::SpawnAgent {
+            prompt: "test".to_string(),
+            branch_name: Some("branch".to_string()),
+            background: None,
+        };
+        let json = serde_json::to_string(&req).unwrap();
+        let parsed: RpcRequest = serde_json::from_str(&json).unwrap();
+        match parsed {
+            RpcRequest::SpawnAgent { background, .. } => {
+                assert_eq!(background, None);
+            }
+            _ => panic!("Wrong variant"),
+        }
+    }
+
+    // ── Git hook suppression tests ──────────────────────────────────────
+
+    #[test]
+    fn test_disable_git_hooks_sets_env_vars() {
+        use std::ffi::OsStr;
+        use std::process::Command;
+
+        let mut cmd = Command::new("true");
+        disable_git_hooks(&mut cmd);
+
+        let envs: std::collections::HashMap<&OsStr, Option<&OsStr>> = cmd.get_envs().collect();
+
+        assert_eq!(
+            envs.get(OsStr::new("GIT_CONFIG_COUNT")),
+            Some(&Some(OsStr::new("1"))),
+            "GIT_CONFIG_COUNT should be set to 1"
+        );
+        assert_eq!(
+            envs.get(OsStr::new("GIT_CONFIG_KEY_0")),
+            Some(&Some(OsStr::new("core.hooksPath"))),
+            "GIT_CONFIG_KEY_0 should be core.hooksPath"
+        );
+        assert_eq!(
+            envs.get(OsStr::new("GIT_CONFIG_VALUE_0")),
+            Some(&Some(OsStr::new("/dev/null"))),
+            "GIT_CONFIG_VALUE_0 should be /dev/null"
+        );
+        // GIT_CONFIG_PARAMETERS must be removed to prevent it from overriding
+        // the GIT_CONFIG_COUNT-based settings
+        assert_eq!(
+            envs.get(OsStr::new("GIT_CONFIG_PARAMETERS")),
+            Some(&None),
+            "GIT_CONFIG_PARAMETERS should be explicitly removed"
+        );
+    }
+
+    #[test]
+    fn test_merge_command_disables_git_hooks() {
+        // Verify that a merge triggered via RPC would have git hooks disabled.
+        // We can't inspect the Command directly from handle_merge (it spawns
+        // internally), so instead we replicate the command-building logic and
+        // assert the env vars are present via the shared disable_git_hooks helper.
+        use std::ffi::OsStr;
+        use std::process::Command;
+
+        let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("workmux"));
+        let mut cmd = Command::new(exe);
+        cmd.arg("merge").arg("test-branch");
+        cmd.args(["--no-verify", "--no-hooks"]);
+        disable_git_hooks(&mut cmd);
+
+        let envs: std::collections::HashMap<&OsStr, Option<&OsStr>> = cmd.get_envs().collect();
+        assert!(
+            envs.contains_key(OsStr::new("GIT_CONFIG_COUNT")),
+            "merge command should have GIT_CONFIG_COUNT set"
+        );
+        assert_eq!(
+            envs.get(OsStr::new("GIT_CONFIG_VALUE_0")),
+            Some(&Some(OsStr::new("/dev/null"))),
+            "merge command should point core.hooksPath to /dev/null"
+        );
+    }
+
+    #[test]
+    fn test_spawn_agent_command_disables_git_hooks() {
+        // Verify that a spawn-agent triggered via RPC would have git hooks
+        // disabled, preventing post-checkout hooks from firing during
+        // git worktree add.
+        use std::ffi::OsStr;
+        use std::process::Command;
+
+        let exe = std::env::current_exe().unwrap_or_else(|_| PathBuf::from("workmux"));
+        let mut cmd = Command::new(exe);
+        cmd.arg("add").arg("--auto-name");
+        cmd.arg("--no-hooks");
+        disable_git_hooks(&mut cmd);
+
+        let envs: std::collections::HashMap<&OsStr, Option<&OsStr>> = cmd.get_envs().collect();
+        assert!(
+            envs.contains_key(OsStr::new("GIT_CONFIG_COUNT")),
+            "spawn-agent command should have GIT_CONFIG_COUNT set"
+        );
+        assert_eq!(
+            envs.get(OsStr::new("GIT_CONFIG_KEY_0")),
+            Some(&Some(OsStr::new("core.hooksPath"))),
+            "spawn-agent command should set core.hooksPath"
+        );
+    }
+}
@@ -0,0 +1,697 @@
+//! Wire types for the guest-host RPC protocol.
+//!
+//! Every message is one line of JSON. A guest sends request frames
+//! (`{"id", "token", "method", "params"}`) and the host answers each with one
+//! or more response frames (`{"id", "result"}` or `{"id", "error"}`) carrying
+//! the request's `id`. `exec` and `merge` stream several results before the
+//! final one; every other method gets exactly one.
+//!
+//! [`json_schema`] describes the same shapes as a JSON Schema for guests
+//! written in other languages. Keep [`METHODS`] and [`RESULTS`] in sync with
+//! the enums below; the tests check that they agree.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+
+/// Wire protocol version. Bump on any incompatible change to the frames or
+/// to a method's params/results.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// RPC request sent from guest to host. Serialized as `method` + `params`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum RpcRequest {
+    /// Must be the first request on a connection. Answered with
+    /// [`RpcResponse::Handshake`], or an error if the versions differ.
+    Handshake { protocol_version: u32 },
+    /// Set the agent's window status, as `workmux set-window-status` does.
+    /// `status` is one of `working`, `waiting`, `done` or `clear`.
+    SetStatus {
+        status: String,
+        /// Tokens used so far, shown next to the status icon
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        tokens: Option<u64>,
+        /// Cost so far in USD, shown next to the status icon
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cost: Option<f64>,
+    },
+    /// Rename the agent's window.
+    SetTitle { title: String },
+    /// Keep-alive; answered with [`RpcResponse::Ok`].
+    Heartbeat,
+    /// Health check; the host echoes `nonce` back with its protocol version.
+    Ping { nonce: String },
+    /// Create a new worktree and agent on the host, like `workmux add`.
+    SpawnAgent {
+        prompt: String,
+        /// Branch to create; generated from the prompt when null
+        branch_name: Option<String>,
+        /// Create the window without switching to it
+        background: Option<bool>,
+    },
+    /// Run one of the configured `host_commands` on the host. Output streams
+    /// back as [`RpcResponse::ExecOutput`] and [`RpcResponse::ExecError`],
+    /// ending with [`RpcResponse::ExecExit`].
+    Exec { command: String, args: Vec<String> },
+    /// Open a file in the host's editor.
+    OpenPath {
+        /// Path relative to the worktree root
+        path: String,
+    },
+    /// Merge a worktree on the host, like `workmux merge`. Progress streams
+    /// back as [`RpcResponse::Output`], ending with [`RpcResponse::Ok`] or an
+    /// error. `no_verify` and `no_hooks` are always forced on by the host.
+    Merge {
+        name: String,
+        /// Target branch; the main branch when null
+        into: Option<String>,
+        rebase: bool,
+        squash: bool,
+        ignore_uncommitted: bool,
+        keep: bool,
+        no_verify: bool,
+        no_hooks: bool,
+        notification: bool,
+    },
+    /// Read the agent's buffered stderr, answered with
+    /// [`RpcResponse::StderrLines`]. Only available when the supervisor runs
+    /// with `--buffer-stderr`.
+    ReadStderr {
+        /// Most recent lines to return; everything buffered when null
+        lines: Option<u16>,
+    },
+    /// Read the supervisor's log of recent requests, answered with
+    /// [`RpcResponse::RequestLog`].
+    ReadRequestLog {
+        /// Most recent entries to return; everything kept when null
+        entries: Option<u16>,
+    },
+    /// Read the agent's recent terminal output, answered with
+    /// [`RpcResponse::OutputTail`]. Only available when the supervisor runs
+    /// with `--buffer-output`.
+    Tail {
+        /// Only return output after this offset, from a previous answer's
+        /// `offset`; everything buffered when null
+        since: Option<u64>,
+        /// Most recent bytes to return; no limit when null
+        bytes: Option<u32>,
+    },
+}
+
+/// RPC response sent from host to guest.
+///
+/// `Error` travels in the frame's `error` field; every other variant is the
+/// frame's `result`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RpcResponse {
+    /// Accepted handshake, with the host's protocol version.
+    Handshake { protocol_version: u32 },
+    /// The request succeeded and has nothing to return.
+    Ok,
+    /// Answer to [`RpcRequest::Ping`].
+    Pong {
+        protocol_version: u32,
+        nonce: String,
+    },
+    /// The request failed.
+    #[serde(skip)]
+    Error { message: String },
+    /// A chunk of progress output from `merge`.
+    Output { message: String },
+    /// A chunk of the `exec` command's stdout.
+    ExecOutput { data: String },
+    /// A chunk of the `exec` command's stderr.
+    ExecError { data: String },
+    /// The `exec` command exited with `code`; always the last frame.
+    ExecExit { code: i32 },
+    /// Answer to [`RpcRequest::ReadStderr`], oldest line first.
+    StderrLines { lines: Vec<String> },
+    /// Answer to [`RpcRequest::ReadRequestLog`], oldest entry first.
+    RequestLog { entries: Vec<RequestLogEntry> },
+    /// Answer to [`RpcRequest::Tail`].
+    OutputTail {
+        /// Raw output, escape sequences included
+        data: String,
+        /// Offset just past `data`, to pass as the next `since`
+        offset: u64,
+        /// Whether output after `since` was skipped: dropped from the
+        /// buffer, or beyond the `bytes` limit
+        truncated: bool,
+    },
+}
+
+/// A request the supervisor handled, as kept for `workmux sandbox rpc-log`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestLogEntry {
+    /// When the request arrived (Unix milliseconds)
+    pub at: u64,
+    pub method: String,
+    /// `ok`, `exit <code>` for `exec`, or `error: <message>`
+    pub outcome: String,
+    /// The params as JSON, with secrets redacted and long values truncated
+    pub params: String,
+}
+
+/// The agent status last reported with [`RpcRequest::SetStatus`]. The
+/// supervisor keeps it so the host can read the agent's state without
+/// asking the guest or the multiplexer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    /// `working`, `waiting`, `done` or `clear`
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+    /// When the guest reported it (Unix seconds)
+    pub updated_at: u64,
+}
+
+/// A request on the wire.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct RequestFrame<R> {
+    pub(super) id: u64,
+    pub(super) token: String,
+    #[serde(flatten)]
+    pub(super) request: R,
+}
+
+/// A response on the wire. Exactly one of `result` and `error` is set.
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct ResponseFrame {
+    pub(super) id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) result: Option<RpcResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(super) error: Option<RpcError>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct RpcError {
+    pub(super) message: String,
+}
+
+impl ResponseFrame {
+    pub(super) fn new(id: u64, response: RpcResponse) -> Self {
+        match response {
+            RpcResponse::Error { message } => Self {
+                id,
+                result: None,
+                error: Some(RpcError { message }),
+            },
+            result => Self {
+                id,
+                result: Some(result),
+                error: None,
+            },
+        }
+    }
+
+    pub(super) fn into_response(self) -> Result<RpcResponse> {
+        match (self.result, self.error) {
+            (_, Some(error)) => Ok(RpcResponse::Error {
+                message: error.message,
+            }),
+            (Some(result), None) => Ok(result),
+            (None, None) => anyhow::bail!("RPC response {} has neither result nor error", self.id),
+        }
+    }
+}
+
+// ── Schema ──────────────────────────────────────────────────────────────
+
+/// A field of a request's params or a response's result.
+pub struct Field {
+    pub name: &'static str,
+    /// JSON Schema type: `string`, `integer`, `number`, `boolean`, `array`
+    /// (of strings) or `objects` (an array of objects)
+    pub ty: &'static str,
+    /// Whether the field may be omitted or null
+    pub optional: bool,
+}
+
+/// A request method or response type, as it appears on the wire.
+pub struct Message {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub fields: &'static [Field],
+}
+
+const fn field(name: &'static str, ty: &'static str) -> Field {
+    Field {
+        name,
+        ty,
+        optional: false,
+    }
+}
+
+const fn optional(name: &'static str, ty: &'static str) -> Field {
+    Field {
+        name,
+        ty,
+        optional: true,
+    }
+}
+
+/// Request methods, mirroring [`RpcRequest`].
+pub const METHODS: &[Message] = &[
+    Message {
+        name: "handshake",
+        description: "Must be the first request on a connection",
+        fields: &[field("protocol_version", "integer")],
+    },
+    Message {
+        name: "set_status",
+        description: "Set the agent's window status: working, waiting, done or clear",
+        fields: &[
+            field("status", "string"),
+            optional("tokens", "integer"),
+            optional("cost", "number"),
+        ],
+    },
+    Message {
+        name: "set_title",
+        description: "Rename the agent's window",
+        fields: &[field("title", "string")],
+    },
+    Message {
+        name: "heartbeat",
+        description: "Keep-alive; takes no params",
+        fields: &[],
+    },
+    Message {
+        name: "ping",
+        description: "Health check; the host echoes the nonce back",
+        fields: &[field("nonce", "string")],
+    },
+    Message {
+        name: "spawn_agent",
+        description: "Create a new worktree and agent on the host",
+        fields: &[
+            field("prompt", "string"),
+            optional("branch_name", "string"),
+            optional("background", "boolean"),
+        ],
+    },
+    Message {
+        name: "exec",
+        description: "Run an allowed host command, streaming exec_output/exec_error until exec_exit",
+        fields: &[field("command", "string"), field("args", "array")],
+    },
+    Message {
+        name: "open_path",
+        description: "Open a file, relative to the worktree root, in the host's editor",
+        fields: &[field("path", "string")],
+    },
+    Message {
+        name: "merge",
+        description: "Merge a worktree on the host, streaming output until ok or an error",
+        fields: &[
+            field("name", "string"),
+            optional("into", "string"),
+            field("rebase", "boolean"),
+            field("squash", "boolean"),
+            field("ignore_uncommitted", "boolean"),
+            field("keep", "boolean"),
+            field("no_verify", "boolean"),
+            field("no_hooks", "boolean"),
+            field("notification", "boolean"),
+        ],
+    },
+    Message {
+        name: "read_stderr",
+        description: "Read the agent's buffered stderr (supervisor started with --buffer-stderr)",
+        fields: &[optional("lines", "integer")],
+    },
+    Message {
+        name: "read_request_log",
+        description: "Read the supervisor's log of recent requests",
+        fields: &[optional("entries", "integer")],
+    },
+    Message {
+        name: "tail",
+        description: "Read the agent's recent output after an offset (supervisor started with --buffer-output)",
+        fields: &[optional("since", "integer"), optional("bytes", "integer")],
+    },
+];
+
+/// Result types, mirroring [`RpcResponse`] minus `Error`.
+pub const RESULTS: &[Message] = &[
+    Message {
+        name: "handshake",
+        description: "Accepted handshake, with the host's protocol version",
+        fields: &[field("protocol_version", "integer")],
+    },
+    Message {
+        name: "ok",
+        description: "The request succeeded and has nothing to return",
+        fields: &[],
+    },
+    Message {
+        name: "pong",
+        description: "Answer to ping",
+        fields: &[
+            field("protocol_version", "integer"),
+            field("nonce", "string"),
+        ],
+    },
+    Message {
+        name: "output",
+        description: "A chunk of merge progress output",
+        fields: &[field("message", "string")],
+    },
+    Message {
+        name: "exec_output",
+        description: "A chunk of the exec command's stdout",
+        fields: &[field("data", "string")],
+    },
+    Message {
+        name: "exec_error",
+        description: "A chunk of the exec command's stderr",
+        fields: &[field("data", "string")],
+    },
+    Message {
+        name: "exec_exit",
+        description: "The exec command's exit code; always the last frame",
+        fields: &[field("code", "integer")],
+    },
+    Message {
+        name: "stderr_lines",
+        description: "Buffered stderr lines, oldest first",
+        fields: &[field("lines", "array")],
+    },
+    Message {
+        name: "request_log",
+        description: "Recent requests (at, method, outcome, params), oldest first",
+        fields: &[field("entries", "objects")],
+    },
+    Message {
+        name: "output_tail",
+        description: "Recent agent output and the offset to read on from",
+        fields: &[
+            field("data", "string"),
+            field("offset", "integer"),
+            field("truncated", "boolean"),
+        ],
+    },
+];
+
+fn field_schema(field: &Field) -> Value {
+    let mut schema = match field.ty {
+        "array" => json!({"type": "array", "items": {"type": "string"}}),
+        "objects" => json!({"type": "array", "items": {"type": "object"}}),
+        ty => json!({"type": ty}),
+    };
+    if field.optional {
+        schema["type"] = json!([field.ty, "null"]);
+    }
+    schema
+}
+
+/// Object schema for `fields`, plus `extra` properties that are required.
+fn object_schema(fields: &[Field], extra: &[(&str, Value)]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for (name, schema) in extra {
+        properties.insert(name.to_string(), schema.clone());
+        required.push(*name);
+    }
+    for f in fields {
+        properties.insert(f.name.to_string(), field_schema(f));
+        if !f.optional {
+            required.push(f.name);
+        }
+    }
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    })
+}
+
+/// JSON Schema (draft 2020-12) for request and response frames.
+pub fn json_schema() -> Value {
+    let requests: Vec<Value> = METHODS
+        .iter()
+        .map(|m| {
+            let mut schema = object_schema(&[], &[("method", json!({"const": m.name}))]);
+            schema["description"] = m.description.into();
+            if !m.fields.is_empty() {
+                schema["properties"]["params"] = object_schema(m.fields, &[]);
+                schema["required"] = json!(["method", "params"]);
+            }
+            schema
+        })
+        .collect();
+
+    let results: Vec<Value> = RESULTS
+        .iter()
+        .map(|r| {
+            let mut schema = object_schema(r.fields, &[("type", json!({"const": r.name}))]);
+            schema["description"] = r.description.into();
+            schema
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "workmux sandbox RPC",
+        "description": format!(
+            "Newline-delimited JSON frames between a sandbox guest and the host supervisor, protocol version {}. The first request on a connection must be a handshake.",
+            PROTOCOL_VERSION
+        ),
+        "protocol_version": PROTOCOL_VERSION,
+        "$defs": {
+            "request": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer", "minimum": 0},
+                    "token": {"type": "string", "description": "The WM_RPC_TOKEN passed to the guest"},
+                },
+                "required": ["id", "token", "method"],
+                "oneOf": requests,
+            },
+            "response": {
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer", "minimum": 0, "description": "The id of the request being answered"},
+                    "result": {"oneOf": results},
+                    "error": object_schema(&[field("message", "string")], &[]),
+                },
+                "required": ["id"],
+                "oneOf": [{"required": ["result"]}, {"required": ["error"]}],
+            },
+        },
+        "oneOf": [{"$ref": "#/$defs/request"}, {"$ref": "#/$defs/response"}],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One value per variant. The match makes adding a variant a compile
+    /// error here until it gets a sample and a schema entry.
+    fn sample_requests() -> Vec<RpcRequest> {
+        let samples = vec![
+            RpcRequest::Handshake {
+                protocol_version: PROTOCOL_VERSION,
+            },
+            RpcRequest::SetStatus {
+                status: "done".into(),
+                tokens: Some(1),
+                cost: Some(0.5),
+            },
+            RpcRequest::SetTitle { title: "t".into() },
+            RpcRequest::Heartbeat,
+            RpcRequest::Ping { nonce: "n".into() },
+            RpcRequest::SpawnAgent {
+                prompt: "p".into(),
+                branch_name: Some("b".into()),
+                background: Some(true),
+            },
+            RpcRequest::Exec {
+                command: "cargo".into(),
+                args: vec!["build".into()],
+            },
+            RpcRequest::OpenPath { path: "f".into() },
+            RpcRequest::Merge {
+                name: "n".into(),
+                into: Some("main".into()),
+                rebase: false,
+                squash: false,
+                ignore_uncommitted: false,
+                keep: false,
+                no_verify: false,
+                no_hooks: false,
+                notification: false,
+            },
+            RpcRequest::ReadStderr { lines: Some(50) },
+            RpcRequest::ReadRequestLog { entries: Some(20) },
+            RpcRequest::Tail {
+                since: Some(4096),
+                bytes: Some(1024),
+            },
+        ];
+        for sample in &samples {
+            match sample {
+                RpcRequest::Handshake { .. }
+                | RpcRequest::SetStatus { .. }
+                | RpcRequest::SetTitle { .. }
+                | RpcRequest::Heartbeat
+                | RpcRequest::Ping { .. }
+                | RpcRequest::SpawnAgent { .. }
+                | RpcRequest::Exec { .. }
+                | RpcRequest::OpenPath { .. }
+                | RpcRequest::Merge { .. }
+                | RpcRequest::ReadStderr { .. }
+                | RpcRequest::ReadRequestLog { .. }
+                | RpcRequest::Tail { .. } => {}
+            }
+        }
+        samples
+    }
+
+    fn sample_results() -> Vec<RpcResponse> {
+        let samples = vec![
+            RpcResponse::Handshake {
+                protocol_version: PROTOCOL_VERSION,
+            },
+            RpcResponse::Ok,
+            RpcResponse::Pong {
+                protocol_version: PROTOCOL_VERSION,
+                nonce: "n".into(),
+            },
+            RpcResponse::Output {
+                message: "m".into(),
+            },
+            RpcResponse::ExecOutput { data: "d".into() },
+            RpcResponse::ExecError { data: "d".into() },
+            RpcResponse::ExecExit { code: 0 },
+            RpcResponse::StderrLines {
+                lines: vec!["l".into()],
+            },
+            RpcResponse::RequestLog {
+                entries: vec![RequestLogEntry {
+                    at: 1,
+                    method: "heartbeat".into(),
+                    outcome: "ok".into(),
+                    params: String::new(),
+                }],
+            },
+            RpcResponse::OutputTail {
+                data: "d".into(),
+                offset: 1,
+                truncated: false,
+            },
+        ];
+        for sample in &samples {
+            match sample {
+                RpcResponse::Handshake { .. }
+                | RpcResponse::Ok
+                | RpcResponse::Pong { .. }
+                | RpcResponse::Error { .. }
+                | RpcResponse::Output { .. }
+                | RpcResponse::ExecOutput { .. }
+                | RpcResponse::ExecError { .. }
+                | RpcResponse::ExecExit { .. }
+                | RpcResponse::StderrLines { .. }
+                | RpcResponse::RequestLog { .. }
+                | RpcResponse::OutputTail { .. } => {}
+            }
+        }
+        samples
+    }
+
+    fn json_type(value: &Value) -> &'static str {
+        match value {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(n) if n.is_f64() => "number",
+            Value::Number(_) => "integer",
+            Value::String(_) => "string",
+            Value::Array(items) if items.iter().all(Value::is_object) && !items.is_empty() => {
+                "objects"
+            }
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
+    /// Assert that the serialized `object` has exactly the fields of
+    /// `message`, with matching types.
+    fn assert_matches(message: &Message, object: Option<&Map<String, Value>>) {
+        let empty = Map::new();
+        let object = object.unwrap_or(&empty);
+        for f in message.fields {
+            match object.get(f.name) {
+                Some(value) => assert_eq!(json_type(value), f.ty, "{}.{}", message.name, f.name),
+                None => assert!(f.optional, "{}.{} missing", message.name, f.name),
+            }
+        }
+        for key in object.keys() {
+            assert!(
+                message.fields.iter().any(|f| f.name == key),
+                "{}.{} is not in the schema",
+                message.name,
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn methods_table_matches_request_enum() {
+        let samples = sample_requests();
+        assert_eq!(samples.len(), METHODS.len());
+        for request in samples {
+            let json = serde_json::to_value(&request).unwrap();
+            let method = json["method"].as_str().unwrap();
+            let spec = METHODS
+                .iter()
+                .find(|m| m.name == method)
+                .unwrap_or_else(|| panic!("{method} missing from METHODS"));
+            assert_matches(spec, json.get("params").and_then(Value::as_object));
+        }
+    }
+
+    #[test]
+    fn results_table_matches_response_enum() {
+        let samples = sample_results();
+        assert_eq!(samples.len(), RESULTS.len());
+        for response in samples {
+            let mut json = serde_json::to_value(&response).unwrap();
+            let object = json.as_object_mut().unwrap();
+            let ty = object.remove("type").unwrap();
+            let spec = RESULTS
+                .iter()
+                .find(|r| r.name == ty.as_str().unwrap())
+                .unwrap_or_else(|| panic!("{ty} missing from RESULTS"));
+            assert_matches(spec, Some(object));
+        }
+    }
+
+    #[test]
+    fn schema_lists_every_method_and_result() {
+        let schema = json_schema();
+        let methods: Vec<&str> = schema["$defs"]["request"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|m| m["properties"]["method"]["const"].as_str().unwrap())
+            .collect();
+        assert_eq!(methods.len(), METHODS.len());
+        assert!(methods.contains(&"spawn_agent"));
+
+        let heartbeat = &schema["$defs"]["request"]["oneOf"][3];
+        assert_eq!(heartbeat["required"], json!(["method"]));
+        let set_status = &schema["$defs"]["request"]["oneOf"][1]["properties"]["params"];
+        assert_eq!(set_status["required"], json!(["status"]));
+        assert_eq!(
+            set_status["properties"]["tokens"]["type"],
+            json!(["integer", "null"])
+        );
+        assert_eq!(schema["protocol_version"], PROTOCOL_VERSION);
+    }
+}
@@ -0,0 +1,190 @@
+//! The most recent requests an RPC server handled, kept in memory for
+//! `workmux sandbox rpc-log`.
+
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::protocol::{RequestLogEntry, RpcRequest, RpcResponse};
+
+/// Requests kept before the oldest are dropped.
+pub const CAPACITY: usize = 200;
+
+/// Longest params summary kept per request, in characters.
+const MAX_PARAMS_CHARS: usize = 200;
+
+/// Ring buffer of request summaries.
+#[derive(Default)]
+pub struct RequestLog(Mutex<VecDeque<RequestLogEntry>>);
+
+impl RequestLog {
+    /// Record `request`, which was answered with `outcome`.
+    pub fn record(&self, request: &RpcRequest, outcome: &str) {
+        let (method, params) = summarize(request);
+        let entry = RequestLogEntry {
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            method,
+            outcome: outcome.to_string(),
+            params,
+        };
+        let mut entries = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if entries.len() == CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// The last `count` entries, or all of them with `None`, oldest first.
+    pub fn tail(&self, count: Option<u16>) -> Vec<RequestLogEntry> {
+        let entries = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let skip = count.map_or(0, |n| entries.len().saturating_sub(n as usize));
+        entries.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// How a request ended, judged by the last response sent for it: `ok`,
+/// `exit <code>` for `exec`, or `error: <message>`. `None` for the
+/// intermediate frames of a streamed response.
+pub fn outcome(response: &RpcResponse) -> Option<String> {
+    match response {
+        RpcResponse::Error { message } => Some(format!("error: {}", message)),
+        RpcResponse::ExecExit { code } => Some(format!("exit {}", code)),
+        RpcResponse::Output { .. }
+        | RpcResponse::ExecOutput { .. }
+        | RpcResponse::ExecError { .. } => None,
+        _ => Some("ok".to_string()),
+    }
+}
+
+/// The request's method name and its params as compact JSON, with secrets
+/// redacted and truncated to [`MAX_PARAMS_CHARS`].
+fn summarize(request: &RpcRequest) -> (String, String) {
+    let mut json = serde_json::to_value(request).unwrap_or_default();
+    let method = json["method"].as_str().unwrap_or_default().to_string();
+    let params = match json.get_mut("params") {
+        Some(params) => {
+            redact(params);
+            truncate(&params.to_string())
+        }
+        None => String::new(),
+    };
+    (method, params)
+}
+
+/// Whether a field or variable name suggests its value is a credential.
+fn is_secret_name(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [
+        "token", "secret", "password", "passwd", "api_key", "apikey", "auth",
+    ]
+    .iter()
+    .any(|word| name.contains(word))
+}
+
+/// Blank out values of secret-looking fields, and of `NAME=value` or
+/// `Header: value` strings (e.g. `exec` args) whose name looks secret.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_secret_name(key) {
+                    *value = Value::String("<redacted>".to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        Value::String(s) => {
+            if let Some(sep) = s.find(['=', ':'])
+                && is_secret_name(&s[..sep])
+            {
+                s.replace_range(sep + 1.., "<redacted>");
+            }
+        }
+        _ => {}
+    }
+}
+
+fn truncate(s: &str) -> String {
+    match s.char_indices().nth(MAX_PARAMS_CHARS) {
+        Some((end, _)) => format!("{}...", &s[..end]),
+        None => s.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_keeps_the_most_recent_entries() {
+        let log = RequestLog::default();
+        for i in 0..CAPACITY + 3 {
+            log.record(
+                &RpcRequest::SetTitle {
+                    title: i.to_string(),
+                },
+                "ok",
+            );
+        }
+        let entries = log.tail(None);
+        assert_eq!(entries.len(), CAPACITY);
+        assert_eq!(entries[0].params, r#"{"title":"3"}"#);
+
+        let last = log.tail(Some(1));
+        assert_eq!(last.len(), 1);
+        assert_eq!(last[0].method, "set_title");
+        assert_eq!(last[0].params, format!(r#"{{"title":"{}"}}"#, CAPACITY + 2));
+        assert!(last[0].at > 0);
+    }
+
+    #[test]
+    fn summarize_redacts_secrets_and_truncates() {
+        let (method, params) = summarize(&RpcRequest::Exec {
+            command: "curl".into(),
+            args: vec![
+                "-H".into(),
+                "Authorization: Bearer ghp_abc".into(),
+                "API_KEY=abc".into(),
+                "https://example.com/?a=b".into(),
+            ],
+        });
+        assert_eq!(method, "exec");
+        assert_eq!(
+            params,
+            r#"{"args":["-H","Authorization:<redacted>","API_KEY=<redacted>","https://example.com/?a=b"],"command":"curl"}"#
+        );
+
+        let (method, params) = summarize(&RpcRequest::Heartbeat);
+        assert_eq!((method.as_str(), params.as_str()), ("heartbeat", ""));
+
+        let (_, params) = summarize(&RpcRequest::SpawnAgent {
+            prompt: "x".repeat(500),
+            branch_name: None,
+            background: None,
+        });
+        assert_eq!(params.chars().count(), MAX_PARAMS_CHARS + 3);
+        assert!(params.ends_with("..."));
+    }
+
+    #[test]
+    fn outcome_skips_streamed_frames() {
+        assert_eq!(outcome(&RpcResponse::Ok).as_deref(), Some("ok"));
+        assert_eq!(
+            outcome(&RpcResponse::ExecExit { code: 2 }).as_deref(),
+            Some("exit 2")
+        );
+        assert_eq!(
+            outcome(&RpcResponse::Error {
+                message: "Invalid token".into()
+            })
+            .as_deref(),
+            Some("error: Invalid token")
+        );
+        assert_eq!(outcome(&RpcResponse::ExecOutput { data: "d".into() }), None);
+    }
+}
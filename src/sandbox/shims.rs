@@ -132,6 +132,9 @@ mod tests {
     }
 
     #[test]
+    // Joining the absolute name is the point: it's where a naive
+    // `shim_bin.join(cmd)` would have written the shim
+    #[allow(clippy::join_absolute_paths)]
     fn test_create_shim_directory_skips_invalid() {
         let tmp = tempfile::tempdir().unwrap();
         let commands = vec!["valid".to_string(), "/bin/evil".to_string(), "".to_string()];
@@ -139,6 +142,7 @@ mod tests {
         let shim_bin = create_shim_directory(tmp.path(), &commands).unwrap();
         assert!(shim_bin.join("valid").exists());
         assert!(!shim_bin.join("/bin/evil").exists());
+        assert!(!shim_bin.join("bin/evil").exists());
     }
 
     #[test]
@@ -0,0 +1,94 @@
+//! The agent's stderr, kept apart from stdout by the supervisor with
+//! `sandbox run --buffer-stderr` and served over RPC for
+//! `workmux capture --stream stderr`.
+//!
+//! Multiplexer panes only ever see both streams interleaved, so this is the
+//! one place they can still be told apart.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// Lines kept before the oldest are dropped.
+pub const CAPACITY: usize = 10_000;
+
+/// Ring buffer of the most recent stderr lines. Cloning is cheap.
+#[derive(Clone, Default)]
+pub struct StderrBuffer(Arc<Mutex<VecDeque<String>>>);
+
+impl StderrBuffer {
+    pub fn push(&self, line: String) {
+        let mut lines = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if lines.len() == CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// The last `count` lines, or all of them with `None`.
+    pub fn tail(&self, count: Option<u16>) -> Vec<String> {
+        let lines = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        let skip = count.map_or(0, |n| lines.len().saturating_sub(n as usize));
+        lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Copy `reader` to our own stderr as it arrives, keeping each complete line
+/// in `buffer`. Returns at EOF, after storing any unterminated last line.
+pub fn relay(mut reader: impl Read, buffer: &StderrBuffer) {
+    let mut stderr = io::stderr();
+    let mut buf = [0u8; 8192];
+    let mut partial = Vec::new();
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        let _ = stderr.write_all(&buf[..n]);
+        let _ = stderr.flush();
+
+        partial.extend_from_slice(&buf[..n]);
+        while let Some(end) = partial.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = partial.drain(..=end).collect();
+            buffer.push(line_text(&line[..end]));
+        }
+    }
+    if !partial.is_empty() {
+        buffer.push(line_text(&partial));
+    }
+}
+
+fn line_text(bytes: &[u8]) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    text.strip_suffix('\r').unwrap_or(&text).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relay_splits_lines_across_reads() {
+        let buffer = StderrBuffer::default();
+        relay(&b"warn: one\r\nerror: t"[..], &buffer);
+        relay(&b"wo\n"[..], &buffer);
+        assert_eq!(buffer.tail(None), ["warn: one", "error: t", "wo"]);
+    }
+
+    #[test]
+    fn tail_returns_the_most_recent_lines() {
+        let buffer = StderrBuffer::default();
+        for i in 0..CAPACITY + 5 {
+            buffer.push(i.to_string());
+        }
+        assert_eq!(buffer.tail(None).len(), CAPACITY);
+        assert_eq!(buffer.tail(None)[0], "5");
+        assert_eq!(
+            buffer.tail(Some(2)),
+            [(CAPACITY + 3).to_string(), (CAPACITY + 4).to_string()]
+        );
+    }
+}
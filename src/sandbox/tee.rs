@@ -0,0 +1,333 @@
+//! Run a sandbox command on a pseudo-terminal, copying its output to a file.
+//!
+//! Plain stdio inheritance can't be teed without losing the TTY, which breaks
+//! interactive agents. Instead the child gets the slave side of a fresh PTY
+//! and the supervisor relays between its own terminal and the master side,
+//! writing everything the child prints to stdout, to the log file and/or
+//! asciinema recording, and to the buffer served over RPC.
+
+use anyhow::{Context, Result};
+use nix::pty::{OpenptyResult, Winsize, openpty};
+use std::fs::{File, OpenOptions};
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use tracing::{debug, warn};
+
+use super::cast::CastWriter;
+use super::output::OutputBuffer;
+
+/// How often the input relay wakes up to check for exit and terminal resizes.
+const POLL_INTERVAL_MS: i32 = 100;
+
+/// Terminal size recorded when stdout isn't a terminal.
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// Where the command's output is copied to besides stdout.
+#[derive(Clone, Default)]
+pub struct PtyOutput {
+    /// Appended to as plain bytes (`--tee`)
+    pub tee: Option<PathBuf>,
+    /// Overwritten with an asciinema v2 recording (`--record`)
+    pub record: Option<PathBuf>,
+    /// Kept for the RPC `tail` method (`--buffer-output`)
+    pub buffer: Option<OutputBuffer>,
+}
+
+impl PtyOutput {
+    /// Whether nothing needs copying, so stdio can be inherited directly.
+    pub fn is_empty(&self) -> bool {
+        self.tee.is_none() && self.record.is_none() && self.buffer.is_none()
+    }
+}
+
+/// Spawn `cmd` on a PTY, mirroring its combined stdout/stderr to the current
+/// stdout and to the files and buffer in `output`. Returns the child's exit status.
+///
+/// `on_spawn` is called with the child's pid right after it starts.
+pub fn run_on_pty(
+    cmd: &mut Command,
+    output: &PtyOutput,
+    on_spawn: impl FnOnce(u32),
+) -> Result<ExitStatus> {
+    let log = output
+        .tee
+        .as_deref()
+        .map(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("Failed to open tee log {}", path.display()))
+        })
+        .transpose()?;
+
+    let size = terminal_size(libc::STDOUT_FILENO);
+    let cast = output
+        .record
+        .as_deref()
+        .map(|path| {
+            let (cols, rows) = size.map_or(DEFAULT_SIZE, |ws| (ws.ws_col, ws.ws_row));
+            CastWriter::create(path, cols, rows)
+        })
+        .transpose()?;
+
+    let OpenptyResult { master, slave } =
+        openpty(size.as_ref(), None).context("Failed to allocate a pseudo-terminal")?;
+
+    cmd.stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+    // SAFETY: setsid and ioctl are async-signal-safe. The child becomes a
+    // session leader so the PTY can be its controlling terminal, which is
+    // what job control and window-size signals rely on.
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+
+    let _raw = RawModeGuard::enable();
+    let mut child = cmd.spawn()?;
+    on_spawn(child.id());
+    // Drop the parent's copies of the slave so reads on the master hit EOF
+    // once the child (and anything it spawned) is gone.
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    debug!(
+        tee = ?output.tee,
+        record = ?output.record,
+        buffer = output.buffer.is_some(),
+        "relaying sandbox output"
+    );
+
+    let done = Arc::new(AtomicBool::new(false));
+    let input = {
+        let master = master.try_clone()?;
+        let done = done.clone();
+        thread::spawn(move || relay_input(master, &done))
+    };
+
+    relay_output(File::from(master), log, cast, output.buffer.as_ref());
+    let status = child.wait()?;
+
+    done.store(true, Ordering::Relaxed);
+    let _ = input.join();
+    Ok(status)
+}
+
+/// Copy PTY output to stdout, the log, the recording and the buffer until the
+/// slave side closes. A file that fails to write is dropped; stdout keeps
+/// going.
+fn relay_output(
+    mut reader: File,
+    mut log: Option<File>,
+    mut cast: Option<CastWriter>,
+    buffer: Option<&OutputBuffer>,
+) {
+    let mut stdout = io::stdout();
+    let mut buf = [0u8; 8192];
+
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            // Linux reports EIO on the master once every slave fd is closed
+            Err(_) => break,
+        };
+        let _ = stdout.write_all(&buf[..n]);
+        let _ = stdout.flush();
+        if let Some(buffer) = buffer {
+            buffer.push(&buf[..n]);
+        }
+        if let Some(f) = &mut log
+            && let Err(e) = f.write_all(&buf[..n])
+        {
+            warn!(error = %e, "failed to write tee log; continuing without it");
+            log = None;
+        }
+        if let Some(c) = &mut cast
+            && let Err(e) = c.output(&buf[..n])
+        {
+            warn!(error = %e, "failed to write recording; continuing without it");
+            cast = None;
+        }
+    }
+}
+
+/// Forward stdin and terminal resizes to the PTY until `done` is set.
+///
+/// Polls with a timeout instead of blocking on read so the thread stops
+/// promptly and doesn't swallow keypresses meant for `--hold`.
+fn relay_input(master: OwnedFd, done: &AtomicBool) {
+    let mut writer = File::from(master);
+    let mut size = terminal_size(libc::STDOUT_FILENO);
+    let mut buf = [0u8; 1024];
+
+    while !done.load(Ordering::Relaxed) {
+        let current = terminal_size(libc::STDOUT_FILENO);
+        if let Some(ws) = current
+            && current.map(|w| (w.ws_row, w.ws_col)) != size.map(|w| (w.ws_row, w.ws_col))
+        {
+            // SAFETY: TIOCSWINSZ reads a winsize struct from a valid pointer
+            unsafe { libc::ioctl(writer.as_raw_fd(), libc::TIOCSWINSZ, &ws) };
+            size = current;
+        }
+
+        let mut pfd = libc::pollfd {
+            fd: libc::STDIN_FILENO,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: pfd is a single valid pollfd
+        let ready = unsafe { libc::poll(&mut pfd, 1, POLL_INTERVAL_MS) };
+        if ready <= 0 {
+            continue;
+        }
+        if pfd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0
+            && pfd.revents & libc::POLLIN == 0
+        {
+            break;
+        }
+
+        match io::stdin().lock().read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => {
+                if writer.write_all(&buf[..n]).is_err() {
+                    break;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        }
+    }
+}
+
+fn terminal_size(fd: RawFd) -> Option<Winsize> {
+    let mut ws: Winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: TIOCGWINSZ writes a winsize struct to a valid pointer
+    let ok = unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut ws) } == 0;
+    (ok && ws.ws_row > 0 && ws.ws_col > 0).then_some(ws)
+}
+
+/// Puts the supervisor's terminal in raw mode so keystrokes reach the
+/// child's PTY unprocessed; restored on drop.
+struct RawModeGuard(bool);
+
+impl RawModeGuard {
+    fn enable() -> Self {
+        Self(io::stdin().is_terminal() && crossterm::terminal::enable_raw_mode().is_ok())
+    }
+}
+
+impl Drop for RawModeGuard {
+    fn drop(&mut self) {
+        if self.0 {
+            let _ = crossterm::terminal::disable_raw_mode();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tee_captures_output_and_exit_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("agent.log");
+
+        let status = run_on_pty(
+            Command::new("sh").args(["-c", "echo out; echo err >&2; exit 3"]),
+            &PtyOutput {
+                tee: Some(log.clone()),
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(status.code(), Some(3));
+        let captured = std::fs::read_to_string(&log).unwrap();
+        assert!(captured.contains("out"), "{captured:?}");
+        assert!(captured.contains("err"), "{captured:?}");
+    }
+
+    #[test]
+    fn tee_child_sees_a_terminal() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("agent.log");
+
+        let status = run_on_pty(
+            Command::new("sh").args(["-c", "test -t 0 && test -t 1"]),
+            &PtyOutput {
+                tee: Some(log),
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(status.success());
+    }
+
+    #[test]
+    fn record_writes_an_asciicast() {
+        let dir = tempfile::tempdir().unwrap();
+        let cast = dir.path().join("agent.cast");
+
+        let status = run_on_pty(
+            Command::new("sh").args(["-c", "echo recorded"]),
+            &PtyOutput {
+                record: Some(cast.clone()),
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(status.success());
+        let content = std::fs::read_to_string(&cast).unwrap();
+        let mut lines = content.lines();
+        let header: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(header["version"], 2);
+        assert!(header["width"].as_u64().unwrap() > 0);
+        let output: String = lines
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()[2].to_string())
+            .collect();
+        assert!(output.contains("recorded"), "{content}");
+    }
+
+    #[test]
+    fn buffer_keeps_output_for_tail() {
+        let buffer = OutputBuffer::default();
+
+        let status = run_on_pty(
+            Command::new("sh").args(["-c", "echo buffered"]),
+            &PtyOutput {
+                buffer: Some(buffer.clone()),
+                ..Default::default()
+            },
+            |_| {},
+        )
+        .unwrap();
+
+        assert!(status.success());
+        let tail = buffer.tail(None, None);
+        assert!(tail.data.contains("buffered"), "{tail:?}");
+        assert_eq!(tail.offset, tail.data.len() as u64);
+    }
+}
@@ -1,10 +1,10 @@
 # Dynamic worktree handle completion (directory names)
-# Used for open/remove/merge/path/close - these accept handles or branch names
+# Used for open/remove/merge/path/close/attach - these accept handles or branch names
 _workmux_handles() {
     workmux _complete-handles 2>/dev/null
 }
 
-# Dynamic git branch completion for add command
+# Dynamic git branch completion for add and list --base
 _workmux_git_branches() {
     workmux _complete-git-branches 2>/dev/null
 }
@@ -40,13 +40,19 @@ _workmux_dynamic() {
                     return
                 fi
                 ;;
-            open|remove|rm|path|close|send|capture|status|wait|run)
+            open|remove|rm|path|close|attach|send|capture|status|wait|run)
                 # Positional arg: handles
                 if [[ "$cur" != -* ]]; then
                     COMPREPLY=($(compgen -W "$(_workmux_handles)" -- "$cur"))
                     return
                 fi
                 ;;
+            list|ls)
+                if [[ "$prev" == "--base" ]]; then
+                    COMPREPLY=($(compgen -W "$(_workmux_git_branches)" -- "$cur"))
+                    return
+                fi
+                ;;
             add)
                 # Handle flags that take specific argument types
                 case "$prev" in
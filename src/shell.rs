@@ -26,6 +26,84 @@ pub fn shell_quote(s: &str) -> String {
     }
 }
 
+/// Quoting rules for the shell a sandbox command is handed to.
+///
+/// Each sandbox backend picks the implementation matching its guest shell;
+/// see [`SandboxBackend::shell_quoting`](crate::config::SandboxBackend::shell_quoting).
+pub trait ShellQuoting {
+    /// Quote `s` so the shell passes it through as a single literal argument.
+    fn quote(&self, s: &str) -> String;
+
+    /// A statement that sets environment variable `name` to the literal `value`.
+    #[allow(dead_code)]
+    fn set_env(&self, name: &str, value: &str) -> String;
+
+    /// Quote each argument and join them into one command line.
+    fn command_line(&self, args: &[String]) -> String {
+        args.iter()
+            .map(|a| self.quote(a))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// `sh`/`bash` quoting, as used by `sh -lc` and `limactl shell`.
+pub struct Posix;
+
+impl ShellQuoting for Posix {
+    fn quote(&self, s: &str) -> String {
+        shell_quote(s)
+    }
+
+    fn set_env(&self, name: &str, value: &str) -> String {
+        format!("export {}={}", name, shell_quote(value))
+    }
+}
+
+/// PowerShell quoting.
+///
+/// Single-quoted PowerShell strings are verbatim except for the quote
+/// itself, which is doubled. PowerShell also treats the typographic single
+/// quotes (U+2018..U+201B) as quote characters, so those are doubled too.
+/// No backend uses it yet.
+#[allow(dead_code)]
+pub struct PowerShell;
+
+#[allow(dead_code)]
+impl PowerShell {
+    fn is_quote(c: char) -> bool {
+        matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}')
+    }
+}
+
+impl ShellQuoting for PowerShell {
+    fn quote(&self, s: &str) -> String {
+        // A leading `-` would be parsed as a parameter name
+        if !s.is_empty()
+            && !s.starts_with('-')
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/' | '\\'))
+        {
+            return s.to_string();
+        }
+
+        let mut quoted = String::with_capacity(s.len() + 2);
+        quoted.push('\'');
+        for c in s.chars() {
+            if Self::is_quote(c) {
+                quoted.push(c);
+            }
+            quoted.push(c);
+        }
+        quoted.push('\'');
+        quoted
+    }
+
+    fn set_env(&self, name: &str, value: &str) -> String {
+        format!("$env:{} = {}", name, self.quote(value))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,4 +152,48 @@ mod tests {
     fn test_shell_quote_empty_string() {
         assert_eq!(shell_quote(""), "''");
     }
+
+    #[test]
+    fn test_posix_quoting_matches_shell_quote() {
+        for s in ["hello", "a b", "it's", "", "$HOME"] {
+            assert_eq!(Posix.quote(s), shell_quote(s));
+        }
+        assert_eq!(Posix.set_env("FOO", "a b"), "export FOO='a b'");
+        assert_eq!(
+            Posix.command_line(&["echo".to_string(), "it's".to_string()]),
+            "echo 'it'\\''s'"
+        );
+    }
+
+    #[test]
+    fn test_powershell_quote_safe_passthrough() {
+        assert_eq!(PowerShell.quote("hello"), "hello");
+        assert_eq!(PowerShell.quote(r"C:\tools\bin"), r"'C:\tools\bin'");
+        assert_eq!(PowerShell.quote(r"tools\bin.exe"), r"tools\bin.exe");
+    }
+
+    #[test]
+    fn test_powershell_quote_wraps_unsafe() {
+        assert_eq!(PowerShell.quote(""), "''");
+        assert_eq!(PowerShell.quote("hello world"), "'hello world'");
+        assert_eq!(PowerShell.quote("$env:HOME"), "'$env:HOME'");
+        assert_eq!(PowerShell.quote("a; b"), "'a; b'");
+        assert_eq!(PowerShell.quote("-Force"), "'-Force'");
+    }
+
+    #[test]
+    fn test_powershell_quote_doubles_quotes() {
+        assert_eq!(PowerShell.quote("it's"), "'it''s'");
+        assert_eq!(PowerShell.quote("it\u{2019}s"), "'it\u{2019}\u{2019}s'");
+        // Backslashes and backticks are literal inside single quotes
+        assert_eq!(PowerShell.quote(r"a`b\'c"), r"'a`b\''c'");
+    }
+
+    #[test]
+    fn test_powershell_set_env() {
+        assert_eq!(
+            PowerShell.set_env("GIT_AUTHOR_NAME", "O'Brien"),
+            "$env:GIT_AUTHOR_NAME = 'O''Brien'"
+        );
+    }
 }
@@ -1,9 +1,22 @@
 use anyhow::Result;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::time::Duration;
+use std::io::IsTerminal;
+use std::process::{Child, ExitStatus};
+use std::time::{Duration, Instant};
 
-/// Create a spinner with consistent styling.
+/// Whether to animate spinners: only on an interactive terminal, and not
+/// when logs are structured (`--log-format json`), since that output is
+/// meant for machines.
+fn interactive() -> bool {
+    std::io::stdout().is_terminal() && crate::logger::format() != crate::logger::LogFormat::Json
+}
+
+/// Create a spinner with consistent styling, or a hidden one when not
+/// [`interactive`].
 fn create_spinner(msg: &str) -> ProgressBar {
+    if !interactive() {
+        return ProgressBar::hidden();
+    }
     let pb = ProgressBar::new_spinner();
     pb.enable_steady_tick(Duration::from_millis(120));
     pb.set_style(
@@ -16,6 +29,15 @@ fn create_spinner(msg: &str) -> ProgressBar {
     pb
 }
 
+/// Print a line above the spinner, or straight to stderr when it's hidden.
+fn print_line(pb: &ProgressBar, line: &str) {
+    if pb.is_hidden() {
+        eprintln!("{}", line);
+    } else {
+        pb.println(line);
+    }
+}
+
 /// Run an operation with a spinner, showing success/failure.
 pub fn with_spinner<T, F>(msg: &str, op: F) -> Result<T>
 where
@@ -37,7 +59,45 @@ where
 /// On completion, the spinner shows success/failure.
 #[allow(dead_code)]
 pub fn with_streaming_command(msg: &str, cmd: std::process::Command) -> Result<()> {
-    with_streaming_command_formatted(msg, cmd, |line| Some(line.to_string()))
+    with_streaming_command_formatted(msg, cmd, None, |line| Some(line.to_string()))
+}
+
+/// A command run by [`with_streaming_command_formatted`] that was killed
+/// because it was still running when its timeout expired.
+#[derive(Debug, thiserror::Error)]
+#[error("{msg} timed out after {}", crate::util::format_elapsed_duration(*.limit))]
+pub struct CommandTimedOut {
+    pub msg: String,
+    pub limit: Duration,
+}
+
+/// How often [`wait_with_timeout`] checks whether the child has exited.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Wait for `child` to exit, killing it once `timeout` has passed. `None`
+/// when it had to be killed.
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<ExitStatus>> {
+    let Some(timeout) = timeout else {
+        return child.wait().map(Some);
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            // It may have exited since try_wait; killing a reaped-but-unwaited
+            // child fails harmlessly
+            let _ = child.kill();
+            child.wait()?;
+            return Ok(None);
+        }
+        std::thread::sleep(WAIT_POLL_INTERVAL.min(deadline - now));
+    }
 }
 
 /// Run a command with a spinner, formatting stderr lines through a formatter.
@@ -45,9 +105,13 @@ pub fn with_streaming_command(msg: &str, cmd: std::process::Command) -> Result<(
 /// Like `with_streaming_command`, but each stderr line is passed through `stderr_formatter`.
 /// Returning `None` filters the line out; returning `Some(s)` prints `s` above the spinner.
 /// Stdout lines are passed through unchanged.
+///
+/// With a `timeout`, the command is killed once it has run that long and a
+/// [`CommandTimedOut`] error is returned.
 pub fn with_streaming_command_formatted(
     msg: &str,
     mut cmd: std::process::Command,
+    timeout: Option<Duration>,
     stderr_formatter: impl Fn(&str) -> Option<String> + Send + 'static,
 ) -> Result<()> {
     use std::io::{BufRead, BufReader};
@@ -76,7 +140,7 @@ pub fn with_streaming_command_formatted(
                 if let Ok(line) = line
                     && !line.trim().is_empty()
                 {
-                    pb_out.println(&line);
+                    print_line(&pb_out, &line);
                 }
             }
         }
@@ -90,19 +154,29 @@ pub fn with_streaming_command_formatted(
                     && let Some(formatted) = stderr_formatter(&line)
                     && !formatted.is_empty()
                 {
-                    pb_err.println(&formatted);
+                    print_line(&pb_err, &formatted);
                 }
             }
         }
     });
 
-    stdout_thread.join().ok();
-    stderr_thread.join().ok();
-
-    let status = child.wait().map_err(|e| {
+    let status = wait_with_timeout(&mut child, timeout).map_err(|e| {
         pb.finish_with_message(format!("✘ {}", msg));
         anyhow::anyhow!("Failed to wait for command: {}", e)
     })?;
+    let Some(status) = status else {
+        // Don't join the readers: processes the command spawned may still
+        // hold its pipes open
+        pb.finish_with_message(format!("✘ {}", msg));
+        return Err(CommandTimedOut {
+            msg: msg.to_string(),
+            limit: timeout.unwrap_or_default(),
+        }
+        .into());
+    };
+
+    stdout_thread.join().ok();
+    stderr_thread.join().ok();
 
     if status.success() {
         pb.finish_with_message(format!("✔ {}", msg));
@@ -112,3 +186,37 @@ pub fn with_streaming_command_formatted(
         anyhow::bail!("{} (exit code: {})", msg, status.code().unwrap_or(-1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn wait_with_timeout_kills_a_command_that_runs_too_long() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let started = Instant::now();
+        let status = wait_with_timeout(&mut child, Some(Duration::from_millis(200))).unwrap();
+        assert!(status.is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let mut child = Command::new("sh").args(["-c", "exit 3"]).spawn().unwrap();
+        let status = wait_with_timeout(&mut child, Some(Duration::from_secs(30))).unwrap();
+        assert_eq!(status.and_then(|s| s.code()), Some(3));
+    }
+
+    #[test]
+    fn streaming_command_reports_timeout() {
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+        let err = with_streaming_command_formatted(
+            "Booting",
+            cmd,
+            Some(Duration::from_millis(200)),
+            |line| Some(line.to_string()),
+        )
+        .unwrap_err();
+        let timed_out = err.downcast_ref::<CommandTimedOut>().unwrap();
+        assert_eq!(timed_out.limit, Duration::from_millis(200));
+    }
+}
@@ -0,0 +1,132 @@
+//! JSON-lines feed of status changes (`status_feed`), for observers such as
+//! status-bar widgets that `tail -F` it.
+
+use anyhow::{Context, Result, anyhow};
+use nix::fcntl::{Flock, FlockArg};
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::warn;
+
+use crate::config::StatusFeedConfig;
+
+/// One line of the feed.
+#[derive(Debug, Serialize)]
+pub struct FeedEvent<'a> {
+    /// Milliseconds since the Unix epoch
+    pub at: u64,
+    /// Directory the agent runs in, when known
+    pub worktree: Option<&'a Path>,
+    pub pane_id: &'a str,
+    /// `working`, `waiting`, `done` or `clear`
+    pub status: &'a str,
+}
+
+impl<'a> FeedEvent<'a> {
+    pub fn now(worktree: Option<&'a Path>, pane_id: &'a str, status: &'a str) -> Self {
+        Self {
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64),
+            worktree,
+            pane_id,
+            status,
+        }
+    }
+}
+
+/// Append `event` to the feed if it's enabled. Best-effort: failures are
+/// logged, never returned, so a broken feed can't block status updates.
+pub fn record(config: &StatusFeedConfig, event: &FeedEvent) {
+    if !config.enabled() {
+        return;
+    }
+    let result = config
+        .path()
+        .and_then(|path| Ok((path, config.max_size()?)))
+        .and_then(|(path, max_size)| append(&path, max_size, event));
+    if let Err(e) = result {
+        warn!(error = %e, "failed to write status feed");
+    }
+}
+
+/// Append `event` as one line, first rotating the file to `<path>.1` if it
+/// has reached `max_size`. Hooks run concurrently, so this holds an
+/// exclusive lock on the feed.
+fn append(path: &Path, max_size: u64, event: &FeedEvent) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut line = serde_json::to_string(event)?;
+    line.push('\n');
+
+    let open = || {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))
+    };
+    let mut file = Flock::lock(open()?, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("Failed to lock {}: {}", path.display(), e))?;
+    if file.metadata()?.len() >= max_size {
+        std::fs::rename(path, rotated_path(path))
+            .with_context(|| format!("Failed to rotate {}", path.display()))?;
+        // The renamed file keeps our lock; writers that were waiting on it
+        // append to the rotated file, which observers no longer follow
+        file = Flock::lock(open()?, FlockArg::LockExclusive)
+            .map_err(|(_, e)| anyhow!("Failed to lock {}: {}", path.display(), e))?;
+    }
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = path.as_os_str().to_owned();
+    rotated.push(".1");
+    PathBuf::from(rotated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_writes_json_lines_and_rotates_at_max_size() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("feed/status.jsonl");
+        let event = FeedEvent {
+            at: 1_700_000_000_000,
+            worktree: Some(Path::new("/repo/feature")),
+            pane_id: "%3",
+            status: "working",
+        };
+        let line = "{\"at\":1700000000000,\"worktree\":\"/repo/feature\",\"pane_id\":\"%3\",\"status\":\"working\"}\n";
+
+        append(&path, 2 * line.len() as u64, &event).unwrap();
+        append(&path, 2 * line.len() as u64, &event).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), line.repeat(2));
+
+        append(&path, 2 * line.len() as u64, &event).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), line);
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("feed/status.jsonl.1")).unwrap(),
+            line.repeat(2)
+        );
+    }
+
+    #[test]
+    fn record_does_nothing_when_disabled() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("status.jsonl");
+        let config = StatusFeedConfig {
+            path: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        };
+        record(&config, &FeedEvent::now(None, "%1", "done"));
+        assert!(!path.exists());
+    }
+}
@@ -3,6 +3,7 @@
 //! This module provides persistent state storage that works across all
 //! terminal multiplexer backends (tmux, WezTerm, Zellij).
 
+pub mod feed;
 pub mod run;
 pub(crate) mod store;
 mod types;
@@ -14,7 +15,7 @@ use tracing::warn;
 use crate::multiplexer::{AgentStatus, Multiplexer};
 
 pub use store::StateStore;
-pub use types::{AgentState, PaneKey};
+pub use types::{AgentState, PaneKey, SandboxInfo};
 
 /// Persist an agent state update to the StateStore.
 ///
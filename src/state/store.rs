@@ -3,10 +3,12 @@
 use anyhow::{Context, Result};
 use std::fs;
 use std::io;
+use std::os::unix::fs::DirBuilderExt;
 use std::path::{Path, PathBuf};
 use tracing::{info, warn};
 
-use super::types::{AgentState, GlobalSettings, PaneKey};
+use super::types::{AgentState, GlobalSettings, PaneKey, SandboxInfo};
+use crate::sandbox::rpc::StatusSnapshot;
 
 /// Manages filesystem-based state persistence for workmux agents.
 ///
@@ -14,9 +16,14 @@ use super::types::{AgentState, GlobalSettings, PaneKey};
 /// ```text
 /// $XDG_STATE_HOME/workmux/           # ~/.local/state/workmux/
 /// ├── settings.json                   # Global dashboard settings
-/// └── agents/
-///     ├── tmux__default__%1.json     # {backend}__{instance}__{pane_id}.json
-///     └── wezterm__main__3.json
+/// ├── agents/
+/// │   ├── tmux__default__%1.json     # {backend}__{instance}__{pane_id}.json
+/// │   └── wezterm__main__3.json
+/// ├── sandboxes/
+/// │   └── my-feature/
+/// │       └── 4242.json               # {handle}/{supervisor_pid}.json
+/// └── worktrees/
+///     └── my-feature-1a2b3c4d/        # {dir name}-{path hash}, see worktree_dir
 /// ```
 pub struct StateStore {
     base_path: PathBuf,
@@ -51,6 +58,38 @@ impl StateStore {
         self.base_path.join("containers")
     }
 
+    /// Directory for files workmux keeps about the worktree at
+    /// `worktree_root` (RPC session, captures), created owner-only.
+    ///
+    /// Sandboxed agents can write anywhere in their worktree, including
+    /// `.workmux/`, so anything the host writes or trusts lives here instead:
+    /// a symlink planted in the worktree can't redirect it.
+    pub fn worktree_dir(&self, worktree_root: &Path) -> Result<PathBuf> {
+        let root = worktree_root
+            .canonicalize()
+            .unwrap_or_else(|_| worktree_root.to_path_buf());
+        let name = root
+            .file_name()
+            .map(|n| slug::slugify(n.to_string_lossy()))
+            .unwrap_or_default();
+        let hash = crate::sandbox::lima::hash_key(&root.to_string_lossy(), 8);
+        let dir = self
+            .base_path
+            .join("worktrees")
+            .join(format!("{}-{}", name, hash));
+        fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        Ok(dir)
+    }
+
+    /// Path to sandbox supervisors directory.
+    fn sandboxes_dir(&self) -> PathBuf {
+        self.base_path.join("sandboxes")
+    }
+
     /// Path to settings file.
     fn settings_path(&self) -> PathBuf {
         self.base_path.join("settings.json")
@@ -116,6 +155,46 @@ impl StateStore {
         }
     }
 
+    /// Point agents of a renamed worktree at its new location and names.
+    ///
+    /// Agents whose workdir is under `from` are moved under `to`, and stored
+    /// window or session names found in `renamed` (old, new) are replaced.
+    /// Returns how many agent files changed.
+    pub fn relocate_agents(
+        &self,
+        from: &Path,
+        to: &Path,
+        renamed: &[(String, String)],
+    ) -> Result<usize> {
+        let rename = |name: &mut Option<String>| {
+            let new = name
+                .as_ref()
+                .and_then(|n| renamed.iter().find(|(old, _)| old == n))
+                .map(|(_, new)| new.clone());
+            let changed = new.is_some();
+            if changed {
+                *name = new;
+            }
+            changed
+        };
+
+        let mut count = 0;
+        for mut state in self.list_all_agents()? {
+            let mut changed = false;
+            if let Ok(rest) = state.workdir.strip_prefix(from) {
+                state.workdir = to.join(rest);
+                changed = true;
+            }
+            changed |= rename(&mut state.window_name);
+            changed |= rename(&mut state.session_name);
+            if changed {
+                self.upsert_agent(&state)?;
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
     /// Load global settings.
     ///
     /// Returns defaults if the file is missing or corrupted.
@@ -186,6 +265,106 @@ impl StateStore {
             .collect()
     }
 
+    // ── Sandbox supervisor state ───────────────────────────────────────────
+
+    /// Record a running sandbox supervisor for a worktree handle.
+    ///
+    /// Writes `sandboxes/<handle>/<pid>.json`.
+    pub fn register_sandbox(&self, handle: &str, info: &SandboxInfo) -> Result<()> {
+        let dir = self.sandboxes_dir().join(handle);
+        fs::create_dir_all(&dir).context("Failed to create sandbox state directory")?;
+        let content = serde_json::to_string_pretty(info)?;
+        write_atomic(&dir.join(format!("{}.json", info.pid)), content.as_bytes())
+    }
+
+    /// Remove a sandbox supervisor record.
+    ///
+    /// Cleans up the handle directory if empty.
+    pub fn unregister_sandbox(&self, handle: &str, pid: u32) {
+        let dir = self.sandboxes_dir().join(handle);
+        let _ = fs::remove_file(dir.join(format!("{}.json", pid)));
+        let _ = fs::remove_dir(&dir);
+    }
+
+    /// Record the agent status last reported to supervisor `pid`.
+    ///
+    /// Does nothing if `pid` has no record.
+    pub fn update_sandbox_status(&self, pid: u32, status: &StatusSnapshot) -> Result<()> {
+        let file_name = format!("{}.json", pid);
+        for entry in fs::read_dir(self.sandboxes_dir()).into_iter().flatten() {
+            let path = match entry {
+                Ok(entry) => entry.path().join(&file_name),
+                Err(_) => continue,
+            };
+            let Some(mut info) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<SandboxInfo>(&content).ok())
+            else {
+                continue;
+            };
+            info.agent_status = Some(status.clone());
+            let content = serde_json::to_string_pretty(&info)?;
+            return write_atomic(&path, content.as_bytes());
+        }
+        Ok(())
+    }
+
+    /// List sandbox supervisors for a worktree handle.
+    ///
+    /// Records whose supervisor process is no longer running (e.g. it was
+    /// killed before it could clean up) are removed and skipped.
+    pub fn list_sandboxes(&self, handle: &str) -> Vec<SandboxInfo> {
+        let dir = self.sandboxes_dir().join(handle);
+        let mut infos: Vec<SandboxInfo> = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == "json"))
+            .filter_map(|path| {
+                let info: SandboxInfo = fs::read_to_string(&path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok())?;
+                if process_alive(info.pid) {
+                    Some(info)
+                } else {
+                    let _ = fs::remove_file(&path);
+                    None
+                }
+            })
+            .collect();
+        infos.sort_by_key(|info| info.started_ts);
+        infos
+    }
+
+    /// Find sandbox records, across all worktrees, whose supervisor process
+    /// is no longer running. Returns `(handle, pid)` pairs.
+    pub fn stale_sandboxes(&self) -> Vec<(String, u32)> {
+        let mut stale = Vec::new();
+        for handle_entry in fs::read_dir(self.sandboxes_dir()).into_iter().flatten() {
+            let Ok(handle_entry) = handle_entry else {
+                continue;
+            };
+            let Ok(handle) = handle_entry.file_name().into_string() else {
+                continue;
+            };
+            for entry in fs::read_dir(handle_entry.path()).into_iter().flatten() {
+                let Some(pid) = entry.ok().and_then(|e| {
+                    e.path()
+                        .file_stem()
+                        .and_then(|s| s.to_str())
+                        .and_then(|s| s.parse::<u32>().ok())
+                }) else {
+                    continue;
+                };
+                if !process_alive(pid) {
+                    stale.push((handle.clone(), pid));
+                }
+            }
+        }
+        stale
+    }
+
     /// Load agents with reconciliation against live multiplexer state.
     ///
     /// Uses batched pane queries for performance, with backend-specific fallback validation.
@@ -285,6 +464,16 @@ fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
     Ok(())
 }
 
+/// Check whether a process with the given PID is still running.
+pub(crate) fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = i32::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 performs the existence/permission check without sending anything
+    let ret = unsafe { libc::kill(pid, 0) };
+    ret == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
 /// Get the XDG state directory.
 ///
 /// Checks XDG_STATE_HOME first, falls back to ~/.local/state.
@@ -369,6 +558,36 @@ mod tests {
         assert_eq!(retrieved.pane_pid, state.pane_pid);
     }
 
+    #[test]
+    fn test_relocate_agents() {
+        let (store, _dir) = test_store();
+        let mut moved = test_agent_state(test_pane_key());
+        moved.workdir = PathBuf::from("/repo__worktrees/old/src");
+        moved.window_name = Some("wm-old".to_string());
+        store.upsert_agent(&moved).unwrap();
+        let other = test_agent_state(PaneKey {
+            pane_id: "%2".to_string(),
+            ..test_pane_key()
+        });
+        store.upsert_agent(&other).unwrap();
+
+        let count = store
+            .relocate_agents(
+                Path::new("/repo__worktrees/old"),
+                Path::new("/repo__worktrees/new"),
+                &[("wm-old".to_string(), "wm-new".to_string())],
+            )
+            .unwrap();
+
+        assert_eq!(count, 1);
+        let moved = store.get_agent(&test_pane_key()).unwrap().unwrap();
+        assert_eq!(moved.workdir, PathBuf::from("/repo__worktrees/new/src"));
+        assert_eq!(moved.window_name.as_deref(), Some("wm-new"));
+        assert_eq!(moved.session_name.as_deref(), Some("main"));
+        let other = store.get_agent(&other.pane_key).unwrap().unwrap();
+        assert_eq!(other.workdir, PathBuf::from("/home/user/project"));
+    }
+
     #[test]
     fn test_get_nonexistent_agent() {
         let (store, _dir) = test_store();
@@ -513,4 +732,89 @@ mod tests {
         let agents = store.list_all_agents().unwrap();
         assert_eq!(agents.len(), 1);
     }
+
+    #[test]
+    fn test_sandbox_register_list_unregister() {
+        let (store, _dir) = test_store();
+        let info = SandboxInfo {
+            pid: std::process::id(),
+            backend: "lima".to_string(),
+            pane_id: "%3".to_string(),
+            rpc_port: 54321,
+            worktree: PathBuf::from("/home/user/project"),
+            started_ts: 1234567890,
+            agent_status: None,
+        };
+
+        store.register_sandbox("my-feature", &info).unwrap();
+        assert_eq!(store.list_sandboxes("my-feature"), vec![info.clone()]);
+        assert!(store.list_sandboxes("other").is_empty());
+
+        let status = StatusSnapshot {
+            status: "waiting".to_string(),
+            tokens: Some(1200),
+            cost: None,
+            updated_at: 1234567999,
+        };
+        store.update_sandbox_status(info.pid, &status).unwrap();
+        store.update_sandbox_status(info.pid + 1, &status).unwrap();
+        assert_eq!(
+            store.list_sandboxes("my-feature")[0].agent_status,
+            Some(status)
+        );
+
+        store.unregister_sandbox("my-feature", info.pid);
+        assert!(store.list_sandboxes("my-feature").is_empty());
+    }
+
+    #[test]
+    fn test_sandbox_list_drops_dead_supervisors() {
+        let (store, dir) = test_store();
+        let info = SandboxInfo {
+            pid: i32::MAX as u32,
+            backend: "container".to_string(),
+            pane_id: String::new(),
+            rpc_port: 1234,
+            worktree: PathBuf::from("/tmp"),
+            started_ts: 0,
+            agent_status: None,
+        };
+
+        store.register_sandbox("stale", &info).unwrap();
+        assert_eq!(
+            store.stale_sandboxes(),
+            vec![("stale".to_string(), info.pid)]
+        );
+        assert!(store.list_sandboxes("stale").is_empty());
+        assert!(
+            !dir.path()
+                .join(format!("sandboxes/stale/{}.json", info.pid))
+                .exists()
+        );
+    }
+
+    #[test]
+    fn test_worktree_dir_is_per_path_and_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let (store, dir) = test_store();
+        let a = dir.path().join("a/feature");
+        let b = dir.path().join("b/feature");
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+
+        let dir_a = store.worktree_dir(&a).unwrap();
+        assert_eq!(dir_a, store.worktree_dir(&a).unwrap());
+        assert_ne!(dir_a, store.worktree_dir(&b).unwrap());
+        assert!(dir_a.starts_with(dir.path().join("worktrees")));
+        assert!(
+            dir_a
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .starts_with("feature-")
+        );
+        let mode = fs::metadata(&dir_a).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
 }
@@ -9,6 +9,7 @@ use std::path::PathBuf;
 const FILENAME_ENCODE_SET: &AsciiSet = &CONTROLS.add(b'/').add(b'\\').add(b':').add(b'%');
 
 use crate::multiplexer::types::{AgentPane, AgentStatus};
+use crate::sandbox::rpc::StatusSnapshot;
 
 /// Composite pane identifier for unique state file naming.
 ///
@@ -144,6 +145,35 @@ pub struct GlobalSettings {
     pub last_pane_id: Option<String>,
 }
 
+/// Runtime details of a running sandbox supervisor.
+///
+/// Written by `workmux sandbox run` so `workmux sandbox info` can report
+/// which RPC port each supervisor grabbed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SandboxInfo {
+    /// Supervisor process ID
+    pub pid: u32,
+
+    /// Sandbox backend: "lima" or "container"
+    pub backend: String,
+
+    /// Multiplexer pane hosting the supervisor (empty if unknown)
+    pub pane_id: String,
+
+    /// Port the RPC server is listening on
+    pub rpc_port: u16,
+
+    /// Working directory of the sandboxed agent
+    pub worktree: PathBuf,
+
+    /// Unix timestamp when the supervisor started
+    pub started_ts: u64,
+
+    /// The status the agent last reported over RPC, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_status: Option<StatusSnapshot>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,5 +1,6 @@
 use std::path::{Path, PathBuf};
-use std::time::Duration;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
 
 /// Canonicalize a path, falling back to the original if canonicalization fails.
 pub fn canon_or_self(p: &Path) -> PathBuf {
@@ -41,6 +42,106 @@ pub fn format_elapsed_duration(d: Duration) -> String {
     }
 }
 
+/// Format the time elapsed since `time` as a relative string like
+/// `3 days ago`. Used by `list --stale` and `sandbox info`.
+pub fn format_time_ago(time: SystemTime) -> String {
+    let duration = match SystemTime::now().duration_since(time) {
+        Ok(d) => d,
+        Err(_) => return "in the future".to_string(),
+    };
+    let plural = |n: u64| if n == 1 { "" } else { "s" };
+
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        return "just now".to_string();
+    }
+
+    let minutes = seconds / 60;
+    if minutes < 60 {
+        return format!("{} minute{} ago", minutes, plural(minutes));
+    }
+
+    let hours = minutes / 60;
+    if hours < 24 {
+        return format!("{} hour{} ago", hours, plural(hours));
+    }
+
+    let days = hours / 24;
+    if days < 30 {
+        return format!("{} day{} ago", days, plural(days));
+    }
+
+    let months = days / 30;
+    if months < 12 {
+        return format!("{} month{} ago", months, plural(months));
+    }
+
+    let years = months / 12;
+    format!("{} year{} ago", years, plural(years))
+}
+
+/// Parse a human duration like `90`, `45s`, `30m`, `2h`, `1h30m` or `2w`.
+/// A bare number is seconds. Usable as a clap value parser.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let invalid = |why: &str| format!("invalid duration '{}': {}", s, why);
+
+    let secs = if let Ok(secs) = s.parse::<u64>() {
+        secs
+    } else {
+        let mut total: u64 = 0;
+        let mut digits = String::new();
+        for c in s.chars() {
+            if c.is_ascii_digit() {
+                digits.push(c);
+                continue;
+            }
+            let unit = match c {
+                's' => 1,
+                'm' => 60,
+                'h' => 3600,
+                'd' => 86400,
+                'w' => 604800,
+                _ => return Err(invalid("use s, m, h, d or w units")),
+            };
+            let n: u64 = digits
+                .parse()
+                .map_err(|_| invalid("expected a number before each unit"))?;
+            total = n
+                .checked_mul(unit)
+                .and_then(|v| total.checked_add(v))
+                .ok_or_else(|| invalid("too large"))?;
+            digits.clear();
+        }
+        if !digits.is_empty() {
+            return Err(invalid("missing unit after the last number"));
+        }
+        total
+    };
+
+    if secs == 0 {
+        return Err(invalid("must be greater than zero"));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// First line of `program arg` (e.g. `tmux -V` -> `tmux 3.4`), or `None`
+/// if the program isn't installed or fails. Empty output falls back to the
+/// program name, so a found tool is never reported as missing.
+pub fn command_version(program: &str, arg: &str) -> Option<String> {
+    let output = Command::new(program).arg(arg).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let line = stdout.lines().next().unwrap_or("").trim();
+    Some(if line.is_empty() {
+        program.to_string()
+    } else {
+        line.to_string()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,4 +194,32 @@ mod tests {
         assert_eq!(format_elapsed_duration(Duration::from_secs(3661)), "1h 01m");
         assert_eq!(format_elapsed_duration(Duration::from_secs(7260)), "2h 01m");
     }
+
+    #[test]
+    fn format_time_ago_picks_largest_unit() {
+        let ago = |secs| format_time_ago(SystemTime::now() - Duration::from_secs(secs));
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3 * 3600), "3 hours ago");
+        assert_eq!(ago(8 * 86400), "8 days ago");
+        assert_eq!(ago(400 * 86400), "1 year ago");
+    }
+
+    #[test]
+    fn parse_duration_accepts_units_and_bare_seconds() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("45s"), Ok(Duration::from_secs(45)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(1209600)));
+    }
+
+    #[test]
+    fn parse_duration_rejects_malformed_input() {
+        for input in ["", "0", "0m", "m", "10x", "1h30", "-5m", "1.5h"] {
+            assert!(parse_duration(input).is_err(), "{input:?} should fail");
+        }
+    }
 }
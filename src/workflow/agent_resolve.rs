@@ -1,5 +1,6 @@
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 use crate::git;
 use crate::multiplexer::{AgentPane, Multiplexer};
@@ -8,7 +9,8 @@ use crate::util::canon_or_self;
 
 /// Resolve a worktree name to its agent panes.
 ///
-/// 1. Finds the worktree path via git
+/// 1. Finds the worktree path via git, falling back to a substring match
+///    (see [`find_worktree_fuzzy`])
 /// 2. Loads reconciled agent state
 /// 3. Matches agents by comparing canonical workdir paths
 ///
@@ -17,7 +19,7 @@ pub fn resolve_worktree_agents(
     name: &str,
     mux: &dyn Multiplexer,
 ) -> Result<(PathBuf, Vec<AgentPane>)> {
-    let (worktree_path, _branch) = git::find_worktree(name)?;
+    let (worktree_path, _branch) = find_worktree_fuzzy(name)?;
     let canon_wt_path = canon_or_self(&worktree_path);
 
     let agent_panes = StateStore::new().and_then(|store| store.load_reconciled_agents(mux))?;
@@ -33,6 +35,50 @@ pub fn resolve_worktree_agents(
     Ok((worktree_path, matching))
 }
 
+/// Find a worktree by handle or branch name, or else by a case-insensitive
+/// substring of either, as long as only one worktree contains it.
+///
+/// Exact matches always win, so a handle that is also a substring of
+/// other worktrees' names still resolves to itself.
+pub fn find_worktree_fuzzy(name: &str) -> Result<(PathBuf, String)> {
+    match git::find_worktree(name) {
+        Err(e) if e.is::<git::WorktreeNotFound>() => {
+            fuzzy_match(name, git::checkouts(git::list_worktrees()?)).ok_or(e)?
+        }
+        found => found,
+    }
+}
+
+/// The single worktree whose handle or branch contains `name`, ignoring
+/// case. `None` if none does; an error naming the candidates if several do.
+fn fuzzy_match(name: &str, worktrees: Vec<(PathBuf, String)>) -> Option<Result<(PathBuf, String)>> {
+    let needle = name.to_lowercase();
+    let handle = |path: &Path| {
+        path.file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+    let mut matches: Vec<_> = worktrees
+        .into_iter()
+        .filter(|(path, branch)| {
+            handle(path).to_lowercase().contains(&needle) || branch.to_lowercase().contains(&needle)
+        })
+        .collect();
+
+    match matches.len() {
+        0 => None,
+        1 => Some(Ok(matches.remove(0))),
+        _ => {
+            let candidates: Vec<String> = matches.iter().map(|(path, _)| handle(path)).collect();
+            Some(Err(anyhow!(
+                "'{}' matches several worktrees: {}. Use a longer name.",
+                name,
+                candidates.join(", ")
+            )))
+        }
+    }
+}
+
 /// Resolve a worktree name to exactly one agent pane (the first/primary).
 ///
 /// Returns an error if no agent is running in the worktree.
@@ -45,6 +91,53 @@ pub fn resolve_worktree_agent(name: &str, mux: &dyn Multiplexer) -> Result<(Path
     Ok((path, agent))
 }
 
+/// Like [`resolve_worktree_agent`], but with a `timeout` (`--wait-for-agent`)
+/// for an agent that may still be starting: polls with backoff until its
+/// pane is registered or the timeout has passed. Other failures, such as an
+/// unknown worktree, return at once. Without a timeout, makes one attempt.
+pub fn wait_for_worktree_agent(
+    name: &str,
+    mux: &dyn Multiplexer,
+    timeout: Option<Duration>,
+) -> Result<(PathBuf, AgentPane)> {
+    let Some(timeout) = timeout else {
+        return resolve_worktree_agent(name, mux);
+    };
+    let found = retry_until(timeout, || {
+        let (path, agents) = resolve_worktree_agents(name, mux)?;
+        Ok(agents.into_iter().next().map(|agent| (path, agent)))
+    })?;
+    found.ok_or_else(|| {
+        anyhow!(
+            "No agent pane found in worktree '{}' after {}",
+            name,
+            crate::util::format_elapsed_duration(timeout)
+        )
+    })
+}
+
+/// Call `attempt` until it returns something or `timeout` has passed,
+/// sleeping 100ms after the first miss and doubling up to 1s. Always makes
+/// at least one attempt.
+fn retry_until<T>(
+    timeout: Duration,
+    mut attempt: impl FnMut() -> Result<Option<T>>,
+) -> Result<Option<T>> {
+    let deadline = Instant::now() + timeout;
+    let mut delay = Duration::from_millis(100);
+    loop {
+        if let Some(found) = attempt()? {
+            return Ok(Some(found));
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Ok(None);
+        }
+        std::thread::sleep(delay.min(left));
+        delay = (delay * 2).min(Duration::from_secs(1));
+    }
+}
+
 /// Match agents to a worktree path from a pre-loaded agent list.
 ///
 /// Used by `status` and `wait` commands that load agents once and match
@@ -62,3 +155,117 @@ pub fn match_agents_to_worktree<'a>(
         })
         .collect()
 }
+
+/// Resolve the worktree containing the current directory to its handle.
+///
+/// Matches the cwd against the paths git reports for each worktree, so it
+/// works from any subdirectory and regardless of where worktrees live.
+pub fn resolve_current_worktree() -> Result<String> {
+    let cwd = std::env::current_dir().context("Failed to get current directory")?;
+    let worktrees = git::checkouts(git::list_worktrees()?);
+    let path = worktree_containing(&cwd, worktrees.iter().map(|(path, _)| path.as_path()))
+        .ok_or_else(|| {
+            anyhow!(
+                "Current directory is not inside a worktree of this repository; pass a worktree name"
+            )
+        })?;
+
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(String::from)
+        .ok_or_else(|| anyhow!("Invalid worktree path: {}", path.display()))
+}
+
+/// The innermost worktree containing `dir`. The longest match wins so that
+/// worktrees nested inside the main checkout resolve to themselves.
+fn worktree_containing<'a>(
+    dir: &Path,
+    worktrees: impl IntoIterator<Item = &'a Path>,
+) -> Option<&'a Path> {
+    let canon_dir = canon_or_self(dir);
+    worktrees
+        .into_iter()
+        .map(|path| (path, canon_or_self(path)))
+        .filter(|(_, canon)| canon_dir.starts_with(canon))
+        .max_by_key(|(_, canon)| canon.components().count())
+        .map(|(path, _)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_until_polls_until_found_or_timed_out() {
+        let mut calls = 0;
+        let found = retry_until(Duration::from_secs(5), || {
+            calls += 1;
+            Ok((calls == 3).then_some("agent"))
+        })
+        .unwrap();
+        assert_eq!((found, calls), (Some("agent"), 3));
+
+        let mut calls = 0;
+        let found: Option<()> = retry_until(Duration::ZERO, || {
+            calls += 1;
+            Ok(None)
+        })
+        .unwrap();
+        assert_eq!((found, calls), (None, 1));
+
+        let err = retry_until::<()>(Duration::from_secs(5), || anyhow::bail!("no such worktree"))
+            .unwrap_err();
+        assert_eq!(err.to_string(), "no such worktree");
+    }
+
+    fn worktrees() -> Vec<(PathBuf, String)> {
+        [
+            ("/repo", "main"),
+            (
+                "/repo__worktrees/auth-refresh",
+                "feature/AUTH-1234-token-refresh",
+            ),
+            ("/repo__worktrees/auth-logout", "feature/auth-logout"),
+        ]
+        .into_iter()
+        .map(|(path, branch)| (PathBuf::from(path), branch.to_string()))
+        .collect()
+    }
+
+    #[test]
+    fn fuzzy_match_requires_a_single_candidate() {
+        let (path, branch) = fuzzy_match("1234", worktrees()).unwrap().unwrap();
+        assert_eq!(path, Path::new("/repo__worktrees/auth-refresh"));
+        assert_eq!(branch, "feature/AUTH-1234-token-refresh");
+
+        // Case-insensitive, on the handle as well as the branch
+        let (path, _) = fuzzy_match("LOGOUT", worktrees()).unwrap().unwrap();
+        assert_eq!(path, Path::new("/repo__worktrees/auth-logout"));
+
+        let err = fuzzy_match("auth", worktrees()).unwrap().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "'auth' matches several worktrees: auth-refresh, auth-logout. Use a longer name."
+        );
+
+        assert!(fuzzy_match("billing", worktrees()).is_none());
+    }
+
+    #[test]
+    fn worktree_containing_prefers_innermost_match() {
+        let main = Path::new("/repo");
+        let nested = Path::new("/repo/.worktrees/feature");
+        let sibling = Path::new("/repo__worktrees/other");
+        let all = [main, nested, sibling];
+
+        assert_eq!(worktree_containing(Path::new("/repo/src"), all), Some(main));
+        assert_eq!(
+            worktree_containing(Path::new("/repo/.worktrees/feature/src"), all),
+            Some(nested)
+        );
+        assert_eq!(worktree_containing(sibling, all), Some(sibling));
+        assert_eq!(worktree_containing(Path::new("/elsewhere"), all), None);
+        // Prefix matching is per component, not per character
+        assert_eq!(worktree_containing(Path::new("/repository"), all), None);
+    }
+}
@@ -43,7 +43,11 @@ fn remove_dir_contents(path: &Path) {
 
 /// Find all windows matching the base handle pattern (including duplicates).
 /// Matches: {prefix}{handle} and {prefix}{handle}-{N}
-fn find_matching_windows(mux: &dyn Multiplexer, prefix: &str, handle: &str) -> Result<Vec<String>> {
+pub(super) fn find_matching_windows(
+    mux: &dyn Multiplexer,
+    prefix: &str,
+    handle: &str,
+) -> Result<Vec<String>> {
     let all_windows = mux.get_all_window_names()?;
     let base_name = prefixed(prefix, handle);
     let escaped_base = regex::escape(&base_name);
@@ -1,33 +1,32 @@
 use anyhow::{Context, Result, anyhow};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::config::MuxMode;
+use crate::config::{Config, MuxMode};
 use crate::multiplexer::MuxHandle;
 use crate::{git, spinner};
 use tracing::{debug, info, warn};
 
-/// Check if a path is registered as a git worktree.
+/// The branch checked out in the git worktree at `path`, if `path` is one.
 /// Uses canonicalize() to handle symlinks, case sensitivity, and relative paths.
-fn is_registered_worktree(path: &Path) -> Result<bool> {
+fn registered_worktree_branch(path: &Path) -> Result<Option<String>> {
     // Canonicalize the input path for reliable comparison
     let abs_path = match std::fs::canonicalize(path) {
         Ok(p) => p,
-        Err(_) => return Ok(false), // Can't canonicalize = not a valid worktree
+        Err(_) => return Ok(None), // Can't canonicalize = not a valid worktree
     };
 
-    let worktrees = git::list_worktrees()?;
-    for (wt_path, _) in worktrees {
+    for (wt_path, branch) in git::checkouts(git::list_worktrees()?) {
         // Canonicalize git's reported path as well
         if let Ok(abs_wt) = std::fs::canonicalize(&wt_path) {
             if abs_wt == abs_path {
-                return Ok(true);
+                return Ok(Some(branch));
             }
         } else if wt_path == path {
             // Fallback to string comparison if canonicalization fails
-            return Ok(true);
+            return Ok(Some(branch));
         }
     }
-    Ok(false)
+    Ok(None)
 }
 
 use super::cleanup;
@@ -36,6 +35,31 @@ use super::setup;
 use super::types::{CreateArgs, CreateResult, SetupOptions};
 
 /// Create a new worktree with tmux window and panes
+/// Directory new worktrees of the repository at `main_worktree_root` are
+/// created in: `worktree_dir` from config, or `<project>__worktrees` next to
+/// the main worktree.
+pub fn worktrees_dir(config: &Config, main_worktree_root: &Path) -> Result<PathBuf> {
+    if let Some(ref worktree_dir) = config.worktree_dir {
+        let path = Path::new(worktree_dir);
+        return Ok(if path.is_absolute() {
+            // Use absolute path as-is
+            path.to_path_buf()
+        } else {
+            // Relative path: resolve from main worktree root
+            main_worktree_root.join(path)
+        });
+    }
+    // Default behavior: <main_worktree_root>/../<project_name>__worktrees
+    let project_name = main_worktree_root
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("Could not determine project name"))?;
+    Ok(main_worktree_root
+        .parent()
+        .ok_or_else(|| anyhow!("Could not determine parent directory"))?
+        .join(format!("{}__worktrees", project_name)))
+}
+
 pub fn create(context: &WorkflowContext, args: CreateArgs) -> Result<CreateResult> {
     let CreateArgs {
         branch_name,
@@ -131,7 +155,9 @@ pub fn create(context: &WorkflowContext, args: CreateArgs) -> Result<CreateResul
     // Check if branch already has a worktree
     if worktree_exists {
         return Err(anyhow!(
-            "A worktree for branch '{}' already exists. Use 'workmux open {}' to open it.",
+            "A worktree for branch '{}' already exists. Use 'workmux open {}' to open it, \
+             or 'workmux path {}' to find it.",
+            branch_name,
             branch_name,
             branch_name
         ));
@@ -198,31 +224,9 @@ pub fn create(context: &WorkflowContext, args: CreateArgs) -> Result<CreateResul
         None
     };
 
-    // Determine worktree path: use config.worktree_dir or default to <project>__worktrees pattern
     // Always use main_worktree_root (not repo_root) to ensure consistent paths even when
     // running from inside an existing worktree.
-    let base_dir = if let Some(ref worktree_dir) = context.config.worktree_dir {
-        let path = Path::new(worktree_dir);
-        if path.is_absolute() {
-            // Use absolute path as-is
-            path.to_path_buf()
-        } else {
-            // Relative path: resolve from main worktree root
-            context.main_worktree_root.join(path)
-        }
-    } else {
-        // Default behavior: <main_worktree_root>/../<project_name>__worktrees
-        let project_name = context
-            .main_worktree_root
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| anyhow!("Could not determine project name"))?;
-        context
-            .main_worktree_root
-            .parent()
-            .ok_or_else(|| anyhow!("Could not determine parent directory"))?
-            .join(format!("{}__worktrees", project_name))
-    };
+    let base_dir = worktrees_dir(&context.config, &context.main_worktree_root)?;
     // Use handle for the worktree directory name (not branch_name)
     let worktree_path = base_dir.join(handle);
 
@@ -231,12 +235,15 @@ pub fn create(context: &WorkflowContext, args: CreateArgs) -> Result<CreateResul
         // Check if this is an orphan directory (exists on disk but not registered with git).
         // This can happen when cleanup renames a worktree but a background process (build tool,
         // file watcher, shell prompt) recreates the directory structure using stale $PWD.
-        if is_registered_worktree(&worktree_path)? {
+        if let Some(other) = registered_worktree_branch(&worktree_path)? {
             return Err(anyhow!(
-                "Worktree directory '{}' already exists and is registered with git.\n\
-                 This may be from another branch with the same handle.\n\
+                "Worktree directory '{}' already exists and is registered with git for \
+                 branch '{}': '{}' and '{}' map to the same directory name.\n\
                  Hint: Use --name to specify a different name.",
-                worktree_path.display()
+                worktree_path.display(),
+                other,
+                branch_name,
+                other
             ));
         }
 
@@ -1,7 +1,10 @@
 use anyhow::{Result, anyhow};
-use std::collections::HashSet;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::cmd::Cmd;
 use crate::config::MuxMode;
 use crate::multiplexer::{Multiplexer, util};
 use crate::state::StateStore;
@@ -50,18 +53,32 @@ fn filter_worktrees(
         .collect()
 }
 
+/// Branch worktrees are compared against for merge status: the explicit
+/// `base`, then `base_branch`, then `main_branch`, then the detected default.
+fn resolve_base_branch(base: Option<&str>, config: &config::Config) -> Option<String> {
+    base.map(String::from)
+        .or_else(|| config.base_branch.clone())
+        .or_else(|| config.main_branch.clone())
+        .or_else(|| git::get_default_branch().ok())
+}
+
 /// List all worktrees with their status
+///
+/// Multiplexer, git and PR state is fetched in batch up front, so the cost
+/// stays constant in the number of worktrees: the per-worktree loop only does
+/// set lookups and path canonicalization. Keep process spawns out of that loop.
 pub fn list(
     config: &config::Config,
     mux: &dyn Multiplexer,
     fetch_pr_status: bool,
     filter: &[String],
+    base: Option<&str>,
 ) -> Result<Vec<WorktreeInfo>> {
     if !git::is_git_repo()? {
         return Err(anyhow!("Not in a git repository"));
     }
 
-    let worktrees_data = git::list_worktrees()?;
+    let worktrees_data = git::checkouts(git::list_worktrees()?);
 
     if worktrees_data.is_empty() {
         return Ok(Vec::new());
@@ -87,16 +104,18 @@ pub fn list(
         HashSet::new()
     };
 
-    // Get the main branch for unmerged checks
-    let main_branch = git::get_default_branch().ok();
+    // Get the base branch for unmerged checks
+    let base_branch = resolve_base_branch(base, config);
 
-    // Get all unmerged branches in one go for efficiency
-    // Prefer checking against remote tracking branch for more accurate results
-    let unmerged_branches = main_branch
+    // Get all unmerged branches in one go for efficiency. This is a single
+    // `for-each-ref --no-merged`; a cache would have to read every branch
+    // head to stay valid, which costs as much as the lookup itself.
+    // Prefer checking against remote tracking branch for more accurate results.
+    // `None` on failure: every branch's merge status is then unknown.
+    let unmerged_branches = base_branch
         .as_deref()
-        .and_then(|main| git::get_merge_base(main).ok())
-        .and_then(|base| git::get_unmerged_branches(&base).ok())
-        .unwrap_or_default(); // Use an empty set on failure
+        .and_then(|base| git::get_merge_base(base).ok())
+        .and_then(|base| git::get_unmerged_branches(&base).ok());
 
     // Batch fetch all PRs if requested (single API call)
     let pr_map = if fetch_pr_status {
@@ -107,7 +126,10 @@ pub fn list(
         std::collections::HashMap::new()
     };
 
-    // Load reconciled agent states (only if multiplexer is running)
+    // Load reconciled agent states (only if multiplexer is running). This is
+    // one bulk `get_all_live_pane_info` query however many worktrees or
+    // sessions there are; keep per-worktree multiplexer calls out of the
+    // loop below.
     let agent_panes = if mux_running {
         StateStore::new()
             .ok()
@@ -149,15 +171,13 @@ pub fn list(
                 mux_windows.contains(&prefixed_name)
             };
 
-            // Check for unmerged commits, but only if this isn't the main branch
-            let has_unmerged = if let Some(ref main) = main_branch {
-                if branch == *main || branch == "(detached)" {
-                    false
-                } else {
-                    unmerged_branches.contains(&branch)
-                }
-            } else {
-                false
+            // Check for unmerged commits, but only if this isn't the base branch
+            let has_unmerged = match &base_branch {
+                Some(base) if branch == *base || branch == "(detached)" => Some(false),
+                Some(_) => unmerged_branches
+                    .as_ref()
+                    .map(|unmerged| unmerged.contains(&branch)),
+                None => None,
             };
 
             // Lookup PR info from batch fetch
@@ -196,3 +216,179 @@ pub fn list(
 
     Ok(worktrees)
 }
+
+/// When each worktree was last worked on, keyed by path.
+///
+/// Uses the committer time of the branch tip, fetched for all branches in one
+/// call. Worktrees whose branch has no commits yet (or a detached HEAD) fall
+/// back to the worktree directory's mtime; worktrees with neither are absent.
+pub fn last_activity(worktrees: &[WorktreeInfo]) -> HashMap<PathBuf, SystemTime> {
+    let commit_times = git::get_branch_commit_times().unwrap_or_default();
+
+    worktrees
+        .iter()
+        .filter_map(|wt| {
+            let time = commit_times
+                .get(&wt.branch)
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(*secs))
+                .or_else(|| wt.path.metadata().and_then(|m| m.modified()).ok())?;
+            Some((wt.path.clone(), time))
+        })
+        .collect()
+}
+
+/// Upstream tracking state of each worktree's branch, keyed by path.
+///
+/// Fetched for all branches in one call. Worktrees whose branch has no
+/// upstream (or a detached HEAD) are absent.
+pub fn upstream_status(worktrees: &[WorktreeInfo]) -> HashMap<PathBuf, git::UpstreamTracking> {
+    let upstreams = git::get_branch_upstreams().unwrap_or_default();
+
+    worktrees
+        .iter()
+        .filter_map(|wt| Some((wt.path.clone(), *upstreams.get(&wt.branch)?)))
+        .collect()
+}
+
+/// Disk usage of each worktree in bytes, keyed by path, measured in
+/// parallel.
+///
+/// Files git ignores (build artifacts, dependencies) are left out unless
+/// `include_ignored`. Worktrees whose directory no longer exists are absent.
+pub fn disk_usage(worktrees: &[WorktreeInfo], include_ignored: bool) -> HashMap<PathBuf, u64> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = worktrees
+            .iter()
+            .map(|wt| {
+                scope.spawn(move || (wt.path.clone(), worktree_size(&wt.path, include_ignored)))
+            })
+            .collect();
+        handles
+            .into_iter()
+            .filter_map(|handle| {
+                let (path, size) = handle.join().ok()?;
+                Some((path, size?))
+            })
+            .collect()
+    })
+}
+
+/// Bytes allocated to the files under `dir`, like `du`. Symlinks are counted
+/// as links, never followed, and a file with several hard links once.
+fn worktree_size(dir: &Path, include_ignored: bool) -> Option<u64> {
+    if !dir.is_dir() {
+        return None;
+    }
+    let mut seen = HashSet::new();
+    let mut total = 0;
+    let mut add = |meta: &std::fs::Metadata| {
+        if meta.nlink() <= 1 || seen.insert((meta.dev(), meta.ino())) {
+            total += meta.blocks() * 512;
+        }
+    };
+
+    if include_ignored {
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = std::fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                // DirEntry::metadata doesn't traverse symlinks
+                let Ok(meta) = entry.metadata() else {
+                    continue;
+                };
+                if meta.is_dir() {
+                    pending.push(entry.path());
+                } else {
+                    add(&meta);
+                }
+            }
+        }
+    } else {
+        // Tracked files plus untracked ones that aren't ignored
+        let output = Cmd::new("git")
+            .workdir(dir)
+            .args(&[
+                "ls-files",
+                "-z",
+                "--cached",
+                "--others",
+                "--exclude-standard",
+            ])
+            .run_and_capture_stdout()
+            .ok()?;
+        let mut listed = HashSet::new();
+        for file in output.split('\0').filter(|f| !f.is_empty()) {
+            // Files with unmerged changes are listed once per stage
+            if !listed.insert(file) {
+                continue;
+            }
+            // Deleted tracked files are still listed; submodules are directories
+            if let Ok(meta) = std::fs::symlink_metadata(dir.join(file))
+                && !meta.is_dir()
+            {
+                add(&meta);
+            }
+        }
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_base_branch_prefers_flag_then_config() {
+        let config = config::Config {
+            main_branch: Some("main".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(resolve_base_branch(None, &config).as_deref(), Some("main"));
+
+        let config = config::Config {
+            base_branch: Some("develop".to_string()),
+            ..config
+        };
+        assert_eq!(
+            resolve_base_branch(None, &config).as_deref(),
+            Some("develop")
+        );
+        assert_eq!(
+            resolve_base_branch(Some("release"), &config).as_deref(),
+            Some("release")
+        );
+    }
+
+    #[test]
+    fn worktree_size_skips_ignored_files_and_counts_links_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(dir)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.join(".gitignore"), "target/\n").unwrap();
+        std::fs::write(dir.join("main.rs"), vec![b'x'; 64 * 1024]).unwrap();
+        std::fs::create_dir(dir.join("target")).unwrap();
+        std::fs::write(dir.join("target/big.bin"), vec![b'x'; 256 * 1024]).unwrap();
+
+        let unignored = worktree_size(dir, false).unwrap();
+        let all = worktree_size(dir, true).unwrap();
+        assert!((64 * 1024..256 * 1024).contains(&unignored));
+        assert!(all >= unignored + 256 * 1024);
+
+        // A hard link and a symlink to the big file add (next to) nothing
+        std::fs::hard_link(dir.join("target/big.bin"), dir.join("target/copy.bin")).unwrap();
+        std::os::unix::fs::symlink(dir.join("target/big.bin"), dir.join("big-link")).unwrap();
+        assert!(worktree_size(dir, true).unwrap() < all + 64 * 1024);
+
+        assert_eq!(worktree_size(&dir.join("missing"), true), None);
+    }
+}
@@ -9,18 +9,23 @@ mod open;
 pub mod pr;
 pub mod prompt_loader;
 mod remove;
+mod rename;
 mod setup;
 pub mod types;
 
 // Public API re-exports
-pub use agent_resolve::{match_agents_to_worktree, resolve_worktree_agent};
-pub use create::{create, create_with_changes};
-pub use list::list;
+pub use agent_resolve::{
+    find_worktree_fuzzy, match_agents_to_worktree, resolve_current_worktree,
+    resolve_worktree_agent, resolve_worktree_agents, wait_for_worktree_agent,
+};
+pub use create::{create, create_with_changes, worktrees_dir};
+pub use list::{disk_usage, last_activity, list, upstream_status};
 pub use merge::merge;
 pub use open::open;
 pub use remove::remove;
-pub use setup::write_prompt_file;
+pub use rename::rename;
+pub use setup::{prompt_file_path, workmux_dir, write_prompt_file};
 
 // Re-export commonly used types for convenience
 pub use context::WorkflowContext;
-pub use types::{CreateArgs, SetupOptions};
+pub use types::{CreateArgs, SetupOptions, WorktreeInfo};
@@ -0,0 +1,181 @@
+use anyhow::{Context, Result, anyhow, bail};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::config::MuxMode;
+use crate::git;
+use crate::multiplexer::util::prefixed;
+use crate::naming::derive_handle;
+use crate::state::StateStore;
+
+use super::cleanup::{find_matching_windows, get_worktree_mode};
+use super::context::WorkflowContext;
+
+/// Rename the branch of the worktree `name` (handle or branch name) to
+/// `new_branch`, printing each step as it's done.
+///
+/// When the worktree's directory name was derived from its branch, the
+/// worktree moves to the name derived from `new_branch`, and its windows (or
+/// session), git metadata and saved agent state follow. A directory named
+/// explicitly with `add --name` is kept.
+pub fn rename(name: &str, new_branch: &str, context: &WorkflowContext) -> Result<()> {
+    let (worktree_path, branch) = git::find_worktree(name)
+        .with_context(|| format!("No worktree found with name '{}'", name))?;
+    let handle = worktree_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| {
+            anyhow!(
+                "Could not derive handle from worktree path: {}",
+                worktree_path.display()
+            )
+        })?
+        .to_string();
+
+    if branch == "(detached)" {
+        bail!(
+            "Worktree '{}' is on a detached HEAD; there's no branch to rename",
+            handle
+        );
+    }
+    if worktree_path == context.main_worktree_root || branch == context.main_branch {
+        bail!(
+            "Cannot rename the main worktree or the main branch ('{}')",
+            branch
+        );
+    }
+    if branch == new_branch {
+        bail!("Branch is already named '{}'", new_branch);
+    }
+    if git::branch_exists(&format!("refs/heads/{}", new_branch))? {
+        bail!("Branch '{}' already exists", new_branch);
+    }
+
+    let derived = derive_handle(&branch, None, &context.config).ok();
+    let new_handle = if derived.as_deref() == Some(handle.as_str()) {
+        derive_handle(new_branch, None, &context.config)?
+    } else {
+        handle.clone()
+    };
+    let new_path = (new_handle != handle).then(|| worktree_path.with_file_name(&new_handle));
+    if let Some(path) = &new_path {
+        if path.exists() {
+            bail!(
+                "Cannot move the worktree: {} already exists",
+                path.display()
+            );
+        }
+        if let Ok(store) = StateStore::new()
+            && !store.list_sandboxes(&handle).is_empty()
+        {
+            bail!(
+                "A sandbox supervisor is running in '{}'; stop it before moving the worktree",
+                handle
+            );
+        }
+    }
+    let mode = get_worktree_mode(&handle);
+
+    info!(
+        branch = %branch,
+        new_branch,
+        handle = %handle,
+        new_handle = %new_handle,
+        "rename:start"
+    );
+    // The worktree may be the current directory, which is about to move
+    context.chdir_to_main_worktree()?;
+
+    git::rename_branch_in(&branch, new_branch, &context.git_common_dir)?;
+    println!("✓ Renamed branch '{}' to '{}'", branch, new_branch);
+
+    let Some(new_path) = new_path else {
+        println!(
+            "  Kept worktree directory '{}' (its name isn't derived from the branch)",
+            handle
+        );
+        return Ok(());
+    };
+
+    git::move_worktree_in(&worktree_path, &new_path, &context.git_common_dir)?;
+    git::rename_worktree_meta(&handle, &new_handle)?;
+    println!(
+        "✓ Moved worktree {} to {}",
+        worktree_path.display(),
+        new_path.display()
+    );
+
+    // Git is done at this point, so multiplexer and state failures are
+    // reported without undoing the rename
+    let renamed = rename_mux_targets(context, &handle, &new_handle, mode);
+    update_agent_state(&worktree_path, &new_path, &renamed);
+
+    Ok(())
+}
+
+/// Rename the worktree's session, or its window and any `-N` duplicates.
+/// Returns the (old, new) names that were renamed.
+fn rename_mux_targets(
+    context: &WorkflowContext,
+    handle: &str,
+    new_handle: &str,
+    mode: MuxMode,
+) -> Vec<(String, String)> {
+    let mux = context.mux.as_ref();
+    if !mux.is_running().unwrap_or(false) {
+        return Vec::new();
+    }
+    let old_base = prefixed(&context.prefix, handle);
+    let new_base = prefixed(&context.prefix, new_handle);
+
+    let (kind, targets) = match mode {
+        MuxMode::Session => {
+            let exists = mux.session_exists(&old_base).unwrap_or(false);
+            (
+                "session",
+                if exists {
+                    vec![old_base.clone()]
+                } else {
+                    Vec::new()
+                },
+            )
+        }
+        MuxMode::Window => (
+            "window",
+            find_matching_windows(mux, &context.prefix, handle).unwrap_or_default(),
+        ),
+    };
+
+    let mut renamed = Vec::new();
+    for old in targets {
+        let new = format!("{}{}", new_base, &old[old_base.len()..]);
+        let result = match mode {
+            MuxMode::Session => mux.rename_session(&old, &new),
+            MuxMode::Window => mux.rename_window(&old, &new),
+        };
+        match result {
+            Ok(()) => {
+                println!("✓ Renamed {} '{}' to '{}'", kind, old, new);
+                renamed.push((old, new));
+            }
+            Err(e) => {
+                warn!(old = %old, new = %new, error = %e, "rename:mux rename failed");
+                eprintln!("workmux: could not rename {} '{}': {}", kind, old, e);
+            }
+        }
+    }
+    renamed
+}
+
+/// Point saved agent state at the moved worktree and renamed windows.
+fn update_agent_state(from: &Path, to: &Path, renamed: &[(String, String)]) {
+    let result = StateStore::new().and_then(|store| store.relocate_agents(from, to, renamed));
+    match result {
+        Ok(0) => {}
+        Ok(n) => println!("✓ Updated {} agent state file(s)", n),
+        Err(e) => {
+            warn!(error = %e, "rename:agent state update failed");
+            eprintln!("workmux: could not update agent state: {}", e);
+        }
+    }
+}
@@ -388,6 +388,7 @@ pub fn resolve_pane_configuration(
         size: None,
         percentage: None,
         target: None,
+        role: None,
     }]
 }
 
@@ -580,34 +581,13 @@ pub fn write_prompt_file(
             .with_context(|| format!("Failed to read prompt file '{}'", path.display()))?,
     };
 
-    // Sanitize branch name: replace path separators with dashes to avoid
-    // interpreting slashes as directory separators (e.g., "feature/foo" -> "feature-foo")
-    let safe_branch_name = branch_name.replace(['/', '\\', ':'], "-");
-
     let prompt_path = if let Some(dir) = working_dir {
         // Write to .workmux/ inside the worktree so it's accessible in container sandbox
-        let workmux_dir = dir.join(".workmux");
-        fs::create_dir_all(&workmux_dir).with_context(|| {
-            format!("Failed to create .workmux directory in '{}'", dir.display())
-        })?;
-
-        // Add .workmux/ to git exclude to avoid polluting git status
-        // In worktrees, .git is a file pointing to the real git dir, so we need to resolve it
-        if let Some(exclude_path) = resolve_git_exclude_path(dir)
-            && exclude_path.exists()
-            && let Ok(content) = fs::read_to_string(&exclude_path)
-            && !content.lines().any(|line| line.trim() == ".workmux/")
-            && let Ok(mut file) = fs::OpenOptions::new().append(true).open(&exclude_path)
-        {
-            use std::io::Write;
-            let _ = writeln!(file, "\n# workmux prompt files\n.workmux/");
-        }
-
-        let prompt_filename = format!("PROMPT-{}.md", safe_branch_name);
-        workmux_dir.join(prompt_filename)
+        workmux_dir(dir)?;
+        prompt_file_path(dir, branch_name)
     } else {
         // Legacy: write to temp directory for open command
-        let prompt_filename = format!("workmux-prompt-{}.md", safe_branch_name);
+        let prompt_filename = format!("workmux-prompt-{}.md", sanitize_prompt_branch(branch_name));
         std::env::temp_dir().join(prompt_filename)
     };
 
@@ -616,6 +596,40 @@ pub fn write_prompt_file(
     Ok(prompt_path)
 }
 
+/// Create `<dir>/.workmux/`, where workmux keeps per-worktree files, and add
+/// it to `.git/info/exclude` to avoid polluting git status.
+pub fn workmux_dir(dir: &Path) -> Result<PathBuf> {
+    let workmux_dir = dir.join(".workmux");
+    fs::create_dir_all(&workmux_dir)
+        .with_context(|| format!("Failed to create .workmux directory in '{}'", dir.display()))?;
+
+    // In worktrees, .git is a file pointing to the real git dir, so we need to resolve it
+    if let Some(exclude_path) = resolve_git_exclude_path(dir)
+        && exclude_path.exists()
+        && let Ok(content) = fs::read_to_string(&exclude_path)
+        && !content.lines().any(|line| line.trim() == ".workmux/")
+        && let Ok(mut file) = fs::OpenOptions::new().append(true).open(&exclude_path)
+    {
+        use std::io::Write;
+        let _ = writeln!(file, "\n# workmux files\n.workmux/");
+    }
+
+    Ok(workmux_dir)
+}
+
+/// Sanitize branch name: replace path separators with dashes to avoid
+/// interpreting slashes as directory separators (e.g., "feature/foo" -> "feature-foo")
+fn sanitize_prompt_branch(branch_name: &str) -> String {
+    branch_name.replace(['/', '\\', ':'], "-")
+}
+
+/// Where [`write_prompt_file`] puts the prompt for `branch_name` inside the
+/// worktree at `dir`.
+pub fn prompt_file_path(dir: &Path, branch_name: &str) -> PathBuf {
+    dir.join(".workmux")
+        .join(format!("PROMPT-{}.md", sanitize_prompt_branch(branch_name)))
+}
+
 /// Resolve the path to .git/info/exclude, handling worktrees correctly.
 /// In a worktree, .git is a file containing "gitdir: /path/to/.git/worktrees/name",
 /// so we need to find the actual git directory.
@@ -672,6 +686,103 @@ fn copy_dir_recursive(src: &Path, dst: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Symlink CLAUDE.local.md from main worktree if it exists and is gitignored.
+fn symlink_claude_local_md(repo_root: &Path, worktree_path: &Path) -> Result<()> {
+    let source = repo_root.join("CLAUDE.local.md");
+    if !source.exists() {
+        return Ok(());
+    }
+
+    if !git::is_path_ignored(repo_root, "CLAUDE.local.md") {
+        return Ok(());
+    }
+
+    let dest = worktree_path.join("CLAUDE.local.md");
+    if dest.symlink_metadata().is_ok() {
+        // Already exists (file, symlink, or dir) -- skip
+        return Ok(());
+    }
+
+    let relative_source = pathdiff::diff_paths(&source, worktree_path)
+        .ok_or_else(|| anyhow!("Could not create relative path for CLAUDE.local.md symlink"))?;
+
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&relative_source, &dest)
+        .context("Failed to symlink CLAUDE.local.md")?;
+
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(&relative_source, &dest)
+        .context("Failed to symlink CLAUDE.local.md")?;
+
+    info!("Symlinked CLAUDE.local.md to worktree");
+    Ok(())
+}
+
+/// Validates that a prompt will actually be consumed by an agent pane.
+///
+/// This prevents the case where a user provides `-p "some prompt"` but no pane
+/// is configured to run an agent that would receive it.
+fn validate_prompt_consumption(
+    panes: &[config::PaneConfig],
+    cli_agent: Option<&str>,
+    config: &config::Config,
+    options: &super::types::SetupOptions,
+) -> Result<()> {
+    if !options.run_pane_commands {
+        return Err(anyhow!(
+            "Prompt provided (-p/-P/-e) but pane commands are disabled (--no-pane-cmds). \
+             The prompt would be ignored."
+        ));
+    }
+
+    // Known agent commands always consume prompts (they have their own agent
+    // profile), so the prompt is consumed regardless of whether a global agent
+    // is configured.
+    let has_self_identifying_agent = panes.iter().any(|pane| {
+        pane.command
+            .as_deref()
+            .is_some_and(crate::multiplexer::agent::is_known_agent)
+    });
+
+    if has_self_identifying_agent {
+        return Ok(());
+    }
+
+    // For non-named panes, require a global agent
+    let effective_agent = cli_agent.or(config.agent.as_deref());
+
+    let Some(agent_cmd) = effective_agent else {
+        return Err(anyhow!(
+            "Prompt provided but no agent is configured to consume it. \
+             Set 'agent' in config or use -a/--agent flag."
+        ));
+    };
+
+    let consumes_prompt = panes.iter().any(|pane| {
+        pane.command
+            .as_deref()
+            .map(|cmd| config::is_agent_command(cmd, agent_cmd))
+            .unwrap_or(false)
+    });
+
+    if !consumes_prompt {
+        let commands: Vec<_> = panes
+            .iter()
+            .map(|p| p.command.as_deref().unwrap_or("<shell>"))
+            .collect();
+
+        return Err(anyhow!(
+            "Prompt provided, but no pane is configured to run the agent '{}'.\n\
+             Resolved pane commands: {:?}\n\
+             Ensure your panes config includes '<agent>' or runs the configured agent.",
+            agent_cmd,
+            commands
+        ));
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -685,6 +796,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
 
         let result = resolve_pane_configuration(&original_panes, None);
@@ -701,6 +813,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
 
         let result = resolve_pane_configuration(&original_panes, Some("claude"));
@@ -718,6 +831,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
             config::PaneConfig {
                 command: Some("npm run dev".to_string()),
@@ -726,6 +840,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
         ];
 
@@ -743,6 +858,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
 
         let result = resolve_pane_configuration(&original_panes, Some("claude"));
@@ -789,6 +905,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
         let config = make_config_with_agent(Some("claude"));
         let options = make_options_with_prompt(false); // pane commands disabled
@@ -812,6 +929,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
         let config = make_config_with_agent(None); // no agent
         let options = make_options_with_prompt(true);
@@ -836,6 +954,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
             config::PaneConfig {
                 command: Some("clear".to_string()),
@@ -844,6 +963,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
         ];
         let config = make_config_with_agent(Some("claude"));
@@ -865,6 +985,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
         let config = make_config_with_agent(Some("claude"));
         let options = make_options_with_prompt(true);
@@ -882,6 +1003,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
         let config = make_config_with_agent(Some("claude"));
         let options = make_options_with_prompt(true);
@@ -899,6 +1021,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
         let config = make_config_with_agent(Some("claude")); // config says claude
         let options = make_options_with_prompt(true);
@@ -923,6 +1046,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
             config::PaneConfig {
                 command: Some("claude --verbose".to_string()), // matches
@@ -931,6 +1055,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
         ];
         let config = make_config_with_agent(Some("claude"));
@@ -949,6 +1074,7 @@ mod tests {
             size: None,
             percentage: None,
             target: None,
+            role: None,
         }];
         let config = make_config_with_agent(None); // no global agent
         let options = make_options_with_prompt(true);
@@ -968,6 +1094,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
             config::PaneConfig {
                 command: Some("codex --yolo".to_string()),
@@ -976,6 +1103,7 @@ mod tests {
                 size: None,
                 percentage: None,
                 target: None,
+                role: None,
             },
         ];
 
@@ -1046,100 +1174,3 @@ mod tests {
         assert_eq!(content, "test prompt");
     }
 }
-
-/// Symlink CLAUDE.local.md from main worktree if it exists and is gitignored.
-fn symlink_claude_local_md(repo_root: &Path, worktree_path: &Path) -> Result<()> {
-    let source = repo_root.join("CLAUDE.local.md");
-    if !source.exists() {
-        return Ok(());
-    }
-
-    if !git::is_path_ignored(repo_root, "CLAUDE.local.md") {
-        return Ok(());
-    }
-
-    let dest = worktree_path.join("CLAUDE.local.md");
-    if dest.symlink_metadata().is_ok() {
-        // Already exists (file, symlink, or dir) -- skip
-        return Ok(());
-    }
-
-    let relative_source = pathdiff::diff_paths(&source, worktree_path)
-        .ok_or_else(|| anyhow!("Could not create relative path for CLAUDE.local.md symlink"))?;
-
-    #[cfg(unix)]
-    std::os::unix::fs::symlink(&relative_source, &dest)
-        .context("Failed to symlink CLAUDE.local.md")?;
-
-    #[cfg(windows)]
-    std::os::windows::fs::symlink_file(&relative_source, &dest)
-        .context("Failed to symlink CLAUDE.local.md")?;
-
-    info!("Symlinked CLAUDE.local.md to worktree");
-    Ok(())
-}
-
-/// Validates that a prompt will actually be consumed by an agent pane.
-///
-/// This prevents the case where a user provides `-p "some prompt"` but no pane
-/// is configured to run an agent that would receive it.
-fn validate_prompt_consumption(
-    panes: &[config::PaneConfig],
-    cli_agent: Option<&str>,
-    config: &config::Config,
-    options: &super::types::SetupOptions,
-) -> Result<()> {
-    if !options.run_pane_commands {
-        return Err(anyhow!(
-            "Prompt provided (-p/-P/-e) but pane commands are disabled (--no-pane-cmds). \
-             The prompt would be ignored."
-        ));
-    }
-
-    // Known agent commands always consume prompts (they have their own agent
-    // profile), so the prompt is consumed regardless of whether a global agent
-    // is configured.
-    let has_self_identifying_agent = panes.iter().any(|pane| {
-        pane.command
-            .as_deref()
-            .is_some_and(crate::multiplexer::agent::is_known_agent)
-    });
-
-    if has_self_identifying_agent {
-        return Ok(());
-    }
-
-    // For non-named panes, require a global agent
-    let effective_agent = cli_agent.or(config.agent.as_deref());
-
-    let Some(agent_cmd) = effective_agent else {
-        return Err(anyhow!(
-            "Prompt provided but no agent is configured to consume it. \
-             Set 'agent' in config or use -a/--agent flag."
-        ));
-    };
-
-    let consumes_prompt = panes.iter().any(|pane| {
-        pane.command
-            .as_deref()
-            .map(|cmd| config::is_agent_command(cmd, agent_cmd))
-            .unwrap_or(false)
-    });
-
-    if !consumes_prompt {
-        let commands: Vec<_> = panes
-            .iter()
-            .map(|p| p.command.as_deref().unwrap_or("<shell>"))
-            .collect();
-
-        return Err(anyhow!(
-            "Prompt provided, but no pane is configured to run the agent '{}'.\n\
-             Resolved pane commands: {:?}\n\
-             Ensure your panes config includes '<agent>' or runs the configured agent.",
-            agent_cmd,
-            commands
-        ));
-    }
-
-    Ok(())
-}
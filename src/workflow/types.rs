@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use crate::config::MuxMode;
-use crate::github::PrSummary;
+pub use crate::github::PrSummary;
 use crate::multiplexer::AgentStatus;
 use crate::prompt::Prompt;
 
@@ -138,16 +138,25 @@ impl SetupOptions {
 }
 
 /// Summary of agent statuses for a worktree (may have multiple agents)
+#[derive(Debug, Clone)]
 pub struct AgentStatusSummary {
     pub statuses: Vec<AgentStatus>,
 }
 
-/// List all worktrees with their status
+/// One worktree as reported by [`list`](super::list).
+///
+/// Part of the library API: fields are only ever added, not renamed.
+#[derive(Debug, Clone)]
 pub struct WorktreeInfo {
+    /// Checked-out branch, or `(detached)`
     pub branch: String,
     pub path: PathBuf,
+    /// Whether a multiplexer window or session exists for the worktree
     pub has_mux_window: bool,
-    pub has_unmerged: bool,
+    /// Whether the branch has commits not merged into the base branch;
+    /// `None` when that couldn't be determined
+    pub has_unmerged: Option<bool>,
+    /// Pull request for the branch, when PR status was requested
     pub pr_info: Option<PrSummary>,
     pub agent_status: Option<AgentStatusSummary>,
 }
@@ -0,0 +1,179 @@
+//! Runs `workmux sandbox run` end to end against a fake `limactl`, chosen
+//! with `WORKMUX_LIMACTL`, that records its arguments and runs the guest
+//! command on the host.
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// Stands in for `limactl`: appends its arguments to `$FAKE_LIMACTL_LOG`
+/// (each followed by a unit separator, one invocation per line), reports a
+/// single running VM, and runs `shell` commands locally from `--workdir`
+/// the way the guest's shell would.
+const FAKE_LIMACTL: &str = r#"#!/bin/sh
+for arg in "$@"; do printf '%s\037' "$arg"; done >> "$FAKE_LIMACTL_LOG"
+printf '\n' >> "$FAKE_LIMACTL_LOG"
+case "$1" in
+    --version) echo "limactl version 0.0.0-fake" ;;
+    list) echo '{"name":"wm-fake","status":"Running","dir":"/nonexistent"}' ;;
+    shell)
+        shift
+        if [ "$1" = --workdir ]; then cd "$2" || exit 1; shift 2; fi
+        shift
+        [ "$1" = -- ] && shift
+        if [ "$1" = eval ]; then eval "$2"; exit $?; fi
+        exec "$@"
+        ;;
+    *) echo "fake limactl: unexpected command: $*" >&2; exit 1 ;;
+esac
+"#;
+
+/// A home directory, worktree and fake `limactl` for one test.
+struct Harness {
+    _tmp: tempfile::TempDir,
+    root: PathBuf,
+    worktree: PathBuf,
+    limactl: PathBuf,
+    log: PathBuf,
+}
+
+impl Harness {
+    fn new(config: &str) -> Self {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+
+        let config_dir = root.join("home/.config/workmux");
+        fs::create_dir_all(&config_dir).unwrap();
+        fs::write(config_dir.join("config.yaml"), config).unwrap();
+
+        let worktree = root.join("worktree");
+        fs::create_dir_all(worktree.join("sub")).unwrap();
+        let status = Command::new("git")
+            .args(["init", "-q"])
+            .current_dir(&worktree)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let limactl = root.join("limactl");
+        fs::write(&limactl, FAKE_LIMACTL).unwrap();
+        fs::set_permissions(&limactl, fs::Permissions::from_mode(0o755)).unwrap();
+
+        Self {
+            log: root.join("limactl.log"),
+            _tmp: tmp,
+            root,
+            worktree,
+            limactl,
+        }
+    }
+
+    /// `workmux sandbox run` with `args`, outside any multiplexer.
+    fn sandbox_run(&self, args: &[&str], envs: &[(&str, &str)]) -> Output {
+        let mut cmd = Command::new(env!("CARGO_BIN_EXE_workmux"));
+        cmd.args(["sandbox", "run"])
+            .args(args)
+            .current_dir(&self.worktree)
+            .env("HOME", self.root.join("home"))
+            .env("XDG_STATE_HOME", self.root.join("state"))
+            .env("WORKMUX_LIMACTL", &self.limactl)
+            .env("FAKE_LIMACTL_LOG", &self.log)
+            .envs(envs.iter().copied());
+        for var in [
+            "TMUX",
+            "WEZTERM_PANE",
+            "KITTY_WINDOW_ID",
+            "ZELLIJ",
+            "WORKMUX_BACKEND",
+            "WM_PANE_ID",
+        ] {
+            cmd.env_remove(var);
+        }
+        cmd.output().unwrap()
+    }
+
+    /// The arguments of each `limactl` invocation, in order.
+    fn invocations(&self) -> Vec<Vec<String>> {
+        fs::read_to_string(&self.log)
+            .unwrap()
+            .lines()
+            .map(|line| line.split_terminator('\x1f').map(str::to_string).collect())
+            .collect()
+    }
+}
+
+fn read(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+}
+
+#[test]
+fn sandbox_run_passes_env_and_workdir_and_propagates_exit_code() {
+    let harness = Harness::new(
+        "nerdfont: false\n\
+         sandbox:\n  \
+           backend: lima\n  \
+           preflight: false\n  \
+           env_passthrough: [WM_TEST_SECRET]\n",
+    );
+    let out = harness.root.join("agent.out");
+    let agent = format!(
+        r#"printf '%s\n' "$WM_TEST_SECRET" "$WM_SANDBOX_GUEST" "$WM_RPC_HOST" "$PWD" > '{}'; exit 7"#,
+        out.display()
+    );
+    let output = harness.sandbox_run(
+        &[
+            harness.worktree.to_str().unwrap(),
+            "--vm-name",
+            "wm-fake",
+            "--workdir",
+            "sub",
+            "--",
+            &agent,
+        ],
+        &[("WM_TEST_SECRET", "s3cret value")],
+    );
+
+    assert_eq!(output.status.code(), Some(7), "{output:?}");
+    let workdir = harness.worktree.join("sub");
+    assert_eq!(
+        read(&out),
+        format!(
+            "s3cret value\n1\nhost.lima.internal\n{}\n",
+            workdir.display()
+        )
+    );
+
+    let invocations = harness.invocations();
+    let agent_call = invocations.last().unwrap();
+    assert_eq!(
+        agent_call[..6],
+        [
+            "shell",
+            "--workdir",
+            workdir.to_str().unwrap(),
+            "wm-fake",
+            "--",
+            "eval"
+        ]
+    );
+    let full_command = &agent_call[6];
+    assert!(
+        full_command.contains("export WM_TEST_SECRET='s3cret value';"),
+        "{full_command}"
+    );
+    assert!(
+        full_command.contains("export WM_RPC_PORT="),
+        "{full_command}"
+    );
+    assert!(
+        full_command.ends_with(&format!("; {agent}")),
+        "{full_command}"
+    );
+    assert!(
+        invocations
+            .iter()
+            .any(|args| args == &["shell", "--workdir", "/", "wm-fake", "true"]),
+        "no SSH probe in {invocations:?}"
+    );
+}
@@ -360,3 +360,29 @@ def test_list_filter_no_match(
     )
 
     assert "No worktrees found" in output
+
+
+def test_list_size_respects_gitignore(
+    mux_server: MuxEnvironment, workmux_exe_path: Path, mux_repo_path: Path
+):
+    """Verifies `list --size` leaves out ignored files unless `--size=all`."""
+    env = mux_server
+    branch_name = "feature-size"
+    worktree_path = get_worktree_path(mux_repo_path, branch_name)
+    write_workmux_config(mux_repo_path)
+    run_workmux_add(env, workmux_exe_path, mux_repo_path, branch_name)
+
+    (worktree_path / ".gitignore").write_text("target/\n")
+    (worktree_path / "target").mkdir()
+    (worktree_path / "target" / "big.bin").write_bytes(os.urandom(3 * 1024 * 1024))
+
+    def size_of(args: str) -> str:
+        output = run_workmux_list(env, workmux_exe_path, mux_repo_path, args)
+        entry = next(r for r in parse_list_output(output) if r["BRANCH"] == branch_name)
+        return entry["SIZE"]
+
+    assert not size_of("--size").endswith("M")
+    assert size_of("--size=all").endswith("M")
+
+    output = run_workmux_list(env, workmux_exe_path, mux_repo_path)
+    assert "SIZE" not in output
@@ -1,3 +1,4 @@
+import shlex
 from pathlib import Path
 
 from .conftest import (
@@ -26,6 +27,23 @@ def test_path_returns_worktree_path(
     assert result.stdout.strip() == str(expected_path)
 
 
+def test_path_cd_prints_quoted_cd_command(
+    mux_server: MuxEnvironment, workmux_exe_path: Path, mux_repo_path: Path
+):
+    """Verifies `workmux path --cd` prints only an eval-able `cd` line."""
+    env = mux_server
+    branch_name = "feature-cd"
+    write_workmux_config(mux_repo_path)
+    run_workmux_add(env, workmux_exe_path, mux_repo_path, branch_name)
+
+    result = run_workmux_command(
+        env, workmux_exe_path, mux_repo_path, f"path --cd {branch_name}"
+    )
+
+    expected_path = get_worktree_path(mux_repo_path, branch_name)
+    assert shlex.split(result.stdout.strip()) == ["cd", "--", str(expected_path)]
+
+
 def test_path_fails_for_nonexistent_worktree(
     mux_server: MuxEnvironment, workmux_exe_path: Path, mux_repo_path: Path
 ):
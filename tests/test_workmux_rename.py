@@ -0,0 +1,56 @@
+from pathlib import Path
+
+from .conftest import (
+    MuxEnvironment,
+    get_window_name,
+    get_worktree_path,
+    run_workmux_add,
+    run_workmux_command,
+    write_workmux_config,
+)
+
+
+def test_rename_moves_branch_worktree_and_window(
+    mux_server: MuxEnvironment, workmux_exe_path: Path, mux_repo_path: Path
+):
+    """Verifies `workmux rename` renames the branch, the worktree directory and the window."""
+    env = mux_server
+    write_workmux_config(mux_repo_path)
+    run_workmux_add(env, workmux_exe_path, mux_repo_path, "feature/old")
+
+    result = run_workmux_command(
+        env, workmux_exe_path, mux_repo_path, "rename feature/old feature/new"
+    )
+
+    assert "Renamed branch 'feature/old' to 'feature/new'" in result.stdout
+    assert not get_worktree_path(mux_repo_path, "feature/old").exists()
+    assert get_worktree_path(mux_repo_path, "feature/new").is_dir()
+    windows = env.list_windows()
+    assert get_window_name("feature/new") in windows
+    assert get_window_name("feature/old") not in windows
+    branches = env.run_command(
+        ["git", "branch", "--list", "feature/*"], cwd=mux_repo_path
+    ).stdout
+    assert "feature/new" in branches
+    assert "feature/old" not in branches
+
+
+def test_rename_refuses_existing_branch(
+    mux_server: MuxEnvironment, workmux_exe_path: Path, mux_repo_path: Path
+):
+    """Verifies `workmux rename` fails without changes when the new branch exists."""
+    env = mux_server
+    write_workmux_config(mux_repo_path)
+    run_workmux_add(env, workmux_exe_path, mux_repo_path, "rename-source")
+    env.run_command(["git", "branch", "taken"], cwd=mux_repo_path)
+
+    result = run_workmux_command(
+        env,
+        workmux_exe_path,
+        mux_repo_path,
+        "rename rename-source taken",
+        expect_fail=True,
+    )
+
+    assert "Branch 'taken' already exists" in result.stderr
+    assert get_worktree_path(mux_repo_path, "rename-source").is_dir()
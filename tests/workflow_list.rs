@@ -0,0 +1,51 @@
+//! Locks the library signature of `workflow::list` against a real repository.
+
+use std::path::Path;
+use std::process::Command;
+
+use workmux::config::Config;
+use workmux::multiplexer;
+use workmux::workflow::{self, WorktreeInfo};
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .unwrap();
+    assert!(status.success(), "git {args:?} failed");
+}
+
+#[test]
+fn list_reports_worktrees_of_the_current_repo() {
+    let tmp = tempfile::tempdir().unwrap();
+    let repo = tmp.path().join("repo");
+    std::fs::create_dir(&repo).unwrap();
+    git(&repo, &["init", "-q", "-b", "main"]);
+    git(&repo, &["commit", "-q", "--allow-empty", "-m", "init"]);
+    git(
+        &repo,
+        &["worktree", "add", "-q", "-b", "feature", "../feature"],
+    );
+    let repo = repo.canonicalize().unwrap();
+
+    // workflow::list works on the repository containing the current directory
+    std::env::set_current_dir(&repo).unwrap();
+    let config = Config::default();
+    let mux = multiplexer::current();
+    let worktrees: Vec<WorktreeInfo> =
+        workflow::list(&config, mux.as_ref(), false, &[], Some("main")).unwrap();
+
+    let mut branches: Vec<&str> = worktrees.iter().map(|wt| wt.branch.as_str()).collect();
+    branches.sort();
+    assert_eq!(branches, ["feature", "main"]);
+
+    let feature = worktrees.iter().find(|wt| wt.branch == "feature").unwrap();
+    assert_eq!(
+        feature.path,
+        tmp.path().canonicalize().unwrap().join("feature")
+    );
+    assert_eq!(feature.has_unmerged, Some(false));
+    assert!(feature.pr_info.is_none());
+}